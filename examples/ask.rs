@@ -0,0 +1,22 @@
+//! 使用库中的 Agent 回答一个问题：
+//!
+//! ```sh
+//! cargo run --example ask -- "What does src/main.rs do?"
+//! ```
+
+use ariste::Agent;
+
+#[tokio::main]
+async fn main() -> Result<(), ariste::Error> {
+    let prompt = std::env::args()
+        .skip(1)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if prompt.is_empty() {
+        eprintln!("Usage: cargo run --example ask -- <question>");
+        return Ok(());
+    }
+
+    let mut agent = Agent::load_from_config().await?;
+    agent.invoke(&prompt).await
+}
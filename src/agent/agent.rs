@@ -1,14 +1,128 @@
+use crate::agent::echo::collapse_echoes;
+use crate::agent::events::{AgentEvent, EventFrontend};
+use crate::agent::exemplars;
+use crate::agent::forget::{self, Selector};
+use crate::agent::experiment::{Experiment, VariantSummary};
+use crate::agent::instructions::ProjectInstructions;
 use crate::agent::message::Message;
-use crate::config::AgentConfig;
+use crate::agent::permissions::{self, Decision, PermissionPolicy};
+use crate::agent::pins::{self, Pin};
+use crate::agent::rag;
+use crate::agent::recall::{self, SessionMemory};
+use crate::agent::redirect::RedirectToken;
+use crate::agent::session::{self, Checkpoint, Session, SessionSummary};
+use crate::agent::analytics::{self, ToolCallRecord};
+use crate::agent::compact;
+use crate::agent::consensus;
+use crate::agent::context::{self, ContextReport};
+use crate::agent::transcript;
+use crate::agent::trim;
+use crate::agent::subagent_view::SubagentView;
+use crate::agent::subagents::{SubAgentProfile, SubAgentRegistry};
+use crate::agent::tasks::{self, TaskManager};
+use crate::agent::timing::{Stopwatch, TimingKind, TimingRecord};
+use crate::agent::turn::Turn;
+use crate::agent::usage::UsageReport;
+use crate::agent::verify::{unsupported_claims, verification_messages};
+use crate::config::{AgentConfig, SubagentLimits, ToolTrimConfig, TrimPolicy};
 use crate::error::Error;
-use crate::llm::Ollama;
-use crate::tools::{BashTool, EditTool, GlobTool, GrepTool, ReadTool, TaskTool, TodoWriteTool, Tool, ToolDefinition, WebFetchTool, WriteTool};
-use crate::ui::UI;
+use crate::index::semantic::DEFAULT_WATCH_INTERVAL_SECS;
+use crate::index::{IndexStats, IngestReport, KnowledgeBase, SearchHit, SemanticIndex};
+use crate::llm::{CancelToken, LlmProvider, create_provider};
+use crate::tools::attachment;
+use crate::tools::{BackgroundShells, DocsSearchTool, ImageSink, ParallelTasksTool, PatchSink, SemanticSearchTool, TaskTool, ToolContext, ToolDefinition, ToolImpl, ToolRegistry};
+use crate::ui::{Approval, Frontend, Glyphs, Notice, SilentFrontend, glyphs};
+use crate::utils::{gist, git};
+use crate::tools::progress::{format_duration, runtime_note, ToolProgress};
+use crate::tools::trash::Trash;
+use crate::workspace::{ProjectProfile, Workspace};
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Settings file, relative to the working directory
+const SETTINGS_FILE: &str = ".ariste/settings.json";
+
+/// Upper bound on the diff attached to CodeReview subagent prompts
+const MAX_REVIEW_DIFF_BYTES: usize = 20_000;
+
+/// Recent turns compaction keeps intact (and `/context` assumes it would)
+const CONTEXT_KEEP_TURNS: usize = 2;
+
+/// Estimated history tokens that trigger automatic compaction
+const DEFAULT_COMPACT_TOKENS: u64 = 32_000;
+
+/// Default seconds between checkpoints of a turn in progress
+const DEFAULT_AUTOSAVE_SECS: u64 = 30;
+
+/// Default seconds between heartbeats while a tool runs
+const DEFAULT_HEARTBEAT_SECS: u64 = 10;
+
+/// Default estimated tokens of parent conversation forwarded to a subagent
+const DEFAULT_SUBAGENT_CONTEXT_TOKENS: u64 = 4_000;
+
+/// Default model replies a subagent may take
+const DEFAULT_SUBAGENT_MAX_TURNS: usize = 10;
+
+/// Default tool calls run from one subagent reply
+const DEFAULT_SUBAGENT_MAX_ITERATIONS: usize = 5;
+
+/// Default wall-clock seconds a subagent may run
+const DEFAULT_SUBAGENT_TIMEOUT_SECS: u64 = 300;
+
+/// Default number of `parallel_tasks` subagents running at once
+const DEFAULT_MAX_PARALLEL_SUBAGENTS: usize = 3;
+
+/// Tools available to read-only subagents (Explore, CodeReview)
+const READ_ONLY_TOOLS: &[&str] = &["read", "glob", "grep", "ls", "data_preview", "compare_files"];
+
+/// Whether calls of `name` may run alongside each other: they only read
+fn runs_concurrently(name: &str) -> bool {
+    READ_ONLY_TOOLS.contains(&name) || matches!(name, "web_fetch" | "semantic_search" | "docs_search")
+}
+
+/// What a tool call produced, as the tool reported it, with a note on its
+/// runtime when it ran long
+struct ToolOutcome {
+    outcome: Result<String, String>,
+    note: Option<String>,
+}
+
+impl ToolOutcome {
+    /// Show the outcome on `frontend` and return it as the model sees it:
+    /// the result or error with the runtime note appended
+    fn show(self, name: &str, frontend: &dyn Frontend) -> Result<String, Error> {
+        let with_note = |text: String| match &self.note {
+            Some(note) => format!("{}\n{}", text.trim_end(), note),
+            None => text,
+        };
+        match self.outcome {
+            Ok(result) => {
+                let result = with_note(result);
+                frontend.tool_result(name, &result);
+                Ok(result)
+            }
+            Err(e) => {
+                frontend.tool_error(&e);
+                Err(Error::Message(format!("Tool execution error: {}", with_note(e))))
+            }
+        }
+    }
+}
+
+/// Tools whose path arguments name files they change, for the code index
+const FILE_CHANGING_TOOLS: &[(&str, &[&str])] = &[
+    ("write", &["file_path"]),
+    ("edit", &["file_path"]),
+    ("delete", &["path"]),
+    ("move", &["source", "destination"]),
+];
+
 /// Execution status of a subagent task
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
@@ -17,6 +131,20 @@ pub enum SubAgentStatus {
     Running,
     Completed,
     Failed(String),
+    /// Stopped by `task_cancel` or `/cancel` before it finished
+    Cancelled,
+}
+
+impl SubAgentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubAgentStatus::Pending => "pending",
+            SubAgentStatus::Running => "running",
+            SubAgentStatus::Completed => "completed",
+            SubAgentStatus::Failed(_) => "failed",
+            SubAgentStatus::Cancelled => "cancelled",
+        }
+    }
 }
 
 /// Execution tracking for subagent tasks
@@ -60,6 +188,11 @@ impl SubAgentExecution {
         self.end_time = Some(Instant::now());
     }
 
+    pub fn cancel(&mut self) {
+        self.status = SubAgentStatus::Cancelled;
+        self.end_time = Some(Instant::now());
+    }
+
     pub fn duration(&self) -> Option<Duration> {
         match (self.start_time, self.end_time) {
             (Some(start), Some(end)) => Some(end.duration_since(start)),
@@ -95,7 +228,8 @@ impl Default for SubAgentIdCounter {
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SubAgentTask {
-    pub subagent_type: SubAgentType,
+    /// Name of a registered subagent type, e.g. `explore`
+    pub subagent_type: String,
     pub description: String,
     pub prompt: String,
     pub include_context: bool,
@@ -108,9 +242,18 @@ impl SubAgentTask {
         subagent_type: SubAgentType,
         description: impl Into<String>,
         prompt: impl Into<String>,
+    ) -> Self {
+        Self::named(subagent_type.name(), description, prompt)
+    }
+
+    /// A task for a subagent type registered by name
+    pub fn named(
+        subagent_type: impl Into<String>,
+        description: impl Into<String>,
+        prompt: impl Into<String>,
     ) -> Self {
         Self {
-            subagent_type,
+            subagent_type: subagent_type.into(),
             description: description.into(),
             prompt: prompt.into(),
             include_context: false,
@@ -129,8 +272,192 @@ impl SubAgentTask {
     }
 }
 
+/// Why a subagent stopped before giving a final answer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Truncation {
+    MaxTurns,
+    Timeout,
+    Cancelled,
+}
+
+impl Truncation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Truncation::MaxTurns => "max_turns",
+            Truncation::Timeout => "timeout",
+            Truncation::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Result of `Agent::run_subagent_loop`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubAgentRun {
+    /// The final answer, or the last one so far when truncated
+    pub content: String,
+    pub truncated: Option<Truncation>,
+}
+
+/// Key for the per-session subagent result cache
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SubAgentCacheKey {
+    subagent_type: String,
+    used_tools: bool,
+    prompt: String,
+    repo_state: u64,
+}
+
+/// One entry of a `parallel_tasks` call
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ParallelTask {
+    #[serde(default = "general_purpose")]
+    pub subagent_type: String,
+    pub description: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub include_tools: bool,
+    #[serde(default)]
+    pub force: bool,
+}
+
+fn general_purpose() -> String {
+    SubAgentType::GeneralPurpose.name().to_string()
+}
+
+/// A subagent task after the cache lookup
+enum PreparedSubagent {
+    Cached(Value),
+    Ready(Box<SubagentJob>),
+}
+
+/// A subagent ready to run. It owns everything it needs, so several can
+/// run at once.
+struct SubagentJob {
+    profile: SubAgentProfile,
+    description: String,
+    messages: Vec<Message>,
+    used_tools: bool,
+    limits: SubagentLimits,
+    cache_key: Option<SubAgentCacheKey>,
+    patches: Option<PatchSink>,
+    /// Id of the subagent in the session, as in `task#3`
+    id: usize,
+    /// Shows the subagent's progress on the parent's frontend
+    frontend: Arc<dyn Frontend>,
+    session: String,
+    started: Instant,
+    /// Stops the subagent; the parent's token for tasks run in the foreground
+    cancel: CancelToken,
+    /// The parent's sampling seed
+    seed: Option<u64>,
+    /// The parent's code index, shared so the subagent's edits update it
+    index: Option<Arc<SemanticIndex>>,
+    /// Runs in the background, where no one can answer approvals
+    background: bool,
+}
+
+/// What a `SubagentJob` produced, with the tokens it used
+struct FinishedSubagent {
+    job: SubagentJob,
+    result: Result<SubAgentRun, Error>,
+    usage: UsageReport,
+    model: String,
+    /// The subagent's tool calls, then the task itself
+    timings: Vec<TimingRecord>,
+}
+
+impl SubagentJob {
+    /// Boxed so background tasks can spawn it: the subagent's own tool
+    /// calls lead back here, which an unboxed future can't be proven `Send` through
+    fn run(mut self) -> BoxFuture<'static, FinishedSubagent> {
+        Box::pin(async move {
+            let watch = Stopwatch::start();
+            let mut usage = UsageReport::default();
+            let mut model = String::new();
+            let mut timings = Vec::new();
+            let result: Result<SubAgentRun, Error> = async {
+                // Create a new Agent instance for the subagent
+                let mut subagent = Agent::load_from_config().await?;
+                if let Some(index) = self.index.clone() {
+                    subagent.use_index(index);
+                }
+                subagent.patches = self.patches.clone();
+                subagent.set_frontend(self.frontend.clone());
+                subagent.session = self.session.clone();
+                subagent.sessions_dir = None;
+                subagent.cancel = self.cancel.clone();
+                subagent.unattended = self.background;
+                if let Some(seed) = self.seed {
+                    subagent.set_seed(seed);
+                }
+                if let Some(model) = &self.profile.model {
+                    subagent.config.model = Some(model.clone());
+                }
+                // 没有可检索的索引时保持关闭
+                if let Some(rag) = self.profile.rag {
+                    subagent.set_rag(rag).ok();
+                }
+
+                // Configure if subagent should use tools
+                if !self.used_tools {
+                    // Remove tools from subagent
+                    subagent.restrict_tools(&[]);
+                    subagent.stream = false;
+                } else if let Some(allowed) = &self.profile.allowed_tools {
+                    let allowed: Vec<&str> = allowed.iter().map(String::as_str).collect();
+                    subagent.restrict_tools(&allowed);
+                }
+
+                model = subagent.config.model.clone().unwrap_or_else(|| "qwen3".to_string());
+                let run = subagent
+                    .run_subagent_loop(std::mem::take(&mut self.messages), &self.limits)
+                    .await;
+                usage = std::mem::take(&mut subagent.usage);
+                timings = std::mem::take(&mut subagent.timings);
+                run
+            }
+            .await;
+
+            let outcome = match &result {
+                Ok(run) => run.truncated.map_or("completed", |truncated| truncated.as_str()),
+                Err(_) => "failed",
+            };
+            for timing in &mut timings {
+                timing.task = Some(self.description.clone());
+            }
+            timings.push(watch.stop(TimingKind::Subagent, &self.profile.name, outcome).task(&self.description));
+
+            FinishedSubagent {
+                job: self,
+                result,
+                usage,
+                model,
+                timings,
+            }
+        })
+    }
+}
+
+/// The text a `task` call returns
+fn format_subagent_output(output: &Value) -> String {
+    format!(
+        "=== Subagent Task Complete ===\n{}",
+        serde_json::to_string_pretty(output).unwrap_or_default()
+    )
+}
+
+/// Lowercase and collapse whitespace so trivially different phrasings of the
+/// same request share a cache entry
+fn normalize_prompt(prompt: &str) -> String {
+    prompt
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Types of subagents that can be spawned
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SubAgentType {
     /// General-purpose agent for complex tasks
     GeneralPurpose,
@@ -145,7 +472,27 @@ pub enum SubAgentType {
 }
 
 impl SubAgentType {
-    fn description(&self) -> &str {
+    /// Every built-in type, in the order the task tool lists them
+    pub const ALL: [SubAgentType; 5] = [
+        SubAgentType::GeneralPurpose,
+        SubAgentType::Explore,
+        SubAgentType::Plan,
+        SubAgentType::CodeReview,
+        SubAgentType::TestRunner,
+    ];
+
+    /// Name used by the `task` tool and in settings
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubAgentType::GeneralPurpose => "general-purpose",
+            SubAgentType::Explore => "explore",
+            SubAgentType::Plan => "plan",
+            SubAgentType::CodeReview => "code-review",
+            SubAgentType::TestRunner => "test-runner",
+        }
+    }
+
+    pub(crate) fn description(&self) -> &'static str {
         match self {
             SubAgentType::GeneralPurpose => "General-purpose agent for complex tasks",
             SubAgentType::Explore => "Fast agent for exploring codebases",
@@ -155,7 +502,7 @@ impl SubAgentType {
         }
     }
 
-    fn system_prompt(&self) -> Option<&'static str> {
+    pub(crate) fn system_prompt(&self) -> Option<&'static str> {
         match self {
             SubAgentType::Explore => Some(
                 "You are a codebase exploration agent. Your goal is to quickly find files, \
@@ -183,7 +530,7 @@ impl SubAgentType {
     }
 
     /// Returns whether this subagent type should have access to tools
-    fn uses_tools(&self) -> bool {
+    pub(crate) fn uses_tools(&self) -> bool {
         match self {
             SubAgentType::Explore => true,
             SubAgentType::CodeReview => true,
@@ -192,20 +539,98 @@ impl SubAgentType {
             SubAgentType::Plan => false, // Plan agent focuses on analysis
         }
     }
+
+    /// Tools this subagent type may be given; `None` means every tool.
+    /// Explorers and reviewers must never be able to modify the workspace.
+    pub(crate) fn allowed_tools(&self) -> Option<&'static [&'static str]> {
+        match self {
+            SubAgentType::Explore | SubAgentType::CodeReview => Some(READ_ONLY_TOOLS),
+            _ => None,
+        }
+    }
 }
 
 pub struct Agent {
     pub config: AgentConfig,
-    pub ollama: Ollama,
+    /// Chat backend selected by the `provider` setting
+    pub llm: Box<dyn LlmProvider>,
     pub messages: Vec<Message>,
-    pub tools: Vec<Tool>,
-    #[allow(dead_code)]
+    /// Tools the model can call; add custom ones with `register_tool`
+    pub tools: ToolRegistry,
     pub tool_definitions: Vec<ToolDefinition>,
+    pub workspace: Workspace,
+    /// Project type of the primary root, detected at startup
+    pub profile: ProjectProfile,
+    /// Project instruction file of the primary root, read at startup
+    pub instructions: Option<ProjectInstructions>,
+    subagent_cache: HashMap<SubAgentCacheKey, Value>,
+    /// Quiet client for the answer verification pass, when enabled
+    verifier: Option<Box<dyn LlmProvider>>,
+    /// Tool definitions offered to the model: all of them unless trimmed or restricted
+    offered_tools: Option<Vec<ToolDefinition>>,
+    /// Stream replies as they are generated; off for tool-less subagents
+    stream: bool,
+    /// A/B experiment assigning variants to turns, when configured
+    experiment: Option<Experiment>,
+    /// Set by stdio/server clients that apply file edits themselves
+    patches: Option<PatchSink>,
+    /// Images read for the model when it accepts them (`vision`)
+    images: Option<ImageSink>,
+    /// Identifies this session in the tool usage log and names its saved file
+    session: String,
+    /// Where the conversation is saved after each turn; `None` disables saving
+    pub sessions_dir: Option<PathBuf>,
+    /// When the session was last saved or checkpointed
+    last_autosave: Instant,
+    /// Calls per tool in earlier sessions, for the most-used trimming policy
+    tool_usage: HashMap<String, usize>,
+    /// Context kept in every request (the `/pin` command)
+    pins: Vec<Pin>,
+    /// Where progress, answers and approvals are shown; nowhere until
+    /// `set_frontend`
+    frontend: Arc<dyn Frontend>,
+    /// Allow/deny rules for side-effecting tools
+    permissions: PermissionPolicy,
+    /// Set for background subagents: no one can answer approvals, so gated
+    /// calls no rule allows are denied instead of asked about
+    unattended: bool,
+    /// Tokens used by each turn of the session
+    usage: UsageReport,
+    /// Subagent types the `task` tool offers
+    subagents: SubAgentRegistry,
+    /// Cancelled by Ctrl-C to stop the reply being generated
+    cancel: CancelToken,
+    /// Requested by Esc to pause for a correction after the running tools
+    redirect: RedirectToken,
+    /// Background subagent tasks
+    tasks: TaskManager,
+    /// Subagents of background tasks not yet collected, by task id
+    running: HashMap<usize, tokio::task::JoinHandle<FinishedSubagent>>,
+    /// When each tool call and subagent task of the session ran; saved with it
+    timings: Vec<TimingRecord>,
+    /// Embedded code searched by `semantic_search`, when `index.enabled`
+    index: Option<Arc<SemanticIndex>>,
+    /// Keeps `index` in step with edits made outside the tools
+    index_watcher: Option<tokio::task::JoinHandle<()>>,
+    /// Documentation searched by `docs_search`, once something is ingested
+    knowledge: Arc<KnowledgeBase>,
+    /// Commands bash runs in the background; killed with the agent
+    shells: BackgroundShells,
+    /// Add retrieved code and docs to each turn (`/rag on|off`)
+    rag: bool,
+    /// What was retrieved for the running turn; sent with its requests but
+    /// never kept in the history
+    retrieved: Option<String>,
+    /// Earlier sessions of the project, searched by `/recall`
+    memory: SessionMemory,
+    /// What `/recall` found, sent with the requests of the next turn and,
+    /// like `retrieved`, never kept in the history
+    recalled: Option<String>,
 }
 
 impl Agent {
     pub async fn load_from_config() -> Result<Self, Error> {
-        let config_file = ".ariste/settings.json";
+        let config_file = SETTINGS_FILE;
         let config = if !tokio::fs::try_exists(&config_file).await? {
             AgentConfig::default()
         } else {
@@ -213,750 +638,3266 @@ impl Agent {
             serde_json::from_slice(&buf)?
         };
 
-        let url = if let Some(base) = &config.base {
-            format!("{}/api/chat", base)
-        } else {
-            "http://localhost:11434/api/chat".to_string()
-        };
+        let llm = create_provider(&config)?;
+
+        let mut workspace = Workspace::from_config(config.roots.as_deref())?;
+        workspace.confine(!config.allow_outside_workdir.unwrap_or(false));
+        let profile = ProjectProfile::detect(&workspace.primary().path);
+        let instructions = ProjectInstructions::load(&workspace.primary().path);
 
         // Register tools
-        let bash = Tool::Bash(BashTool);
-        let bash_def = bash.definition();
-        let read = Tool::Read(ReadTool);
-        let read_def = read.definition();
-        let write = Tool::Write(WriteTool);
-        let write_def = write.definition();
-        let glob = Tool::Glob(GlobTool);
-        let glob_def = glob.definition();
-        let grep = Tool::Grep(GrepTool);
-        let grep_def = grep.definition();
-        let edit = Tool::Edit(EditTool);
-        let edit_def = edit.definition();
-        let web_fetch = Tool::WebFetch(WebFetchTool);
-        let web_fetch_def = web_fetch.definition();
-        let todo_write = Tool::TodoWrite(TodoWriteTool);
-        let todo_write_def = todo_write.definition();
-        let task = Tool::Task(TaskTool);
-        let task_def = task.definition();
-        let tools: Vec<Tool> = vec![bash, read, write, glob, grep, edit, web_fetch, todo_write, task];
-        let tool_definitions = vec![bash_def, read_def, write_def, glob_def, grep_def, edit_def, web_fetch_def, todo_write_def, task_def];
-
-        let tool_defs_for_ollama = tool_definitions.clone();
-        let ollama = Ollama::new()
-            .url(url)
-            .think(false)
-            .tools(tool_defs_for_ollama);
-
-        Ok(Self {
+        let mut tools = ToolRegistry::builtin();
+        let index = SemanticIndex::from_config(&config, &workspace.primary().path)?.map(Arc::new);
+        if let Some(index) = &index {
+            tools.register(SemanticSearchTool::new(index.clone()));
+        }
+        let knowledge = Arc::new(KnowledgeBase::from_config(&config, &workspace.primary().path)?);
+        if knowledge.exists() {
+            tools.register(DocsSearchTool::new(knowledge.clone()));
+        }
+        let memory = SessionMemory::from_config(&config, &workspace.primary().path)?;
+        let tool_definitions = tools.definitions();
+
+        // 校验需要在显示前拿到完整回复，因此不流式输出内容
+        let verify = config
+            .verification
+            .as_ref()
+            .is_some_and(|verification| verification.is_enabled());
+        let verifier = verify.then(|| llm.quiet());
+
+        let mut tool_usage = HashMap::new();
+        if config.tool_trimming.as_ref().and_then(|trim| trim.policy) == Some(TrimPolicy::MostUsed) {
+            let records = analytics::load(std::path::Path::new(analytics::TOOL_STATS_FILE))
+                .await
+                .unwrap_or_default();
+            for record in records {
+                *tool_usage.entry(record.tool).or_default() += 1;
+            }
+        }
+
+        let experiment = match config.experiment.clone() {
+            Some(experiment) => Some(Experiment::new(experiment)?.log(".ariste/experiments.jsonl")),
+            None => None,
+        };
+
+        let accessible = glyphs::accessibility_requested(config.accessible);
+        let glyphs = Glyphs::from_settings(config.glyphs.as_ref(), accessible);
+        glyphs::configure(glyphs);
+
+        let offered_tools = Some(tool_definitions.clone());
+        let permissions = PermissionPolicy::from_config(config.permissions.as_ref());
+        let price = config
+            .pricing
+            .as_ref()
+            .and_then(|pricing| pricing.get(config.model.as_deref().unwrap_or("qwen3")))
+            .copied();
+
+        let profiles = config
+            .subagents
+            .as_ref()
+            .map(|subagents| subagents.profiles.clone())
+            .unwrap_or_default();
+        let images = (config.vision == Some(true)).then(ImageSink::default);
+        let rag = config.rag.as_ref().is_some_and(|rag| rag.is_enabled());
+        let mut agent = Self {
             config,
-            ollama,
+            llm,
             messages: Vec::new(),
             tools,
             tool_definitions,
-        })
+            workspace,
+            profile,
+            instructions,
+            subagent_cache: HashMap::new(),
+            verifier,
+            offered_tools,
+            stream: true,
+            experiment,
+            patches: None,
+            images,
+            session: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis().to_string())
+                .unwrap_or_default(),
+            sessions_dir: Some(PathBuf::from(session::SESSIONS_DIR)),
+            last_autosave: Instant::now(),
+            tool_usage,
+            pins: Vec::new(),
+            frontend: SilentFrontend::shared(),
+            permissions,
+            unattended: false,
+            usage: UsageReport::new(price),
+            subagents: SubAgentRegistry::builtin(),
+            cancel: CancelToken::new(),
+            redirect: RedirectToken::new(),
+            tasks: TaskManager::new(),
+            running: HashMap::new(),
+            timings: Vec::new(),
+            index,
+            index_watcher: None,
+            knowledge,
+            shells: BackgroundShells::default(),
+            rag,
+            retrieved: None,
+            memory,
+            recalled: None,
+        };
+        for profile in &profiles {
+            agent.register_subagent_type(profile.into());
+        }
+        Ok(agent)
     }
 
-    pub async fn invoke(&mut self, prompt: &str) -> Result<(), Error> {
-        // 添加用户消息到历史
-        self.messages.push(Message {
-            role: "user".to_string(),
-            content: prompt.to_string(),
-            tool_calls: None,
-            tool_call_id: None,
-        });
-
-        // Tool calling 循环
-        let max_iterations = 5;
-        let mut iteration = 0;
-
-        loop {
-            iteration += 1;
-            if iteration > max_iterations {
-                return Err(Error::Message("Too many tool call iterations".to_string()));
+    /// Messages sent to the model: the conversation history plus the messages
+    /// staged for the current turn, preceded by a system note describing the
+    /// project and workspace layout
+    fn request_messages(&self, staged: &[Message]) -> Vec<Message> {
+        let mut notes = Vec::new();
+        if let Some(prompt) = self
+            .experiment
+            .as_ref()
+            .and_then(|experiment| experiment.current())
+            .and_then(|variant| variant.system_prompt.clone())
+        {
+            notes.push(prompt);
+        }
+        if let Some(config) = &self.config.tool_examples {
+            let available: Vec<String> = self
+                .offered_tools
+                .iter()
+                .flatten()
+                .map(|definition| definition.function.name.clone())
+                .collect();
+            if let Some(note) = exemplars::system_note(config, &available) {
+                notes.push(note);
             }
+        }
+        if let Some(project) = self.profile.describe() {
+            notes.push(project);
+        }
+        if self.workspace.is_multi_root() {
+            notes.push(self.workspace.describe());
+        }
+        if let Some(scope) = self.workspace.scope() {
+            notes.push(format!(
+                "File tools (read, glob, grep) are scoped to `{}`; paths outside it are hidden.",
+                scope.display()
+            ));
+        }
 
-            // 使用完整的消息历史调用 Ollama
-            let model = self.config.model.as_deref().unwrap_or("qwen3");
-            let ollama_response = self
-                .ollama
-                .execute_with_messages(model, &self.messages)
-                .await?;
+        let mut messages = Vec::with_capacity(self.messages.len() + staged.len() + 1);
+        if let Some(prompt) = self.system_prompt() {
+            messages.push(Message::system(prompt));
+        }
+        if !notes.is_empty() {
+            messages.push(Message::system(notes.join("\n\n")));
+        }
+        if let Some(pinned) = pins::render_all(&self.pins, &self.workspace) {
+            messages.push(Message::system(pinned));
+        }
+        messages.extend(self.messages.iter().cloned());
+        messages.extend(staged.iter().cloned());
+        if let Some(retrieved) = &self.retrieved {
+            rag::insert(&mut messages, retrieved);
+        }
+        if let Some(recalled) = &self.recalled {
+            rag::insert(&mut messages, recalled);
+        }
+        messages
+    }
 
-            // 检查是否有 tool calls
-            if let Some(tool_calls) = ollama_response.tool_calls {
-                // 添加助手消息（包含 tool_calls）到历史
-                self.messages.push(Message {
-                    role: "assistant".to_string(),
-                    content: ollama_response.content.clone(),
-                    tool_calls: Some(tool_calls.clone()),
-                    tool_call_id: None,
-                });
-
-                // 执行每个工具调用
-                for tool_call in &tool_calls {
-                    if let Some(function) = tool_call.get("function") {
-                        let name = function
-                            .get("name")
-                            .and_then(|v: &Value| v.as_str())
-                            .unwrap_or("");
-                        let default_args = serde_json::json!({});
-                        let arguments = function.get("arguments").unwrap_or(&default_args);
-
-                        // 获取 tool_call_id
-                        let tool_call_id = tool_call
-                            .get("id")
-                            .and_then(|v: &Value| v.as_str())
-                            .unwrap_or("");
-
-                        // 查找并执行工具
-                        let result = self.execute_tool(name, arguments).await?;
-
-                        // 将工具结果作为 tool 角色的消息添加到历史
-                        self.messages.push(Message {
-                            role: "tool".to_string(),
-                            content: result,
-                            tool_calls: None,
-                            tool_call_id: Some(tool_call_id.to_string()),
-                        });
-                    }
-                }
+    /// The first system message: the configured system prompt for the turn's
+    /// model followed by the project instructions
+    fn system_prompt(&self) -> Option<String> {
+        let configured = self
+            .config
+            .model_system_prompts
+            .as_ref()
+            .and_then(|prompts| prompts.get(self.turn_model()))
+            .or(self.config.system_prompt.as_ref())
+            .filter(|prompt| !prompt.trim().is_empty());
+        let parts: Vec<String> = configured
+            .cloned()
+            .into_iter()
+            .chain(self.instructions.as_ref().map(ProjectInstructions::render))
+            .collect();
+        (!parts.is_empty()).then(|| parts.join("\n\n"))
+    }
 
-                // 继续循环，让模型基于工具结果生成最终回复
-                continue;
-            } else {
-                // 没有 tool calls，这是最终回复
-                self.messages.push(Message {
-                    role: "assistant".to_string(),
-                    content: ollama_response.content.clone(),
-                    tool_calls: None,
-                    tool_call_id: None,
-                });
+    /// Pin a file (when `arg` names one) or a note so it stays in context
+    pub fn pin(&mut self, arg: &str) -> Result<Pin, Error> {
+        let pin = Pin::parse(arg, &self.workspace)
+            .ok_or_else(|| Error::Message("Nothing to pin; give a file path or a note".to_string()))?;
+        if !self.pins.contains(&pin) {
+            self.pins.push(pin.clone());
+        }
+        Ok(pin)
+    }
 
-                return Ok(());
-            }
+    /// Remove the pin at `index` (as listed by `/pin`)
+    pub fn unpin(&mut self, index: usize) -> Result<Pin, Error> {
+        if index >= self.pins.len() {
+            return Err(Error::Message(format!("No pin #{}", index)));
         }
+        Ok(self.pins.remove(index))
     }
 
-    /// Run a complete message loop for a subagent (used by Task tool)
-    /// This allows the subagent to have multi-turn conversations and use tools
-    pub async fn run_subagent_loop(
-        &mut self,
-        initial_messages: Vec<Message>,
-        max_turns: usize,
-    ) -> Result<String, Error> {
-        // Set initial messages
-        self.messages = initial_messages;
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
 
-        let max_iterations = 5;
-        let mut iteration = 0;
-        let mut turn = 0;
+    /// Restrict read/glob/grep to a subdirectory (the `/scope` command)
+    pub fn set_scope(&mut self, path: &str) -> Result<std::path::PathBuf, Error> {
+        self.workspace.set_scope(path)
+    }
 
-        loop {
-            turn += 1;
-            if turn > max_turns {
-                break; // Reached max turns, return current best result
-            }
+    /// Restore tool visibility to the full workspace
+    pub fn reset_scope(&mut self) {
+        self.workspace.reset_scope();
+    }
 
-            iteration += 1;
-            if iteration > max_iterations {
-                return Err(Error::Message("Subagent: Too many iterations in one turn".to_string()));
-            }
+    /// Reverse the last delete or move made by the tools (the `/undo`
+    /// command), returning what was restored
+    pub fn undo(&self) -> Result<Option<String>, Error> {
+        Trash::new(&self.workspace).undo().map_err(Error::Message)
+    }
 
-            // Call LLM
-            let model = self.config.model.as_deref().unwrap_or("qwen3");
-            let ollama_response = self
-                .ollama
-                .execute_with_messages(model, &self.messages)
-                .await?;
+    fn tool_context(&self) -> ToolContext {
+        ToolContext::new(self.workspace.clone())
+            .with_profile(self.profile.clone())
+            .with_patches(self.patches.clone())
+            .with_images(self.images.clone())
+            .with_shells(self.shells.clone())
+    }
 
-            // Check for tool calls
-            if let Some(tool_calls) = ollama_response.tool_calls {
-                // Add assistant message
-                self.messages.push(Message {
-                    role: "assistant".to_string(),
-                    content: ollama_response.content.clone(),
-                    tool_calls: Some(tool_calls.clone()),
-                    tool_call_id: None,
-                });
-
-                // Execute tools
-                for tool_call in &tool_calls {
-                    if let Some(function) = tool_call.get("function") {
-                        let name = function
-                            .get("name")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("");
-                        let default_args = json!({});
-                        let arguments = function.get("arguments").unwrap_or(&default_args);
-
-                        let tool_call_id = tool_call
-                            .get("id")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("");
+    /// Show the agent loop on `frontend` (the CLI's terminal, a TUI, server
+    /// or stdio client), streamed replies included
+    pub fn set_frontend(&mut self, frontend: Arc<dyn Frontend>) {
+        self.llm.set_frontend(Some(frontend.clone()));
+        self.frontend = frontend;
+    }
 
-                        // Subagents cannot spawn additional subagents (prevent infinite recursion)
-                        if name == "task" {
-                            let result = json!({
-                                "error": "Subagents cannot spawn additional subagents",
-                                "suggestion": "Complete the task yourself using available tools"
-                            }).to_string();
-
-                            self.messages.push(Message {
-                                role: "tool".to_string(),
-                                content: result,
-                                tool_calls: None,
-                                tool_call_id: Some(tool_call_id.to_string()),
-                            });
-                            continue;
-                        }
+    /// Where the agent loop is shown, for workflows that ask the user
+    /// something themselves
+    pub fn frontend(&self) -> Arc<dyn Frontend> {
+        self.frontend.clone()
+    }
 
-                        // Execute tool
-                        let result = match Box::pin(self.execute_tool(name, arguments)).await {
-                            Ok(result) => result,
-                            Err(e) => {
-                                format!("Tool execution error: {}", e)
-                            }
-                        };
-
-                        self.messages.push(Message {
-                            role: "tool".to_string(),
-                            content: result,
-                            tool_calls: None,
-                            tool_call_id: Some(tool_call_id.to_string()),
-                        });
-                    }
+    /// Run a turn like `invoke`, reporting its progress as [`AgentEvent`]s
+    /// instead of showing it, for GUI and web embedders that render output
+    /// themselves. Confirmations and approvals still go to the current
+    /// frontend. `Done` is sent only when the turn succeeds.
+    pub async fn invoke_with_events(&mut self, prompt: &str, mut on_event: impl FnMut(AgentEvent)) -> Result<(), Error> {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let frontend = self.frontend.clone();
+        self.set_frontend(Arc::new(EventFrontend::new(sender, frontend.clone())));
+
+        let result = {
+            let invoke = self.invoke(prompt);
+            tokio::pin!(invoke);
+            loop {
+                tokio::select! {
+                    result = &mut invoke => break result,
+                    Some(event) = receiver.recv() => on_event(event),
                 }
-
-                continue;
-            } else {
-                // No tool calls - add final response
-                self.messages.push(Message {
-                    role: "assistant".to_string(),
-                    content: ollama_response.content.clone(),
-                    tool_calls: None,
-                    tool_call_id: None,
-                });
-
-                // Return the final response content
-                return Ok(ollama_response.content);
             }
-        }
+        };
 
-        // If we exited due to max_turns, return the last assistant message
-        if let Some(last_msg) = self.messages.iter().rev().find(|m| m.role == "assistant") {
-            Ok(last_msg.content.clone())
-        } else {
-            Err(Error::Message("Subagent: No response generated".to_string()))
+        self.set_frontend(frontend);
+        while let Ok(event) = receiver.try_recv() {
+            on_event(event);
+        }
+        if result.is_ok() {
+            on_event(AgentEvent::Done(self.last_answer().unwrap_or_default().to_string()));
         }
+        result
     }
 
-    async fn execute_tool(&mut self, name: &str, arguments: &Value) -> Result<String, Error> {
-        // Special handling for Task tool
-        if name == "task" {
-            // Format a concise description for Task tool
-            let display_args = if let Some(desc) = arguments.get("description").and_then(|v| v.as_str()) {
-                Some(format!("\"{}\"", desc))
-            } else {
-                None
-            };
-            UI::tool_start("Task", display_args.as_deref());
+    /// Send write/edit changes to `sink` as patch events instead of writing
+    /// them, for clients that preview and apply edits themselves. The CLI
+    /// never calls this and keeps writing directly.
+    pub fn propose_edits(&mut self, sink: Option<PatchSink>) {
+        self.patches = sink;
+    }
 
-            // Parse arguments
-            let subagent_type_str = arguments
-                .get("subagent_type")
-                .and_then(|v| v.as_str())
-                .unwrap_or("general-purpose");
+    /// Background subagent tasks of the session
+    pub fn tasks(&self) -> &TaskManager {
+        &self.tasks
+    }
 
-            let subagent_type = match subagent_type_str {
-                "general-purpose" => SubAgentType::GeneralPurpose,
-                "explore" => SubAgentType::Explore,
-                "plan" => SubAgentType::Plan,
-                "code-review" => SubAgentType::CodeReview,
-                "test-runner" => SubAgentType::TestRunner,
-                _ => return Err(Error::Message(format!("Invalid subagent type: {}", subagent_type_str))),
-            };
+    /// Stop background task `id` (the `task_cancel` tool, `/cancel <id>`)
+    pub fn cancel_task(&mut self, id: usize) -> Result<String, Error> {
+        let execution = self.tasks.cancel(id)?;
+        Ok(format!("Cancelled task {} ({})", id, execution.task.description))
+    }
 
-            let description = arguments
-                .get("description")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| Error::Message("Missing 'description' argument".to_string()))?;
+    /// Record the background tasks that have finished since the last
+    /// check: their tokens and timings join the session's, and their results
+    /// become available through `tasks()` and `task_result`
+    pub fn collect_tasks(&mut self) {
+        use futures_util::FutureExt;
+
+        let finished: Vec<usize> = self
+            .running
+            .iter()
+            .filter(|(_, handle)| handle.is_finished())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in finished {
+            if let Some(joined) = self.running.remove(&id).and_then(|handle| handle.now_or_never()) {
+                self.settle_task(id, joined);
+            }
+        }
+    }
 
-            let prompt = arguments
-                .get("prompt")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| Error::Message("Missing 'prompt' argument".to_string()))?;
+    /// Background task `id` once collected; with `wait`, first wait for it
+    /// to finish (the wait ends early when the turn is cancelled)
+    pub async fn task_result(&mut self, id: usize, wait: bool) -> Result<&SubAgentExecution, Error> {
+        self.collect_tasks();
+        if wait && let Some(mut handle) = self.running.remove(&id) {
+            match self.cancel.or_cancelled(&mut handle).await {
+                Some(joined) => self.settle_task(id, joined),
+                None => {
+                    self.running.insert(id, handle);
+                }
+            }
+        }
+        self.tasks
+            .get(id)
+            .ok_or_else(|| Error::Message(format!("No background task {}", id)))
+    }
 
-            let include_tools = arguments
-                .get("include_tools")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
+    fn settle_task(&mut self, id: usize, joined: Result<FinishedSubagent, tokio::task::JoinError>) {
+        let outcome = match joined {
+            Ok(finished) => self
+                .finish_subagent(finished)
+                .map(|output| format_subagent_output(&output))
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(format!("Subagent stopped unexpectedly: {}", e)),
+        };
+        self.tasks.finish(id, outcome);
+        if let Some(execution) = self.tasks.get(id) {
+            self.frontend.notify(Notice::Info, &format!("Background {}", tasks::describe(execution)));
+        }
+    }
 
-            let start_time = Instant::now();
+    /// Start a subagent in the background (`run_in_background`) and return
+    /// the tool result naming its task id. A cached answer is returned as is.
+    async fn spawn_background(
+        &mut self,
+        profile: SubAgentProfile,
+        description: &str,
+        prompt: &str,
+        include_tools: bool,
+        force: bool,
+    ) -> Result<String, Error> {
+        let mut job = match self
+            .prepare_subagent(profile, description, prompt, None, include_tools, force)
+            .await?
+        {
+            PreparedSubagent::Cached(output) => return Ok(format_subagent_output(&output)),
+            PreparedSubagent::Ready(job) => job,
+        };
+        let id = job.id;
+        let task = SubAgentTask::named(job.profile.name.clone(), description, prompt).with_tools(include_tools);
+        // 后台任务有自己的取消令牌，不把输出流式写进当前回复，也不向用户提问（会和 REPL 抢 stdin）
+        job.cancel = self.tasks.track_reserved(id, task);
+        job.background = true;
+        job.frontend = Arc::new(SubagentView::new(id, self.frontend.clone()).quiet(true).unattended(true));
+        self.running.insert(id, tokio::spawn(job.run()));
+        Ok(json!({
+            "task_id": id,
+            "status": "running",
+            "message": format!("Task {} is running in the background; use task_result to collect its answer", id)
+        })
+        .to_string())
+    }
 
-            UI::info(&format!(
-                "🤖 Spawning {} subagent: {}",
-                subagent_type.description(),
-                description
-            ));
+    /// Token that stops the turn in progress: the reply streaming now ends
+    /// with what has arrived, no further tool calls run, and the partial
+    /// turn is kept in the history
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
 
-            // Build initial messages for subagent
-            let mut messages = Vec::new();
+    /// Token that pauses the turn in progress once its running tool calls
+    /// finish and asks the frontend for a correction (`Frontend::redirect`),
+    /// which joins the turn as a user message before the loop goes on
+    pub fn redirect_token(&self) -> RedirectToken {
+        self.redirect.clone()
+    }
 
-            // Add system prompt if applicable
-            if let Some(system_prompt) = subagent_type.system_prompt() {
-                messages.push(Message {
-                    role: "system".to_string(),
-                    content: system_prompt.to_string(),
-                    tool_calls: None,
-                    tool_call_id: None,
-                });
+    pub async fn invoke(&mut self, prompt: &str) -> Result<(), Error> {
+        self.usage.begin(prompt);
+        self.collect_tasks();
+        // `llm` may have been replaced since the last turn
+        self.cancel.reset();
+        self.redirect.take();
+        self.llm.set_cancel(self.cancel.clone());
+        if self.needs_compaction() {
+            match self.compact_history().await {
+                Ok(Some((before, after))) => {
+                    self.frontend.notify(Notice::Info, &format!("Compacted earlier turns: ~{} → ~{} tokens", before, after))
+                }
+                Ok(None) => {}
+                Err(e) => self.frontend.notify(Notice::Warning, &format!("Context compaction failed: {}", e)),
             }
+        }
+        self.retrieve_context(prompt).await;
+        let result = self.run_experiment_turn(prompt).await;
+        self.retrieved = None;
+        self.recalled = None;
+        if result.is_ok() {
+            self.save_session().await;
+        }
+        // 本轮已结束（成功或失败），不再需要崩溃恢复点
+        if let Some(dir) = &self.sessions_dir
+            && let Err(e) = session::clear_checkpoint(dir, &self.session).await
+        {
+            self.frontend
+                .notify(Notice::Warning, &format!("Failed to remove session checkpoint: {}", e));
+        }
+        result
+    }
 
-            // Build the full prompt
-            let full_prompt = format!("Task: {}\n\nDetails:\n{}", description, prompt);
-
-            messages.push(Message {
-                role: "user".to_string(),
-                content: full_prompt,
-                tool_calls: None,
-                tool_call_id: None,
-            });
-
-            // Create a new Agent instance for the subagent
-            let mut subagent = Agent::load_from_config().await?;
+    /// Run a turn, assigning and recording an experiment variant if one is configured
+    async fn run_experiment_turn(&mut self, prompt: &str) -> Result<(), Error> {
+        let Some(experiment) = self.experiment.as_mut() else {
+            return self.run_turn(prompt).await;
+        };
 
-            // Configure if subagent should use tools
-            if !include_tools || !subagent_type.uses_tools() {
-                // Remove tools from subagent
-                subagent.ollama = Ollama::new()
-                    .stream(false)
-                    .think(false);
-            }
+        // 实验模式：轮流分配变体，并记录本轮结果
+        let variant = experiment.assign().name.clone();
+        self.frontend.notify(Notice::Info, &format!("Experiment {}: variant {}", experiment.name(), variant));
+
+        let start = Instant::now();
+        let history_len = self.messages.len();
+        let result = self.run_turn(prompt).await;
+        let tool_calls = self.messages[history_len..]
+            .iter()
+            .filter(|m| m.is_tool())
+            .count();
+
+        if let Some(experiment) = self.experiment.as_mut()
+            && let Err(e) = experiment
+                .record(prompt, result.is_ok(), tool_calls, start.elapsed())
+                .await
+        {
+            self.frontend.notify(Notice::Warning, &format!("Failed to log experiment trial: {}", e));
+        }
+        result
+    }
 
-            // Run the subagent's complete message loop
-            // Allow multiple turns (default 10) for complex tasks
-            let max_turns = 10;
-            let result_content = subagent.run_subagent_loop(messages, max_turns).await?;
+    /// Per-variant results of the running experiment (the `/experiment` command)
+    pub fn experiment_summary(&self) -> Option<(&str, Vec<VariantSummary>)> {
+        self.experiment
+            .as_ref()
+            .map(|experiment| (experiment.name(), experiment.summary()))
+    }
 
-            let elapsed = start_time.elapsed();
+    /// Model for the turn in progress: the experiment variant's model, if any
+    fn turn_model(&self) -> &str {
+        self.experiment
+            .as_ref()
+            .and_then(|experiment| experiment.current())
+            .and_then(|variant| variant.model.as_deref())
+            .or(self.config.model.as_deref())
+            .unwrap_or("qwen3")
+    }
 
-            let output = json!({
-                "task": description,
-                "agent_type": subagent_type.description(),
-                "model": subagent.config.model.as_deref().unwrap_or("qwen3"),
-                "duration_ms": elapsed.as_millis(),
-                "used_tools": include_tools && subagent_type.uses_tools(),
-                "result": result_content,
-            });
+    async fn run_turn(&mut self, prompt: &str) -> Result<(), Error> {
+        if let Some(guard) = self.llm.cost_guard() {
+            // 实验变体可能换用其他模型，按本轮的模型计价
+            guard.start_turn(self.config.pricing.as_ref().and_then(|pricing| pricing.get(self.turn_model())).copied());
+        }
 
-            let result = format!(
-                "=== Subagent Task Complete ===\n{}",
-                serde_json::to_string_pretty(&output).unwrap_or_default()
-            );
+        // 小模型：本轮只提供部分工具，模型可通过 request_tools 按需扩展
+        if let Some(config) = &self.config.tool_trimming {
+            let offered = trim::select(&self.tool_definitions, config, prompt, &self.tool_usage);
+            self.offered_tools = Some(trim::offered_definitions(&self.tool_definitions, &offered, config));
+        }
 
-            UI::tool_content(&result);
-            UI::tool_end();
+        // 本轮的消息先暂存，成功后才写入历史
+        let mut turn = Turn::new(prompt);
 
-            return Ok(result);
-        }
+        // Tool calling 循环
+        let max_iterations = 5;
+        let mut iteration = 0;
 
-        // Regular tool execution
-        for tool in &self.tools {
-            if tool.definition().function.name == name {
-                // Format display args - special handling for todo_write
-                let display_args = if name == "todo_write" {
-                    // For todo_write, show a clean header instead of JSON
-                    Some("updated".to_string())
-                } else if !arguments.is_null() {
-                    Some(serde_json::to_string_pretty(arguments).unwrap_or_default())
-                } else {
-                    None
-                };
-                UI::tool_start(name, display_args.as_deref());
-
-                // 执行工具
-                let result = match tool.execute(arguments).await {
-                    Ok(result) => result,
-                    Err(e) => {
-                        // 显示工具执行错误
-                        UI::tool_error(&e);
-                        return Err(Error::Message(format!("Tool execution error: {}", e)));
-                    }
-                };
+        loop {
+            iteration += 1;
+            if iteration > max_iterations {
+                return Err(Error::Message("Too many tool call iterations".to_string()));
+            }
+
+            if self.cancel.is_cancelled() {
+                self.finish_cancelled(turn, "");
+                return Ok(());
+            }
+            self.autosave(turn.messages()).await;
+
+            // 使用完整的消息历史（加上本轮暂存的消息）调用 Ollama
+            let response = self
+                .llm
+                .chat_stream(self.turn_model(), &self.request_messages(turn.messages()), self.offered_tools.as_deref())
+                .await?;
+            self.usage.record(response.usage);
+            if self.cancel.is_cancelled() {
+                // 中断的回复里的工具调用可能不完整，不执行
+                self.finish_cancelled(turn, &response.content);
+                return Ok(());
+            }
+
+            // 检查是否有 tool calls
+            if let Some(tool_calls) = response.tool_calls {
+                if !self.llm.stream_content() {
+                    self.frontend.response(&response.content);
+                }
 
-                // 显示工具执行结果 - special handling for todo_write
-                if name == "todo_write" {
-                    // For todo_write, display with proper line breaks
-                    println!();
-                    for line in result.lines() {
-                        println!("{}", line);
+                // 添加助手消息（包含 tool_calls）
+                turn.push(Message::assistant_with_tools(response.content.clone(), tool_calls.clone()));
+
+                // 执行每个工具调用，结果按调用顺序作为 tool 角色的消息暂存
+                // 结果与调用顺序一致；read 读到的图片附在对应的结果上
+                let paths = tool_calls
+                    .iter()
+                    .filter_map(|call| call.get("function"))
+                    .map(|function| function.pointer("/arguments/file_path").and_then(|v| v.as_str()));
+                let results = self.run_tool_calls(&tool_calls).await?;
+                for ((tool_call_id, name, result), path) in results.into_iter().zip(paths) {
+                    let images = match (&self.images, path) {
+                        (Some(sink), Some(path)) => attachment::take_for(sink, path),
+                        _ => Vec::new(),
+                    };
+                    turn.push(Message::tool(Some(tool_call_id), Some(name), result).with_images(images));
+                }
+
+                // 检查点：用户按了 Esc，工具执行完后暂停，带上纠正继续
+                if self.redirect.take() && !self.cancel.is_cancelled() {
+                    match self.frontend.redirect() {
+                        Some(correction) => {
+                            turn.push(Message::user(correction));
+                            // 新的指示重新计算工具调用轮数
+                            iteration = 0;
+                            self.frontend.notify(Notice::Info, "Going on with your correction");
+                        }
+                        None => self.frontend.notify(Notice::Info, "Going on unchanged"),
                     }
-                } else {
-                    UI::tool_content(&result);
                 }
-                UI::tool_end();
 
-                return Ok(result);
+                // 继续循环，让模型基于工具结果生成最终回复
+                continue;
+            } else {
+                // 没有 tool calls，这是最终回复
+                let tool_outputs: Vec<&str> = turn
+                    .messages()
+                    .iter()
+                    .filter(|m| m.is_tool())
+                    .map(|m| m.content())
+                    .collect();
+
+                // 显示前用第二个模型核对回复是否有工具结果支撑
+                let claims = self.verify_answer(&response.content, &tool_outputs).await;
+
+                if !self.llm.stream_content() {
+                    // 折叠逐字重复的工具输出
+                    let display = collapse_echoes(&response.content, &tool_outputs);
+                    self.frontend.response(display.as_deref().unwrap_or(&response.content));
+                }
+                if let Some(claims) = claims {
+                    self.frontend.notify(Notice::Warning, &format!("Possibly unsupported claims:\n{}", claims));
+                }
+
+                turn.push(Message::assistant(response.content.clone()));
+
+                // 标注回复所依据的工具结果
+                self.frontend.citations(&turn.citations());
+
+                if let Some(guard) = self.llm.cost_guard()
+                    && guard.priced()
+                {
+                    self.frontend.notify(Notice::Info, &format!("Turn cost: ~${:.4}", guard.spent()));
+                }
+
+                // 本轮成功完成，提交到历史
+                turn.commit(&mut self.messages);
+                return Ok(());
             }
         }
-        Err(Error::Message(format!("Tool not found: {}", name)))
     }
 
-    pub fn clear_history(&mut self) {
-        self.messages.clear();
+    /// Keep what a cancelled turn produced: the prompt, the tool calls that
+    /// finished and the partial reply. A turn that produced nothing is dropped.
+    fn finish_cancelled(&mut self, mut turn: Turn, partial: &str) {
+        if !partial.is_empty() {
+            if !self.llm.stream_content() {
+                self.frontend.response(partial);
+            }
+            turn.push(Message::assistant(partial.to_string()));
+        }
+        self.frontend.notify(Notice::Warning, "Generation cancelled");
+        if turn.messages().len() > 1 {
+            turn.commit(&mut self.messages);
+        }
     }
 
-    /// Spawn a subagent to handle a specialized task
-    #[allow(dead_code)]
-    pub async fn spawn_task(
+    /// Ask the verification model which claims in `answer` the tool outputs
+    /// don't support. Skipped when verification is off or no tools ran.
+    async fn verify_answer(&mut self, answer: &str, tool_outputs: &[&str]) -> Option<String> {
+        let verifier = self.verifier.as_ref()?;
+        if tool_outputs.is_empty() || answer.trim().is_empty() {
+            return None;
+        }
+
+        let model = self
+            .config
+            .verification
+            .as_ref()
+            .and_then(|verification| verification.model.as_deref())
+            .or(self.config.model.as_deref())
+            .unwrap_or("qwen3");
+
+        match verifier
+            .chat(model, &verification_messages(answer, tool_outputs), None)
+            .await
+        {
+            Ok(response) => {
+                self.usage.record(response.usage);
+                unsupported_claims(&response.content)
+            }
+            Err(e) => {
+                self.frontend.notify(Notice::Warning, &format!("Answer verification failed: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Ask several models the same question in parallel and have a judge
+    /// model merge their answers (the `/consensus` command). Tools are not
+    /// available to the panel; the exchange is recorded like a normal turn.
+    pub async fn consensus(&mut self, prompt: &str, n: Option<usize>) -> Result<(), Error> {
+        use futures_util::future::join_all;
+
+        let default_model = self.config.model.as_deref().unwrap_or("qwen3").to_string();
+        let consensus = self.config.consensus.clone().unwrap_or_default();
+        let models = consensus::panel(&consensus.models, &default_model, n);
+        let judge = consensus.judge.unwrap_or(default_model);
+
+        self.usage.begin(prompt);
+        let mut turn = Turn::new(prompt);
+        let messages = self.request_messages(turn.messages());
+        let client = self.llm.quiet();
+
+        self.frontend.notify(Notice::Info, &format!("Asking {} models: {}", models.len(), models.join(", ")));
+        let results = join_all(
+            models
+                .iter()
+                .map(|model| client.chat(model, &messages, None)),
+        )
+        .await;
+
+        let mut answers = Vec::new();
+        for (model, result) in models.iter().zip(results) {
+            if let Ok(response) = &result {
+                self.usage.record(response.usage);
+            }
+            match result {
+                Ok(response) if !response.content.trim().is_empty() => {
+                    answers.push((model.clone(), response.content));
+                }
+                Ok(_) => self.frontend.notify(Notice::Warning, &format!("{} returned an empty answer", model)),
+                Err(e) => self.frontend.notify(Notice::Warning, &format!("{} failed: {}", model, e)),
+            }
+        }
+        if answers.is_empty() {
+            return Err(Error::Message("No model on the panel answered".to_string()));
+        }
+
+        // 只有一个回答时无需裁判
+        let content = if answers.len() == 1 {
+            answers.remove(0).1
+        } else {
+            self.frontend.notify(Notice::Info, &format!("Merging {} answers with {}", answers.len(), judge));
+            let response = client
+                .chat(&judge, &consensus::judge_messages(prompt, &answers), None)
+                .await?;
+            self.usage.record(response.usage);
+            response.content
+        };
+        self.frontend.response(&content);
+
+        turn.push(Message::assistant(content));
+        turn.commit(&mut self.messages);
+        self.save_session().await;
+        Ok(())
+    }
+
+    /// Whether the history has grown past the compaction threshold
+    fn needs_compaction(&self) -> bool {
+        let config = self.config.compaction.clone().unwrap_or_default();
+        config.is_enabled()
+            && compact::total_tokens(&self.messages) > config.max_tokens.unwrap_or(DEFAULT_COMPACT_TOKENS)
+    }
+
+    /// Summarize the turns before the most recent ones with the model and
+    /// replace them with a system note (the `/compact` command). Returns
+    /// the estimated history tokens before and after, or `None` when there
+    /// is nothing old enough to compact.
+    pub async fn compact(&mut self) -> Result<Option<(u64, u64)>, Error> {
+        self.usage.begin("/compact");
+        self.compact_history().await
+    }
+
+    /// `compact`, counting the summary request towards the current turn
+    async fn compact_history(&mut self) -> Result<Option<(u64, u64)>, Error> {
+        let config = self.config.compaction.clone().unwrap_or_default();
+        let split = compact::split_point(&self.messages, config.keep_turns.unwrap_or(CONTEXT_KEEP_TURNS));
+        if split == 0 {
+            return Ok(None);
+        }
+
+        let model = config
+            .model
+            .as_deref()
+            .or(self.config.model.as_deref())
+            .unwrap_or("qwen3");
+        let before = compact::total_tokens(&self.messages);
+        let response = self
+            .llm
+            .quiet()
+            .chat(model, &compact::summary_messages(&self.messages[..split]), None)
+            .await?;
+        self.usage.record(response.usage);
+        let summary = response.content;
+        if summary.trim().is_empty() {
+            return Err(Error::Message("The model returned an empty summary".to_string()));
+        }
+
+        compact::apply(&mut self.messages, split, &summary);
+        self.save_session().await;
+        Ok(Some((before, compact::total_tokens(&self.messages))))
+    }
+
+    /// Completed turns in the conversation (the user prompts in history)
+    pub fn turns(&self) -> usize {
+        self.messages.iter().filter(|m| m.is_user()).count()
+    }
+
+    /// The most recent final answer, for `/last`
+    pub fn last_answer(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| matches!(m, Message::Assistant { tool_calls, content } if tool_calls.is_empty() && !content.trim().is_empty()))
+            .map(|m| m.content())
+    }
+
+    /// Identifier of this conversation, as accepted by `/resume`
+    pub fn session_id(&self) -> &str {
+        &self.session
+    }
+
+    /// Checkpoint the history plus the unfinished turn when the autosave
+    /// interval has passed since the last save, so a crash mid-turn can be
+    /// recovered on the next start
+    async fn autosave(&mut self, staged: &[Message]) {
+        let interval = Duration::from_secs(self.config.autosave_secs.unwrap_or(DEFAULT_AUTOSAVE_SECS));
+        let Some(dir) = &self.sessions_dir else {
+            return;
+        };
+        if interval.is_zero() || self.last_autosave.elapsed() < interval {
+            return;
+        }
+        let mut messages = self.messages.clone();
+        messages.extend(staged.iter().cloned());
+        if let Err(e) = session::save_checkpoint(dir, Session::new(&self.session, &messages).timings(&self.timings).seed(self.config.seed)).await {
+            self.frontend
+                .notify(Notice::Warning, &format!("Failed to checkpoint session: {}", e));
+        }
+        self.last_autosave = Instant::now();
+    }
+
+    /// Sessions whose process died mid-turn, newest first
+    pub async fn crashed_sessions(&self) -> Result<Vec<Checkpoint>, Error> {
+        session::crashed(&self.sessions_path()).await
+    }
+
+    /// Continue a crashed session from its checkpoint, saving it as a
+    /// normal session. Returns the number of recovered messages.
+    pub async fn recover(&mut self, checkpoint: Checkpoint) -> Result<usize, Error> {
+        self.messages = checkpoint.session.messages;
+        self.timings = checkpoint.session.timings;
+        self.adopt_seed(checkpoint.session.seed);
+        self.session = checkpoint.session.id;
+        self.save_session().await;
+        self.discard_checkpoint(&self.session).await?;
+        Ok(self.messages.len())
+    }
+
+    /// Delete the checkpoint of session `id` (a declined recovery)
+    pub async fn discard_checkpoint(&self, id: &str) -> Result<(), Error> {
+        session::clear_checkpoint(&self.sessions_path(), id).await
+    }
+
+    /// Save the conversation to the sessions directory. A failed save is
+    /// reported but doesn't fail the turn.
+    async fn save_session(&mut self) {
+        let Some(dir) = &self.sessions_dir else {
+            return;
+        };
+        if self.messages.is_empty() {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        let saved = Session::new(&self.session, &self.messages).timings(&self.timings).seed(self.config.seed);
+        if let Err(e) = session::save(dir, &saved).await {
+            self.frontend.notify(Notice::Warning, &format!("Failed to save session: {}", e));
+        }
+    }
+
+    /// Directory sessions are restored from, even when saving is off
+    fn sessions_path(&self) -> PathBuf {
+        self.sessions_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(session::SESSIONS_DIR))
+    }
+
+    /// Saved sessions, most recently active first (the `/resume` picker)
+    pub async fn saved_sessions(&self) -> Result<Vec<SessionSummary>, Error> {
+        session::list(&self.sessions_path()).await
+    }
+
+    /// Replace the conversation with saved session `id` and continue it
+    /// under that id. Returns the number of restored messages.
+    pub async fn resume(&mut self, id: &str) -> Result<usize, Error> {
+        let saved = session::load(&self.sessions_path(), id).await?;
+        self.messages = saved.messages;
+        self.timings = saved.timings;
+        self.session = saved.id;
+        self.adopt_seed(saved.seed);
+        Ok(self.messages.len())
+    }
+
+    /// Sample every request of the session (subagents and answer
+    /// verification included) with `seed`; it is saved with the session
+    pub fn set_seed(&mut self, seed: u64) {
+        self.config.seed = Some(seed);
+        self.llm.set_seed(Some(seed));
+        if let Some(verifier) = self.verifier.as_mut() {
+            verifier.set_seed(Some(seed));
+        }
+    }
+
+    /// The sampling seed of the session, if any
+    pub fn seed(&self) -> Option<u64> {
+        self.config.seed
+    }
+
+    /// Go on with the seed of a restored session unless one is already set
+    fn adopt_seed(&mut self, saved: Option<u64>) {
+        if self.config.seed.is_none()
+            && let Some(seed) = saved
+        {
+            self.set_seed(seed);
+        }
+    }
+
+    /// Run a complete message loop for a subagent (used by Task tool)
+    /// This allows the subagent to have multi-turn conversations and use tools.
+    /// Hitting `max_turns` or the timeout returns the last answer so far, marked truncated.
+    pub async fn run_subagent_loop(
         &mut self,
-        subagent_type: SubAgentType,
-        description: &str,
-        prompt: &str,
-    ) -> Result<String, Error> {
-        self.spawn_task_with_options(subagent_type, description, prompt, None, false).await
+        initial_messages: Vec<Message>,
+        limits: &SubagentLimits,
+    ) -> Result<SubAgentRun, Error> {
+        // Set initial messages
+        self.messages = initial_messages;
+        self.llm.set_cancel(self.cancel.clone());
+        // 以任务本身作为检索的查询
+        let task = self.messages.iter().rfind(|message| message.is_user()).map(|message| message.content().to_string());
+        if let Some(task) = task {
+            self.retrieve_context(&task).await;
+        }
+
+        let max_turns = limits.max_turns.unwrap_or(DEFAULT_SUBAGENT_MAX_TURNS);
+        let max_iterations = limits.max_iterations.unwrap_or(DEFAULT_SUBAGENT_MAX_ITERATIONS);
+        let turns = self.subagent_turns(max_turns, max_iterations);
+        let outcome = match limits.timeout_secs.unwrap_or(DEFAULT_SUBAGENT_TIMEOUT_SECS) {
+            0 => Ok(turns.await),
+            secs => tokio::time::timeout(Duration::from_secs(secs), turns).await,
+        };
+        let truncated = match outcome {
+            Ok(Ok(Ok(content))) => return Ok(SubAgentRun { content, truncated: None }),
+            Ok(Ok(Err(truncated))) => truncated,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => Truncation::Timeout,
+        };
+
+        // 被截断时返回目前为止最后一条有内容的回复
+        match self
+            .messages
+            .iter()
+            .rev()
+            .find(|m| matches!(m, Message::Assistant { .. }) && !m.content().trim().is_empty())
+        {
+            Some(last_msg) => Ok(SubAgentRun {
+                content: last_msg.content().to_string(),
+                truncated: Some(truncated),
+            }),
+            None => Err(Error::Message(format!(
+                "Subagent: No response generated (stopped by {})",
+                truncated.as_str()
+            ))),
+        }
     }
 
-    /// Spawn a subagent with additional options
-    #[allow(dead_code)]
-    pub async fn spawn_task_with_options(
+    /// Model replies and their tool calls until a final answer, `max_turns`
+    /// replies or cancellation. At most `max_iterations` calls of each reply
+    /// are run.
+    async fn subagent_turns(
         &mut self,
-        subagent_type: SubAgentType,
-        description: &str,
-        prompt: &str,
-        context_messages: Option<&[Message]>,
-        include_tools: bool,
-    ) -> Result<String, Error> {
-        let start_time = Instant::now();
+        max_turns: usize,
+        max_iterations: usize,
+    ) -> Result<Result<String, Truncation>, Error> {
+        for _ in 0..max_turns {
+            if self.cancel.is_cancelled() {
+                return Ok(Err(Truncation::Cancelled));
+            }
+            // Call LLM
+            let model = self.config.model.as_deref().unwrap_or("qwen3");
+            let messages = self.request_messages(&[]);
+            let tools = self.offered_tools.as_deref();
+            let response = if self.stream {
+                self.llm.chat_stream(model, &messages, tools).await?
+            } else {
+                self.llm.chat(model, &messages, tools).await?
+            };
+            self.usage.record(response.usage);
+            if self.cancel.is_cancelled() {
+                // 中断的回复只保留文字部分
+                self.messages.push(Message::assistant(response.content));
+                return Ok(Err(Truncation::Cancelled));
+            }
 
-        UI::info(&format!(
-            "🤖 Spawning {} subagent: {}",
-            subagent_type.description(),
-            description
-        ));
+            // Check for tool calls
+            let Some(tool_calls) = response.tool_calls else {
+                // No tool calls - add final response
+                self.messages.push(Message::assistant(response.content.clone()));
+                return Ok(Ok(response.content));
+            };
 
-        // Build the messages
-        let mut messages = Vec::new();
+            // Add assistant message
+            self.messages.push(Message::assistant_with_tools(response.content.clone(), tool_calls.clone()));
+
+            // Execute tools
+            for (iteration, tool_call) in tool_calls.iter().enumerate() {
+                if let Some(function) = tool_call.get("function") {
+                    let name = function
+                        .get("name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    let default_args = json!({});
+                    let arguments = function.get("arguments").unwrap_or(&default_args);
+
+                    let tool_call_id = tool_call
+                        .get("id")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+
+                    let refusal = if self.cancel.is_cancelled() {
+                        Some(json!({"error": "The task was cancelled before this call ran"}))
+                    } else if iteration >= max_iterations {
+                        Some(json!({
+                            "error": format!("Only {} tool calls are run per reply", max_iterations),
+                            "suggestion": "Make the remaining calls in your next reply"
+                        }))
+                    } else if name == "task" || name == "parallel_tasks" {
+                        // Subagents cannot spawn additional subagents (prevent infinite recursion)
+                        Some(json!({
+                            "error": "Subagents cannot spawn additional subagents",
+                            "suggestion": "Complete the task yourself using available tools"
+                        }))
+                    } else {
+                        None
+                    };
+                    if let Some(refusal) = refusal {
+                        self.messages.push(Message::tool(Some(tool_call_id.to_string()), Some(name.to_string()), refusal.to_string()));
+                        continue;
+                    }
 
-        // Add system prompt if applicable
-        if let Some(system_prompt) = subagent_type.system_prompt() {
-            messages.push(Message {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
-                tool_calls: None,
-                tool_call_id: None,
-            });
-        }
+                    // Execute tool
+                    let result = match Box::pin(self.timed_tool(tool_call_id, name, arguments)).await {
+                        Ok(result) => result,
+                        Err(e) => {
+                            format!("Tool execution error: {}", e)
+                        }
+                    };
 
-        // Add context if provided (limit to last 10 messages to avoid overwhelming the agent)
-        if let Some(context) = context_messages {
-            let context_len = context.len();
-            let start = if context_len > 10 { context_len - 10 } else { 0 };
-            for msg in &context[start..] {
-                // Skip system messages in context
-                if msg.role != "system" {
-                    messages.push(msg.clone());
+                    self.messages.push(Message::tool(Some(tool_call_id.to_string()), Some(name.to_string()), result));
                 }
             }
         }
 
-        // Build the full prompt
-        let full_prompt = format!("Task: {}\n\nDetails:\n{}", description, prompt);
+        Ok(Err(Truncation::MaxTurns))
+    }
 
-        // Add user message
-        messages.push(Message {
-            role: "user".to_string(),
-            content: full_prompt,
-            tool_calls: None,
-            tool_call_id: None,
-        });
+    /// Run one tool as if the model had called it: the same permission
+    /// checks, workspace confinement, progress display and usage log. For
+    /// embedders and tests that drive tools without a model turn. The call
+    /// is not added to the conversation.
+    pub async fn run_tool(&mut self, name: &str, arguments: Value) -> Result<String, Error> {
+        if !arguments.is_object() {
+            return Err(Error::Message(format!("Arguments for {} must be a JSON object", name)));
+        }
+        self.execute_tool(name, &arguments).await
+    }
+
+    /// Execute a tool call and record it in the tool usage log
+    async fn execute_tool(&mut self, name: &str, arguments: &Value) -> Result<String, Error> {
+        let result = self.dispatch_tool(name, arguments).await;
+        self.record_tool_call(name, arguments, &result).await;
+        result
+    }
 
-        // Create a new Agent instance for the subagent
-        let mut subagent = Agent::load_from_config().await?;
+    async fn record_tool_call(&self, name: &str, arguments: &Value, result: &Result<String, Error>) {
+        let entry = match result {
+            Ok(output) => ToolCallRecord::new(&self.session, name, arguments, Ok(output)),
+            Err(e) => ToolCallRecord::new(&self.session, name, arguments, Err(&e.to_string())),
+        };
+        // 统计只用于调优，写入失败不影响本轮
+        analytics::record(std::path::Path::new(analytics::TOOL_STATS_FILE), &entry)
+            .await
+            .ok();
+    }
 
-        // Configure if subagent should use tools
-        if !include_tools || !subagent_type.uses_tools() {
-            // Remove tools from subagent
-            subagent.ollama = Ollama::new()
-                .stream(false)
-                .think(false);
+    /// Run the calls of one reply, returning `(id, name, result)` for each
+    /// in call order. Consecutive read-only calls run at the same time; any
+    /// other call waits for the calls before it, and they for it.
+    async fn run_tool_calls(&mut self, tool_calls: &[Value]) -> Result<Vec<(String, String, String)>, Error> {
+        let calls: Vec<(String, String, Value)> = tool_calls
+            .iter()
+            .filter_map(|call| {
+                let function = call.get("function")?;
+                let name = function.get("name").and_then(|v| v.as_str()).unwrap_or("");
+                let arguments = function.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                let id = call.get("id").and_then(|v| v.as_str()).unwrap_or("");
+                Some((id.to_string(), name.to_string(), arguments))
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(calls.len());
+        let mut start = 0;
+        while start < calls.len() {
+            let batch = calls[start..]
+                .iter()
+                .take_while(|(_, name, _)| runs_concurrently(name))
+                .count()
+                .max(1);
+            let outputs = match &calls[start..start + batch] {
+                [(id, name, arguments)] => vec![self.timed_tool(id, name, arguments).await?],
+                batch => self.execute_concurrently(batch).await?,
+            };
+            for ((id, name, _), output) in calls[start..start + batch].iter().zip(outputs) {
+                results.push((id.clone(), name.clone(), output));
+            }
+            start += batch;
         }
+        Ok(results)
+    }
 
-        // Run the subagent's complete message loop
-        let max_turns = 10;
-        let result_content = subagent.run_subagent_loop(messages, max_turns).await?;
+    /// `execute_tool`, recording when the call ran
+    async fn timed_tool(&mut self, call_id: &str, name: &str, arguments: &Value) -> Result<String, Error> {
+        let watch = Stopwatch::start();
+        let result = self.execute_tool(name, arguments).await;
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        self.timings.push(watch.stop(TimingKind::Tool, name, outcome).call_id(call_id));
+        result
+    }
 
-        let elapsed = start_time.elapsed();
+    /// Run read-only calls at the same time. Approvals are asked one by one
+    /// first; results are shown in call order once all have finished. Each
+    /// call runs as in `dispatch_tool`, heartbeat and runtime note included.
+    async fn execute_concurrently(&mut self, calls: &[(String, String, Value)]) -> Result<Vec<String>, Error> {
+        use futures_util::future::join_all;
 
-        // Format structured output
-        let output = json!({
-            "task": description,
-            "agent_type": subagent_type.description(),
-            "model": subagent.config.model.as_deref().unwrap_or("qwen3"),
-            "duration_ms": elapsed.as_millis(),
-            "used_tools": include_tools && subagent_type.uses_tools(),
-            "result": result_content,
+        let mut refusals = Vec::with_capacity(calls.len());
+        for (_, name, arguments) in calls {
+            let refusal = match self.tools.contains(name) {
+                true => self.authorize(name, arguments).await,
+                false => None,
+            };
+            refusals.push(refusal);
+        }
+
+        let context = self.tool_context();
+        let this = &*self;
+        let executions = calls.iter().zip(&refusals).map(|((id, name, arguments), refusal)| {
+            let context = &context;
+            async move {
+                let watch = Stopwatch::start();
+                let outcome = match (refusal, this.tools.get(name)) {
+                    (Some(_), _) => None,
+                    (None, Some(tool)) => Some(this.execute_registered(tool, name, arguments, context.clone()).await),
+                    (None, None) => None,
+                };
+                let status = match &outcome {
+                    Some(ToolOutcome { outcome: Err(_), .. }) => "error",
+                    None if refusal.is_none() => "error",
+                    _ => "ok",
+                };
+                (outcome, watch.stop(TimingKind::Tool, name, status).call_id(id))
+            }
         });
+        let (outcomes, timings): (Vec<_>, Vec<_>) = join_all(executions).await.into_iter().unzip();
+        self.timings.extend(timings);
+
+        let mut outputs = Vec::with_capacity(outcomes.len());
+        let mut failure = None;
+        for (((_, name, arguments), refusal), outcome) in calls.iter().zip(&refusals).zip(outcomes) {
+            let outcome = match (refusal, outcome) {
+                (Some(refusal), _) => Ok(refusal.clone()),
+                (None, Some(outcome)) => outcome.show(name, &*self.frontend),
+                (None, None) => Err(Error::Message(format!("Tool not found: {}", name))),
+            };
+            self.record_tool_call(name, arguments, &outcome).await;
+            match outcome {
+                Ok(result) => outputs.push(result),
+                // 与逐个执行一致：工具出错时结束本轮，但先展示并记录其余结果
+                Err(e) => failure = failure.or(Some(e)),
+            }
+        }
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(outputs),
+        }
+    }
 
-        let formatted = format!(
-            "=== Subagent Task Complete ===\n{}",
-            serde_json::to_string_pretty(&output).unwrap_or_default()
-        );
+    async fn dispatch_tool(&mut self, name: &str, arguments: &Value) -> Result<String, Error> {
+        if name == trim::REQUEST_TOOLS
+            && let Some(config) = self.config.tool_trimming.clone()
+        {
+            let requested: Vec<&str> = arguments
+                .get("tools")
+                .and_then(|v| v.as_array())
+                .map(|tools| tools.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+            return Ok(self.expand_tools(&requested, &config));
+        }
 
-        UI::success(&format!("✓ Subagent completed in {:.2}s", elapsed.as_secs_f64()));
+        // Special handling for Task tool
+        if name == "task" {
+            // Format a concise description for Task tool
+            let display_args = arguments
+                .get("description")
+                .and_then(|v| v.as_str())
+                .map(|desc| format!("\"{}\"", desc));
+            self.frontend.tool_start("Task", display_args.as_deref());
 
-        Ok(formatted)
-    }
+            // Parse arguments
+            let subagent_type_str = arguments
+                .get("subagent_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("general-purpose");
 
-    /// Spawn multiple subagent tasks concurrently
-    #[allow(dead_code)]
-    pub async fn spawn_multiple_tasks(&mut self, tasks: Vec<SubAgentTask>) -> Result<Vec<String>, Error> {
-        use futures_util::future::join_all;
+            let profile = self.subagent_profile(subagent_type_str)?;
 
-        let total = tasks.len();
-        UI::info(&format!("🚀 Spawning {} subagent tasks concurrently...", total));
+            let description = arguments
+                .get("description")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| Error::Message("Missing 'description' argument".to_string()))?;
 
-        let start_time = Instant::now();
+            let prompt = arguments
+                .get("prompt")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| Error::Message("Missing 'prompt' argument".to_string()))?;
 
-        // Build futures for all tasks
-        let mut futures = Vec::new();
+            let include_tools = arguments
+                .get("include_tools")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
 
-        for task in tasks {
-            let future = async move {
-                let mut agent = Agent::load_from_config().await?;
-                agent
-                    .spawn_task(
-                        task.subagent_type,
-                        &task.description,
-                        &task.prompt,
-                    )
-                    .await
+            let force = arguments
+                .get("force")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let in_background = arguments
+                .get("run_in_background")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let result = if in_background {
+                self.spawn_background(profile, description, prompt, include_tools, force)
+                    .await?
+            } else {
+                self.run_subagent(profile, description, prompt, None, include_tools, force)
+                    .await?
             };
-            futures.push(future);
+
+            self.frontend.tool_result(name, &result);
+
+            return Ok(result);
         }
 
-        // Execute all tasks concurrently
-        let results = join_all(futures).await;
+        if name == "task_cancel" {
+            let id = arguments
+                .get("task_id")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| Error::Message("Missing 'task_id' argument".to_string()))?;
+            self.frontend.tool_start("Cancel task", Some(&id.to_string()));
+            let result = match self.cancel_task(id as usize) {
+                Ok(message) => json!({"success": true, "message": message}),
+                Err(e) => json!({"success": false, "error": e.to_string()}),
+            };
+            let result = result.to_string();
+            self.frontend.tool_result(name, &result);
+            return Ok(result);
+        }
+
+        if name == "task_status" {
+            let id = arguments.get("task_id").and_then(|v| v.as_u64());
+            self.frontend.tool_start("Task status", id.map(|id| id.to_string()).as_deref());
+            self.collect_tasks();
+            let result = match id {
+                Some(id) => match self.tasks.get(id as usize) {
+                    Some(execution) => tasks::status_json(execution),
+                    None => json!({"error": format!("No background task {}", id)}),
+                },
+                None => json!(self.tasks.iter().map(tasks::status_json).collect::<Vec<_>>()),
+            };
+            let result = result.to_string();
+            self.frontend.tool_result(name, &result);
+            return Ok(result);
+        }
+
+        if name == "task_result" {
+            let id = arguments
+                .get("task_id")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| Error::Message("Missing 'task_id' argument".to_string()))?;
+            let wait = arguments.get("wait").and_then(|v| v.as_bool()).unwrap_or(true);
+            self.frontend.tool_start("Task result", Some(&id.to_string()));
+            let result = match self.task_result(id as usize, wait).await {
+                Ok(execution) => match (&execution.result, &execution.status) {
+                    (Some(result), _) => result.clone(),
+                    (None, SubAgentStatus::Failed(error)) => json!({"task_id": id, "error": error}).to_string(),
+                    (None, _) => tasks::status_json(execution).to_string(),
+                },
+                Err(e) => json!({"error": e.to_string()}).to_string(),
+            };
+            self.frontend.tool_result(name, &result);
+            return Ok(result);
+        }
+
+        if name == "parallel_tasks" {
+            let tasks: Vec<ParallelTask> = serde_json::from_value(arguments.get("tasks").cloned().unwrap_or_default())
+                .map_err(|e| Error::Message(format!("Invalid 'tasks' argument: {}", e)))?;
+            if tasks.is_empty() {
+                return Err(Error::Message("'tasks' must list at least one task".to_string()));
+            }
+            let summary = tasks
+                .iter()
+                .map(|task| format!("\"{}\"", task.description))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.frontend.tool_start("Parallel tasks", Some(&summary));
+
+            let outputs = self.run_parallel_subagents(&tasks).await?;
+            let result = format!(
+                "=== {} Subagent Tasks Complete ===\n{}",
+                outputs.len(),
+                serde_json::to_string_pretty(&outputs).unwrap_or_default()
+            );
+            self.frontend.tool_result(name, &result);
+            return Ok(result);
+        }
+
+        // 有副作用的工具按权限规则放行、拒绝或询问用户
+        if self.tools.contains(name)
+            && let Some(refusal) = self.authorize(name, arguments).await
+        {
+            return Ok(refusal);
+        }
+
+        // Regular tool execution
+        if let Some(tool) = self.tools.get(name) {
+            let result = self.execute_registered(tool, name, arguments, self.tool_context()).await.show(name, &*self.frontend)?;
+            self.reindex_after(name, arguments);
+            return Ok(result);
+        }
+        Err(Error::Message(format!("Tool not found: {}", name)))
+    }
+
+    /// Run a registered tool past its permission check: show the call,
+    /// show a heartbeat while it runs long, and note its runtime. Shared by
+    /// calls run one by one and those run at the same time; the caller
+    /// shows the outcome with `ToolOutcome::show`.
+    async fn execute_registered(&self, tool: &dyn ToolImpl, name: &str, arguments: &Value, context: ToolContext) -> ToolOutcome {
+        // Format display args - special handling for todo_write
+        let display_args = if name == "todo_write" {
+            // For todo_write, show a clean header instead of JSON
+            Some("updated".to_string())
+        } else if !arguments.is_null() {
+            Some(serde_json::to_string_pretty(arguments).unwrap_or_default())
+        } else {
+            None
+        };
+        self.frontend.tool_start(name, display_args.as_deref());
+
+        // 执行工具；运行较久时定期显示心跳，结果附上总耗时
+        let progress = ToolProgress::new();
+        let context = context.with_progress(progress.clone());
+        let heartbeat = Duration::from_secs(self.config.heartbeat_secs.unwrap_or(DEFAULT_HEARTBEAT_SECS));
+        let quiet_after = if heartbeat.is_zero() { Duration::from_secs(DEFAULT_HEARTBEAT_SECS) } else { heartbeat };
+        let started = Instant::now();
+        let execution = tool.execute_with_context(&context, arguments);
+        tokio::pin!(execution);
+        let outcome = if heartbeat.is_zero() {
+            execution.await
+        } else {
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat, heartbeat);
+            loop {
+                tokio::select! {
+                    outcome = &mut execution => break outcome,
+                    _ = ticker.tick() => self.frontend.tool_heartbeat(
+                        &format_duration(started.elapsed()),
+                        progress.snapshot().describe(quiet_after).as_deref(),
+                    ),
+                }
+            }
+        };
+        ToolOutcome {
+            outcome,
+            note: runtime_note(started.elapsed(), &progress.snapshot(), quiet_after),
+        }
+    }
+
+    /// Check a tool call against the permission policy, asking the user
+    /// when no rule decides. Returns the message sent back to the model in
+    /// place of the tool result when the call is refused.
+    async fn authorize(&mut self, name: &str, arguments: &Value) -> Option<String> {
+        // 由客户端预览并应用修改时，由客户端负责确认；delete/move 不经过客户端，照常确认
+        if self.patches.is_some() && matches!(name, "write" | "edit") {
+            return None;
+        }
+        match self.permissions.check(name, arguments) {
+            Decision::Allow => None,
+            Decision::Deny(rule) => {
+                self.frontend
+                    .notify(Notice::Warning, &format!("Blocked {} by permission rule `{}`", name, rule));
+                Some(format!("Permission denied: this {} call is blocked by the rule `{}`.", name, rule))
+            }
+            Decision::Ask if self.unattended => Some(format!(
+                "Permission denied: background tasks can't ask for approval, and no permission rule allows this {} call.",
+                name
+            )),
+            Decision::Ask => {
+                let request = match permissions::subject(name, arguments) {
+                    Some(subject) => format!("Allow {} `{}`?", name, subject),
+                    None => format!("Allow {}?", name),
+                };
+                let approval = self.frontend.approve(&request);
+                if matches!(approval, Approval::Always | Approval::Never) {
+                    let allow = approval == Approval::Always;
+                    let rule = permissions::rule_for(name, arguments);
+                    self.permissions.add(&rule, allow);
+                    if let Err(e) = permissions::persist(std::path::Path::new(SETTINGS_FILE), &rule, allow).await {
+                        self.frontend
+                            .notify(Notice::Warning, &format!("Failed to save permission rule: {}", e));
+                    }
+                }
+                match approval {
+                    Approval::Once | Approval::Always => None,
+                    Approval::Deny | Approval::Never => {
+                        Some(format!("Permission denied: the user declined this {} call.", name))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add `requested` to the trimmed tool set for the rest of the turn
+    fn expand_tools(&mut self, requested: &[&str], config: &ToolTrimConfig) -> String {
+        let mut offered: Vec<String> = self
+            .offered_tools
+            .iter()
+            .flatten()
+            .map(|definition| definition.function.name.clone())
+            .filter(|name| name != trim::REQUEST_TOOLS)
+            .collect();
+
+        let mut enabled = Vec::new();
+        for name in requested {
+            if self.tool_definitions.iter().any(|d| d.function.name == *name)
+                && !offered.iter().any(|offered| offered == name)
+            {
+                offered.push(name.to_string());
+                enabled.push(*name);
+            }
+        }
+        self.offered_tools = Some(trim::offered_definitions(&self.tool_definitions, &offered, config));
+
+        if enabled.is_empty() {
+            return "No new tools enabled; the requested tools are unknown or already available".to_string();
+        }
+        self.frontend.notify(Notice::Info, &format!("Enabled tools: {}", enabled.join(", ")));
+        format!("Enabled tools: {}. You can call them now.", enabled.join(", "))
+    }
+
+    /// Search `index` with `semantic_search` and keep it updated with
+    /// this agent's edits, in place of the index the settings opened
+    pub fn use_index(&mut self, index: Arc<SemanticIndex>) {
+        if self.tools.contains("semantic_search") {
+            self.register_tool(SemanticSearchTool::new(index.clone()));
+        }
+        self.index = Some(index);
+    }
+
+    /// Start keeping the code index in step with the files: it is brought
+    /// up to date now, then checked every `index.watch_interval_secs`.
+    /// Edits by the agent's own tools are picked up right away regardless.
+    pub fn watch_index(&mut self) {
+        let Some(index) = &self.index else {
+            return;
+        };
+        let interval = self
+            .config
+            .index
+            .as_ref()
+            .and_then(|index| index.watch_interval_secs)
+            .unwrap_or(DEFAULT_WATCH_INTERVAL_SECS);
+        if interval > 0 && self.index_watcher.is_none() {
+            self.index_watcher = Some(index.watch(Duration::from_secs(interval)));
+        }
+    }
+
+    /// Build the code index again from nothing (`/index rebuild`)
+    pub async fn rebuild_index(&self) -> Result<IndexStats, Error> {
+        match &self.index {
+            Some(index) => index.rebuild().await,
+            None => Err(Error::Message(
+                "The code index is off; set \"index\": {\"enabled\": true} in .ariste/settings.json".to_string(),
+            )),
+        }
+    }
+
+    /// Size of the code index and its last failed update (`/index`)
+    pub async fn index_status(&self) -> Result<Option<String>, Error> {
+        match &self.index {
+            Some(index) => index.status().await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Add the documents of `source` (a file, directory or URL) to the
+    /// knowledge base, offering `docs_search` once it holds any
+    pub async fn ingest(&mut self, source: &str) -> Result<IngestReport, Error> {
+        let report = self.knowledge.ingest(source).await?;
+        if !self.tools.contains("docs_search") && self.knowledge.exists() {
+            self.register_tool(DocsSearchTool::new(self.knowledge.clone()));
+        }
+        Ok(report)
+    }
+
+    /// Size of the knowledge base, when anything has been ingested
+    pub async fn knowledge_status(&self) -> Result<Option<String>, Error> {
+        if !self.knowledge.exists() {
+            return Ok(None);
+        }
+        self.knowledge.status().await.map(Some)
+    }
+
+    /// Search the earlier sessions of the project for `query` (`/recall`),
+    /// embedding those saved since the last search. What is found goes
+    /// with the next turn.
+    pub async fn recall(&mut self, query: &str) -> Result<Vec<SearchHit>, Error> {
+        let dir = self.sessions_path();
+        self.memory.sync(&dir, &self.session).await?;
+        let hits = self.memory.search(query, self.memory.default_results(), &self.session).await?;
+        self.recalled = recall::render(&hits);
+        Ok(hits)
+    }
+
+    /// Whether turns get retrieved code and docs (`/rag`)
+    pub fn rag_enabled(&self) -> bool {
+        self.rag
+    }
+
+    /// Turn retrieval augmentation on or off (`/rag on|off`). It needs the
+    /// code index or an ingested knowledge base to search.
+    pub fn set_rag(&mut self, on: bool) -> Result<(), Error> {
+        if on && self.index.is_none() && !self.knowledge.exists() {
+            return Err(Error::Message(
+                "Nothing to retrieve from; enable the code index (\"index\": {\"enabled\": true}) or add docs with `ariste ingest`".to_string(),
+            ));
+        }
+        self.rag = on;
+        Ok(())
+    }
+
+    /// Look up the chunks closest to `query` for the coming requests, when
+    /// retrieval is on. A failed search is reported and the turn goes on
+    /// without them.
+    async fn retrieve_context(&mut self, query: &str) {
+        self.retrieved = None;
+        if !self.rag {
+            return;
+        }
+        let knowledge = self.knowledge.exists().then_some(self.knowledge.as_ref());
+        match rag::retrieve(self.index.as_deref(), knowledge, query, self.config.rag.as_ref()).await {
+            Ok(hits) if !hits.is_empty() => {
+                let sources: Vec<&str> = hits.iter().map(|hit| hit.source.as_str()).collect();
+                self.frontend.notify(Notice::Info, &format!("Retrieved {}", sources.join(", ")));
+                self.retrieved = rag::render(&hits, self.config.rag.as_ref());
+            }
+            Ok(_) => {}
+            Err(e) => self.frontend.notify(Notice::Warning, &format!("Retrieval failed: {}", e)),
+        }
+    }
+
+    /// Re-embed the files a successful tool call changed, in the background
+    fn reindex_after(&self, name: &str, arguments: &Value) {
+        let Some(index) = &self.index else {
+            return;
+        };
+        // 由客户端应用的修改尚未落盘，交给定期检查
+        if self.patches.is_some() {
+            return;
+        }
+        let Some((_, keys)) = FILE_CHANGING_TOOLS.iter().find(|(tool, _)| *tool == name) else {
+            return;
+        };
+        let paths: Vec<PathBuf> = keys
+            .iter()
+            .filter_map(|key| arguments.get(*key).and_then(|v| v.as_str()))
+            .map(|path| self.workspace.resolve(path))
+            .collect();
+        index.refresh_in_background(paths);
+    }
+
+    /// Make a custom tool available to the model, replacing any tool of
+    /// the same name
+    pub fn register_tool(&mut self, tool: impl ToolImpl + 'static) {
+        let definition = tool.definition();
+        let name = definition.function.name.clone();
+        self.tools.register(tool);
+        self.tool_definitions.retain(|def| def.function.name != name);
+        self.tool_definitions.push(definition.clone());
+        if let Some(offered) = &mut self.offered_tools {
+            offered.retain(|def| def.function.name != name);
+            offered.push(definition);
+        } else {
+            self.offered_tools = Some(vec![definition]);
+        }
+    }
+
+    /// Remove a tool so the model is no longer offered it; returns whether
+    /// it was registered
+    pub fn unregister_tool(&mut self, name: &str) -> bool {
+        self.tool_definitions.retain(|def| def.function.name != name);
+        if let Some(offered) = &mut self.offered_tools {
+            offered.retain(|def| def.function.name != name);
+        }
+        self.tools.unregister(name).is_some()
+    }
+
+    /// Remove every tool not in `allowed` from the registry and from the
+    /// definitions sent to the model, so calls to them fail as unknown tools
+    pub fn restrict_tools(&mut self, allowed: &[&str]) {
+        self.tools.retain(|name| allowed.contains(&name));
+        self.tool_definitions
+            .retain(|def| allowed.contains(&def.function.name.as_str()));
+        self.offered_tools = if self.tool_definitions.is_empty() {
+            None
+        } else {
+            Some(self.tool_definitions.clone())
+        };
+    }
+
+    /// Uncommitted changes for a CodeReview subagent, so it doesn't have to
+    /// re-grep the files itself. Limited to the changed files the task
+    /// mentions, or every changed file when it mentions none.
+    async fn review_diff(&self, task: &str) -> Option<String> {
+        let root = &self.workspace.primary().path;
+        if !git::is_repository(root).await {
+            return None;
+        }
+
+        let changed = git::changed_files(root).await.ok()?;
+        let mentioned: Vec<String> = changed
+            .iter()
+            .filter(|file| {
+                let name = std::path::Path::new(file.as_str())
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                task.contains(file.as_str()) || (!name.is_empty() && task.contains(&name))
+            })
+            .cloned()
+            .collect();
+
+        let mut patch = git::diff(root, &mentioned, true).await.ok()?;
+        if patch.trim().is_empty() {
+            return None;
+        }
+
+        if patch.len() > MAX_REVIEW_DIFF_BYTES {
+            let mut end = MAX_REVIEW_DIFF_BYTES;
+            while !patch.is_char_boundary(end) {
+                end -= 1;
+            }
+            patch.truncate(end);
+            patch.push_str("\n... (diff truncated; read the files for the rest)");
+        }
+
+        Some(format!(
+            "Uncommitted changes under review (git diff HEAD, with enclosing functions):\n```diff\n{}\n```",
+            patch.trim_end()
+        ))
+    }
+
+    /// Tokens used per turn and for the whole session (the `/usage` command).
+    /// Subagent and side requests count towards the turn that made them.
+    pub fn usage(&self) -> &UsageReport {
+        &self.usage
+    }
+
+    /// Breakdown of what the next request would send (the `/context` command).
+    /// Message numbers are history indices, as accepted by `/forget`.
+    pub fn context_report(&self) -> ContextReport {
+        let messages = self.request_messages(&[]);
+        let offset = messages.len() - self.messages.len();
+        let mut report = context::analyze(&messages, CONTEXT_KEEP_TURNS);
+        for result in &mut report.largest {
+            result.index -= offset;
+        }
+        report
+    }
+
+    /// Remove messages from the history (the `/forget` command), keeping
+    /// tool calls and their results paired. Returns how many were removed.
+    pub fn forget(&mut self, selector: &str) -> Result<usize, Error> {
+        let selector = Selector::parse(selector).ok_or_else(|| {
+            Error::Message("Expected a message number, a range like 3-7, or a tool name".to_string())
+        })?;
+        Ok(forget::forget(&mut self.messages, &selector))
+    }
+
+    /// Render the conversation as Markdown and upload it as a secret gist
+    /// (the `/share` command). Returns the gist URL.
+    pub async fn share(&self) -> Result<String, Error> {
+        if self.messages.is_empty() {
+            return Err(Error::Message("Nothing to share yet".to_string()));
+        }
+        let token = self
+            .config
+            .github_token
+            .clone()
+            .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+            .ok_or_else(|| {
+                Error::Message("Set github_token in .ariste/settings.json or GITHUB_TOKEN to share".to_string())
+            })?;
+
+        // 记录 seed，便于复现
+        let title = match self.config.seed {
+            Some(seed) => format!("Ariste session (seed {})", seed),
+            None => "Ariste session".to_string(),
+        };
+        let markdown = transcript::render_markdown(&title, &self.messages, &self.timings);
+        gist::create_secret_gist(&token, "ariste-session.md", "Ariste session", &markdown).await
+    }
+
+    pub fn clear_history(&mut self) {
+        self.messages.clear();
+        self.timings.clear();
+        self.recalled = None;
+    }
+
+    /// When each tool call and subagent task of the session ran
+    pub fn timings(&self) -> &[TimingRecord] {
+        &self.timings
+    }
+
+    /// Spawn a subagent to handle a specialized task
+    #[allow(dead_code)]
+    pub async fn spawn_task(
+        &mut self,
+        subagent_type: SubAgentType,
+        description: &str,
+        prompt: &str,
+    ) -> Result<String, Error> {
+        self.spawn_task_with_options(subagent_type, description, prompt, None, false).await
+    }
+
+    /// Spawn a subagent with additional options
+    #[allow(dead_code)]
+    pub async fn spawn_task_with_options(
+        &mut self,
+        subagent_type: SubAgentType,
+        description: &str,
+        prompt: &str,
+        context_messages: Option<&[Message]>,
+        include_tools: bool,
+    ) -> Result<String, Error> {
+        self.spawn_named_task(subagent_type.name(), description, prompt, context_messages, include_tools)
+            .await
+    }
+
+    /// Spawn a subagent of a registered type by name, built-in or added
+    /// with `register_subagent_type`
+    pub async fn spawn_named_task(
+        &mut self,
+        subagent_type: &str,
+        description: &str,
+        prompt: &str,
+        context_messages: Option<&[Message]>,
+        include_tools: bool,
+    ) -> Result<String, Error> {
+        let start_time = Instant::now();
+
+        let profile = self.subagent_profile(subagent_type)?;
+        let formatted = self
+            .run_subagent(profile, description, prompt, context_messages, include_tools, false)
+            .await?;
+
+        let elapsed = start_time.elapsed();
+        self.frontend.notify(Notice::Success, &format!("✓ Subagent completed in {:.2}s", elapsed.as_secs_f64()));
+
+        Ok(formatted)
+    }
+
+    /// Build, run, and report a subagent task. Results of subagents that
+    /// can't modify the workspace are cached per repository state; `force`
+    /// reruns the subagent anyway.
+    async fn run_subagent(
+        &mut self,
+        profile: SubAgentProfile,
+        description: &str,
+        prompt: &str,
+        context_messages: Option<&[Message]>,
+        include_tools: bool,
+        force: bool,
+    ) -> Result<String, Error> {
+        let output = match self
+            .prepare_subagent(profile, description, prompt, context_messages, include_tools, force)
+            .await?
+        {
+            PreparedSubagent::Cached(output) => output,
+            PreparedSubagent::Ready(job) => {
+                let finished = job.run().await;
+                self.finish_subagent(finished)?
+            }
+        };
+        Ok(format_subagent_output(&output))
+    }
+
+    /// Run several subagent tasks at once, at most `max_parallel` at a
+    /// time (the `parallel_tasks` tool). Results are in task order; a
+    /// failed task has an `error` instead of a `result`.
+    pub async fn run_parallel_subagents(&mut self, tasks: &[ParallelTask]) -> Result<Vec<Value>, Error> {
+        use futures_util::stream::{self, StreamExt};
+
+        let limit = self
+            .config
+            .subagents
+            .as_ref()
+            .and_then(|config| config.max_parallel)
+            .unwrap_or(DEFAULT_MAX_PARALLEL_SUBAGENTS)
+            .max(1);
+        let start_time = Instant::now();
+        self.frontend.notify(Notice::Info, &format!(
+            "🚀 Spawning {} subagent tasks, {} at a time...",
+            tasks.len(),
+            limit.min(tasks.len())
+        ));
+
+        // 先依次准备（查缓存、取 diff），再并发运行，最后按顺序收尾
+        let mut outputs: Vec<Option<Value>> = Vec::with_capacity(tasks.len());
+        let mut jobs = Vec::new();
+        for (index, task) in tasks.iter().enumerate() {
+            let prepared = match self.subagent_profile(&task.subagent_type) {
+                Ok(profile) => {
+                    self.prepare_subagent(profile, &task.description, &task.prompt, None, task.include_tools, task.force)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match prepared {
+                Ok(PreparedSubagent::Cached(output)) => outputs.push(Some(output)),
+                Ok(PreparedSubagent::Ready(job)) => {
+                    outputs.push(None);
+                    jobs.push((index, job));
+                }
+                Err(e) => outputs.push(Some(json!({"task": task.description, "error": e.to_string()}))),
+            }
+        }
+
+        let finished: Vec<(usize, FinishedSubagent)> = stream::iter(jobs)
+            .map(|(index, job)| async move { (index, job.run().await) })
+            .buffer_unordered(limit)
+            .collect()
+            .await;
+        for (index, finished) in finished {
+            let description = finished.job.description.clone();
+            outputs[index] = Some(match self.finish_subagent(finished) {
+                Ok(output) => output,
+                Err(e) => json!({"task": description, "error": e.to_string()}),
+            });
+        }
+
+        self.frontend.notify(Notice::Success, &format!(
+            "✓ {} subagent tasks finished in {:.2}s",
+            tasks.len(),
+            start_time.elapsed().as_secs_f64()
+        ));
+        Ok(outputs.into_iter().flatten().collect())
+    }
+
+    /// Look up a cached result for the task, or get a subagent ready to run it
+    async fn prepare_subagent(
+        &mut self,
+        profile: SubAgentProfile,
+        description: &str,
+        prompt: &str,
+        context_messages: Option<&[Message]>,
+        include_tools: bool,
+        force: bool,
+    ) -> Result<PreparedSubagent, Error> {
+        let started = Instant::now();
+        let used_tools = include_tools && profile.uses_tools;
+
+        let cache_key = if !used_tools || profile.allowed_tools.is_some() {
+            self.subagent_cache_key(&profile.name, used_tools, description, prompt)
+                .await
+        } else {
+            None
+        };
+
+        if !force
+            && let Some(key) = &cache_key
+            && let Some(cached) = self.subagent_cache.get(key)
+        {
+            self.frontend.notify(Notice::Info, "♻️ Reusing cached subagent result (pass force: true to rerun)");
+            return Ok(PreparedSubagent::Cached(cached.clone()));
+        }
+
+        let id = self.tasks.reserve_id();
+        self.frontend.notify(Notice::Info, &format!(
+            "🤖 Spawning {} subagent task#{}: {}",
+            profile.description,
+            id,
+            description
+        ));
+
+        // Build the messages
+        let mut messages = Vec::new();
+
+        // Add system prompt if applicable
+        if let Some(system_prompt) = &profile.system_prompt {
+            messages.push(Message::system(system_prompt.clone()));
+        }
+
+        // Add context if provided, chosen by token budget and relevance to the task
+        if let Some(context) = context_messages {
+            let budget = self
+                .config
+                .subagents
+                .as_ref()
+                .and_then(|config| config.context_tokens)
+                .unwrap_or(DEFAULT_SUBAGENT_CONTEXT_TOKENS);
+            let task = format!("{}\n{}", description, prompt);
+            messages.extend(context::select_for_task(context, &task, budget));
+        }
+
+        // Build the full prompt
+        let mut full_prompt = format!("Task: {}\n\nDetails:\n{}", description, prompt);
+        if profile.attach_diff
+            && let Some(diff) = self.review_diff(&full_prompt).await
+        {
+            full_prompt.push_str("\n\n");
+            full_prompt.push_str(&diff);
+        }
+
+        // Add user message
+        messages.push(Message::user(full_prompt));
+
+        // Run the subagent's complete message loop within the configured limits
+        let limits = self
+            .config
+            .subagents
+            .clone()
+            .unwrap_or_default()
+            .limits_for(&profile.name);
+
+        let show_progress = self
+            .config
+            .subagents
+            .as_ref()
+            .and_then(|config| config.show_progress)
+            .unwrap_or(true);
+        Ok(PreparedSubagent::Ready(Box::new(SubagentJob {
+            profile,
+            description: description.to_string(),
+            messages,
+            used_tools,
+            limits,
+            cache_key,
+            patches: self.patches.clone(),
+            id,
+            frontend: Arc::new(SubagentView::new(id, self.frontend.clone()).quiet(!show_progress)),
+            session: self.session.clone(),
+            started,
+            cancel: self.cancel.clone(),
+            seed: self.config.seed,
+            index: self.index.clone(),
+            background: false,
+        })))
+    }
+
+    /// Count a finished subagent's tokens, cache its result and build the
+    /// report returned to the model
+    fn finish_subagent(&mut self, finished: FinishedSubagent) -> Result<Value, Error> {
+        self.usage.absorb(&finished.usage);
+        self.timings.extend(finished.timings);
+        let job = finished.job;
+        let run = finished.result?;
+
+        let elapsed = job.started.elapsed();
+
+        // Format structured output
+        let mut output = json!({
+            "task": job.description,
+            "agent_type": job.profile.description,
+            "model": finished.model,
+            "duration_ms": elapsed.as_millis(),
+            "used_tools": job.used_tools,
+            "result": run.content,
+        });
+        if let Some(truncated) = run.truncated {
+            self.frontend.notify(Notice::Warning, &format!(
+                "Subagent stopped early ({}); returning its partial result",
+                truncated.as_str()
+            ));
+            output["truncated"] = json!(truncated.as_str());
+        }
+
+        if let Some(key) = job.cache_key
+            && run.truncated.is_none()
+        {
+            self.subagent_cache.insert(key, output.clone());
+        }
+
+        Ok(output)
+    }
+
+    /// Cache key for a subagent task, or `None` when the repository state
+    /// can't be determined (outside git), in which case nothing is cached
+    async fn subagent_cache_key(
+        &self,
+        subagent_type: &str,
+        used_tools: bool,
+        description: &str,
+        prompt: &str,
+    ) -> Option<SubAgentCacheKey> {
+        let repo_state = git::state_hash(&self.workspace.primary().path).await.ok()?;
+        Some(SubAgentCacheKey {
+            subagent_type: subagent_type.to_string(),
+            used_tools,
+            prompt: normalize_prompt(&format!("{}\n{}", description, prompt)),
+            repo_state,
+        })
+    }
+
+    /// Subagent types the `task` tool offers
+    pub fn subagent_types(&self) -> &SubAgentRegistry {
+        &self.subagents
+    }
+
+    /// Add a subagent type (or replace one of the same name) and offer it
+    /// to the model through the `task` and `parallel_tasks` tools
+    pub fn register_subagent_type(&mut self, profile: SubAgentProfile) {
+        self.subagents.register(profile);
+        self.replace_tool(TaskTool::new(&self.subagents));
+        self.replace_tool(ParallelTasksTool::new(&self.subagents));
+    }
+
+    /// Swap in a new version of a registered tool, leaving whether it is
+    /// offered unchanged
+    fn replace_tool(&mut self, tool: impl ToolImpl + 'static) {
+        let definition = tool.definition();
+        let name = definition.function.name.clone();
+        if self.tools.register(tool).is_none() {
+            self.tools.unregister(&name);
+            return;
+        }
+        for definitions in std::iter::once(&mut self.tool_definitions).chain(self.offered_tools.as_mut()) {
+            for existing in definitions.iter_mut().filter(|def| def.function.name == name) {
+                *existing = definition.clone();
+            }
+        }
+    }
+
+    fn subagent_profile(&self, name: &str) -> Result<SubAgentProfile, Error> {
+        self.subagents.get(name).cloned().ok_or_else(|| {
+            Error::Message(format!(
+                "Invalid subagent type: {} (expected one of: {})",
+                name,
+                self.subagents.names().join(", ")
+            ))
+        })
+    }
+
+    /// Spawn multiple subagent tasks concurrently
+    #[allow(dead_code)]
+    pub async fn spawn_multiple_tasks(&mut self, tasks: Vec<SubAgentTask>) -> Result<Vec<String>, Error> {
+        use futures_util::future::join_all;
+
+        let total = tasks.len();
+        self.frontend.notify(Notice::Info, &format!("🚀 Spawning {} subagent tasks concurrently...", total));
+
+        let start_time = Instant::now();
+
+        // Build futures for all tasks
+        let mut futures = Vec::new();
+
+        for task in tasks {
+            let future = async move {
+                let mut agent = Agent::load_from_config().await?;
+                agent
+                    .spawn_named_task(
+                        &task.subagent_type,
+                        &task.description,
+                        &task.prompt,
+                        None,
+                        false,
+                    )
+                    .await
+            };
+            futures.push(future);
+        }
+
+        // Execute all tasks concurrently
+        let results = join_all(futures).await;
+
+        let elapsed = start_time.elapsed();
+        self.frontend.notify(Notice::Success, &format!(
+            "✓ All {} subagent tasks completed in {:.2}s",
+            total,
+            elapsed.as_secs_f64()
+        ));
+
+        // Collect results
+        let mut outputs = Vec::new();
+        for result in results {
+            outputs.push(result?);
+        }
+
+        Ok(outputs)
+    }
+
+    pub async fn quit(&mut self) -> Result<(), Error> {
+        if let Some(watcher) = self.index_watcher.take() {
+            watcher.abort();
+        }
+        self.shells.kill_all();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatFuture, ChatResponse, Ollama};
+
+    #[test]
+    fn test_subagent_type_descriptions() {
+        assert!(SubAgentType::GeneralPurpose.description().contains("General-purpose"));
+        assert!(SubAgentType::Explore.description().contains("explor"));
+        assert!(SubAgentType::Plan.description().contains("architect"));
+        assert!(SubAgentType::CodeReview.description().contains("review"));
+        assert!(SubAgentType::TestRunner.description().contains("test"));
+    }
+
+    #[test]
+    fn test_subagent_type_system_prompts() {
+        // GeneralPurpose has no system prompt
+        assert!(SubAgentType::GeneralPurpose.system_prompt().is_none());
+
+        // Explore has a system prompt
+        let explore_prompt = SubAgentType::Explore.system_prompt();
+        assert!(explore_prompt.is_some());
+        assert!(explore_prompt.unwrap().contains("exploration agent"));
+
+        // Plan has a system prompt
+        let plan_prompt = SubAgentType::Plan.system_prompt();
+        assert!(plan_prompt.is_some());
+        assert!(plan_prompt.unwrap().contains("architect agent"));
+
+        // CodeReview has a system prompt
+        let review_prompt = SubAgentType::CodeReview.system_prompt();
+        assert!(review_prompt.is_some());
+        assert!(review_prompt.unwrap().contains("code reviewer"));
+
+        // TestRunner has a system prompt
+        let test_prompt = SubAgentType::TestRunner.system_prompt();
+        assert!(test_prompt.is_some());
+        assert!(test_prompt.unwrap().contains("test runner"));
+    }
+
+    #[test]
+    fn test_subagent_type_uses_tools() {
+        // These agents should use tools
+        assert!(SubAgentType::Explore.uses_tools());
+        assert!(SubAgentType::CodeReview.uses_tools());
+        assert!(SubAgentType::TestRunner.uses_tools());
+        assert!(SubAgentType::GeneralPurpose.uses_tools());
+
+        // Plan agent should not use tools by default
+        assert!(!SubAgentType::Plan.uses_tools());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_task_general_purpose() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+
+        let result = agent
+            .spawn_task(
+                SubAgentType::GeneralPurpose,
+                "简单计算",
+                "1 + 1 等于多少？",
+            )
+            .await;
+
+        // This test requires Ollama to be running
+        // Skip in CI or when Ollama is not available
+        if let Ok(output) = result {
+            assert!(output.contains("Subagent Task Complete"));
+            assert!(output.contains("简单计算"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_task_explore() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+
+        let result = agent
+            .spawn_task(
+                SubAgentType::Explore,
+                "测试探索",
+                "当前目录有哪些文件？",
+            )
+            .await;
+
+        // Requires Ollama to be running
+        if let Ok(output) = result {
+            assert!(output.contains("Subagent Task Complete"));
+            assert!(output.contains("agent_type"));
+            assert!(output.contains("duration_ms"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_task_plan() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+
+        let result = agent
+            .spawn_task(
+                SubAgentType::Plan,
+                "测试规划",
+                "如何实现一个简单的计数器？",
+            )
+            .await;
+
+        // Requires Ollama to be running
+        if let Ok(output) = result {
+            assert!(output.contains("Subagent Task Complete"));
+            assert!(output.contains("agent_type"));
+            // Plan agent should provide structured response
+            assert!(output.len() > 50);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_task_with_options() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+
+        // Create some context messages
+        let context = vec![
+            Message::user("Previous context")
+        ];
+
+        let result = agent
+            .spawn_task_with_options(
+                SubAgentType::Explore,
+                "测试带选项的任务",
+                "列出当前目录的文件",
+                Some(&context),
+                true, // Enable tools
+            )
+            .await;
+
+        // Requires Ollama to be running
+        if let Ok(output) = result {
+            assert!(output.contains("Subagent Task Complete"));
+            assert!(output.contains("used_tools"));
+            assert!(output.contains("duration_ms"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_task_code_review() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+
+        let result = agent
+            .spawn_task(
+                SubAgentType::CodeReview,
+                "代码审查测试",
+                "Review this function: fn add(a: i32, b: i32) -> i32 { a + b }",
+            )
+            .await;
+
+        // Requires Ollama to be running
+        if let Ok(output) = result {
+            assert!(output.contains("Subagent Task Complete"));
+            assert!(output.contains("Code reviewer"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_task_test_runner() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+
+        let result = agent
+            .spawn_task(
+                SubAgentType::TestRunner,
+                "测试执行测试",
+                "Design tests for a simple calculator",
+            )
+            .await;
+
+        // Requires Ollama to be running
+        if let Ok(output) = result {
+            assert!(output.contains("Subagent Task Complete"));
+            assert!(output.contains("Test runner"));
+        }
+    }
+
+    #[test]
+    fn test_read_only_subagent_tools() {
+        for subagent_type in [SubAgentType::Explore, SubAgentType::CodeReview] {
+            let allowed = subagent_type.allowed_tools().unwrap();
+            for tool in ["write", "edit", "bash", "task"] {
+                assert!(!allowed.contains(&tool));
+            }
+            assert!(allowed.contains(&"read"));
+        }
+        assert!(SubAgentType::GeneralPurpose.allowed_tools().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_restrict_tools() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+
+        agent.restrict_tools(READ_ONLY_TOOLS);
+        assert_eq!(agent.tools.len(), READ_ONLY_TOOLS.len());
+        assert_eq!(agent.offered_tools.as_ref().unwrap().len(), READ_ONLY_TOOLS.len());
+
+        // Removed tools can't be executed even if the model asks for them
+        let result = agent
+            .execute_tool("write", &json!({"file_path": "/tmp/never.txt", "content": "x"}))
+            .await;
+        assert!(result.is_err());
+        assert!(!std::path::Path::new("/tmp/never.txt").exists());
+
+        agent.restrict_tools(&[]);
+        assert!(agent.tools.is_empty());
+        assert!(agent.offered_tools.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_turn_leaves_history_untouched() {
+        let mut agent = Agent::load_from_config()
+            .await
+            .expect("Failed to load agent");
+        agent.messages.push(Message::user("earlier"));
+
+        // Nothing listens on the discard port, so the LLM call fails
+        agent.llm = Box::new(Ollama::new().url("http://127.0.0.1:9/api/chat".to_string()));
+        assert!(agent.invoke("hello").await.is_err());
+
+        assert_eq!(agent.messages.len(), 1);
+        assert_eq!(agent.messages[0].content(), "earlier");
+    }
+
+    /// Replies with scripted responses and records the tools offered
+    #[derive(Debug, Default)]
+    struct MockProvider {
+        replies: std::sync::Arc<std::sync::Mutex<Vec<ChatResponse>>>,
+        offered: std::sync::Arc<std::sync::Mutex<Vec<usize>>>,
+    }
+
+    impl LlmProvider for MockProvider {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn stream_content(&self) -> bool {
+            true
+        }
+
+        fn cost_guard(&self) -> Option<&crate::llm::CostGuard> {
+            None
+        }
+
+        fn chat<'a>(
+            &'a self,
+            model: &'a str,
+            messages: &'a [Message],
+            tools: Option<&'a [ToolDefinition]>,
+        ) -> ChatFuture<'a> {
+            self.chat_stream(model, messages, tools)
+        }
+
+        fn chat_stream<'a>(
+            &'a self,
+            _model: &'a str,
+            _messages: &'a [Message],
+            tools: Option<&'a [ToolDefinition]>,
+        ) -> ChatFuture<'a> {
+            self.offered.lock().unwrap().push(tools.map_or(0, |tools| tools.len()));
+            let reply = self.replies.lock().unwrap().remove(0);
+            Box::pin(async move { Ok(reply) })
+        }
+
+        fn quiet(&self) -> Box<dyn LlmProvider> {
+            // Side requests draw from the same scripted replies
+            Box::new(MockProvider {
+                replies: self.replies.clone(),
+                offered: self.offered.clone(),
+            })
+        }
+
+        fn set_frontend(&mut self, _frontend: Option<Arc<dyn Frontend>>) {}
+
+        fn set_cancel(&mut self, _cancel: CancelToken) {}
+    }
+
+    /// Streams part of a reply and is interrupted, like Ctrl-C mid-generation
+    #[derive(Debug, Default)]
+    struct InterruptedProvider {
+        cancel: CancelToken,
+    }
+
+    impl LlmProvider for InterruptedProvider {
+        fn name(&self) -> &'static str {
+            "interrupted"
+        }
+
+        fn stream_content(&self) -> bool {
+            true
+        }
+
+        fn cost_guard(&self) -> Option<&crate::llm::CostGuard> {
+            None
+        }
+
+        fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+            self.chat_stream(model, messages, tools)
+        }
+
+        fn chat_stream<'a>(&'a self, _model: &'a str, _messages: &'a [Message], _tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+            self.cancel.cancel();
+            Box::pin(async move {
+                Ok(ChatResponse {
+                    content: "Let me read".to_string(),
+                    tool_calls: Some(vec![json!({"id": "call_1", "function": {"name": "read", "arguments": {}}})]),
+                    usage: None,
+                })
+            })
+        }
+
+        fn quiet(&self) -> Box<dyn LlmProvider> {
+            Box::new(InterruptedProvider::default())
+        }
+
+        fn set_frontend(&mut self, _frontend: Option<Arc<dyn Frontend>>) {}
+
+        fn set_cancel(&mut self, cancel: CancelToken) {
+            self.cancel = cancel;
+        }
+    }
+
+    /// Records what the agent loop shows, in order
+    #[derive(Debug, Default)]
+    struct RecordingFrontend {
+        events: std::sync::Mutex<Vec<String>>,
+        /// What `redirect` answers
+        correction: Option<String>,
+    }
+
+    impl RecordingFrontend {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    impl Frontend for RecordingFrontend {
+        fn prompt(&self) -> Option<String> {
+            None
+        }
+
+        fn stream(&self, fragment: crate::ui::StreamFragment<'_>) {
+            self.record(format!("stream {:?}", fragment));
+        }
+
+        fn response(&self, content: &str) {
+            self.record(format!("response {}", content));
+        }
+
+        fn citations(&self, citations: &[String]) {
+            self.record(format!("citations {}", citations.len()));
+        }
+
+        fn tool_start(&self, name: &str, _args: Option<&str>) {
+            self.record(format!("tool_start {}", name));
+        }
+
+        fn tool_heartbeat(&self, _elapsed: &str, _detail: Option<&str>) {}
+
+        fn tool_result(&self, name: &str, _result: &str) {
+            self.record(format!("tool_result {}", name));
+        }
+
+        fn tool_error(&self, error: &str) {
+            self.record(format!("tool_error {}", error));
+        }
+
+        fn confirm(&self, _question: &str) -> bool {
+            false
+        }
+
+        fn notify(&self, notice: Notice, message: &str) {
+            self.record(format!("{:?} {}", notice, message));
+        }
+
+        fn redirect(&self) -> Option<String> {
+            self.record("redirect".to_string());
+            self.correction.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_calls_run_concurrently() {
+        /// A `glob` that takes `delay_ms` and echoes its pattern
+        struct SlowGlob;
+
+        impl ToolImpl for SlowGlob {
+            fn definition(&self) -> ToolDefinition {
+                serde_json::from_value(json!({
+                    "type": "function",
+                    "function": {"name": "glob", "description": "Slow glob", "parameters": {"type": "object", "properties": {}, "required": []}}
+                }))
+                .unwrap()
+            }
+
+            fn execute<'a>(&'a self, arguments: &'a Value) -> crate::tools::ToolFuture<'a> {
+                Box::pin(async move {
+                    let delay = arguments["delay_ms"].as_u64().unwrap_or(0);
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    Ok(arguments["pattern"].as_str().unwrap_or_default().to_string())
+                })
+            }
+        }
+
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.register_tool(SlowGlob);
+        agent.set_frontend(Arc::new(RecordingFrontend::default()));
+        let call = |id: &str, pattern: &str, delay_ms: u64| {
+            json!({"id": id, "function": {"name": "glob", "arguments": {"pattern": pattern, "delay_ms": delay_ms}}})
+        };
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: Some(vec![call("call_1", "a", 400), call("call_2", "b", 300), call("call_3", "c", 200)]),
+                    usage: None,
+                },
+                ChatResponse {
+                    content: "done".to_string(),
+                    tool_calls: None,
+                    usage: None,
+                },
+            ])),
+            ..MockProvider::default()
+        });
+
+        let started = Instant::now();
+        agent.invoke("find things").await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(850), "took {:?}", started.elapsed());
+
+        // Results keep the order of the calls, not of completion
+        let results: Vec<(String, &str)> = agent
+            .messages
+            .iter()
+            .filter(|m| m.is_tool())
+            .map(|m| (m.tool_call_id().unwrap().to_string(), m.content()))
+            .collect();
+        assert_eq!(
+            results,
+            vec![("call_1".to_string(), "a"), ("call_2".to_string(), "b"), ("call_3".to_string(), "c")]
+        );
 
-        let elapsed = start_time.elapsed();
-        UI::success(&format!(
-            "✓ All {} subagent tasks completed in {:.2}s",
-            total,
-            elapsed.as_secs_f64()
-        ));
+        // Each call is timed on its own, overlapping the others
+        let timings = agent.timings();
+        assert_eq!(timings.len(), 3);
+        for (timing, (id, delay)) in timings.iter().zip([("call_1", 400), ("call_2", 300), ("call_3", 200)]) {
+            assert_eq!(timing.call_id.as_deref(), Some(id));
+            assert_eq!(timing.outcome, "ok");
+            assert!(timing.duration_ms >= delay);
+        }
+        assert!(timings[2].started_ms < timings[0].finished_ms);
+    }
 
-        // Collect results
-        let mut outputs = Vec::new();
-        for result in results {
-            outputs.push(result?);
+    #[tokio::test]
+    async fn test_concurrent_calls_show_tool_errors_as_reported() {
+        /// A `grep` that fails on the pattern "fail"
+        struct FailingGrep;
+
+        impl ToolImpl for FailingGrep {
+            fn definition(&self) -> ToolDefinition {
+                serde_json::from_value(json!({
+                    "type": "function",
+                    "function": {"name": "grep", "description": "Failing grep", "parameters": {"type": "object", "properties": {}, "required": []}}
+                }))
+                .unwrap()
+            }
+
+            fn execute<'a>(&'a self, arguments: &'a Value) -> crate::tools::ToolFuture<'a> {
+                Box::pin(async move {
+                    match arguments["pattern"].as_str() {
+                        Some("fail") => Err("boom".to_string()),
+                        pattern => Ok(pattern.unwrap_or_default().to_string()),
+                    }
+                })
+            }
         }
 
-        Ok(outputs)
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.register_tool(FailingGrep);
+        let frontend = Arc::new(RecordingFrontend::default());
+        agent.set_frontend(frontend.clone());
+        let call = |id: &str, pattern: &str| json!({"id": id, "function": {"name": "grep", "arguments": {"pattern": pattern}}});
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![ChatResponse {
+                content: String::new(),
+                tool_calls: Some(vec![call("call_1", "a"), call("call_2", "fail")]),
+                usage: None,
+            }])),
+            ..MockProvider::default()
+        });
+
+        let error = agent.invoke("search").await.unwrap_err();
+        assert_eq!(error.to_string(), "Tool execution error: boom");
+        let events = frontend.events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            ["tool_start grep", "tool_start grep", "tool_result grep", "tool_error boom"]
+        );
+        assert_eq!(agent.timings()[1].outcome, "error");
     }
 
-    pub async fn quit(&mut self) -> Result<(), Error> {
-        Ok(())
+    #[tokio::test]
+    async fn test_cancelled_turn_keeps_partial_reply() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        let frontend = Arc::new(RecordingFrontend::default());
+        agent.set_frontend(frontend.clone());
+        agent.llm = Box::new(InterruptedProvider::default());
+
+        agent.invoke("what is the package name?").await.unwrap();
+
+        // The interrupted reply's tool call is not run
+        assert_eq!(agent.messages.len(), 2);
+        assert!(agent.messages[0].is_user());
+        assert_eq!(agent.messages[1].content(), "Let me read");
+        assert!(agent.messages[1].tool_calls().is_empty());
+        let events = frontend.events.lock().unwrap().clone();
+        assert!(events.iter().any(|event| event == "Warning Generation cancelled"));
+        assert!(!events.iter().any(|event| event.starts_with("tool_start")));
+
+        // The next turn starts uncancelled
+        assert!(agent.cancel_token().is_cancelled());
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![ChatResponse {
+                content: "ariste".to_string(),
+                tool_calls: None,
+                usage: None,
+            }])),
+            ..MockProvider::default()
+        });
+        agent.invoke("again").await.unwrap();
+        assert_eq!(agent.messages.len(), 4);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_turn_with_mock_provider() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.restrict_tools(&["read"]);
+        let mock = MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: Some(vec![json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "Cargo.toml"}}})]),
+                    usage: None,
+                },
+                ChatResponse {
+                    content: "The package is ariste".to_string(),
+                    tool_calls: None,
+                    usage: None,
+                },
+            ])),
+            ..MockProvider::default()
+        };
+        let offered = mock.offered.clone();
+        agent.llm = Box::new(mock);
+
+        agent.invoke("what is the package name?").await.unwrap();
+        let roles: Vec<&str> = agent.messages.iter().map(|m| m.role()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "tool", "assistant"]);
+        assert!(agent.messages[2].content().contains("ariste"));
+        assert_eq!(agent.messages[2].tool_call_id(), Some("call_1"));
+        assert_eq!(agent.messages[3].content(), "The package is ariste");
+        assert_eq!(agent.turns(), 1);
+        assert_eq!(agent.last_answer(), Some("The package is ariste"));
+        // Both requests offered the one remaining tool
+        assert_eq!(*offered.lock().unwrap(), vec![1, 1]);
+    }
 
-    #[test]
-    fn test_subagent_type_descriptions() {
-        assert!(SubAgentType::GeneralPurpose.description().contains("General-purpose"));
-        assert!(SubAgentType::Explore.description().contains("explor"));
-        assert!(SubAgentType::Plan.description().contains("architect"));
-        assert!(SubAgentType::CodeReview.description().contains("review"));
-        assert!(SubAgentType::TestRunner.description().contains("test"));
+    #[tokio::test]
+    async fn test_turn_reports_to_frontend() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.restrict_tools(&["read"]);
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: Some(vec![json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "Cargo.toml"}}})]),
+                    usage: None,
+                },
+                ChatResponse {
+                    content: "The package is ariste".to_string(),
+                    tool_calls: None,
+                    usage: None,
+                },
+            ])),
+            ..MockProvider::default()
+        });
+        let frontend = Arc::new(RecordingFrontend::default());
+        agent.set_frontend(frontend.clone());
+
+        agent.invoke("what is the package name?").await.unwrap();
+        assert_eq!(
+            *frontend.events.lock().unwrap(),
+            vec!["tool_start read", "tool_result read", "citations 1"]
+        );
     }
 
-    #[test]
-    fn test_subagent_type_system_prompts() {
-        // GeneralPurpose has no system prompt
-        assert!(SubAgentType::GeneralPurpose.system_prompt().is_none());
+    #[tokio::test]
+    async fn test_redirect_after_tools() {
+        /// A `glob` during which the user presses Esc
+        struct EscGlob(RedirectToken);
+
+        impl ToolImpl for EscGlob {
+            fn definition(&self) -> ToolDefinition {
+                serde_json::from_value(json!({
+                    "type": "function",
+                    "function": {"name": "glob", "description": "Glob", "parameters": {"type": "object", "properties": {}, "required": []}}
+                }))
+                .unwrap()
+            }
 
-        // Explore has a system prompt
-        let explore_prompt = SubAgentType::Explore.system_prompt();
-        assert!(explore_prompt.is_some());
-        assert!(explore_prompt.unwrap().contains("exploration agent"));
+            fn execute<'a>(&'a self, _arguments: &'a Value) -> crate::tools::ToolFuture<'a> {
+                self.0.request();
+                Box::pin(async move { Ok("src/api/v1.rs".to_string()) })
+            }
+        }
 
-        // Plan has a system prompt
-        let plan_prompt = SubAgentType::Plan.system_prompt();
-        assert!(plan_prompt.is_some());
-        assert!(plan_prompt.unwrap().contains("architect agent"));
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.restrict_tools(&[]);
+        agent.register_tool(EscGlob(agent.redirect_token()));
+        let frontend = Arc::new(RecordingFrontend {
+            correction: Some("no, use the v2 API".to_string()),
+            ..RecordingFrontend::default()
+        });
+        agent.set_frontend(frontend.clone());
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: Some(vec![json!({"id": "call_1", "function": {"name": "glob", "arguments": {}}})]),
+                    usage: None,
+                },
+                ChatResponse {
+                    content: "Switched to v2".to_string(),
+                    tool_calls: None,
+                    usage: None,
+                },
+            ])),
+            ..MockProvider::default()
+        });
 
-        // CodeReview has a system prompt
-        let review_prompt = SubAgentType::CodeReview.system_prompt();
-        assert!(review_prompt.is_some());
-        assert!(review_prompt.unwrap().contains("code reviewer"));
+        agent.invoke("update the client").await.unwrap();
+        let roles: Vec<&str> = agent.messages.iter().map(|m| m.role()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "tool", "user", "assistant"]);
+        assert_eq!(agent.messages[3].content(), "no, use the v2 API");
+        let events = frontend.events.lock().unwrap();
+        assert!(events.contains(&"Info Going on with your correction".to_string()));
+        // The request was used up: nothing pauses the next turn
+        assert!(!agent.redirect_token().is_requested());
+    }
 
-        // TestRunner has a system prompt
-        let test_prompt = SubAgentType::TestRunner.system_prompt();
-        assert!(test_prompt.is_some());
-        assert!(test_prompt.unwrap().contains("test runner"));
+    #[tokio::test]
+    async fn test_usage_counts_turn_requests() {
+        let usage = |prompt_tokens, output_tokens| {
+            Some(crate::llm::TokenUsage { prompt_tokens, output_tokens, estimated: false })
+        };
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.restrict_tools(&["read"]);
+        agent.set_frontend(Arc::new(RecordingFrontend::default()));
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: Some(vec![json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "Cargo.toml"}}})]),
+                    usage: usage(120, 15),
+                },
+                ChatResponse {
+                    content: "The package is ariste".to_string(),
+                    tool_calls: None,
+                    usage: usage(400, 8),
+                },
+            ])),
+            ..MockProvider::default()
+        });
+
+        agent.invoke("what is the package name?").await.unwrap();
+        let turn = agent.usage().last_turn().unwrap();
+        assert_eq!(turn.label, "what is the package name?");
+        assert_eq!(turn.requests, 2);
+        assert_eq!((turn.tokens.prompt_tokens, turn.tokens.output_tokens), (520, 23));
+        assert!(!turn.tokens.estimated);
     }
 
-    #[test]
-    fn test_subagent_type_uses_tools() {
-        // These agents should use tools
-        assert!(SubAgentType::Explore.uses_tools());
-        assert!(SubAgentType::CodeReview.uses_tools());
-        assert!(SubAgentType::TestRunner.uses_tools());
-        assert!(SubAgentType::GeneralPurpose.uses_tools());
+    #[tokio::test]
+    async fn test_invoke_with_events() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.restrict_tools(&["read"]);
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: Some(vec![json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "Cargo.toml"}}})]),
+                    usage: None,
+                },
+                ChatResponse {
+                    content: "The package is ariste".to_string(),
+                    tool_calls: None,
+                    usage: None,
+                },
+            ])),
+            ..MockProvider::default()
+        });
+        let frontend = Arc::new(RecordingFrontend::default());
+        agent.set_frontend(frontend.clone());
 
-        // Plan agent should not use tools by default
-        assert!(!SubAgentType::Plan.uses_tools());
+        let mut events = Vec::new();
+        agent
+            .invoke_with_events("what is the package name?", |event| events.push(event))
+            .await
+            .unwrap();
+        assert!(matches!(&events[0], AgentEvent::ToolStart { name, .. } if name == "read"));
+        assert!(matches!(&events[1], AgentEvent::ToolResult { name, result } if name == "read" && result.contains("ariste")));
+        assert_eq!(events.last(), Some(&AgentEvent::Done("The package is ariste".to_string())));
+        // Nothing reached the previous frontend, which is back in place afterwards
+        assert!(frontend.events.lock().unwrap().is_empty());
+        agent.frontend.notify(Notice::Info, "later");
+        assert_eq!(*frontend.events.lock().unwrap(), vec!["Info later"]);
     }
 
     #[tokio::test]
-    async fn test_spawn_task_general_purpose() {
-        let mut agent = Agent::load_from_config()
+    async fn test_side_effecting_tools_need_permission() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        let frontend = Arc::new(RecordingFrontend::default());
+        agent.set_frontend(frontend.clone());
+
+        // The recording frontend declines every request
+        let declined = agent
+            .execute_tool("bash", &json!({"command": "touch /tmp/ariste-permission-test"}))
             .await
-            .expect("Failed to load agent");
+            .unwrap();
+        assert!(declined.contains("the user declined this bash call"));
+        assert!(!std::path::Path::new("/tmp/ariste-permission-test").exists());
+
+        agent.permissions = PermissionPolicy::from_config(Some(&crate::config::PermissionsConfig {
+            allow: vec!["bash(echo *)".to_string()],
+            deny: vec!["bash(rm -rf*)".to_string()],
+            default: None,
+        }));
+        let allowed = agent.execute_tool("bash", &json!({"command": "echo permitted"})).await.unwrap();
+        assert!(allowed.contains("permitted"));
+        let blocked = agent.execute_tool("bash", &json!({"command": "rm -rf /tmp/none"})).await.unwrap();
+        assert!(blocked.contains("blocked by the rule `bash(rm -rf*)`"));
+        assert!(frontend.events.lock().unwrap().contains(&"Warning Blocked bash by permission rule `bash(rm -rf*)`".to_string()));
+
+        // Background subagents never ask: what no rule allows is denied
+        agent.unattended = true;
+        let unattended = agent.execute_tool("bash", &json!({"command": "touch /tmp/ariste-permission-test"})).await.unwrap();
+        assert!(unattended.contains("background tasks can't ask for approval"));
+        let allowed = agent.execute_tool("bash", &json!({"command": "echo permitted"})).await.unwrap();
+        assert!(allowed.contains("permitted"));
+    }
 
-        let result = agent
-            .spawn_task(
-                SubAgentType::GeneralPurpose,
-                "简单计算",
-                "1 + 1 等于多少？",
-            )
-            .await;
+    #[tokio::test]
+    async fn test_run_tool() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        let frontend = Arc::new(RecordingFrontend::default());
+        agent.set_frontend(frontend.clone());
+
+        let output = agent.run_tool("read", json!({"file_path": "Cargo.toml"})).await.unwrap();
+        assert!(output.contains("ariste"));
+        assert!(agent.messages.is_empty());
+        assert_eq!(*frontend.events.lock().unwrap(), vec!["tool_start read", "tool_result read"]);
+
+        // Same checks as model calls: permissions, confinement, unknown tools
+        let declined = agent.run_tool("bash", json!({"command": "echo hi"})).await.unwrap();
+        assert!(declined.starts_with("Permission denied"));
+        let outside = agent.run_tool("read", json!({"file_path": "../outside.txt"})).await;
+        assert!(outside.unwrap_err().to_string().contains("outside the working directory"));
+        assert!(agent.run_tool("nonexistent", json!({})).await.is_err());
+        assert!(agent.run_tool("read", json!("Cargo.toml")).await.is_err());
+    }
 
-        // This test requires Ollama to be running
-        // Skip in CI or when Ollama is not available
-        if result.is_ok() {
-            let output = result.unwrap();
-            assert!(output.contains("Subagent Task Complete"));
-            assert!(output.contains("简单计算"));
+    #[tokio::test]
+    async fn test_register_custom_tool() {
+        struct Echo;
+
+        impl ToolImpl for Echo {
+            fn definition(&self) -> ToolDefinition {
+                serde_json::from_value(json!({
+                    "type": "function",
+                    "function": {
+                        "name": "echo",
+                        "description": "Repeat the text",
+                        "parameters": {"type": "object", "properties": {"text": {"type": "string"}}, "required": ["text"]}
+                    }
+                }))
+                .unwrap()
+            }
+
+            fn execute<'a>(&'a self, arguments: &'a Value) -> crate::tools::ToolFuture<'a> {
+                Box::pin(async move { Ok(arguments["text"].as_str().unwrap_or_default().to_string()) })
+            }
         }
+
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.set_frontend(Arc::new(RecordingFrontend::default()));
+        agent.register_tool(Echo);
+        assert!(agent.offered_tools.as_ref().unwrap().iter().any(|def| def.function.name == "echo"));
+        assert_eq!(agent.run_tool("echo", json!({"text": "hi"})).await.unwrap(), "hi");
+
+        assert!(agent.unregister_tool("echo"));
+        assert!(!agent.unregister_tool("echo"));
+        assert!(!agent.tool_definitions.iter().any(|def| def.function.name == "echo"));
+        assert!(agent.run_tool("echo", json!({"text": "hi"})).await.is_err());
     }
 
     #[tokio::test]
-    async fn test_spawn_task_explore() {
-        let mut agent = Agent::load_from_config()
-            .await
-            .expect("Failed to load agent");
+    async fn test_subagent_limits() {
+        let read = |id: &str| json!({"id": id, "function": {"name": "read", "arguments": {"file_path": "Cargo.toml"}}});
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.restrict_tools(&["read"]);
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: "Reading the manifest".to_string(),
+                    tool_calls: Some(vec![read("call_1"), read("call_2")]),
+                    usage: None,
+                },
+                ChatResponse {
+                    content: String::new(),
+                    tool_calls: Some(vec![read("call_3")]),
+                    usage: None,
+                },
+            ])),
+            ..MockProvider::default()
+        });
 
-        let result = agent
-            .spawn_task(
-                SubAgentType::Explore,
-                "测试探索",
-                "当前目录有哪些文件？",
-            )
-            .await;
+        let limits = SubagentLimits {
+            max_turns: Some(2),
+            max_iterations: Some(1),
+            timeout_secs: Some(0),
+        };
+        let run = agent.run_subagent_loop(vec![Message::user("what is this?")], &limits).await.unwrap();
+        assert_eq!(run, SubAgentRun { content: "Reading the manifest".to_string(), truncated: Some(Truncation::MaxTurns) });
+        // The second call of the first reply was refused, the next reply's call still ran
+        assert!(agent.messages[3].content().contains("Only 1 tool calls"));
+        assert!(agent.messages[5].content().contains("[package]"));
+
+        // Per-type settings fall back to the shared ones
+        let config: crate::config::SubagentConfig =
+            serde_json::from_value(json!({"max_turns": 4, "timeout_secs": 60, "types": {"explore": {"max_turns": 20}}})).unwrap();
+        assert_eq!(
+            config.limits_for("explore"),
+            SubagentLimits { max_turns: Some(20), max_iterations: None, timeout_secs: Some(60) }
+        );
+        assert_eq!(config.limits_for("plan").max_turns, Some(4));
+
+        // A settings profile's own limits come between the two
+        let config: crate::config::SubagentConfig = serde_json::from_value(json!({
+            "max_turns": 4,
+            "timeout_secs": 60,
+            "types": {"docs-writer": {"timeout_secs": 600}},
+            "profiles": [{"name": "docs-writer", "max_turns": 30, "timeout_secs": 120}]
+        }))
+        .unwrap();
+        assert_eq!(
+            config.limits_for("docs-writer"),
+            SubagentLimits { max_turns: Some(30), max_iterations: None, timeout_secs: Some(600) }
+        );
+    }
 
-        // Requires Ollama to be running
-        if result.is_ok() {
-            let output = result.unwrap();
-            assert!(output.contains("Subagent Task Complete"));
-            assert!(output.contains("agent_type"));
-            assert!(output.contains("duration_ms"));
+    #[tokio::test]
+    async fn test_subagent_timeout() {
+        struct Slow;
+
+        impl ToolImpl for Slow {
+            fn definition(&self) -> ToolDefinition {
+                serde_json::from_value(json!({
+                    "type": "function",
+                    "function": {"name": "slow", "description": "Take a while", "parameters": {"type": "object", "properties": {}, "required": []}}
+                }))
+                .unwrap()
+            }
+
+            fn execute<'a>(&'a self, _arguments: &'a Value) -> crate::tools::ToolFuture<'a> {
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    Ok(String::new())
+                })
+            }
         }
+
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.register_tool(Slow);
+        agent.set_frontend(Arc::new(RecordingFrontend::default()));
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![ChatResponse {
+                content: "Starting the slow part".to_string(),
+                tool_calls: Some(vec![json!({"id": "call_1", "function": {"name": "slow", "arguments": {}}})]),
+                usage: None,
+            }])),
+            ..MockProvider::default()
+        });
+
+        let limits = SubagentLimits { timeout_secs: Some(1), ..SubagentLimits::default() };
+        let run = agent.run_subagent_loop(vec![Message::user("go")], &limits).await.unwrap();
+        assert_eq!(run.truncated, Some(Truncation::Timeout));
+        assert_eq!(run.content, "Starting the slow part");
     }
 
     #[tokio::test]
-    async fn test_spawn_task_plan() {
-        let mut agent = Agent::load_from_config()
+    async fn test_subagent_cancelled() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.set_frontend(Arc::new(RecordingFrontend::default()));
+        agent.llm = Box::new(InterruptedProvider::default());
+
+        let run = agent
+            .run_subagent_loop(vec![Message::user("go")], &SubagentLimits::default())
             .await
-            .expect("Failed to load agent");
+            .unwrap();
+        assert_eq!(run.truncated, Some(Truncation::Cancelled));
+        assert_eq!(run.content, "Let me read");
+        // The interrupted reply's tool call was not run
+        assert!(!agent.messages.iter().any(Message::is_tool));
+    }
 
-        let result = agent
-            .spawn_task(
-                SubAgentType::Plan,
-                "测试规划",
-                "如何实现一个简单的计数器？",
-            )
-            .await;
+    #[tokio::test]
+    async fn test_checkpoint_during_turn() {
+        let dir = std::env::temp_dir().join(format!("ariste-autosave-{}", std::process::id()));
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = Some(dir.clone());
+        let checkpoint = dir.join(format!("{}.checkpoint.json", agent.session_id()));
+
+        let staged = vec![Message::user("long running task")];
+        // Not due yet
+        agent.autosave(&staged).await;
+        assert!(!checkpoint.exists());
+
+        agent.last_autosave = Instant::now() - Duration::from_secs(60);
+        agent.autosave(&staged).await;
+        let saved: Checkpoint = serde_json::from_slice(&std::fs::read(&checkpoint).unwrap()).unwrap();
+        assert_eq!(saved.pid, std::process::id());
+        assert_eq!(saved.session.messages[0].content(), "long running task");
+
+        // A finished turn saves the session and removes the checkpoint
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![ChatResponse {
+                content: "done".to_string(),
+                tool_calls: None,
+                usage: None,
+            }])),
+            ..MockProvider::default()
+        });
+        agent.invoke("long running task").await.unwrap();
+        assert!(!checkpoint.exists());
+        assert!(dir.join(format!("{}.json", agent.session_id())).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 
-        // Requires Ollama to be running
-        if result.is_ok() {
-            let output = result.unwrap();
-            assert!(output.contains("Subagent Task Complete"));
-            assert!(output.contains("agent_type"));
-            // Plan agent should provide structured response
-            assert!(output.len() > 50);
+    #[tokio::test]
+    async fn test_compaction_before_turn() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.config.compaction = Some(crate::config::CompactionConfig {
+            max_tokens: Some(10),
+            keep_turns: Some(1),
+            ..Default::default()
+        });
+        for (prompt, answer) in [("first question", "first answer"), ("second question", "second answer")] {
+            agent.messages.push(Message::user(prompt));
+            agent.messages.push(Message::assistant(answer.repeat(10)));
         }
+        agent.llm = Box::new(MockProvider {
+            replies: std::sync::Arc::new(std::sync::Mutex::new(vec![
+                ChatResponse {
+                    content: "- The user asked a first question".to_string(),
+                    tool_calls: None,
+                    usage: None,
+                },
+                ChatResponse {
+                    content: "third answer".to_string(),
+                    tool_calls: None,
+                    usage: None,
+                },
+            ])),
+            ..MockProvider::default()
+        });
+
+        agent.invoke("third question").await.unwrap();
+        let roles: Vec<&str> = agent.messages.iter().map(|m| m.role()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user", "assistant"]);
+        assert!(agent.messages[0].content().ends_with("- The user asked a first question"));
+        assert_eq!(agent.messages[1].content(), "second question");
+        assert_eq!(agent.last_answer(), Some("third answer"));
+
+        // Only the summary precedes the kept turns, so there is nothing to do
+        agent.config.compaction = Some(crate::config::CompactionConfig {
+            keep_turns: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(agent.compact().await.unwrap(), None);
     }
 
     #[tokio::test]
-    async fn test_spawn_task_with_options() {
-        let mut agent = Agent::load_from_config()
-            .await
-            .expect("Failed to load agent");
+    async fn test_expand_trimmed_tools() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        let config = ToolTrimConfig {
+            policy: Some(TrimPolicy::Relevant),
+            max_tools: Some(1),
+            short_descriptions: Some(false),
+        };
+        agent.offered_tools = Some(trim::offered_definitions(
+            &agent.tool_definitions,
+            &["read".to_string()],
+            &config,
+        ));
 
-        // Create some context messages
-        let context = vec![
-            Message {
-                role: "user".to_string(),
-                content: "Previous context".to_string(),
-                tool_calls: None,
-                tool_call_id: None,
-            }
-        ];
+        let result = agent.expand_tools(&["grep", "nonexistent"], &config);
+        assert!(result.contains("grep"));
+        let names: Vec<String> = agent
+            .offered_tools
+            .iter()
+            .flatten()
+            .map(|d| d.function.name.clone())
+            .collect();
+        assert!(names.contains(&"read".to_string()));
+        assert!(names.contains(&"grep".to_string()));
+        assert!(names.contains(&trim::REQUEST_TOOLS.to_string()));
+
+        // Asking again for an offered tool changes nothing
+        assert!(agent.expand_tools(&["grep"], &config).starts_with("No new tools"));
+    }
 
-        let result = agent
-            .spawn_task_with_options(
-                SubAgentType::Explore,
-                "测试带选项的任务",
-                "列出当前目录的文件",
-                Some(&context),
-                true, // Enable tools
-            )
-            .await;
+    #[tokio::test]
+    async fn test_pins_survive_history_changes() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.pin("always answer in English").unwrap();
+        agent.pin("always answer in English").unwrap();
+        assert_eq!(agent.pins().len(), 1);
+
+        agent.messages.push(Message::user("hi"));
+        agent.clear_history();
+        let messages = agent.request_messages(&[]);
+        assert!(messages.iter().any(|m| m.role() == "system" && m.content().contains("always answer in English")));
+
+        assert!(agent.unpin(1).is_err());
+        agent.unpin(0).unwrap();
+        assert!(agent.request_messages(&[]).iter().all(|m| !m.content().contains("Pinned")));
+    }
 
-        // Requires Ollama to be running
-        if result.is_ok() {
-            let output = result.unwrap();
-            assert!(output.contains("Subagent Task Complete"));
-            assert!(output.contains("used_tools"));
-            assert!(output.contains("duration_ms"));
-        }
+    #[tokio::test]
+    async fn test_system_prompt_comes_first() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.instructions = Some(ProjectInstructions {
+            file: "AGENTS.md",
+            content: "Run the linter.".to_string(),
+        });
+        agent.config.system_prompt = Some("You are terse.".to_string());
+        agent.messages.push(Message::user("hi"));
+        assert_eq!(
+            agent.request_messages(&[])[0],
+            Message::system("You are terse.\n\nProject instructions from `AGENTS.md`:\n\nRun the linter.")
+        );
+
+        // A prompt for the configured model takes the place of the default one
+        let model = agent.turn_model().to_string();
+        agent.config.model_system_prompts = Some(HashMap::from([(model, "Think step by step.".to_string())]));
+        agent.instructions = None;
+        assert_eq!(agent.request_messages(&[])[0], Message::system("Think step by step."));
     }
 
     #[tokio::test]
-    async fn test_spawn_task_code_review() {
-        let mut agent = Agent::load_from_config()
-            .await
-            .expect("Failed to load agent");
+    async fn test_register_subagent_type() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.register_subagent_type(
+            SubAgentProfile::new("docs-writer", "Writes documentation").system_prompt("You write docs."),
+        );
+        assert!(agent.subagent_types().get("docs-writer").is_some());
 
-        let result = agent
-            .spawn_task(
-                SubAgentType::CodeReview,
-                "代码审查测试",
-                "Review this function: fn add(a: i32, b: i32) -> i32 { a + b }",
-            )
-            .await;
+        let task = agent.offered_tools.as_ref().unwrap().iter().find(|def| def.function.name == "task").unwrap();
+        let types = task.function.parameters.properties["subagent_type"]["enum"].as_array().unwrap();
+        assert!(types.contains(&json!("docs-writer")));
 
-        // Requires Ollama to be running
-        if result.is_ok() {
-            let output = result.unwrap();
-            assert!(output.contains("Subagent Task Complete"));
-            assert!(output.contains("Code reviewer"));
-        }
+        let err = agent
+            .spawn_named_task("security-audit", "audit", "look for bugs", None, false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("expected one of: general-purpose, explore"));
+        assert!(err.to_string().ends_with("docs-writer)"));
+    }
+
+    #[test]
+    fn test_normalize_prompt() {
+        assert_eq!(
+            normalize_prompt("  Explore the\n  TOOLS module "),
+            normalize_prompt("explore the tools module")
+        );
+        assert_ne!(normalize_prompt("explore tools"), normalize_prompt("explore agent"));
     }
 
     #[tokio::test]
-    async fn test_spawn_task_test_runner() {
+    async fn test_subagent_cache_hit() {
         let mut agent = Agent::load_from_config()
             .await
             .expect("Failed to load agent");
 
+        // Requires running inside a git repository
+        let Some(key) = agent
+            .subagent_cache_key("explore", true, "Explore", "the tools module")
+            .await
+        else {
+            return;
+        };
+        agent.subagent_cache.insert(key, json!("cached result"));
+
         let result = agent
-            .spawn_task(
-                SubAgentType::TestRunner,
-                "测试执行测试",
-                "Design tests for a simple calculator",
-            )
+            .run_subagent(SubAgentType::Explore.into(), "explore", "The  tools module", None, true, false)
             .await;
+        assert_eq!(result.unwrap(), format_subagent_output(&json!("cached result")));
+    }
 
-        // Requires Ollama to be running
-        if result.is_ok() {
-            let output = result.unwrap();
-            assert!(output.contains("Subagent Task Complete"));
-            assert!(output.contains("Test runner"));
-        }
+    #[tokio::test]
+    async fn test_parallel_tasks_keep_order() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.set_frontend(Arc::new(RecordingFrontend::default()));
+
+        // Requires running inside a git repository
+        let Some(key) = agent
+            .subagent_cache_key("explore", true, "Explore", "the tools module")
+            .await
+        else {
+            return;
+        };
+        agent.subagent_cache.insert(key, json!({"result": "tools live in src/tools"}));
+
+        let tasks: Vec<ParallelTask> = serde_json::from_value(json!([
+            {"subagent_type": "security-audit", "description": "Audit", "prompt": "find bugs"},
+            {"subagent_type": "explore", "description": "Explore", "prompt": "the tools module", "include_tools": true},
+        ]))
+        .unwrap();
+        let outputs = agent.run_parallel_subagents(&tasks).await.unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert!(outputs[0]["error"].as_str().unwrap().contains("Invalid subagent type"));
+        assert_eq!(outputs[1]["result"], "tools live in src/tools");
+    }
+
+    #[tokio::test]
+    async fn test_background_task() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        agent.sessions_dir = None;
+        agent.set_frontend(Arc::new(RecordingFrontend::default()));
+
+        let started = agent
+            .run_tool("task", json!({
+                "subagent_type": "plan",
+                "description": "Plan",
+                "prompt": "plan nothing",
+                "force": true,
+                "run_in_background": true
+            }))
+            .await
+            .unwrap();
+        let started: Value = serde_json::from_str(&started).unwrap();
+        assert_eq!(started["task_id"], 1);
+        assert_eq!(started["status"], "running");
+
+        let status = agent.run_tool("task_status", json!({})).await.unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&status).unwrap()[0]["subagent_type"], "plan");
+
+        // Without a model to answer the task fails, but either way it finishes
+        let execution = agent.task_result(1, true).await.unwrap();
+        assert!(matches!(execution.status, SubAgentStatus::Completed | SubAgentStatus::Failed(_)));
+        assert!(agent.running.is_empty());
+        let missing = agent.run_tool("task_result", json!({"task_id": 9})).await.unwrap();
+        assert!(missing.contains("No background task 9"));
     }
 
     #[test]
@@ -996,8 +3937,7 @@ mod tests {
         let result = agent.spawn_multiple_tasks(tasks).await;
 
         // Requires Ollama to be running
-        if result.is_ok() {
-            let outputs = result.unwrap();
+        if let Ok(outputs) = result {
             assert_eq!(outputs.len(), 2);
             for output in outputs {
                 assert!(output.contains("Subagent Task Complete"));
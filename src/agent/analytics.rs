@@ -0,0 +1,198 @@
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+/// Where tool calls are recorded, relative to the working directory
+pub const TOOL_STATS_FILE: &str = ".ariste/tool_stats.jsonl";
+
+/// One tool call as seen by the agent
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ToolCallRecord {
+    pub session: String,
+    pub tool: String,
+    /// Argument names the model passed, sorted (the call's "shape")
+    pub arguments: Vec<String>,
+    pub error: bool,
+    pub truncated: bool,
+    pub output_chars: usize,
+}
+
+impl ToolCallRecord {
+    pub fn new(session: &str, tool: &str, arguments: &Value, result: Result<&str, &str>) -> Self {
+        let mut names: Vec<String> = arguments
+            .as_object()
+            .map(|args| args.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+
+        let output = match result {
+            Ok(output) | Err(output) => output,
+        };
+        Self {
+            session: session.to_string(),
+            tool: tool.to_string(),
+            arguments: names,
+            error: result.is_err(),
+            truncated: is_truncated(output),
+            output_chars: output.chars().count(),
+        }
+    }
+
+    fn shape(&self) -> String {
+        format!("({})", self.arguments.join(", "))
+    }
+}
+
+/// Whether a tool output carries one of the truncation notes tools append
+fn is_truncated(output: &str) -> bool {
+    let tail = output.len().saturating_sub(200);
+    let tail = output
+        .char_indices()
+        .find(|(i, _)| *i >= tail)
+        .map_or("", |(i, _)| &output[i..]);
+    tail.contains("truncated")
+}
+
+/// Append a record to the stats log
+pub async fn record(path: &Path, record: &ToolCallRecord) -> Result<(), Error> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Read every record in the stats log, skipping malformed lines
+pub async fn load(path: &Path) -> Result<Vec<ToolCallRecord>, Error> {
+    if !tokio::fs::try_exists(path).await? {
+        return Ok(Vec::new());
+    }
+    let contents = tokio::fs::read_to_string(path).await?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Usage of one tool across all recorded sessions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolSummary {
+    pub tool: String,
+    pub calls: usize,
+    pub sessions: usize,
+    pub errors: usize,
+    pub truncated: usize,
+    pub avg_output_chars: usize,
+    /// Argument shapes by frequency, most common first
+    pub shapes: Vec<(String, usize)>,
+}
+
+impl std::fmt::Display for ToolSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}: {} calls in {} sessions, {} errors, {} truncated, ~{} chars/output",
+            self.tool, self.calls, self.sessions, self.errors, self.truncated, self.avg_output_chars
+        )?;
+        for (shape, count) in &self.shapes {
+            writeln!(f, "  {:>5}  {}", count, shape)?;
+        }
+        Ok(())
+    }
+}
+
+/// Aggregate records per tool, most-called tool first
+pub fn summarize(records: &[ToolCallRecord]) -> Vec<ToolSummary> {
+    let mut by_tool: BTreeMap<&str, Vec<&ToolCallRecord>> = BTreeMap::new();
+    for record in records {
+        by_tool.entry(&record.tool).or_default().push(record);
+    }
+
+    let mut summaries: Vec<ToolSummary> = by_tool
+        .into_iter()
+        .map(|(tool, records)| {
+            let mut shapes: BTreeMap<String, usize> = BTreeMap::new();
+            for record in &records {
+                *shapes.entry(record.shape()).or_default() += 1;
+            }
+            let mut shapes: Vec<(String, usize)> = shapes.into_iter().collect();
+            shapes.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+            let calls = records.len();
+            ToolSummary {
+                tool: tool.to_string(),
+                calls,
+                sessions: records.iter().map(|r| &r.session).collect::<HashSet<_>>().len(),
+                errors: records.iter().filter(|r| r.error).count(),
+                truncated: records.iter().filter(|r| r.truncated).count(),
+                avg_output_chars: records.iter().map(|r| r.output_chars).sum::<usize>() / calls,
+                shapes,
+            }
+        })
+        .collect();
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.calls));
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_shape() {
+        let record = ToolCallRecord::new(
+            "s1",
+            "grep",
+            &json!({"pattern": "fn", "output_mode": "content"}),
+            Ok("a.rs:1:fn main()\n... (truncated)"),
+        );
+        assert_eq!(record.arguments, vec!["output_mode", "pattern"]);
+        assert!(record.truncated);
+        assert!(!record.error);
+
+        let failed = ToolCallRecord::new("s1", "read", &json!({}), Err("Missing 'file_path' argument"));
+        assert!(failed.error);
+        assert!(!failed.truncated);
+        assert_eq!(failed.shape(), "()");
+    }
+
+    #[test]
+    fn test_summarize() {
+        let records = vec![
+            ToolCallRecord::new("s1", "read", &json!({"file_path": "a"}), Ok("abcd")),
+            ToolCallRecord::new("s1", "read", &json!({"file_path": "b"}), Ok("ab")),
+            ToolCallRecord::new("s2", "read", &json!({"file_path": "c", "limit": 5}), Err("no")),
+            ToolCallRecord::new("s2", "glob", &json!({"pattern": "*"}), Ok("x")),
+        ];
+        let summary = summarize(&records);
+
+        assert_eq!(summary[0].tool, "read");
+        assert_eq!(summary[0].calls, 3);
+        assert_eq!(summary[0].sessions, 2);
+        assert_eq!(summary[0].errors, 1);
+        assert_eq!(summary[0].shapes[0], ("(file_path)".to_string(), 2));
+        assert_eq!(summary[1].tool, "glob");
+    }
+
+    #[tokio::test]
+    async fn test_record_and_load() {
+        let path = Path::new("/tmp/test_tool_stats.jsonl");
+        tokio::fs::remove_file(path).await.ok();
+        assert!(load(path).await.unwrap().is_empty());
+
+        let entry = ToolCallRecord::new("s1", "bash", &json!({"command": "ls"}), Ok("file"));
+        record(path, &entry).await.unwrap();
+        record(path, &entry).await.unwrap();
+        assert_eq!(load(path).await.unwrap(), vec![entry.clone(), entry]);
+
+        tokio::fs::remove_file(path).await.ok();
+    }
+}
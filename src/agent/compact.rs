@@ -0,0 +1,140 @@
+use crate::agent::context::{message_tokens, recent_start, tool_names};
+use crate::agent::message::Message;
+
+/// Per-tool-result cap on what the summarizer sees
+const MAX_RESULT_CHARS: usize = 2_000;
+
+/// Opening line of the note that replaces compacted turns
+pub const SUMMARY_HEADER: &str = "Summary of the earlier conversation (older turns were compacted):";
+
+const SUMMARIZER_PROMPT: &str = "You are summarizing the earlier part of a conversation between a user and a \
+     coding agent so it can continue without the full history. Keep the user's goals and decisions, files \
+     read or changed and what was found in them, commands run and their outcomes, and anything left to do. \
+     Drop pleasantries and tool output that no longer matters. Reply with the summary only, as short bullet points.";
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}\n... (truncated)", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Estimated tokens of the whole history
+pub fn total_tokens(messages: &[Message]) -> u64 {
+    messages.iter().map(message_tokens).sum()
+}
+
+/// Number of leading messages to compact so that the last `keep_turns`
+/// turns stay intact. Turns are cut only at user prompts, so a tool call
+/// is never separated from its result. Zero when there is no older turn,
+/// e.g. when only an earlier summary precedes the kept turns.
+pub fn split_point(messages: &[Message], keep_turns: usize) -> usize {
+    let split = recent_start(messages, keep_turns);
+    if messages[..split].iter().any(|m| m.is_user()) {
+        split
+    } else {
+        0
+    }
+}
+
+/// The messages as a plain transcript for the summarizer
+fn transcript(messages: &[Message]) -> String {
+    let names = tool_names(messages);
+    let mut out = String::new();
+    for (index, message) in messages.iter().enumerate() {
+        match names.get(&index) {
+            Some(tool) => out.push_str(&format!(
+                "[{} result]\n{}\n\n",
+                tool,
+                truncate(message.content(), MAX_RESULT_CHARS)
+            )),
+            None => {
+                if !message.content().trim().is_empty() {
+                    out.push_str(&format!("{}: {}\n\n", message.role(), message.content().trim()));
+                }
+                for call in message.tool_calls() {
+                    let Some(function) = call.get("function") else {
+                        continue;
+                    };
+                    let name = function.get("name").and_then(|v| v.as_str()).unwrap_or("unknown");
+                    let arguments = function.get("arguments").map(|v| v.to_string()).unwrap_or_default();
+                    out.push_str(&format!("[called {} {}]\n", name, arguments));
+                }
+            }
+        }
+    }
+    out.trim_end().to_string()
+}
+
+/// Messages asking the summarizer to condense `older`
+pub fn summary_messages(older: &[Message]) -> Vec<Message> {
+    vec![
+        Message::system(SUMMARIZER_PROMPT),
+        Message::user(format!("## Conversation\n{}", transcript(older))),
+    ]
+}
+
+/// Replace the first `split` messages with a system note holding `summary`
+pub fn apply(messages: &mut Vec<Message>, split: usize, summary: &str) {
+    let note = Message::system(format!("{}\n{}", SUMMARY_HEADER, summary.trim()));
+    messages.splice(..split, [note]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(role: &str, content: &str) -> Message {
+        match role {
+            "user" => Message::user(content),
+            _ => Message::assistant(content),
+        }
+    }
+
+    fn history() -> Vec<Message> {
+        let call = Message::assistant_with_tools(
+            "",
+            vec![json!({"id": "a", "function": {"name": "read", "arguments": {"file_path": "Cargo.toml"}}})],
+        );
+        let result = Message::tool(Some("a".to_string()), None, "x".repeat(3_000));
+        vec![
+            message("user", "what is the package name?"),
+            call,
+            result,
+            message("assistant", "It is ariste"),
+            message("user", "and the edition?"),
+            message("assistant", "2024"),
+        ]
+    }
+
+    #[test]
+    fn test_split_keeps_recent_turns() {
+        let messages = history();
+        assert_eq!(split_point(&messages, 1), 4);
+        assert_eq!(split_point(&messages, 2), 0);
+        assert_eq!(split_point(&messages, 0), messages.len());
+        assert!(total_tokens(&messages) > 750);
+
+        let mut compacted = messages.clone();
+        apply(&mut compacted, 4, "- The package is ariste");
+        assert_eq!(split_point(&compacted, 1), 0);
+    }
+
+    #[test]
+    fn test_summary_request_and_apply() {
+        let mut messages = history();
+        let request = summary_messages(&messages[..4]);
+        let transcript = request[1].content();
+        assert!(transcript.contains("user: what is the package name?"));
+        assert!(transcript.contains(r#"[called read {"file_path":"Cargo.toml"}]"#));
+        assert!(transcript.contains("[read result]"));
+        assert!(transcript.contains("(truncated)"));
+        assert!(!transcript.contains("edition"));
+
+        apply(&mut messages, 4, "- The package is ariste\n");
+        let roles: Vec<&str> = messages.iter().map(Message::role).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant"]);
+        assert_eq!(messages[0].content(), format!("{}\n- The package is ariste", SUMMARY_HEADER));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::agent::message::Message;
+
+/// Number of answers gathered when `/consensus` is given no count
+pub const DEFAULT_PANEL_SIZE: usize = 3;
+
+const JUDGE_PROMPT: &str = "You are a judge. Several assistants answered the same question independently. \
+     Compare their answers, point out where they disagree and which reasoning holds up, then write one \
+     final answer that merges the best parts. Start with the final answer; keep the comparison brief.";
+
+/// Parse `/consensus [n] <prompt>` arguments into the panel size and prompt
+pub fn parse_args(args: &str) -> Option<(Option<usize>, &str)> {
+    let args = args.trim();
+    let (count, prompt) = match args.split_once(char::is_whitespace) {
+        Some((first, rest)) => match first.parse::<usize>() {
+            Ok(n) => (Some(n), rest.trim()),
+            Err(_) => (None, args),
+        },
+        None => (None, args),
+    };
+    if prompt.is_empty() || count == Some(0) {
+        return None;
+    }
+    Some((count, prompt))
+}
+
+/// Models on the panel: the first `n` configured consensus models, cycling
+/// through them (or repeating `fallback`) when fewer are configured
+pub fn panel(models: &[String], fallback: &str, n: Option<usize>) -> Vec<String> {
+    let n = n.unwrap_or(if models.is_empty() {
+        DEFAULT_PANEL_SIZE
+    } else {
+        models.len()
+    });
+    if models.is_empty() {
+        return vec![fallback.to_string(); n];
+    }
+    models.iter().cycle().take(n).cloned().collect()
+}
+
+/// Messages asking the judge to merge the panel's answers
+pub fn judge_messages(question: &str, answers: &[(String, String)]) -> Vec<Message> {
+    let mut prompt = format!("## Question\n{}\n\n", question);
+    for (i, (model, answer)) in answers.iter().enumerate() {
+        prompt.push_str(&format!("## Answer {} ({})\n{}\n\n", i + 1, model, answer.trim()));
+    }
+
+    vec![
+        Message::system(JUDGE_PROMPT),
+        Message::user(prompt.trim_end()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args("3 which db?"), Some((Some(3), "which db?")));
+        assert_eq!(parse_args("which db?"), Some((None, "which db?")));
+        assert_eq!(parse_args("question"), Some((None, "question")));
+        assert_eq!(parse_args("0 which db?"), None);
+        assert_eq!(parse_args("   "), None);
+    }
+
+    #[test]
+    fn test_panel() {
+        let models = vec!["qwen3".to_string(), "llama3".to_string()];
+        assert_eq!(panel(&models, "x", None), models);
+        assert_eq!(panel(&models, "x", Some(3)), vec!["qwen3", "llama3", "qwen3"]);
+        assert_eq!(panel(&[], "qwen3", None), vec!["qwen3"; DEFAULT_PANEL_SIZE]);
+        assert_eq!(panel(&[], "qwen3", Some(2)), vec!["qwen3", "qwen3"]);
+    }
+
+    #[test]
+    fn test_judge_messages() {
+        let answers = vec![
+            ("qwen3".to_string(), "Use Postgres.\n".to_string()),
+            ("llama3".to_string(), "Use SQLite.".to_string()),
+        ];
+        let messages = judge_messages("Which database?", &answers);
+
+        assert_eq!(messages[0].role(), "system");
+        assert_eq!(
+            messages[1].content(),
+            "## Question\nWhich database?\n\n## Answer 1 (qwen3)\nUse Postgres.\n\n## Answer 2 (llama3)\nUse SQLite."
+        );
+    }
+}
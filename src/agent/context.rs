@@ -0,0 +1,474 @@
+use crate::agent::message::Message;
+use crate::llm::estimate_tokens;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Tool results listed in the "largest" section
+const LARGEST_RESULTS: usize = 5;
+
+/// Token usage of one role, or of one tool's results
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextGroup {
+    /// `user`, `assistant`, `system` or `tool:<name>`
+    pub label: String,
+    pub messages: usize,
+    pub tokens: u64,
+}
+
+/// A single tool result and where it sits in the conversation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolResultSize {
+    pub index: usize,
+    pub tool: String,
+    pub tokens: u64,
+}
+
+/// What is occupying the context window (the `/context` command)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextReport {
+    pub total: u64,
+    /// Groups by descending token count
+    pub groups: Vec<ContextGroup>,
+    pub largest: Vec<ToolResultSize>,
+    /// Tokens freed by compacting tool results outside the recent turns
+    pub compactable: u64,
+    pub keep_turns: usize,
+}
+
+/// The assistant message and call position each tool result answers,
+/// matched by call id or, for calls without ids, in order
+pub(crate) fn tool_result_calls(messages: &[Message]) -> HashMap<usize, (usize, usize)> {
+    let mut links = HashMap::new();
+    let mut pending: Vec<(usize, usize, String)> = Vec::new();
+
+    for (index, message) in messages.iter().enumerate() {
+        match message.role() {
+            "assistant" => {
+                pending = message
+                    .tool_calls()
+                    .iter()
+                    .enumerate()
+                    .map(|(position, call)| {
+                        let id = call.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+                        (index, position, id.to_string())
+                    })
+                    .collect();
+            }
+            "tool" if !pending.is_empty() => {
+                let id = message.tool_call_id().unwrap_or_default();
+                let position = pending
+                    .iter()
+                    .position(|(_, _, call_id)| !id.is_empty() && call_id == id)
+                    .unwrap_or(0);
+                let (assistant, call, _) = pending.remove(position);
+                links.insert(index, (assistant, call));
+            }
+            _ => {}
+        }
+    }
+    links
+}
+
+/// Id for a tool call the provider sent without one, derived from where
+/// the reply sits in the history and what it calls, so replaying the same
+/// history gives the same id
+pub(crate) fn synthetic_call_id(position: usize, index: usize, call: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}:{}:{}", position, index, call.get("function").unwrap_or(call)));
+    let digest = hasher.finalize();
+    format!("call_{}", digest[..6].iter().map(|byte| format!("{:02x}", byte)).collect::<String>())
+}
+
+/// Give every tool call in `calls` an id, keeping the ones it has
+pub(crate) fn fill_ids(position: usize, calls: &mut [Value]) {
+    for (index, call) in calls.iter_mut().enumerate() {
+        let missing = call.get("id").and_then(|v| v.as_str()).is_none_or(str::is_empty);
+        if missing {
+            let id = synthetic_call_id(position, index, call);
+            if let Some(object) = call.as_object_mut() {
+                object.insert("id".to_string(), Value::String(id));
+            }
+        }
+    }
+}
+
+/// Give id-less calls in a history (e.g. saved from Ollama before ids were
+/// generated) synthetic ids, and point their results at them
+pub(crate) fn fill_call_ids(messages: &mut [Message]) {
+    let links = tool_result_calls(messages);
+    for (position, message) in messages.iter_mut().enumerate() {
+        if let Message::Assistant { tool_calls, .. } = message {
+            fill_ids(position, tool_calls);
+        }
+    }
+    for (result, (assistant, call)) in links {
+        let call_id = messages[assistant].tool_calls()[call]
+            .get("id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        if let Message::Tool { id, .. } = &mut messages[result]
+            && id.as_deref().is_none_or(str::is_empty)
+        {
+            *id = call_id;
+        }
+    }
+}
+
+/// Name of the tool behind each tool result; older sessions don't record
+/// it, so it is looked up from the call
+pub(crate) fn tool_names(messages: &[Message]) -> HashMap<usize, String> {
+    let links = tool_result_calls(messages);
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.is_tool())
+        .map(|(index, message)| {
+            let name = message
+                .tool_name()
+                .or_else(|| {
+                    let (assistant, call) = links.get(&index)?;
+                    messages[*assistant].tool_calls().get(*call)?.get("function")?.get("name")?.as_str()
+                })
+                .unwrap_or("unknown");
+            (index, name.to_string())
+        })
+        .collect()
+}
+
+/// Record on each tool result the name of the tool that produced it
+pub(crate) fn fill_tool_names(messages: &mut [Message]) {
+    for (index, tool) in tool_names(messages) {
+        if let Message::Tool { name, .. } = &mut messages[index]
+            && name.is_none()
+            && tool != "unknown"
+        {
+            *name = Some(tool);
+        }
+    }
+}
+
+/// Estimated tokens of one message, tool calls included
+pub(crate) fn message_tokens(message: &Message) -> u64 {
+    let mut tokens = estimate_tokens(message.content());
+    let calls = message.tool_calls();
+    if !calls.is_empty() {
+        tokens += estimate_tokens(&serde_json::to_string(calls).unwrap_or_default());
+    }
+    tokens
+}
+
+/// Paths and file names mentioned in `text`, such as `src/main.rs` or `Cargo.toml`
+fn mentioned_files(text: &str) -> Vec<&str> {
+    let mut files: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || "`'\"(),;:<>[]{}".contains(c))
+        .map(|word| word.trim_end_matches(['.', '!', '?']))
+        .filter(|word| !word.starts_with("//"))
+        .filter(|word| {
+            let name = word.rsplit('/').next().unwrap_or_default();
+            let has_extension = name
+                .rsplit_once('.')
+                .is_some_and(|(stem, ext)| !stem.is_empty() && ext.chars().any(|c| c.is_ascii_alphabetic()));
+            has_extension || (word.contains('/') && !name.is_empty())
+        })
+        .collect();
+    files.sort_unstable();
+    files.dedup();
+    files
+}
+
+/// Messages from `messages` to forward to a subagent working on `task`,
+/// within about `budget` tokens. Up to half the budget goes to messages
+/// mentioning files named in the task, newest first, and the rest to the
+/// most recent messages. Tool calls stay together with their results,
+/// system messages are left out, and the order is kept.
+pub(crate) fn select_for_task(messages: &[Message], task: &str, budget: u64) -> Vec<Message> {
+    let files = mentioned_files(task);
+    let mentions = |message: &Message| {
+        let calls = serde_json::to_string(message.tool_calls()).unwrap_or_default();
+        files.iter().any(|file| message.content().contains(file) || calls.contains(file))
+    };
+
+    // 把工具结果并入发起调用的助手消息，作为一个整体选取
+    struct Unit {
+        indices: Vec<usize>,
+        tokens: u64,
+        relevant: bool,
+    }
+    let links = tool_result_calls(messages);
+    let mut units: Vec<Unit> = Vec::new();
+    let mut unit_of: HashMap<usize, usize> = HashMap::new();
+    for (index, message) in messages.iter().enumerate() {
+        if matches!(message, Message::System { .. }) {
+            continue;
+        }
+        let tokens = message_tokens(message);
+        let relevant = mentions(message);
+        match links.get(&index).and_then(|(assistant, _)| unit_of.get(assistant)) {
+            Some(&unit) => {
+                let unit = &mut units[unit];
+                unit.indices.push(index);
+                unit.tokens += tokens;
+                unit.relevant |= relevant;
+            }
+            None => {
+                unit_of.insert(index, units.len());
+                units.push(Unit {
+                    indices: vec![index],
+                    tokens,
+                    relevant,
+                });
+            }
+        }
+    }
+
+    let mut chosen = vec![false; units.len()];
+    let mut used = 0;
+    for (position, unit) in units.iter().enumerate().rev() {
+        if unit.relevant && used + unit.tokens <= budget / 2 {
+            chosen[position] = true;
+            used += unit.tokens;
+        }
+    }
+    for (position, unit) in units.iter().enumerate().rev() {
+        if chosen[position] {
+            continue;
+        }
+        if used + unit.tokens > budget {
+            break;
+        }
+        chosen[position] = true;
+        used += unit.tokens;
+    }
+
+    units
+        .iter()
+        .zip(chosen)
+        .filter(|(_, chosen)| *chosen)
+        .flat_map(|(unit, _)| unit.indices.iter().map(|&index| messages[index].clone()))
+        .collect()
+}
+
+/// Index of the user prompt that opens the last `keep_turns` turns (the
+/// history length for zero turns, 0 when there are fewer turns)
+pub(crate) fn recent_start(messages: &[Message], keep_turns: usize) -> usize {
+    match keep_turns {
+        0 => messages.len(),
+        n => messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.is_user())
+            .map(|(index, _)| index)
+            .rev()
+            .nth(n - 1)
+            .unwrap_or(0),
+    }
+}
+
+/// Analyze `messages`, treating everything before the last `keep_turns`
+/// user prompts as eligible for compaction
+pub fn analyze(messages: &[Message], keep_turns: usize) -> ContextReport {
+    let names = tool_names(messages);
+    let recent_start = recent_start(messages, keep_turns);
+
+    let mut groups: HashMap<String, ContextGroup> = HashMap::new();
+    let mut results = Vec::new();
+    let mut total = 0;
+    let mut compactable = 0;
+
+    for (index, message) in messages.iter().enumerate() {
+        let tokens = message_tokens(message);
+        total += tokens;
+
+        let label = match names.get(&index) {
+            Some(tool) => {
+                results.push(ToolResultSize {
+                    index,
+                    tool: tool.clone(),
+                    tokens,
+                });
+                if index < recent_start {
+                    compactable += tokens;
+                }
+                format!("tool:{}", tool)
+            }
+            None => message.role().to_string(),
+        };
+        let group = groups.entry(label.clone()).or_insert(ContextGroup {
+            label,
+            messages: 0,
+            tokens: 0,
+        });
+        group.messages += 1;
+        group.tokens += tokens;
+    }
+
+    let mut groups: Vec<ContextGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.label.cmp(&b.label)));
+    results.sort_by_key(|result| std::cmp::Reverse(result.tokens));
+    results.truncate(LARGEST_RESULTS);
+
+    ContextReport {
+        total,
+        groups,
+        largest: results,
+        compactable,
+        keep_turns,
+    }
+}
+
+impl std::fmt::Display for ContextReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Context: ~{} tokens", self.total)?;
+        for group in &self.groups {
+            let share = if self.total == 0 {
+                0.0
+            } else {
+                group.tokens as f64 * 100.0 / self.total as f64
+            };
+            writeln!(
+                f,
+                "  {:<20} {:>7} tokens  {:>5.1}%  ({} messages)",
+                group.label, group.tokens, share, group.messages
+            )?;
+        }
+        if !self.largest.is_empty() {
+            writeln!(f, "Largest tool results:")?;
+            for result in &self.largest {
+                writeln!(f, "  #{:<4} {:<14} {:>7} tokens", result.index, result.tool, result.tokens)?;
+            }
+        }
+        writeln!(
+            f,
+            "Compacting tool results older than the last {} turns would free ~{} tokens",
+            self.keep_turns, self.compactable
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(role: &str, content: &str) -> Message {
+        match role {
+            "user" => Message::user(content),
+            "tool" => Message::tool(None, None, content),
+            _ => Message::assistant(content),
+        }
+    }
+
+    fn tool_call(name: &str) -> Message {
+        Message::assistant_with_tools("", vec![json!({"function": {"name": name, "arguments": {}}})])
+    }
+
+    #[test]
+    fn test_analyze() {
+        let messages = vec![
+            message("user", "read the file"),
+            tool_call("read"),
+            message("tool", &"x".repeat(400)),
+            message("assistant", "done"),
+            message("user", "now grep"),
+            tool_call("grep"),
+            message("tool", &"y".repeat(40)),
+            message("assistant", "found it"),
+        ];
+        let report = analyze(&messages, 1);
+
+        assert_eq!(report.groups[0].label, "tool:read");
+        assert_eq!(report.groups[0].tokens, 100);
+        assert_eq!(report.largest[0], ToolResultSize { index: 2, tool: "read".to_string(), tokens: 100 });
+        assert_eq!(report.largest[1].tool, "grep");
+        // Only the read result falls outside the last turn
+        assert_eq!(report.compactable, 100);
+        assert!(report.to_string().contains("would free ~100 tokens"));
+    }
+
+    #[test]
+    fn test_fill_call_ids() {
+        let call = |name: &str| json!({"function": {"name": name, "arguments": {}}});
+        let mut messages = vec![
+            message("user", "look"),
+            Message::assistant_with_tools("", vec![call("read"), json!({"id": "kept", "function": {"name": "ls"}})]),
+            message("tool", "read output"),
+            Message::tool(Some("kept".to_string()), None, "ls output"),
+            Message::assistant_with_tools("", vec![call("read")]),
+            message("tool", "read again"),
+        ];
+        fill_call_ids(&mut messages);
+
+        let first = messages[1].tool_calls()[0]["id"].as_str().unwrap().to_string();
+        assert!(first.starts_with("call_"));
+        assert_eq!(messages[1].tool_calls()[1]["id"], "kept");
+        assert_eq!(messages[2].tool_call_id(), Some(first.as_str()));
+        assert_eq!(messages[3].tool_call_id(), Some("kept"));
+        // The same call later in the history gets a different id
+        assert_ne!(messages[5].tool_call_id(), Some(first.as_str()));
+
+        // Stable: filling a fresh copy gives the same ids, and filling again changes nothing
+        let filled = messages.clone();
+        fill_call_ids(&mut messages);
+        assert_eq!(messages, filled);
+        assert_eq!(synthetic_call_id(1, 0, &call("read")), first);
+    }
+
+    #[test]
+    fn test_tool_names_by_id() {
+        let call = Message::assistant_with_tools(
+            "",
+            vec![
+                json!({"id": "a", "function": {"name": "read"}}),
+                json!({"id": "b", "function": {"name": "glob"}}),
+            ],
+        );
+        let first = Message::tool(Some("b".to_string()), None, "glob output");
+        let second = Message::tool(Some("a".to_string()), None, "read output");
+        let named = Message::tool(Some("c".to_string()), Some("bash".to_string()), "ok");
+
+        let names = tool_names(&[call, first, second, named]);
+        assert_eq!(names[&1], "glob");
+        assert_eq!(names[&2], "read");
+        assert_eq!(names[&3], "bash");
+    }
+
+    #[test]
+    fn test_select_for_task() {
+        let read = Message::assistant_with_tools(
+            "",
+            vec![json!({"function": {"name": "read", "arguments": {"file_path": "src/parser.rs"}}})],
+        );
+        let messages = vec![
+            Message::system("be brief"),
+            message("user", "how should the parser handle errors?"),
+            read,
+            message("tool", &"p".repeat(400)),
+            message("assistant", "Keep positions in src/parser.rs errors"),
+            message("user", &"unrelated ".repeat(100)),
+            message("assistant", &"z".repeat(800)),
+            message("user", "thanks"),
+            message("assistant", "you're welcome"),
+        ];
+
+        assert_eq!(mentioned_files("Fix `src/parser.rs`, then Cargo.toml. See https://x.io/a."), vec!["Cargo.toml", "src/parser.rs"]);
+
+        let selected = select_for_task(&messages, "Add spans to parser.rs errors", 300);
+        // The earlier read stays with its result; the big unrelated turn is dropped
+        assert_eq!(
+            selected,
+            vec![
+                messages[2].clone(),
+                messages[3].clone(),
+                messages[4].clone(),
+                messages[7].clone(),
+                messages[8].clone(),
+            ]
+        );
+
+        // Nothing relevant: only the most recent messages that fit
+        let selected = select_for_task(&messages, "Write a changelog", 20);
+        assert_eq!(selected, messages[7..].to_vec());
+        assert!(select_for_task(&messages, "anything", 0).is_empty());
+    }
+}
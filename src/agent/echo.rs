@@ -0,0 +1,107 @@
+/// Shortest verbatim repeat (in characters) worth collapsing
+const MIN_ECHO_CHARS: usize = 200;
+
+/// Placeholder shown instead of a repeated tool result
+pub const ECHO_PLACEHOLDER: &str = "[see tool output above]";
+
+/// Collapse runs of lines in `answer` that repeat a tool output verbatim.
+/// Returns `None` when nothing large enough was repeated.
+pub fn collapse_echoes(answer: &str, tool_outputs: &[&str]) -> Option<String> {
+    let tool_lines: std::collections::HashSet<&str> = tool_outputs
+        .iter()
+        .flat_map(|output| output.lines())
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if tool_lines.is_empty() {
+        return None;
+    }
+
+    let lines: Vec<&str> = answer.lines().collect();
+    let is_echo = |line: &str| {
+        let line = line.trim();
+        line.is_empty() || tool_lines.contains(line)
+    };
+
+    let mut output: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut collapsed = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        if lines[i].trim().is_empty() || !is_echo(lines[i]) {
+            output.push(lines[i]);
+            i += 1;
+            continue;
+        }
+
+        // Extend the run of echoed lines (blank lines inside the run count)
+        let mut end = i;
+        while end < lines.len() && is_echo(lines[end]) {
+            end += 1;
+        }
+        while end > i && lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+
+        let run_chars: usize = lines[i..end].iter().map(|line| line.trim().len()).sum();
+        if run_chars < MIN_ECHO_CHARS {
+            output.extend(&lines[i..end]);
+            i = end;
+            continue;
+        }
+
+        // Swallow a code fence wrapped around the echoed block
+        let fenced_before = output
+            .last()
+            .is_some_and(|line| line.trim_start().starts_with("```"));
+        let fenced_after = lines
+            .get(end)
+            .is_some_and(|line| line.trim() == "```");
+        if fenced_before && fenced_after {
+            output.pop();
+            end += 1;
+        }
+
+        output.push(ECHO_PLACEHOLDER);
+        collapsed = true;
+        i = end;
+    }
+
+    collapsed.then(|| output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_output() -> String {
+        (1..=20)
+            .map(|i| format!("src/tools/file_number_{}.rs", i))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_collapse_large_echo() {
+        let output = tool_output();
+        let answer = format!("Here are the files:\n```\n{}\n```\nThat's all of them.", output);
+
+        let collapsed = collapse_echoes(&answer, &[&output]).unwrap();
+        assert_eq!(
+            collapsed,
+            format!("Here are the files:\n{}\nThat's all of them.", ECHO_PLACEHOLDER)
+        );
+    }
+
+    #[test]
+    fn test_short_quotes_are_kept() {
+        let output = tool_output();
+        let answer = "The first file is:\nsrc/tools/file_number_1.rs\nand it's small.";
+        assert!(collapse_echoes(answer, &[&output]).is_none());
+    }
+
+    #[test]
+    fn test_no_tool_outputs() {
+        assert!(collapse_echoes("anything", &[]).is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::ui::{Approval, Frontend, Notice, StreamFragment};
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Progress of a turn run with `Agent::invoke_with_events`
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentEvent {
+    /// Part of the model's reasoning, as it is generated
+    ThinkingDelta(String),
+    /// Part of the reply. Models that don't stream send the whole reply as one delta.
+    ContentDelta(String),
+    ToolStart {
+        name: String,
+        /// Arguments as pretty-printed JSON
+        arguments: Option<String>,
+    },
+    ToolResult {
+        name: String,
+        result: String,
+    },
+    ToolError(String),
+    /// A warning or status message, such as a compaction or cost note
+    Notice(Notice, String),
+    /// A line of a subagent's reply or tool calls
+    SubagentOutput {
+        /// The subagent's task, e.g. `task#3`
+        task: String,
+        line: String,
+    },
+    /// The turn finished; carries the final answer
+    Done(String),
+}
+
+/// Frontend that turns what the agent loop shows into events. Questions
+/// for the user (prompts, confirmations, approvals) go to `fallback`.
+#[derive(Debug)]
+pub(crate) struct EventFrontend {
+    events: UnboundedSender<AgentEvent>,
+    fallback: Arc<dyn Frontend>,
+}
+
+impl EventFrontend {
+    pub(crate) fn new(events: UnboundedSender<AgentEvent>, fallback: Arc<dyn Frontend>) -> Self {
+        Self { events, fallback }
+    }
+
+    fn send(&self, event: AgentEvent) {
+        // 接收端已结束时丢弃事件
+        self.events.send(event).ok();
+    }
+}
+
+impl Frontend for EventFrontend {
+    fn prompt(&self) -> Option<String> {
+        self.fallback.prompt()
+    }
+
+    fn stream(&self, fragment: StreamFragment<'_>) {
+        match fragment {
+            StreamFragment::Thinking(text) => self.send(AgentEvent::ThinkingDelta(text.to_string())),
+            StreamFragment::Content(text) => self.send(AgentEvent::ContentDelta(text.to_string())),
+            StreamFragment::End => {}
+        }
+    }
+
+    fn response(&self, content: &str) {
+        self.send(AgentEvent::ContentDelta(content.to_string()));
+    }
+
+    fn citations(&self, _citations: &[String]) {}
+
+    fn tool_start(&self, name: &str, args: Option<&str>) {
+        self.send(AgentEvent::ToolStart {
+            name: name.to_string(),
+            arguments: args.map(str::to_string),
+        });
+    }
+
+    fn tool_heartbeat(&self, _elapsed: &str, _detail: Option<&str>) {}
+
+    fn tool_result(&self, name: &str, result: &str) {
+        self.send(AgentEvent::ToolResult {
+            name: name.to_string(),
+            result: result.to_string(),
+        });
+    }
+
+    fn tool_error(&self, error: &str) {
+        self.send(AgentEvent::ToolError(error.to_string()));
+    }
+
+    fn confirm(&self, question: &str) -> bool {
+        self.fallback.confirm(question)
+    }
+
+    fn approve(&self, request: &str) -> Approval {
+        self.fallback.approve(request)
+    }
+
+    fn notify(&self, notice: Notice, message: &str) {
+        self.send(AgentEvent::Notice(notice, message.to_string()));
+    }
+
+    fn redirect(&self) -> Option<String> {
+        self.fallback.redirect()
+    }
+
+    fn subagent_output(&self, task: &str, line: &str) {
+        self.send(AgentEvent::SubagentOutput {
+            task: task.to_string(),
+            line: line.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::SilentFrontend;
+
+    #[test]
+    fn test_fragments_become_events() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let frontend = EventFrontend::new(sender, SilentFrontend::shared());
+        frontend.stream(StreamFragment::Thinking("hmm"));
+        frontend.stream(StreamFragment::Content("Hel"));
+        frontend.stream(StreamFragment::End);
+        frontend.tool_heartbeat("5s", None);
+        frontend.response("Hello");
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                AgentEvent::ThinkingDelta("hmm".to_string()),
+                AgentEvent::ContentDelta("Hel".to_string()),
+                AgentEvent::ContentDelta("Hello".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::config::ToolExamplesConfig;
+use serde_json::{json, Value};
+
+/// Canonical example arguments for the built-in tools
+fn builtin() -> Vec<(&'static str, Value)> {
+    vec![
+        ("read", json!({"file_path": "src/main.rs"})),
+        ("glob", json!({"pattern": "**/*.rs"})),
+        ("ls", json!({"path": "src", "depth": 2})),
+        ("grep", json!({"pattern": "fn main", "path": "src", "output_mode": "content"})),
+        ("bash", json!({"command": "cargo test"})),
+        ("write", json!({"file_path": "notes.txt", "content": "first line\nsecond line\n"})),
+        (
+            "edit",
+            json!({"file_path": "src/lib.rs", "old_string": "let x = 1;", "new_string": "let x = 2;"}),
+        ),
+        ("web_fetch", json!({"url": "https://example.com"})),
+        ("deps", json!({"path": "Cargo.toml", "audit": true})),
+        ("data_preview", json!({"file_path": "data/sales.csv", "rows": 5})),
+        ("compare_files", json!({"expected": "tests/golden/report.txt", "actual": "target/report.txt"})),
+        ("delete", json!({"path": "target/tmp-output.log"})),
+        ("move", json!({"source": "src/util.rs", "destination": "src/utils/mod.rs"})),
+        (
+            "todo_write",
+            json!({"todos": [{"content": "Run tests", "status": "in_progress", "activeForm": "Running tests"}]}),
+        ),
+        (
+            "task",
+            json!({
+                "subagent_type": "explore",
+                "description": "Find config loading",
+                "prompt": "Find where .ariste/settings.json is read and summarize the fields."
+            }),
+        ),
+    ]
+}
+
+/// System note showing example calls for the tools in `available`, or
+/// `None` when exemplars are off or none apply. Configured examples for a
+/// tool replace the built-in one.
+pub fn system_note(config: &ToolExamplesConfig, available: &[String]) -> Option<String> {
+    if !config.enabled.unwrap_or(true) {
+        return None;
+    }
+
+    let mut examples: Vec<(String, Value)> = Vec::new();
+    if config.builtin.unwrap_or(true) {
+        for (tool, arguments) in builtin() {
+            if !config.examples.contains_key(tool) {
+                examples.push((tool.to_string(), arguments));
+            }
+        }
+    }
+    for (tool, calls) in &config.examples {
+        for arguments in calls {
+            examples.push((tool.clone(), arguments.clone()));
+        }
+    }
+
+    let lines: Vec<String> = available
+        .iter()
+        .flat_map(|tool| {
+            examples
+                .iter()
+                .filter(move |(name, _)| name == tool)
+                .map(|(name, arguments)| format!(r#"{{"name": "{}", "arguments": {}}}"#, name, arguments))
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "Call tools through the tool-calling interface with a JSON object of arguments, exactly like these examples:\n{}",
+        lines.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn available(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_builtin_examples_for_available_tools() {
+        let note = system_note(&ToolExamplesConfig::default(), &available(&["read", "glob"])).unwrap();
+        assert!(note.contains(r#"{"name": "read", "arguments": {"file_path":"src/main.rs"}}"#));
+        assert!(note.contains(r#""name": "glob""#));
+        assert!(!note.contains(r#""name": "bash""#));
+    }
+
+    #[test]
+    fn test_configured_examples_replace_builtin() {
+        let config = ToolExamplesConfig {
+            enabled: None,
+            builtin: None,
+            examples: HashMap::from([("read".to_string(), vec![json!({"file_path": "README.md"})])]),
+        };
+        let note = system_note(&config, &available(&["read"])).unwrap();
+        assert!(note.contains("README.md"));
+        assert!(!note.contains("src/main.rs"));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let config = ToolExamplesConfig {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        assert!(system_note(&config, &available(&["read"])).is_none());
+
+        let no_builtin = ToolExamplesConfig {
+            builtin: Some(false),
+            ..Default::default()
+        };
+        assert!(system_note(&no_builtin, &available(&["read"])).is_none());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::config::{ExperimentConfig, VariantConfig};
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Outcome of one turn run under an experiment variant
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Trial {
+    pub experiment: String,
+    pub variant: String,
+    pub prompt: String,
+    pub success: bool,
+    pub tool_calls: usize,
+    pub duration_ms: u64,
+}
+
+/// Aggregated metrics for one variant
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantSummary {
+    pub variant: String,
+    pub trials: usize,
+    pub successes: usize,
+    pub avg_tool_calls: f64,
+    pub avg_duration: Duration,
+}
+
+impl std::fmt::Display for VariantSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} succeeded, {:.1} tool calls, {:.2}s per turn",
+            self.variant,
+            self.successes,
+            self.trials,
+            self.avg_tool_calls,
+            self.avg_duration.as_secs_f64()
+        )
+    }
+}
+
+/// Assigns variants to turns alternately and records how each turn went.
+/// Trials are appended to a JSONL log so runs can be compared later.
+#[derive(Debug)]
+pub struct Experiment {
+    config: ExperimentConfig,
+    log: Option<PathBuf>,
+    next: usize,
+    current: Option<usize>,
+    trials: Vec<Trial>,
+}
+
+impl Experiment {
+    pub fn new(config: ExperimentConfig) -> Result<Self, Error> {
+        if config.variants.len() < 2 {
+            return Err(Error::Message(format!(
+                "Experiment '{}' needs at least two variants",
+                config.name
+            )));
+        }
+        Ok(Self {
+            config,
+            log: None,
+            next: 0,
+            current: None,
+            trials: Vec::new(),
+        })
+    }
+
+    pub fn log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log = Some(path.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Pick the variant for the next turn, alternating between variants
+    pub fn assign(&mut self) -> &VariantConfig {
+        let index = self.next;
+        self.next = (self.next + 1) % self.config.variants.len();
+        self.current = Some(index);
+        &self.config.variants[index]
+    }
+
+    /// Variant assigned to the turn in progress
+    pub fn current(&self) -> Option<&VariantConfig> {
+        self.current.map(|index| &self.config.variants[index])
+    }
+
+    /// Record the outcome of the current turn and append it to the log
+    pub async fn record(
+        &mut self,
+        prompt: &str,
+        success: bool,
+        tool_calls: usize,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let Some(variant) = self.current.take() else {
+            return Ok(());
+        };
+        let trial = Trial {
+            experiment: self.config.name.clone(),
+            variant: self.config.variants[variant].name.clone(),
+            prompt: prompt.to_string(),
+            success,
+            tool_calls,
+            duration_ms: duration.as_millis() as u64,
+        };
+
+        if let Some(log) = &self.log {
+            let mut line = serde_json::to_string(&trial)?;
+            line.push('\n');
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log)
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+        }
+
+        self.trials.push(trial);
+        Ok(())
+    }
+
+    /// Per-variant metrics over the trials recorded so far, in variant order
+    pub fn summary(&self) -> Vec<VariantSummary> {
+        self.config
+            .variants
+            .iter()
+            .map(|variant| {
+                let trials: Vec<&Trial> = self
+                    .trials
+                    .iter()
+                    .filter(|trial| trial.variant == variant.name)
+                    .collect();
+                let count = trials.len();
+                let total_ms: u64 = trials.iter().map(|trial| trial.duration_ms).sum();
+                let total_tools: usize = trials.iter().map(|trial| trial.tool_calls).sum();
+                VariantSummary {
+                    variant: variant.name.clone(),
+                    trials: count,
+                    successes: trials.iter().filter(|trial| trial.success).count(),
+                    avg_tool_calls: if count == 0 { 0.0 } else { total_tools as f64 / count as f64 },
+                    avg_duration: Duration::from_millis(if count == 0 { 0 } else { total_ms / count as u64 }),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ExperimentConfig {
+        ExperimentConfig {
+            name: "terse".to_string(),
+            variants: vec![
+                VariantConfig {
+                    name: "control".to_string(),
+                    system_prompt: None,
+                    model: None,
+                },
+                VariantConfig {
+                    name: "terse".to_string(),
+                    system_prompt: Some("Answer in one sentence.".to_string()),
+                    model: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_needs_two_variants() {
+        let mut config = config();
+        config.variants.pop();
+        assert!(Experiment::new(config).is_err());
+    }
+
+    #[test]
+    fn test_assign_alternates() {
+        let mut experiment = Experiment::new(config()).unwrap();
+        assert!(experiment.current().is_none());
+        assert_eq!(experiment.assign().name, "control");
+        assert_eq!(experiment.assign().name, "terse");
+        assert_eq!(experiment.assign().name, "control");
+        assert_eq!(experiment.current().unwrap().name, "control");
+    }
+
+    #[tokio::test]
+    async fn test_record_and_summary() {
+        let log = "/tmp/test_experiment_log.jsonl";
+        tokio::fs::remove_file(log).await.ok();
+
+        let mut experiment = Experiment::new(config()).unwrap().log(log);
+        experiment.assign();
+        experiment.record("a", true, 2, Duration::from_millis(100)).await.unwrap();
+        experiment.assign();
+        experiment.record("b", false, 0, Duration::from_millis(50)).await.unwrap();
+        experiment.assign();
+        experiment.record("c", true, 4, Duration::from_millis(300)).await.unwrap();
+
+        let summary = experiment.summary();
+        assert_eq!(summary[0].trials, 2);
+        assert_eq!(summary[0].successes, 2);
+        assert_eq!(summary[0].avg_tool_calls, 3.0);
+        assert_eq!(summary[0].avg_duration, Duration::from_millis(200));
+        assert_eq!(summary[1].trials, 1);
+        assert_eq!(summary[1].successes, 0);
+        assert_eq!(
+            summary[0].to_string(),
+            "control: 2/2 succeeded, 3.0 tool calls, 0.20s per turn"
+        );
+
+        let logged = tokio::fs::read_to_string(log).await.unwrap();
+        let trials: Vec<Trial> = logged.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(trials.len(), 3);
+        assert_eq!(trials[1].variant, "terse");
+        assert!(!trials[1].success);
+
+        tokio::fs::remove_file(log).await.ok();
+    }
+}
@@ -0,0 +1,158 @@
+use crate::agent::context::{tool_names, tool_result_calls};
+use crate::agent::message::Message;
+use std::collections::{HashMap, HashSet};
+
+/// Which messages `/forget` removes. Indices are positions in the history,
+/// as shown by `/context`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Index(usize),
+    /// Inclusive range
+    Range(usize, usize),
+    /// Every result of the named tool
+    Tool(String),
+}
+
+impl Selector {
+    pub fn parse(arg: &str) -> Option<Self> {
+        let arg = arg.trim();
+        if arg.is_empty() {
+            return None;
+        }
+        if let Ok(index) = arg.parse() {
+            return Some(Selector::Index(index));
+        }
+        if let Some((start, end)) = arg.split_once('-')
+            && let (Ok(start), Ok(end)) = (start.trim().parse(), end.trim().parse())
+        {
+            return (start <= end).then_some(Selector::Range(start, end));
+        }
+        arg.chars()
+            .all(|c| c.is_alphanumeric() || c == '_')
+            .then(|| Selector::Tool(arg.to_string()))
+    }
+}
+
+/// Remove the selected messages and return how many were removed. Tool
+/// calls and their results go together: forgetting a result drops the call
+/// from its assistant message, and forgetting an assistant message drops
+/// the results of its calls.
+pub fn forget(messages: &mut Vec<Message>, selector: &Selector) -> usize {
+    let links = tool_result_calls(messages);
+    let mut selected: HashSet<usize> = match selector {
+        Selector::Index(index) => [*index].into_iter().filter(|i| *i < messages.len()).collect(),
+        Selector::Range(start, end) => (*start..=*end).filter(|i| *i < messages.len()).collect(),
+        Selector::Tool(tool) => tool_names(messages)
+            .into_iter()
+            .filter(|(_, name)| name == tool)
+            .map(|(index, _)| index)
+            .collect(),
+    };
+
+    // 删除助手消息时一并删除其工具结果
+    for (result, (assistant, _)) in &links {
+        if selected.contains(assistant) {
+            selected.insert(*result);
+        }
+    }
+
+    // 删除工具结果时，从助手消息中移除对应的调用
+    let mut dropped_calls: HashMap<usize, HashSet<usize>> = HashMap::new();
+    for (result, (assistant, call)) in &links {
+        if selected.contains(result) && !selected.contains(assistant) {
+            dropped_calls.entry(*assistant).or_default().insert(*call);
+        }
+    }
+
+    let before = messages.len();
+    let old = std::mem::take(messages);
+    for (index, mut message) in old.into_iter().enumerate() {
+        if selected.contains(&index) {
+            continue;
+        }
+        if let Some(calls) = dropped_calls.get(&index)
+            && let Message::Assistant { content, tool_calls } = &mut message
+        {
+            let mut position = 0;
+            tool_calls.retain(|_| {
+                position += 1;
+                !calls.contains(&(position - 1))
+            });
+            if tool_calls.is_empty() && content.trim().is_empty() {
+                continue;
+            }
+        }
+        messages.push(message);
+    }
+    before - messages.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(role: &str, content: &str) -> Message {
+        match role {
+            "user" => Message::user(content),
+            "tool" => Message::tool(None, None, content),
+            _ => Message::assistant(content),
+        }
+    }
+
+    fn history() -> Vec<Message> {
+        let calls = Message::assistant_with_tools(
+            "",
+            vec![
+                json!({"function": {"name": "read", "arguments": {"file_path": "Cargo.lock"}}}),
+                json!({"function": {"name": "glob", "arguments": {"pattern": "*"}}}),
+            ],
+        );
+        vec![
+            message("user", "look around"),
+            calls,
+            message("tool", "huge lockfile"),
+            message("tool", "a.rs"),
+            message("assistant", "done"),
+        ]
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Selector::parse("3"), Some(Selector::Index(3)));
+        assert_eq!(Selector::parse("2-4"), Some(Selector::Range(2, 4)));
+        assert_eq!(Selector::parse("4-2"), None);
+        assert_eq!(Selector::parse("read"), Some(Selector::Tool("read".to_string())));
+        assert_eq!(Selector::parse(""), None);
+        assert_eq!(Selector::parse("a b"), None);
+    }
+
+    #[test]
+    fn test_forget_tool_result_drops_its_call() {
+        let mut messages = history();
+        assert_eq!(forget(&mut messages, &Selector::Tool("read".to_string())), 1);
+
+        assert_eq!(messages.len(), 4);
+        let calls = messages[1].tool_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0]["function"]["name"], "glob");
+        assert_eq!(messages[2].content(), "a.rs");
+    }
+
+    #[test]
+    fn test_forget_last_result_drops_empty_assistant() {
+        let mut messages = history();
+        assert_eq!(forget(&mut messages, &Selector::Range(2, 3)), 3);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content(), "done");
+    }
+
+    #[test]
+    fn test_forget_assistant_drops_results() {
+        let mut messages = history();
+        assert_eq!(forget(&mut messages, &Selector::Index(1)), 3);
+        assert!(messages.iter().all(|m| !m.is_tool()));
+
+        assert_eq!(forget(&mut messages, &Selector::Index(99)), 0);
+    }
+}
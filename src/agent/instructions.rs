@@ -0,0 +1,65 @@
+use std::path::Path;
+
+/// Project instruction files, relative to the primary root, in order of preference
+pub const INSTRUCTION_FILES: &[&str] = &[".ariste/ARISTE.md", "AGENTS.md", "CLAUDE.md"];
+
+/// Largest instruction file (in characters) included in full
+const MAX_INSTRUCTION_CHARS: usize = 20_000;
+
+/// Instructions the project gives the agent, read from the first
+/// instruction file found at startup
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectInstructions {
+    /// File name as listed in `INSTRUCTION_FILES`
+    pub file: &'static str,
+    pub content: String,
+}
+
+impl ProjectInstructions {
+    /// Read the first non-empty instruction file under `root`
+    pub fn load(root: &Path) -> Option<Self> {
+        INSTRUCTION_FILES.iter().find_map(|file| {
+            let content = std::fs::read_to_string(root.join(file)).ok()?;
+            let content = content.trim();
+            if content.is_empty() {
+                return None;
+            }
+            let content = match content.char_indices().nth(MAX_INSTRUCTION_CHARS) {
+                Some((end, _)) => format!("{}\n... (truncated)", &content[..end]),
+                None => content.to_string(),
+            };
+            Some(Self { file, content })
+        })
+    }
+
+    /// Text included in the request
+    pub fn render(&self) -> String {
+        format!("Project instructions from `{}`:\n\n{}", self.file, self.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_prefers_ariste_file() {
+        let root = std::env::temp_dir().join(format!("ariste_instructions_{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".ariste")).unwrap();
+        assert_eq!(ProjectInstructions::load(&root), None);
+
+        std::fs::write(root.join("AGENTS.md"), "Run `make check` before committing.\n").unwrap();
+        let loaded = ProjectInstructions::load(&root).unwrap();
+        assert_eq!(loaded.file, "AGENTS.md");
+        assert_eq!(loaded.content, "Run `make check` before committing.");
+
+        // An empty file is skipped; a non-empty ARISTE.md wins
+        std::fs::write(root.join(".ariste/ARISTE.md"), "  \n").unwrap();
+        assert_eq!(ProjectInstructions::load(&root).unwrap().file, "AGENTS.md");
+        std::fs::write(root.join(".ariste/ARISTE.md"), "Use tabs.").unwrap();
+        let loaded = ProjectInstructions::load(&root).unwrap();
+        assert_eq!(loaded.render(), "Project instructions from `.ariste/ARISTE.md`:\n\nUse tabs.");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
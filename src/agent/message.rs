@@ -1,11 +1,221 @@
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Message {
-    pub role: String,
-    pub content: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_calls: Option<Vec<serde_json::Value>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_call_id: Option<String>,
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// One entry of the conversation. On the wire (and in saved sessions) it is
+/// the Ollama chat format: `{"role", "content", "tool_calls"?, "tool_call_id"?, "tool_name"?}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    System {
+        content: String,
+    },
+    User {
+        content: String,
+    },
+    /// A reply; `tool_calls` is empty for a final answer
+    Assistant {
+        content: String,
+        tool_calls: Vec<Value>,
+    },
+    /// The result of a tool call. `id` links it to the call; histories saved
+    /// before tools were named have no `name`. `images` (base64) are files
+    /// the call read for a vision model.
+    Tool {
+        content: String,
+        id: Option<String>,
+        name: Option<String>,
+        images: Vec<String>,
+    },
+}
+
+impl Message {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::System { content: content.into() }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::User { content: content.into() }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::assistant_with_tools(content, Vec::new())
+    }
+
+    pub fn assistant_with_tools(content: impl Into<String>, tool_calls: Vec<Value>) -> Self {
+        Self::Assistant {
+            content: content.into(),
+            tool_calls,
+        }
+    }
+
+    pub fn tool(id: Option<String>, name: Option<String>, content: impl Into<String>) -> Self {
+        Self::Tool {
+            content: content.into(),
+            id,
+            name,
+            images: Vec::new(),
+        }
+    }
+
+    /// A tool result with images attached; other messages are unchanged
+    pub fn with_images(mut self, attached: Vec<String>) -> Self {
+        if let Self::Tool { images, .. } = &mut self {
+            *images = attached;
+        }
+        self
+    }
+
+    pub fn role(&self) -> &'static str {
+        match self {
+            Self::System { .. } => "system",
+            Self::User { .. } => "user",
+            Self::Assistant { .. } => "assistant",
+            Self::Tool { .. } => "tool",
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        match self {
+            Self::System { content }
+            | Self::User { content }
+            | Self::Assistant { content, .. }
+            | Self::Tool { content, .. } => content,
+        }
+    }
+
+    /// The calls of an assistant reply; empty for every other message
+    pub fn tool_calls(&self) -> &[Value] {
+        match self {
+            Self::Assistant { tool_calls, .. } => tool_calls,
+            _ => &[],
+        }
+    }
+
+    /// The call a tool result answers
+    pub fn tool_call_id(&self) -> Option<&str> {
+        match self {
+            Self::Tool { id, .. } => id.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn tool_name(&self) -> Option<&str> {
+        match self {
+            Self::Tool { name, .. } => name.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Images attached to a tool result, base64
+    pub fn images(&self) -> &[String] {
+        match self {
+            Self::Tool { images, .. } => images,
+            _ => &[],
+        }
+    }
+
+    pub fn is_user(&self) -> bool {
+        matches!(self, Self::User { .. })
+    }
+
+    pub fn is_tool(&self) -> bool {
+        matches!(self, Self::Tool { .. })
+    }
+}
+
+/// The flat wire shape of a message
+#[derive(Serialize, Deserialize)]
+struct Wire<'a> {
+    role: Cow<'a, str>,
+    #[serde(default)]
+    content: Cow<'a, str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Cow<'a, [Value]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<Cow<'a, str>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<Cow<'a, str>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    images: Option<Cow<'a, [String]>>,
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let calls = self.tool_calls();
+        Wire {
+            role: self.role().into(),
+            content: self.content().into(),
+            tool_calls: (!calls.is_empty()).then_some(calls.into()),
+            tool_call_id: self.tool_call_id().map(Into::into),
+            tool_name: self.tool_name().map(Into::into),
+            images: (!self.images().is_empty()).then(|| self.images().into()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = Wire::deserialize(deserializer)?;
+        let content = wire.content.into_owned();
+        Ok(match wire.role.as_ref() {
+            "system" => Self::System { content },
+            "user" => Self::User { content },
+            "assistant" => Self::Assistant {
+                content,
+                tool_calls: wire.tool_calls.map(Cow::into_owned).unwrap_or_default(),
+            },
+            "tool" => Self::Tool {
+                content,
+                id: wire.tool_call_id.map(Cow::into_owned),
+                name: wire.tool_name.map(Cow::into_owned),
+                images: wire.images.map(Cow::into_owned).unwrap_or_default(),
+            },
+            role => return Err(serde::de::Error::unknown_variant(role, &["system", "user", "assistant", "tool"])),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_wire_format() {
+        let call = json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}});
+        let messages = vec![
+            Message::system("be brief"),
+            Message::assistant_with_tools("", vec![call.clone()]),
+            Message::tool(Some("call_1".to_string()), Some("read".to_string()), "fn main() {}"),
+            Message::assistant("done"),
+        ];
+        assert_eq!(
+            serde_json::to_value(&messages).unwrap(),
+            json!([
+                {"role": "system", "content": "be brief"},
+                {"role": "assistant", "content": "", "tool_calls": [call]},
+                {"role": "tool", "content": "fn main() {}", "tool_call_id": "call_1", "tool_name": "read"},
+                {"role": "assistant", "content": "done"},
+            ])
+        );
+
+        // 旧会话：没有 tool_name，字段可能为 null 或空列表
+        let old: Vec<Message> = serde_json::from_value(json!([
+            {"role": "user", "content": "hi", "tool_calls": null},
+            {"role": "assistant", "content": "", "tool_calls": []},
+            {"role": "tool", "content": "ok", "tool_call_id": "call_1"},
+        ]))
+        .unwrap();
+        assert_eq!(old[0], Message::user("hi"));
+        assert_eq!(old[1], Message::assistant(""));
+        assert_eq!(old[2], Message::tool(Some("call_1".to_string()), None, "ok"));
+
+        // 图片以 Ollama 的 images 字段保存
+        let image = Message::tool(None, Some("read".to_string()), "logo.png").with_images(vec!["iVBORw0KGgo=".to_string()]);
+        let value = serde_json::to_value(&image).unwrap();
+        assert_eq!(value["images"], json!(["iVBORw0KGgo="]));
+        assert_eq!(serde_json::from_value::<Message>(value).unwrap(), image);
+        assert!(serde_json::from_value::<Message>(json!({"role": "robot", "content": ""})).is_err());
+    }
 }
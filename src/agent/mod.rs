@@ -1,6 +1,40 @@
+#[allow(clippy::module_inception)]
 mod agent;
+pub mod analytics;
+mod compact;
+pub mod consensus;
+pub mod context;
+mod echo;
+mod events;
+mod exemplars;
+mod experiment;
+mod forget;
+mod instructions;
 mod message;
+pub mod permissions;
+mod pins;
+mod rag;
+mod recall;
+mod redirect;
+pub mod session;
+mod subagent_view;
+mod subagents;
+pub mod tasks;
+pub mod timing;
+pub mod transcript;
+mod trim;
+mod turn;
+mod usage;
+mod verify;
 
 #[allow(unused_imports)]
-pub use agent::{Agent, SubAgentType};
+pub use agent::{
+    Agent, ParallelTask, SubAgentExecution, SubAgentIdCounter, SubAgentRun, SubAgentStatus, SubAgentTask, SubAgentType, Truncation,
+};
+pub use events::AgentEvent;
+pub use instructions::{INSTRUCTION_FILES, ProjectInstructions};
 pub use message::Message;
+pub use redirect::RedirectToken;
+pub use subagents::{SubAgentProfile, SubAgentRegistry};
+pub use tasks::TaskManager;
+pub use usage::{TurnUsage, UsageReport};
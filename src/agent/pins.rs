@@ -0,0 +1,104 @@
+use crate::workspace::Workspace;
+
+/// Largest pinned file (in characters) included in full
+const MAX_PINNED_FILE_CHARS: usize = 20_000;
+
+/// Content kept in every request regardless of history changes (the
+/// `/pin` command). Pins live outside the history, so `/forget`, `/clear`
+/// and compaction never evict them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pin {
+    /// A file, re-read on every request so edits show up
+    File(String),
+    Note(String),
+}
+
+impl Pin {
+    /// A file pin when `arg` names an existing file, a note otherwise
+    pub fn parse(arg: &str, workspace: &Workspace) -> Option<Self> {
+        let arg = arg.trim();
+        if arg.is_empty() {
+            return None;
+        }
+        Some(if workspace.resolve(arg).is_file() {
+            Pin::File(arg.to_string())
+        } else {
+            Pin::Note(arg.to_string())
+        })
+    }
+
+    /// One-line description for listings
+    pub fn label(&self) -> String {
+        match self {
+            Pin::File(path) => format!("file {}", path),
+            Pin::Note(note) => format!("note \"{}\"", note),
+        }
+    }
+
+    /// Text included in the request
+    pub fn render(&self, workspace: &Workspace) -> String {
+        match self {
+            Pin::Note(note) => format!("Pinned note: {}", note),
+            Pin::File(path) => match std::fs::read_to_string(workspace.resolve(path)) {
+                Ok(content) => {
+                    let content = match content.char_indices().nth(MAX_PINNED_FILE_CHARS) {
+                        Some((end, _)) => format!("{}\n... (truncated)", &content[..end]),
+                        None => content,
+                    };
+                    format!("Pinned file `{}`:\n```\n{}\n```", path, content.trim_end())
+                }
+                Err(e) => format!("Pinned file `{}` could not be read: {}", path, e),
+            },
+        }
+    }
+}
+
+/// System message content for all pins, or `None` when nothing is pinned
+pub fn render_all(pins: &[Pin], workspace: &Workspace) -> Option<String> {
+    if pins.is_empty() {
+        return None;
+    }
+    let blocks: Vec<String> = pins.iter().map(|pin| pin.render(workspace)).collect();
+    Some(format!(
+        "The user pinned the following context; treat it as authoritative.\n\n{}",
+        blocks.join("\n\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_or_note() {
+        let file = "/tmp/test_pin_file.md";
+        std::fs::write(file, "# API contract\nGET /v1/items").unwrap();
+        let workspace = Workspace::default();
+
+        assert_eq!(Pin::parse(file, &workspace), Some(Pin::File(file.to_string())));
+        assert_eq!(
+            Pin::parse("never touch prod", &workspace),
+            Some(Pin::Note("never touch prod".to_string()))
+        );
+        assert_eq!(Pin::parse("  ", &workspace), None);
+
+        std::fs::remove_file(file).ok();
+    }
+
+    #[test]
+    fn test_render_all() {
+        let file = "/tmp/test_pin_render.md";
+        std::fs::write(file, "GET /v1/items\n").unwrap();
+        let workspace = Workspace::default();
+
+        let pins = vec![Pin::File(file.to_string()), Pin::Note("use snake_case".to_string())];
+        let rendered = render_all(&pins, &workspace).unwrap();
+        assert!(rendered.contains("```\nGET /v1/items\n```"));
+        assert!(rendered.ends_with("Pinned note: use snake_case"));
+        assert!(render_all(&[], &workspace).is_none());
+
+        // Files are re-read each time
+        std::fs::remove_file(file).ok();
+        assert!(pins[0].render(&workspace).contains("could not be read"));
+    }
+}
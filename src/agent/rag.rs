@@ -0,0 +1,116 @@
+use crate::agent::message::Message;
+use crate::config::RagConfig;
+use crate::error::Error;
+use crate::index::{KnowledgeBase, SearchHit, SemanticIndex};
+use std::collections::HashSet;
+
+/// Chunks added per turn unless `rag.top_k` says otherwise
+const DEFAULT_TOP_K: usize = 5;
+/// Characters of retrieved text per turn unless `rag.max_chars` says otherwise
+const DEFAULT_MAX_CHARS: usize = 12_000;
+
+/// The chunks of the code index and the knowledge base closest to
+/// `query`, best first. Both are searched and their results interleaved
+/// by score; docs of the project can be in both, and are kept once.
+pub async fn retrieve(
+    index: Option<&SemanticIndex>,
+    knowledge: Option<&KnowledgeBase>,
+    query: &str,
+    settings: Option<&RagConfig>,
+) -> Result<Vec<SearchHit>, Error> {
+    let top_k = settings.and_then(|settings| settings.top_k).unwrap_or(DEFAULT_TOP_K).max(1);
+    let mut hits = Vec::new();
+    if let Some(index) = index {
+        hits.extend(index.search(query, top_k, None).await?);
+    }
+    if let Some(knowledge) = knowledge {
+        hits.extend(knowledge.search(query, top_k, None).await?);
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut seen = HashSet::new();
+    hits.retain(|hit| seen.insert(hit.source.clone()));
+    hits.truncate(top_k);
+    Ok(hits)
+}
+
+/// System note holding `hits`, as many as fit in `rag.max_chars`; `None`
+/// when there are none
+pub fn render(hits: &[SearchHit], settings: Option<&RagConfig>) -> Option<String> {
+    let budget = settings.and_then(|settings| settings.max_chars).unwrap_or(DEFAULT_MAX_CHARS);
+    let mut used = 0;
+    let mut sections = Vec::new();
+    for hit in hits {
+        let section = format!("--- {} ---\n{}", hit.source, hit.text);
+        // 至少保留一个，即使超出预算
+        if !sections.is_empty() && used + section.len() > budget {
+            break;
+        }
+        used += section.len();
+        sections.push(section);
+    }
+    if sections.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Retrieved automatically for the user's latest message; it may be incomplete or beside the point, so check it before relying on it:\n\n{}",
+        sections.join("\n\n")
+    ))
+}
+
+/// Put `note` just before the last user message, where it reads as context
+/// for that message
+pub fn insert(messages: &mut Vec<Message>, note: &str) {
+    let at = messages.iter().rposition(Message::is_user).unwrap_or(messages.len());
+    messages.insert(at, Message::system(note));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::knowledge::DOCS_INDEX_FILE;
+    use crate::index::semantic::{CODE_INDEX_FILE, WordEmbedder};
+
+    #[tokio::test]
+    async fn test_retrieve_code_and_docs() {
+        let dir = std::env::temp_dir().join("ariste_test_rag");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/retry.rs"), "/// Back off after a rate limit\nfn backoff(attempt: u32) -> u64 {\n    500 << attempt\n}\n").unwrap();
+        std::fs::write(dir.join("src/spinner.rs"), "/// Frames of the spinner\nconst FRAMES: &[&str] = &[];\n").unwrap();
+        std::fs::write(dir.join("limits.md"), "# Rate limit\n\nThe API allows 100 requests per minute before the rate limit.\n").unwrap();
+
+        let index = SemanticIndex::new(&dir, dir.join(CODE_INDEX_FILE), Box::new(WordEmbedder));
+        index.rebuild().await.unwrap();
+        let knowledge = KnowledgeBase::new(&dir, dir.join(DOCS_INDEX_FILE), Box::new(WordEmbedder));
+        knowledge.ingest(dir.join("limits.md").to_str().unwrap()).await.unwrap();
+
+        let settings = RagConfig { top_k: Some(2), ..Default::default() };
+        let hits = retrieve(Some(&index), Some(&knowledge), "rate limit", Some(&settings)).await.unwrap();
+        // limits.md is in both indexes and comes back once
+        let mut sources: Vec<&str> = hits.iter().map(|hit| hit.source.as_str()).collect();
+        sources.sort();
+        assert_eq!(sources, ["limits.md:1-3", "src/retry.rs:1-4"]);
+
+        let note = render(&hits, None).unwrap();
+        assert!(note.contains("--- src/retry.rs:1-4 ---\n/// Back off"));
+        // Past the budget only the best chunk is kept
+        let small = RagConfig { max_chars: Some(10), ..Default::default() };
+        assert_eq!(render(&hits, Some(&small)).unwrap().matches("--- ").count(), 1);
+        assert!(render(&[], None).is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_insert_before_latest_user_message() {
+        let mut messages = vec![
+            Message::system("You are helpful."),
+            Message::user("hi"),
+            Message::assistant("hello"),
+            Message::user("where are retries?"),
+            Message::assistant("Let me look"),
+        ];
+        insert(&mut messages, "retrieved");
+        assert_eq!(messages[3], Message::system("retrieved"));
+        assert_eq!(messages[4], Message::user("where are retries?"));
+    }
+}
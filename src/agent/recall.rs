@@ -0,0 +1,265 @@
+use crate::agent::message::Message;
+use crate::agent::session::{self, Session};
+use crate::config::AgentConfig;
+use crate::error::Error;
+use crate::index::chunk::{Chunk, content_hash};
+use crate::index::rerank::{self, Reranker, SearchHit};
+use crate::index::semantic::{RERANK_CANDIDATES, embed_chunks};
+use crate::index::store::{IndexStore, IndexedFile};
+use crate::llm::{EmbeddingProvider, create_embedder};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+/// Where earlier sessions are embedded, under the workspace root
+pub const MEMORY_INDEX_FILE: &str = ".ariste/index/sessions.json";
+/// Characters of an exchange that are embedded and recalled
+const EXCHANGE_CHARS: usize = 4_000;
+
+/// The exchanges of `session` as chunks: each prompt with the last reply
+/// before the next one, numbered from 1 in `start_line`. Tool calls and
+/// their results are left out.
+fn exchanges(session: &Session) -> Vec<Chunk> {
+    let mut pairs: Vec<(&str, &str)> = Vec::new();
+    for message in &session.messages {
+        match message {
+            Message::User { content } => pairs.push((content, "")),
+            Message::Assistant { content, .. } if !content.trim().is_empty() => {
+                if let Some(pair) = pairs.last_mut() {
+                    pair.1 = content;
+                }
+            }
+            _ => {}
+        }
+    }
+    pairs
+        .into_iter()
+        .filter(|(prompt, _)| !prompt.trim().is_empty())
+        .enumerate()
+        .map(|(index, (prompt, answer))| {
+            let text = format!("User: {}\n\nAssistant: {}", prompt.trim(), answer.trim());
+            let text = match text.char_indices().nth(EXCHANGE_CHARS) {
+                Some((end, _)) => format!("{}…", &text[..end]),
+                None => text,
+            };
+            Chunk {
+                path: session.id.clone(),
+                start_line: index + 1,
+                end_line: index + 1,
+                symbol: None,
+                context: None,
+                text,
+            }
+        })
+        .collect()
+}
+
+/// How a hit is shown: `session <id>, exchange <n>` in place of the
+/// `<id>:<n>-<n>` the store names it by
+fn label(source: &str) -> String {
+    match source.rsplit_once(':') {
+        Some((id, lines)) => format!("session {}, exchange {}", id, lines.split('-').next().unwrap_or(lines)),
+        None => format!("session {}", source),
+    }
+}
+
+/// System note holding what `/recall` found, sent with the next turn;
+/// `None` when nothing was found
+pub fn render(hits: &[SearchHit]) -> Option<String> {
+    if hits.is_empty() {
+        return None;
+    }
+    let sections: Vec<String> = hits.iter().map(|hit| format!("--- {} ---\n{}", hit.source, hit.text)).collect();
+    Some(format!(
+        "Recalled from earlier sessions at the user's request (/recall); the code may have changed since, so check it before relying on them:\n\n{}",
+        sections.join("\n\n")
+    ))
+}
+
+/// Earlier sessions of the project, embedded so `/recall` can bring back
+/// what was asked and answered in them. Saved sessions are embedded when
+/// new or changed since the last search; deleted ones are dropped.
+/// Matches are reranked like `docs_search` results.
+#[derive(Debug)]
+pub struct SessionMemory {
+    file: PathBuf,
+    embedder: Box<dyn EmbeddingProvider>,
+    reranker: Option<Reranker>,
+    /// Exchanges `/recall` brings back
+    results: usize,
+    /// Loaded from `file` on first use
+    store: Mutex<Option<IndexStore>>,
+}
+
+impl SessionMemory {
+    pub fn new(file: PathBuf, embedder: Box<dyn EmbeddingProvider>) -> Self {
+        Self {
+            file,
+            embedder,
+            reranker: None,
+            results: rerank::DEFAULT_TOP_K,
+            store: Mutex::new(None),
+        }
+    }
+
+    pub fn reranker(mut self, reranker: Option<Reranker>) -> Self {
+        self.reranker = reranker;
+        self
+    }
+
+    pub fn results(mut self, results: usize) -> Self {
+        self.results = results.max(1);
+        self
+    }
+
+    /// The session memory of the workspace at `root`, embedded with the
+    /// `embedding` model and reranked as the `rerank` settings say
+    pub fn from_config(config: &AgentConfig, root: &Path) -> Result<Self, Error> {
+        Ok(Self::new(root.join(MEMORY_INDEX_FILE), create_embedder(config)?)
+            .reranker(Reranker::from_config(config)?)
+            .results(rerank::top_k(config.rerank.as_ref())))
+    }
+
+    pub fn default_results(&self) -> usize {
+        self.results
+    }
+
+    async fn store(&self) -> Result<MappedMutexGuard<'_, IndexStore>, Error> {
+        let mut store = self.store.lock().await;
+        if store.is_none() {
+            *store = Some(IndexStore::load(&self.file, self.embedder.model()).await?);
+        }
+        Ok(MutexGuard::map(store, |store| store.get_or_insert_with(IndexStore::default)))
+    }
+
+    /// Bring the memory up to date with the sessions saved in `dir`, all
+    /// but `current`, which is still being written; how many exchanges were
+    /// embedded. Sessions that can't be read are skipped.
+    pub async fn sync(&self, dir: &Path, current: &str) -> Result<usize, Error> {
+        let summaries = session::list(dir).await?;
+        let mut embedded = 0;
+        let mut changed = false;
+        for summary in summaries.iter().filter(|summary| summary.id != current) {
+            // 会话每轮保存一次，保存时间（秒，记在 modified 里）未变即内容未变
+            let known = self.store().await?.files.get(&summary.id).map(|file| file.modified);
+            if known == Some(summary.updated) {
+                continue;
+            }
+            let Ok(saved) = session::load(dir, &summary.id).await else {
+                continue;
+            };
+            let chunks = exchanges(&saved);
+            let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+            let hash = content_hash(&texts.join("\n"));
+            let vectors = embed_chunks(self.embedder.as_ref(), &chunks).await?;
+            embedded += chunks.len();
+            let file = IndexedFile { hash, modified: summary.updated, chunks, vectors };
+            self.store().await?.files.insert(summary.id.clone(), file);
+            changed = true;
+        }
+
+        let mut store = self.store().await?;
+        let saved: HashSet<&str> = summaries.iter().map(|summary| summary.id.as_str()).collect();
+        let before = store.files.len();
+        store.files.retain(|id, _| saved.contains(id.as_str()));
+        if changed || store.files.len() != before {
+            store.save(&self.file).await?;
+        }
+        Ok(embedded)
+    }
+
+    /// The `limit` exchanges that best match `query`, leaving out those of
+    /// `current`
+    pub async fn search(&self, query: &str, limit: usize, current: &str) -> Result<Vec<SearchHit>, Error> {
+        let vectors = self.embedder.embed(&[query.to_string()]).await?;
+        let vector = vectors
+            .first()
+            .ok_or_else(|| Error::Message("The embedder returned no vector for the query".to_string()))?;
+        let candidates = if self.reranker.is_some() { limit.saturating_mul(RERANK_CANDIDATES) } else { limit };
+        let store = self.store().await?;
+        // 当前会话可能有旧的嵌入，多取这些条数再滤掉
+        let own = store.files.get(current).map_or(0, |file| file.chunks.len());
+        let mut hits = store.search(vector, candidates.saturating_add(own), None);
+        drop(store);
+        hits.retain(|hit| hit.source.rsplit_once(':').is_none_or(|(id, _)| id != current));
+        hits.truncate(candidates);
+        for hit in &mut hits {
+            hit.source = label(&hit.source);
+        }
+        match &self.reranker {
+            Some(reranker) => reranker.rerank(query, hits, limit).await,
+            None => Ok(hits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::semantic::WordEmbedder;
+
+    #[tokio::test]
+    async fn test_recall_earlier_sessions() {
+        let dir = std::env::temp_dir().join(format!("ariste_test_recall_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let sessions = dir.join("sessions");
+        let retry = vec![
+            Message::user("why do requests fail with 429?"),
+            Message::assistant_with_tools("", vec![serde_json::json!({"id": "call_1", "function": {"name": "read", "arguments": {}}})]),
+            Message::tool(Some("call_1".to_string()), Some("read".to_string()), "fn spinner() {}"),
+            Message::assistant("The rate limit is 100 requests per minute; backoff retries after it"),
+            Message::user("thanks"),
+        ];
+        session::save(&sessions, &Session::new("1", &retry)).await.unwrap();
+        let spinner = vec![Message::user("make the spinner frames smoother"), Message::assistant("Added more spinner frames")];
+        session::save(&sessions, &Session::new("2", &spinner)).await.unwrap();
+        session::save(&sessions, &Session::new("3", &[Message::user("rate limit backoff again")])).await.unwrap();
+
+        let memory = SessionMemory::new(dir.join("index/sessions.json"), Box::new(WordEmbedder));
+        assert_eq!(memory.sync(&sessions, "3").await.unwrap(), 3);
+        let hits = memory.search("rate limit backoff", 1, "3").await.unwrap();
+        assert_eq!(hits[0].source, "session 1, exchange 1");
+        assert!(hits[0].text.starts_with("User: why do requests fail with 429?\n\nAssistant: The rate limit"));
+        // Tool results are not part of the exchange
+        assert!(!hits[0].text.contains("fn spinner"));
+        assert!(render(&hits).unwrap().contains("--- session 1, exchange 1 ---"));
+
+        // Nothing changed, nothing embedded; a deleted session is dropped
+        assert_eq!(memory.sync(&sessions, "3").await.unwrap(), 0);
+        std::fs::remove_file(sessions.join("1.json")).unwrap();
+        memory.sync(&sessions, "3").await.unwrap();
+        let hits = memory.search("rate limit backoff", 5, "3").await.unwrap();
+        assert!(hits.iter().all(|hit| hit.source.starts_with("session 2")));
+
+        // Once another session runs, the one before is recalled too
+        let memory = SessionMemory::new(dir.join("index/sessions.json"), Box::new(WordEmbedder));
+        assert_eq!(memory.sync(&sessions, "4").await.unwrap(), 1);
+        let hits = memory.search("rate limit backoff", 1, "4").await.unwrap();
+        assert_eq!(hits[0].source, "session 3, exchange 1");
+        assert!(render(&[]).is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_recall_is_reranked() {
+        let dir = std::env::temp_dir().join(format!("ariste_test_recall_rerank_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let sessions = dir.join("sessions");
+        let flaky = [Message::user("flaky flaky flaky flaky"), Message::assistant("ok")];
+        session::save(&sessions, &Session::new("a", &flaky)).await.unwrap();
+        let retry = [Message::user("retry"), Message::assistant("flaky tests everywhere here today")];
+        session::save(&sessions, &Session::new("b", &retry)).await.unwrap();
+        let file = dir.join("index/sessions.json");
+
+        // The closer vector loses to the rarer keyword once reranked, which
+        // needs more candidates than the one result asked for
+        let memory = SessionMemory::new(file.clone(), Box::new(WordEmbedder));
+        memory.sync(&sessions, "c").await.unwrap();
+        assert_eq!(memory.search("flaky retry", 1, "c").await.unwrap()[0].source, "session a, exchange 1");
+        let memory = SessionMemory::new(file, Box::new(WordEmbedder)).reranker(Some(Reranker::Bm25 { weight: 1.0 }));
+        let hits = memory.search("flaky retry", 1, "c").await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].source, "session b, exchange 1");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
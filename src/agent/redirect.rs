@@ -0,0 +1,29 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Asks the turn in progress to pause at its next checkpoint, after the
+/// running tool calls finish, so the user can add a correction before the
+/// loop goes on (Esc in the REPL). Clones share the request.
+#[derive(Debug, Clone, Default)]
+pub struct RedirectToken {
+    requested: Arc<AtomicBool>,
+}
+
+impl RedirectToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Whether a pause was requested, clearing the request
+    pub fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+}
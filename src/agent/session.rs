@@ -0,0 +1,387 @@
+use crate::agent::context;
+use crate::agent::message::Message;
+use crate::agent::timing::TimingRecord;
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Where conversations are saved, relative to the working directory
+pub const SESSIONS_DIR: &str = ".ariste/sessions";
+
+/// Checkpoints are saved as `<id>.checkpoint.json`
+const CHECKPOINT_EXTENSION: &str = "checkpoint.json";
+const CHECKPOINT_SUFFIX: &str = ".checkpoint.json";
+
+/// Format version written into every saved session and checkpoint.
+/// Version 1 is the unversioned format from before this field existed.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Longest title shown by the `/resume` picker, in characters
+const TITLE_CHARS: usize = 60;
+
+/// A saved conversation, written to `<dir>/<id>.json` after every turn
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Session {
+    /// Format version the session was saved in; see [`SCHEMA_VERSION`]
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    pub id: String,
+    /// Unix time of the last save, in seconds
+    pub updated: u64,
+    /// The full history, including tool calls and tool results
+    pub messages: Vec<Message>,
+    /// When each tool call and subagent task ran
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timings: Vec<TimingRecord>,
+    /// Sampling seed the session ran with (`--seed`), to reproduce it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+fn unversioned() -> u32 {
+    1
+}
+
+impl Session {
+    pub fn new(id: &str, messages: &[Message]) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            id: id.to_string(),
+            updated: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            messages: messages.to_vec(),
+            timings: Vec::new(),
+            seed: None,
+        }
+    }
+
+    pub fn timings(mut self, timings: &[TimingRecord]) -> Self {
+        self.timings = timings.to_vec();
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// A saved session as listed by the `/resume` picker
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub id: String,
+    /// First line of the first prompt
+    pub title: String,
+    pub updated: u64,
+    pub messages: usize,
+}
+
+impl SessionSummary {
+    fn of(session: &Session) -> Self {
+        let first_prompt = session
+            .messages
+            .iter()
+            .find(|m| m.is_user())
+            .and_then(|m| m.content().lines().find(|line| !line.trim().is_empty()))
+            .unwrap_or("(no prompt)")
+            .trim();
+        let title = match first_prompt.char_indices().nth(TITLE_CHARS) {
+            Some((end, _)) => format!("{}…", &first_prompt[..end]),
+            None => first_prompt.to_string(),
+        };
+        Self {
+            id: session.id.clone(),
+            title,
+            updated: session.updated,
+            messages: session.messages.len(),
+        }
+    }
+}
+
+/// Time since `updated` as "just now", "5m ago", "3h ago" or "2d ago"
+pub fn format_age(now: u64, updated: u64) -> String {
+    let secs = now.saturating_sub(updated);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3_600 => format!("{}m ago", secs / 60),
+        3_600..86_400 => format!("{}h ago", secs / 3_600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+/// Read a saved session of any supported version, upgrading it to the
+/// current format
+fn migrate(value: Value) -> Result<Session, Error> {
+    let mut session: Session = serde_json::from_value(value)?;
+    if session.version > SCHEMA_VERSION {
+        return Err(Error::Message(format!(
+            "Session '{}' was saved by a newer ariste (format {}, this version reads up to {}); upgrade to open it",
+            session.id, session.version, SCHEMA_VERSION
+        )));
+    }
+    if session.version < 2 {
+        // v1：Ollama 的工具调用没有 id，工具结果没有记录工具名
+        context::fill_call_ids(&mut session.messages);
+        context::fill_tool_names(&mut session.messages);
+    }
+    session.version = SCHEMA_VERSION;
+    Ok(session)
+}
+
+/// Session ids become file names, so only plain names are accepted
+fn path_for(dir: &Path, id: &str) -> Result<PathBuf, Error> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(Error::Message(format!("Invalid session id '{}'", id)));
+    }
+    Ok(dir.join(format!("{}.json", id)))
+}
+
+/// Write `value` to `path` by writing next to it and renaming over it, so
+/// a crash mid-write never leaves a truncated file behind
+async fn write_atomic(path: &Path, value: &impl Serialize) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let partial = path.with_extension("tmp");
+    tokio::fs::write(&partial, serde_json::to_vec_pretty(value)?).await?;
+    tokio::fs::rename(&partial, path).await?;
+    Ok(())
+}
+
+/// Write the session, replacing any earlier save of it
+pub async fn save(dir: &Path, session: &Session) -> Result<PathBuf, Error> {
+    let path = path_for(dir, &session.id)?;
+    write_atomic(&path, session).await?;
+    Ok(path)
+}
+
+/// Snapshot of a turn still in progress, including its unfinished
+/// messages. Removed when the turn ends, so one left behind on startup
+/// means the process died mid-turn.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Checkpoint {
+    /// Process that wrote it; a checkpoint of a running process isn't a crash
+    pub pid: u32,
+    #[serde(flatten)]
+    pub session: Session,
+}
+
+fn checkpoint_path(dir: &Path, id: &str) -> Result<PathBuf, Error> {
+    Ok(path_for(dir, id)?.with_extension(CHECKPOINT_EXTENSION))
+}
+
+pub async fn save_checkpoint(dir: &Path, session: Session) -> Result<(), Error> {
+    let path = checkpoint_path(dir, &session.id)?;
+    let checkpoint = Checkpoint {
+        pid: std::process::id(),
+        session,
+    };
+    write_atomic(&path, &checkpoint).await
+}
+
+pub async fn clear_checkpoint(dir: &Path, id: &str) -> Result<(), Error> {
+    let path = checkpoint_path(dir, id)?;
+    if tokio::fs::try_exists(&path).await? {
+        tokio::fs::remove_file(&path).await?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// 没有 /proc 时无法判断，按已退出处理
+#[cfg(not(target_os = "linux"))]
+fn process_alive(pid: u32) -> bool {
+    pid == std::process::id()
+}
+
+/// Checkpoints left by processes that are no longer running, newest first
+pub async fn crashed(dir: &Path) -> Result<Vec<Checkpoint>, Error> {
+    if !tokio::fs::try_exists(dir).await? {
+        return Ok(Vec::new());
+    }
+    let mut found = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if !entry.file_name().to_string_lossy().ends_with(CHECKPOINT_SUFFIX) {
+            continue;
+        }
+        let Ok(buf) = tokio::fs::read(entry.path()).await else {
+            continue;
+        };
+        let Ok(value) = serde_json::from_slice::<Value>(&buf) else {
+            continue;
+        };
+        let Some(pid) = value.get("pid").and_then(|v| v.as_u64()).and_then(|pid| u32::try_from(pid).ok()) else {
+            continue;
+        };
+        if !process_alive(pid)
+            && let Ok(session) = migrate(value)
+        {
+            found.push(Checkpoint { pid, session });
+        }
+    }
+    found.sort_by_key(|checkpoint| std::cmp::Reverse(checkpoint.session.updated));
+    Ok(found)
+}
+
+pub async fn load(dir: &Path, id: &str) -> Result<Session, Error> {
+    let path = path_for(dir, id)?;
+    if !tokio::fs::try_exists(&path).await? {
+        return Err(Error::Message(format!("No session '{}' in {}", id, dir.display())));
+    }
+    let buf = tokio::fs::read(&path).await?;
+    migrate(serde_json::from_slice(&buf)?)
+}
+
+/// Saved sessions in `dir`, most recently active first. Files that can't
+/// be read as sessions are skipped.
+pub async fn list(dir: &Path) -> Result<Vec<SessionSummary>, Error> {
+    if !tokio::fs::try_exists(dir).await? {
+        return Ok(Vec::new());
+    }
+    let mut summaries = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.ends_with(".json") || name.ends_with(CHECKPOINT_SUFFIX) {
+            continue;
+        }
+        let Ok(buf) = tokio::fs::read(&path).await else {
+            continue;
+        };
+        // 较新版本保存的会话也列出，打开时再提示升级
+        if let Ok(session) = serde_json::from_slice::<Session>(&buf) {
+            summaries.push(SessionSummary::of(&session));
+        }
+    }
+    summaries.sort_by(|a, b| b.updated.cmp(&a.updated).then_with(|| b.id.cmp(&a.id)));
+    Ok(summaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_session_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ariste-sessions-{}", std::process::id()));
+        let messages = vec![
+            Message::user("what is in Cargo.toml?"),
+            Message::assistant_with_tools(
+                "",
+                vec![json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "Cargo.toml"}}})],
+            ),
+            Message::tool(Some("call_1".to_string()), Some("read".to_string()), "[package]"),
+        ];
+
+        let path = save(&dir, &Session::new("1700000000000", &messages).seed(Some(7))).await.unwrap();
+        assert_eq!(path, dir.join("1700000000000.json"));
+
+        let restored = load(&dir, "1700000000000").await.unwrap();
+        assert_eq!(restored.messages.len(), 3);
+        assert_eq!(restored.messages, messages);
+        assert_eq!(restored.seed, Some(7));
+
+        assert!(load(&dir, "missing").await.unwrap_err().to_string().contains("No session 'missing'"));
+        assert!(load(&dir, "../settings").await.unwrap_err().to_string().contains("Invalid session id"));
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_migrate_old_sessions() {
+        let dir = std::env::temp_dir().join(format!("ariste-session-migrate-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        // 版本字段出现之前保存的会话
+        let v1 = json!({
+            "id": "old",
+            "updated": 1_700_000_000,
+            "messages": [
+                {"role": "user", "content": "list files"},
+                {"role": "assistant", "content": "", "tool_calls": [{"function": {"name": "ls", "arguments": {}}}]},
+                {"role": "tool", "content": "Cargo.toml", "tool_call_id": ""},
+            ]
+        });
+        tokio::fs::write(dir.join("old.json"), v1.to_string()).await.unwrap();
+
+        let session = load(&dir, "old").await.unwrap();
+        assert_eq!(session.version, SCHEMA_VERSION);
+        let id = session.messages[1].tool_calls()[0]["id"].as_str().unwrap();
+        assert_eq!(session.messages[2].tool_call_id(), Some(id));
+        assert_eq!(session.messages[2].tool_name(), Some("ls"));
+
+        let newer = json!({"version": SCHEMA_VERSION + 1, "id": "new", "updated": 0, "messages": []});
+        tokio::fs::write(dir.join("new.json"), newer.to_string()).await.unwrap();
+        assert!(load(&dir, "new").await.unwrap_err().to_string().contains("newer ariste"));
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions() {
+        let dir = std::env::temp_dir().join(format!("ariste-session-list-{}", std::process::id()));
+        assert!(list(&dir).await.unwrap().is_empty());
+
+        let prompt = |content: &str| Message::user(content);
+        let mut older = Session::new("older", &[prompt("\nfix the build\nit fails on CI")]);
+        older.updated -= 7_200;
+        save(&dir, &older).await.unwrap();
+        save(&dir, &Session::new("newer", &[prompt(&"x".repeat(80)), prompt("and again")])).await.unwrap();
+        tokio::fs::write(dir.join("notes.json"), "not a session").await.unwrap();
+
+        let sessions = list(&dir).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, "newer");
+        assert_eq!(sessions[0].messages, 2);
+        assert_eq!(sessions[0].title, format!("{}…", "x".repeat(60)));
+        assert_eq!(sessions[1].title, "fix the build");
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_crash_checkpoints() {
+        let dir = std::env::temp_dir().join(format!("ariste-checkpoints-{}", std::process::id()));
+        let prompt = Message::user("refactor the parser");
+
+        // A checkpoint of this (running) process is not a crash
+        save_checkpoint(&dir, Session::new("live", std::slice::from_ref(&prompt))).await.unwrap();
+        assert!(crashed(&dir).await.unwrap().is_empty());
+
+        let dead = Checkpoint {
+            pid: u32::MAX,
+            session: Session::new("dead", &[prompt]),
+        };
+        write_atomic(&checkpoint_path(&dir, "dead").unwrap(), &dead).await.unwrap();
+        let found = crashed(&dir).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].session.id, "dead");
+        assert_eq!(found[0].session.messages[0].content(), "refactor the parser");
+        // Checkpoints are not listed as saved sessions
+        assert!(list(&dir).await.unwrap().is_empty());
+
+        clear_checkpoint(&dir, "dead").await.unwrap();
+        clear_checkpoint(&dir, "dead").await.unwrap();
+        assert!(crashed(&dir).await.unwrap().is_empty());
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(1_000, 990), "just now");
+        assert_eq!(format_age(1_000, 1_200), "just now");
+        assert_eq!(format_age(10_000, 10_000 - 300), "5m ago");
+        assert_eq!(format_age(100_000, 100_000 - 3 * 3_600), "3h ago");
+        assert_eq!(format_age(1_000_000, 1_000_000 - 2 * 86_400), "2d ago");
+    }
+}
@@ -0,0 +1,218 @@
+use crate::ui::{Approval, Frontend, Notice, StreamFragment, glyphs};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Longest tool argument or result summary shown in a subagent line
+const MAX_SUMMARY_CHARS: usize = 100;
+
+/// What a subagent's loop shows, as `task#3 │ ...` lines on the parent's
+/// frontend. Replies are passed on a line at a time and tool calls as one
+/// line each. Questions for the user go to the parent; a quiet view passes
+/// on nothing else, and an unattended one (for background tasks) declines
+/// them without asking.
+#[derive(Debug)]
+pub(crate) struct SubagentView {
+    task: String,
+    parent: Arc<dyn Frontend>,
+    quiet: bool,
+    unattended: bool,
+    /// Reply text after the last complete line
+    partial: Mutex<String>,
+    /// Tool calls whose results are awaited, oldest first: concurrent calls
+    /// all start before their results come, in call order
+    tools: Mutex<VecDeque<String>>,
+}
+
+impl SubagentView {
+    pub(crate) fn new(id: usize, parent: Arc<dyn Frontend>) -> Self {
+        Self {
+            task: format!("task#{}", id),
+            parent,
+            quiet: false,
+            unattended: false,
+            partial: Mutex::new(String::new()),
+            tools: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    pub(crate) fn unattended(mut self, unattended: bool) -> Self {
+        self.unattended = unattended;
+        self
+    }
+
+    fn line(&self, line: &str) {
+        if !self.quiet && !line.trim().is_empty() {
+            self.parent.subagent_output(&self.task, line.trim_end());
+        }
+    }
+
+    /// Show the oldest tool call awaiting its result, followed by `outcome`
+    fn finish_tool(&self, outcome: &str) {
+        let call = self.tools.lock().ok().and_then(|mut tools| tools.pop_front());
+        match call {
+            Some(call) => self.line(&format!("{} = {}", call, outcome)),
+            None => self.line(outcome),
+        }
+    }
+}
+
+/// First line of `text`, cut to `MAX_SUMMARY_CHARS`
+fn summary(text: &str) -> String {
+    let compact = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if compact.chars().count() <= MAX_SUMMARY_CHARS {
+        return compact;
+    }
+    let cut: String = compact.chars().take(MAX_SUMMARY_CHARS).collect();
+    format!("{}{}", cut, glyphs().ellipsis())
+}
+
+impl Frontend for SubagentView {
+    fn prompt(&self) -> Option<String> {
+        None
+    }
+
+    fn stream(&self, fragment: StreamFragment<'_>) {
+        let Ok(mut partial) = self.partial.lock() else {
+            return;
+        };
+        match fragment {
+            // 子代理的思考过程不显示
+            StreamFragment::Thinking(_) => {}
+            StreamFragment::Content(text) => {
+                partial.push_str(text);
+                while let Some(end) = partial.find('\n') {
+                    let line: String = partial.drain(..=end).collect();
+                    self.line(&line);
+                }
+            }
+            StreamFragment::End => {
+                let rest = std::mem::take(&mut *partial);
+                self.line(&rest);
+            }
+        }
+    }
+
+    fn response(&self, content: &str) {
+        for line in content.lines() {
+            self.line(line);
+        }
+    }
+
+    fn citations(&self, _citations: &[String]) {}
+
+    fn tool_start(&self, name: &str, args: Option<&str>) {
+        let call = match args {
+            Some(args) if !args.trim().is_empty() && args != "null" => {
+                format!("{} {} {}", glyphs().tool, name, summary(args))
+            }
+            _ => format!("{} {}", glyphs().tool, name),
+        };
+        if let Ok(mut tools) = self.tools.lock() {
+            tools.push_back(call);
+        }
+    }
+
+    fn tool_heartbeat(&self, _elapsed: &str, _detail: Option<&str>) {}
+
+    fn tool_result(&self, _name: &str, result: &str) {
+        self.finish_tool(&summary(result));
+    }
+
+    fn tool_error(&self, error: &str) {
+        self.finish_tool(&format!("{} {}", glyphs().error, summary(error)));
+    }
+
+    fn confirm(&self, question: &str) -> bool {
+        !self.unattended && self.parent.confirm(&format!("{}: {}", self.task, question))
+    }
+
+    fn approve(&self, request: &str) -> Approval {
+        if self.unattended {
+            return Approval::Deny;
+        }
+        self.parent.approve(&format!("{}: {}", self.task, request))
+    }
+
+    fn notify(&self, _notice: Notice, message: &str) {
+        self.line(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Lines(Mutex<Vec<String>>);
+
+    impl Frontend for Lines {
+        fn prompt(&self) -> Option<String> {
+            None
+        }
+        fn stream(&self, _fragment: StreamFragment<'_>) {}
+        fn response(&self, _content: &str) {}
+        fn citations(&self, _citations: &[String]) {}
+        fn tool_start(&self, _name: &str, _args: Option<&str>) {}
+        fn tool_heartbeat(&self, _elapsed: &str, _detail: Option<&str>) {}
+        fn tool_result(&self, _name: &str, _result: &str) {}
+        fn tool_error(&self, _error: &str) {}
+        fn confirm(&self, question: &str) -> bool {
+            self.0.lock().unwrap().push(format!("? {}", question));
+            true
+        }
+        fn notify(&self, _notice: Notice, _message: &str) {}
+        fn subagent_output(&self, task: &str, line: &str) {
+            self.0.lock().unwrap().push(format!("{} {}", task, line));
+        }
+    }
+
+    #[test]
+    fn test_subagent_lines() {
+        let parent = Arc::new(Lines::default());
+        let view = SubagentView::new(3, parent.clone());
+        view.stream(StreamFragment::Content("Looking at "));
+        view.stream(StreamFragment::Content("the crate\nThen"));
+        view.stream(StreamFragment::End);
+        view.tool_start("grep", Some("{\n  \"pattern\": \"fn main\"\n}"));
+        view.tool_result("grep", "src/main.rs:1\nsrc/lib.rs:4");
+        assert!(view.confirm("Run bash?"));
+
+        let glyph = glyphs().tool;
+        assert_eq!(*parent.0.lock().unwrap(), vec![
+            "task#3 Looking at the crate".to_string(),
+            "task#3 Then".to_string(),
+            format!("task#3 {} grep {{ \"pattern\": \"fn main\" }} = src/main.rs:1 src/lib.rs:4", glyph),
+            "? task#3: Run bash?".to_string(),
+        ]);
+
+        // Concurrent calls: both start before either result
+        parent.0.lock().unwrap().clear();
+        view.tool_start("read", Some("a.rs"));
+        view.tool_start("read", Some("b.rs"));
+        view.tool_result("read", "fn a() {}");
+        view.tool_error("not found");
+        assert_eq!(*parent.0.lock().unwrap(), vec![
+            format!("task#3 {} read a.rs = fn a() {{}}", glyph),
+            format!("task#3 {} read b.rs = {} not found", glyph, glyphs().error),
+        ]);
+
+        // Quiet views still ask
+        let parent = Arc::new(Lines::default());
+        let view = SubagentView::new(4, parent.clone()).quiet(true);
+        view.notify(Notice::Info, "working");
+        view.confirm("Run bash?");
+        assert_eq!(*parent.0.lock().unwrap(), vec!["? task#4: Run bash?".to_string()]);
+
+        // Unattended views never do
+        let parent = Arc::new(Lines::default());
+        let view = SubagentView::new(5, parent.clone()).quiet(true).unattended(true);
+        assert!(!view.confirm("Run bash?"));
+        assert_eq!(view.approve("Allow bash?"), Approval::Deny);
+        assert!(parent.0.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::agent::SubAgentType;
+use crate::config::SubagentProfileConfig;
+
+/// What a kind of subagent is told and may use. The built-in types come
+/// from `SubAgentType`; embedders can register their own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubAgentProfile {
+    /// Name the task tool and settings use, e.g. `explore`
+    pub name: String,
+    pub description: String,
+    pub system_prompt: Option<String>,
+    /// Whether the subagent gets tools when the caller asks for them
+    pub uses_tools: bool,
+    /// Tools it may be given; `None` means every tool
+    pub allowed_tools: Option<Vec<String>>,
+    /// Attach the uncommitted diff to the task (code review)
+    pub attach_diff: bool,
+    /// Model the subagent runs on; `None` uses the chat model
+    pub model: Option<String>,
+    /// Retrieval augmentation on or off; `None` keeps the `rag.enabled` default
+    pub rag: Option<bool>,
+}
+
+impl SubAgentProfile {
+    /// A profile with tools and no system prompt
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            system_prompt: None,
+            uses_tools: true,
+            allowed_tools: None,
+            attach_diff: false,
+            model: None,
+            rag: None,
+        }
+    }
+
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn uses_tools(mut self, uses_tools: bool) -> Self {
+        self.uses_tools = uses_tools;
+        self
+    }
+
+    pub fn allowed_tools(mut self, tools: &[&str]) -> Self {
+        self.allowed_tools = Some(tools.iter().map(|tool| tool.to_string()).collect());
+        self
+    }
+
+    pub fn attach_diff(mut self, attach: bool) -> Self {
+        self.attach_diff = attach;
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn rag(mut self, rag: bool) -> Self {
+        self.rag = Some(rag);
+        self
+    }
+}
+
+impl From<&SubagentProfileConfig> for SubAgentProfile {
+    fn from(config: &SubagentProfileConfig) -> Self {
+        let mut profile = SubAgentProfile::new(config.name.clone(), config.description.clone());
+        if let Some(prompt) = &config.system_prompt {
+            profile = profile.system_prompt(prompt.clone());
+        }
+        if let Some(model) = &config.model {
+            profile = profile.model(model.clone());
+        }
+        if let Some(tools) = &config.allowed_tools {
+            profile.uses_tools = !tools.is_empty();
+            profile.allowed_tools = Some(tools.clone());
+        }
+        profile.rag = config.rag;
+        profile
+    }
+}
+
+impl From<SubAgentType> for SubAgentProfile {
+    fn from(subagent_type: SubAgentType) -> Self {
+        let mut profile = SubAgentProfile::new(subagent_type.name(), subagent_type.description())
+            .uses_tools(subagent_type.uses_tools())
+            .attach_diff(subagent_type == SubAgentType::CodeReview);
+        if let Some(prompt) = subagent_type.system_prompt() {
+            profile = profile.system_prompt(prompt);
+        }
+        if let Some(tools) = subagent_type.allowed_tools() {
+            profile = profile.allowed_tools(tools);
+        }
+        profile
+    }
+}
+
+/// The subagent types the `task` tool offers and `spawn_task` accepts, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubAgentRegistry {
+    profiles: Vec<SubAgentProfile>,
+}
+
+impl SubAgentRegistry {
+    /// The built-in types
+    pub fn builtin() -> Self {
+        Self {
+            profiles: SubAgentType::ALL.iter().map(|&subagent_type| subagent_type.into()).collect(),
+        }
+    }
+
+    /// Add a type, replacing (in place) and returning one of the same name
+    pub fn register(&mut self, profile: SubAgentProfile) -> Option<SubAgentProfile> {
+        match self.profiles.iter_mut().find(|existing| existing.name == profile.name) {
+            Some(existing) => Some(std::mem::replace(existing, profile)),
+            None => {
+                self.profiles.push(profile);
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SubAgentProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.profiles.iter().map(|profile| profile.name.as_str()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SubAgentProfile> {
+        self.profiles.iter()
+    }
+}
+
+impl Default for SubAgentRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut registry = SubAgentRegistry::builtin();
+        assert_eq!(registry.names(), vec!["general-purpose", "explore", "plan", "code-review", "test-runner"]);
+        let review = registry.get("code-review").unwrap();
+        assert!(review.attach_diff);
+        assert!(review.allowed_tools.as_ref().unwrap().contains(&"grep".to_string()));
+        assert!(!registry.get("plan").unwrap().uses_tools);
+
+        let audit = SubAgentProfile::new("security-audit", "Looks for vulnerabilities")
+            .system_prompt("You are a security auditor.")
+            .allowed_tools(&["read", "grep"]);
+        assert_eq!(registry.register(audit.clone()), None);
+        assert_eq!(registry.get("security-audit"), Some(&audit));
+
+        // Replacing a built-in keeps its position
+        let explore = SubAgentProfile::new("explore", "Reads everything");
+        assert!(registry.register(explore).is_some());
+        assert_eq!(registry.names()[1], "explore");
+        assert_eq!(registry.get("explore").unwrap().description, "Reads everything");
+    }
+
+    #[test]
+    fn test_profile_from_settings() {
+        let config: SubagentProfileConfig = serde_json::from_value(serde_json::json!({
+            "name": "docs-writer",
+            "description": "Writes documentation",
+            "system_prompt": "You write concise docs.",
+            "model": "qwen3:14b",
+            "allowed_tools": ["read", "write"],
+            "rag": true,
+            "max_turns": 20
+        }))
+        .unwrap();
+        assert_eq!(config.limits.max_turns, Some(20));
+
+        let profile = SubAgentProfile::from(&config);
+        assert_eq!(profile.model.as_deref(), Some("qwen3:14b"));
+        assert_eq!(profile.system_prompt.as_deref(), Some("You write concise docs."));
+        assert!(profile.uses_tools);
+        assert_eq!(profile.allowed_tools, Some(vec!["read".to_string(), "write".to_string()]));
+        assert_eq!(profile.rag, Some(true));
+
+        let no_tools = SubagentProfileConfig {
+            name: "editor".to_string(),
+            allowed_tools: Some(vec![]),
+            ..Default::default()
+        };
+        assert!(!SubAgentProfile::from(&no_tools).uses_tools);
+        assert_eq!(SubAgentProfile::from(&no_tools).rag, None);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::agent::{SubAgentExecution, SubAgentIdCounter, SubAgentStatus, SubAgentTask};
+use crate::error::Error;
+use crate::llm::CancelToken;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+/// Background subagent tasks of the session, by id: what each was asked,
+/// how it is doing, and the token that stops it (`task_cancel`, `/cancel`)
+#[derive(Debug, Default)]
+pub struct TaskManager {
+    ids: SubAgentIdCounter,
+    tasks: BTreeMap<usize, TrackedTask>,
+}
+
+#[derive(Debug)]
+struct TrackedTask {
+    execution: SubAgentExecution,
+    cancel: CancelToken,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An id for a subagent run in the foreground, from the same sequence
+    /// as the tracked tasks, so every subagent of the session has its own
+    pub fn reserve_id(&mut self) -> usize {
+        self.ids.next() + 1
+    }
+
+    /// Record a task that is about to start; returns its id (counting from 1)
+    /// and the token its subagent checks
+    pub fn track(&mut self, task: SubAgentTask) -> (usize, CancelToken) {
+        let id = self.reserve_id();
+        (id, self.track_reserved(id, task))
+    }
+
+    /// Record a task under an id from `reserve_id`; returns the token its
+    /// subagent checks
+    pub fn track_reserved(&mut self, id: usize, task: SubAgentTask) -> CancelToken {
+        let cancel = CancelToken::new();
+        let mut execution = SubAgentExecution::new(id, task);
+        execution.start();
+        self.tasks.insert(id, TrackedTask {
+            execution,
+            cancel: cancel.clone(),
+        });
+        cancel
+    }
+
+    pub fn get(&self, id: usize) -> Option<&SubAgentExecution> {
+        self.tasks.get(&id).map(|tracked| &tracked.execution)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SubAgentExecution> {
+        self.tasks.values().map(|tracked| &tracked.execution)
+    }
+
+    /// Ask task `id` to stop: its reply in flight ends and no further tools
+    /// run. Finished tasks can't be cancelled.
+    pub fn cancel(&mut self, id: usize) -> Result<&SubAgentExecution, Error> {
+        let tracked = self
+            .tasks
+            .get_mut(&id)
+            .ok_or_else(|| Error::Message(format!("No background task {}", id)))?;
+        if !matches!(tracked.execution.status, SubAgentStatus::Pending | SubAgentStatus::Running) {
+            return Err(Error::Message(format!(
+                "Task {} already finished ({})",
+                id,
+                tracked.execution.status.as_str()
+            )));
+        }
+        tracked.cancel.cancel();
+        tracked.execution.cancel();
+        Ok(&tracked.execution)
+    }
+
+    /// Record how task `id` ended: its formatted output or why it failed.
+    /// A cancelled task stays cancelled but keeps the partial result.
+    pub fn finish(&mut self, id: usize, outcome: Result<String, String>) {
+        let Some(tracked) = self.tasks.get_mut(&id) else {
+            return;
+        };
+        let execution = &mut tracked.execution;
+        match (outcome, &execution.status) {
+            (Ok(result), SubAgentStatus::Cancelled) => execution.result = Some(result),
+            (Err(_), SubAgentStatus::Cancelled) => {}
+            (Ok(result), _) => execution.complete(result),
+            (Err(error), _) => execution.fail(error),
+        }
+    }
+
+    /// Whether any task is still running
+    pub fn has_running(&self) -> bool {
+        self.iter().any(|execution| execution.status == SubAgentStatus::Running)
+    }
+}
+
+/// One task as `task_status` reports it
+pub fn status_json(execution: &SubAgentExecution) -> Value {
+    let mut status = json!({
+        "task_id": execution.id,
+        "subagent_type": execution.task.subagent_type,
+        "description": execution.task.description,
+        "status": execution.status.as_str(),
+        "duration_ms": execution.duration().map(|elapsed| elapsed.as_millis() as u64),
+    });
+    if let SubAgentStatus::Failed(error) = &execution.status {
+        status["error"] = json!(error);
+    }
+    status
+}
+
+/// One line per task for `/tasks`, e.g. `#1 running 3.2s explore: Survey`
+pub fn describe(execution: &SubAgentExecution) -> String {
+    let elapsed = execution
+        .duration()
+        .map(|elapsed| format!(" {:.1}s", elapsed.as_secs_f64()))
+        .unwrap_or_default();
+    format!(
+        "#{} {}{} {}: {}",
+        execution.id,
+        execution.status.as_str(),
+        elapsed,
+        execution.task.subagent_type,
+        execution.task.description
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::SubAgentType;
+
+    #[test]
+    fn test_cancel_task() {
+        let mut tasks = TaskManager::new();
+        let (id, cancel) = tasks.track(SubAgentTask::new(SubAgentType::Explore, "Survey", "map the crate"));
+        assert_eq!(id, 1);
+        assert_eq!(tasks.get(id).unwrap().status, SubAgentStatus::Running);
+
+        let execution = tasks.cancel(id).unwrap();
+        assert_eq!(execution.status, SubAgentStatus::Cancelled);
+        assert!(execution.end_time.is_some());
+        assert!(cancel.is_cancelled());
+
+        let error = tasks.cancel(id).unwrap_err();
+        assert_eq!(error.to_string(), "Task 1 already finished (cancelled)");
+        assert_eq!(tasks.cancel(7).unwrap_err().to_string(), "No background task 7");
+    }
+
+    #[test]
+    fn test_finish_task() {
+        let mut tasks = TaskManager::new();
+        let (done, _) = tasks.track(SubAgentTask::named("security-audit", "Audit", "look for injections"));
+        let (broken, _) = tasks.track(SubAgentTask::new(SubAgentType::Plan, "Plan", "plan the refactor"));
+        let (stopped, _) = tasks.track(SubAgentTask::new(SubAgentType::Explore, "Survey", "map the crate"));
+        assert!(tasks.has_running());
+
+        tasks.finish(done, Ok("all clear".to_string()));
+        tasks.finish(broken, Err("model unavailable".to_string()));
+        tasks.cancel(stopped).unwrap();
+        tasks.finish(stopped, Ok("partial map".to_string()));
+        assert!(!tasks.has_running());
+
+        let audit = tasks.get(done).unwrap();
+        assert_eq!(audit.result.as_deref(), Some("all clear"));
+        assert_eq!(status_json(audit)["subagent_type"], "security-audit");
+        let failed = status_json(tasks.get(broken).unwrap());
+        assert_eq!(failed["status"], "failed");
+        assert_eq!(failed["error"], "model unavailable");
+        let survey = tasks.get(stopped).unwrap();
+        assert_eq!(survey.status, SubAgentStatus::Cancelled);
+        assert_eq!(survey.result.as_deref(), Some("partial map"));
+        assert!(describe(survey).starts_with("#3 cancelled "));
+        assert!(describe(survey).ends_with(" explore: Survey"));
+    }
+}
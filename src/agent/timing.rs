@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// What a timing record measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimingKind {
+    Tool,
+    Subagent,
+}
+
+/// When a tool call or subagent task ran and how it ended, saved with the
+/// session so latency can be attributed afterwards
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TimingRecord {
+    pub kind: TimingKind,
+    /// Tool name, or subagent type
+    pub name: String,
+    /// The tool call this measured, matching the id of its tool message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<String>,
+    /// Description of the subagent task: the task measured, or the one
+    /// whose subagent made this tool call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// Unix time in milliseconds
+    pub started_ms: u64,
+    pub finished_ms: u64,
+    pub duration_ms: u64,
+    /// `ok` or `error` for tools; `completed`, `failed` or why a subagent
+    /// stopped early (`max_turns`, `timeout`, `cancelled`)
+    pub outcome: String,
+}
+
+impl TimingRecord {
+    pub fn call_id(mut self, call_id: &str) -> Self {
+        self.call_id = Some(call_id.to_string());
+        self
+    }
+
+    pub fn task(mut self, task: &str) -> Self {
+        self.task = Some(task.to_string());
+        self
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Measures one tool call or subagent task
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    started_at: SystemTime,
+    started: Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self {
+            started_at: SystemTime::now(),
+            started: Instant::now(),
+        }
+    }
+
+    /// A record ending now
+    pub fn stop(&self, kind: TimingKind, name: &str, outcome: &str) -> TimingRecord {
+        let duration = self.started.elapsed();
+        let started_ms = unix_ms(self.started_at);
+        TimingRecord {
+            kind,
+            name: name.to_string(),
+            call_id: None,
+            task: None,
+            started_ms,
+            finished_ms: started_ms + duration.as_millis() as u64,
+            duration_ms: duration.as_millis() as u64,
+            outcome: outcome.to_string(),
+        }
+    }
+}
+
+/// Duration of the tool call with id `call_id`, e.g. `1.24s`
+pub fn call_duration(timings: &[TimingRecord], call_id: &str) -> Option<String> {
+    timings
+        .iter()
+        .rfind(|record| record.kind == TimingKind::Tool && record.call_id.as_deref() == Some(call_id))
+        .map(|record| format!("{:.2}s", record.duration_ms as f64 / 1000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopwatch_record() {
+        let watch = Stopwatch::start();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let record = watch.stop(TimingKind::Tool, "grep", "ok").call_id("call_2");
+        assert!(record.duration_ms >= 20);
+        assert_eq!(record.finished_ms - record.started_ms, record.duration_ms);
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["kind"], "tool");
+        assert_eq!(value["call_id"], "call_2");
+        assert!(value.get("task").is_none());
+        let parsed: TimingRecord = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, record);
+
+        let timings = vec![TimingRecord { duration_ms: 1240, ..record }];
+        assert_eq!(call_duration(&timings, "call_2").as_deref(), Some("1.24s"));
+        assert_eq!(call_duration(&timings, "call_1"), None);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::agent::message::Message;
+use crate::agent::timing::{self, TimingRecord};
+
+/// Shortest backtick fence that doesn't occur in `content`
+fn fence(content: &str) -> String {
+    let mut fence = "```".to_string();
+    while content.contains(&fence) {
+        fence.push('`');
+    }
+    fence
+}
+
+fn code_block(content: &str, lang: &str) -> String {
+    let fence = fence(content);
+    format!("{}{}\n{}\n{}", fence, lang, content.trim_end(), fence)
+}
+
+/// Render a conversation as Markdown. Tool calls are shown with their
+/// arguments and tool outputs are folded into `<details>` blocks, labelled
+/// with how long the call took when `timings` has it; system messages are
+/// left out.
+pub fn render_markdown(title: &str, messages: &[Message], timings: &[TimingRecord]) -> String {
+    let mut sections = vec![format!("# {}", title)];
+
+    for message in messages {
+        match message {
+            Message::User { content } => sections.push(format!("## User\n\n{}", content.trim())),
+            Message::Assistant { content, tool_calls } => {
+                let mut section = "## Assistant".to_string();
+                if !content.trim().is_empty() {
+                    section.push_str(&format!("\n\n{}", content.trim()));
+                }
+                for call in tool_calls {
+                    let Some(function) = call.get("function") else {
+                        continue;
+                    };
+                    let name = function.get("name").and_then(|v| v.as_str()).unwrap_or("tool");
+                    let arguments = function
+                        .get("arguments")
+                        .map(|args| serde_json::to_string_pretty(args).unwrap_or_default())
+                        .unwrap_or_default();
+                    section.push_str(&format!(
+                        "\n\n**Tool call:** `{}`\n\n{}",
+                        name,
+                        code_block(&arguments, "json")
+                    ));
+                }
+                sections.push(section);
+            }
+            Message::Tool { content, .. } => {
+                let summary = match message.tool_call_id().and_then(|id| timing::call_duration(timings, id)) {
+                    Some(duration) => format!("Tool output ({})", duration),
+                    None => "Tool output".to_string(),
+                };
+                sections.push(format!(
+                    "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+                    summary,
+                    code_block(content, "")
+                ))
+            }
+            Message::System { .. } => {}
+        }
+    }
+
+    let mut markdown = sections.join("\n\n");
+    markdown.push('\n');
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_markdown() {
+        let call = Message::assistant_with_tools(
+            "",
+            vec![serde_json::json!({
+                "function": {"name": "read", "arguments": {"file_path": "src/main.rs"}}
+            })],
+        );
+        let messages = vec![
+            Message::system("hidden"),
+            Message::user("What's in main.rs?"),
+            call,
+            Message::tool(None, Some("read".to_string()), "fn main() {}"),
+            Message::assistant("It defines `main`."),
+        ];
+
+        let markdown = render_markdown("Session", &messages, &[]);
+        assert!(markdown.starts_with("# Session\n\n## User\n\nWhat's in main.rs?"));
+        assert!(!markdown.contains("hidden"));
+        assert!(markdown.contains("**Tool call:** `read`\n\n```json\n{\n  \"file_path\": \"src/main.rs\"\n}\n```"));
+        assert!(markdown.contains("<summary>Tool output</summary>\n\n```\nfn main() {}\n```"));
+        assert!(markdown.ends_with("## Assistant\n\nIt defines `main`.\n"));
+    }
+
+    #[test]
+    fn test_tool_output_duration() {
+        use crate::agent::timing::{Stopwatch, TimingKind};
+
+        let messages = vec![Message::tool(Some("call_1".to_string()), Some("grep".to_string()), "3 matches")];
+        let timing = TimingRecord {
+            duration_ms: 1500,
+            ..Stopwatch::start().stop(TimingKind::Tool, "grep", "ok").call_id("call_1")
+        };
+        let markdown = render_markdown("Session", &messages, &[timing]);
+        assert!(markdown.contains("<summary>Tool output (1.50s)</summary>"));
+    }
+
+    #[test]
+    fn test_fence_longer_than_content() {
+        assert_eq!(fence("plain"), "```");
+        assert_eq!(fence("```rust\n```"), "````");
+        assert_eq!(code_block("```x```", ""), "````\n```x```\n````");
+    }
+}
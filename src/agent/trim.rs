@@ -0,0 +1,201 @@
+use crate::config::{ToolTrimConfig, TrimPolicy};
+use crate::tools::{FunctionDefinition, ParametersSchema, ToolDefinition};
+use std::collections::HashMap;
+
+/// Name of the meta tool the model calls to unlock hidden tools
+pub const REQUEST_TOOLS: &str = "request_tools";
+
+/// Tools sent when no `max_tools` is configured
+const DEFAULT_MAX_TOOLS: usize = 4;
+
+/// First sentence of `text`
+fn first_sentence(text: &str) -> &str {
+    match text.find(". ") {
+        Some(end) => &text[..=end],
+        None => text,
+    }
+}
+
+/// Lowercase words of at least three letters
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| word.len() >= 3)
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Score how relevant a tool is to the prompt by word overlap with its name
+/// and description; a prompt that names the tool scores highest
+fn relevance(definition: &ToolDefinition, prompt_words: &[String]) -> usize {
+    let name = definition.function.name.to_lowercase();
+    let description = words(&definition.function.description);
+    prompt_words
+        .iter()
+        .map(|word| {
+            if *word == name {
+                5
+            } else if description.contains(word) {
+                1
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+/// Names of the tools to offer for `prompt`, in registry order. `usage`
+/// holds how often each tool was called in earlier sessions.
+pub fn select(
+    definitions: &[ToolDefinition],
+    config: &ToolTrimConfig,
+    prompt: &str,
+    usage: &HashMap<String, usize>,
+) -> Vec<String> {
+    let max_tools = config.max_tools.unwrap_or(DEFAULT_MAX_TOOLS);
+    let prompt_words = words(prompt);
+
+    let mut ranked: Vec<(usize, &ToolDefinition)> = definitions.iter().enumerate().collect();
+    // sort 是稳定排序，分数相同时保持注册顺序
+    ranked.sort_by_key(|(_, definition)| {
+        let name = &definition.function.name;
+        let score = match config.policy.unwrap_or_default() {
+            TrimPolicy::MostUsed => usage.get(name).copied().unwrap_or(0),
+            TrimPolicy::Relevant => relevance(definition, &prompt_words),
+        };
+        std::cmp::Reverse(score)
+    });
+    ranked.truncate(max_tools);
+    ranked.sort_by_key(|(index, _)| *index);
+
+    ranked
+        .into_iter()
+        .map(|(_, definition)| definition.function.name.clone())
+        .collect()
+}
+
+/// Definition with the tool and parameter descriptions cut to one sentence
+pub fn shorten(definition: &ToolDefinition) -> ToolDefinition {
+    let mut short = definition.clone();
+    short.function.description = first_sentence(&definition.function.description).to_string();
+    for property in short.function.parameters.properties.values_mut() {
+        if let Some(description) = property.get("description").and_then(|v| v.as_str()) {
+            let description = first_sentence(description).to_string();
+            property["description"] = description.into();
+        }
+    }
+    short
+}
+
+/// Meta tool listing the hidden tools, so the model can ask for them
+pub fn request_tools_definition(hidden: &[String]) -> ToolDefinition {
+    let mut properties = serde_json::Map::new();
+    properties.insert(
+        "tools".to_string(),
+        serde_json::json!({
+            "type": "array",
+            "items": {"type": "string", "enum": hidden},
+            "description": "Names of the tools to enable"
+        }),
+    );
+
+    ToolDefinition {
+        r#type: "function".to_string(),
+        function: FunctionDefinition {
+            name: REQUEST_TOOLS.to_string(),
+            description: format!(
+                "Enable more tools for this turn when the ones offered aren't enough. Available: {}.",
+                hidden.join(", ")
+            ),
+            parameters: ParametersSchema {
+                r#type: "object".to_string(),
+                properties,
+                required: vec!["tools".to_string()],
+            },
+        },
+    }
+}
+
+/// Definitions to send for the offered tools, plus the `request_tools` meta
+/// tool when anything is hidden
+pub fn offered_definitions(
+    definitions: &[ToolDefinition],
+    offered: &[String],
+    config: &ToolTrimConfig,
+) -> Vec<ToolDefinition> {
+    let short = config.short_descriptions.unwrap_or(true);
+    let mut result: Vec<ToolDefinition> = definitions
+        .iter()
+        .filter(|definition| offered.contains(&definition.function.name))
+        .map(|definition| if short { shorten(definition) } else { definition.clone() })
+        .collect();
+
+    let hidden: Vec<String> = definitions
+        .iter()
+        .map(|definition| definition.function.name.clone())
+        .filter(|name| !offered.contains(name))
+        .collect();
+    if !hidden.is_empty() {
+        result.push(request_tools_definition(&hidden));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{BashTool, GlobTool, GrepTool, ReadTool, ToolImpl, WriteTool};
+
+    fn definitions() -> Vec<ToolDefinition> {
+        vec![
+            BashTool.definition(),
+            ReadTool.definition(),
+            WriteTool.definition(),
+            GlobTool.definition(),
+            GrepTool.definition(),
+        ]
+    }
+
+    fn config(policy: TrimPolicy, max_tools: usize) -> ToolTrimConfig {
+        ToolTrimConfig {
+            policy: Some(policy),
+            max_tools: Some(max_tools),
+            short_descriptions: None,
+        }
+    }
+
+    #[test]
+    fn test_select_relevant() {
+        let selected = select(
+            &definitions(),
+            &config(TrimPolicy::Relevant, 2),
+            "grep for TODO and write a summary file",
+            &HashMap::new(),
+        );
+        assert_eq!(selected, vec!["write", "grep"]);
+    }
+
+    #[test]
+    fn test_select_most_used() {
+        let usage = HashMap::from([("glob".to_string(), 7), ("read".to_string(), 12)]);
+        let selected = select(&definitions(), &config(TrimPolicy::MostUsed, 2), "anything", &usage);
+        assert_eq!(selected, vec!["read", "glob"]);
+    }
+
+    #[test]
+    fn test_offered_definitions() {
+        let offered = vec!["read".to_string()];
+        let defs = offered_definitions(&definitions(), &offered, &config(TrimPolicy::Relevant, 1));
+
+        assert_eq!(defs.len(), 2);
+        assert_eq!(defs[0].function.name, "read");
+        assert!(!defs[0].function.description.contains(". "));
+        assert_eq!(defs[1].function.name, REQUEST_TOOLS);
+        assert!(defs[1].function.description.contains("bash, write, glob, grep"));
+    }
+
+    #[test]
+    fn test_first_sentence() {
+        assert_eq!(first_sentence("Read a file. Supports offsets."), "Read a file.");
+        assert_eq!(first_sentence("No period"), "No period");
+    }
+}
@@ -0,0 +1,138 @@
+use crate::agent::message::Message;
+use serde_json::Value;
+
+/// Messages produced during one user turn. They are staged here and only
+/// committed to the conversation history once the turn completes, so a turn
+/// that fails or is cancelled midway leaves the history untouched.
+#[derive(Debug, Clone)]
+pub struct Turn {
+    messages: Vec<Message>,
+}
+
+impl Turn {
+    /// Start a turn with the user's prompt
+    pub fn new(prompt: &str) -> Self {
+        Self {
+            messages: vec![Message::user(prompt)],
+        }
+    }
+
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// Messages staged so far, starting with the user prompt
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Footnote-style references to the tool calls made during this turn,
+    /// e.g. `read(src/agent/agent.rs)` or `grep('execute_tool')`, deduplicated
+    pub fn citations(&self) -> Vec<String> {
+        let mut citations: Vec<String> = Vec::new();
+        for message in &self.messages {
+            for call in message.tool_calls() {
+                if let Some(function) = call.get("function")
+                    && let Some(name) = function.get("name").and_then(|v| v.as_str())
+                    && let Some(citation) = citation(name, function.get("arguments"))
+                    && !citations.contains(&citation)
+                {
+                    citations.push(citation);
+                }
+            }
+        }
+        citations
+    }
+
+    /// Append the staged messages to the history
+    pub fn commit(self, history: &mut Vec<Message>) {
+        history.extend(self.messages);
+    }
+}
+
+/// Short description of a tool call used as evidence for an answer. Tools
+/// that only track state (todo_write) aren't evidence.
+fn citation(name: &str, arguments: Option<&Value>) -> Option<String> {
+    let arg = |key: &str| {
+        arguments
+            .and_then(|args| args.get(key))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+
+    let detail = match name {
+        "todo_write" => return None,
+        "read" | "write" | "edit" | "data_preview" => arg("file_path"),
+        "grep" | "glob" => arg("pattern").map(|pattern| match arg("path") {
+            Some(path) => format!("'{}' in {}", pattern, path),
+            None => format!("'{}'", pattern),
+        }),
+        "bash" => arg("command").map(|command| match arg("cwd") {
+            Some(cwd) => format!("`{}` in {}", command, cwd),
+            None => format!("`{}`", command),
+        }),
+        "bash_output" | "kill_shell" => arg("shell_id"),
+        "web_fetch" => arg("url"),
+        "semantic_search" | "docs_search" => arg("query").map(|query| format!("'{}'", query)),
+        "deps" | "delete" | "ls" => arg("path"),
+        "compare_files" => arg("expected").zip(arg("actual")).map(|(expected, actual)| format!("{} vs {}", expected, actual)),
+        "move" => arg("source").zip(arg("destination")).map(|(source, destination)| format!("{} -> {}", source, destination)),
+        "task" => arg("description").map(|description| format!("\"{}\"", description)),
+        _ => None,
+    };
+
+    Some(match detail {
+        Some(detail) => format!("{}({})", name, detail),
+        None => name.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turn_commit() {
+        let mut history = Vec::new();
+        let mut turn = Turn::new("hello");
+        turn.push(Message::assistant("hi"));
+        assert_eq!(turn.messages().len(), 2);
+        assert!(history.is_empty());
+
+        turn.commit(&mut history);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].role(), "user");
+        assert_eq!(history[1].content(), "hi");
+    }
+
+    #[test]
+    fn test_citations() {
+        let mut turn = Turn::new("where is execute_tool?");
+        turn.push(Message::assistant_with_tools(
+            "",
+            vec![
+                serde_json::json!({"function": {"name": "grep", "arguments": {"pattern": "execute_tool"}}}),
+                serde_json::json!({"function": {"name": "read", "arguments": {"file_path": "src/agent/agent.rs"}}}),
+                serde_json::json!({"function": {"name": "todo_write", "arguments": {"todos": []}}}),
+            ],
+        ));
+        turn.push(Message::assistant_with_tools(
+            "",
+            vec![serde_json::json!({"function": {"name": "read", "arguments": {"file_path": "src/agent/agent.rs"}}})],
+        ));
+
+        assert_eq!(
+            turn.citations(),
+            vec!["grep('execute_tool')".to_string(), "read(src/agent/agent.rs)".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_bash_citation_with_cwd() {
+        let arguments = serde_json::json!({"command": "cargo test", "cwd": "crates/core"});
+        assert_eq!(
+            citation("bash", Some(&arguments)),
+            Some("bash(`cargo test` in crates/core)".to_string())
+        );
+    }
+}
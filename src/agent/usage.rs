@@ -0,0 +1,167 @@
+use crate::config::ModelPrice;
+use crate::llm::TokenUsage;
+use serde::Serialize;
+
+/// Tokens used by one turn, its verification, compaction and subagent
+/// requests included
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TurnUsage {
+    /// The prompt, or the command (such as `/compact`) outside a turn
+    pub label: String,
+    pub requests: usize,
+    pub tokens: TokenUsage,
+}
+
+/// Token counts of the session, turn by turn (`Agent::usage`, the `/usage` command)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageReport {
+    pub turns: Vec<TurnUsage>,
+    /// Price of the chat model, when the pricing table has it
+    #[serde(skip)]
+    pub price: Option<ModelPrice>,
+}
+
+impl UsageReport {
+    pub(crate) fn new(price: Option<ModelPrice>) -> Self {
+        Self { turns: Vec::new(), price }
+    }
+
+    /// Count the following requests towards a new entry
+    pub(crate) fn begin(&mut self, label: &str) {
+        self.turns.push(TurnUsage {
+            label: label.to_string(),
+            requests: 0,
+            tokens: TokenUsage::default(),
+        });
+    }
+
+    /// Add one request to the current entry. Backends that report nothing
+    /// make the counts estimated.
+    pub(crate) fn record(&mut self, usage: Option<TokenUsage>) {
+        let turn = self.current();
+        turn.requests += 1;
+        turn.tokens += usage.unwrap_or(TokenUsage {
+            estimated: true,
+            ..TokenUsage::default()
+        });
+    }
+
+    /// Add everything `other` (a finished subagent) used to the current entry
+    pub(crate) fn absorb(&mut self, other: &UsageReport) {
+        let (requests, tokens) = (other.requests(), other.total());
+        let turn = self.current();
+        turn.requests += requests;
+        turn.tokens += tokens;
+    }
+
+    fn current(&mut self) -> &mut TurnUsage {
+        if self.turns.is_empty() {
+            self.begin("(startup)");
+        }
+        self.turns.last_mut().expect("an entry was just added")
+    }
+
+    pub fn total(&self) -> TokenUsage {
+        let mut total = TokenUsage::default();
+        for turn in &self.turns {
+            total += turn.tokens;
+        }
+        total
+    }
+
+    pub fn requests(&self) -> usize {
+        self.turns.iter().map(|turn| turn.requests).sum()
+    }
+
+    pub fn last_turn(&self) -> Option<&TurnUsage> {
+        self.turns.last()
+    }
+
+    /// Cost in USD of `tokens`, when the model is priced
+    pub fn cost(&self, tokens: &TokenUsage) -> Option<f64> {
+        self.price
+            .map(|price| price.cost(tokens.prompt_tokens, tokens.output_tokens))
+    }
+
+    fn describe(&self, tokens: &TokenUsage) -> String {
+        let approx = if tokens.estimated { "~" } else { "" };
+        let mut line = format!(
+            "{}{} prompt + {}{} output tokens",
+            approx, tokens.prompt_tokens, approx, tokens.output_tokens
+        );
+        if let Some(cost) = self.cost(tokens) {
+            line.push_str(&format!(", ${:.4}", cost));
+        }
+        line
+    }
+}
+
+impl std::fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Token usage: {} requests in {} turns", self.requests(), self.turns.len())?;
+        for (index, turn) in self.turns.iter().enumerate() {
+            let mut label: String = turn.label.chars().take(40).collect();
+            if turn.label.chars().count() > 40 {
+                label.push('…');
+            }
+            writeln!(
+                f,
+                "  #{:<3} {:<41} {:>2} requests  {}",
+                index + 1,
+                label,
+                turn.requests,
+                self.describe(&turn.tokens)
+            )?;
+        }
+        writeln!(f, "Session: {}", self.describe(&self.total()))?;
+        if self.turns.iter().any(|turn| turn.tokens.estimated) {
+            writeln!(f, "Counts marked ~ are estimated; the backend did not report them")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(prompt_tokens: u64, output_tokens: u64) -> Option<TokenUsage> {
+        Some(TokenUsage {
+            prompt_tokens,
+            output_tokens,
+            estimated: false,
+        })
+    }
+
+    #[test]
+    fn test_usage_per_turn() {
+        let mut usage = UsageReport::new(Some(ModelPrice {
+            input_per_million: 1.0,
+            output_per_million: 4.0,
+        }));
+        usage.begin("explain the parser");
+        usage.record(tokens(1000, 200));
+        usage.record(tokens(1500, 100));
+
+        let mut subagent = UsageReport::default();
+        subagent.begin("Task: survey");
+        subagent.record(tokens(300, 50));
+        subagent.record(tokens(400, 60));
+        usage.absorb(&subagent);
+
+        usage.begin("/compact");
+        usage.record(None);
+
+        assert_eq!(usage.turns[0].requests, 4);
+        assert_eq!(usage.turns[0].tokens, TokenUsage { prompt_tokens: 3200, output_tokens: 410, estimated: false });
+        assert!(usage.turns[1].tokens.estimated);
+        assert_eq!(usage.total().total(), 3610);
+        assert_eq!(usage.requests(), 5);
+
+        let text = usage.to_string();
+        assert!(text.contains("5 requests in 2 turns"));
+        assert!(text.contains("3200 prompt + 410 output tokens, $0.0048"));
+        assert!(text.contains("Session: ~3200 prompt + ~410 output tokens"));
+        assert!(text.contains("marked ~ are estimated"));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::agent::message::Message;
+
+/// Per-tool-result cap on the evidence shown to the verifier
+const MAX_EVIDENCE_CHARS: usize = 4_000;
+
+/// Reply the verifier gives when every claim is supported
+const SUPPORTED: &str = "SUPPORTED";
+
+const VERIFIER_PROMPT: &str = "You are a verification agent. You are given tool outputs collected while \
+     answering a question, followed by the answer. List every factual claim in the answer about files, \
+     code, or command output that the tool outputs do not support, one per line starting with \"- \". \
+     If every claim is supported, reply with exactly SUPPORTED.";
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}\n... (truncated)", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Messages asking the verifier to check `answer` against the tool evidence
+pub fn verification_messages(answer: &str, evidence: &[&str]) -> Vec<Message> {
+    let mut prompt = String::new();
+    for (i, output) in evidence.iter().enumerate() {
+        prompt.push_str(&format!(
+            "## Tool output {}\n{}\n\n",
+            i + 1,
+            truncate(output, MAX_EVIDENCE_CHARS)
+        ));
+    }
+    prompt.push_str(&format!("## Answer\n{}", answer));
+
+    vec![
+        Message::system(VERIFIER_PROMPT),
+        Message::user(prompt),
+    ]
+}
+
+/// Unsupported claims flagged by the verifier, or `None` if it found none
+pub fn unsupported_claims(verdict: &str) -> Option<String> {
+    let verdict = verdict.trim();
+    if verdict.is_empty() || verdict.trim_matches(['.', '*']).eq_ignore_ascii_case(SUPPORTED) {
+        None
+    } else {
+        Some(verdict.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verification_messages() {
+        let long_output = "x".repeat(MAX_EVIDENCE_CHARS + 10);
+        let messages = verification_messages("The file has 3 lines.", &["a\nb\nc", &long_output]);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role(), "system");
+        let prompt = &messages[1].content();
+        assert!(prompt.contains("## Tool output 1\na\nb\nc"));
+        assert!(prompt.contains("(truncated)"));
+        assert!(prompt.ends_with("## Answer\nThe file has 3 lines."));
+    }
+
+    #[test]
+    fn test_unsupported_claims() {
+        assert!(unsupported_claims("SUPPORTED").is_none());
+        assert!(unsupported_claims("  supported.\n").is_none());
+        assert!(unsupported_claims("").is_none());
+        assert_eq!(
+            unsupported_claims("- The function returns a Result").as_deref(),
+            Some("- The function returns a Result")
+        );
+    }
+}
@@ -43,10 +43,34 @@ impl AgentHinter {
         hints.insert(CommandHint::new("/quit"));
         hints.insert(CommandHint::new("/clear"));
         hints.insert(CommandHint::new("/help"));
+        hints.insert(CommandHint::new("/scope"));
+        hints.insert(CommandHint::new("/consensus"));
+        hints.insert(CommandHint::new("/experiment"));
+        hints.insert(CommandHint::new("/share"));
+        hints.insert(CommandHint::new("/context"));
+        hints.insert(CommandHint::new("/usage"));
+        hints.insert(CommandHint::new("/tasks"));
+        hints.insert(CommandHint::new("/cancel"));
+        hints.insert(CommandHint::new("/compact"));
+        hints.insert(CommandHint::new("/forget"));
+        hints.insert(CommandHint::new("/pin"));
+        hints.insert(CommandHint::new("/unpin"));
+        hints.insert(CommandHint::new("/undo"));
+        hints.insert(CommandHint::new("/index"));
+        hints.insert(CommandHint::new("/rag"));
+        hints.insert(CommandHint::new("/recall"));
+        hints.insert(CommandHint::new("/resume"));
+        hints.insert(CommandHint::new("/last"));
         AgentHinter { hints }
     }
 }
 
+impl Default for AgentHinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Hinter for AgentHinter {
     type Hint = CommandHint;
     fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
@@ -0,0 +1,239 @@
+use crate::cli::{TextWrapper, UI, terminal_width};
+use ariste::ui::{Approval, Frontend, Notice, StreamFragment};
+use std::io::{Write, stdin, stdout};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, sleep};
+
+/// Where a streamed reply is: waiting for the first token, inside a
+/// thinking block, or printing content
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Waiting,
+    Thinking,
+    Content,
+}
+
+/// The reply being drawn
+#[derive(Debug)]
+struct Reply {
+    stage: Stage,
+    thinking_buffer: String,
+    /// Wraps streamed content to the terminal width
+    wrapper: TextWrapper,
+}
+
+impl Reply {
+    fn new() -> Self {
+        Self {
+            stage: Stage::Waiting,
+            thinking_buffer: String::new(),
+            wrapper: TextWrapper::new(terminal_width()),
+        }
+    }
+
+    fn end_thinking(&mut self) {
+        if !self.thinking_buffer.is_empty() {
+            UI::thinking_block_content(&self.thinking_buffer);
+            self.thinking_buffer.clear();
+        }
+        UI::thinking_block_end();
+        self.stage = Stage::Content;
+    }
+}
+
+/// The spinner shown while a reply is awaited. Frames are drawn with
+/// `running` locked, so none is drawn once `stop` has cleared the line.
+#[derive(Debug, Default)]
+struct Spinner {
+    /// The spinner currently drawn, if any
+    running: Arc<Mutex<Option<u64>>>,
+    next: AtomicU64,
+}
+
+impl Spinner {
+    fn start(&self) {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let Ok(mut running) = self.running.lock() else {
+            return;
+        };
+        *running = Some(id);
+        let current = self.running.clone();
+        tokio::spawn(async move {
+            let mut ui = UI::new();
+            loop {
+                match current.lock() {
+                    Ok(running) if *running == Some(id) => ui.thinking_start(),
+                    _ => break,
+                }
+                sleep(Duration::from_millis(150)).await;
+            }
+        });
+    }
+
+    /// Stop the spinner and clear its line
+    fn stop(&self) {
+        if let Ok(mut running) = self.running.lock()
+            && running.take().is_some()
+        {
+            UI::clear_line();
+        }
+    }
+}
+
+/// The interactive terminal. The CLI reads prompts with its own line
+/// editor (history, hints); `prompt` serves callers that use the terminal
+/// only through this trait. Streamed replies are drawn with a spinner
+/// while waiting, then the thinking block, then the wrapped content.
+#[derive(Debug)]
+pub struct TerminalFrontend {
+    reply: Mutex<Reply>,
+    spinner: Spinner,
+}
+
+impl TerminalFrontend {
+    pub fn new() -> Self {
+        Self {
+            reply: Mutex::new(Reply::new()),
+            spinner: Spinner::default(),
+        }
+    }
+
+    /// Shared handle, as held by the agent
+    pub fn shared() -> Arc<dyn Frontend> {
+        Arc::new(Self::new())
+    }
+}
+
+impl Default for TerminalFrontend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frontend for TerminalFrontend {
+    fn prompt(&self) -> Option<String> {
+        print!("{}", UI::prompt());
+        stdout().flush().ok();
+        let mut line = String::new();
+        match stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches(['\r', '\n']).to_string()),
+        }
+    }
+
+    fn waiting(&self) {
+        if let Ok(mut reply) = self.reply.lock() {
+            *reply = Reply::new();
+        }
+        self.spinner.start();
+    }
+
+    fn stream(&self, fragment: StreamFragment<'_>) {
+        let Ok(mut reply) = self.reply.lock() else {
+            return;
+        };
+        match fragment {
+            StreamFragment::Thinking(text) => {
+                if reply.stage == Stage::Waiting {
+                    // 停止 spinner 并显示思考块开始
+                    self.spinner.stop();
+                    UI::thinking_block_start();
+                    reply.stage = Stage::Thinking;
+                }
+                // 累积思考内容，逐行输出完整的行
+                reply.thinking_buffer.push_str(text);
+                while let Some(newline_pos) = reply.thinking_buffer.find('\n') {
+                    UI::thinking_block_content(&reply.thinking_buffer[..newline_pos]);
+                    reply.thinking_buffer.drain(..=newline_pos);
+                }
+            }
+            StreamFragment::Content(text) => {
+                if reply.stage == Stage::Waiting {
+                    // 还没有看到 thinking，直接停止 spinner
+                    self.spinner.stop();
+                    UI::response_start();
+                    reply.stage = Stage::Content;
+                } else if reply.stage == Stage::Thinking {
+                    reply.end_thinking();
+                }
+                print!("{}", reply.wrapper.push(text));
+                stdout().flush().ok();
+            }
+            StreamFragment::End => {
+                self.spinner.stop();
+                match reply.stage {
+                    Stage::Thinking => reply.end_thinking(),
+                    Stage::Content => println!("{}", reply.wrapper.finish()),
+                    // 内容由调用方输出，spinner 已清除
+                    Stage::Waiting => {}
+                }
+                stdout().flush().ok();
+            }
+        }
+    }
+
+    fn response(&self, content: &str) {
+        UI::response_content(content);
+    }
+
+    fn citations(&self, citations: &[String]) {
+        UI::citations(citations);
+    }
+
+    fn tool_start(&self, name: &str, args: Option<&str>) {
+        UI::tool_start(name, args);
+    }
+
+    fn tool_heartbeat(&self, elapsed: &str, detail: Option<&str>) {
+        UI::tool_heartbeat(elapsed, detail);
+    }
+
+    fn tool_result(&self, name: &str, result: &str) {
+        if name == "todo_write" {
+            // 待办列表按行显示，不压缩到工具行上
+            println!();
+            for line in result.lines() {
+                println!("{}", line);
+            }
+        } else {
+            UI::tool_content(result);
+        }
+        UI::tool_end();
+    }
+
+    fn tool_error(&self, error: &str) {
+        UI::tool_error(error);
+    }
+
+    fn confirm(&self, question: &str) -> bool {
+        self.spinner.stop();
+        UI::confirm(question)
+    }
+
+    fn diff(&self, diff: &str) {
+        UI::diff(diff);
+    }
+
+    fn approve(&self, request: &str) -> Approval {
+        self.spinner.stop();
+        UI::approve(request)
+    }
+
+    fn notify(&self, notice: Notice, message: &str) {
+        match notice {
+            Notice::Info => UI::info(message),
+            Notice::Success => UI::success(message),
+            Notice::Warning => UI::warning(message),
+            Notice::Error => UI::error(message),
+        }
+    }
+
+    fn redirect(&self) -> Option<String> {
+        UI::redirect()
+    }
+
+    fn subagent_output(&self, task: &str, line: &str) {
+        UI::subagent_line(task, line);
+    }
+}
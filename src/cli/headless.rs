@@ -0,0 +1,134 @@
+use ariste::agent::{AgentEvent, Message};
+use ariste::ui::{Notice, glyphs};
+use ariste::{Agent, Error};
+use colored::Colorize;
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// What a `--prompt` run did, collected from the turn's events. Shown as
+/// progress on stderr, or written out whole as JSON (`--output-format json`).
+pub struct HeadlessRun {
+    json: bool,
+    started: Instant,
+    tools: Vec<Value>,
+    /// Calls awaiting their result, oldest first, with when they started.
+    /// A batch of concurrent calls starts them all before any result, and
+    /// results come in call order.
+    pending: VecDeque<(usize, Instant)>,
+    notices: Vec<Value>,
+    answer: String,
+}
+
+impl HeadlessRun {
+    pub fn new(json: bool) -> Self {
+        Self {
+            json,
+            started: Instant::now(),
+            tools: Vec::new(),
+            pending: VecDeque::new(),
+            notices: Vec::new(),
+            answer: String::new(),
+        }
+    }
+
+    pub fn answer(&self) -> &str {
+        &self.answer
+    }
+
+    pub fn on_event(&mut self, event: AgentEvent) {
+        match event {
+            AgentEvent::ToolStart { name, arguments } => {
+                if !self.json {
+                    eprintln!("{} {}", glyphs().tool.bright_magenta(), name.bright_magenta());
+                }
+                let arguments = arguments.and_then(|arguments| serde_json::from_str::<Value>(&arguments).ok());
+                self.pending.push_back((self.tools.len(), Instant::now()));
+                self.tools.push(json!({"name": name, "arguments": arguments}));
+            }
+            AgentEvent::ToolResult { result, .. } => self.finish_tool("result", result),
+            AgentEvent::ToolError(error) => {
+                if !self.json {
+                    eprintln!("{} {}", glyphs().error.bright_red(), error.bright_red());
+                }
+                self.finish_tool("error", error);
+            }
+            AgentEvent::Notice(notice, message) => {
+                if !self.json && matches!(notice, Notice::Warning | Notice::Error) {
+                    eprintln!("{} {}", glyphs().warning.bright_yellow(), message.bright_yellow());
+                }
+                self.notices.push(json!({"level": format!("{:?}", notice).to_lowercase(), "message": message}));
+            }
+            AgentEvent::SubagentOutput { task, line } => {
+                if !self.json {
+                    eprintln!("  {} {} {}", task.cyan(), glyphs().border().dimmed(), line.dimmed());
+                }
+            }
+            AgentEvent::Done(answer) => self.answer = answer,
+            AgentEvent::ThinkingDelta(_) | AgentEvent::ContentDelta(_) => {}
+        }
+    }
+
+    fn finish_tool(&mut self, key: &str, text: String) {
+        if let Some((index, started)) = self.pending.pop_front() {
+            let tool = &mut self.tools[index];
+            tool[key] = Value::String(text);
+            tool["duration_ms"] = json!(started.elapsed().as_millis() as u64);
+        }
+    }
+
+    /// The JSON document for the finished turn
+    pub fn report(&self, agent: &Agent, prompt: &str, outcome: &Result<(), Error>) -> Value {
+        // 本轮从最后一条用户消息开始；失败的轮次不会提交到历史
+        let turn: &[Message] = match agent.messages.iter().rposition(Message::is_user) {
+            Some(start) if outcome.is_ok() => &agent.messages[start..],
+            _ => &[],
+        };
+        let usage = agent.usage();
+        let tokens = usage.last_turn().map(|turn| turn.tokens).unwrap_or_default();
+        json!({
+            "session": agent.session_id(),
+            "seed": agent.seed(),
+            "prompt": prompt,
+            "answer": self.answer,
+            "is_error": outcome.is_err(),
+            "error": outcome.as_ref().err().map(|e| e.to_string()),
+            "duration_ms": self.started.elapsed().as_millis() as u64,
+            "messages": turn,
+            "tool_calls": self.tools,
+            "notices": self.notices,
+            "usage": {
+                "estimated": tokens.estimated,
+                "requests": usage.last_turn().map_or(0, |turn| turn.requests),
+                "prompt_tokens": tokens.prompt_tokens,
+                "output_tokens": tokens.output_tokens,
+                "context_tokens": agent.context_report().total,
+                "cost_usd": usage.cost(&tokens),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_results_match_their_calls() {
+        let mut run = HeadlessRun::new(true);
+        for path in ["a.rs", "b.rs"] {
+            run.on_event(AgentEvent::ToolStart {
+                name: "read".to_string(),
+                arguments: Some(json!({"file_path": path}).to_string()),
+            });
+        }
+        run.on_event(AgentEvent::ToolResult { name: "read".to_string(), result: "fn a() {}".to_string() });
+        run.on_event(AgentEvent::ToolError("b.rs: not found".to_string()));
+
+        assert_eq!(run.tools[0]["arguments"]["file_path"], "a.rs");
+        assert_eq!(run.tools[0]["result"], "fn a() {}");
+        assert_eq!(run.tools[1]["error"], "b.rs: not found");
+        assert!(run.tools[1].get("result").is_none());
+        assert!(run.tools.iter().all(|tool| tool["duration_ms"].is_u64()));
+    }
+}
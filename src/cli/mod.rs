@@ -1,3 +1,13 @@
 mod command;
+mod frontend;
+mod headless;
+mod terminal;
+pub mod typeahead;
+mod wrap;
 
 pub use command::AgentHinter;
+pub use frontend::TerminalFrontend;
+pub use headless::HeadlessRun;
+pub use terminal::{SpinnerStyle, UI, WelcomeScreen};
+pub use typeahead::TypeAhead;
+pub use wrap::{TextWrapper, terminal_width};
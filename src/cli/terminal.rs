@@ -0,0 +1,832 @@
+use crate::cli::typeahead;
+use crate::cli::wrap::{TextWrapper, center_padding, display_width, terminal_width, wrap_line};
+use ariste::config::{SpinnerConfig, WelcomeConfig};
+use ariste::ui::{Approval, Glyphs, glyphs};
+use colored::Colorize;
+use std::io::{stdout, IsTerminal, Write};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+// Claude Code 风格的 ASCII spinner 字符
+const SPINNER_CHARS: &[&str] = &["·", "✻", "✽", "✶", "✳", "✢"];
+// 纯 ASCII 模式的 spinner 字符
+const ASCII_SPINNER_CHARS: &[&str] = &["|", "/", "-", "\\"];
+
+// 状态消息
+const STATUS_MESSAGES: &[&str] = &[
+    "Sparkling",
+    "Sketching",
+    "Thinking",
+    "Polishing",
+    "Crafting",
+];
+
+// 欢迎横幅：标题和边框内宽（显示列数）
+const BANNER_TITLE: &str = "Ariste AI Agent";
+const BANNER_WIDTH: usize = 40;
+const WELCOME_MESSAGE: &str = "Working directory: {workdir}";
+
+// 分隔线最宽的显示列数
+const SEPARATOR_WIDTH: usize = 72;
+
+/// Spinner frames and status words, or a static line when animations
+/// are off (dumb terminals, output redirected to a file)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpinnerStyle {
+    pub frames: Vec<String>,
+    pub messages: Vec<String>,
+    pub animated: bool,
+}
+
+impl Default for SpinnerStyle {
+    fn default() -> Self {
+        Self {
+            frames: SPINNER_CHARS.iter().map(|c| c.to_string()).collect(),
+            messages: STATUS_MESSAGES.iter().map(|m| m.to_string()).collect(),
+            animated: true,
+        }
+    }
+}
+
+impl SpinnerStyle {
+    /// Style from the `spinner` and `animations` settings. Without an
+    /// explicit setting, animations are on only for an interactive terminal.
+    /// In `ascii` mode the built-in frames are plain ASCII.
+    pub fn from_settings(spinner: Option<&SpinnerConfig>, animations: Option<bool>, ascii: bool) -> Self {
+        let mut builtin = Self::default();
+        if ascii {
+            builtin.frames = ASCII_SPINNER_CHARS.iter().map(|c| c.to_string()).collect();
+        }
+        let pick = |configured: Option<&Vec<String>>, fallback: Vec<String>| match configured {
+            Some(list) if !list.is_empty() => list.clone(),
+            _ => fallback,
+        };
+        let animated = animations.unwrap_or_else(|| {
+            stdout().is_terminal() && std::env::var("TERM").map(|term| term != "dumb").unwrap_or(true)
+        });
+        Self {
+            frames: pick(spinner.and_then(|s| s.frames.as_ref()), builtin.frames),
+            messages: pick(spinner.and_then(|s| s.messages.as_ref()), builtin.messages),
+            animated,
+        }
+    }
+}
+
+/// What the welcome screen shows, from the `welcome` settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct WelcomeScreen {
+    /// Shown at startup; `/help` and `/clear` show it regardless
+    pub banner: bool,
+    pub title: String,
+    /// Line under the banner, placeholders not yet filled in
+    pub message: String,
+    pub help: Option<String>,
+    /// Value of the `{model}` placeholder
+    pub model: String,
+}
+
+impl Default for WelcomeScreen {
+    fn default() -> Self {
+        Self {
+            banner: true,
+            title: BANNER_TITLE.to_string(),
+            message: WELCOME_MESSAGE.to_string(),
+            help: None,
+            model: String::new(),
+        }
+    }
+}
+
+impl WelcomeScreen {
+    /// Screen from the `welcome` settings. Without an explicit `banner`
+    /// setting it is shown at startup only on an interactive terminal.
+    pub fn from_settings(welcome: Option<&WelcomeConfig>, model: &str) -> Self {
+        let builtin = Self::default();
+        let welcome = welcome.cloned().unwrap_or_default();
+        Self {
+            banner: welcome.banner.unwrap_or_else(|| stdout().is_terminal()),
+            title: welcome.title.filter(|title| !title.is_empty()).unwrap_or(builtin.title),
+            message: welcome.message.unwrap_or(builtin.message),
+            help: welcome.help,
+            model: model.to_string(),
+        }
+    }
+
+    /// `template` with `{workdir}`, `{model}` and `{version}` filled in
+    pub fn fill(&self, template: &str, workdir: &std::path::Path) -> String {
+        template
+            .replace("{workdir}", &workdir.display().to_string())
+            .replace("{model}", &self.model)
+            .replace("{version}", env!("CARGO_PKG_VERSION"))
+    }
+}
+
+// 全局样式：启动时按配置设置一次，之后所有 UI 实例共用
+static STYLE: RwLock<Option<SpinnerStyle>> = RwLock::new(None);
+static WELCOME: RwLock<Option<WelcomeScreen>> = RwLock::new(None);
+
+fn style() -> SpinnerStyle {
+    STYLE.read().ok().and_then(|style| style.clone()).unwrap_or_default()
+}
+
+fn welcome_screen() -> WelcomeScreen {
+    WELCOME.read().ok().and_then(|screen| screen.clone()).unwrap_or_default()
+}
+
+pub struct UI {
+    spinner_index: usize,
+    status_index: usize,
+    last_update: Instant,
+    /// The static "working…" line has been printed for this wait
+    static_shown: bool,
+}
+
+impl UI {
+    pub fn new() -> Self {
+        Self {
+            spinner_index: 0,
+            status_index: 0,
+            last_update: Instant::now(),
+            static_shown: false,
+        }
+    }
+
+    /// Set the spinner style used from now on
+    pub fn configure(spinner: SpinnerStyle) {
+        if let Ok(mut style) = STYLE.write() {
+            *style = Some(spinner);
+        }
+    }
+
+    /// Set the welcome screen shown from now on
+    pub fn configure_welcome(screen: WelcomeScreen) {
+        if let Ok(mut welcome) = WELCOME.write() {
+            *welcome = Some(screen);
+        }
+    }
+
+    /// Whether the welcome screen is shown at startup
+    pub fn shows_banner() -> bool {
+        welcome_screen().banner
+    }
+
+    /// 打印欢迎信息 - Claude Code 风格
+    pub fn welcome(workdir: &std::path::Path) {
+        let screen = welcome_screen();
+        let glyphs = glyphs();
+        let title = screen.title.as_str();
+        println!();
+        if glyphs.accessible {
+            // 无障碍模式：不画边框，逐行输出纯文本
+            println!("Welcome to {}", title);
+            for line in screen.fill(&screen.message, workdir).lines() {
+                println!("{}", line);
+            }
+        } else {
+            Self::banner(&screen, &glyphs, workdir);
+        }
+        println!();
+        if let Some(help) = &screen.help {
+            println!("{}", screen.fill(help, workdir).trim_end());
+            println!();
+        }
+        Self::print_available_commands();
+    }
+
+    /// 欢迎横幅：标题边框和其下的信息行
+    fn banner(screen: &WelcomeScreen, glyphs: &Glyphs, workdir: &std::path::Path) {
+        let [top_left, top_right, bottom_left, bottom_right, edge, side] = glyphs.banner_box();
+        let title = screen.title.as_str();
+        println!("{} {}", glyphs.star().bright_yellow(), "Welcome to".dimmed());
+        // 按显示宽度计算边框，标题含中文等宽字符时也能对齐
+        let inner = BANNER_WIDTH.max(display_width(title) + 4);
+        let (left, right) = center_padding(title, inner);
+        let blank = format!("  {}{}{}", side, " ".repeat(inner), side);
+        println!("{}", format!("  {}{}{}", top_left, edge.repeat(inner), top_right).bright_yellow());
+        println!("{}", blank.bright_yellow());
+        println!(
+            "  {}{}{}{}{}",
+            side.bright_yellow(),
+            " ".repeat(left),
+            title.bright_cyan().bold(),
+            " ".repeat(right),
+            side.bright_yellow(),
+        );
+        println!("{}", blank.bright_yellow());
+        println!("{}", format!("  {}{}{}", bottom_left, edge.repeat(inner), bottom_right).bright_yellow());
+        println!();
+        for line in screen.fill(&screen.message, workdir).lines() {
+            println!("{} {}", glyphs.border().dimmed(), line.bright_white());
+        }
+    }
+
+    /// 打印可用命令
+    fn print_available_commands() {
+        println!("{}", "Available commands:".dimmed());
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "help".bright_green(),
+            "Show this help message".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "clear".bright_green(),
+            "Clear the terminal screen".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "scope <path>|reset".bright_green(),
+            "Restrict read/glob/grep to a subdirectory".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "consensus [n] <question>".bright_green(),
+            "Ask n models in parallel and merge their answers".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "experiment".bright_green(),
+            "Compare the variants of the configured A/B experiment".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "context".bright_green(),
+            "Show what is taking up the context window".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "usage".bright_green(),
+            "Show tokens (and cost, when priced) per turn and for the session".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "tasks [id]".bright_green(),
+            "List background subagent tasks, or wait for one and show its result".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "cancel <id>".bright_green(),
+            "Stop a background subagent task".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "compact".bright_green(),
+            "Summarize older turns to free context".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "forget <n|a-b|tool>".bright_green(),
+            "Remove messages from history (numbers from /context)".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "pin <file|note>".bright_green(),
+            "Keep a file or note in context; /unpin <n> removes it".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "index [rebuild]".bright_green(),
+            "Show the code index searched by semantic_search, or build it again".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "rag [on|off]".bright_green(),
+            "Add the code and docs closest to each message to the turn".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "undo".bright_green(),
+            "Restore the last file deleted or moved by the agent".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "last".bright_green(),
+            "Print the previous answer again".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "resume [id]".bright_green(),
+            "Continue a saved conversation: pick from a list, or give its id".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "share".bright_green(),
+            "Upload the conversation as a secret GitHub gist".dimmed()
+        );
+        println!(
+            "  {}{}  {}",
+            "/".bright_green(),
+            "quit".bright_green(),
+            "Exit the program".dimmed()
+        );
+        println!(
+            "  {}  {}",
+            "Ctrl-C".bright_green(),
+            "Stop the reply being generated; press again to exit".dimmed()
+        );
+        println!(
+            "  {}  {}",
+            "Esc".bright_green(),
+            "Pause after the running tools and add a correction".dimmed()
+        );
+        println!(
+            "  {}  {}",
+            "Typing".bright_green(),
+            "Messages typed while a reply streams are queued and sent after it".dimmed()
+        );
+        println!();
+    }
+
+    /// 打印用户输入提示符 - Claude Code 风格
+    pub fn prompt() -> String {
+        format!("{} ", glyphs().prompt.bright_cyan())
+    }
+
+    /// 显示正在思考状态 - 带 spinner 动画
+    pub fn thinking_start(&mut self) {
+        let style = style();
+        if !style.animated {
+            // 不支持光标控制的终端或日志文件里只打印一行静态提示
+            if !self.static_shown {
+                let glyphs = glyphs();
+                let word = if glyphs.accessible { "Thinking" } else { "working" };
+                println!("{}", format!("{}{}", word, glyphs.ellipsis()).dimmed());
+                self.static_shown = true;
+            }
+            return;
+        }
+        let spinner = &style.frames[self.spinner_index % style.frames.len()];
+        let status = &style.messages[self.status_index % style.messages.len()];
+
+        print!(
+            "\r{} {}{} ",
+            spinner.bright_yellow(),
+            status.bright_yellow(),
+            glyphs().ellipsis().dimmed()
+        );
+        stdout().flush().ok();
+
+        // 更新 spinner 索引
+        if self.last_update.elapsed() >= Duration::from_millis(150) {
+            self.spinner_index = (self.spinner_index + 1) % style.frames.len();
+            // 偶尔切换状态消息
+            if self.spinner_index == 0 {
+                self.status_index = (self.status_index + 1) % style.messages.len();
+            }
+            self.last_update = Instant::now();
+        }
+    }
+
+    /// 重置 spinner 状态
+    pub fn reset_spinner(&mut self) {
+        self.spinner_index = 0;
+        self.status_index = 0;
+        self.last_update = Instant::now();
+        self.static_shown = false;
+    }
+
+    /// 清除当前行
+    pub fn clear_line() {
+        // 静态提示已经换行，无需清除，也不输出转义序列
+        if !style().animated {
+            return;
+        }
+        print!("\r\x1b[2K\r");
+        stdout().flush().ok();
+    }
+
+    /// 清除上一行（用于退出时清除 prompt）
+    pub fn clear_previous_line() {
+        // 无障碍模式不改写已输出的行
+        if glyphs().accessible {
+            return;
+        }
+        print!("\r\x1b[1A\x1b[2K\r");
+        stdout().flush().ok();
+    }
+
+    /// 在每次用户输入和助手回复之前打印分隔线：时间和轮次，方便在长滚动记录中定位
+    pub fn separator(turn: usize, speaker: &str) {
+        let time = chrono::Local::now().format("%H:%M:%S").to_string();
+        let width = terminal_width().unwrap_or(SEPARATOR_WIDTH).min(SEPARATOR_WIDTH);
+        let glyphs = glyphs();
+        if glyphs.accessible {
+            println!("{}", format!("Turn {}, {}, {}", turn, speaker, time).bright_black());
+            return;
+        }
+        println!("{}", separator_line(&time, turn, speaker, width, glyphs.rule()).bright_black());
+    }
+
+    /// 显示响应开始
+    pub fn response_start() {}
+
+    /// 显示响应结束
+    pub fn response_end() {}
+
+    /// 输出未流式显示的响应内容
+    pub fn response_content(content: &str) {
+        if !content.is_empty() {
+            let mut wrapper = TextWrapper::new(terminal_width());
+            let mut wrapped = wrapper.push(content);
+            wrapped.push_str(&wrapper.finish());
+            println!("{}", wrapped);
+        }
+    }
+
+    /// 显示思考块开始 - Claude Code 风格
+    pub fn thinking_block_start() {
+        if glyphs().accessible {
+            println!("{}", "Thinking:".dimmed());
+            return;
+        }
+        println!(
+            "{} {}",
+            glyphs().corner_top().dimmed(),
+            "Thinking".dimmed().italic()
+        );
+    }
+
+    /// 显示思考块内容
+    pub fn thinking_block_content(content: &str) {
+        // 按终端宽度折行（减去边框和空格），避免终端自动换行打断边框
+        let width = terminal_width().map(|width| width.saturating_sub(2));
+        let glyphs = glyphs();
+        if glyphs.accessible {
+            // 交给屏幕阅读器自行折行
+            for line in content.lines() {
+                println!("{}", line.dimmed());
+            }
+            return;
+        }
+        let border = glyphs.border();
+        for line in content.lines() {
+            let pieces = match width {
+                Some(width) => wrap_line(line, width),
+                None => vec![line.to_string()],
+            };
+            for piece in pieces {
+                println!("{} {}", border.dimmed(), piece.dimmed().italic());
+            }
+        }
+    }
+
+    /// 显示思考块结束
+    pub fn thinking_block_end() {
+        let glyphs = glyphs();
+        if glyphs.accessible {
+            println!("{}", "End of thinking".dimmed());
+            return;
+        }
+        println!("{}", glyphs.corner_bottom().dimmed());
+    }
+
+    /// 在回复下方显示所依据的工具调用
+    pub fn citations(citations: &[String]) {
+        if citations.is_empty() {
+            return;
+        }
+        println!(
+            "{} {}",
+            glyphs().citation().dimmed(),
+            format!("based on {}", citations.join(", ")).dimmed().italic()
+        );
+    }
+
+    /// 显示工具调用开始 - Claude Code 风格
+    pub fn tool_start(tool_name: &str, args: Option<&str>) {
+        // 格式化参数，使其更紧凑
+        let formatted_args = if let Some(args_str) = args {
+            // 移除换行和多余空格，使 JSON 更紧凑
+            let compact = args_str
+                .lines()
+                .map(|line| line.trim())
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some(compact)
+        } else {
+            None
+        };
+
+        let glyphs = glyphs();
+        if glyphs.accessible {
+            // 一行一个事件：结果另起一行
+            match formatted_args {
+                Some(args) if !args.is_empty() && args != "null" => println!("{} {}: {}", glyphs.tool, tool_name, args),
+                _ => println!("{} {}", glyphs.tool, tool_name),
+            }
+            return;
+        }
+        let icon = glyphs.tool;
+        match formatted_args {
+            Some(args) if !args.is_empty() && args != "null" => {
+                print!(
+                    "{} {} {}",
+                    icon.bright_magenta(),
+                    tool_name.bright_magenta(),
+                    args.dimmed()
+                );
+            }
+            _ => {
+                print!("{} {}", icon.bright_magenta(), tool_name.bright_magenta());
+            }
+        }
+        stdout().flush().ok();
+    }
+
+    /// 显示工具调用内容
+    pub fn tool_content(content: &str) {
+        // 在同一行显示结果
+        let trimmed = content
+            .lines()
+            .map(|l| l.trim())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if glyphs().accessible {
+            if !trimmed.is_empty() {
+                println!("Result: {}", trimmed);
+            }
+        } else if !trimmed.is_empty() {
+            println!(" {} {}", "=".bright_black(), trimmed.bright_green());
+        } else {
+            println!();
+        }
+    }
+
+    /// 长时间运行的工具的心跳：已运行时间和最近的输出（与 tool_start 一样不换行，结果接在后面）
+    pub fn tool_heartbeat(elapsed: &str, detail: Option<&str>) {
+        let glyphs = glyphs();
+        if glyphs.accessible {
+            match detail {
+                Some(detail) => println!("Still running ({}): {}", elapsed, detail),
+                None => println!("Still running ({})", elapsed),
+            }
+            return;
+        }
+        let mark = glyphs.heartbeat();
+        match detail {
+            Some(detail) => print!("\n  {} {} {}", mark.bright_black(), format!("still running ({})", elapsed).dimmed(), detail.bright_black()),
+            None => print!("\n  {} {}", mark.bright_black(), format!("still running ({})", elapsed).dimmed()),
+        }
+        stdout().flush().ok();
+    }
+
+    /// 显示工具调用结束
+    pub fn tool_end() {
+        // 不需要额外显示，结果已在 tool_content 中显示
+    }
+
+    /// 显示工具调用错误
+    pub fn tool_error(error: &str) {
+        println!("{} {}", glyphs().error.bright_red(), error.bright_red());
+    }
+
+    /// 打印错误信息 - Claude Code 风格
+    pub fn error(msg: &str) {
+        println!("\n{} {}", glyphs().error.bright_red(), msg.bright_red());
+    }
+
+    /// 打印信息提示
+    pub fn info(msg: &str) {
+        println!("{} {}", glyphs().info.bright_blue(), msg.bright_blue());
+    }
+
+    /// 打印成功信息
+    pub fn success(msg: &str) {
+        println!("{} {}", glyphs().success.bright_green(), msg.bright_green());
+    }
+
+    /// 子代理的输出行，缩进并以任务编号开头：`  task#3 │ ...`
+    pub fn subagent_line(task: &str, line: &str) {
+        println!("  {} {} {}", task.cyan(), glyphs().border().dimmed(), line.dimmed());
+    }
+
+    /// 回复过程中输入的下一条消息：排队，等本轮结束后发送
+    pub fn queued(line: &str) {
+        println!(
+            "\n{} {} {}",
+            glyphs().prompt.bright_cyan(),
+            line,
+            "(queued; sent when this reply ends, Ctrl-C stops the reply)".dimmed()
+        );
+    }
+
+    /// 打印警告信息
+    pub fn warning(msg: &str) {
+        println!("{} {}", glyphs().warning.bright_yellow(), msg.bright_yellow());
+    }
+
+    /// 显示统一格式的 diff，增删行着色
+    pub fn diff(diff: &str) {
+        for line in diff.lines() {
+            if line.starts_with("+++") || line.starts_with("---") {
+                println!("{}", line.bold());
+            } else if line.starts_with('+') {
+                println!("{}", line.green());
+            } else if line.starts_with('-') {
+                println!("{}", line.red());
+            } else if line.starts_with("@@") {
+                println!("{}", line.cyan());
+            } else {
+                println!("{}", line.dimmed());
+            }
+        }
+    }
+
+    /// 询问用户是否继续，输入 y/yes 时返回 true
+    pub fn confirm(question: &str) -> bool {
+        print!("{} {} {} ", "?".bright_yellow(), question.yellow(), "[y/N]".dimmed());
+        stdout().flush().ok();
+
+        let mut answer = String::new();
+        if typeahead::paused(|| std::io::stdin().read_line(&mut answer)).is_err() {
+            return false;
+        }
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+
+    /// 询问是否允许执行工具：y 本次允许，a 总是允许，d 总是拒绝，其余拒绝
+    pub fn approve(request: &str) -> Approval {
+        print!(
+            "{} {} {} ",
+            "?".bright_yellow(),
+            request.yellow(),
+            "[y]es / [N]o / [a]lways / [d]eny always".dimmed()
+        );
+        stdout().flush().ok();
+
+        let mut answer = String::new();
+        if typeahead::paused(|| std::io::stdin().read_line(&mut answer)).is_err() {
+            return Approval::Deny;
+        }
+        parse_approval(&answer)
+    }
+
+    /// Esc 暂停后询问对本轮的纠正；直接回车则按原计划继续
+    pub fn redirect() -> Option<String> {
+        print!(
+            "{} {} {} ",
+            glyphs().prompt.bright_cyan(),
+            "Correction for the agent".yellow(),
+            "[Enter to go on unchanged]".dimmed()
+        );
+        stdout().flush().ok();
+
+        let mut answer = String::new();
+        if typeahead::paused(|| std::io::stdin().read_line(&mut answer)).is_err() {
+            return None;
+        }
+        let answer = answer.trim();
+        (!answer.is_empty()).then(|| answer.to_string())
+    }
+
+    /// 列出编号选项让用户选择，返回所选下标；直接回车或输入无效时返回 None
+    pub fn choose(question: &str, options: &[String]) -> Option<usize> {
+        for (index, option) in options.iter().enumerate() {
+            println!("  {} {}", format!("{:>2}.", index + 1).bright_cyan(), option);
+        }
+        print!(
+            "{} {} {} ",
+            "?".bright_yellow(),
+            question.yellow(),
+            format!("[1-{}, Enter to cancel]", options.len()).dimmed()
+        );
+        stdout().flush().ok();
+
+        let mut answer = String::new();
+        if typeahead::paused(|| std::io::stdin().read_line(&mut answer)).is_err() {
+            return None;
+        }
+        parse_choice(&answer, options.len())
+    }
+
+    /// 清除屏幕
+    pub fn clear() {
+        print!("\x1b[2J\x1b[H");
+        stdout().flush().ok();
+    }
+
+    /// 显示退出信息
+    pub fn goodbye() {
+        println!("{} {}", glyphs().star().bright_yellow(), "Goodbye!".bright_yellow());
+    }
+}
+
+/// Answer to `UI::approve`; anything unrecognized declines
+fn parse_approval(answer: &str) -> Approval {
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Approval::Once,
+        "a" | "always" => Approval::Always,
+        "d" | "deny" | "never" => Approval::Never,
+        _ => Approval::Deny,
+    }
+}
+
+/// 1-based answer to `UI::choose` as an index into `count` options
+fn parse_choice(answer: &str, count: usize) -> Option<usize> {
+    let choice: usize = answer.trim().parse().ok()?;
+    (1..=count).contains(&choice).then(|| choice - 1)
+}
+
+/// `── 14:32:05 · turn 3 · you ───…` filling `width` columns, drawn with `rule`
+fn separator_line(time: &str, turn: usize, speaker: &str, width: usize, rule: &str) -> String {
+    let dot = if rule.is_ascii() { "-" } else { "·" };
+    let label = format!("{} {} {} turn {} {} {} ", rule.repeat(2), time, dot, turn, dot, speaker);
+    let rest = width.saturating_sub(display_width(&label));
+    format!("{}{}", label, rule.repeat(rest))
+}
+
+impl Default for UI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spinner_style_from_settings() {
+        let style = SpinnerStyle::from_settings(None, Some(true), false);
+        assert_eq!(style, SpinnerStyle::default());
+        let style = SpinnerStyle::from_settings(None, Some(true), true);
+        assert!(style.frames.iter().all(|frame| frame.is_ascii()));
+
+        let spinner = SpinnerConfig {
+            frames: Some(vec!["|".to_string(), "/".to_string()]),
+            messages: Some(vec![]),
+        };
+        let style = SpinnerStyle::from_settings(Some(&spinner), Some(false), false);
+        assert_eq!(style.frames, vec!["|", "/"]);
+        // An empty list keeps the built-in words
+        assert_eq!(style.messages, SpinnerStyle::default().messages);
+        assert!(!style.animated);
+    }
+
+    #[test]
+    fn test_welcome_screen_from_settings() {
+        let welcome = WelcomeConfig {
+            banner: Some(false),
+            title: Some(String::new()),
+            message: Some("{model} in {workdir} (v{version})".to_string()),
+            help: None,
+        };
+        let screen = WelcomeScreen::from_settings(Some(&welcome), "qwen3");
+        assert!(!screen.banner);
+        // An empty title keeps the built-in one
+        assert_eq!(screen.title, BANNER_TITLE);
+        let line = screen.fill(&screen.message, std::path::Path::new("/work"));
+        assert_eq!(line, format!("qwen3 in /work (v{})", env!("CARGO_PKG_VERSION")));
+
+        let screen = WelcomeScreen::from_settings(None, "qwen3");
+        assert_eq!(screen.message, WELCOME_MESSAGE);
+        assert_eq!(screen.help, None);
+    }
+
+    #[test]
+    fn test_parse_approval() {
+        assert_eq!(parse_approval("y\n"), Approval::Once);
+        assert_eq!(parse_approval("Always"), Approval::Always);
+        assert_eq!(parse_approval("d"), Approval::Never);
+        assert_eq!(parse_approval("\n"), Approval::Deny);
+        assert_eq!(parse_approval("maybe"), Approval::Deny);
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("2\n", 3), Some(1));
+        assert_eq!(parse_choice(" 1 ", 3), Some(0));
+        assert_eq!(parse_choice("\n", 3), None);
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("two", 3), None);
+    }
+
+    #[test]
+    fn test_separator_line() {
+        let line = separator_line("14:32:05", 3, "you", 40, "─");
+        assert!(line.starts_with("── 14:32:05 · turn 3 · you ─"));
+        assert_eq!(display_width(&line), 40);
+        // Labels longer than the width are kept whole
+        assert_eq!(separator_line("14:32:05", 12, "ariste", 10, "─"), "── 14:32:05 · turn 12 · ariste ");
+        assert_eq!(separator_line("14:32:05", 1, "you", 30, "-"), "-- 14:32:05 - turn 1 - you ---");
+    }
+}
@@ -0,0 +1,308 @@
+use crate::cli::UI;
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// How long the reader waits for a key before checking whether to stop
+const POLL_MS: i32 = 50;
+
+/// Keys typed while a turn runs, turned into lines. Echo is off so the
+/// keys don't mix with the reply; Enter queues the line, Backspace and
+/// Ctrl-U edit it, and escape sequences (arrow keys) are dropped.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    partial: String,
+    /// Bytes of a UTF-8 character still incomplete
+    pending: Vec<u8>,
+    /// Inside an escape sequence, which ends at its final letter or `~`
+    escape: bool,
+}
+
+impl LineBuffer {
+    /// Add `bytes`; returns the lines they complete
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in bytes {
+            if self.escape {
+                self.escape = !(byte.is_ascii_alphabetic() || byte == b'~');
+                continue;
+            }
+            match byte {
+                b'\r' | b'\n' => {
+                    let line = std::mem::take(&mut self.partial);
+                    if !line.trim().is_empty() {
+                        lines.push(line.trim().to_string());
+                    }
+                }
+                0x7f | 0x08 => {
+                    self.partial.pop();
+                }
+                0x15 => self.partial.clear(),
+                0x1b => self.escape = true,
+                byte if byte < 0x20 => {}
+                byte => {
+                    self.pending.push(byte);
+                    match std::str::from_utf8(&self.pending) {
+                        Ok(text) => {
+                            self.partial.push_str(text);
+                            self.pending.clear();
+                        }
+                        // 多字节字符尚未读完
+                        Err(e) if e.error_len().is_none() => {}
+                        Err(_) => self.pending.clear(),
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    /// Text typed after the last complete line
+    pub fn partial(&self) -> &str {
+        &self.partial
+    }
+}
+
+/// What was typed during a turn: lines to send next, in order, and the
+/// unfinished one to put back into the prompt
+#[derive(Debug, Default)]
+pub struct Typed {
+    pub lines: Vec<String>,
+    pub partial: String,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    stop: AtomicBool,
+    paused: AtomicBool,
+    /// Held while the reader waits for or reads keys; `paused` takes it to
+    /// be sure no key is read after it returns
+    reading: Mutex<()>,
+    buffer: Mutex<LineBuffer>,
+    lines: Mutex<VecDeque<String>>,
+}
+
+// 当前轮次的键盘读取：询问用户时需要暂停
+static ACTIVE: Mutex<Option<Arc<Shared>>> = Mutex::new(None);
+
+/// Reads the next prompts while a turn streams, so keys typed meanwhile
+/// are queued instead of interleaving with the reply. Esc on its own
+/// (not part of an arrow key) calls the `on_escape` given to `start`.
+#[derive(Debug)]
+pub struct TypeAhead {
+    shared: Arc<Shared>,
+    reader: Option<JoinHandle<()>>,
+    mode: tty::Mode,
+}
+
+impl TypeAhead {
+    /// Start reading keys; `None` when stdin isn't a terminal
+    pub fn start(on_escape: impl Fn() + Send + 'static) -> Option<Self> {
+        if !std::io::stdin().is_terminal() {
+            return None;
+        }
+        let mode = tty::quiet()?;
+        let shared = Arc::new(Shared::default());
+        let reader = {
+            let shared = shared.clone();
+            std::thread::spawn(move || read_keys(&shared, on_escape))
+        };
+        if let Ok(mut active) = ACTIVE.lock() {
+            *active = Some(shared.clone());
+        }
+        Some(Self {
+            shared,
+            reader: Some(reader),
+            mode,
+        })
+    }
+
+    /// Stop reading and give back what was typed
+    pub fn finish(mut self) -> Typed {
+        self.stop();
+        let lines = self.shared.lines.lock().map(|mut lines| lines.drain(..).collect()).unwrap_or_default();
+        let partial = self.shared.buffer.lock().map(|buffer| buffer.partial().to_string()).unwrap_or_default();
+        Typed { lines, partial }
+    }
+
+    fn stop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        if let Some(reader) = self.reader.take() {
+            reader.join().ok();
+        }
+        if let Ok(mut active) = ACTIVE.lock() {
+            *active = None;
+        }
+        tty::restore(&self.mode);
+    }
+}
+
+impl Drop for TypeAhead {
+    fn drop(&mut self) {
+        if self.reader.is_some() {
+            self.stop();
+        }
+    }
+}
+
+fn read_keys(shared: &Shared, on_escape: impl Fn()) {
+    while !shared.stop.load(Ordering::SeqCst) {
+        let Ok(reading) = shared.reading.lock() else {
+            return;
+        };
+        if shared.paused.load(Ordering::SeqCst) {
+            drop(reading);
+            std::thread::sleep(std::time::Duration::from_millis(POLL_MS as u64));
+            continue;
+        }
+        let Some(bytes) = tty::read_ready(POLL_MS) else {
+            continue;
+        };
+        // 方向键等转义序列一次读到多个字节，单独的 ESC 才是按下了 Esc
+        if bytes == [0x1b] {
+            on_escape();
+            continue;
+        }
+        let lines = match shared.buffer.lock() {
+            Ok(mut buffer) => buffer.feed(&bytes),
+            Err(_) => return,
+        };
+        for line in lines {
+            UI::queued(&line);
+            if let Ok(mut queued) = shared.lines.lock() {
+                queued.push_back(line);
+            }
+        }
+    }
+}
+
+/// Run `ask` (a question read from stdin) with the typeahead reader
+/// paused and the terminal back in line mode, so the answer reaches it
+pub fn paused<T>(ask: impl FnOnce() -> T) -> T {
+    let shared = ACTIVE.lock().ok().and_then(|active| active.clone());
+    let Some(shared) = shared else {
+        return ask();
+    };
+    shared.paused.store(true, Ordering::SeqCst);
+    let mode = {
+        let _reading = shared.reading.lock();
+        tty::line_mode()
+    };
+    let answer = ask();
+    if let Some(mode) = mode {
+        tty::restore(&mode);
+    }
+    shared.paused.store(false, Ordering::SeqCst);
+    answer
+}
+
+#[cfg(unix)]
+mod tty {
+    use std::os::fd::AsRawFd;
+
+    /// Terminal settings to restore
+    #[derive(Debug)]
+    pub struct Mode(libc::termios);
+
+    fn current() -> Option<libc::termios> {
+        let fd = std::io::stdin().as_raw_fd();
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: tcgetattr fills the struct when it returns 0
+        (unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) } == 0).then(|| unsafe { termios.assume_init() })
+    }
+
+    fn apply(termios: &libc::termios) {
+        // SAFETY: `termios` came from tcgetattr on the same descriptor
+        unsafe {
+            libc::tcsetattr(std::io::stdin().as_raw_fd(), libc::TCSANOW, termios);
+        }
+    }
+
+    /// Keys one at a time, without echo; Ctrl-C still interrupts.
+    /// Returns the settings before.
+    pub fn quiet() -> Option<Mode> {
+        let saved = current()?;
+        let mut quiet = saved;
+        quiet.c_lflag &= !(libc::ICANON | libc::ECHO);
+        quiet.c_cc[libc::VMIN] = 1;
+        quiet.c_cc[libc::VTIME] = 0;
+        apply(&quiet);
+        Some(Mode(saved))
+    }
+
+    /// Line input with echo, for a question; returns the settings before
+    pub fn line_mode() -> Option<Mode> {
+        let saved = current()?;
+        let mut line = saved;
+        line.c_lflag |= libc::ICANON | libc::ECHO;
+        apply(&line);
+        Some(Mode(saved))
+    }
+
+    pub fn restore(mode: &Mode) {
+        apply(&mode.0);
+    }
+
+    /// Bytes waiting on stdin, waiting at most `timeout_ms` for them
+    pub fn read_ready(timeout_ms: i32) -> Option<Vec<u8>> {
+        let fd = std::io::stdin().as_raw_fd();
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: one valid pollfd
+        if unsafe { libc::poll(&mut poll, 1, timeout_ms) } <= 0 || poll.revents & libc::POLLIN == 0 {
+            return None;
+        }
+        let mut buffer = [0u8; 256];
+        // SAFETY: reads at most the buffer's length into it
+        let read = unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+        (read > 0).then(|| buffer[..read as usize].to_vec())
+    }
+}
+
+// 其他平台不支持：输入照旧在回复结束后读取
+#[cfg(not(unix))]
+mod tty {
+    #[derive(Debug)]
+    pub struct Mode;
+
+    pub fn quiet() -> Option<Mode> {
+        None
+    }
+
+    pub fn line_mode() -> Option<Mode> {
+        None
+    }
+
+    pub fn restore(_mode: &Mode) {}
+
+    pub fn read_ready(_timeout_ms: i32) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.feed(b"fix the tets").is_empty());
+        // Backspace twice, then an arrow key that is dropped
+        assert_eq!(buffer.feed(b"\x7f\x7fst\x1b[Dx\n"), vec!["fix the testx".to_string()]);
+        assert_eq!(buffer.feed(b"\n  \nnext\rdrop\x15keep"), vec!["next".to_string()]);
+        assert_eq!(buffer.partial(), "keep");
+
+        // A character split across reads
+        let mut buffer = LineBuffer::default();
+        let text = "改进".as_bytes();
+        buffer.feed(&text[..2]);
+        buffer.feed(&text[2..]);
+        assert_eq!(buffer.partial(), "改进");
+    }
+}
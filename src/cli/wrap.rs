@@ -0,0 +1,242 @@
+use std::io::{IsTerminal, stdout};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Narrowest width worth wrapping to; below this lines are left alone
+const MIN_WIDTH: usize = 20;
+
+/// Width of `text` in terminal columns: CJK and most emoji take two
+/// columns, combining marks none
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Columns taken by one character
+fn char_width(ch: char) -> usize {
+    UnicodeWidthChar::width(ch).unwrap_or(0)
+}
+
+/// Spaces before and after `text` to center it in `width` columns
+pub fn center_padding(text: &str, width: usize) -> (usize, usize) {
+    let free = width.saturating_sub(display_width(text));
+    (free / 2, free - free / 2)
+}
+
+/// Columns available for output, or `None` when stdout is not a terminal
+/// (output piped to a file is left unwrapped). Uses `COLUMNS`, else 80.
+pub fn terminal_width() -> Option<usize> {
+    if !stdout().is_terminal() {
+        return None;
+    }
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .unwrap_or(80);
+    Some(columns)
+}
+
+/// Whether `word` opens a list item (`-`, `*`, `+`, `1.`, `2)`)
+fn is_list_marker(word: &str) -> bool {
+    matches!(word, "-" | "*" | "+")
+        || word
+            .strip_suffix(['.', ')'])
+            .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Indentation for the continuation lines of a line that starts with
+/// `leading` columns of whitespace followed by `first_word`
+fn hang_for(leading: usize, first_word: &str, width: usize) -> usize {
+    let hang = if is_list_marker(first_word) {
+        leading + display_width(first_word) + 1
+    } else {
+        leading
+    };
+    hang.min(width / 2)
+}
+
+/// Split one line into pieces no wider than `width`, breaking at spaces.
+/// Continuation pieces are indented to line up under the text (past any
+/// leading indentation and list marker); words wider than a line are cut.
+pub fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if width < MIN_WIDTH || display_width(line) <= width {
+        return vec![line.to_string()];
+    }
+
+    let mut wrapper = TextWrapper::new(Some(width));
+    let mut wrapped = wrapper.push(line);
+    wrapped.push_str(&wrapper.finish());
+    wrapped.split('\n').map(str::to_string).collect()
+}
+
+/// Word-wraps text that arrives in fragments, such as a streamed reply.
+/// A word is held back until the whitespace after it arrives, so it can
+/// move to the next line as a whole.
+#[derive(Debug)]
+pub struct TextWrapper {
+    width: Option<usize>,
+    column: usize,
+    /// Indentation of continuation lines of the current line
+    hang: usize,
+    /// Nothing but whitespace on the current line yet
+    line_start: bool,
+    spaces: String,
+    word: String,
+}
+
+impl TextWrapper {
+    /// Wrap to `width` columns; `None` passes text through unchanged
+    pub fn new(width: Option<usize>) -> Self {
+        Self {
+            width: width.filter(|width| *width >= MIN_WIDTH),
+            column: 0,
+            hang: 0,
+            line_start: true,
+            spaces: String::new(),
+            word: String::new(),
+        }
+    }
+
+    /// Take in a fragment and return the text ready to print
+    pub fn push(&mut self, fragment: &str) -> String {
+        let Some(width) = self.width else {
+            return fragment.to_string();
+        };
+        let mut out = String::new();
+        for ch in fragment.chars() {
+            match ch {
+                '\n' => {
+                    self.flush_word(width, &mut out);
+                    out.push('\n');
+                    self.column = 0;
+                    self.hang = 0;
+                    self.line_start = true;
+                    self.spaces.clear();
+                }
+                ' ' | '\t' => {
+                    self.flush_word(width, &mut out);
+                    self.spaces.push(ch);
+                }
+                _ => self.word.push(ch),
+            }
+        }
+        out
+    }
+
+    /// Text still held back when the input ends
+    pub fn finish(&mut self) -> String {
+        let Some(width) = self.width else {
+            return String::new();
+        };
+        let mut out = String::new();
+        self.flush_word(width, &mut out);
+        out.push_str(&self.spaces);
+        self.spaces.clear();
+        out
+    }
+
+    fn flush_word(&mut self, width: usize, out: &mut String) {
+        if self.word.is_empty() {
+            return;
+        }
+        let word = std::mem::take(&mut self.word);
+        let spaces = std::mem::take(&mut self.spaces);
+        let word_width = display_width(&word);
+
+        if self.line_start {
+            // 行首的缩进原样保留，并据此确定续行的悬挂缩进
+            let leading = display_width(&spaces).min(width / 2);
+            out.extend(spaces.chars().take(leading));
+            self.column = leading;
+            self.hang = hang_for(leading, &word, width);
+            self.line_start = false;
+        } else if self.column + display_width(&spaces) + word_width > width {
+            self.break_line(out);
+        } else {
+            out.push_str(&spaces);
+            self.column += display_width(&spaces);
+        }
+
+        // 比一整行还长的词（URL、路径、没有空格的中文）按字符切开，宽字符不会被拆到两行
+        for ch in word.chars() {
+            let ch_width = char_width(ch);
+            if self.column + ch_width > width && self.column > self.hang {
+                self.break_line(out);
+            }
+            out.push(ch);
+            self.column += ch_width;
+        }
+    }
+
+    fn break_line(&mut self, out: &mut String) {
+        out.push('\n');
+        out.push_str(&" ".repeat(self.hang));
+        self.column = self.hang;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width_counts_cells() {
+        assert_eq!(display_width("Ariste"), 6);
+        assert_eq!(display_width("思考中"), 6);
+        assert_eq!(display_width("ok 完成"), 7);
+        assert_eq!(display_width("e\u{301}"), 1);
+
+        assert_eq!(center_padding("Ariste AI Agent", 40), (12, 13));
+        assert_eq!(center_padding("欢迎使用 Ariste", 20), (2, 3));
+        assert_eq!(center_padding("too long", 4), (0, 0));
+    }
+
+    #[test]
+    fn test_wrap_mixed_width_text() {
+        // 中文没有空格，按字符切开，每行不超过终端宽度
+        let line = "这是一段很长的中文思考内容需要按照终端宽度进行折行显示";
+        let pieces = wrap_line(line, 20);
+        assert!(pieces.len() > 1);
+        assert!(pieces.iter().all(|piece| display_width(piece) <= 20));
+        assert_eq!(pieces.concat(), line);
+
+        let pieces = wrap_line("- 读取 settings.json 然后 检查 provider 字段 是否 正确", 20);
+        assert!(pieces.iter().all(|piece| display_width(piece) <= 20));
+        assert!(pieces[1].starts_with("  "));
+    }
+
+    #[test]
+    fn test_wrap_line_with_hanging_indent() {
+        let line = "  - the spinner keeps running while the tool output streams into the terminal";
+        let pieces = wrap_line(line, 30);
+        assert_eq!(
+            pieces,
+            vec![
+                "  - the spinner keeps running",
+                "    while the tool output",
+                "    streams into the terminal",
+            ]
+        );
+        assert!(pieces.iter().all(|piece| display_width(piece) <= 30));
+
+        assert_eq!(wrap_line("short", 30), vec!["short"]);
+        assert_eq!(
+            wrap_line(&"x".repeat(45), 20),
+            vec!["x".repeat(20), "x".repeat(20), "x".repeat(5)]
+        );
+    }
+
+    #[test]
+    fn test_streamed_fragments_wrap_at_words() {
+        let mut wrapper = TextWrapper::new(Some(24));
+        let mut out = String::new();
+        for fragment in ["1. Open the conf", "ig file and set ", "the model name\nDone", "."] {
+            out.push_str(&wrapper.push(fragment));
+        }
+        out.push_str(&wrapper.finish());
+        assert_eq!(out, "1. Open the config file\n   and set the model\n   name\nDone.");
+
+        // Without a terminal, text passes through untouched
+        let mut plain = TextWrapper::new(None);
+        assert_eq!(plain.push("a  b\n"), "a  b\n");
+        assert_eq!(plain.finish(), "");
+    }
+}
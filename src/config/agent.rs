@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AgentConfig {
@@ -6,8 +7,557 @@ pub struct AgentConfig {
     pub provider: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base: Option<String>,
+    /// API key for hosted providers. Defaults to `OPENAI_API_KEY` (`OPENROUTER_API_KEY`, `ANTHROPIC_API_KEY`,
+    /// `AZURE_OPENAI_API_KEY`, `GEMINI_API_KEY`, `LLAMA_API_KEY`, `LM_API_TOKEN` for those providers).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// System prompt sent first in every request, ahead of the project instruction file
+    /// (`.ariste/ARISTE.md`, `AGENTS.md` or `CLAUDE.md`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// System prompts keyed by model name, used instead of `system_prompt` for those models
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_system_prompts: Option<HashMap<String, String>>,
+    /// Workspace roots; the first one is the primary root. Defaults to the current directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<Vec<RootConfig>>,
+    /// Collapse final answers that repeat tool output verbatim (buffers the answer instead of streaming it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress_echo: Option<bool>,
+    /// Check final answers against the turn's tool evidence with a second model call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<VerificationConfig>,
+    /// Models asked in parallel by `/consensus`, and the judge merging their answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<ConsensusConfig>,
+    /// A/B experiment: turns alternate between the variants and results are logged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentConfig>,
+    /// Per-token pricing keyed by model name, for paid providers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<HashMap<String, ModelPrice>>,
+    /// Abort generation (after asking) when a turn would cost more than this, in USD
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_turn_cost: Option<f64>,
+    /// Token used by `/share` to create gists. Falls back to `GITHUB_TOKEN`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+    /// Send a reduced tool set to small models, expanding it when they ask
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_trimming: Option<ToolTrimConfig>,
+    /// Example tool calls added to the system prompt, for models that format calls badly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_examples: Option<ToolExamplesConfig>,
+    /// Constrain tool-call turns to the tool-call JSON schema (Ollama `format`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constrained_tool_calls: Option<bool>,
+    /// Describe tools in the prompt and read `Action:` blocks back from the
+    /// reply, for models without native tool calling (less reliable)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub react_tool_calls: Option<bool>,
+    /// Seconds between progress updates while a tool runs (default 10; 0 disables)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heartbeat_secs: Option<u64>,
+    /// Spinner frames and status words shown while waiting for the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spinner: Option<SpinnerConfig>,
+    /// Animate the spinner; `false` prints a single static "working…" line instead.
+    /// Defaults to true on an interactive terminal other than TERM=dumb.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animations: Option<bool>,
+    /// Summarize older turns once the history grows past a token threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compaction: Option<CompactionConfig>,
+    /// Allow/deny rules for side-effecting tools (bash, write, edit, delete, move)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<PermissionsConfig>,
+    /// Seconds between crash-recovery checkpoints of a turn in progress (default 30; 0 disables)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autosave_secs: Option<u64>,
+    /// Let file tools use paths outside the workspace roots (default false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_outside_workdir: Option<bool>,
+    /// How much of the conversation subagents are given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subagents: Option<SubagentConfig>,
+    /// How failed model requests are retried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+    /// Deployment and API version for `provider: "azure"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub azure: Option<AzureConfig>,
+    /// Model that embeds text for semantic search, set up apart from the chat model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<EmbeddingConfig>,
+    /// Reorder search results before they enter the context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<RerankConfig>,
+    /// The code index searched by `semantic_search`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<IndexConfig>,
+    /// Add code and docs matching the user's message to each turn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rag: Option<RagConfig>,
+    /// Seconds to wait for data from the model before a request fails (default: no limit)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_timeout_secs: Option<u64>,
+    /// Banner and help text of the interactive prompt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub welcome: Option<WelcomeConfig>,
+    /// Prompt, tool and status symbols, and the ASCII fallback for terminals without them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyphs: Option<GlyphConfig>,
+    /// Screen-reader friendly output: no spinner, box drawing or rewritten
+    /// lines, one plain status line per event. Also `ARISTE_ACCESSIBLE=1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessible: Option<bool>,
+    /// The model accepts images: the read tool passes image files to it
+    /// instead of describing them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+    /// Sampling seed sent with every request, so runs can be reproduced on
+    /// backends that support seeding (Ollama, OpenAI-compatible). `--seed`
+    /// overrides it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+/// Settings for the answer verification pass
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct VerificationConfig {
+    /// Defaults to true when the section is present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Model used for verification, ideally a cheaper one. Defaults to the chat model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl VerificationConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+}
+
+/// Settings for spawned subagents
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SubagentConfig {
+    /// Estimated tokens of parent conversation forwarded as context (default 4000).
+    /// The most recent messages and earlier ones naming files in the task are kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_tokens: Option<u64>,
+    /// Subagents a `parallel_tasks` call runs at once (default 3)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_parallel: Option<usize>,
+    /// Show subagents' replies and tool calls as they run, prefixed with
+    /// their task (default true). Background tasks only report when done.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_progress: Option<bool>,
+    /// Limits for every subagent type
+    #[serde(flatten)]
+    pub limits: SubagentLimits,
+    /// Limits for one type, keyed by type name (`explore`, `code-review`, ...).
+    /// Unset fields fall back to the limits above.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub types: HashMap<String, SubagentLimits>,
+    /// Subagent types of your own, offered by the task tools after the
+    /// built-in ones; one named like a built-in type replaces it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<SubagentProfileConfig>,
+}
+
+impl SubagentConfig {
+    /// Limits for the subagent type called `name`: its `types` entry, then
+    /// its profile, then the shared limits
+    pub fn limits_for(&self, name: &str) -> SubagentLimits {
+        let specific = self.types.get(name).cloned().unwrap_or_default();
+        let profile = self
+            .profiles
+            .iter()
+            .rfind(|profile| profile.name == name)
+            .map(|profile| profile.limits.clone())
+            .unwrap_or_default();
+        SubagentLimits {
+            max_turns: specific.max_turns.or(profile.max_turns).or(self.limits.max_turns),
+            max_iterations: specific.max_iterations.or(profile.max_iterations).or(self.limits.max_iterations),
+            timeout_secs: specific.timeout_secs.or(profile.timeout_secs).or(self.limits.timeout_secs),
+        }
+    }
+}
+
+/// A subagent type defined in settings, e.g. a `security-audit` or
+/// `docs-writer` agent
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SubagentProfileConfig {
+    /// Name the model passes as `subagent_type`
+    pub name: String,
+    /// When the model should pick this type, shown in the task tools
+    #[serde(default)]
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Model the subagent runs on (default: the chat model)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tools it may be given (default: every tool; `[]` for none)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Retrieval augmentation for this type, over the `rag.enabled` default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rag: Option<bool>,
+    #[serde(flatten)]
+    pub limits: SubagentLimits,
+}
+
+/// How long a subagent may work before its partial result is returned
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SubagentLimits {
+    /// Model replies before the last answer so far is returned (default 10)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<usize>,
+    /// Tool calls run from a single reply; further calls are refused (default 5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_iterations: Option<usize>,
+    /// Wall-clock seconds before the subagent is stopped (default 300; 0 disables)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Retrying model requests that fail with a rate limit (429), a server
+/// error (5xx) or a dropped connection
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RetryConfig {
+    /// Attempts per request, the first included (default 3; 1 disables retrying)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    /// Wait before the first retry in milliseconds, doubled each time (default 500)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backoff_ms: Option<u64>,
+    /// Longest wait between attempts in milliseconds (default 8000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_backoff_ms: Option<u64>,
+}
+
+/// An Azure OpenAI deployment. `base` is the resource endpoint, e.g.
+/// `https://contoso.openai.azure.com` (default `AZURE_OPENAI_ENDPOINT`).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct AzureConfig {
+    /// Deployment to call; defaults to the model name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+    /// `api-version` query parameter (default 2024-10-21)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+}
+
+/// The embedding model. Nothing is taken from the chat model's settings:
+/// a hosted chat model can pair with a small local embedder.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct EmbeddingConfig {
+    /// `ollama` (default) or `openai` (any OpenAI-compatible `/embeddings`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Endpoint; defaults to the provider's usual one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Defaults to `nomic-embed-text` (Ollama) or `text-embedding-3-small`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    /// Key for hosted embedders; defaults to `OPENAI_API_KEY`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+/// Reranking search results: a BM25 keyword score blended with the search
+/// score (`bm25`, default), or a cross-encoder behind a `/rerank` endpoint
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RerankConfig {
+    /// Rerank results (default false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// `bm25` or `cross-encoder`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Server of the cross-encoder, e.g. `http://localhost:8012/v1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Results kept (default 5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+    /// Keyword share of the `bm25` score, 0 to 1 (default 0.3)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f32>,
+}
+
+impl RerankConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+}
+
+/// Retrieval augmentation: before a turn, the chunks of the code index
+/// and the knowledge base closest to the user's message are added to the
+/// request, without entering the history
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RagConfig {
+    /// Start sessions with retrieval on (default false; `/rag on|off` switches it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Chunks added per turn (default 5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+    /// Most characters of retrieved text per turn (default 12000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_chars: Option<usize>,
+}
+
+impl RagConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+}
+
+/// Settings for the code index
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct IndexConfig {
+    /// Embed the workspace for `semantic_search` (default false)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Seconds between checks for files changed outside the agent's tools
+    /// (default 10; 0 disables, leaving only the tools' own edits)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch_interval_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<ChunkingConfig>,
+}
+
+impl IndexConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+}
+
+/// How files are cut into chunks: at functions, types and sections by
+/// default, oversized items at their nested items
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ChunkingConfig {
+    /// Most lines in a chunk (default 60)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lines: Option<usize>,
+    /// Lines consecutive fixed-size chunks share (default 5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlap_lines: Option<usize>,
+    /// Settings for one language, keyed by name (`rust`, `python`,
+    /// `markdown`, `text`, ...)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub languages: HashMap<String, LanguageChunkingConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LanguageChunkingConfig {
+    /// `syntax` (default) cuts at item boundaries, `fixed` every `max_lines` lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lines: Option<usize>,
+}
+
+/// Settings for automatic context compaction
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CompactionConfig {
+    /// Compact automatically before a turn (default true); `/compact` always works
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Estimated history tokens that trigger compaction (default 32000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// Recent turns kept verbatim (default 2)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_turns: Option<usize>,
+    /// Model that writes the summary. Defaults to the chat model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl CompactionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+}
+
+/// What happens to a side-effecting tool call no rule covers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionMode {
+    /// Ask the user each time
+    #[default]
+    Ask,
+    Allow,
+    Deny,
+}
+
+/// Tool permission rules, written `tool` or `tool(pattern)`, e.g.
+/// `bash(git status)`, `bash(rm -rf *)`, `write(src/*)`. The pattern is
+/// matched against the bash command or the file path; `*` matches anything.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PermissionsConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    /// Checked before `allow`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    /// Calls no rule covers (default ask)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<PermissionMode>,
+}
+
+/// Settings for the `/consensus` command
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ConsensusConfig {
+    /// Panel models. Defaults to the chat model, asked several times.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// Model that merges and ranks the answers. Defaults to the chat model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub judge: Option<String>,
+}
+
+/// An A/B experiment comparing system-prompt or model variants
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExperimentConfig {
+    pub name: String,
+    pub variants: Vec<VariantConfig>,
+}
+
+/// One arm of an experiment. Unset fields fall back to the normal settings.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VariantConfig {
+    pub name: String,
+    /// Extra system prompt sent with every request in this variant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    /// Cost in USD of a request with these token counts
+    pub fn cost(&self, prompt_tokens: u64, output_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.input_per_million + output_tokens as f64 * self.output_per_million) / 1_000_000.0
+    }
+}
+
+/// How tools are picked when the tool set is trimmed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimPolicy {
+    /// Tools whose name or description matches the prompt
+    #[default]
+    Relevant,
+    /// Tools called most often in earlier sessions
+    MostUsed,
+}
+
+/// Settings for trimming the tool schemas sent to the model
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ToolTrimConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<TrimPolicy>,
+    /// Tools offered up front (default 4)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tools: Option<usize>,
+    /// Cut tool and parameter descriptions to their first sentence (default true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_descriptions: Option<bool>,
+}
+
+/// Spinner appearance; empty or missing lists keep the built-in ones
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SpinnerConfig {
+    /// Characters cycled through, e.g. ["|", "/", "-", "\\"]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frames: Option<Vec<String>>,
+    /// Status words shown next to the spinner, switched after each full cycle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<String>>,
+}
+
+/// Welcome screen of the interactive prompt. `message` and `help` may use
+/// the placeholders `{workdir}`, `{model}` and `{version}`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct WelcomeConfig {
+    /// Show the welcome screen at startup (default: only on an interactive terminal)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banner: Option<bool>,
+    /// Title in the banner box (default "Ariste AI Agent")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Line under the banner (default "Working directory: {workdir}")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Text shown before the command list, at startup and by /help
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+}
+
+/// Symbols of the terminal UI; missing or empty ones keep the built-in
+/// symbol of the mode
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GlyphConfig {
+    /// Plain ASCII everywhere, box lines included. Defaults to true for a
+    /// non-UTF-8 locale or a terminal like the Linux console.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ascii: Option<bool>,
+    /// Input prompt (default "⟩", ASCII ">")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Shown before each tool call (default "🔨", ASCII "*")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+}
+
+/// Few-shot tool-call examples
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ToolExamplesConfig {
+    /// Defaults to true when the section is present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Include the built-in example for each tool (default true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub builtin: Option<bool>,
+    /// Example arguments per tool name; replaces that tool's built-in example
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub examples: HashMap<String, Vec<serde_json::Value>>,
+}
+
+/// A workspace root entry in `.ariste/settings.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RootConfig {
+    pub path: String,
+    /// Label shown to the model (e.g. "frontend"). Defaults to the directory name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
 }
 
 impl Default for AgentConfig {
@@ -15,7 +565,42 @@ impl Default for AgentConfig {
         Self {
             provider: Some("ollama".to_string()),
             base: Some("http://127.0.0.1:11434".to_string()),
+            api_key: None,
             model: Some("qwen3".to_string()),
+            system_prompt: None,
+            model_system_prompts: None,
+            roots: None,
+            suppress_echo: None,
+            verification: None,
+            consensus: None,
+            experiment: None,
+            pricing: None,
+            max_turn_cost: None,
+            github_token: None,
+            tool_trimming: None,
+            tool_examples: None,
+            constrained_tool_calls: None,
+            react_tool_calls: None,
+            heartbeat_secs: None,
+            spinner: None,
+            animations: None,
+            compaction: None,
+            permissions: None,
+            autosave_secs: None,
+            allow_outside_workdir: None,
+            subagents: None,
+            retry: None,
+            azure: None,
+            embedding: None,
+            rerank: None,
+            index: None,
+            rag: None,
+            request_timeout_secs: None,
+            welcome: None,
+            glyphs: None,
+            accessible: None,
+            vision: None,
+            seed: None,
         }
     }
 }
@@ -1,3 +1,6 @@
 mod agent;
 
-pub use agent::AgentConfig;
+pub use agent::{
+    AgentConfig, AzureConfig, ChunkingConfig, CompactionConfig, ConsensusConfig, EmbeddingConfig, ExperimentConfig, GlyphConfig, IndexConfig, LanguageChunkingConfig, ModelPrice, PermissionMode, PermissionsConfig, RagConfig, RerankConfig, RetryConfig, RootConfig, SpinnerConfig, SubagentConfig, SubagentLimits, SubagentProfileConfig, ToolExamplesConfig, ToolTrimConfig, TrimPolicy,
+    VariantConfig, VerificationConfig, WelcomeConfig,
+};
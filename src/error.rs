@@ -1,4 +1,5 @@
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("{0}")]
     Reqwest(#[from] reqwest::Error),
@@ -12,9 +13,6 @@ pub enum Error {
     #[error("{0}")]
     Tungstenite(#[from] tungstenite::Error),
 
-    #[error("{0}")]
-    ReadlineError(#[from] rustyline::error::ReadlineError),
-
     #[error("{0}")]
     UrlParseError(#[from] url::ParseError),
 
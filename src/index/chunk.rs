@@ -0,0 +1,584 @@
+//! Cutting files into chunks for the index. Item boundaries are found by
+//! a line scanner that tracks brackets, strings, comments and indentation,
+//! not by parsing with tree-sitter grammars: those are C libraries built
+//! per language, which the crate doesn't depend on. Rust's raw strings and
+//! nested block comments are recognised. The scanner still has no grammar
+//! to lean on, so some constructs mislead it: regex literals in JavaScript
+//! and TypeScript (`/[{"]/`), C++ raw strings (`R"(...)"`) and PHP heredocs.
+//! A chunk may then end early or run on into the next item.
+
+use crate::config::ChunkingConfig;
+use crate::error::Error;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Most lines in a chunk unless `index.chunking.max_lines` says otherwise
+pub const DEFAULT_MAX_LINES: usize = 60;
+/// Lines consecutive fixed-size chunks share
+pub const DEFAULT_OVERLAP_LINES: usize = 5;
+
+/// Name of the item a chunk starts with: a function, type, module or class
+static SYMBOL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b(?:fn|struct|enum|trait|impl|mod|union|type|macro_rules!|class|def|func|function|interface|record|object|namespace)\s+(?:\([^)]*\)\s*)?(?:<[^>]*>\s*)?([A-Za-z_$][\w$]*)",
+    )
+    .expect("valid symbol pattern")
+});
+
+/// How a language's source divides into items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    /// Items are delimited by braces: Go, the C family, JavaScript...
+    Braces,
+    /// Braces, with Rust's raw strings and nested block comments
+    Rust,
+    /// Items are delimited by indentation: Python
+    Indent,
+    /// Sections start at headings
+    Markdown,
+    /// No structure known: fixed-size chunks
+    Plain,
+}
+
+/// Language name and syntax of the file at `path`, from its extension
+pub fn language(path: &Path) -> (&'static str, Syntax) {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "rs" => ("rust", Syntax::Rust),
+        "go" => ("go", Syntax::Braces),
+        "js" | "jsx" | "mjs" | "cjs" => ("javascript", Syntax::Braces),
+        "ts" | "tsx" | "mts" | "cts" => ("typescript", Syntax::Braces),
+        "java" => ("java", Syntax::Braces),
+        "kt" | "kts" => ("kotlin", Syntax::Braces),
+        "scala" => ("scala", Syntax::Braces),
+        "swift" => ("swift", Syntax::Braces),
+        "c" | "h" => ("c", Syntax::Braces),
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => ("cpp", Syntax::Braces),
+        "cs" => ("csharp", Syntax::Braces),
+        "php" => ("php", Syntax::Braces),
+        "py" | "pyi" => ("python", Syntax::Indent),
+        "md" | "markdown" => ("markdown", Syntax::Markdown),
+        _ => ("text", Syntax::Plain),
+    }
+}
+
+/// Hex SHA-256 of `content`, telling whether a file changed since it was cut
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A piece of a file as the index stores it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub path: String,
+    /// First and last line, counted from 1
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Item the chunk starts with, e.g. `parse_config`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Headers of the items a chunk was cut from, e.g. `impl Config {`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub text: String,
+}
+
+impl Chunk {
+    /// What to embed: the enclosing headers, then the chunk
+    pub fn embedding_text(&self) -> String {
+        match &self.context {
+            Some(context) => format!("{}\n{}", context, self.text),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// How one language is cut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// At item boundaries, nested items splitting oversized ones
+    Syntax,
+    /// Every `max_lines` lines, overlapping a little
+    Fixed,
+}
+
+/// Bracket depth and open strings or comments, carried from line to line
+#[derive(Debug, Default, Clone, Copy)]
+struct Scan {
+    depth: usize,
+    quote: Option<(char, bool)>,
+    /// Open Rust raw string, by the number of `#` closing it
+    raw: Option<usize>,
+    /// Open block comments; only Rust's nest
+    block_comment: usize,
+}
+
+impl Scan {
+    fn at_top(&self) -> bool {
+        self.depth == 0 && self.quote.is_none() && self.raw.is_none() && self.block_comment == 0
+    }
+
+    /// Move past `line`. Python has `#` comments and triple quotes; Rust
+    /// has raw strings and nested block comments.
+    fn line(&mut self, line: &str, syntax: Syntax) {
+        let (python, rust) = (syntax == Syntax::Indent, syntax == Syntax::Rust);
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let (c, next) = (chars[i], chars.get(i + 1).copied());
+            let tripled = |q: char| next == Some(q) && chars.get(i + 2) == Some(&q);
+            let hashes = |from: usize| chars[from.min(chars.len())..].iter().take_while(|&&c| c == '#').count();
+            if self.block_comment > 0 {
+                if c == '*' && next == Some('/') {
+                    self.block_comment -= 1;
+                    i += 1;
+                } else if rust && c == '/' && next == Some('*') {
+                    self.block_comment += 1;
+                    i += 1;
+                }
+            } else if let Some(closing) = self.raw {
+                if c == '"' && hashes(i + 1) >= closing {
+                    self.raw = None;
+                    i += closing;
+                }
+            } else if let Some((quote, triple)) = self.quote {
+                if c == '\\' {
+                    i += 1;
+                } else if c == quote && (!triple || tripled(quote)) {
+                    self.quote = None;
+                    i += if triple { 2 } else { 0 };
+                }
+            } else {
+                match c {
+                    '#' if python => return,
+                    '/' if !python && next == Some('/') => return,
+                    '/' if !python && next == Some('*') => {
+                        self.block_comment = 1;
+                        i += 1;
+                    }
+                    // r"..."、r#"..."#、br"..."：里面没有转义
+                    'r' | 'b' if rust && !(i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_')) => {
+                        let r = if c == 'b' && next == Some('r') { i + 1 } else { i };
+                        let count = hashes(r + 1);
+                        if chars[r] == 'r' && chars.get(r + 1 + count) == Some(&'"') {
+                            self.raw = Some(count);
+                            i = r + 1 + count;
+                        }
+                    }
+                    '"' | '\'' if python && tripled(c) => {
+                        self.quote = Some((c, true));
+                        i += 2;
+                    }
+                    // 'x' 和 '\n' 是字符字面量，其余的单引号（生命周期）跳过
+                    '\'' if !python => {
+                        if chars.get(i + 2) == Some(&'\'') {
+                            i += 2;
+                        } else if next == Some('\\') {
+                            i += chars[i + 1..].iter().position(|&c| c == '\'').unwrap_or(0);
+                        }
+                    }
+                    '"' | '\'' | '`' => self.quote = Some((c, false)),
+                    '{' | '(' | '[' => self.depth += 1,
+                    '}' | ')' | ']' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        // Python 的单引号字符串不跨行
+        if python && matches!(self.quote, Some((_, false))) {
+            self.quote = None;
+        }
+    }
+}
+
+/// A line carrying on the item above it
+fn continues(trimmed: &str) -> bool {
+    trimmed.starts_with(['}', ')', ']', '.', '?', '&', '|', '+'])
+        || ["else", "elif", "except", "finally", "catch", "where"]
+            .iter()
+            .any(|keyword| trimmed.strip_prefix(keyword).is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_')))
+}
+
+/// Lines where top-level items of `lines` start
+fn item_starts(lines: &[&str], syntax: Syntax) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut scan = Scan::default();
+    let mut fence = false;
+    let mut previous = "";
+    let mut blank_before = false;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        let start = !trimmed.is_empty()
+            && match syntax {
+                Syntax::Braces | Syntax::Rust => {
+                    let previous = previous.trim_end();
+                    scan.at_top()
+                        && !continues(trimmed)
+                        && (blank_before || previous.ends_with('}') || previous.ends_with(';') || previous.ends_with("},"))
+                }
+                // 注释和装饰器属于下面的定义
+                Syntax::Indent => {
+                    scan.at_top()
+                        && !line.starts_with(char::is_whitespace)
+                        && !continues(trimmed)
+                        && (blank_before || !(previous.starts_with('@') || previous.starts_with('#')))
+                }
+                Syntax::Markdown => !fence && line.starts_with('#'),
+                Syntax::Plain => false,
+            };
+        if start && index > 0 {
+            starts.push(index);
+        }
+        match syntax {
+            Syntax::Braces | Syntax::Rust | Syntax::Indent => scan.line(line, syntax),
+            Syntax::Markdown if trimmed.starts_with("```") => fence = !fence,
+            _ => {}
+        }
+        if !trimmed.is_empty() {
+            previous = line;
+        }
+        blank_before = trimmed.is_empty();
+    }
+    starts
+}
+
+/// `lines` without the indentation they all share
+fn dedented<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines.iter().map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start())).collect()
+}
+
+/// `range` without its blank lines at either end
+fn trimmed(lines: &[&str], range: Range<usize>) -> Range<usize> {
+    let mut range = range;
+    while range.start < range.end && lines[range.start].trim().is_empty() {
+        range.start += 1;
+    }
+    while range.end > range.start && lines[range.end - 1].trim().is_empty() {
+        range.end -= 1;
+    }
+    range
+}
+
+/// Line ending the header of the item at `range` and the lines of its
+/// body, when it has nested items to cut at
+fn body(lines: &[&str], range: Range<usize>, syntax: Syntax) -> Option<(usize, Range<usize>)> {
+    match syntax {
+        Syntax::Braces | Syntax::Rust => {
+            let header = range.clone().find(|&i| lines[i].trim_end().ends_with('{'))?;
+            let footer = (header + 1..range.end).rev().find(|&i| lines[i].trim_start().starts_with('}'))?;
+            Some((header, header + 1..footer))
+        }
+        Syntax::Indent => {
+            let header = range.clone().find(|&i| {
+                let line = lines[i].trim();
+                line.ends_with(':') && !line.starts_with('@') && !line.starts_with('#')
+            })?;
+            Some((header, header + 1..range.end))
+        }
+        Syntax::Markdown | Syntax::Plain => None,
+    }
+}
+
+/// Lines of one chunk and the headers around it
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    lines: Range<usize>,
+    context: Option<String>,
+}
+
+/// Cuts files into chunks for the index
+#[derive(Debug, Clone)]
+pub struct Chunker {
+    pub max_lines: usize,
+    pub overlap_lines: usize,
+    /// Strategy and line limit per language, overriding the defaults
+    pub languages: HashMap<String, (Strategy, Option<usize>)>,
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self {
+            max_lines: DEFAULT_MAX_LINES,
+            overlap_lines: DEFAULT_OVERLAP_LINES,
+            languages: HashMap::new(),
+        }
+    }
+}
+
+impl Chunker {
+    /// The chunker the `index.chunking` settings describe
+    pub fn from_config(config: Option<&ChunkingConfig>) -> Result<Self, Error> {
+        let mut chunker = Self::default();
+        let Some(config) = config else {
+            return Ok(chunker);
+        };
+        chunker.max_lines = config.max_lines.unwrap_or(DEFAULT_MAX_LINES).max(1);
+        chunker.overlap_lines = config.overlap_lines.unwrap_or(DEFAULT_OVERLAP_LINES);
+        for (name, settings) in &config.languages {
+            let strategy = match settings.strategy.as_deref().unwrap_or("syntax") {
+                "syntax" => Strategy::Syntax,
+                "fixed" => Strategy::Fixed,
+                other => {
+                    return Err(Error::Message(format!(
+                        "Unknown chunking strategy '{}' for {}; expected \"syntax\" or \"fixed\"",
+                        other, name
+                    )));
+                }
+            };
+            chunker.languages.insert(name.clone(), (strategy, settings.max_lines.map(|lines| lines.max(1))));
+        }
+        Ok(chunker)
+    }
+
+    /// Chunks of the file at `path` holding `content`
+    pub fn chunk(&self, path: &str, content: &str) -> Vec<Chunk> {
+        self.chunk_as(path, content, language(Path::new(path)))
+    }
+
+    /// Chunks of `content` read as `language`, for documents whose name
+    /// doesn't tell, like a web page converted to markdown
+    pub fn chunk_as(&self, path: &str, content: &str, language: (&str, Syntax)) -> Vec<Chunk> {
+        let (name, syntax) = language;
+        let (strategy, max_lines) = match self.languages.get(name) {
+            Some((strategy, max_lines)) => (*strategy, max_lines.unwrap_or(self.max_lines)),
+            None => (Strategy::Syntax, self.max_lines),
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        let mut spans = Vec::new();
+        match (strategy, syntax) {
+            (Strategy::Fixed, _) | (_, Syntax::Plain) => self.fixed(0..lines.len(), None, max_lines, &mut spans),
+            (Strategy::Syntax, _) => self.split(&lines, 0..lines.len(), syntax, None, max_lines, &mut spans),
+        }
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        for span in packed(&lines, spans, max_lines) {
+            let range = trimmed(&lines, span.lines);
+            if range.is_empty() {
+                continue;
+            }
+            chunks.push(Chunk {
+                path: path.to_string(),
+                start_line: range.start + 1,
+                end_line: range.end,
+                symbol: symbol(&lines[range.clone()], syntax, span.context.as_deref().and_then(|c| c.lines().last())),
+                context: span.context,
+                text: lines[range].join("\n"),
+            });
+        }
+        chunks
+    }
+
+    /// Windows of `max_lines` over `range`, sharing `overlap_lines`
+    fn fixed(&self, range: Range<usize>, context: Option<String>, max_lines: usize, spans: &mut Vec<Span>) {
+        let step = max_lines.saturating_sub(self.overlap_lines).max(1);
+        let mut start = range.start;
+        while start < range.end {
+            let end = (start + max_lines).min(range.end);
+            spans.push(Span { lines: start..end, context: context.clone() });
+            if end == range.end {
+                break;
+            }
+            start += step;
+        }
+    }
+
+    /// Items of `range`; one longer than `max_lines` is cut at its nested
+    /// items, or into fixed windows when it has none
+    fn split(
+        &self,
+        lines: &[&str],
+        range: Range<usize>,
+        syntax: Syntax,
+        context: Option<String>,
+        max_lines: usize,
+        spans: &mut Vec<Span>,
+    ) {
+        let view = dedented(&lines[range.clone()]);
+        let starts = item_starts(&view, syntax);
+        let ends = starts.iter().skip(1).copied().chain([view.len()]);
+        for (start, end) in starts.iter().zip(ends) {
+            let item = trimmed(lines, range.start + start..range.start + end);
+            if item.len() <= max_lines {
+                spans.push(Span { lines: range.start + start..range.start + end, context: context.clone() });
+                continue;
+            }
+            match body(lines, item.clone(), syntax) {
+                Some((header, inner)) if !trimmed(lines, inner.clone()).is_empty() => {
+                    let header_line = lines[header].trim();
+                    let inner_context = match &context {
+                        Some(context) => format!("{}\n{}", context, header_line),
+                        None => header_line.to_string(),
+                    };
+                    let first = spans.len();
+                    self.split(lines, inner, syntax, Some(inner_context), max_lines, spans);
+                    // 头部（文档注释、签名）和结尾的括号并入相邻的块
+                    spans[first].lines.start = item.start;
+                    if let Some(last) = spans.last_mut() {
+                        last.lines.end = range.start + end;
+                    }
+                }
+                _ => self.fixed(item, context.clone(), max_lines, spans),
+            }
+        }
+    }
+}
+
+/// Neighbouring spans under the same headers joined while they fit in
+/// `max_lines`
+fn packed(lines: &[&str], spans: Vec<Span>, max_lines: usize) -> Vec<Span> {
+    let mut packed: Vec<Span> = Vec::new();
+    for span in spans {
+        if let Some(last) = packed.last_mut()
+            && last.context == span.context
+            && last.lines.end == span.lines.start
+            && trimmed(lines, last.lines.start..span.lines.end).len() <= max_lines
+        {
+            last.lines.end = span.lines.end;
+            continue;
+        }
+        packed.push(span);
+    }
+    packed
+}
+
+/// Name of the first item in `lines`, passing over the `header` of the
+/// item they were cut from
+fn symbol(lines: &[&str], syntax: Syntax, header: Option<&str>) -> Option<String> {
+    if syntax == Syntax::Markdown {
+        return lines
+            .iter()
+            .find(|line| line.starts_with('#'))
+            .map(|line| line.trim_start_matches('#').trim().to_string());
+    }
+    lines
+        .iter()
+        .map(|line| line.trim_start())
+        .filter(|line| !(line.starts_with("//") || line.starts_with("/*") || line.starts_with('*')))
+        .filter(|line| syntax != Syntax::Indent || !line.starts_with('#'))
+        .filter(|line| Some(line.trim_end()) != header)
+        .find_map(|line| SYMBOL.captures(line).map(|captures| captures[1].to_string()))
+}
+
+/// Chunks of each file, cut again only when its content changes
+#[derive(Debug, Default)]
+pub struct ChunkCache {
+    files: HashMap<String, (String, Vec<Chunk>)>,
+}
+
+impl ChunkCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chunks of `path` for `content`, and whether they were cut again:
+    /// true for a new or changed file
+    pub fn update(&mut self, chunker: &Chunker, path: &str, content: &str) -> (&[Chunk], bool) {
+        let hash = content_hash(content);
+        let changed = self.files.get(path).is_none_or(|(known, _)| *known != hash);
+        if changed {
+            self.files.insert(path.to_string(), (hash, chunker.chunk(path, content)));
+        }
+        (&self.files[path].1, changed)
+    }
+
+    /// Forget a deleted file; true when it was known
+    pub fn remove(&mut self, path: &str) -> bool {
+        self.files.remove(path).is_some()
+    }
+
+    pub fn get(&self, path: &str) -> Option<&[Chunk]> {
+        self.files.get(path).map(|(_, chunks)| chunks.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LanguageChunkingConfig;
+
+    const RUST: &str = "use std::fmt;\nuse std::io;\n\n/// Settings\n#[derive(Debug)]\npub struct Config {\n    name: String,\n}\n\nimpl Config {\n    /// Make one\n    pub fn new() -> Self {\n        // a { in a comment\n        let s = \"}\";\n        Self { name: s.into() }\n    }\n\n    fn len(&self) -> usize {\n        self.name.len()\n    }\n}\n";
+
+    #[test]
+    fn test_chunk_at_item_boundaries() {
+        let chunks = Chunker { max_lines: 8, ..Chunker::default() }.chunk("src/config.rs", RUST);
+        let summary: Vec<(usize, usize, Option<&str>, Option<&str>)> = chunks
+            .iter()
+            .map(|chunk| (chunk.start_line, chunk.end_line, chunk.symbol.as_deref(), chunk.context.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, 8, Some("Config"), None),
+                (10, 16, Some("new"), Some("impl Config {")),
+                (18, 21, Some("len"), Some("impl Config {")),
+            ]
+        );
+        assert!(chunks[1].text.starts_with("impl Config {\n    /// Make one"));
+        assert!(chunks[2].embedding_text().starts_with("impl Config {\n    fn len"));
+
+        // Small enough files stay whole
+        assert_eq!(Chunker::default().chunk("src/config.rs", RUST).len(), 1);
+
+        let python = "import os\n\n\n@cached\ndef load(path):\n    return open(path).read()\n\n\nclass Store:\n    def get(self):\n        return 1\n\n    def put(self, value):\n        self.value = value\n";
+        let chunks = Chunker { max_lines: 4, ..Chunker::default() }.chunk("store.py", python);
+        let symbols: Vec<Option<&str>> = chunks.iter().map(|chunk| chunk.symbol.as_deref()).collect();
+        assert_eq!(symbols, [None, Some("load"), Some("get"), Some("put")]);
+        assert_eq!(chunks[1].start_line, 4);
+        assert_eq!(chunks[3].context.as_deref(), Some("class Store:"));
+
+        let markdown = "# Guide\nintro\n## Install\nrun it\n```sh\n# not a heading\n```\n## Use\nread it\n";
+        let chunks = Chunker { max_lines: 5, ..Chunker::default() }.chunk("README.md", markdown);
+        let symbols: Vec<Option<&str>> = chunks.iter().map(|chunk| chunk.symbol.as_deref()).collect();
+        assert_eq!(symbols, [Some("Guide"), Some("Install"), Some("Use")]);
+    }
+
+    #[test]
+    fn test_rust_raw_strings_and_nested_comments() {
+        let rust = "macro_rules! square {\n    ($x:expr) => { $x * $x };\n}\n\nconst PAGE: &str = r#\"\n<a title='x\">{</a>\n\"#;\n\n/* outer /* inner */ still { a comment */\nfn after() {\n    square!(2);\n}\n";
+        let chunks = Chunker { max_lines: 4, ..Chunker::default() }.chunk("src/page.rs", rust);
+        let summary: Vec<(usize, usize, Option<&str>)> =
+            chunks.iter().map(|chunk| (chunk.start_line, chunk.end_line, chunk.symbol.as_deref())).collect();
+        assert_eq!(summary, [(1, 3, Some("square")), (5, 7, None), (9, 12, Some("after"))]);
+    }
+
+    #[test]
+    fn test_chunk_config_and_cache() {
+        let config = ChunkingConfig {
+            max_lines: Some(10),
+            overlap_lines: Some(2),
+            languages: HashMap::from([(
+                "rust".to_string(),
+                LanguageChunkingConfig { strategy: Some("fixed".to_string()), max_lines: Some(8) },
+            )]),
+        };
+        let chunker = Chunker::from_config(Some(&config)).unwrap();
+        let lines: Vec<(usize, usize)> =
+            chunker.chunk("src/config.rs", RUST).iter().map(|chunk| (chunk.start_line, chunk.end_line)).collect();
+        assert_eq!(lines, [(1, 8), (7, 14), (13, 20), (19, 21)]);
+
+        let mut bad = config.clone();
+        bad.languages.get_mut("rust").unwrap().strategy = Some("ast".to_string());
+        assert!(Chunker::from_config(Some(&bad)).is_err());
+
+        let mut cache = ChunkCache::new();
+        assert!(cache.update(&chunker, "src/config.rs", RUST).1);
+        assert!(!cache.update(&chunker, "src/config.rs", RUST).1);
+        let (chunks, changed) = cache.update(&chunker, "src/config.rs", "fn main() {}\n");
+        assert!(changed);
+        assert_eq!(chunks.len(), 1);
+        assert!(cache.remove("src/config.rs"));
+        assert!(cache.get("src/config.rs").is_none());
+    }
+}
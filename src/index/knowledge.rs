@@ -0,0 +1,384 @@
+use crate::config::AgentConfig;
+use crate::error::Error;
+use crate::index::chunk::{Chunker, Syntax, content_hash};
+use crate::index::rerank::{self, Reranker, SearchHit};
+use crate::index::semantic::{RERANK_CANDIDATES, SKIPPED_DIRS, embed_chunks, modified_millis};
+use crate::index::store::{IndexStore, IndexedFile};
+use crate::llm::{EmbeddingProvider, create_embedder};
+use crate::utils::html;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+/// Where ingested documents are saved, under the workspace root; apart
+/// from the code index so docs don't crowd out code in `semantic_search`
+pub const DOCS_INDEX_FILE: &str = ".ariste/index/docs.json";
+/// Files picked up when a directory is ingested
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "txt", "rst", "adoc", "html", "htm", "pdf"];
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What a document is written in, which decides how its text is read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Markdown,
+    Text,
+    Html,
+    Pdf,
+}
+
+impl Format {
+    fn from_extension(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" | "mdx" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            "pdf" => Some(Self::Pdf),
+            "txt" | "rst" | "adoc" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        match mime.as_str() {
+            "text/html" | "application/xhtml+xml" => Some(Self::Html),
+            "application/pdf" => Some(Self::Pdf),
+            "text/markdown" | "text/x-markdown" => Some(Self::Markdown),
+            "application/json" => Some(Self::Text),
+            _ if mime.starts_with("text/") => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+/// A document read and turned into text, ready to be cut into chunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    /// Workspace-relative path or URL it was read from
+    pub source: String,
+    pub text: String,
+    /// Markdown unless it came as plain text or from a PDF
+    pub markdown: bool,
+    /// Modification time of a file, 0 for a web page
+    pub modified: u64,
+}
+
+impl Document {
+    /// The document in `bytes`; for a PDF, the text pdftotext extracted
+    fn new(source: String, format: Format, bytes: Vec<u8>, modified: u64) -> Self {
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+        let (text, markdown) = match format {
+            Format::Markdown => (text, true),
+            Format::Text | Format::Pdf => (text, false),
+            Format::Html => (html::readable(&text), true),
+        };
+        Self { source, text, markdown, modified }
+    }
+}
+
+/// Text of the PDF at `path`, through poppler's `pdftotext`
+async fn pdf_text(path: &Path) -> Result<String, Error> {
+    let output = tokio::process::Command::new("pdftotext")
+        .arg("-layout")
+        .arg(path)
+        .arg("-")
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                Error::Message("Reading PDFs needs pdftotext (poppler-utils) on the PATH".to_string())
+            }
+            _ => Error::Message(format!("Failed to run pdftotext: {}", e)),
+        })?;
+    if !output.status.success() {
+        return Err(Error::Message(format!(
+            "pdftotext failed on {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Document files under `dir`, hidden and dependency directories left out
+fn document_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let (path, Ok(file_type)) = (entry.path(), entry.file_type()) else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() && Format::from_extension(&name).is_some() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// What `ariste ingest` did with each document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IngestReport {
+    /// Source, chunks, and whether it was embedded (false: unchanged since
+    /// it was last ingested)
+    pub ingested: Vec<(String, usize, bool)>,
+    /// Source and why it couldn't be read
+    pub failed: Vec<(String, String)>,
+}
+
+/// Documentation pulled in from outside the code (guides, vendor API
+/// docs, PDFs, web pages), cut into chunks and embedded for `docs_search`.
+/// Sources stay until ingested again; a changed one replaces its chunks.
+#[derive(Debug)]
+pub struct KnowledgeBase {
+    root: PathBuf,
+    file: PathBuf,
+    chunker: Chunker,
+    embedder: Box<dyn EmbeddingProvider>,
+    reranker: Option<Reranker>,
+    results: usize,
+    /// Loaded from `file` on first use
+    store: Mutex<Option<IndexStore>>,
+}
+
+impl KnowledgeBase {
+    pub fn new(root: &Path, file: PathBuf, embedder: Box<dyn EmbeddingProvider>) -> Self {
+        Self {
+            root: std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf()),
+            file,
+            chunker: Chunker::default(),
+            embedder,
+            reranker: None,
+            results: rerank::DEFAULT_TOP_K,
+            store: Mutex::new(None),
+        }
+    }
+
+    pub fn chunker(mut self, chunker: Chunker) -> Self {
+        self.chunker = chunker;
+        self
+    }
+
+    pub fn reranker(mut self, reranker: Option<Reranker>) -> Self {
+        self.reranker = reranker;
+        self
+    }
+
+    /// Results a search returns unless it asks for a number
+    pub fn results(mut self, results: usize) -> Self {
+        self.results = results.max(1);
+        self
+    }
+
+    /// The knowledge base of the workspace at `root`, embedded with the
+    /// `embedding` model and cut like the code index
+    pub fn from_config(config: &AgentConfig, root: &Path) -> Result<Self, Error> {
+        let chunking = config.index.as_ref().and_then(|index| index.chunking.as_ref());
+        Ok(Self::new(root, root.join(DOCS_INDEX_FILE), create_embedder(config)?)
+            .chunker(Chunker::from_config(chunking)?)
+            .reranker(Reranker::from_config(config)?)
+            .results(rerank::top_k(config.rerank.as_ref())))
+    }
+
+    /// Whether anything has been ingested yet
+    pub fn exists(&self) -> bool {
+        self.file.is_file()
+    }
+
+    pub fn default_results(&self) -> usize {
+        self.results
+    }
+
+    async fn store(&self) -> Result<MappedMutexGuard<'_, IndexStore>, Error> {
+        let mut store = self.store.lock().await;
+        if store.is_none() {
+            *store = Some(IndexStore::load(&self.file, self.embedder.model()).await?);
+        }
+        Ok(MutexGuard::map(store, |store| store.get_or_insert_with(IndexStore::default)))
+    }
+
+    /// How `path` is named in the knowledge base: relative to the root
+    /// when inside it
+    fn source_name(&self, path: &Path) -> String {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let name = path.strip_prefix(&self.root).unwrap_or(&path);
+        name.to_string_lossy().replace('\\', "/")
+    }
+
+    async fn read_file(&self, path: &Path) -> Result<Document, Error> {
+        let source = self.source_name(path);
+        let format = Format::from_extension(&source).unwrap_or(Format::Text);
+        let modified = modified_millis(&tokio::fs::metadata(path).await?);
+        let bytes = match format {
+            Format::Pdf => pdf_text(path).await?.into_bytes(),
+            _ => tokio::fs::read(path).await?,
+        };
+        Ok(Document::new(source, format, bytes, modified))
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Document, Error> {
+        let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+        let resp = client.get(url).send().await?;
+        if !resp.status().is_success() {
+            return Err(Error::Message(format!("{} returned {}", url, resp.status())));
+        }
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let format = Format::from_content_type(&content_type)
+            .or_else(|| Format::from_extension(resp.url().path()))
+            .ok_or_else(|| Error::Message(format!("{} is {}, not a document", url, content_type)))?;
+        let mut bytes = resp.bytes().await?.to_vec();
+        if format == Format::Pdf {
+            // pdftotext 只读文件，先落到临时文件
+            let partial = std::env::temp_dir().join(format!("ariste-ingest-{}.pdf", std::process::id()));
+            tokio::fs::write(&partial, &bytes).await?;
+            let text = pdf_text(&partial).await;
+            tokio::fs::remove_file(&partial).await.ok();
+            bytes = text?.into_bytes();
+        }
+        Ok(Document::new(url.to_string(), format, bytes, 0))
+    }
+
+    /// Read, cut, and embed the documents of `source`: a file, every
+    /// document file under a directory, or an http(s) URL
+    pub async fn ingest(&self, source: &str) -> Result<IngestReport, Error> {
+        let mut documents = Vec::new();
+        let mut report = IngestReport::default();
+        if source.starts_with("http://") || source.starts_with("https://") {
+            documents.push(self.fetch(source).await?);
+        } else {
+            let path = Path::new(source);
+            if !path.exists() {
+                return Err(Error::Message(format!("{} does not exist", source)));
+            }
+            let files = if path.is_dir() { document_files(path) } else { vec![path.to_path_buf()] };
+            if files.is_empty() {
+                return Err(Error::Message(format!(
+                    "No documents under {} (looked for {})",
+                    source,
+                    DOC_EXTENSIONS.join(", ")
+                )));
+            }
+            for file in files {
+                match self.read_file(&file).await {
+                    Ok(document) => documents.push(document),
+                    Err(e) => report.failed.push((self.source_name(&file), e.to_string())),
+                }
+            }
+        }
+
+        for document in documents {
+            match self.add(&document).await {
+                Ok((chunks, updated)) => report.ingested.push((document.source, chunks, updated)),
+                Err(e) => report.failed.push((document.source, e.to_string())),
+            }
+        }
+        if report.ingested.iter().any(|(_, _, updated)| *updated) {
+            self.store().await?.save(&self.file).await?;
+        }
+        Ok(report)
+    }
+
+    /// Embed `document` unless its text is already in; its chunk count and
+    /// whether it was embedded
+    async fn add(&self, document: &Document) -> Result<(usize, bool), Error> {
+        let hash = content_hash(&document.text);
+        if let Some(file) = self.store().await?.files.get(&document.source)
+            && file.hash == hash
+        {
+            return Ok((file.chunks.len(), false));
+        }
+        let language = if document.markdown { ("markdown", Syntax::Markdown) } else { ("text", Syntax::Plain) };
+        let chunks = self.chunker.chunk_as(&document.source, &document.text, language);
+        let vectors = embed_chunks(self.embedder.as_ref(), &chunks).await?;
+        let count = chunks.len();
+        let file = IndexedFile { hash, modified: document.modified, chunks, vectors };
+        self.store().await?.files.insert(document.source.clone(), file);
+        Ok((count, true))
+    }
+
+    /// The `limit` chunks that best match `query`, from sources starting
+    /// with `prefix` when one is given
+    pub async fn search(&self, query: &str, limit: usize, prefix: Option<&str>) -> Result<Vec<SearchHit>, Error> {
+        let vectors = self.embedder.embed(&[query.to_string()]).await?;
+        let vector = vectors
+            .first()
+            .ok_or_else(|| Error::Message("The embedder returned no vector for the query".to_string()))?;
+        let candidates = if self.reranker.is_some() { limit.saturating_mul(RERANK_CANDIDATES) } else { limit };
+        let hits = self.store().await?.search(vector, candidates, prefix);
+        match &self.reranker {
+            Some(reranker) => reranker.rerank(query, hits, limit).await,
+            None => Ok(hits),
+        }
+    }
+
+    /// Documents and chunks ingested
+    pub async fn status(&self) -> Result<String, Error> {
+        let store = self.store().await?;
+        Ok(format!(
+            "Knowledge base: {} documents, {} chunks, embedded with {}",
+            store.files.len(),
+            store.chunk_count(),
+            self.embedder.model()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::semantic::WordEmbedder;
+
+    #[tokio::test]
+    async fn test_ingest_documents() {
+        let dir = std::env::temp_dir().join("ariste_test_knowledge");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(dir.join("docs/api/.drafts")).unwrap();
+        std::fs::write(dir.join("docs/guide.md"), "# Deploying\n\nRun the release script, then restart the workers.\n").unwrap();
+        std::fs::write(
+            dir.join("docs/api/limits.html"),
+            "<html><head><title>Rate limits</title></head><body><p>Each key may send 100 requests per minute.</p></body></html>",
+        )
+        .unwrap();
+        std::fs::write(dir.join("docs/api/.drafts/wip.md"), "# Draft\n").unwrap();
+        std::fs::write(dir.join("docs/logo.png"), [0u8; 4]).unwrap();
+
+        let knowledge = KnowledgeBase::new(&dir, dir.join(DOCS_INDEX_FILE), Box::new(WordEmbedder));
+        assert!(!knowledge.exists());
+        let report = knowledge.ingest(dir.join("docs").to_str().unwrap()).await.unwrap();
+        assert_eq!(
+            report.ingested,
+            vec![("docs/api/limits.html".to_string(), 1, true), ("docs/guide.md".to_string(), 1, true)]
+        );
+        assert!(knowledge.exists());
+
+        let hits = knowledge.search("requests per minute rate limits", 1, None).await.unwrap();
+        assert_eq!(hits[0].source, "docs/api/limits.html:1-3");
+        assert!(hits[0].text.starts_with("# Rate limits\n\nEach key"));
+        let hits = knowledge.search("requests per minute", 5, Some("docs/guide.md")).await.unwrap();
+        assert_eq!(hits.len(), 1);
+
+        // Ingesting again embeds only what changed
+        let report = knowledge.ingest(dir.join("docs/guide.md").to_str().unwrap()).await.unwrap();
+        assert_eq!(report.ingested, vec![("docs/guide.md".to_string(), 1, false)]);
+        assert!(knowledge.ingest(dir.join("missing").to_str().unwrap()).await.is_err());
+
+        let reopened = KnowledgeBase::new(&dir, dir.join(DOCS_INDEX_FILE), Box::new(WordEmbedder));
+        assert!(reopened.status().await.unwrap().starts_with("Knowledge base: 2 documents, 2 chunks"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
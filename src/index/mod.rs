@@ -0,0 +1,15 @@
+//! Retrieval over the project and its documentation: files are cut into
+//! chunks and embedded into an index kept fresh as they change, and search
+//! results are reranked before they enter the context.
+
+pub mod chunk;
+pub mod knowledge;
+pub mod rerank;
+pub mod semantic;
+pub mod store;
+
+pub use chunk::{Chunk, ChunkCache, Chunker};
+pub use knowledge::{IngestReport, KnowledgeBase};
+pub use rerank::{Reranker, SearchHit};
+pub use semantic::{IndexStats, SemanticIndex};
+pub use store::IndexStore;
@@ -0,0 +1,275 @@
+use crate::config::{AgentConfig, RerankConfig};
+use crate::error::Error;
+use crate::llm::RetryPolicy;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Results kept after reranking unless `rerank.top_k` says otherwise
+pub const DEFAULT_TOP_K: usize = 5;
+/// Share of the keyword score in the hybrid score
+const DEFAULT_BM25_WEIGHT: f32 = 0.3;
+// BM25 的常用参数
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+/// A search result on its way into the context: where it is from, its
+/// text, and how well it matched (higher is better)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub source: String,
+    pub text: String,
+    pub score: f32,
+}
+
+impl SearchHit {
+    pub fn new(source: impl Into<String>, text: impl Into<String>, score: f32) -> Self {
+        Self {
+            source: source.into(),
+            text: text.into(),
+            score,
+        }
+    }
+}
+
+/// Lowercase words of `text`; identifiers count as words
+fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// BM25 score of each of `documents` for `query`, with document
+/// frequencies taken from the documents themselves
+pub fn bm25_scores(query: &str, documents: &[&str]) -> Vec<f32> {
+    let query: HashSet<String> = terms(query).into_iter().collect();
+    let documents: Vec<Vec<String>> = documents.iter().map(|text| terms(text)).collect();
+    let count = documents.len() as f32;
+    let average = documents.iter().map(Vec::len).sum::<usize>() as f32 / count.max(1.0);
+
+    let mut frequency: HashMap<&str, f32> = HashMap::new();
+    for document in &documents {
+        let unique: HashSet<&str> = document.iter().map(String::as_str).collect();
+        for term in unique {
+            *frequency.entry(term).or_default() += 1.0;
+        }
+    }
+
+    documents
+        .iter()
+        .map(|document| {
+            let length = document.len() as f32;
+            query
+                .iter()
+                .map(|term| {
+                    let occurrences = document.iter().filter(|word| *word == term).count() as f32;
+                    if occurrences == 0.0 {
+                        return 0.0;
+                    }
+                    let containing = frequency.get(term.as_str()).copied().unwrap_or(0.0);
+                    let idf = ((count - containing + 0.5) / (containing + 0.5) + 1.0).ln();
+                    let saturation = BM25_K1 * (1.0 - BM25_B + BM25_B * length / average.max(1.0));
+                    idf * occurrences * (BM25_K1 + 1.0) / (occurrences + saturation)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// `scores` scaled to 0..=1; all equal scores become 1
+fn normalized(scores: &[f32]) -> Vec<f32> {
+    let min = scores.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    scores
+        .iter()
+        .map(|score| if max > min { (score - min) / (max - min) } else { 1.0 })
+        .collect()
+}
+
+/// A cross-encoder served behind a `/rerank` endpoint (llama.cpp,
+/// Text Embeddings Inference, Jina, Cohere and others share its shape)
+#[derive(Debug, Clone)]
+pub struct CrossEncoder {
+    /// Full `/rerank` URL
+    pub url: String,
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+    pub retry: RetryPolicy,
+    pub timeout: Option<Duration>,
+}
+
+impl CrossEncoder {
+    /// Relevance of each of `documents` to `query`, in order
+    async fn scores(&self, query: &str, documents: &[&str]) -> Result<Vec<f32>, Error> {
+        let mut body = json!({"query": query, "documents": documents, "top_n": documents.len()});
+        if let Some(model) = &self.model {
+            body["model"] = json!(model);
+        }
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout {
+            builder = builder.connect_timeout(timeout).read_timeout(timeout);
+        }
+        let mut request = builder.build()?.post(&self.url).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let resp = self.retry.send(request).await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(Error::Message(format!("{} returned {}: {}", self.url, status, text.trim())));
+        }
+        let reply: Value = resp.json().await?;
+        parse_rerank_scores(&reply, documents.len())
+    }
+}
+
+/// Scores of a `/rerank` reply, put back in document order
+pub(crate) fn parse_rerank_scores(reply: &Value, expected: usize) -> Result<Vec<f32>, Error> {
+    let results = reply
+        .get("results")
+        .or_else(|| reply.get("data"))
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| Error::Message("The rerank endpoint returned no results".to_string()))?;
+    let mut scores = vec![f32::NEG_INFINITY; expected];
+    for result in results {
+        let index = result.get("index").and_then(|v| v.as_u64()).map(|index| index as usize);
+        let score = result
+            .get("relevance_score")
+            .or_else(|| result.get("score"))
+            .and_then(|v| v.as_f64());
+        if let (Some(index), Some(score)) = (index, score)
+            && index < expected
+        {
+            scores[index] = score as f32;
+        }
+    }
+    Ok(scores)
+}
+
+/// How search results are reordered before they enter the context, so
+/// fewer and better chunks use up the budget
+#[derive(Debug, Clone)]
+pub enum Reranker {
+    /// Blend the search score with a BM25 keyword score over the results;
+    /// `weight` is the keyword share
+    Bm25 { weight: f32 },
+    /// Score each result against the query with a cross-encoder model
+    CrossEncoder(CrossEncoder),
+}
+
+impl Reranker {
+    /// The reranker the `rerank` settings ask for; `None` when reranking
+    /// is off
+    pub fn from_config(config: &AgentConfig) -> Result<Option<Self>, Error> {
+        let Some(settings) = config.rerank.as_ref().filter(|settings| settings.is_enabled()) else {
+            return Ok(None);
+        };
+        match settings.method.as_deref().unwrap_or("bm25") {
+            "bm25" => Ok(Some(Self::Bm25 {
+                weight: settings.weight.unwrap_or(DEFAULT_BM25_WEIGHT).clamp(0.0, 1.0),
+            })),
+            "cross-encoder" => {
+                let base = settings.base.as_deref().ok_or_else(|| {
+                    Error::Message("rerank.method \"cross-encoder\" needs rerank.base, the server with /rerank".to_string())
+                })?;
+                Ok(Some(Self::CrossEncoder(CrossEncoder {
+                    url: format!("{}/rerank", base.trim_end_matches('/')),
+                    model: settings.model.clone(),
+                    api_key: settings.api_key.clone(),
+                    retry: RetryPolicy::from_config(config.retry.as_ref()),
+                    timeout: config.request_timeout_secs.filter(|&secs| secs > 0).map(Duration::from_secs),
+                })))
+            }
+            other => Err(Error::Message(format!(
+                "Unknown rerank.method '{}'; expected \"bm25\" or \"cross-encoder\"",
+                other
+            ))),
+        }
+    }
+
+    /// `hits` best first by the new score, cut to `top_k`
+    pub async fn rerank(&self, query: &str, hits: Vec<SearchHit>, top_k: usize) -> Result<Vec<SearchHit>, Error> {
+        if hits.is_empty() {
+            return Ok(hits);
+        }
+        let documents: Vec<&str> = hits.iter().map(|hit| hit.text.as_str()).collect();
+        let scores = match self {
+            Self::Bm25 { weight } => {
+                let keyword = normalized(&bm25_scores(query, &documents));
+                let search = normalized(&hits.iter().map(|hit| hit.score).collect::<Vec<_>>());
+                keyword
+                    .iter()
+                    .zip(&search)
+                    .map(|(keyword, search)| weight * keyword + (1.0 - weight) * search)
+                    .collect()
+            }
+            Self::CrossEncoder(encoder) => encoder.scores(query, &documents).await?,
+        };
+
+        let mut reranked: Vec<SearchHit> = hits
+            .into_iter()
+            .zip(scores)
+            .map(|(hit, score)| SearchHit { score, ..hit })
+            .collect();
+        reranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+        reranked.truncate(top_k.max(1));
+        Ok(reranked)
+    }
+}
+
+/// Results the `rerank` settings keep
+pub fn top_k(settings: Option<&RerankConfig>) -> usize {
+    settings.and_then(|settings| settings.top_k).unwrap_or(DEFAULT_TOP_K)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bm25_hybrid_rerank() {
+        let hits = vec![
+            SearchHit::new("a.rs", "fn parse_config(path: &Path) -> Config", 0.80),
+            SearchHit::new("b.rs", "struct Spinner { frames: Vec<String> }", 0.82),
+            SearchHit::new("c.rs", "impl Config { fn default() -> Self }", 0.79),
+        ];
+        // The keyword match lifts the config code above the closer vector hit
+        let reranker = Reranker::Bm25 { weight: 0.6 };
+        let reranked = reranker.rerank("parse config", hits.clone(), 2).await.unwrap();
+        let sources: Vec<&str> = reranked.iter().map(|hit| hit.source.as_str()).collect();
+        assert_eq!(sources, ["a.rs", "c.rs"]);
+
+        // Without the keyword share the search order stays
+        let reranked = Reranker::Bm25 { weight: 0.0 }.rerank("parse config", hits, 3).await.unwrap();
+        assert_eq!(reranked[0].source, "b.rs");
+
+        let scores = bm25_scores("config", &["config config", "nothing here"]);
+        assert!(scores[0] > 0.0 && scores[1] == 0.0);
+    }
+
+    #[test]
+    fn test_reranker_from_config() {
+        assert!(Reranker::from_config(&AgentConfig::default()).unwrap().is_none());
+
+        let config = |method: &str, base: Option<&str>| AgentConfig {
+            rerank: Some(RerankConfig {
+                enabled: Some(true),
+                method: Some(method.to_string()),
+                base: base.map(str::to_string),
+                ..Default::default()
+            }),
+            ..AgentConfig::default()
+        };
+        assert!(matches!(Reranker::from_config(&config("bm25", None)).unwrap(), Some(Reranker::Bm25 { .. })));
+        let Some(Reranker::CrossEncoder(encoder)) = Reranker::from_config(&config("cross-encoder", Some("http://gpu:8012/v1/"))).unwrap() else {
+            panic!("expected a cross-encoder");
+        };
+        assert_eq!(encoder.url, "http://gpu:8012/v1/rerank");
+        assert!(Reranker::from_config(&config("cross-encoder", None)).is_err());
+
+        let reply = json!({"results": [{"index": 1, "relevance_score": 0.9}, {"index": 0, "relevance_score": 0.1}]});
+        assert_eq!(parse_rerank_scores(&reply, 2).unwrap(), vec![0.1, 0.9]);
+    }
+}
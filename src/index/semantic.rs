@@ -0,0 +1,421 @@
+use crate::config::AgentConfig;
+use crate::error::Error;
+use crate::index::chunk::{Chunk, Chunker, content_hash, language};
+use crate::index::rerank::{self, Reranker, SearchHit};
+use crate::index::store::{IndexStore, IndexedFile};
+use crate::llm::{EmbeddingProvider, create_embedder};
+use crate::utils::gitignore::Gitignore;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+
+/// Where the code index is saved, under the workspace root
+pub const CODE_INDEX_FILE: &str = ".ariste/index/code.json";
+/// Seconds between checks for files changed outside the agent's tools
+pub const DEFAULT_WATCH_INTERVAL_SECS: u64 = 10;
+/// Larger files are generated or data, not code worth searching
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+/// Texts sent to the embedder per request
+const EMBED_BATCH: usize = 32;
+/// Candidates fetched per result when a reranker picks among them
+pub(crate) const RERANK_CANDIDATES: usize = 4;
+/// Indexed besides the languages the chunker knows
+const TEXT_EXTENSIONS: &[&str] = &["toml", "yaml", "yml", "sh", "sql", "txt", "rst", "html", "css", "scss", "proto", "graphql"];
+/// Never indexed, ignored by git or not
+pub(crate) const SKIPPED_DIRS: &[&str] = &[".git", ".ariste", "node_modules", "vendor", "target", ".venv", "venv", "__pycache__", "bower_components"];
+
+fn indexable(path: &Path) -> bool {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    language(path).0 != "text" || TEXT_EXTENSIONS.contains(&extension)
+}
+
+/// A vector for each of `chunks`, embedded a batch at a time
+pub(crate) async fn embed_chunks(embedder: &dyn EmbeddingProvider, chunks: &[Chunk]) -> Result<Vec<Vec<f32>>, Error> {
+    let texts: Vec<String> = chunks.iter().map(Chunk::embedding_text).collect();
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBED_BATCH) {
+        vectors.extend(embedder.embed(batch).await?);
+    }
+    Ok(vectors)
+}
+
+pub(crate) fn modified_millis(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Files under `root` the index holds, with their modification times
+fn indexable_files(root: &Path) -> Vec<(PathBuf, u64)> {
+    let mut ignore = Gitignore::new(root);
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let (path, Ok(file_type)) = (entry.path(), entry.file_type()) else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let name = entry.file_name();
+                if !SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref()) && !ignore.is_ignored(&path, true) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file()
+                && indexable(&path)
+                && let Ok(metadata) = entry.metadata()
+                && metadata.len() <= MAX_FILE_BYTES
+                && !ignore.is_ignored(&path, false)
+            {
+                files.push((path, modified_millis(&metadata)));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// What an update of the index did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexStats {
+    /// Files and chunks in the index afterwards
+    pub files: usize,
+    pub chunks: usize,
+    /// Files cut and embedded again
+    pub updated: usize,
+    /// Files dropped because they are gone or no longer indexed
+    pub removed: usize,
+}
+
+/// The code index `semantic_search` reads: the workspace cut into chunks
+/// and embedded, saved under `.ariste/index`. Edited files are cut and
+/// embedded again in the background, so it stays fresh during a session.
+#[derive(Debug)]
+pub struct SemanticIndex {
+    root: PathBuf,
+    file: PathBuf,
+    chunker: Chunker,
+    embedder: Box<dyn EmbeddingProvider>,
+    reranker: Option<Reranker>,
+    results: usize,
+    /// Loaded from `file` on first use
+    store: Mutex<Option<IndexStore>>,
+    /// Held while files are embedded, so two updates don't embed the same file
+    updating: Mutex<()>,
+    last_error: std::sync::Mutex<Option<String>>,
+}
+
+impl SemanticIndex {
+    pub fn new(root: &Path, file: PathBuf, embedder: Box<dyn EmbeddingProvider>) -> Self {
+        Self {
+            root: std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf()),
+            file,
+            chunker: Chunker::default(),
+            embedder,
+            reranker: None,
+            results: rerank::DEFAULT_TOP_K,
+            store: Mutex::new(None),
+            updating: Mutex::new(()),
+            last_error: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn chunker(mut self, chunker: Chunker) -> Self {
+        self.chunker = chunker;
+        self
+    }
+
+    pub fn reranker(mut self, reranker: Option<Reranker>) -> Self {
+        self.reranker = reranker;
+        self
+    }
+
+    /// Results a search returns unless it asks for a number
+    pub fn results(mut self, results: usize) -> Self {
+        self.results = results.max(1);
+        self
+    }
+
+    /// The index of the workspace at `root`, when the `index` settings
+    /// enable it
+    pub fn from_config(config: &AgentConfig, root: &Path) -> Result<Option<Self>, Error> {
+        let Some(settings) = config.index.as_ref().filter(|settings| settings.is_enabled()) else {
+            return Ok(None);
+        };
+        let index = Self::new(root, root.join(CODE_INDEX_FILE), create_embedder(config)?)
+            .chunker(Chunker::from_config(settings.chunking.as_ref())?)
+            .reranker(Reranker::from_config(config)?)
+            .results(rerank::top_k(config.rerank.as_ref()));
+        Ok(Some(index))
+    }
+
+    pub fn default_results(&self) -> usize {
+        self.results
+    }
+
+    async fn store(&self) -> Result<MappedMutexGuard<'_, IndexStore>, Error> {
+        let mut store = self.store.lock().await;
+        if store.is_none() {
+            *store = Some(IndexStore::load(&self.file, self.embedder.model()).await?);
+        }
+        Ok(MutexGuard::map(store, |store| store.get_or_insert_with(IndexStore::default)))
+    }
+
+    /// `path` as the index names it: relative to the root, with `/`
+    fn relative(&self, path: &Path) -> Option<String> {
+        let path = std::path::absolute(path).ok()?;
+        let relative = path.strip_prefix(&self.root).ok()?;
+        Some(relative.to_string_lossy().replace('\\', "/"))
+    }
+
+    /// Whether the file at `path` belongs in the index
+    fn wanted(&self, path: &Path) -> bool {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return false;
+        };
+        let skipped = path.components().any(|component| match component {
+            Component::Normal(name) => SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref()),
+            _ => false,
+        });
+        metadata.is_file()
+            && metadata.len() <= MAX_FILE_BYTES
+            && indexable(path)
+            && !skipped
+            && !Gitignore::new(&self.root).is_ignored(path, false)
+    }
+
+    /// Cut and embed one file unless the index holds its content already;
+    /// true when its entry changed
+    async fn index_file(&self, relative: &str, path: &Path) -> Result<bool, Error> {
+        let modified = modified_millis(&tokio::fs::metadata(path).await?);
+        let content = match String::from_utf8(tokio::fs::read(path).await?) {
+            Ok(content) if !content.contains('\0') => content,
+            _ => return Ok(self.store().await?.files.remove(relative).is_some()),
+        };
+        let hash = content_hash(&content);
+        if let Some(file) = self.store().await?.files.get_mut(relative)
+            && file.hash == hash
+        {
+            file.modified = modified;
+            return Ok(false);
+        }
+
+        let chunks = self.chunker.chunk(relative, &content);
+        let vectors = embed_chunks(self.embedder.as_ref(), &chunks).await?;
+        let file = IndexedFile { hash, modified, chunks, vectors };
+        self.store().await?.files.insert(relative.to_string(), file);
+        Ok(true)
+    }
+
+    /// Bring the whole tree up to date: files whose modification time
+    /// changed are cut and embedded again, missing ones dropped
+    pub async fn sync(&self) -> Result<IndexStats, Error> {
+        let _updating = self.updating.lock().await;
+        self.sync_tree(false).await
+    }
+
+    /// Build the index again from nothing (`/index rebuild`)
+    pub async fn rebuild(&self) -> Result<IndexStats, Error> {
+        let _updating = self.updating.lock().await;
+        *self.store().await? = IndexStore::new(self.embedder.model());
+        self.sync_tree(true).await
+    }
+
+    async fn sync_tree(&self, save: bool) -> Result<IndexStats, Error> {
+        let root = self.root.clone();
+        let files = tokio::task::spawn_blocking(move || indexable_files(&root))
+            .await
+            .map_err(|e| Error::Message(format!("Failed to list files for the index: {}", e)))?;
+        let mut stats = IndexStats::default();
+        let mut seen = HashSet::new();
+        for (path, modified) in files {
+            let Some(relative) = self.relative(&path) else {
+                continue;
+            };
+            let known = self.store().await?.files.get(&relative).map(|file| file.modified);
+            if known != Some(modified) && self.index_file(&relative, &path).await? {
+                stats.updated += 1;
+            }
+            seen.insert(relative);
+        }
+
+        let mut store = self.store().await?;
+        let before = store.files.len();
+        store.files.retain(|path, _| seen.contains(path));
+        stats.removed = before - store.files.len();
+        stats.files = store.files.len();
+        stats.chunks = store.chunk_count();
+        if save || stats.updated + stats.removed > 0 {
+            store.save(&self.file).await?;
+        }
+        Ok(stats)
+    }
+
+    /// Update the entries of `paths`, e.g. the files a tool just wrote,
+    /// moved or deleted
+    pub async fn refresh(&self, paths: &[PathBuf]) -> Result<IndexStats, Error> {
+        let _updating = self.updating.lock().await;
+        let mut stats = IndexStats::default();
+        for path in paths {
+            let Some(relative) = self.relative(path) else {
+                continue;
+            };
+            if self.wanted(path) {
+                if self.index_file(&relative, path).await? {
+                    stats.updated += 1;
+                }
+            } else if self.store().await?.files.remove(&relative).is_some() {
+                stats.removed += 1;
+            }
+        }
+        let store = self.store().await?;
+        stats.files = store.files.len();
+        stats.chunks = store.chunk_count();
+        if stats.updated + stats.removed > 0 {
+            store.save(&self.file).await?;
+        }
+        Ok(stats)
+    }
+
+    fn note<T>(&self, result: Result<T, Error>) {
+        *self.last_error.lock().unwrap() = result.err().map(|e| e.to_string());
+    }
+
+    /// `refresh` on a task of its own; a failure shows in `status`
+    pub fn refresh_in_background(self: &Arc<Self>, paths: Vec<PathBuf>) {
+        let index = self.clone();
+        tokio::spawn(async move {
+            let result = index.refresh(&paths).await;
+            index.note(result);
+        });
+    }
+
+    /// Watch the tree for changes made outside the agent's tools, e.g. in
+    /// an editor: `sync` now and then every `interval`. Polling keeps it
+    /// to modification times; only changed files are read again.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let index = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let result = index.sync().await;
+                index.note(result);
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// The `limit` chunks that best match `query`, from files under
+    /// `prefix` when one is given; reranked when a reranker is set
+    pub async fn search(&self, query: &str, limit: usize, prefix: Option<&str>) -> Result<Vec<SearchHit>, Error> {
+        let vectors = self.embedder.embed(&[query.to_string()]).await?;
+        let vector = vectors
+            .first()
+            .ok_or_else(|| Error::Message("The embedder returned no vector for the query".to_string()))?;
+        let candidates = if self.reranker.is_some() { limit.saturating_mul(RERANK_CANDIDATES) } else { limit };
+        let hits = self.store().await?.search(vector, candidates, prefix);
+        match &self.reranker {
+            Some(reranker) => reranker.rerank(query, hits, limit).await,
+            None => Ok(hits),
+        }
+    }
+
+    /// Files and chunks indexed, and the last failed update
+    pub async fn status(&self) -> Result<String, Error> {
+        let store = self.store().await?;
+        let mut status = format!(
+            "Code index: {} files, {} chunks, embedded with {}",
+            store.files.len(),
+            store.chunk_count(),
+            self.embedder.model()
+        );
+        if let Some(error) = self.last_error.lock().unwrap().as_deref() {
+            status.push_str(&format!("\nLast update failed: {}", error));
+        }
+        Ok(status)
+    }
+}
+
+/// Embeds text as counts of its words hashed into a few buckets: texts
+/// sharing words come out close. For tests, which have no model server.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct WordEmbedder;
+
+#[cfg(test)]
+impl EmbeddingProvider for WordEmbedder {
+    fn name(&self) -> &'static str {
+        "test"
+    }
+
+    fn model(&self) -> &str {
+        "words"
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> crate::llm::EmbedFuture<'a> {
+        let vectors = texts
+            .iter()
+            .map(|text| {
+                let mut vector = vec![0.0; 64];
+                for word in text.split(|c: char| !c.is_alphanumeric()).filter(|word| word.len() > 2) {
+                    let bucket = word.to_lowercase().bytes().fold(7usize, |hash, byte| hash * 31 + byte as usize) % 64;
+                    vector[bucket] += 1.0;
+                }
+                vector
+            })
+            .collect();
+        Box::pin(async move { Ok(vectors) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_index_updates_on_change() {
+        let dir = std::env::temp_dir().join("ariste_test_semantic_index");
+        std::fs::remove_dir_all(&dir).ok();
+        for sub in ["src", "target", "generated"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        std::fs::write(dir.join(".gitignore"), "generated/\n").unwrap();
+        std::fs::write(dir.join("src/config.rs"), "/// Parse the settings file\nfn parse_config(path: &str) -> Config {\n    todo!()\n}\n").unwrap();
+        std::fs::write(dir.join("src/spinner.rs"), "/// Spinner frames drawn while waiting\nstruct Spinner {\n    frames: Vec<String>,\n}\n").unwrap();
+        std::fs::write(dir.join("target/build.rs"), "fn spinner_frames() {}\n").unwrap();
+        std::fs::write(dir.join("generated/spinner.rs"), "fn spinner_frames() {}\n").unwrap();
+
+        let file = dir.join(CODE_INDEX_FILE);
+        let index = Arc::new(SemanticIndex::new(&dir, file.clone(), Box::new(WordEmbedder)));
+        let stats = index.rebuild().await.unwrap();
+        assert_eq!((stats.files, stats.updated), (2, 2));
+        let hits = index.search("spinner frames waiting", 1, None).await.unwrap();
+        assert_eq!(hits[0].source, "src/spinner.rs:1-4");
+
+        // An edit is embedded again, an unchanged file is not
+        std::fs::write(dir.join("src/spinner.rs"), "/// Progress bar shown while saving\nstruct ProgressBar {\n    width: usize,\n}\n").unwrap();
+        let changed = [dir.join("src/spinner.rs"), dir.join("src/config.rs")];
+        assert_eq!(index.refresh(&changed).await.unwrap().updated, 1);
+        let hits = index.search("progress bar saving", 1, None).await.unwrap();
+        assert!(hits[0].text.contains("ProgressBar"));
+        assert_eq!(index.refresh(&changed).await.unwrap().updated, 0);
+
+        // Deleted and new files, as the watcher sees them
+        std::fs::remove_file(dir.join("src/spinner.rs")).unwrap();
+        assert_eq!(index.refresh(&[dir.join("src/spinner.rs")]).await.unwrap().removed, 1);
+        std::fs::write(dir.join("src/lib.rs"), "mod config;\n").unwrap();
+        let stats = index.sync().await.unwrap();
+        assert_eq!((stats.files, stats.updated, stats.removed), (2, 1, 0));
+
+        // Saved for the next session
+        let reopened = SemanticIndex::new(&dir, file, Box::new(WordEmbedder));
+        assert!(reopened.status().await.unwrap().starts_with("Code index: 2 files, 2 chunks"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,122 @@
+use crate::error::Error;
+use crate::index::chunk::Chunk;
+use crate::index::rerank::SearchHit;
+use crate::llm::cosine_similarity;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One file of the index: what it held when it was cut, and a vector for
+/// each of its chunks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexedFile {
+    /// `content_hash` of the file
+    pub hash: String,
+    /// Modification time in milliseconds since the epoch
+    pub modified: u64,
+    pub chunks: Vec<Chunk>,
+    pub vectors: Vec<Vec<f32>>,
+}
+
+/// Chunks and their vectors by file path, as saved on disk
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexStore {
+    /// Embedding model the vectors come from; vectors of another model don't compare
+    pub model: String,
+    pub files: BTreeMap<String, IndexedFile>,
+}
+
+impl IndexStore {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// The store saved at `path` for `model`; empty when there is none yet
+    /// or it was built with another model
+    pub async fn load(path: &Path, model: &str) -> Result<Self, Error> {
+        if !tokio::fs::try_exists(path).await? {
+            return Ok(Self::new(model));
+        }
+        let store: Self = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+        Ok(if store.model == model { store } else { Self::new(model) })
+    }
+
+    /// Write the store to `path`, through a temporary file so a crash
+    /// doesn't leave half of it
+    pub async fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let partial = path.with_extension("json.tmp");
+        tokio::fs::write(&partial, serde_json::to_vec(self)?).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.files.values().map(|file| file.chunks.len()).sum()
+    }
+
+    /// The `limit` chunks closest to `query`, best first, from files under
+    /// `prefix` when one is given
+    pub fn search(&self, query: &[f32], limit: usize, prefix: Option<&str>) -> Vec<SearchHit> {
+        let prefix = prefix.map(|prefix| prefix.trim_start_matches("./").trim_end_matches('/'));
+        let mut hits: Vec<SearchHit> = self
+            .files
+            .iter()
+            .filter(|(path, _)| {
+                prefix.is_none_or(|prefix| {
+                    prefix.is_empty() || path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                })
+            })
+            .flat_map(|(_, file)| file.chunks.iter().zip(&file.vectors))
+            .map(|(chunk, vector)| {
+                let source = format!("{}:{}-{}", chunk.path, chunk.start_line, chunk.end_line);
+                SearchHit::new(source, chunk.text.clone(), cosine_similarity(query, vector))
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, text: &str, vector: Vec<f32>) -> IndexedFile {
+        let chunk = Chunk {
+            path: path.to_string(),
+            start_line: 1,
+            end_line: 1,
+            symbol: None,
+            context: None,
+            text: text.to_string(),
+        };
+        IndexedFile { hash: String::new(), modified: 0, chunks: vec![chunk], vectors: vec![vector] }
+    }
+
+    #[tokio::test]
+    async fn test_store_search_and_persist() {
+        let mut store = IndexStore::new("nomic-embed-text");
+        store.files.insert("src/a.rs".to_string(), file("src/a.rs", "fn a() {}", vec![1.0, 0.0]));
+        store.files.insert("src/ab/b.rs".to_string(), file("src/ab/b.rs", "fn b() {}", vec![0.6, 0.8]));
+        store.files.insert("docs/c.md".to_string(), file("docs/c.md", "# C", vec![0.0, 1.0]));
+
+        let sources = |hits: Vec<SearchHit>| hits.into_iter().map(|hit| hit.source).collect::<Vec<_>>();
+        assert_eq!(sources(store.search(&[1.0, 0.1], 2, None)), ["src/a.rs:1-1", "src/ab/b.rs:1-1"]);
+        assert_eq!(sources(store.search(&[1.0, 0.1], 5, Some("src/ab/"))), ["src/ab/b.rs:1-1"]);
+        assert!(store.search(&[1.0, 0.0], 5, Some("src/a")).is_empty());
+
+        let path = std::env::temp_dir().join("ariste_test_store/index/code.json");
+        store.save(&path).await.unwrap();
+        assert_eq!(IndexStore::load(&path, "nomic-embed-text").await.unwrap(), store);
+        // Vectors of another model are dropped
+        assert!(IndexStore::load(&path, "mxbai-embed-large").await.unwrap().files.is_empty());
+        std::fs::remove_dir_all(std::env::temp_dir().join("ariste_test_store")).ok();
+    }
+}
@@ -3,13 +3,15 @@
 //! 这是一个用于构建 AI Agent 的框架，支持工具调用和多代理协作。
 
 pub mod agent;
-pub mod cli;
 pub mod config;
 pub mod error;
+pub mod index;
 pub mod llm;
 pub mod tools;
 pub mod ui;
 pub mod utils;
+pub mod workflow;
+pub mod workspace;
 
 // Re-export commonly used types
 pub use agent::{Agent, SubAgentType};
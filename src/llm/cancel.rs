@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+/// Stops a generation in flight (Ctrl-C in the REPL). Clones share the
+/// state, so the agent keeps one and hands copies to the client and the
+/// signal handler. A cancelled stream ends early and returns what it has.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Clear a cancellation before the next turn
+    pub fn reset(&self) {
+        self.inner.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Wait for `future`, or return `None` as soon as the token is cancelled
+    pub async fn or_cancelled<T>(&self, future: impl Future<Output = T>) -> Option<T> {
+        let notified = self.inner.notify.notified();
+        tokio::pin!(notified);
+        // 先登记等待再检查标志，避免错过并发的 cancel()
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return None;
+        }
+        tokio::select! {
+            output = future => Some(output),
+            _ = notified => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_stops_waiting() {
+        let token = CancelToken::new();
+        assert_eq!(token.or_cancelled(async { 7 }).await, Some(7));
+
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+        let never = std::future::pending::<()>();
+        assert_eq!(token.or_cancelled(never).await, None);
+        assert!(token.is_cancelled());
+
+        // Already cancelled: nothing more is awaited until reset
+        assert_eq!(token.or_cancelled(async { 7 }).await, None);
+        token.reset();
+        assert_eq!(token.or_cancelled(async { 7 }).await, Some(7));
+    }
+}
@@ -0,0 +1,543 @@
+use crate::agent::Message;
+use crate::error::Error;
+use crate::llm::cancel::CancelToken;
+use crate::llm::cost::{CostGuard, TokenUsage, estimate_tokens};
+use crate::llm::display::StreamPrinter;
+use crate::llm::openai::PartialCall;
+use crate::llm::provider::{ChatResponse, http_client};
+use crate::llm::retry::RetryPolicy;
+use crate::llm::sse::SseDecoder;
+use crate::tools::ToolDefinition;
+use crate::ui::Frontend;
+use crate::utils::base64_mime;
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_ANTHROPIC_BASE: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The Messages API requires a cap on the reply length
+const DEFAULT_MAX_TOKENS: u64 = 8192;
+
+/// Client for Anthropic's Messages API
+#[derive(Debug)]
+pub struct ClaudeProvider {
+    /// Full `/messages` URL
+    pub url: String,
+    pub api_key: Option<String>,
+    pub max_tokens: u64,
+    pub stream: bool,
+    pub verbose: bool,
+    /// Print response content as it streams; when false the caller prints it
+    pub stream_content: bool,
+    pub tools: Option<Vec<ToolDefinition>>,
+    /// Per-turn spending cap for paid models
+    pub cost_guard: Option<CostGuard>,
+    /// Receives streamed replies instead of the terminal
+    pub frontend: Option<Arc<dyn Frontend>>,
+    /// Resending requests that fail transiently
+    pub retry: RetryPolicy,
+    /// How long to wait for data from the server before failing
+    pub timeout: Option<Duration>,
+    /// Cancelling it ends the reply in flight with what has arrived
+    pub cancel: CancelToken,
+}
+
+impl Default for ClaudeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Block being streamed, keyed by its index in the reply
+#[derive(Debug)]
+enum PartialBlock {
+    Text,
+    Thinking,
+    ToolUse(PartialCall),
+}
+
+/// Thinking and text fragments carried by one event
+type Fragments = (Option<String>, Option<String>);
+
+/// What the streamed events have carried so far besides the text
+#[derive(Debug, Default)]
+struct StreamState {
+    blocks: BTreeMap<u64, PartialBlock>,
+    prompt_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+impl StreamState {
+    /// Take in one event, returning its thinking and text fragments, or
+    /// `None` for events that carry neither. An `error` event fails the reply.
+    fn apply(&mut self, event: &Value) -> Result<Option<Fragments>, Error> {
+        let index = event.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+        match event.get("type").and_then(|v| v.as_str()).unwrap_or_default() {
+            "message_start" => {
+                let usage = event.pointer("/message/usage");
+                self.prompt_tokens = usage
+                    .and_then(|usage| usage.get("input_tokens"))
+                    .and_then(|v| v.as_u64());
+            }
+            "content_block_start" => {
+                let block = event.get("content_block").cloned().unwrap_or_default();
+                let partial = match block.get("type").and_then(|v| v.as_str()) {
+                    Some("tool_use") => PartialBlock::ToolUse(PartialCall {
+                        id: block.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                        name: block.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                        arguments: String::new(),
+                    }),
+                    Some("thinking") => PartialBlock::Thinking,
+                    _ => PartialBlock::Text,
+                };
+                self.blocks.insert(index, partial);
+            }
+            "content_block_delta" => {
+                let delta = event.get("delta").cloned().unwrap_or_default();
+                let text = |field: &str| delta.get(field).and_then(|v| v.as_str()).map(str::to_string);
+                match delta.get("type").and_then(|v| v.as_str()) {
+                    Some("text_delta") => return Ok(Some((None, text("text")))),
+                    Some("thinking_delta") => return Ok(Some((text("thinking"), None))),
+                    Some("input_json_delta") => {
+                        if let Some(PartialBlock::ToolUse(call)) = self.blocks.get_mut(&index)
+                            && let Some(json) = delta.get("partial_json").and_then(|v| v.as_str())
+                        {
+                            call.arguments.push_str(json);
+                        }
+                        return Ok(Some((None, None)));
+                    }
+                    _ => {}
+                }
+            }
+            "message_delta" => {
+                if let Some(tokens) = event.pointer("/usage/output_tokens").and_then(|v| v.as_u64()) {
+                    self.output_tokens = Some(tokens);
+                }
+            }
+            "error" => {
+                let message = event
+                    .pointer("/error/message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown error");
+                return Err(Error::Message(format!("Anthropic API error: {}", message)));
+            }
+            // ping、content_block_stop、message_stop 无需处理
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn into_calls(self) -> Vec<Value> {
+        self.blocks
+            .into_values()
+            .filter_map(|block| match block {
+                PartialBlock::ToolUse(call) => Some(call.finish()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A tool call's arguments as a JSON object, which `tool_use.input` must be
+fn call_input(call: &Value) -> Value {
+    match call.pointer("/function/arguments") {
+        Some(Value::Object(arguments)) => Value::Object(arguments.clone()),
+        Some(Value::String(arguments)) => serde_json::from_str(arguments)
+            .ok()
+            .filter(Value::is_object)
+            .unwrap_or_else(|| json!({})),
+        _ => json!({}),
+    }
+}
+
+/// The system prompt and messages in the Messages API format. System
+/// messages move to the top-level prompt, tool calls become `tool_use`
+/// blocks and tool results `tool_result` blocks in a user turn, and
+/// consecutive turns of the same role are merged since the API requires
+/// the roles to alternate. Calls without an id (e.g. history from Ollama)
+/// get one, and their results are matched to them in order.
+pub(crate) fn to_claude_messages(messages: &[Message]) -> (Option<String>, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut turns: Vec<(String, Vec<Value>)> = Vec::new();
+    let mut pending: VecDeque<String> = VecDeque::new();
+
+    for (position, message) in messages.iter().enumerate() {
+        let (role, blocks) = match message {
+            Message::System { content } => {
+                system.push(content.clone());
+                continue;
+            }
+            Message::Assistant { content, tool_calls } => {
+                let mut blocks = Vec::new();
+                if !content.trim().is_empty() {
+                    blocks.push(json!({"type": "text", "text": content}));
+                }
+                pending.clear();
+                for (index, call) in tool_calls.iter().enumerate() {
+                    let id = match call.get("id").and_then(|v| v.as_str()) {
+                        Some(id) if !id.is_empty() => id.to_string(),
+                        _ => format!("toolu_{}_{}", position, index),
+                    };
+                    pending.push_back(id.clone());
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": id,
+                        "name": call.pointer("/function/name").and_then(|v| v.as_str()).unwrap_or_default(),
+                        "input": call_input(call),
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            Message::Tool { content, id, images, .. } => {
+                let id = match id.as_deref() {
+                    Some(id) if !id.is_empty() => {
+                        pending.retain(|pending| pending != id);
+                        id.to_string()
+                    }
+                    _ => pending.pop_front().unwrap_or_default(),
+                };
+                let mut block = json!({"type": "tool_result", "tool_use_id": id, "content": content});
+                if !images.is_empty() {
+                    let mut parts = vec![json!({"type": "text", "text": content})];
+                    parts.extend(images.iter().map(|data| {
+                        json!({"type": "image", "source": {"type": "base64", "media_type": base64_mime(data), "data": data}})
+                    }));
+                    block["content"] = Value::Array(parts);
+                }
+                ("user", vec![block])
+            }
+            Message::User { content } => {
+                if content.trim().is_empty() {
+                    continue;
+                }
+                ("user", vec![json!({"type": "text", "text": content})])
+            }
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last, existing)) if last == role => existing.extend(blocks),
+            _ => turns.push((role.to_string(), blocks)),
+        }
+    }
+
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    let messages = turns
+        .into_iter()
+        .map(|(role, content)| json!({"role": role, "content": content}))
+        .collect();
+    (system, messages)
+}
+
+/// Tool definitions in the Messages API format
+fn to_claude_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.function.name,
+                "description": tool.function.description,
+                "input_schema": tool.function.parameters,
+            })
+        })
+        .collect()
+}
+
+impl ClaudeProvider {
+    pub fn new() -> Self {
+        ClaudeProvider {
+            url: format!("{}/messages", DEFAULT_ANTHROPIC_BASE),
+            api_key: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            stream: true,
+            verbose: true,
+            stream_content: true,
+            tools: None,
+            cost_guard: None,
+            frontend: None,
+            retry: RetryPolicy::default(),
+            timeout: None,
+            cancel: CancelToken::new(),
+        }
+    }
+
+    /// Use the endpoint at `base`, e.g. a proxy in front of the API
+    pub fn base(mut self, base: &str) -> Self {
+        self.url = format!("{}/messages", base.trim_end_matches('/'));
+        self
+    }
+
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn stream_content(mut self, stream_content: bool) -> Self {
+        self.stream_content = stream_content;
+        self
+    }
+
+    pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn cost_guard(mut self, cost_guard: CostGuard) -> Self {
+        self.cost_guard = Some(cost_guard);
+        self
+    }
+
+    fn payload(&self, model: &str, messages: &[Message], tools: Option<&[ToolDefinition]>, stream: bool) -> Value {
+        let (system, messages) = to_claude_messages(messages);
+        let mut payload = json!({
+            "model": model,
+            "max_tokens": self.max_tokens,
+            "messages": messages,
+            "stream": stream,
+        });
+        if let Some(system) = system {
+            payload["system"] = json!(system);
+        }
+        if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+            payload["tools"] = Value::Array(to_claude_tools(tools));
+        }
+        payload
+    }
+
+    pub async fn execute_with_messages(&self, model: &str, messages: &[Message]) -> Result<ChatResponse, Error> {
+        self.send(model, messages, self.tools.as_deref(), self.stream).await
+    }
+
+    /// Send the conversation offering `tools`, streaming the reply when `stream` is set
+    pub async fn send(
+        &self,
+        model: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+        stream: bool,
+    ) -> Result<ChatResponse, Error> {
+        let payload = self.payload(model, messages, tools, stream);
+        let mut request = http_client(self.timeout)?
+            .post(&self.url)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&payload);
+        if let Some(key) = &self.api_key {
+            request = request.header("x-api-key", key);
+        }
+        let Some(resp) = self.cancel.or_cancelled(self.retry.send(request)).await else {
+            return Ok(ChatResponse { content: String::new(), tool_calls: None, usage: None });
+        };
+        let resp = resp?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(Error::Message(format!("{} returned {}: {}", self.url, status, body.trim())));
+        }
+
+        let mut prompt_tokens = estimate_tokens(&payload.to_string());
+        let mut output_tokens: u64 = 0;
+        let mut reported = false;
+        let mut printer = StreamPrinter::start(self.verbose, self.stream_content, self.frontend.clone());
+        let mut content = String::new();
+        let tool_calls: Vec<Value>;
+
+        if stream {
+            let mut decoder = SseDecoder::new();
+            let mut stream = resp.bytes_stream();
+            let mut state = StreamState::default();
+            loop {
+                let (events, ended) = match self.cancel.or_cancelled(stream.next()).await {
+                    Some(Some(chunk)) => (decoder.push(&chunk?), false),
+                    Some(None) => (decoder.finish(), true),
+                    // 已取消：保留目前收到的内容
+                    None => break,
+                };
+                for event in events {
+                    let Ok(event) = serde_json::from_str::<Value>(&event.data) else {
+                        continue;
+                    };
+                    let Some((thinking, text)) = state.apply(&event)? else {
+                        continue;
+                    };
+
+                    output_tokens += 1;
+                    if let Some(guard) = &self.cost_guard {
+                        printer.check_cost(guard, prompt_tokens, output_tokens)?;
+                    }
+                    if let Some(thinking) = thinking {
+                        printer.thinking(&thinking);
+                    }
+                    if let Some(text) = text {
+                        printer.content(&text);
+                        content.push_str(&text);
+                    }
+                }
+                if ended {
+                    break;
+                }
+            }
+            reported = state.output_tokens.is_some();
+            prompt_tokens = state.prompt_tokens.unwrap_or(prompt_tokens);
+            output_tokens = state.output_tokens.unwrap_or(output_tokens);
+            tool_calls = state.into_calls();
+        } else {
+            let body: Value = resp.json().await?;
+            if let Some(usage) = body.get("usage") {
+                reported = true;
+                prompt_tokens = usage.get("input_tokens").and_then(|v| v.as_u64()).unwrap_or(prompt_tokens);
+                output_tokens = usage.get("output_tokens").and_then(|v| v.as_u64()).unwrap_or(output_tokens);
+            }
+            let mut calls = Vec::new();
+            for block in body.get("content").and_then(|v| v.as_array()).into_iter().flatten() {
+                match block.get("type").and_then(|v| v.as_str()) {
+                    Some("text") => {
+                        let text = block.get("text").and_then(|v| v.as_str()).unwrap_or_default();
+                        printer.content(text);
+                        content.push_str(text);
+                    }
+                    Some("thinking") => {
+                        printer.thinking(block.get("thinking").and_then(|v| v.as_str()).unwrap_or_default());
+                    }
+                    Some("tool_use") => calls.push(json!({
+                        "id": block.get("id").and_then(|v| v.as_str()).unwrap_or_default(),
+                        "function": {
+                            "name": block.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
+                            "arguments": block.get("input").cloned().unwrap_or_else(|| json!({})),
+                        }
+                    })),
+                    _ => {}
+                }
+            }
+            tool_calls = calls;
+        }
+
+        if let Some(guard) = &self.cost_guard {
+            guard.record(prompt_tokens, output_tokens);
+        }
+        printer.finish();
+
+        Ok(ChatResponse {
+            content,
+            tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                output_tokens,
+                estimated: !reported,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_use_content_blocks() {
+        let call = Message::assistant_with_tools(
+            "Checking both",
+            vec![
+                json!({"id": "toolu_a", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}}),
+                json!({"function": {"name": "grep", "arguments": "{\"pattern\": \"fn\"}"}}),
+            ],
+        );
+        let first = Message::tool(Some("toolu_a".to_string()), None, "logo.png").with_images(vec!["iVBORw0KGgo=".to_string()]);
+        let second = Message::tool(None, None, "a.rs:1");
+
+        let (system, converted) = to_claude_messages(&[
+            Message::system("Be brief"),
+            Message::user("look at a.rs"),
+            call,
+            first,
+            second,
+            Message::user("thanks"),
+        ]);
+        assert_eq!(system.as_deref(), Some("Be brief"));
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[0], json!({"role": "user", "content": [{"type": "text", "text": "look at a.rs"}]}));
+
+        let blocks = converted[1]["content"].as_array().unwrap();
+        assert_eq!(blocks[0]["text"], "Checking both");
+        assert_eq!(blocks[1]["input"], json!({"file_path": "a.rs"}));
+        assert_eq!(blocks[2]["id"], "toolu_2_1");
+        assert_eq!(blocks[2]["input"], json!({"pattern": "fn"}));
+
+        // 两个工具结果和随后的用户消息合并为同一个 user 轮次
+        let results = converted[2]["content"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["tool_use_id"], "toolu_a");
+        assert_eq!(results[0]["content"][0]["text"], "logo.png");
+        assert_eq!(results[0]["content"][1]["source"]["media_type"], "image/png");
+        assert_eq!(results[1]["content"], "a.rs:1");
+        assert_eq!(results[1]["tool_use_id"], "toolu_2_1");
+        assert_eq!(results[2]["text"], "thanks");
+    }
+
+    #[test]
+    fn test_stream_state_assembles_tool_use() {
+        let mut state = StreamState::default();
+        let events = [
+            json!({"type": "message_start", "message": {"usage": {"input_tokens": 310, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "hmm"}}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Reading"}}),
+            json!({"type": "content_block_start", "index": 2, "content_block": {"type": "tool_use", "id": "toolu_7", "name": "read", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "{\"file_path\": "}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "\"a.rs\"}"}}),
+            json!({"type": "ping"}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 42}}),
+        ];
+        let deltas: Vec<_> = events.iter().map(|event| state.apply(event).unwrap()).collect();
+        assert_eq!(deltas[0], None);
+        assert_eq!(deltas[2], Some((Some("hmm".to_string()), None)));
+        assert_eq!(deltas[4], Some((None, Some("Reading".to_string()))));
+        assert_eq!((state.prompt_tokens, state.output_tokens), (Some(310), Some(42)));
+        assert_eq!(
+            state.into_calls(),
+            vec![json!({"id": "toolu_7", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}})]
+        );
+
+        let error = json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}});
+        assert!(StreamState::default().apply(&error).unwrap_err().to_string().contains("Overloaded"));
+    }
+
+    #[test]
+    fn test_payload() {
+        let tool = crate::tools::ToolImpl::definition(&crate::tools::ReadTool);
+        let payload = ClaudeProvider::new().payload("claude-sonnet-4-5", &[Message::user("hi")], Some(&[tool]), false);
+        assert_eq!(payload["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(payload["stream"], false);
+        assert_eq!(payload["tools"][0]["name"], "read");
+        assert_eq!(payload["tools"][0]["input_schema"]["type"], "object");
+        assert!(payload.get("system").is_none());
+        assert_eq!(
+            ClaudeProvider::new().base("http://localhost:8080/v1/").url,
+            "http://localhost:8080/v1/messages"
+        );
+    }
+}
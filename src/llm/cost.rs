@@ -0,0 +1,173 @@
+use crate::config::ModelPrice;
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// Rough token estimate for text sent to the model (about 4 chars per token)
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Tokens used by one or more model requests. `estimated` is set when a
+/// backend didn't report its counts and they were guessed from text length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.output_tokens
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.output_tokens += other.output_tokens;
+        self.estimated |= other.estimated;
+    }
+}
+
+/// Tracks what the current turn has cost so far and decides when a response
+/// that is still streaming would push it over the per-turn cap. Each turn
+/// is priced at the rates of the model it runs on.
+#[derive(Debug)]
+pub struct CostGuard {
+    cap: f64,
+    state: Mutex<GuardState>,
+}
+
+#[derive(Debug, Default)]
+struct GuardState {
+    /// Rates of this turn's model; an unpriced model is not capped
+    price: Option<ModelPrice>,
+    /// Cost of the requests already completed in this turn
+    spent: f64,
+    /// Cap in effect for this turn; raised when the user chooses to continue
+    cap: f64,
+}
+
+impl CostGuard {
+    pub fn new(price: Option<ModelPrice>, cap: f64) -> Self {
+        Self {
+            cap,
+            state: Mutex::new(GuardState { price, spent: 0.0, cap }),
+        }
+    }
+
+    pub fn cost(&self, prompt_tokens: u64, output_tokens: u64) -> f64 {
+        self.state
+            .lock()
+            .unwrap()
+            .price
+            .map_or(0.0, |price| price.cost(prompt_tokens, output_tokens))
+    }
+
+    /// Whether this turn's model has a price, and so a cap
+    pub fn priced(&self) -> bool {
+        self.state.lock().unwrap().price.is_some()
+    }
+
+    /// Reset the running total at the start of a turn run on a model
+    /// priced at `price`
+    pub fn start_turn(&self, price: Option<ModelPrice>) {
+        let mut state = self.state.lock().unwrap();
+        state.price = price;
+        state.spent = 0.0;
+        state.cap = self.cap;
+    }
+
+    /// Turn cost if the request in flight ended now
+    pub fn projected(&self, prompt_tokens: u64, output_tokens: u64) -> f64 {
+        let cost = self.cost(prompt_tokens, output_tokens);
+        self.state.lock().unwrap().spent + cost
+    }
+
+    /// Whether the request in flight would exceed the turn's cap
+    pub fn exceeded(&self, prompt_tokens: u64, output_tokens: u64) -> bool {
+        self.priced() && self.projected(prompt_tokens, output_tokens) > self.state.lock().unwrap().cap
+    }
+
+    pub fn cap(&self) -> f64 {
+        self.state.lock().unwrap().cap
+    }
+
+    /// Allow another cap's worth of spending for this turn
+    pub fn extend(&self) {
+        self.state.lock().unwrap().cap += self.cap;
+    }
+
+    /// Add a finished request to the turn's total
+    pub fn record(&self, prompt_tokens: u64, output_tokens: u64) {
+        let cost = self.cost(prompt_tokens, output_tokens);
+        self.state.lock().unwrap().spent += cost;
+    }
+
+    pub fn spent(&self) -> f64 {
+        self.state.lock().unwrap().spent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> CostGuard {
+        CostGuard::new(
+            Some(ModelPrice {
+                input_per_million: 1.0,
+                output_per_million: 10.0,
+            }),
+            0.01,
+        )
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_cost() {
+        let guard = guard();
+        assert!((guard.cost(1_000_000, 0) - 1.0).abs() < 1e-9);
+        assert!((guard.cost(0, 1_000) - 0.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cap_over_turn() {
+        let guard = guard();
+        assert!(!guard.exceeded(1_000, 500));
+        guard.record(1_000, 500);
+        assert!((guard.spent() - 0.006).abs() < 1e-9);
+
+        // The second request pushes the turn over the cap
+        assert!(guard.exceeded(1_000, 400));
+        guard.extend();
+        assert!(!guard.exceeded(1_000, 400));
+
+        let price = Some(ModelPrice {
+            input_per_million: 1.0,
+            output_per_million: 10.0,
+        });
+        guard.start_turn(price);
+        assert_eq!(guard.spent(), 0.0);
+        assert_eq!(guard.cap(), 0.01);
+
+        // A turn on a cheaper model is priced at its rates
+        guard.start_turn(Some(ModelPrice {
+            input_per_million: 0.1,
+            output_per_million: 1.0,
+        }));
+        assert!((guard.cost(0, 1_000) - 0.001).abs() < 1e-9);
+        assert!(!guard.exceeded(1_000, 5_000));
+        // and one on an unpriced model is not capped
+        guard.start_turn(None);
+        assert!(!guard.priced());
+        assert!(!guard.exceeded(1_000_000, 1_000_000));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::Error;
+use crate::llm::CostGuard;
+use crate::ui::{Frontend, StreamFragment};
+use std::sync::Arc;
+
+/// Hands a streamed reply to the frontend, shared by every provider: the
+/// wait for the first token, the thinking, then the content. Without a
+/// frontend, or in quiet mode, nothing is shown.
+pub(crate) struct StreamPrinter {
+    verbose: bool,
+    frontend: Option<Arc<dyn Frontend>>,
+    stream_content: bool,
+    finished: bool,
+}
+
+impl StreamPrinter {
+    /// Tell the frontend a reply is awaited (not in quiet mode)
+    pub fn start(verbose: bool, stream_content: bool, frontend: Option<Arc<dyn Frontend>>) -> Self {
+        if let Some(frontend) = &frontend
+            && verbose
+        {
+            frontend.waiting();
+        }
+        Self {
+            verbose,
+            frontend,
+            stream_content,
+            finished: false,
+        }
+    }
+
+    /// Where the reply is shown, unless in quiet mode
+    fn shown(&self) -> Option<&Arc<dyn Frontend>> {
+        self.frontend.as_ref().filter(|_| self.verbose)
+    }
+
+    /// Once the turn goes over its cost cap, ask whether to continue;
+    /// declining aborts the reply (the caller drops the response stream).
+    /// Asked even in quiet mode; without a frontend the reply is aborted.
+    pub fn check_cost(&self, guard: &CostGuard, prompt_tokens: u64, output_tokens: u64) -> Result<(), Error> {
+        if !guard.exceeded(prompt_tokens, output_tokens) {
+            return Ok(());
+        }
+        let question = format!(
+            "This turn has cost about ${:.4}, over the ${:.4} cap. Continue?",
+            guard.projected(prompt_tokens, output_tokens),
+            guard.cap()
+        );
+        let approved = self.frontend.as_ref().is_some_and(|frontend| frontend.confirm(&question));
+        if !approved {
+            return Err(Error::Message(format!(
+                "Generation aborted at the ${:.4} turn cost cap",
+                guard.cap()
+            )));
+        }
+        guard.extend();
+        Ok(())
+    }
+
+    /// Show a fragment of the model's reasoning
+    pub fn thinking(&mut self, fragment: &str) {
+        if let Some(frontend) = self.shown() {
+            frontend.stream(StreamFragment::Thinking(fragment));
+        }
+    }
+
+    /// Show a fragment of the reply content, unless the caller shows the
+    /// reply itself once it is complete
+    pub fn content(&mut self, fragment: &str) {
+        if let Some(frontend) = self.shown()
+            && self.stream_content
+        {
+            frontend.stream(StreamFragment::Content(fragment));
+        }
+    }
+
+    /// End the output once the reply is complete
+    pub fn finish(&mut self) {
+        if let Some(frontend) = self.shown()
+            && !self.finished
+        {
+            frontend.stream(StreamFragment::End);
+        }
+        self.finished = true;
+    }
+}
+
+impl Drop for StreamPrinter {
+    fn drop(&mut self) {
+        // 请求出错提前返回时也要结束输出（停止 spinner）
+        self.finish();
+    }
+}
@@ -0,0 +1,321 @@
+use crate::config::{AgentConfig, EmbeddingConfig};
+use crate::error::Error;
+use crate::llm::openai::DEFAULT_OPENAI_BASE;
+use crate::llm::provider::http_client;
+use crate::llm::retry::RetryPolicy;
+use futures_util::future::BoxFuture;
+use serde_json::{Value, json};
+use std::time::Duration;
+
+const DEFAULT_OLLAMA_BASE: &str = "http://localhost:11434";
+/// Small local model used when the `embedding` settings name none
+pub const DEFAULT_OLLAMA_EMBEDDING_MODEL: &str = "nomic-embed-text";
+pub const DEFAULT_OPENAI_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Names accepted by `embedding.provider`
+pub const EMBEDDING_PROVIDER_NAMES: &[&str] = &["ollama", "openai"];
+
+/// Vectors being computed by an embedder, one per input text
+pub type EmbedFuture<'a> = BoxFuture<'a, Result<Vec<Vec<f32>>, Error>>;
+
+/// A backend that turns text into vectors, for the semantic index and
+/// memory recall (`/recall`). Set up apart from the chat model (the
+/// `embedding` settings), so a large hosted chat model can pair with a
+/// small local embedder. Object safe, like `LlmProvider`.
+pub trait EmbeddingProvider: Send + Sync + std::fmt::Debug {
+    /// Backend name as written in `embedding.provider`
+    fn name(&self) -> &'static str;
+
+    /// The embedding model; vectors of different models don't compare
+    fn model(&self) -> &str;
+
+    /// One vector per text, in order
+    fn embed<'a>(&'a self, texts: &'a [String]) -> EmbedFuture<'a>;
+}
+
+/// Cosine of the angle between `a` and `b`: 1 for the same direction,
+/// 0 for unrelated (or empty) vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let (norm_a, norm_b) = (norm(a), norm(b));
+    if norm_a == 0.0 || norm_b == 0.0 { 0.0 } else { dot / (norm_a * norm_b) }
+}
+
+fn to_vector(value: &Value) -> Option<Vec<f32>> {
+    value.as_array()?.iter().map(|x| x.as_f64().map(|x| x as f32)).collect()
+}
+
+/// Fail with the server's explanation when it refused the request
+async fn check(url: &str, resp: reqwest::Response) -> Result<reqwest::Response, Error> {
+    if resp.status().is_success() {
+        return Ok(resp);
+    }
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    Err(Error::Message(format!("{} returned {}: {}", url, status, body.trim())))
+}
+
+/// Text embeddings from Ollama: the batch `/api/embed` endpoint, or
+/// `/api/embeddings` one text at a time on servers too old to have it
+#[derive(Debug)]
+pub struct OllamaEmbedder {
+    /// Server root, e.g. `http://localhost:11434`
+    pub base: String,
+    pub model: String,
+    pub retry: RetryPolicy,
+    pub timeout: Option<Duration>,
+}
+
+impl OllamaEmbedder {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            base: DEFAULT_OLLAMA_BASE.to_string(),
+            model: model.into(),
+            retry: RetryPolicy::default(),
+            timeout: None,
+        }
+    }
+
+    pub fn base(mut self, base: &str) -> Self {
+        self.base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn send(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        let client = http_client(self.timeout)?;
+        let url = format!("{}/api/embed", self.base);
+        let request = client.post(&url).json(&json!({"model": self.model, "input": texts}));
+        let resp = self.retry.send(request).await?;
+        if resp.status() != reqwest::StatusCode::NOT_FOUND {
+            let body: Value = check(&url, resp).await?.json().await?;
+            return parse_ollama_embeddings(&body, texts.len());
+        }
+
+        // 旧版 Ollama 只有逐条计算的接口
+        let url = format!("{}/api/embeddings", self.base);
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            let request = client.post(&url).json(&json!({"model": self.model, "prompt": text}));
+            let body: Value = check(&url, self.retry.send(request).await?).await?.json().await?;
+            let vector = body.get("embedding").and_then(to_vector);
+            vectors.push(vector.ok_or_else(|| Error::Message(format!("{} returned no embedding", url)))?);
+        }
+        Ok(vectors)
+    }
+}
+
+/// Vectors of an `/api/embed` reply, checked against the number of inputs
+pub(crate) fn parse_ollama_embeddings(body: &Value, expected: usize) -> Result<Vec<Vec<f32>>, Error> {
+    let vectors: Option<Vec<Vec<f32>>> = body
+        .get("embeddings")
+        .and_then(|v| v.as_array())
+        .map(|vectors| vectors.iter().filter_map(to_vector).collect());
+    match vectors {
+        Some(vectors) if vectors.len() == expected => Ok(vectors),
+        _ => Err(Error::Message(format!("Ollama returned no embeddings for {} texts", expected))),
+    }
+}
+
+impl EmbeddingProvider for OllamaEmbedder {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> EmbedFuture<'a> {
+        Box::pin(self.send(texts))
+    }
+}
+
+/// Text embeddings from an OpenAI-compatible `/embeddings` endpoint
+#[derive(Debug)]
+pub struct OpenAiEmbedder {
+    /// Full `/embeddings` URL
+    pub url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub retry: RetryPolicy,
+    pub timeout: Option<Duration>,
+}
+
+impl OpenAiEmbedder {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            url: format!("{}/embeddings", DEFAULT_OPENAI_BASE),
+            api_key: None,
+            model: model.into(),
+            retry: RetryPolicy::default(),
+            timeout: None,
+        }
+    }
+
+    /// Use the endpoint at `base`, e.g. `http://localhost:8000/v1`
+    pub fn base(mut self, base: &str) -> Self {
+        self.url = format!("{}/embeddings", base.trim_end_matches('/'));
+        self
+    }
+
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn send(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        let mut request = http_client(self.timeout)?
+            .post(&self.url)
+            .json(&json!({"model": self.model, "input": texts}));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let body: Value = check(&self.url, self.retry.send(request).await?).await?.json().await?;
+        parse_openai_embeddings(&body, texts.len())
+    }
+}
+
+/// Vectors of an `/embeddings` reply in input order (the `data` entries
+/// carry their input's `index`)
+pub(crate) fn parse_openai_embeddings(body: &Value, expected: usize) -> Result<Vec<Vec<f32>>, Error> {
+    let mut entries: Vec<(u64, Vec<f32>)> = body
+        .get("data")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(position, entry)| {
+            let index = entry.get("index").and_then(|v| v.as_u64()).unwrap_or(position as u64);
+            Some((index, to_vector(entry.get("embedding")?)?))
+        })
+        .collect();
+    if entries.len() != expected {
+        return Err(Error::Message(format!(
+            "The embeddings endpoint returned {} vectors for {} texts",
+            entries.len(),
+            expected
+        )));
+    }
+    entries.sort_by_key(|(index, _)| *index);
+    Ok(entries.into_iter().map(|(_, vector)| vector).collect())
+}
+
+impl EmbeddingProvider for OpenAiEmbedder {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, texts: &'a [String]) -> EmbedFuture<'a> {
+        Box::pin(self.send(texts))
+    }
+}
+
+/// Embedder from the `embedding` settings, which default to a local Ollama
+/// with `nomic-embed-text`. Retries and the request timeout are shared
+/// with the chat model; endpoint, key and model are not.
+pub fn create_embedder(config: &AgentConfig) -> Result<Box<dyn EmbeddingProvider>, Error> {
+    let settings: EmbeddingConfig = config.embedding.clone().unwrap_or_default();
+    let retry = RetryPolicy::from_config(config.retry.as_ref());
+    let timeout = config.request_timeout_secs.filter(|&secs| secs > 0).map(Duration::from_secs);
+    match settings.provider.as_deref().unwrap_or("ollama") {
+        "ollama" => Ok(Box::new(
+            OllamaEmbedder::new(settings.model.unwrap_or_else(|| DEFAULT_OLLAMA_EMBEDDING_MODEL.to_string()))
+                .base(settings.base.as_deref().unwrap_or(DEFAULT_OLLAMA_BASE))
+                .retry(retry)
+                .timeout(timeout),
+        )),
+        "openai" => Ok(Box::new(
+            OpenAiEmbedder::new(settings.model.unwrap_or_else(|| DEFAULT_OPENAI_EMBEDDING_MODEL.to_string()))
+                .base(settings.base.as_deref().unwrap_or(DEFAULT_OPENAI_BASE))
+                .api_key(settings.api_key.or_else(|| std::env::var("OPENAI_API_KEY").ok()))
+                .retry(retry)
+                .timeout(timeout),
+        )),
+        other => Err(Error::Message(format!(
+            "Unknown embedding provider '{}' in .ariste/settings.json; expected one of: {}",
+            other,
+            EMBEDDING_PROVIDER_NAMES.join(", ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_embeddings() {
+        let body = json!({"data": [
+            {"object": "embedding", "index": 1, "embedding": [0.0, 1.0]},
+            {"object": "embedding", "index": 0, "embedding": [1.0, 0.0]},
+        ]});
+        assert_eq!(parse_openai_embeddings(&body, 2).unwrap(), vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        assert!(parse_openai_embeddings(&body, 3).is_err());
+
+        let body = json!({"model": "nomic-embed-text", "embeddings": [[0.5, 0.5]]});
+        assert_eq!(parse_ollama_embeddings(&body, 1).unwrap(), vec![vec![0.5, 0.5]]);
+        assert!(parse_ollama_embeddings(&json!({"error": "model not found"}), 1).is_err());
+
+        assert!((cosine_similarity(&[1.0, 1.0], &[2.0, 2.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn test_embedder_from_config() {
+        // Independent of the chat provider
+        let config = AgentConfig {
+            provider: Some("anthropic".to_string()),
+            base: Some("https://api.anthropic.com/v1".to_string()),
+            ..AgentConfig::default()
+        };
+        let embedder = create_embedder(&config).unwrap();
+        assert_eq!((embedder.name(), embedder.model()), ("ollama", DEFAULT_OLLAMA_EMBEDDING_MODEL));
+
+        let config = AgentConfig {
+            embedding: Some(EmbeddingConfig {
+                provider: Some("openai".to_string()),
+                base: Some("http://localhost:8000/v1/".to_string()),
+                model: Some("bge-small".to_string()),
+                api_key: Some("sk-test".to_string()),
+            }),
+            ..AgentConfig::default()
+        };
+        let embedder = create_embedder(&config).unwrap();
+        assert_eq!((embedder.name(), embedder.model()), ("openai", "bge-small"));
+
+        let config = AgentConfig {
+            embedding: Some(EmbeddingConfig {
+                provider: Some("word2vec".to_string()),
+                ..Default::default()
+            }),
+            ..AgentConfig::default()
+        };
+        assert!(create_embedder(&config).unwrap_err().to_string().contains("expected one of: ollama, openai"));
+    }
+}
@@ -0,0 +1,463 @@
+use crate::agent::Message;
+use crate::error::Error;
+use crate::llm::cancel::CancelToken;
+use crate::llm::cost::{CostGuard, TokenUsage, estimate_tokens};
+use crate::llm::display::StreamPrinter;
+use crate::llm::provider::{ChatResponse, http_client};
+use crate::llm::retry::RetryPolicy;
+use crate::llm::sse::SseDecoder;
+use crate::tools::ToolDefinition;
+use crate::ui::Frontend;
+use crate::utils::base64_mime;
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_GEMINI_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// Client for Google's Gemini API (`generateContent`)
+#[derive(Debug)]
+pub struct GeminiProvider {
+    /// API root; the model and method are appended per request
+    pub base: String,
+    pub api_key: Option<String>,
+    pub verbose: bool,
+    /// Print response content as it streams; when false the caller prints it
+    pub stream_content: bool,
+    /// Per-turn spending cap for paid models
+    pub cost_guard: Option<CostGuard>,
+    /// Receives streamed replies instead of the terminal
+    pub frontend: Option<Arc<dyn Frontend>>,
+    /// Resending requests that fail transiently
+    pub retry: RetryPolicy,
+    /// How long to wait for data from the server before failing
+    pub timeout: Option<Duration>,
+    /// Cancelling it ends the reply in flight with what has arrived
+    pub cancel: CancelToken,
+    /// Sampling seed, for reproducible replies
+    pub seed: Option<u64>,
+}
+
+impl Default for GeminiProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Thought and text fragments carried by one chunk
+type Fragments = (Option<String>, Option<String>);
+
+/// What the streamed chunks have carried so far besides the text. Gemini
+/// sends each function call whole, so calls need no assembling.
+#[derive(Debug, Default)]
+struct StreamState {
+    calls: Vec<Value>,
+    prompt_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+impl StreamState {
+    /// Take in one response chunk, returning its thought and text. An
+    /// `error` chunk fails the reply.
+    fn apply(&mut self, chunk: &Value) -> Result<Fragments, Error> {
+        if let Some(error) = chunk.get("error") {
+            let message = error.get("message").and_then(|v| v.as_str()).unwrap_or("unknown error");
+            return Err(Error::Message(format!("Gemini API error: {}", message)));
+        }
+        if let Some(usage) = chunk.get("usageMetadata") {
+            self.prompt_tokens = usage.get("promptTokenCount").and_then(|v| v.as_u64()).or(self.prompt_tokens);
+            self.output_tokens = usage.get("candidatesTokenCount").and_then(|v| v.as_u64()).or(self.output_tokens);
+        }
+
+        let (mut thought, mut text) = (None::<String>, None::<String>);
+        let parts = chunk.pointer("/candidates/0/content/parts").and_then(|v| v.as_array());
+        for part in parts.into_iter().flatten() {
+            if let Some(call) = part.get("functionCall") {
+                self.calls.push(to_call(call, part.get("thoughtSignature")));
+            } else if let Some(fragment) = part.get("text").and_then(|v| v.as_str()) {
+                // 思考摘要也是 text，只是带有 thought: true
+                let target = if part.get("thought").and_then(|v| v.as_bool()) == Some(true) {
+                    &mut thought
+                } else {
+                    &mut text
+                };
+                target.get_or_insert_with(String::new).push_str(fragment);
+            }
+        }
+        Ok((thought, text))
+    }
+}
+
+/// A `functionCall` in the agent's shape. Its thought signature, which
+/// Gemini wants back with the call, rides along on the call.
+fn to_call(call: &Value, signature: Option<&Value>) -> Value {
+    let mut normalized = json!({
+        "id": call.get("id").and_then(|v| v.as_str()).unwrap_or_default(),
+        "function": {
+            "name": call.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
+            "arguments": call.get("args").cloned().unwrap_or_else(|| json!({})),
+        }
+    });
+    if let Some(signature) = signature {
+        normalized["thought_signature"] = signature.clone();
+    }
+    normalized
+}
+
+/// A tool call's arguments as a JSON object, which `functionCall.args` must be
+fn call_args(call: &Value) -> Value {
+    match call.pointer("/function/arguments") {
+        Some(Value::Object(arguments)) => Value::Object(arguments.clone()),
+        Some(Value::String(arguments)) => serde_json::from_str(arguments)
+            .ok()
+            .filter(Value::is_object)
+            .unwrap_or_else(|| json!({})),
+        _ => json!({}),
+    }
+}
+
+/// The system instruction and contents in the Gemini format. Replies are
+/// `model` turns with `functionCall` parts, tool results `functionResponse`
+/// parts of a user turn (named after their call, which Gemini matches by
+/// name), and consecutive turns of the same role are merged.
+pub(crate) fn to_gemini_contents(messages: &[Message]) -> (Option<String>, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut pending: VecDeque<String> = VecDeque::new();
+
+    for message in messages {
+        let (role, parts) = match message {
+            Message::System { content } => {
+                system.push(content.clone());
+                continue;
+            }
+            Message::Assistant { content, tool_calls } => {
+                let mut parts = Vec::new();
+                if !content.trim().is_empty() {
+                    parts.push(json!({"text": content}));
+                }
+                pending.clear();
+                for call in tool_calls {
+                    let name = call.pointer("/function/name").and_then(|v| v.as_str()).unwrap_or_default();
+                    if let Some(id) = call.get("id").and_then(|v| v.as_str()) {
+                        names.insert(id.to_string(), name.to_string());
+                    }
+                    pending.push_back(name.to_string());
+                    let mut part = json!({"functionCall": {"name": name, "args": call_args(call)}});
+                    if let Some(signature) = call.get("thought_signature") {
+                        part["thoughtSignature"] = signature.clone();
+                    }
+                    parts.push(part);
+                }
+                ("model", parts)
+            }
+            Message::Tool { content, id, name, images } => {
+                let by_id = id.as_ref().and_then(|id| names.get(id)).cloned();
+                let name = match name.clone().or(by_id) {
+                    Some(name) => {
+                        if let Some(index) = pending.iter().position(|pending| *pending == name) {
+                            pending.remove(index);
+                        }
+                        name
+                    }
+                    None => pending.pop_front().unwrap_or_default(),
+                };
+                let mut parts = vec![json!({
+                    "functionResponse": {"name": name, "response": {"content": content}}
+                })];
+                parts.extend(images.iter().map(|data| json!({"inlineData": {"mimeType": base64_mime(data), "data": data}})));
+                ("user", parts)
+            }
+            Message::User { content } => {
+                if content.trim().is_empty() {
+                    continue;
+                }
+                ("user", vec![json!({"text": content})])
+            }
+        };
+        if parts.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last, existing)) if *last == role => existing.extend(parts),
+            _ => turns.push((role, parts)),
+        }
+    }
+
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    let contents = turns
+        .into_iter()
+        .map(|(role, parts)| json!({"role": role, "parts": parts}))
+        .collect();
+    (system, contents)
+}
+
+/// Tool definitions as Gemini function declarations. Tools without
+/// arguments leave out `parameters`, which may not be an empty object.
+fn to_gemini_tools(tools: &[ToolDefinition]) -> Value {
+    let declarations: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            let mut declaration = json!({
+                "name": tool.function.name,
+                "description": tool.function.description,
+            });
+            if !tool.function.parameters.properties.is_empty() {
+                declaration["parameters"] = json!(tool.function.parameters);
+            }
+            declaration
+        })
+        .collect();
+    json!([{"functionDeclarations": declarations}])
+}
+
+impl GeminiProvider {
+    pub fn new() -> Self {
+        GeminiProvider {
+            base: DEFAULT_GEMINI_BASE.to_string(),
+            api_key: None,
+            verbose: true,
+            stream_content: true,
+            cost_guard: None,
+            frontend: None,
+            retry: RetryPolicy::default(),
+            timeout: None,
+            cancel: CancelToken::new(),
+            seed: None,
+        }
+    }
+
+    /// Use the endpoint at `base`, e.g. a proxy in front of the API
+    pub fn base(mut self, base: &str) -> Self {
+        self.base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn stream_content(mut self, stream_content: bool) -> Self {
+        self.stream_content = stream_content;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn cost_guard(mut self, cost_guard: CostGuard) -> Self {
+        self.cost_guard = Some(cost_guard);
+        self
+    }
+
+    /// URL of `model`'s generate method; streaming uses server-sent events
+    pub(crate) fn url(&self, model: &str, stream: bool) -> String {
+        if stream {
+            format!("{}/models/{}:streamGenerateContent?alt=sse", self.base, model)
+        } else {
+            format!("{}/models/{}:generateContent", self.base, model)
+        }
+    }
+
+    pub(crate) fn payload(&self, messages: &[Message], tools: Option<&[ToolDefinition]>) -> Value {
+        let (system, contents) = to_gemini_contents(messages);
+        let mut payload = json!({"contents": contents});
+        if let Some(system) = system {
+            payload["systemInstruction"] = json!({"parts": [{"text": system}]});
+        }
+        if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+            payload["tools"] = to_gemini_tools(tools);
+        }
+        if let Some(seed) = self.seed {
+            payload["generationConfig"] = json!({"seed": seed});
+        }
+        payload
+    }
+
+    /// Send the conversation offering `tools`, streaming the reply when `stream` is set
+    pub async fn send(
+        &self,
+        model: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+        stream: bool,
+    ) -> Result<ChatResponse, Error> {
+        let url = self.url(model, stream);
+        let payload = self.payload(messages, tools);
+        let mut request = http_client(self.timeout)?.post(&url).json(&payload);
+        if let Some(key) = &self.api_key {
+            request = request.header("x-goog-api-key", key);
+        }
+        let Some(resp) = self.cancel.or_cancelled(self.retry.send(request)).await else {
+            return Ok(ChatResponse { content: String::new(), tool_calls: None, usage: None });
+        };
+        let resp = resp?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(Error::Message(format!("{} returned {}: {}", url, status, body.trim())));
+        }
+
+        let mut output_tokens: u64 = 0;
+        let prompt_estimate = estimate_tokens(&payload.to_string());
+        let mut printer = StreamPrinter::start(self.verbose, self.stream_content, self.frontend.clone());
+        let mut content = String::new();
+        let mut state = StreamState::default();
+
+        if stream {
+            let mut decoder = SseDecoder::new();
+            let mut stream = resp.bytes_stream();
+            loop {
+                let (events, ended) = match self.cancel.or_cancelled(stream.next()).await {
+                    Some(Some(chunk)) => (decoder.push(&chunk?), false),
+                    Some(None) => (decoder.finish(), true),
+                    // 已取消：保留目前收到的内容
+                    None => break,
+                };
+                for event in events {
+                    let Ok(chunk) = serde_json::from_str::<Value>(&event.data) else {
+                        continue;
+                    };
+                    let (thought, text) = state.apply(&chunk)?;
+
+                    output_tokens += 1;
+                    if let Some(guard) = &self.cost_guard {
+                        printer.check_cost(guard, prompt_estimate, output_tokens)?;
+                    }
+                    if let Some(thought) = thought {
+                        printer.thinking(&thought);
+                    }
+                    if let Some(text) = text {
+                        printer.content(&text);
+                        content.push_str(&text);
+                    }
+                }
+                if ended {
+                    break;
+                }
+            }
+        } else {
+            let body: Value = resp.json().await?;
+            let (thought, text) = state.apply(&body)?;
+            if let Some(thought) = thought {
+                printer.thinking(&thought);
+            }
+            if let Some(text) = text {
+                printer.content(&text);
+                content.push_str(&text);
+            }
+        }
+
+        let reported = state.output_tokens.is_some();
+        let prompt_tokens = state.prompt_tokens.unwrap_or(prompt_estimate);
+        let output_tokens = state.output_tokens.unwrap_or(output_tokens);
+        if let Some(guard) = &self.cost_guard {
+            guard.record(prompt_tokens, output_tokens);
+        }
+        printer.finish();
+
+        Ok(ChatResponse {
+            content,
+            tool_calls: if state.calls.is_empty() { None } else { Some(state.calls) },
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                output_tokens,
+                estimated: !reported,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ReadTool, ToolImpl};
+
+    #[test]
+    fn test_contents_use_function_parts() {
+        let call = Message::assistant_with_tools(
+            "Checking",
+            vec![
+                json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}, "thought_signature": "c2ln"}),
+                json!({"id": "call_2", "function": {"name": "grep", "arguments": "{\"pattern\": \"fn\"}"}}),
+            ],
+        );
+        let (system, contents) = to_gemini_contents(&[
+            Message::system("Be brief"),
+            Message::user("look at a.rs"),
+            call,
+            Message::tool(Some("call_1".to_string()), None, "fn a() {}").with_images(vec!["iVBORw0KGgo=".to_string()]),
+            Message::tool(None, None, "a.rs:1"),
+        ]);
+        assert_eq!(system.as_deref(), Some("Be brief"));
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        let parts = contents[1]["parts"].as_array().unwrap();
+        assert_eq!(parts[1]["functionCall"], json!({"name": "read", "args": {"file_path": "a.rs"}}));
+        assert_eq!(parts[1]["thoughtSignature"], "c2ln");
+        assert_eq!(parts[2]["functionCall"]["args"], json!({"pattern": "fn"}));
+
+        // Results are named after their calls: by id, else in order
+        let results = contents[2]["parts"].as_array().unwrap();
+        assert_eq!(results[0]["functionResponse"]["name"], "read");
+        assert_eq!(results[1]["inlineData"]["mimeType"], "image/png");
+        assert_eq!(results[2]["functionResponse"]["name"], "grep");
+        assert_eq!(results[2]["functionResponse"]["response"]["content"], "a.rs:1");
+
+        let provider = GeminiProvider::new().seed(Some(3));
+        let payload = provider.payload(&[Message::user("hi")], Some(&[ReadTool.definition()]));
+        assert_eq!(payload["tools"][0]["functionDeclarations"][0]["name"], "read");
+        assert_eq!(payload["generationConfig"]["seed"], 3);
+        assert_eq!(
+            provider.url("gemini-2.5-flash", true),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:streamGenerateContent?alt=sse"
+        );
+    }
+
+    #[test]
+    fn test_stream_state_collects_calls() {
+        let mut state = StreamState::default();
+        let chunks = [
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "weighing it", "thought": true}]}}]}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "Reading"}]}}]}),
+            json!({
+                "candidates": [{"content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "read", "args": {"file_path": "a.rs"}}, "thoughtSignature": "c2ln"}
+                ]}}],
+                "usageMetadata": {"promptTokenCount": 200, "candidatesTokenCount": 17}
+            }),
+        ];
+        let fragments: Vec<_> = chunks.iter().map(|chunk| state.apply(chunk).unwrap()).collect();
+        assert_eq!(fragments[0], (Some("weighing it".to_string()), None));
+        assert_eq!(fragments[1], (None, Some("Reading".to_string())));
+        assert_eq!((state.prompt_tokens, state.output_tokens), (Some(200), Some(17)));
+        assert_eq!(
+            state.calls,
+            vec![json!({"id": "", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}, "thought_signature": "c2ln"})]
+        );
+
+        let error = json!({"error": {"code": 429, "message": "Resource exhausted"}});
+        assert!(StreamState::default().apply(&error).unwrap_err().to_string().contains("Resource exhausted"));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::tools::ToolDefinition;
+use serde_json::{json, Value};
+
+/// Instruction sent alongside the schema; the schema alone only constrains
+/// the shape, the model still needs to know what the fields mean
+pub const INSTRUCTION: &str = "Reply with a JSON object. To use tools, list the calls in `tool_calls` \
+     (each with the tool `name` and its `arguments`) and leave `content` empty. To answer, put the \
+     answer in `content` and leave `tool_calls` empty.";
+
+/// JSON schema for Ollama's `format` field that only admits a reply text
+/// or calls to the given tools with their declared arguments
+pub fn tool_call_schema(tools: &[ToolDefinition]) -> Value {
+    let calls: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            let parameters = &tool.function.parameters;
+            json!({
+                "type": "object",
+                "description": tool.function.description,
+                "properties": {
+                    "name": {"type": "string", "enum": [tool.function.name]},
+                    "arguments": {
+                        "type": parameters.r#type,
+                        "properties": parameters.properties,
+                        "required": parameters.required,
+                    }
+                },
+                "required": ["name", "arguments"]
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": {
+            "content": {"type": "string"},
+            "tool_calls": {
+                "type": "array",
+                "items": {"anyOf": calls}
+            }
+        },
+        "required": ["content", "tool_calls"]
+    })
+}
+
+/// Split a constrained reply into its text and tool calls, converting the
+/// calls to the shape Ollama uses for native tool calls. Returns `None` if
+/// the reply isn't the expected JSON.
+pub fn parse_reply(reply: &str) -> Option<(String, Option<Vec<Value>>)> {
+    let reply: Value = serde_json::from_str(reply.trim()).ok()?;
+    let content = reply.get("content")?.as_str().unwrap_or_default().to_string();
+
+    let calls: Vec<Value> = reply
+        .get("tool_calls")
+        .and_then(|calls| calls.as_array())
+        .map(|calls| {
+            calls
+                .iter()
+                .filter_map(|call| {
+                    let name = call.get("name")?.as_str()?;
+                    let arguments = call.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                    Some(json!({"function": {"name": name, "arguments": arguments}}))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some((content, if calls.is_empty() { None } else { Some(calls) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ReadTool, ToolImpl};
+
+    #[test]
+    fn test_tool_call_schema() {
+        let schema = tool_call_schema(&[ReadTool.definition()]);
+        let call = &schema["properties"]["tool_calls"]["items"]["anyOf"][0];
+        assert_eq!(call["properties"]["name"]["enum"][0], "read");
+        assert_eq!(call["properties"]["arguments"]["required"][0], "file_path");
+        assert!(call["properties"]["arguments"]["properties"]["file_path"].is_object());
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        let (content, calls) =
+            parse_reply(r#"{"content": "", "tool_calls": [{"name": "read", "arguments": {"file_path": "a.rs"}}]}"#)
+                .unwrap();
+        assert!(content.is_empty());
+        let calls = calls.unwrap();
+        assert_eq!(calls[0]["function"]["name"], "read");
+        assert_eq!(calls[0]["function"]["arguments"]["file_path"], "a.rs");
+
+        let (content, calls) = parse_reply(r#"{"content": "Done.", "tool_calls": []}"#).unwrap();
+        assert_eq!(content, "Done.");
+        assert!(calls.is_none());
+
+        assert!(parse_reply("not json").is_none());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::agent::Message;
+use crate::error::Error;
+use crate::llm::cancel::CancelToken;
+use crate::llm::cost::{CostGuard, TokenUsage, estimate_tokens};
+use crate::llm::display::StreamPrinter;
+use crate::llm::grammar;
+use crate::llm::provider::{ChatResponse, http_client};
+use crate::llm::react::tool_list;
+use crate::llm::retry::RetryPolicy;
+use crate::llm::sse::SseDecoder;
+use crate::tools::ToolDefinition;
+use crate::ui::Frontend;
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_LLAMACPP_BASE: &str = "http://localhost:8080";
+
+/// Client for the native endpoints of llama.cpp's `llama-server`: the
+/// conversation is turned into a prompt with the model's own chat template
+/// (`/apply-template`) and completed by `/completion`. The server has no
+/// tool calling there, so turns offering tools constrain the completion to
+/// the tool-call JSON schema, which the server compiles to a grammar.
+#[derive(Debug)]
+pub struct LlamaCppProvider {
+    /// Server root, e.g. `http://localhost:8080`
+    pub base: String,
+    pub api_key: Option<String>,
+    pub verbose: bool,
+    /// Print response content as it streams; when false the caller prints it
+    pub stream_content: bool,
+    /// Per-turn spending cap for paid models
+    pub cost_guard: Option<CostGuard>,
+    /// Receives streamed replies instead of the terminal
+    pub frontend: Option<Arc<dyn Frontend>>,
+    /// Resending requests that fail transiently
+    pub retry: RetryPolicy,
+    /// How long to wait for data from the server before failing
+    pub timeout: Option<Duration>,
+    /// Cancelling it ends the reply in flight with what has arrived
+    pub cancel: CancelToken,
+    /// Sampling seed, for reproducible replies
+    pub seed: Option<u64>,
+}
+
+impl Default for LlamaCppProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Messages for the chat template, which only knows text turns: earlier
+/// calls are written as the JSON the grammar makes the model produce, and
+/// their results come back from the user. The tool instruction joins the
+/// system prompt and consecutive turns of one role are merged, since
+/// templates such as Mistral's and Gemma's accept a single leading system
+/// message and alternating roles only.
+pub(crate) fn to_template_messages(messages: &[Message], tools: Option<&[ToolDefinition]>) -> Vec<Value> {
+    let instruction = tools.map(|tools| format!("{}\n\nTools:\n{}", grammar::INSTRUCTION, tool_list(tools)));
+    let mut turns: Vec<(&str, String)> = Vec::with_capacity(messages.len() + 1);
+    if let Some(instruction) = &instruction
+        && !matches!(messages.first(), Some(Message::System { .. }))
+    {
+        turns.push(("system", instruction.clone()));
+    }
+    for (index, message) in messages.iter().enumerate() {
+        let (role, content) = match message {
+            Message::System { content } if index == 0 => match &instruction {
+                Some(instruction) => ("system", format!("{}\n\n{}", content, instruction)),
+                None => ("system", content.clone()),
+            },
+            Message::Assistant { content, tool_calls } if !tool_calls.is_empty() => {
+                let calls: Vec<Value> = tool_calls
+                    .iter()
+                    .map(|call| json!({"name": call["function"]["name"], "arguments": call["function"]["arguments"]}))
+                    .collect();
+                let reply = json!({"content": content, "tool_calls": calls});
+                ("assistant", reply.to_string())
+            }
+            Message::Tool { content, name, .. } => (
+                "user",
+                format!("Result of {}:\n{}", name.as_deref().unwrap_or("the tool call"), content),
+            ),
+            other => (other.role(), other.content().to_string()),
+        };
+        match turns.last_mut() {
+            Some((last, text)) if *last == role => {
+                text.push_str("\n\n");
+                text.push_str(&content);
+            }
+            _ => turns.push((role, content)),
+        }
+    }
+    turns.into_iter().map(|(role, content)| json!({"role": role, "content": content})).collect()
+}
+
+/// Prompt and output tokens the server counted, falling back to the
+/// estimates, and whether it reported them
+fn token_counts(body: &Value, prompt_tokens: u64, output_tokens: u64) -> (u64, u64, bool) {
+    let predicted = body.get("tokens_predicted").and_then(|v| v.as_u64());
+    (
+        body.get("tokens_evaluated").and_then(|v| v.as_u64()).unwrap_or(prompt_tokens),
+        predicted.unwrap_or(output_tokens),
+        predicted.is_some(),
+    )
+}
+
+impl LlamaCppProvider {
+    pub fn new() -> Self {
+        LlamaCppProvider {
+            base: DEFAULT_LLAMACPP_BASE.to_string(),
+            api_key: None,
+            verbose: true,
+            stream_content: true,
+            cost_guard: None,
+            frontend: None,
+            retry: RetryPolicy::default(),
+            timeout: None,
+            cancel: CancelToken::new(),
+            seed: None,
+        }
+    }
+
+    /// Use the server at `base`, e.g. `http://gpu:8080`
+    pub fn base(mut self, base: &str) -> Self {
+        self.base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn stream_content(mut self, stream_content: bool) -> Self {
+        self.stream_content = stream_content;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn cost_guard(mut self, cost_guard: CostGuard) -> Self {
+        self.cost_guard = Some(cost_guard);
+        self
+    }
+
+    /// Body of a `/completion` request for `prompt`
+    pub(crate) fn payload(&self, prompt: &str, tools: Option<&[ToolDefinition]>, stream: bool) -> Value {
+        let mut payload = json!({
+            "prompt": prompt,
+            "stream": stream,
+            "cache_prompt": true,
+        });
+        if let Some(seed) = self.seed {
+            payload["seed"] = json!(seed);
+        }
+        if let Some(tools) = tools {
+            payload["json_schema"] = grammar::tool_call_schema(tools);
+        }
+        payload
+    }
+
+    fn post(&self, client: &reqwest::Client, path: &str, body: &Value) -> reqwest::RequestBuilder {
+        let request = client.post(format!("{}{}", self.base, path)).json(body);
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// Fail with the server's explanation when it refused the request
+    async fn check(&self, path: &str, resp: reqwest::Response) -> Result<reqwest::Response, Error> {
+        if resp.status().is_success() {
+            return Ok(resp);
+        }
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        Err(Error::Message(format!("{}{} returned {}: {}", self.base, path, status, body.trim())))
+    }
+
+    /// Send the conversation offering `tools`, streaming the reply when
+    /// `stream` is set. `model` is ignored: the server runs one model.
+    pub async fn send(
+        &self,
+        _model: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+        stream: bool,
+    ) -> Result<ChatResponse, Error> {
+        let empty = ChatResponse { content: String::new(), tool_calls: None, usage: None };
+        let client = http_client(self.timeout)?;
+        let template = json!({"messages": to_template_messages(messages, tools)});
+        let Some(resp) = self.cancel.or_cancelled(self.retry.send(self.post(&client, "/apply-template", &template))).await else {
+            return Ok(empty);
+        };
+        let body: Value = self.check("/apply-template", resp?).await?.json().await?;
+        let prompt = body.get("prompt").and_then(|v| v.as_str()).unwrap_or_default();
+
+        let payload = self.payload(prompt, tools, stream);
+        let Some(resp) = self.cancel.or_cancelled(self.retry.send(self.post(&client, "/completion", &payload))).await else {
+            return Ok(empty);
+        };
+        let resp = self.check("/completion", resp?).await?;
+
+        let mut prompt_tokens = estimate_tokens(prompt);
+        let mut output_tokens: u64 = 0;
+        let mut reported = false;
+        let mut printer = StreamPrinter::start(self.verbose, self.stream_content, self.frontend.clone());
+        let mut content = String::new();
+        // 工具轮次的回复是 JSON，解析之后再显示
+        let show_fragments = tools.is_none();
+
+        if stream {
+            let mut decoder = SseDecoder::new();
+            let mut stream = resp.bytes_stream();
+            let mut streamed: u64 = 0;
+            'stream: loop {
+                let (events, ended) = match self.cancel.or_cancelled(stream.next()).await {
+                    Some(Some(chunk)) => (decoder.push(&chunk?), false),
+                    Some(None) => (decoder.finish(), true),
+                    // 已取消：保留目前收到的内容
+                    None => break,
+                };
+                for event in events {
+                    let Ok(chunk) = serde_json::from_str::<Value>(&event.data) else {
+                        continue;
+                    };
+                    if let Some(fragment) = chunk.get("content").and_then(|v| v.as_str())
+                        && !fragment.is_empty()
+                    {
+                        streamed += 1;
+                        if let Some(guard) = &self.cost_guard {
+                            printer.check_cost(guard, prompt_tokens, streamed)?;
+                        }
+                        if show_fragments {
+                            printer.content(fragment);
+                        }
+                        content.push_str(fragment);
+                    }
+                    // 最后一个分片带 stop 和 token 统计
+                    if chunk.get("stop").and_then(|v| v.as_bool()) == Some(true) {
+                        (prompt_tokens, output_tokens, reported) = token_counts(&chunk, prompt_tokens, output_tokens);
+                        break 'stream;
+                    }
+                }
+                if ended {
+                    break;
+                }
+            }
+            if !reported {
+                output_tokens = streamed;
+            }
+        } else {
+            let body: Value = resp.json().await?;
+            (prompt_tokens, output_tokens, reported) = token_counts(&body, prompt_tokens, output_tokens);
+            content = body.get("content").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            if show_fragments {
+                printer.content(&content);
+            }
+        }
+
+        let mut tool_calls = None;
+        if tools.is_some() {
+            // 解析失败时把原始回复当作最终回答
+            if let Some((text, calls)) = grammar::parse_reply(&content) {
+                content = text;
+                tool_calls = calls;
+            }
+            printer.content(&content);
+        }
+
+        if let Some(guard) = &self.cost_guard {
+            guard.record(prompt_tokens, output_tokens);
+        }
+        printer.finish();
+
+        Ok(ChatResponse {
+            content,
+            tool_calls,
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                output_tokens,
+                estimated: !reported,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ReadTool, ToolImpl};
+
+    #[test]
+    fn test_template_messages() {
+        let call = json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}});
+        let messages = [
+            Message::user("What is in a.rs?"),
+            Message::assistant_with_tools("", vec![call.clone()]),
+            Message::tool(Some("call_1".to_string()), Some("read".to_string()), "fn a() {}"),
+        ];
+        let tools = [ReadTool.definition()];
+        let converted = to_template_messages(&messages, Some(&tools));
+        assert_eq!(converted.len(), 4);
+        assert!(converted[0]["content"].as_str().unwrap().contains("\n- read: "));
+        // The call reads back through the grammar's reply parser
+        let (_, calls) = grammar::parse_reply(converted[2]["content"].as_str().unwrap()).unwrap();
+        assert_eq!(calls.unwrap()[0]["function"]["arguments"]["file_path"], "a.rs");
+        assert_eq!(converted[3], json!({"role": "user", "content": "Result of read:\nfn a() {}"}));
+        assert_eq!(to_template_messages(&messages[..1], None), vec![json!({"role": "user", "content": "What is in a.rs?"})]);
+
+        // One leading system message, and roles alternate
+        let other = json!({"id": "call_2", "function": {"name": "read", "arguments": {"file_path": "b.rs"}}});
+        let messages = [
+            Message::system("You are Ariste."),
+            Message::user("Compare a.rs and b.rs"),
+            Message::assistant_with_tools("", vec![call, other]),
+            Message::tool(Some("call_1".to_string()), Some("read".to_string()), "fn a() {}"),
+            Message::tool(Some("call_2".to_string()), Some("read".to_string()), "fn b() {}"),
+            Message::user("And now?"),
+        ];
+        let converted = to_template_messages(&messages, Some(&tools));
+        let roles: Vec<&str> = converted.iter().map(|message| message["role"].as_str().unwrap()).collect();
+        assert_eq!(roles, ["system", "user", "assistant", "user"]);
+        assert!(converted[0]["content"].as_str().unwrap().starts_with("You are Ariste.\n\n"));
+        assert!(converted[0]["content"].as_str().unwrap().contains("\n- read: "));
+        assert_eq!(converted[3]["content"], "Result of read:\nfn a() {}\n\nResult of read:\nfn b() {}\n\nAnd now?");
+
+        let provider = LlamaCppProvider::new().base("http://gpu:8080/").seed(Some(7));
+        assert_eq!(provider.base, "http://gpu:8080");
+        let payload = provider.payload("<|user|>hi", Some(&tools), true);
+        assert_eq!(payload["seed"], 7);
+        assert!(payload["json_schema"]["properties"]["tool_calls"].is_object());
+        assert!(provider.payload("hi", None, false).get("json_schema").is_none());
+    }
+}
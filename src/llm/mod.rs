@@ -1,3 +1,26 @@
+mod cancel;
+mod claude;
+mod cost;
+mod display;
+mod embedding;
+mod gemini;
+mod grammar;
+mod llamacpp;
 mod ollama;
+mod openai;
+mod provider;
+mod react;
+mod retry;
+mod sse;
 
+pub use cancel::CancelToken;
+pub use claude::ClaudeProvider;
+pub use cost::{CostGuard, TokenUsage, estimate_tokens};
+pub use embedding::{EmbedFuture, EmbeddingProvider, OllamaEmbedder, OpenAiEmbedder, cosine_similarity, create_embedder};
+pub use gemini::GeminiProvider;
+pub use llamacpp::LlamaCppProvider;
 pub use ollama::Ollama;
+pub use openai::OpenAiProvider;
+pub use provider::{ChatFuture, ChatResponse, LlmProvider, create_provider};
+pub use react::ReactProvider;
+pub use retry::RetryPolicy;
@@ -1,22 +1,19 @@
 #![allow(unused)]
 use crate::agent::Message;
 use crate::error::Error;
+use crate::llm::cancel::CancelToken;
+use crate::llm::cost::{CostGuard, TokenUsage, estimate_tokens};
+use crate::llm::display::StreamPrinter;
+use crate::llm::grammar;
+use crate::llm::provider::{ChatResponse, http_client};
+use crate::llm::retry::RetryPolicy;
 use crate::tools::ToolDefinition;
-use crate::ui::UI;
+use crate::ui::Frontend;
 use crate::utils::load_image_as_base64;
-use colored::Colorize;
 use futures_util::StreamExt;
 use serde_json::{Value, json};
-use std::io::{Write, stdout};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::time::{Duration, sleep};
-
-#[derive(Debug, Clone)]
-pub struct OllamaResponse {
-    pub content: String,
-    pub tool_calls: Option<Vec<Value>>,
-}
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct Ollama {
@@ -24,7 +21,29 @@ pub struct Ollama {
     pub stream: bool,
     pub verbose: bool,
     pub think: bool,
+    /// Print response content as it streams; when false the caller prints it
+    pub stream_content: bool,
     pub tools: Option<Vec<ToolDefinition>>,
+    /// Per-turn spending cap for paid models
+    pub cost_guard: Option<CostGuard>,
+    /// Receives streamed replies instead of the terminal
+    pub frontend: Option<Arc<dyn Frontend>>,
+    /// Constrain replies to the tool-call JSON schema via Ollama's `format`
+    pub constrain_tool_calls: bool,
+    /// Resending requests that fail transiently
+    pub retry: RetryPolicy,
+    /// How long to wait for data from the server before failing
+    pub timeout: Option<Duration>,
+    /// Cancelling it ends the reply in flight with what has arrived
+    pub cancel: CancelToken,
+    /// Sampling seed, for reproducible replies
+    pub seed: Option<u64>,
+}
+
+impl Default for Ollama {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Ollama {
@@ -34,7 +53,15 @@ impl Ollama {
             stream: true,
             verbose: true,
             think: true,
+            stream_content: true,
             tools: None,
+            cost_guard: None,
+            frontend: None,
+            constrain_tool_calls: false,
+            retry: RetryPolicy::default(),
+            timeout: None,
+            cancel: CancelToken::new(),
+            seed: None,
         }
     }
 
@@ -58,12 +85,42 @@ impl Ollama {
         self
     }
 
+    pub fn stream_content(mut self, stream_content: bool) -> Self {
+        self.stream_content = stream_content;
+        self
+    }
+
     pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
         self.tools = Some(tools);
         self
     }
 
-    pub async fn execute(&self, model: &str, prompt: &str) -> Result<OllamaResponse, Error> {
+    pub fn constrain_tool_calls(mut self, constrain_tool_calls: bool) -> Self {
+        self.constrain_tool_calls = constrain_tool_calls;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn cost_guard(mut self, cost_guard: CostGuard) -> Self {
+        self.cost_guard = Some(cost_guard);
+        self
+    }
+
+    pub async fn execute(&self, model: &str, prompt: &str) -> Result<ChatResponse, Error> {
         let mut payload = json!({
             "model": model,
             "messages": [{
@@ -86,22 +143,77 @@ impl Ollama {
         &self,
         model: &str,
         messages: &[Message],
-    ) -> Result<OllamaResponse, Error> {
+    ) -> Result<ChatResponse, Error> {
+        self.send(model, messages, self.tools.as_deref(), self.stream).await
+    }
+
+    /// Send the conversation offering `tools`, streaming the reply when `stream` is set
+    pub async fn send(
+        &self,
+        model: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+        stream: bool,
+    ) -> Result<ChatResponse, Error> {
+        if self.constrain_tool_calls
+            && let Some(tools) = tools
+        {
+            return self.execute_constrained(model, messages, tools, stream).await;
+        }
+
         let mut payload = json!({
             "model": model,
             "messages": messages,
-            "stream": self.stream,
+            "stream": stream,
             "think": self.think
         });
+        self.add_options(&mut payload);
 
         // Add tools if available
-        if let Some(tools) = &self.tools {
+        if let Some(tools) = tools {
             payload["tools"] = serde_json::to_value(tools).unwrap();
         }
 
         self.execute_impl(&payload).await
     }
 
+    /// Generation options of a chat request
+    fn add_options(&self, payload: &mut Value) {
+        if let Some(seed) = self.seed {
+            payload["options"] = json!({"seed": seed});
+        }
+    }
+
+    /// Grammar-constrained request: the reply must be JSON matching the
+    /// tool-call schema, which is then unpacked into content and tool calls
+    async fn execute_constrained(
+        &self,
+        model: &str,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        stream: bool,
+    ) -> Result<ChatResponse, Error> {
+        let mut constrained = Vec::with_capacity(messages.len() + 1);
+        constrained.push(Message::system(grammar::INSTRUCTION));
+        constrained.extend(messages.iter().cloned());
+
+        let mut payload = json!({
+            "model": model,
+            "messages": constrained,
+            "stream": stream,
+            "think": self.think,
+            "format": grammar::tool_call_schema(tools)
+        });
+        self.add_options(&mut payload);
+        let response = self.execute_impl(&payload).await?;
+
+        // 解析失败时把原始回复当作最终回答
+        Ok(match grammar::parse_reply(&response.content) {
+            Some((content, tool_calls)) => ChatResponse { content, tool_calls, ..response },
+            None => response,
+        })
+    }
+
     pub async fn execute_with_image<I, E>(
         &self,
         model: &str,
@@ -135,137 +247,119 @@ impl Ollama {
         Ok(response.content)
     }
 
-    async fn execute_impl(&self, payload: &serde_json::Value) -> Result<OllamaResponse, Error> {
-        let client = reqwest::Client::new();
+    async fn execute_impl(&self, payload: &serde_json::Value) -> Result<ChatResponse, Error> {
+        let client = http_client(self.timeout)?;
 
         let url = self
             .url
             .as_deref()
             .unwrap_or("http://localhost:11434/api/chat");
-        let resp = client.post(url).json(payload).send().await?;
+        let Some(resp) = self.cancel.or_cancelled(self.retry.send(client.post(url).json(payload))).await else {
+            return Ok(ChatResponse { content: String::new(), tool_calls: None, usage: None });
+        };
+        let resp = resp?;
 
-        let mut status = 0;
         let mut response = String::new();
-        let mut thinking_buffer = String::new();
         let mut tool_calls_buffer: Vec<Value> = Vec::new();
-        let mut stream = resp.bytes_stream();
-
-        // 启动 spinner
-        let spinner_running = Arc::new(AtomicBool::new(true));
-        let spinner_running_clone = spinner_running.clone();
-
-        // 在异步任务中运行 spinner
-        tokio::spawn(async move {
-            let mut ui = UI::new();
-            while spinner_running_clone.load(Ordering::Relaxed) {
-                ui.thinking_start();
-                sleep(Duration::from_millis(150)).await;
-            }
-        });
 
-        while let Some(chunk) = stream.next().await {
-            if let Ok(bytes) = chunk
-                && let Ok(text) = std::str::from_utf8(&bytes)
-                && let Ok(resp) = serde_json::from_str::<serde_json::Value>(text)
-            {
-                // Check for tool_calls
-                if let Some(message) = resp.get("message") {
-                    if let Some(tool_calls) = message.get("tool_calls") {
-                        if let Some(calls) = tool_calls.as_array() {
-                            for call in calls {
-                                tool_calls_buffer.push(call.clone());
-                            }
+        // 费用估算：提示词按字符数估算，输出按流式片段计数，结束时以服务端统计为准
+        let mut prompt_tokens = estimate_tokens(&payload.to_string());
+        let mut output_tokens: u64 = 0;
+        let mut reported = false;
+
+        // 启动 spinner（静默模式下不显示）
+        let mut printer = StreamPrinter::start(self.verbose, self.stream_content, self.frontend.clone());
+
+        if payload.get("stream").and_then(|v| v.as_bool()) == Some(false) {
+            // 非流式：整个回复是一个 JSON 对象，可能分多个网络分片到达
+            let body: Value = resp.json().await?;
+            reported = body.get("eval_count").is_some();
+            prompt_tokens = body.get("prompt_eval_count").and_then(|v| v.as_u64()).unwrap_or(prompt_tokens);
+            output_tokens = body.get("eval_count").and_then(|v| v.as_u64()).unwrap_or(output_tokens);
+            let message = body.get("message").cloned().unwrap_or_default();
+            if let Some(thinking) = message.get("thinking").and_then(|v| v.as_str()) {
+                printer.thinking(thinking);
+            }
+            if let Some(content) = message.get("content").and_then(|v| v.as_str()) {
+                printer.content(content);
+                response.push_str(content);
+            }
+            if let Some(calls) = message.get("tool_calls").and_then(|v| v.as_array()) {
+                tool_calls_buffer.extend(calls.iter().cloned());
+            }
+        } else {
+            let mut stream = resp.bytes_stream();
+            while let Some(Some(chunk)) = self.cancel.or_cancelled(stream.next()).await {
+                if let Ok(bytes) = chunk
+                    && let Ok(text) = std::str::from_utf8(&bytes)
+                    && let Ok(resp) = serde_json::from_str::<serde_json::Value>(text)
+                {
+                    // Check for tool_calls
+                    if let Some(message) = resp.get("message")
+                        && let Some(tool_calls) = message.get("tool_calls")
+                        && let Some(calls) = tool_calls.as_array()
+                    {
+                        for call in calls {
+                            tool_calls_buffer.push(call.clone());
                         }
                     }
-                }
 
-                if let Some(done) = resp.get("done")
-                    && let Some(done) = done.as_bool()
-                    && done
-                {
-                    break;
-                }
-
-                if let Some(message) = resp.get("message") {
-                    if let Some(fragment) = message.get("thinking")
-                        && let Some(fragment) = fragment.as_str()
+                    if let Some(done) = resp.get("done")
+                        && let Some(done) = done.as_bool()
+                        && done
                     {
-                        if self.verbose {
-                            if status == 0 {
-                                // 停止 spinner 并清除行
-                                spinner_running.store(false, Ordering::Relaxed);
-                                sleep(Duration::from_millis(50)).await;
-                                UI::clear_line();
-
-                                // 显示思考块开始
-                                UI::thinking_block_start();
-                                status = 1;
-                            }
-
-                            // 累积思考内容
-                            thinking_buffer.push_str(fragment);
-
-                            // 处理buffer中的所有完整行
-                            while let Some(newline_pos) = thinking_buffer.find('\n') {
-                                let line = &thinking_buffer[..newline_pos];
-                                UI::thinking_block_content(line);
-                                // 移除已处理的行（包括换行符）
-                                thinking_buffer = thinking_buffer[newline_pos + 1..].to_string();
-                            }
+                        if let Some(count) = resp.get("prompt_eval_count").and_then(|v| v.as_u64()) {
+                            prompt_tokens = count;
+                        }
+                        if let Some(count) = resp.get("eval_count").and_then(|v| v.as_u64()) {
+                            output_tokens = count;
+                            reported = true;
                         }
+                        break;
+                    }
 
-                        continue;
+                    output_tokens += 1;
+                    if let Some(guard) = &self.cost_guard {
+                        printer.check_cost(guard, prompt_tokens, output_tokens)?;
                     }
 
-                    if let Some(fragment) = message.get("content")
-                        && let Some(fragment) = fragment.as_str()
-                    {
-                        if self.verbose {
-                            if status == 0 {
-                                // 还没有看到 thinking，直接停止 spinner
-                                spinner_running.store(false, Ordering::Relaxed);
-                                sleep(Duration::from_millis(50)).await;
-                                UI::clear_line();
-                                UI::response_start();
-                                status = 2;
-                            } else if status == 1 {
-                                // 完成思考块
-                                if !thinking_buffer.is_empty() {
-                                    UI::thinking_block_content(&thinking_buffer);
-                                    thinking_buffer.clear();
-                                }
-                                UI::thinking_block_end();
-                                status = 2;
-                            }
-
-                            print!("{}", fragment);
-                            drop(stdout().flush());
+                    if let Some(message) = resp.get("message") {
+                        if let Some(fragment) = message.get("thinking")
+                            && let Some(fragment) = fragment.as_str()
+                        {
+                            printer.thinking(fragment);
+                            continue;
                         }
 
-                        response.push_str(fragment);
-                        continue;
+                        if let Some(fragment) = message.get("content")
+                            && let Some(fragment) = fragment.as_str()
+                        {
+                            printer.content(fragment);
+                            response.push_str(fragment);
+                            continue;
+                        }
                     }
                 }
             }
         }
 
-        // 停止 spinner
-        spinner_running.store(false, Ordering::Relaxed);
-
-        if self.verbose {
-            if !response.is_empty() {
-                println!();
-            }
-            drop(stdout().flush());
+        if let Some(guard) = &self.cost_guard {
+            guard.record(prompt_tokens, output_tokens);
         }
+        printer.finish();
 
-        Ok(OllamaResponse {
+        Ok(ChatResponse {
             content: response,
             tool_calls: if tool_calls_buffer.is_empty() {
                 None
             } else {
                 Some(tool_calls_buffer)
             },
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                output_tokens,
+                estimated: !reported,
+            }),
         })
     }
 }
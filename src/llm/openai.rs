@@ -0,0 +1,485 @@
+use crate::agent::Message;
+use crate::error::Error;
+use crate::llm::cancel::CancelToken;
+use crate::llm::cost::{CostGuard, TokenUsage, estimate_tokens};
+use crate::llm::display::StreamPrinter;
+use crate::llm::provider::{ChatResponse, http_client};
+use crate::llm::retry::RetryPolicy;
+use crate::llm::sse::SseDecoder;
+use crate::tools::ToolDefinition;
+use crate::ui::Frontend;
+use crate::utils::base64_mime;
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_OPENAI_BASE: &str = "https://api.openai.com/v1";
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// Where an Azure OpenAI deployment is: the deployment is part of the path,
+/// the API version a query parameter, and the key goes in an `api-key` header
+#[derive(Debug, Clone, PartialEq)]
+pub struct AzureDeployment {
+    /// Resource endpoint, e.g. `https://contoso.openai.azure.com`
+    pub endpoint: String,
+    /// Deployment name; `None` uses the model name
+    pub deployment: Option<String>,
+    pub api_version: String,
+}
+
+/// Client for OpenAI-compatible chat completion endpoints (OpenAI,
+/// OpenRouter, vLLM, and other servers exposing `/chat/completions`), and
+/// Azure OpenAI deployments
+#[derive(Debug)]
+pub struct OpenAiProvider {
+    /// Full `/chat/completions` URL
+    pub url: String,
+    /// Azure deployment to call instead of `url`
+    pub azure: Option<AzureDeployment>,
+    pub api_key: Option<String>,
+    pub stream: bool,
+    pub verbose: bool,
+    /// Print response content as it streams; when false the caller prints it
+    pub stream_content: bool,
+    pub tools: Option<Vec<ToolDefinition>>,
+    /// Per-turn spending cap for paid models
+    pub cost_guard: Option<CostGuard>,
+    /// Receives streamed replies instead of the terminal
+    pub frontend: Option<Arc<dyn Frontend>>,
+    /// Resending requests that fail transiently
+    pub retry: RetryPolicy,
+    /// How long to wait for data from the server before failing
+    pub timeout: Option<Duration>,
+    /// Cancelling it ends the reply in flight with what has arrived
+    pub cancel: CancelToken,
+    /// Sampling seed, for reproducible replies
+    pub seed: Option<u64>,
+}
+
+impl Default for OpenAiProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tool call being assembled from streamed deltas
+#[derive(Debug, Default)]
+pub(crate) struct PartialCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+impl PartialCall {
+    pub fn finish(self) -> Value {
+        normalize_call(&self.id, &self.name, &self.arguments)
+    }
+}
+
+/// What the streamed chunks have carried so far besides the text
+#[derive(Debug, Default)]
+struct StreamState {
+    calls: BTreeMap<u64, PartialCall>,
+    prompt_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+impl StreamState {
+    /// Take in one chunk, returning its reasoning and content fragments, or
+    /// `None` for chunks without a delta (e.g. the final usage chunk)
+    fn apply(&mut self, chunk: &Value) -> Option<(Option<String>, Option<String>)> {
+        if let Some(usage) = chunk.get("usage").filter(|usage| !usage.is_null()) {
+            self.prompt_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64());
+            self.output_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64());
+        }
+        let delta = chunk.pointer("/choices/0/delta")?;
+
+        for call in delta.get("tool_calls").and_then(|v| v.as_array()).into_iter().flatten() {
+            let index = call.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+            let partial = self.calls.entry(index).or_default();
+            if let Some(id) = call.get("id").and_then(|v| v.as_str()) {
+                partial.id = id.to_string();
+            }
+            if let Some(name) = call.pointer("/function/name").and_then(|v| v.as_str()) {
+                partial.name.push_str(name);
+            }
+            if let Some(arguments) = call.pointer("/function/arguments").and_then(|v| v.as_str()) {
+                partial.arguments.push_str(arguments);
+            }
+        }
+
+        // DeepSeek/OpenRouter 等把推理过程放在单独的字段里
+        let reasoning = delta
+            .get("reasoning_content")
+            .or_else(|| delta.get("reasoning"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let content = delta.get("content").and_then(|v| v.as_str()).map(str::to_string);
+        Some((reasoning, content))
+    }
+}
+
+/// A tool call in the agent's shape: arguments as a JSON object (the raw
+/// string if the model produced invalid JSON)
+pub(crate) fn normalize_call(id: &str, name: &str, arguments: &str) -> Value {
+    let arguments = if arguments.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.to_string()))
+    };
+    json!({"id": id, "function": {"name": name, "arguments": arguments}})
+}
+
+/// Messages in the chat completions format: tool call arguments are JSON
+/// strings and every call has a type. Tool results can't carry images, so
+/// those follow the results of a reply as a user message.
+pub(crate) fn to_openai_messages(messages: &[Message]) -> Vec<Value> {
+    let mut converted = Vec::with_capacity(messages.len());
+    let mut images: Vec<Value> = Vec::new();
+    for message in messages {
+        // 工具结果必须紧跟在调用之后，图片等这一组结果结束再发送
+        if !message.is_tool() && !images.is_empty() {
+            converted.push(json!({"role": "user", "content": std::mem::take(&mut images)}));
+        }
+        for data in message.images() {
+            if images.is_empty() {
+                images.push(json!({"type": "text", "text": "Images read by the tool calls above"}));
+            }
+            let url = format!("data:{};base64,{}", base64_mime(data), data);
+            images.push(json!({"type": "image_url", "image_url": {"url": url}}));
+        }
+        converted.push({
+            let mut value = json!({"role": message.role(), "content": message.content()});
+            let calls = message.tool_calls();
+            if !calls.is_empty() {
+                let calls: Vec<Value> = calls
+                    .iter()
+                    .map(|call| {
+                        let function = call.get("function").cloned().unwrap_or_default();
+                        let arguments = match function.get("arguments") {
+                            Some(Value::String(arguments)) => arguments.clone(),
+                            Some(arguments) => arguments.to_string(),
+                            None => "{}".to_string(),
+                        };
+                        json!({
+                            "id": call.get("id").and_then(|v| v.as_str()).unwrap_or_default(),
+                            "type": "function",
+                            "function": {
+                                "name": function.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
+                                "arguments": arguments,
+                            }
+                        })
+                    })
+                    .collect();
+                value["tool_calls"] = Value::Array(calls);
+                if message.content().is_empty() {
+                    value["content"] = Value::Null;
+                }
+            }
+            if let Some(id) = message.tool_call_id() {
+                value["tool_call_id"] = json!(id);
+            }
+            value
+        });
+    }
+    if !images.is_empty() {
+        converted.push(json!({"role": "user", "content": images}));
+    }
+    converted
+}
+
+impl OpenAiProvider {
+    pub fn new() -> Self {
+        OpenAiProvider {
+            url: format!("{}/chat/completions", DEFAULT_OPENAI_BASE),
+            azure: None,
+            api_key: None,
+            stream: true,
+            verbose: true,
+            stream_content: true,
+            tools: None,
+            cost_guard: None,
+            frontend: None,
+            retry: RetryPolicy::default(),
+            timeout: None,
+            cancel: CancelToken::new(),
+            seed: None,
+        }
+    }
+
+    /// Use the endpoint at `base`, e.g. `https://openrouter.ai/api/v1`
+    pub fn base(mut self, base: &str) -> Self {
+        self.url = format!("{}/chat/completions", base.trim_end_matches('/'));
+        self
+    }
+
+    /// Call an Azure OpenAI deployment instead of the endpoint at `base`
+    pub fn azure(mut self, azure: AzureDeployment) -> Self {
+        self.azure = Some(azure);
+        self
+    }
+
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Chat completions URL for `model`
+    pub(crate) fn request_url(&self, model: &str) -> String {
+        match &self.azure {
+            Some(azure) => format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                azure.endpoint.trim_end_matches('/'),
+                azure.deployment.as_deref().unwrap_or(model),
+                azure.api_version
+            ),
+            None => self.url.clone(),
+        }
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn stream_content(mut self, stream_content: bool) -> Self {
+        self.stream_content = stream_content;
+        self
+    }
+
+    pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn cost_guard(mut self, cost_guard: CostGuard) -> Self {
+        self.cost_guard = Some(cost_guard);
+        self
+    }
+
+    pub(crate) fn payload(&self, model: &str, messages: &[Message], tools: Option<&[ToolDefinition]>, stream: bool) -> Value {
+        let mut payload = json!({
+            "model": model,
+            "messages": to_openai_messages(messages),
+            "stream": stream,
+        });
+        if let Some(seed) = self.seed {
+            payload["seed"] = json!(seed);
+        }
+        if stream {
+            // 最后一个分片附带 token 用量
+            payload["stream_options"] = json!({"include_usage": true});
+        }
+        if let Some(tools) = tools {
+            payload["tools"] = serde_json::to_value(tools).unwrap();
+        }
+        payload
+    }
+
+    pub async fn execute_with_messages(&self, model: &str, messages: &[Message]) -> Result<ChatResponse, Error> {
+        self.send(model, messages, self.tools.as_deref(), self.stream).await
+    }
+
+    /// Send the conversation offering `tools`, streaming the reply when `stream` is set
+    pub async fn send(
+        &self,
+        model: &str,
+        messages: &[Message],
+        tools: Option<&[ToolDefinition]>,
+        stream: bool,
+    ) -> Result<ChatResponse, Error> {
+        let payload = self.payload(model, messages, tools, stream);
+        let url = self.request_url(model);
+        let mut request = http_client(self.timeout)?.post(&url).json(&payload);
+        if let Some(key) = &self.api_key {
+            request = match self.azure {
+                Some(_) => request.header("api-key", key),
+                None => request.bearer_auth(key),
+            };
+        }
+        let Some(resp) = self.cancel.or_cancelled(self.retry.send(request)).await else {
+            return Ok(ChatResponse { content: String::new(), tool_calls: None, usage: None });
+        };
+        let resp = resp?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(Error::Message(format!("{} returned {}: {}", url, status, body.trim())));
+        }
+
+        let mut prompt_tokens = estimate_tokens(&payload.to_string());
+        let mut output_tokens: u64 = 0;
+        let mut reported = false;
+        let mut printer = StreamPrinter::start(self.verbose, self.stream_content, self.frontend.clone());
+        let mut content = String::new();
+        let mut calls: BTreeMap<u64, PartialCall> = BTreeMap::new();
+
+        if stream {
+            let mut decoder = SseDecoder::new();
+            let mut stream = resp.bytes_stream();
+            let mut state = StreamState::default();
+            'stream: loop {
+                let (events, ended) = match self.cancel.or_cancelled(stream.next()).await {
+                    Some(Some(chunk)) => (decoder.push(&chunk?), false),
+                    Some(None) => (decoder.finish(), true),
+                    // 已取消：保留目前收到的内容
+                    None => break,
+                };
+                for event in events {
+                    if event.data == "[DONE]" {
+                        break 'stream;
+                    }
+                    let Ok(chunk) = serde_json::from_str::<Value>(&event.data) else {
+                        continue;
+                    };
+                    let delta = state.apply(&chunk);
+                    if delta.is_none() {
+                        continue;
+                    }
+
+                    output_tokens += 1;
+                    if let Some(guard) = &self.cost_guard {
+                        printer.check_cost(guard, prompt_tokens, output_tokens)?;
+                    }
+                    if let Some((reasoning, text)) = delta {
+                        if let Some(reasoning) = reasoning {
+                            printer.thinking(&reasoning);
+                        }
+                        if let Some(text) = text {
+                            printer.content(&text);
+                            content.push_str(&text);
+                        }
+                    }
+                }
+                if ended {
+                    break;
+                }
+            }
+            reported = state.output_tokens.is_some();
+            prompt_tokens = state.prompt_tokens.unwrap_or(prompt_tokens);
+            output_tokens = state.output_tokens.unwrap_or(output_tokens);
+            calls = state.calls;
+        } else {
+            let body: Value = resp.json().await?;
+            if let Some(usage) = body.get("usage") {
+                reported = true;
+                prompt_tokens = usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(prompt_tokens);
+                output_tokens = usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(output_tokens);
+            }
+            let message = body.pointer("/choices/0/message").cloned().unwrap_or_default();
+            if let Some(text) = message.get("content").and_then(|v| v.as_str()) {
+                printer.content(text);
+                content.push_str(text);
+            }
+            for (index, call) in message.get("tool_calls").and_then(|v| v.as_array()).into_iter().flatten().enumerate() {
+                calls.insert(
+                    index as u64,
+                    PartialCall {
+                        id: call.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                        name: call.pointer("/function/name").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                        arguments: call.pointer("/function/arguments").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                    },
+                );
+            }
+        }
+
+        if let Some(guard) = &self.cost_guard {
+            guard.record(prompt_tokens, output_tokens);
+        }
+        printer.finish();
+
+        let tool_calls: Vec<Value> = calls.into_values().map(PartialCall::finish).collect();
+        Ok(ChatResponse {
+            content,
+            tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                output_tokens,
+                estimated: !reported,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_messages_use_openai_shape() {
+        let call = Message::assistant_with_tools(
+            "",
+            vec![json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}})],
+        );
+        let result = Message::tool(Some("call_1".to_string()), Some("read".to_string()), "fn main() {}");
+
+        let converted = to_openai_messages(&[Message::user("hi"), call, result]);
+        assert_eq!(converted[0], json!({"role": "user", "content": "hi"}));
+        assert!(converted[1]["content"].is_null());
+        assert_eq!(converted[1]["tool_calls"][0]["type"], "function");
+        assert_eq!(converted[1]["tool_calls"][0]["function"]["arguments"], "{\"file_path\":\"a.rs\"}");
+        assert_eq!(converted[2]["tool_call_id"], "call_1");
+
+        // Images of tool results follow as one user message
+        let png = "iVBORw0KGgo=";
+        let image = Message::tool(Some("call_1".to_string()), Some("read".to_string()), "attached").with_images(vec![png.to_string()]);
+        let converted = to_openai_messages(&[image, Message::assistant("a logo")]);
+        assert_eq!(converted[0]["content"], "attached");
+        assert_eq!(converted[1]["role"], "user");
+        assert_eq!(converted[1]["content"][1]["image_url"]["url"], format!("data:image/png;base64,{}", png));
+        assert_eq!(converted[2]["content"], "a logo");
+    }
+
+    #[test]
+    fn test_stream_state_assembles_tool_calls() {
+        let mut state = StreamState::default();
+        let chunks = [
+            json!({"choices": [{"delta": {"role": "assistant", "content": "Let me look"}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "id": "call_9", "function": {"name": "grep", "arguments": "{\"pat"}}]}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": "tern\": \"fn\"}"}}]}}]}),
+            json!({"choices": [], "usage": {"prompt_tokens": 120, "completion_tokens": 14}}),
+        ];
+        let deltas: Vec<_> = chunks.iter().map(|chunk| state.apply(chunk)).collect();
+        assert_eq!(deltas[0], Some((None, Some("Let me look".to_string()))));
+        assert_eq!(deltas[3], None);
+        assert_eq!((state.prompt_tokens, state.output_tokens), (Some(120), Some(14)));
+
+        let calls: Vec<Value> = state.calls.into_values().map(PartialCall::finish).collect();
+        assert_eq!(calls, vec![json!({"id": "call_9", "function": {"name": "grep", "arguments": {"pattern": "fn"}}})]);
+    }
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(OpenAiProvider::new().url, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(
+            OpenAiProvider::new().base("https://openrouter.ai/api/v1/").url,
+            "https://openrouter.ai/api/v1/chat/completions"
+        );
+        let azure = OpenAiProvider::new().azure(AzureDeployment {
+            endpoint: "https://contoso.openai.azure.com/".to_string(),
+            deployment: None,
+            api_version: DEFAULT_AZURE_API_VERSION.to_string(),
+        });
+        assert_eq!(
+            azure.request_url("gpt-4o"),
+            "https://contoso.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21"
+        );
+    }
+}
@@ -0,0 +1,617 @@
+use crate::agent::Message;
+use crate::agent::context;
+use crate::config::AgentConfig;
+use crate::error::Error;
+use crate::llm::cancel::CancelToken;
+use crate::llm::claude::{ClaudeProvider, DEFAULT_ANTHROPIC_BASE};
+use crate::llm::cost::{CostGuard, TokenUsage};
+use crate::llm::gemini::{DEFAULT_GEMINI_BASE, GeminiProvider};
+use crate::llm::llamacpp::{DEFAULT_LLAMACPP_BASE, LlamaCppProvider};
+use crate::llm::ollama::Ollama;
+use crate::llm::openai::{AzureDeployment, DEFAULT_AZURE_API_VERSION, DEFAULT_OPENAI_BASE, OpenAiProvider};
+use crate::llm::react::ReactProvider;
+use crate::llm::retry::RetryPolicy;
+use crate::tools::ToolDefinition;
+use crate::ui::Frontend;
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434/api/chat";
+const OPENROUTER_BASE: &str = "https://openrouter.ai/api/v1";
+/// LM Studio's REST API, used through the OpenAI-compatible client: its
+/// chat endpoint takes and streams the same JSON. The extra `stats` and
+/// `model_info` it returns are not read.
+const LMSTUDIO_BASE: &str = "http://localhost:1234/api/v0";
+
+/// Names accepted by the `provider` setting
+pub const PROVIDER_NAMES: &[&str] = &["ollama", "openai", "openrouter", "azure", "anthropic", "gemini", "llamacpp", "lmstudio"];
+
+/// A model reply. Tool calls are normalized to
+/// `{"id": .., "function": {"name": .., "arguments": {..}}}` whatever the backend.
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    pub content: String,
+    pub tool_calls: Option<Vec<Value>>,
+    /// Tokens the request used; `None` when the backend can't tell
+    pub usage: Option<TokenUsage>,
+}
+
+/// Reply being produced by a provider
+pub type ChatFuture<'a> = BoxFuture<'a, Result<ChatResponse, Error>>;
+
+impl ChatResponse {
+    /// Give calls the backend sent without an id (Ollama usually does) a
+    /// synthetic one. `position` is the length of the conversation it
+    /// replies to, which keeps ids of identical calls in different turns apart.
+    pub fn with_call_ids(mut self, position: usize) -> Self {
+        if let Some(calls) = &mut self.tool_calls {
+            context::fill_ids(position, calls);
+        }
+        self
+    }
+}
+
+/// HTTP client giving up when the server sends nothing for `timeout`. The
+/// limit applies between reads, so a long streamed reply is not cut off.
+pub(crate) fn http_client(timeout: Option<Duration>) -> Result<reqwest::Client, Error> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(timeout) = timeout {
+        builder = builder.connect_timeout(timeout).read_timeout(timeout);
+    }
+    Ok(builder.build()?)
+}
+
+/// `reply` with synthetic ids filled in for a reply to `messages`
+fn with_call_ids<'a>(
+    reply: impl Future<Output = Result<ChatResponse, Error>> + Send + 'a,
+    messages: &'a [Message],
+) -> ChatFuture<'a> {
+    Box::pin(async move { Ok(reply.await?.with_call_ids(messages.len())) })
+}
+
+/// A chat backend the agent can talk to. Object safe, so the agent can
+/// hold any backend (or a mock in tests) as a `Box<dyn LlmProvider>`.
+pub trait LlmProvider: Send + Sync + std::fmt::Debug {
+    /// Backend name as written in the `provider` setting
+    fn name(&self) -> &'static str;
+
+    /// Whether content is printed as it streams; when false the caller prints it
+    fn stream_content(&self) -> bool;
+
+    /// Per-turn spending cap, when configured
+    fn cost_guard(&self) -> Option<&CostGuard>;
+
+    /// Send the conversation to `model`, offering `tools`, and wait for the
+    /// complete reply
+    fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a>;
+
+    /// Like `chat`, but the reply is streamed and shown as it is generated
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+        tools: Option<&'a [ToolDefinition]>,
+    ) -> ChatFuture<'a>;
+
+    /// A client on the same endpoint that prints nothing, for side
+    /// requests such as verification and consensus
+    fn quiet(&self) -> Box<dyn LlmProvider>;
+
+    /// Send streamed replies to `frontend` instead of drawing them on the
+    /// terminal; `None` goes back to the terminal
+    fn set_frontend(&mut self, frontend: Option<Arc<dyn Frontend>>);
+
+    /// Stop streaming when `cancel` is cancelled, returning the partial reply
+    fn set_cancel(&mut self, cancel: CancelToken);
+
+    /// Sample with `seed` so the same request gets the same reply; ignored
+    /// by backends that can't seed sampling
+    fn set_seed(&mut self, _seed: Option<u64>) {}
+}
+
+impl LlmProvider for Ollama {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn stream_content(&self) -> bool {
+        self.stream_content
+    }
+
+    fn cost_guard(&self) -> Option<&CostGuard> {
+        self.cost_guard.as_ref()
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, false), messages)
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+        tools: Option<&'a [ToolDefinition]>,
+    ) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, true), messages)
+    }
+
+    fn set_frontend(&mut self, frontend: Option<Arc<dyn Frontend>>) {
+        self.frontend = frontend;
+    }
+
+    fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    fn quiet(&self) -> Box<dyn LlmProvider> {
+        Box::new(
+            Ollama::new()
+                .url(self.url.clone().unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string()))
+                .verbose(false)
+                .think(false)
+                .retry(self.retry)
+                .timeout(self.timeout)
+                .seed(self.seed),
+        )
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn stream_content(&self) -> bool {
+        self.stream_content
+    }
+
+    fn cost_guard(&self) -> Option<&CostGuard> {
+        self.cost_guard.as_ref()
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, false), messages)
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+        tools: Option<&'a [ToolDefinition]>,
+    ) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, true), messages)
+    }
+
+    fn set_frontend(&mut self, frontend: Option<Arc<dyn Frontend>>) {
+        self.frontend = frontend;
+    }
+
+    fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    fn quiet(&self) -> Box<dyn LlmProvider> {
+        Box::new(OpenAiProvider {
+            url: self.url.clone(),
+            azure: self.azure.clone(),
+            api_key: self.api_key.clone(),
+            retry: self.retry,
+            timeout: self.timeout,
+            seed: self.seed,
+            ..OpenAiProvider::new().verbose(false)
+        })
+    }
+}
+
+impl LlmProvider for ClaudeProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn stream_content(&self) -> bool {
+        self.stream_content
+    }
+
+    fn cost_guard(&self) -> Option<&CostGuard> {
+        self.cost_guard.as_ref()
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, false), messages)
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+        tools: Option<&'a [ToolDefinition]>,
+    ) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, true), messages)
+    }
+
+    fn set_frontend(&mut self, frontend: Option<Arc<dyn Frontend>>) {
+        self.frontend = frontend;
+    }
+
+    fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    fn quiet(&self) -> Box<dyn LlmProvider> {
+        Box::new(ClaudeProvider {
+            url: self.url.clone(),
+            api_key: self.api_key.clone(),
+            max_tokens: self.max_tokens,
+            retry: self.retry,
+            timeout: self.timeout,
+            ..ClaudeProvider::new().verbose(false)
+        })
+    }
+}
+
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn stream_content(&self) -> bool {
+        self.stream_content
+    }
+
+    fn cost_guard(&self) -> Option<&CostGuard> {
+        self.cost_guard.as_ref()
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, false), messages)
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+        tools: Option<&'a [ToolDefinition]>,
+    ) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, true), messages)
+    }
+
+    fn set_frontend(&mut self, frontend: Option<Arc<dyn Frontend>>) {
+        self.frontend = frontend;
+    }
+
+    fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    fn quiet(&self) -> Box<dyn LlmProvider> {
+        Box::new(GeminiProvider {
+            base: self.base.clone(),
+            api_key: self.api_key.clone(),
+            retry: self.retry,
+            timeout: self.timeout,
+            seed: self.seed,
+            ..GeminiProvider::new().verbose(false)
+        })
+    }
+}
+
+impl LlmProvider for LlamaCppProvider {
+    fn name(&self) -> &'static str {
+        "llamacpp"
+    }
+
+    fn stream_content(&self) -> bool {
+        self.stream_content
+    }
+
+    fn cost_guard(&self) -> Option<&CostGuard> {
+        self.cost_guard.as_ref()
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, false), messages)
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+        tools: Option<&'a [ToolDefinition]>,
+    ) -> ChatFuture<'a> {
+        with_call_ids(self.send(model, messages, tools, true), messages)
+    }
+
+    fn set_frontend(&mut self, frontend: Option<Arc<dyn Frontend>>) {
+        self.frontend = frontend;
+    }
+
+    fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    fn quiet(&self) -> Box<dyn LlmProvider> {
+        Box::new(LlamaCppProvider {
+            base: self.base.clone(),
+            api_key: self.api_key.clone(),
+            retry: self.retry,
+            timeout: self.timeout,
+            seed: self.seed,
+            ..LlamaCppProvider::new().verbose(false)
+        })
+    }
+}
+
+/// Whether the agent's client should print content as it streams. Answer
+/// verification, echo suppression, constrained (JSON) replies and calls
+/// written as text all need the complete reply before it is shown.
+fn streams_content(config: &AgentConfig) -> bool {
+    let verify = config
+        .verification
+        .as_ref()
+        .is_some_and(|verification| verification.is_enabled());
+    !(config.suppress_echo.unwrap_or(false)
+        || verify
+        || config.constrained_tool_calls.unwrap_or(false)
+        || config.react_tool_calls.unwrap_or(false))
+}
+
+/// Spending cap for paid models: the configured per-turn cap, priced from
+/// the pricing table entry of the configured model until a turn runs on
+/// another (`CostGuard::start_turn`)
+fn turn_cost_guard(config: &AgentConfig) -> Option<CostGuard> {
+    let model = config.model.as_deref().unwrap_or("qwen3");
+    let cap = config.max_turn_cost?;
+    let pricing = config.pricing.as_ref()?;
+    Some(CostGuard::new(pricing.get(model).copied(), cap))
+}
+
+fn retry_policy(config: &AgentConfig) -> RetryPolicy {
+    RetryPolicy::from_config(config.retry.as_ref())
+}
+
+fn request_timeout(config: &AgentConfig) -> Option<Duration> {
+    config.request_timeout_secs.filter(|&secs| secs > 0).map(Duration::from_secs)
+}
+
+fn api_key(config: &AgentConfig, variable: &str) -> Option<String> {
+    config.api_key.clone().or_else(|| std::env::var(variable).ok())
+}
+
+fn ollama(config: &AgentConfig) -> Ollama {
+    let url = match &config.base {
+        Some(base) => format!("{}/api/chat", base.trim_end_matches('/')),
+        None => DEFAULT_OLLAMA_URL.to_string(),
+    };
+    let mut ollama = Ollama::new()
+        .url(url)
+        .think(false)
+        .stream_content(streams_content(config))
+        .constrain_tool_calls(config.constrained_tool_calls.unwrap_or(false))
+        .retry(retry_policy(config))
+        .timeout(request_timeout(config))
+        .seed(config.seed);
+    ollama.cost_guard = turn_cost_guard(config);
+    ollama
+}
+
+fn openai_compatible(config: &AgentConfig, default_base: &str, key_variable: &str) -> OpenAiProvider {
+    let mut openai = OpenAiProvider::new()
+        .base(config.base.as_deref().unwrap_or(default_base))
+        .api_key(api_key(config, key_variable))
+        .stream_content(streams_content(config))
+        .retry(retry_policy(config))
+        .timeout(request_timeout(config))
+        .seed(config.seed);
+    openai.cost_guard = turn_cost_guard(config);
+    openai
+}
+
+/// An Azure OpenAI client: the deployment (the model name unless set) of
+/// the resource at `base` or `AZURE_OPENAI_ENDPOINT`
+fn azure(config: &AgentConfig) -> Result<OpenAiProvider, Error> {
+    let endpoint = config
+        .base
+        .clone()
+        .or_else(|| std::env::var("AZURE_OPENAI_ENDPOINT").ok())
+        .ok_or_else(|| {
+            Error::Message("The azure provider needs the resource endpoint in `base` or AZURE_OPENAI_ENDPOINT".to_string())
+        })?;
+    let settings = config.azure.clone().unwrap_or_default();
+    Ok(openai_compatible(config, &endpoint, "AZURE_OPENAI_API_KEY").azure(AzureDeployment {
+        endpoint,
+        deployment: settings.deployment,
+        api_version: settings.api_version.unwrap_or_else(|| DEFAULT_AZURE_API_VERSION.to_string()),
+    }))
+}
+
+fn gemini(config: &AgentConfig) -> GeminiProvider {
+    let mut gemini = GeminiProvider::new()
+        .base(config.base.as_deref().unwrap_or(DEFAULT_GEMINI_BASE))
+        .api_key(api_key(config, "GEMINI_API_KEY"))
+        .stream_content(streams_content(config))
+        .retry(retry_policy(config))
+        .timeout(request_timeout(config))
+        .seed(config.seed);
+    gemini.cost_guard = turn_cost_guard(config);
+    gemini
+}
+
+fn llamacpp(config: &AgentConfig) -> LlamaCppProvider {
+    let mut llamacpp = LlamaCppProvider::new()
+        .base(config.base.as_deref().unwrap_or(DEFAULT_LLAMACPP_BASE))
+        .api_key(api_key(config, "LLAMA_API_KEY"))
+        .stream_content(streams_content(config))
+        .retry(retry_policy(config))
+        .timeout(request_timeout(config))
+        .seed(config.seed);
+    llamacpp.cost_guard = turn_cost_guard(config);
+    llamacpp
+}
+
+fn claude(config: &AgentConfig) -> ClaudeProvider {
+    let mut claude = ClaudeProvider::new()
+        .base(config.base.as_deref().unwrap_or(DEFAULT_ANTHROPIC_BASE))
+        .api_key(api_key(config, "ANTHROPIC_API_KEY"))
+        .stream_content(streams_content(config))
+        .retry(retry_policy(config))
+        .timeout(request_timeout(config));
+    claude.cost_guard = turn_cost_guard(config);
+    claude
+}
+
+/// Client for the configured provider and endpoint. `base` defaults to the
+/// provider's public endpoint; hosted providers take `api_key` or the
+/// provider's usual environment variable. With `react_tool_calls` the
+/// client calls tools through the prompt instead (see `ReactProvider`).
+pub fn create_provider(config: &AgentConfig) -> Result<Box<dyn LlmProvider>, Error> {
+    let provider: Box<dyn LlmProvider> = match config.provider.as_deref().unwrap_or("ollama") {
+        "ollama" => Box::new(ollama(config)),
+        "openai" => Box::new(openai_compatible(config, DEFAULT_OPENAI_BASE, "OPENAI_API_KEY")),
+        "openrouter" => Box::new(openai_compatible(config, OPENROUTER_BASE, "OPENROUTER_API_KEY")),
+        "azure" => Box::new(azure(config)?),
+        "anthropic" => Box::new(claude(config)),
+        "gemini" => Box::new(gemini(config)),
+        "llamacpp" => Box::new(llamacpp(config)),
+        "lmstudio" => Box::new(openai_compatible(config, LMSTUDIO_BASE, "LM_API_TOKEN")),
+        other => {
+            return Err(Error::Message(format!(
+                "Unknown provider '{}' in .ariste/settings.json; expected one of: {}",
+                other,
+                PROVIDER_NAMES.join(", ")
+            )));
+        }
+    };
+    if config.react_tool_calls.unwrap_or(false) {
+        return Ok(Box::new(ReactProvider::new(provider)));
+    }
+    Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VerificationConfig;
+
+    fn config(provider: &str, base: Option<&str>) -> AgentConfig {
+        AgentConfig {
+            provider: Some(provider.to_string()),
+            base: base.map(str::to_string),
+            api_key: Some("sk-test".to_string()),
+            ..AgentConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_missing_call_ids_are_filled() {
+        let reply = ChatResponse {
+            content: String::new(),
+            tool_calls: Some(vec![
+                serde_json::json!({"function": {"name": "read", "arguments": {}}}),
+                serde_json::json!({"id": "", "function": {"name": "read", "arguments": {}}}),
+                serde_json::json!({"id": "call_9", "function": {"name": "ls", "arguments": {}}}),
+            ]),
+            usage: None,
+        };
+        let calls = reply.clone().with_call_ids(4).tool_calls.unwrap();
+        let ids: Vec<&str> = calls.iter().map(|call| call["id"].as_str().unwrap()).collect();
+        assert!(ids[0].starts_with("call_") && ids[1].starts_with("call_"));
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(ids[2], "call_9");
+        assert_eq!(reply.clone().with_call_ids(4).tool_calls.unwrap(), calls);
+        assert_ne!(reply.with_call_ids(6).tool_calls.unwrap()[0], calls[0]);
+    }
+
+    #[test]
+    fn test_provider_from_config() {
+        let local = ollama(&config("ollama", Some("http://gpu:11434/")));
+        assert_eq!(local.url.as_deref(), Some("http://gpu:11434/api/chat"));
+        assert_eq!(local.retry, RetryPolicy::default());
+
+        let openrouter = openai_compatible(&config("openrouter", None), OPENROUTER_BASE, "OPENROUTER_API_KEY");
+        assert_eq!(openrouter.url, "https://openrouter.ai/api/v1/chat/completions");
+        assert_eq!(openrouter.api_key.as_deref(), Some("sk-test"));
+
+        assert_eq!(claude(&config("anthropic", None)).url, "https://api.anthropic.com/v1/messages");
+
+        let patient = AgentConfig {
+            retry: Some(crate::config::RetryConfig {
+                max_attempts: Some(5),
+                ..Default::default()
+            }),
+            ..config("anthropic", None)
+        };
+        assert_eq!(claude(&patient).retry.max_attempts, 5);
+        assert_eq!(local.timeout, None);
+        let impatient = AgentConfig {
+            request_timeout_secs: Some(30),
+            ..config("ollama", None)
+        };
+        assert_eq!(ollama(&impatient).timeout, Some(Duration::from_secs(30)));
+        let seeded = AgentConfig {
+            seed: Some(42),
+            ..config("openai", None)
+        };
+        assert_eq!(ollama(&seeded).seed, Some(42));
+        let openai = openai_compatible(&seeded, DEFAULT_OPENAI_BASE, "OPENAI_API_KEY");
+        assert_eq!(openai.payload("gpt-4o", &[], None, false)["seed"], 42);
+        assert!(local.seed.is_none());
+
+        let provider = create_provider(&config("openai", Some("http://localhost:8000/v1"))).unwrap();
+        assert_eq!(provider.name(), "openai");
+        assert_eq!(provider.quiet().name(), "openai");
+        assert_eq!(create_provider(&config("anthropic", None)).unwrap().name(), "anthropic");
+        let deployed = AgentConfig {
+            azure: Some(crate::config::AzureConfig {
+                deployment: Some("prod-gpt4o".to_string()),
+                api_version: None,
+            }),
+            ..config("azure", Some("https://contoso.openai.azure.com"))
+        };
+        assert_eq!(
+            azure(&deployed).unwrap().request_url("gpt-4o"),
+            "https://contoso.openai.azure.com/openai/deployments/prod-gpt4o/chat/completions?api-version=2024-10-21"
+        );
+        let google = gemini(&config("gemini", None));
+        assert_eq!((google.base.as_str(), google.api_key.as_deref()), (DEFAULT_GEMINI_BASE, Some("sk-test")));
+        assert_eq!(create_provider(&config("gemini", None)).unwrap().quiet().name(), "gemini");
+        assert_eq!(llamacpp(&config("llamacpp", None)).base, "http://localhost:8080");
+        assert_eq!(create_provider(&config("llamacpp", Some("http://gpu:8080"))).unwrap().name(), "llamacpp");
+        let lmstudio = openai_compatible(&config("lmstudio", None), LMSTUDIO_BASE, "LM_API_TOKEN");
+        assert_eq!(lmstudio.url, "http://localhost:1234/api/v0/chat/completions");
+
+        let error = create_provider(&config("bard", None)).unwrap_err();
+        assert!(error.to_string().contains("expected one of: ollama, openai, openrouter, azure, anthropic, gemini, llamacpp, lmstudio"));
+    }
+
+    #[test]
+    fn test_streams_content() {
+        assert!(streams_content(&config("openai", None)));
+        let verified = AgentConfig {
+            verification: Some(VerificationConfig {
+                enabled: Some(true),
+                model: None,
+            }),
+            ..config("openai", None)
+        };
+        assert!(!streams_content(&verified));
+        assert!(!create_provider(&verified).unwrap().stream_content());
+    }
+}
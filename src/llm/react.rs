@@ -0,0 +1,298 @@
+use crate::agent::Message;
+use crate::error::Error;
+use crate::llm::cancel::CancelToken;
+use crate::llm::cost::CostGuard;
+use crate::llm::provider::{ChatFuture, ChatResponse, LlmProvider};
+use crate::tools::ToolDefinition;
+use crate::ui::Frontend;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+/// How a call is written in the reply, shown to the model with the tools
+const CALL_FORMAT: &str = "To use a tool, write `Action:` followed by a JSON object with the tool `name` \
+     and its `arguments` in a ```json block, for example:\n\nAction:\n```json\n{\"name\": \"read\", \
+     \"arguments\": {\"file_path\": \"src/main.rs\"}}\n```\n\nThen stop: the result comes back as an \
+     `Observation`. Several actions can follow one another. To answer, reply without an action.";
+
+/// The tools described in prose, for a model without native tool calling
+pub fn tools_prompt(tools: &[ToolDefinition]) -> String {
+    format!("{}\n\nTools:\n{}", CALL_FORMAT, tool_list(tools))
+}
+
+/// One entry per tool: its name, what it does and its arguments
+pub(crate) fn tool_list(tools: &[ToolDefinition]) -> String {
+    let list: Vec<String> = tools
+        .iter()
+        .map(|tool| {
+            let parameters = &tool.function.parameters;
+            format!(
+                "- {}: {}\n  Arguments: {} (required: {})",
+                tool.function.name,
+                tool.function.description,
+                json!(parameters.properties),
+                if parameters.required.is_empty() { "none".to_string() } else { parameters.required.join(", ") }
+            )
+        })
+        .collect();
+    list.join("\n")
+}
+
+/// The conversation as a text-only model sees it: tools described in the
+/// system prompt, earlier calls written as actions and their results as
+/// observations from the user
+pub fn rewrite_messages(messages: &[Message], tools: &[ToolDefinition]) -> Vec<Message> {
+    let prompt = tools_prompt(tools);
+    let mut rewritten = Vec::with_capacity(messages.len() + 1);
+    if !matches!(messages.first(), Some(Message::System { .. })) {
+        rewritten.push(Message::system(prompt.clone()));
+    }
+    for (index, message) in messages.iter().enumerate() {
+        rewritten.push(match message {
+            Message::System { content } if index == 0 => Message::system(format!("{}\n\n{}", content, prompt)),
+            Message::Assistant { content, tool_calls } if !tool_calls.is_empty() => {
+                let actions: Vec<String> = tool_calls
+                    .iter()
+                    .map(|call| {
+                        let function = &call["function"];
+                        let action = json!({"name": function["name"], "arguments": function["arguments"]});
+                        format!("Action:\n```json\n{}\n```", action)
+                    })
+                    .collect();
+                let text = [content.trim(), &actions.join("\n\n")].join("\n\n");
+                Message::assistant(text.trim_start())
+            }
+            Message::Tool { content, name, .. } => Message::user(format!(
+                "Observation from {}:\n{}",
+                name.as_deref().unwrap_or("the tool"),
+                content
+            )),
+            other => other.clone(),
+        });
+    }
+    rewritten
+}
+
+/// A call in the shape of native tool calls, from `{"name", "arguments"}`
+fn to_call(value: &Value) -> Option<Value> {
+    let name = value.get("name").or_else(|| value.get("tool"))?.as_str()?;
+    let arguments = value
+        .get("arguments")
+        .or_else(|| value.get("parameters"))
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    arguments.is_object().then(|| json!({"function": {"name": name, "arguments": arguments}}))
+}
+
+/// The first JSON value of `text` and the bytes it took
+fn leading_json(text: &str) -> Option<(Value, usize)> {
+    let mut values = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+    let value = values.next()?.ok()?;
+    Some((value, values.byte_offset()))
+}
+
+/// `text` after the opening line of a ``` fence, when it starts with one
+fn skip_fence(text: &str) -> &str {
+    match text.strip_prefix("```") {
+        Some(fenced) => fenced.split_once('\n').map(|(_, body)| body).unwrap_or(""),
+        None => text,
+    }
+}
+
+/// A call written at the start of `text` (just after `Action:`): a JSON
+/// object, in a fence or not, or the classic `name` line followed by
+/// `Action Input: {..}`. Returns the call and the bytes it took.
+fn action_at(text: &str) -> Option<(Value, usize)> {
+    let trimmed = text.trim_start();
+    let body = skip_fence(trimmed);
+    if body.trim_start().starts_with('{') {
+        let start = text.len() - body.trim_start().len();
+        let (value, used) = leading_json(&text[start..])?;
+        let mut end = start + used;
+        if let Some(close) = text[end..].trim_start().strip_prefix("```") {
+            end = text.len() - close.len();
+        }
+        return Some((to_call(&value)?, end));
+    }
+
+    let (name, rest) = trimmed.split_once('\n').unwrap_or((trimmed, ""));
+    let name = name.trim().trim_matches('`');
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+        return None;
+    }
+    let input = rest.trim_start().strip_prefix("Action Input:")?;
+    let input_start = text.len() - input.len();
+    let (arguments, used) = match action_at(input) {
+        // 参数本身写成了 {"name", "arguments"} 的形式也接受
+        Some((call, used)) => (call["function"]["arguments"].clone(), used),
+        None => {
+            let body = skip_fence(input.trim_start());
+            let start = input.len() - body.trim_start().len();
+            let (arguments, used) = leading_json(&input[start..])?;
+            (arguments, start + used)
+        }
+    };
+    to_call(&json!({"name": name, "arguments": arguments})).map(|call| (call, input_start + used))
+}
+
+/// Split a reply into its text and the calls it writes: `Action:` blocks
+/// or fenced JSON blocks holding a `name` and `arguments`. The text is what
+/// comes before the first call; `None` when there are no calls.
+pub fn parse_actions(reply: &str) -> (String, Option<Vec<Value>>) {
+    let mut calls = Vec::new();
+    let mut first = None;
+    let mut at = 0;
+    while at < reply.len() {
+        let rest = &reply[at..];
+        let marker = [rest.find("Action:"), rest.find("```")].into_iter().flatten().min();
+        let Some(offset) = marker else {
+            break;
+        };
+        let start = at + offset;
+        let after_marker = if reply[start..].starts_with("Action:") { start + "Action:".len() } else { start };
+        match action_at(&reply[after_marker..]) {
+            Some((call, used)) => {
+                calls.push(call);
+                first.get_or_insert(start);
+                at = after_marker + used;
+            }
+            None => at = start + 3,
+        }
+    }
+    match first {
+        Some(first) => (reply[..first].trim_end().to_string(), Some(calls)),
+        None => (reply.to_string(), None),
+    }
+}
+
+/// Tool calling for models without it (`react_tool_calls`): the tools are
+/// described in the prompt, calls are read back from the reply text, and
+/// results go to the model as observations. Less reliable than native
+/// calls, since nothing holds the model to the format.
+#[derive(Debug)]
+pub struct ReactProvider {
+    inner: Box<dyn LlmProvider>,
+}
+
+impl ReactProvider {
+    pub fn new(inner: Box<dyn LlmProvider>) -> Self {
+        Self { inner }
+    }
+
+    async fn send(
+        &self,
+        model: &str,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        stream: bool,
+    ) -> Result<ChatResponse, Error> {
+        let rewritten = rewrite_messages(messages, tools);
+        let mut reply = if stream {
+            self.inner.chat_stream(model, &rewritten, None).await?
+        } else {
+            self.inner.chat(model, &rewritten, None).await?
+        };
+        let (content, tool_calls) = parse_actions(&reply.content);
+        reply.content = content;
+        reply.tool_calls = tool_calls;
+        Ok(reply.with_call_ids(messages.len()))
+    }
+}
+
+impl LlmProvider for ReactProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn stream_content(&self) -> bool {
+        self.inner.stream_content()
+    }
+
+    fn cost_guard(&self) -> Option<&CostGuard> {
+        self.inner.cost_guard()
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: &'a [Message], tools: Option<&'a [ToolDefinition]>) -> ChatFuture<'a> {
+        match tools {
+            Some(tools) => Box::pin(self.send(model, messages, tools, false)),
+            None => self.inner.chat(model, messages, None),
+        }
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+        tools: Option<&'a [ToolDefinition]>,
+    ) -> ChatFuture<'a> {
+        match tools {
+            Some(tools) => Box::pin(self.send(model, messages, tools, true)),
+            None => self.inner.chat_stream(model, messages, None),
+        }
+    }
+
+    fn quiet(&self) -> Box<dyn LlmProvider> {
+        Box::new(ReactProvider::new(self.inner.quiet()))
+    }
+
+    fn set_frontend(&mut self, frontend: Option<Arc<dyn Frontend>>) {
+        self.inner.set_frontend(frontend);
+    }
+
+    fn set_cancel(&mut self, cancel: CancelToken) {
+        self.inner.set_cancel(cancel);
+    }
+
+    fn set_seed(&mut self, seed: Option<u64>) {
+        self.inner.set_seed(seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ReadTool, ToolImpl};
+
+    #[test]
+    fn test_parse_actions() {
+        let reply = "I'll look at the entry point.\n\nAction:\n```json\n{\"name\": \"read\", \"arguments\": {\"file_path\": \"src/main.rs\"}}\n```\nAction: {\"name\": \"ls\", \"arguments\": {}}";
+        let (content, calls) = parse_actions(reply);
+        assert_eq!(content, "I'll look at the entry point.");
+        let calls = calls.unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0]["function"]["arguments"]["file_path"], "src/main.rs");
+        assert_eq!(calls[1]["function"]["name"], "ls");
+
+        // The classic form, and a bare fenced block
+        let (content, calls) = parse_actions("Thought: list it\nAction: glob\nAction Input: {\"pattern\": \"*.rs\"}");
+        assert_eq!(content, "Thought: list it");
+        assert_eq!(calls.unwrap()[0]["function"]["arguments"]["pattern"], "*.rs");
+        let (_, calls) = parse_actions("```json\n{\"tool\": \"ls\", \"parameters\": {\"path\": \"src\"}}\n```");
+        assert_eq!(calls.unwrap()[0]["function"]["arguments"]["path"], "src");
+
+        // Code that isn't a call stays in the answer
+        let answer = "Use this:\n```rust\nfn main() {}\n```\nor `{\"a\": 1}`.";
+        assert_eq!(parse_actions(answer), (answer.to_string(), None));
+    }
+
+    #[test]
+    fn test_rewrite_messages() {
+        let call = json!({"id": "call_1", "function": {"name": "read", "arguments": {"file_path": "a.rs"}}});
+        let messages = vec![
+            Message::system("You are Ariste."),
+            Message::user("What is in a.rs?"),
+            Message::assistant_with_tools("", vec![call]),
+            Message::tool(Some("call_1".to_string()), Some("read".to_string()), "fn a() {}"),
+        ];
+        let rewritten = rewrite_messages(&messages, &[ReadTool.definition()]);
+        assert_eq!(rewritten.len(), 4);
+        assert!(rewritten[0].content().starts_with("You are Ariste.\n\nTo use a tool"));
+        assert!(rewritten[0].content().contains("- read: "));
+        assert!(rewritten.iter().all(|message| message.tool_calls().is_empty() && !message.is_tool()));
+        // The action written back parses to the same call
+        let (_, calls) = parse_actions(rewritten[2].content());
+        assert_eq!(calls.unwrap()[0]["function"]["arguments"]["file_path"], "a.rs");
+        assert_eq!(rewritten[3], Message::user("Observation from read:\nfn a() {}"));
+
+        assert_eq!(rewrite_messages(&messages[1..2], &[]).len(), 2);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::config::RetryConfig;
+use crate::error::Error;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_BACKOFF_MS: u64 = 500;
+const DEFAULT_MAX_BACKOFF_MS: u64 = 8_000;
+
+/// How a model request is sent again after a rate limit (429), a server
+/// error (5xx) or a dropped connection. Waits double after each attempt;
+/// a `Retry-After` header from the server is honoured up to `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per request, the first included
+    pub max_attempts: u32,
+    /// Wait before the first retry
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: Duration::from_millis(DEFAULT_BACKOFF_MS),
+            max_backoff: Duration::from_millis(DEFAULT_MAX_BACKOFF_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The `retry` settings, with defaults for what they leave out
+    pub fn from_config(config: Option<&RetryConfig>) -> Self {
+        let config = config.cloned().unwrap_or_default();
+        Self {
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1),
+            backoff: Duration::from_millis(config.backoff_ms.unwrap_or(DEFAULT_BACKOFF_MS)),
+            max_backoff: Duration::from_millis(config.max_backoff_ms.unwrap_or(DEFAULT_MAX_BACKOFF_MS)),
+        }
+    }
+
+    /// Wait after failed attempt number `attempt` (counting from 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Send `request`, retrying transient failures. Once the attempts run
+    /// out the last response is returned as is, so callers still report the
+    /// server's error.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let mut request = request;
+        let mut attempt = 1;
+        loop {
+            // 流式请求体无法复制，这类请求只发送一次
+            let next = if attempt < self.max_attempts { request.try_clone() } else { None };
+            let outcome = request.send().await;
+            let wait = match (&outcome, next) {
+                (Ok(resp), Some(next)) if retryable_status(resp.status()) => {
+                    request = next;
+                    retry_after(resp).unwrap_or_else(|| self.delay(attempt)).min(self.max_backoff)
+                }
+                (Err(e), Some(next)) if transient(e) => {
+                    request = next;
+                    self.delay(attempt)
+                }
+                (Err(e), _) if attempt > 1 => {
+                    return Err(Error::Message(format!("{} (after {} attempts)", e, attempt)));
+                }
+                _ => return Ok(outcome?),
+            };
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Rate limits and server errors are worth another attempt
+pub fn retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Timeouts and connections reset by the server. A refused connection is
+/// not retried: nothing is listening, and waiting won't change that.
+fn transient(error: &reqwest::Error) -> bool {
+    if error.is_timeout() {
+        return true;
+    }
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionAborted | std::io::ErrorKind::BrokenPipe
+            );
+        }
+        source = cause.source();
+    }
+    false
+}
+
+/// Wait the server asked for, when given in seconds
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let seconds = resp.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A server answering with `statuses` in turn (the last one repeats,
+    /// 0 resets the connection); returns its URL and the number of requests
+    /// it received
+    async fn serve(statuses: &'static [u16]) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/chat", listener.local_addr().unwrap());
+        let count = Arc::new(AtomicUsize::new(0));
+        let served = count.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let index = served.fetch_add(1, Ordering::SeqCst);
+                let status = statuses[index.min(statuses.len() - 1)];
+                let mut buffer = [0u8; 4096];
+                let _ = socket.read(&mut buffer).await;
+                if status == 0 {
+                    // A zero linger time sends a reset on drop without blocking
+                    #[allow(deprecated)]
+                    let _ = socket.set_linger(Some(Duration::ZERO));
+                    continue;
+                }
+                let reply = format!(
+                    "HTTP/1.1 {} Status\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    status
+                );
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+        (url, count)
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_max() {
+        let policy = RetryPolicy::from_config(Some(&RetryConfig {
+            max_attempts: Some(0),
+            backoff_ms: Some(100),
+            max_backoff_ms: Some(300),
+        }));
+        assert_eq!(policy.max_attempts, 1);
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(300));
+        assert_eq!(policy.delay(40), Duration::from_millis(300));
+        assert_eq!(RetryPolicy::from_config(None), RetryPolicy::default());
+
+        assert!(retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(!retryable_status(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_transient_status() {
+        let (url, count) = serve(&[503, 429, 200]).await;
+        let resp = policy(3).send(reqwest::Client::new().post(&url).json(&"{}")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+
+        // Out of attempts: the last error response is returned
+        let (url, count) = serve(&[500]).await;
+        let resp = policy(2).send(reqwest::Client::new().post(&url)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        // Client errors are not retried
+        let (url, count) = serve(&[401]).await;
+        let resp = policy(3).send(reqwest::Client::new().post(&url)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_reset_connection() {
+        let (url, count) = serve(&[0, 200]).await;
+        let resp = policy(3).send(reqwest::Client::new().get(&url)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        let (url, _) = serve(&[0]).await;
+        let error = policy(2).send(reqwest::Client::new().get(&url)).await.unwrap_err();
+        assert!(error.to_string().contains("after 2 attempts"));
+
+        // Nothing listens on the port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let error = policy(3).send(reqwest::Client::new().get(&url)).await.unwrap_err();
+        assert!(!error.to_string().contains("attempts"));
+    }
+}
@@ -0,0 +1,108 @@
+/// One server-sent event
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SseEvent {
+    /// The `event:` field, when the server names its events
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Splits a `text/event-stream` body into events. Network chunks can end
+/// anywhere, even inside a UTF-8 character, so partial lines are kept as
+/// bytes until the rest arrives.
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of the body and return the events it completes
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            // 只解码完整的行，多字节字符不会被切开
+            let bytes: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&bytes);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // 空行结束一个事件
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take(),
+                        data: self.data.join("\n"),
+                    });
+                    self.data.clear();
+                }
+                self.event = None;
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            } else if let Some(value) = line.strip_prefix("event:") {
+                self.event = Some(value.trim().to_string());
+            }
+            // 注释（以 ':' 开头）以及 id/retry 字段不需要处理
+        }
+        events
+    }
+
+    /// Events left when the body ends without a final blank line
+    pub fn finish(&mut self) -> Vec<SseEvent> {
+        let mut events = self.push(b"\n");
+        if !self.data.is_empty() {
+            events.extend(self.push(b"\n"));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: {\"a\":").is_empty());
+        let events = decoder.push(b"1}\n\n: keep-alive\n\nevent: message_stop\ndata: {}\r\n\r\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: None,
+                    data: "{\"a\":1}".to_string()
+                },
+                SseEvent {
+                    event: Some("message_stop".to_string()),
+                    data: "{}".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiline_data_and_unterminated_event() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: one\ndata: two\n").is_empty());
+        assert_eq!(decoder.finish()[0].data, "one\ntwo");
+
+        let mut decoder = SseDecoder::new();
+        decoder.push(b"data: [DONE]");
+        assert_eq!(decoder.finish()[0].data, "[DONE]");
+    }
+
+    #[test]
+    fn test_character_split_across_chunks() {
+        let bytes = "data: 你好😀\n\n".as_bytes();
+        let mut decoder = SseDecoder::new();
+        // 在“你”的中间切开
+        assert!(decoder.push(&bytes[..7]).is_empty());
+        let events = decoder.push(&bytes[7..]);
+        assert_eq!(events[0].data, "你好😀");
+    }
+}
@@ -1,28 +1,399 @@
-mod agent;
 mod cli;
-mod config;
-mod error;
-mod llm;
-mod tools;
-mod ui;
-mod utils;
-
-use agent::Agent;
-use clap::Parser;
-use cli::AgentHinter;
-use error::Error;
+
+use ariste::ui::glyphs;
+use ariste::workflow::findings::{self, Finding, OutputFormat, Severity};
+use ariste::agent::{SubAgentStatus, session, tasks};
+use ariste::{Agent, agent};
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use cli::{AgentHinter, HeadlessRun, SpinnerStyle, TerminalFrontend, TypeAhead, UI, WelcomeScreen};
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
 use rustyline::Editor;
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use ui::UI;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
-struct Args {}
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Continue a saved conversation (ids are the file names in .ariste/sessions)
+    #[arg(long, value_name = "ID")]
+    resume: Option<String>,
+    /// Run one turn with this prompt, print the answer and exit; progress goes to stderr
+    #[arg(short, long, value_name = "PROMPT")]
+    prompt: Option<String>,
+    /// With --prompt, print a JSON report of the turn (messages, tool calls, timing, tokens) instead
+    #[arg(long, value_parser = ["text", "json"], default_value = "text", requires = "prompt")]
+    output_format: String,
+    /// Start without the welcome banner (also the `welcome.banner` setting)
+    #[arg(long)]
+    no_banner: bool,
+    /// Sampling seed for reproducible runs, where the provider supports it; saved with the session
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show usage statistics recorded in .ariste
+    Stats {
+        #[command(subcommand)]
+        what: StatsCommand,
+    },
+    /// Generate or update documentation for a directory, reviewing each change
+    Document {
+        /// Directory or file to document
+        path: String,
+    },
+    /// Security review of files using risky constructs (exec, deserialization, SQL)
+    AuditCode {
+        /// Directory or file to audit
+        #[arg(default_value = ".")]
+        path: String,
+        #[command(flatten)]
+        output: FindingsOutput,
+    },
+    /// Translate resource files, checking keys and placeholders before writing
+    Translate {
+        /// Source files, e.g. 'locales/en/**'
+        #[arg(long)]
+        glob: String,
+        /// Target locale, replacing the source locale in each path
+        #[arg(long)]
+        to: String,
+        /// Source locale
+        #[arg(long, default_value = "en")]
+        from: String,
+    },
+    /// Add documentation (markdown, text, HTML or PDF files, directories of them, or web pages) to the knowledge base searched by docs_search
+    Ingest {
+        /// Files, directories or http(s) URLs
+        #[arg(required = true, value_name = "PATH|URL")]
+        sources: Vec<String>,
+    },
+    /// Review uncommitted changes and report findings
+    Review {
+        /// Only review changes to this file
+        path: Option<String>,
+        #[command(flatten)]
+        output: FindingsOutput,
+    },
+}
+
+/// Where review and audit findings go besides the terminal
+#[derive(clap::Args, Debug)]
+struct FindingsOutput {
+    /// Also write the findings as JSON or SARIF (audit-code defaults to sarif)
+    #[arg(long, value_parser = OutputFormat::NAMES)]
+    format: Option<String>,
+    /// File for --format output [default: ariste-<command>.<format>]
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Exit with an error when a finding is at least this severe
+    #[arg(long, value_parser = ["critical", "high", "medium", "low"])]
+    fail_on: Option<String>,
+}
+
+impl FindingsOutput {
+    /// Write `findings` in the requested format and apply `--fail-on`
+    async fn emit(&self, name: &str, default: OutputFormat, findings: &[Finding]) -> Result<(), Box<dyn std::error::Error>> {
+        let format = self.format.as_deref().and_then(OutputFormat::parse).unwrap_or(default);
+        if format.is_machine_readable() {
+            let extension = if format == OutputFormat::Sarif { "sarif" } else { "json" };
+            let path = match &self.output {
+                Some(path) => path.clone(),
+                None => PathBuf::from(format!("ariste-{}.{}", name, extension)),
+            };
+            tokio::fs::write(&path, findings::render(findings, format)).await?;
+            UI::success(&format!("Findings written to {}", path.display()));
+        }
+
+        if let Some(threshold) = self.fail_on.as_deref().map(Severity::parse)
+            && findings::any_at(findings, threshold)
+        {
+            return Err(format!("Findings at or above {} severity", threshold.name()).into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum StatsCommand {
+    /// Which tools the model calls, with what arguments, and how often they fail
+    Tools,
+}
+
+async fn print_tool_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let records = agent::analytics::load(agent::analytics::TOOL_STATS_FILE.as_ref()).await?;
+    if records.is_empty() {
+        UI::info("No tool calls recorded yet");
+        return Ok(());
+    }
+    for summary in agent::analytics::summarize(&records) {
+        print!("{}", summary);
+    }
+    Ok(())
+}
+
+/// The agent from `.ariste/settings.json`, shown on the terminal, with its
+/// spinner and welcome screen set up from the same settings
+async fn load_agent() -> Result<Agent, ariste::Error> {
+    let mut agent = Agent::load_from_config().await?;
+    agent.set_frontend(TerminalFrontend::shared());
+    let config = &agent.config;
+    // 无障碍模式下不使用动画 spinner
+    let animations = if glyphs::accessibility_requested(config.accessible) { Some(false) } else { config.animations };
+    UI::configure(SpinnerStyle::from_settings(config.spinner.as_ref(), animations, glyphs().ascii));
+    UI::configure_welcome(WelcomeScreen::from_settings(
+        config.welcome.as_ref(),
+        config.model.as_deref().unwrap_or("qwen3"),
+    ));
+    Ok(agent)
+}
+
+async fn document(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut agent = load_agent().await?;
+    let report = ariste::workflow::document::run(&mut agent, path).await?;
+    UI::success(&format!(
+        "Documentation updated: {} files kept, {} reverted",
+        report.kept.len(),
+        report.reverted.len()
+    ));
+    Ok(())
+}
+
+async fn audit_code(path: &str, output: &FindingsOutput) -> Result<(), Box<dyn std::error::Error>> {
+    let mut agent = load_agent().await?;
+    let report = ariste::workflow::audit::run(&mut agent, path).await?;
+    print!("{}", report);
+    output.emit("audit", OutputFormat::Sarif, &report.findings).await
+}
+
+async fn translate(pattern: &str, from: &str, to: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut agent = load_agent().await?;
+    let report = ariste::workflow::translate::run(&mut agent, pattern, from, to).await?;
+    for file in &report.written {
+        UI::success(&format!("Wrote {}", file));
+    }
+    for (file, reason) in &report.failed {
+        UI::error(&format!("Skipped {}: {}", file, reason));
+    }
+    if !report.failed.is_empty() {
+        return Err(format!("{} of {} files not translated", report.failed.len(), report.written.len() + report.failed.len()).into());
+    }
+    Ok(())
+}
+
+async fn ingest(sources: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut agent = load_agent().await?;
+    let mut failed = 0;
+    for source in sources {
+        let report = match agent.ingest(source).await {
+            Ok(report) => report,
+            Err(e) => {
+                UI::error(&format!("Skipped {}: {}", source, e));
+                failed += 1;
+                continue;
+            }
+        };
+        for (document, chunks, updated) in &report.ingested {
+            if *updated {
+                UI::success(&format!("Ingested {} ({} chunks)", document, chunks));
+            } else {
+                UI::info(&format!("Unchanged {}", document));
+            }
+        }
+        for (document, reason) in &report.failed {
+            UI::error(&format!("Skipped {}: {}", document, reason));
+        }
+        failed += report.failed.len();
+    }
+    if let Some(status) = agent.knowledge_status().await? {
+        UI::info(&status);
+    }
+    if failed > 0 {
+        return Err(format!("{} sources not ingested", failed).into());
+    }
+    Ok(())
+}
+
+async fn review(path: Option<&str>, output: &FindingsOutput) -> Result<(), Box<dyn std::error::Error>> {
+    let mut agent = load_agent().await?;
+    let findings = ariste::workflow::review::run(&mut agent, path).await?;
+    println!("Review: {} findings", findings.len());
+    print!("{}", findings::terminal(&findings));
+    output.emit("review", OutputFormat::Terminal, &findings).await
+}
+
+/// `--prompt`: a single turn for scripts and CI. Only the answer (or the
+/// JSON report) goes to stdout; a failed turn exits with an error.
+async fn one_shot(prompt: &str, resume: Option<&str>, seed: Option<u64>, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut agent = load_agent().await?;
+    if let Some(seed) = seed {
+        agent.set_seed(seed);
+    }
+    if let Some(id) = resume {
+        agent.resume(id).await?;
+    }
+    let mut run = HeadlessRun::new(json);
+    let outcome = agent.invoke_with_events(prompt, |event| run.on_event(event)).await;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&run.report(&agent, prompt, &outcome))?);
+    } else if outcome.is_ok() {
+        println!("{}", run.answer());
+    }
+    Ok(outcome?)
+}
+
+/// Restore saved session `id` into the agent, reporting the outcome
+async fn resume(agent: &mut Agent, id: &str) {
+    match agent.resume(id).await {
+        Ok(count) => UI::success(&format!("Resumed session {} ({} messages)", id, count)),
+        Err(e) => UI::error(&e.to_string()),
+    }
+}
+
+/// Offer to continue a session whose process died mid-turn. Declined
+/// checkpoints are deleted so the question isn't asked again.
+async fn offer_recovery(agent: &mut Agent) {
+    let crashed = match agent.crashed_sessions().await {
+        Ok(crashed) => crashed,
+        Err(e) => return UI::warning(&format!("Failed to look for unfinished sessions: {}", e)),
+    };
+    let mut crashed = crashed.into_iter();
+    let Some(latest) = crashed.next() else {
+        return;
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let question = format!(
+        "Session {} ended unexpectedly {} ({} messages). Recover it?",
+        latest.session.id,
+        session::format_age(now, latest.session.updated),
+        latest.session.messages.len()
+    );
+    let id = latest.session.id.clone();
+    if UI::confirm(&question) {
+        match agent.recover(latest).await {
+            Ok(count) => UI::success(&format!("Recovered session {} ({} messages)", id, count)),
+            Err(e) => UI::error(&e.to_string()),
+        }
+    } else if let Err(e) = agent.discard_checkpoint(&id).await {
+        UI::warning(&e.to_string());
+    }
+    // 只提供最新的一个；更早会话已完成的轮次仍保存在各自的会话文件中
+    for older in crashed {
+        if let Err(e) = agent.discard_checkpoint(&older.session.id).await {
+            UI::warning(&e.to_string());
+        }
+    }
+}
+
+/// Run a turn that Ctrl-C cancels: the first press stops the reply being
+/// generated and keeps what arrived, a second one exits
+async fn invoke_cancellable(agent: &mut Agent, prompt: &str) -> Result<(), ariste::Error> {
+    let watcher = watch_ctrl_c(agent.cancel_token());
+    let result = agent.invoke(prompt).await;
+    watcher.abort();
+    result
+}
+
+/// Cancel `cancel` on Ctrl-C; a second press exits
+fn watch_ctrl_c(cancel: ariste::llm::CancelToken) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if cancel.is_cancelled() {
+                std::process::exit(130);
+            }
+            cancel.cancel();
+            UI::warning("Cancelling… press Ctrl-C again to exit");
+        }
+    })
+}
+
+/// `/tasks`: list the background tasks, or wait for task `id` (Ctrl-C stops
+/// waiting) and show its result
+async fn show_tasks(agent: &mut Agent, id: Option<usize>) {
+    let Some(id) = id else {
+        agent.collect_tasks();
+        if agent.tasks().iter().next().is_none() {
+            return UI::info("No background tasks yet (the task tool's run_in_background)");
+        }
+        for execution in agent.tasks().iter() {
+            println!("  {}", tasks::describe(execution));
+        }
+        return;
+    };
+    let cancel = agent.cancel_token();
+    cancel.reset();
+    let watcher = watch_ctrl_c(cancel);
+    let result = agent.task_result(id, true).await;
+    watcher.abort();
+    match result {
+        Ok(execution) => {
+            println!("  {}", tasks::describe(execution));
+            match (&execution.result, &execution.status) {
+                (Some(result), _) => println!("{}", result),
+                (None, SubAgentStatus::Failed(error)) => UI::error(error),
+                (None, _) => UI::info("Still running; /tasks <id> again to wait for it"),
+            }
+        }
+        Err(e) => UI::error(&e.to_string()),
+    }
+}
+
+/// Let the user pick a saved session to resume (`/resume` without an id)
+async fn pick_session(agent: &mut Agent) {
+    let sessions = match agent.saved_sessions().await {
+        Ok(sessions) => sessions,
+        Err(e) => return UI::error(&e.to_string()),
+    };
+    let current = agent.session_id().to_string();
+    let sessions: Vec<_> = sessions.into_iter().filter(|s| s.id != current).collect();
+    if sessions.is_empty() {
+        return UI::info("No other saved sessions in .ariste/sessions");
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let options: Vec<String> = sessions
+        .iter()
+        .map(|s| {
+            format!(
+                "{}  {}",
+                s.title,
+                format!("{} · {} messages · {}", session::format_age(now, s.updated), s.messages, s.id).dimmed()
+            )
+        })
+        .collect();
+    if let Some(choice) = UI::choose("Resume which session?", &options) {
+        resume(agent, &sessions[choice].id).await;
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    match args.command {
+        Some(Command::Stats { what: StatsCommand::Tools }) => return print_tool_stats().await,
+        Some(Command::Document { path }) => return document(&path).await,
+        Some(Command::AuditCode { path, output }) => return audit_code(&path, &output).await,
+        Some(Command::Review { path, output }) => return review(path.as_deref(), &output).await,
+        Some(Command::Translate { glob, to, from }) => return translate(&glob, &from, &to).await,
+        Some(Command::Ingest { sources }) => return ingest(&sources).await,
+        None => {}
+    }
+    if let Some(prompt) = &args.prompt {
+        return one_shot(prompt, args.resume.as_deref(), args.seed, args.output_format == "json").await;
+    }
+
     // 1. 指定工作目录
     let ariste_folder: PathBuf = ".ariste".into();
     if !ariste_folder.exists() {
@@ -30,12 +401,23 @@ async fn main() -> Result<(), Error> {
     }
 
     // 2. 创建Agent和UI
-    let mut agent = Agent::load_from_config().await?;
+    let mut agent = load_agent().await?;
+    if let Some(seed) = args.seed {
+        agent.set_seed(seed);
+    }
+    agent.watch_index();
     let mut ui = UI::new();
 
     // 3. 显示欢迎信息
     let workdir: PathBuf = std::env::current_dir()?;
-    UI::welcome(&workdir);
+    if !args.no_banner && UI::shows_banner() {
+        UI::welcome(&workdir);
+    }
+    if let Some(id) = &args.resume {
+        resume(&mut agent, id).await;
+    } else {
+        offer_recovery(&mut agent).await;
+    }
 
     let mut rl: Editor<AgentHinter, DefaultHistory> = Editor::new()?;
     let history_file = ariste_folder.join("history.txt");
@@ -44,10 +426,22 @@ async fn main() -> Result<(), Error> {
     }
     rl.set_helper(Some(AgentHinter::new()));
 
+    // 回复过程中输入的消息按顺序排队；未输完的一行放回提示符
+    let mut queued: VecDeque<String> = VecDeque::new();
+    let mut draft = String::new();
+
     // 4. 聊天对话
     loop {
+        UI::separator(agent.turns() + 1, "you");
         let prompt = UI::prompt();
-        match rl.readline(&prompt) {
+        let input = match queued.pop_front() {
+            Some(line) => {
+                println!("{}{}", prompt, line);
+                Ok(line)
+            }
+            None => rl.readline_with_initial(&prompt, (&std::mem::take(&mut draft), "")),
+        };
+        match input {
             Ok(line) => {
                 let line = line.trim();
                 rl.add_history_entry(line)?;
@@ -71,6 +465,210 @@ async fn main() -> Result<(), Error> {
                         UI::info("Conversation history cleared");
                         continue;
                     }
+                    "/scope" => {
+                        match agent.workspace.scope() {
+                            Some(scope) => UI::info(&format!("Current scope: {}", scope.display())),
+                            None => UI::info("No scope set; tools see the full workspace"),
+                        }
+                        continue;
+                    }
+                    "/scope reset" => {
+                        agent.reset_scope();
+                        UI::info("Scope reset; tools see the full workspace");
+                        continue;
+                    }
+                    cmd if cmd.starts_with("/scope ") => {
+                        match agent.set_scope(cmd["/scope ".len()..].trim()) {
+                            Ok(scope) => UI::info(&format!("Tools are now scoped to {}", scope.display())),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/context" => {
+                        print!("{}", agent.context_report());
+                        continue;
+                    }
+                    "/usage" => {
+                        print!("{}", agent.usage());
+                        continue;
+                    }
+                    cmd if cmd == "/tasks" || cmd.starts_with("/tasks ") => {
+                        match cmd["/tasks".len()..].trim() {
+                            "" => show_tasks(&mut agent, None).await,
+                            id => match id.parse() {
+                                Ok(id) => show_tasks(&mut agent, Some(id)).await,
+                                Err(_) => UI::warning("Usage: /tasks [task id]"),
+                            },
+                        }
+                        continue;
+                    }
+                    cmd if cmd == "/cancel" || cmd.starts_with("/cancel ") => {
+                        match cmd["/cancel".len()..].trim().parse() {
+                            Ok(id) => match agent.cancel_task(id) {
+                                Ok(message) => UI::info(&message),
+                                Err(e) => UI::error(&e.to_string()),
+                            },
+                            Err(_) => UI::warning("Usage: /cancel <task id>"),
+                        }
+                        continue;
+                    }
+                    "/compact" => {
+                        match agent.compact().await {
+                            Ok(Some((before, after))) => {
+                                UI::info(&format!("Compacted earlier turns: ~{} → ~{} tokens", before, after))
+                            }
+                            Ok(None) => UI::info("Nothing to compact yet"),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    cmd if cmd == "/forget" || cmd.starts_with("/forget ") => {
+                        match agent.forget(&cmd["/forget".len()..]) {
+                            Ok(0) => UI::warning("No matching messages"),
+                            Ok(removed) => UI::info(&format!("Removed {} messages from history", removed)),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/pin" => {
+                        if agent.pins().is_empty() {
+                            UI::info("Nothing pinned; use /pin <file|note>");
+                        }
+                        for (index, pin) in agent.pins().iter().enumerate() {
+                            UI::info(&format!("#{} {}", index, pin.label()));
+                        }
+                        continue;
+                    }
+                    cmd if cmd.starts_with("/pin ") => {
+                        match agent.pin(&cmd["/pin ".len()..]) {
+                            Ok(pin) => UI::info(&format!("Pinned {}", pin.label())),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    cmd if cmd.starts_with("/unpin ") => {
+                        let removed = cmd["/unpin ".len()..]
+                            .trim()
+                            .parse()
+                            .map_err(|_| "Usage: /unpin <n>".to_string())
+                            .and_then(|index| agent.unpin(index).map_err(|e| e.to_string()));
+                        match removed {
+                            Ok(pin) => UI::info(&format!("Unpinned {}", pin.label())),
+                            Err(e) => UI::error(&e),
+                        }
+                        continue;
+                    }
+                    "/index" => {
+                        match agent.index_status().await {
+                            Ok(Some(status)) => UI::info(&status),
+                            Ok(None) => UI::info("The code index is off; enable it with \"index\": {\"enabled\": true} in .ariste/settings.json"),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        match agent.knowledge_status().await {
+                            Ok(Some(status)) => UI::info(&status),
+                            Ok(None) => {}
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/index rebuild" => {
+                        UI::info("Rebuilding the code index...");
+                        match agent.rebuild_index().await {
+                            Ok(stats) => UI::success(&format!("Indexed {} files ({} chunks)", stats.files, stats.chunks)),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/rag" => {
+                        if agent.rag_enabled() {
+                            UI::info("Retrieval augmentation is on; /rag off stops adding retrieved code and docs");
+                        } else {
+                            UI::info("Retrieval augmentation is off; /rag on adds the closest code and docs to each turn");
+                        }
+                        continue;
+                    }
+                    "/rag on" | "/rag off" => {
+                        match agent.set_rag(line == "/rag on") {
+                            Ok(()) if line == "/rag on" => UI::success("Retrieval augmentation on"),
+                            Ok(()) => UI::success("Retrieval augmentation off"),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/recall" => {
+                        UI::info("Usage: /recall <what to look for in earlier sessions>");
+                        continue;
+                    }
+                    cmd if cmd.starts_with("/recall ") => {
+                        match agent.recall(cmd["/recall ".len()..].trim()).await {
+                            Ok(hits) if hits.is_empty() => UI::info("Nothing in earlier sessions matches"),
+                            Ok(hits) => {
+                                for hit in &hits {
+                                    let prompt = hit.text.lines().next().unwrap_or_default().trim_start_matches("User: ");
+                                    UI::info(&format!("{}: {}", hit.source, prompt));
+                                }
+                                UI::info("These go with your next message");
+                            }
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/undo" => {
+                        match agent.undo() {
+                            Ok(Some(restored)) => UI::success(&restored),
+                            Ok(None) => UI::info("Nothing to undo"),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/last" => {
+                        match agent.last_answer() {
+                            Some(answer) => UI::response_content(answer),
+                            None => UI::info("No answer yet"),
+                        }
+                        continue;
+                    }
+                    "/resume" => {
+                        pick_session(&mut agent).await;
+                        continue;
+                    }
+                    cmd if cmd.starts_with("/resume ") => {
+                        resume(&mut agent, cmd["/resume ".len()..].trim()).await;
+                        continue;
+                    }
+                    "/share" => {
+                        UI::info("Uploading conversation as a secret gist...");
+                        match agent.share().await {
+                            Ok(url) => UI::success(&format!("Shared: {}", url)),
+                            Err(e) => UI::error(&e.to_string()),
+                        }
+                        continue;
+                    }
+                    "/experiment" => {
+                        match agent.experiment_summary() {
+                            Some((name, summary)) => {
+                                UI::info(&format!("Experiment {}", name));
+                                for variant in summary {
+                                    UI::info(&format!("  {}", variant));
+                                }
+                            }
+                            None => UI::info("No experiment configured in .ariste/settings.json"),
+                        }
+                        continue;
+                    }
+                    cmd if cmd == "/consensus" || cmd.starts_with("/consensus ") => {
+                        match agent::consensus::parse_args(&cmd["/consensus".len()..]) {
+                            Some((count, question)) => {
+                                UI::separator(agent.turns() + 1, "ariste");
+                                if let Err(e) = agent.consensus(question, count).await {
+                                    UI::error(&e.to_string());
+                                }
+                                UI::response_end();
+                            }
+                            None => UI::warning("Usage: /consensus [n] <question>"),
+                        }
+                        continue;
+                    }
                     cmd if cmd.starts_with('/') => {
                         UI::warning(&format!("Unknown command: {}", cmd));
                         UI::info("Type /help to see available commands");
@@ -79,9 +677,19 @@ async fn main() -> Result<(), Error> {
                     _ => {
                         // 执行 AI 调用
                         ui.reset_spinner();
-                        if let Err(e) = agent.invoke(line).await {
+                        UI::separator(agent.turns() + 1, "ariste");
+                        let redirect = agent.redirect_token();
+                        let typeahead = TypeAhead::start(move || {
+                            redirect.request();
+                            UI::info("Pausing for a correction once the running tools finish");
+                        });
+                        if let Err(e) = invoke_cancellable(&mut agent, line).await {
                             UI::error(&e.to_string());
                         }
+                        if let Some(typed) = typeahead.map(TypeAhead::finish) {
+                            queued.extend(typed.lines);
+                            draft = typed.partial;
+                        }
                         UI::response_end();
                     }
                 }
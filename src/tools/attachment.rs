@@ -0,0 +1,32 @@
+use std::sync::{Arc, Mutex};
+
+/// An image a tool read for the model to look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageAttachment {
+    /// The path as the tool call gave it
+    pub path: String,
+    /// Base64 of the file
+    pub data: String,
+}
+
+/// Where tools leave images for a vision model. The agent takes them
+/// after the calls ran and attaches each to the result of the call that
+/// read it; without a sink, tools describe images instead.
+pub type ImageSink = Arc<Mutex<Vec<ImageAttachment>>>;
+
+/// Hand `attachment` to the model along with the tool result
+pub fn attach(sink: &ImageSink, attachment: ImageAttachment) {
+    if let Ok(mut images) = sink.lock() {
+        images.push(attachment);
+    }
+}
+
+/// The images of a call that read `path`, removed from `sink`
+pub fn take_for(sink: &ImageSink, path: &str) -> Vec<String> {
+    let Ok(mut images) = sink.lock() else {
+        return Vec::new();
+    };
+    let (taken, kept): (Vec<_>, Vec<_>) = images.drain(..).partition(|image| image.path == path);
+    *images = kept;
+    taken.into_iter().map(|image| image.data).collect()
+}
@@ -1,9 +1,25 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::interactive::interactive_reason;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use crate::tools::progress::ToolProgress;
 use serde_json::Value;
-use std::process::Command;
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::task;
 
+const DEFAULT_TIMEOUT_MS: u64 = 120_000;
+const MAX_TIMEOUT_MS: u64 = 600_000;
+/// Bytes kept of each of stdout and stderr; the middle of longer output is dropped
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 30_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// How long the output readers get to finish once the command is killed;
+/// a process that left its process group may still hold the pipes
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
 /// Bash tool for executing shell commands
 pub struct BashTool;
 
@@ -18,11 +34,43 @@ impl ToolImpl for BashTool {
             }),
         );
 
+        properties.insert(
+            "cwd".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Directory to run the command in, instead of chaining `cd dir && ...`. Must be inside the workspace."
+            }),
+        );
+
+        properties.insert(
+            "timeout_ms".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Kill the command after this many milliseconds (default {}, at most {})", DEFAULT_TIMEOUT_MS, MAX_TIMEOUT_MS)
+            }),
+        );
+
+        properties.insert(
+            "max_output_bytes".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Bytes kept of each of stdout and stderr; longer output keeps its start and end (default {})", DEFAULT_MAX_OUTPUT_BYTES)
+            }),
+        );
+
+        properties.insert(
+            "run_in_background".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Start the command and return its shell id at once instead of waiting, for dev servers and watchers. Read its output with bash_output and stop it with kill_shell; timeout_ms doesn't apply"
+            }),
+        );
+
         ToolDefinition {
             r#type: "function".to_string(),
             function: FunctionDefinition {
                 name: "bash".to_string(),
-                description: "Execute bash commands in the shell".to_string(),
+                description: "Execute bash commands in the shell. The result shows the exit code and stdout and stderr separately. Commands running past timeout_ms are killed; run long-lived ones with run_in_background".to_string(),
                 parameters: ParametersSchema {
                     r#type: "object".to_string(),
                     properties,
@@ -32,47 +80,284 @@ impl ToolImpl for BashTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let command = arguments
-            .get("command")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'command' argument".to_string())?
-            .to_string(); // Clone the command string to own it
-
-        // Execute the command in a blocking task
-        let result = task::spawn_blocking(move || {
-            // Use sh -c to execute the command, which supports pipes, redirects, etc.
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(&command)
-                .output();
-
-            match output {
-                Ok(output) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-                    if output.status.success() {
-                        Ok(stdout)
-                    } else {
-                        let error_msg = if !stderr.is_empty() {
-                            stderr
-                        } else {
-                            format!("Command failed with exit code: {:?}", output.status.code())
-                        };
-                        Err(error_msg)
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let command = arguments
+                .get("command")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'command' argument".to_string())?
+                .to_string(); // Clone the command string to own it
+
+            // 没有终端可用，交互式命令会一直阻塞
+            if let Some(reason) = interactive_reason(&command) {
+                return Err(format!("Refusing to run an interactive command: {}", reason));
+            }
+
+            let cwd = match arguments.get("cwd").and_then(|v| v.as_str()) {
+                Some(cwd) => {
+                    let resolved = context.workspace.resolve(cwd);
+                    context.workspace.check_scope(&resolved)?;
+                    if !resolved.is_dir() {
+                        return Err(format!("Working directory '{}' does not exist", cwd));
                     }
+                    Some(resolved)
                 }
-                Err(e) => Err(format!("Failed to execute command: {}", e)),
+                None => None,
+            };
+
+            if arguments.get("run_in_background").and_then(|v| v.as_bool()).unwrap_or(false) {
+                let (id, pid) = context.shells.spawn(&command, cwd.as_deref())?;
+                return Ok(format!(
+                    "Started {} in the background (pid {}). Read its output with bash_output and stop it with kill_shell",
+                    id, pid
+                ));
             }
+
+            let timeout_ms = match arguments.get("timeout_ms").and_then(|v| v.as_u64()) {
+                Some(0) => return Err("timeout_ms must be greater than 0".to_string()),
+                Some(ms) => ms.min(MAX_TIMEOUT_MS),
+                None => DEFAULT_TIMEOUT_MS,
+            };
+            let max_output_bytes = match arguments.get("max_output_bytes").and_then(|v| v.as_u64()) {
+                Some(0) => return Err("max_output_bytes must be greater than 0".to_string()),
+                Some(bytes) => bytes as usize,
+                None => DEFAULT_MAX_OUTPUT_BYTES,
+            };
+
+            let progress = context.progress.clone();
+            progress.watch();
+
+            // Execute the command in a blocking task
+            task::spawn_blocking(move || {
+                let mut child = shell_command(&command, cwd.as_deref())
+                    .spawn()
+                    .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+                // 边读边记录输出，心跳才能显示命令是否仍在产出
+                let stdout = collect(child.stdout.take(), max_output_bytes, progress.clone());
+                let stderr = collect(child.stderr.take(), max_output_bytes, progress);
+
+                let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+                let status = loop {
+                    match child.try_wait().map_err(|e| format!("Failed to execute command: {}", e))? {
+                        Some(status) => break Some(status),
+                        None if Instant::now() >= deadline => {
+                            kill(&mut child);
+                            let _ = child.wait();
+                            break None;
+                        }
+                        None => std::thread::sleep(POLL_INTERVAL),
+                    }
+                };
+                // sh 退出后，它放到后台的进程可能仍占着管道：仍按 deadline 结束整个进程组
+                let readers_done = || stdout.0.is_finished() && stderr.0.is_finished();
+                let mut lingering = false;
+                if status.is_some() {
+                    while !readers_done() {
+                        if Instant::now() >= deadline {
+                            kill(&mut child);
+                            lingering = true;
+                            break;
+                        }
+                        std::thread::sleep(POLL_INTERVAL);
+                    }
+                }
+                let grace = Instant::now() + KILL_GRACE;
+                while !readers_done() && Instant::now() < grace {
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                let join = |(reader, capture): (std::thread::JoinHandle<()>, Arc<Mutex<Capture>>)| {
+                    if reader.is_finished() {
+                        let _ = reader.join();
+                    }
+                    std::mem::take(&mut *capture.lock().unwrap())
+                };
+                let (stdout, stderr) = (join(stdout), join(stderr));
+
+                // 非零退出码和超时仍然返回已有的输出，只是标记为错误
+                match status {
+                    Some(status) if lingering => Err(format!(
+                        "Command exited, but processes it started still held its output after {} ms and were killed; \
+                         start long-running processes with run_in_background\n{}",
+                        timeout_ms,
+                        format_output(status.code(), &stdout, &stderr)
+                    )),
+                    Some(status) if status.success() => Ok(format_output(status.code(), &stdout, &stderr)),
+                    Some(status) => Err(format_output(status.code(), &stdout, &stderr)),
+                    None => Err(format!(
+                        "Command timed out after {} ms and was killed\n{}",
+                        timeout_ms,
+                        format_output(None, &stdout, &stderr)
+                    )),
+                }
+            })
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
         })
-        .await
-        .map_err(|e| format!("Task join error: {}", e))?;
+    }
+}
+
+/// `sh -c command` with its output piped. It supports pipes, redirects,
+/// etc. stdin is closed so commands that read it see EOF instead of waiting
+/// forever, and pagers/prompts are disabled.
+pub(crate) fn shell_command(command: &str, cwd: Option<&Path>) -> Command {
+    let mut process = Command::new("sh");
+    if let Some(cwd) = cwd {
+        process.current_dir(cwd);
+    }
+    // 放进单独的进程组，超时时连同 sh 启动的子进程一起结束
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        process.process_group(0);
+    }
+    process
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .env("PAGER", "cat")
+        .env("GIT_PAGER", "cat")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("DEBIAN_FRONTEND", "noninteractive");
+    process
+}
+
+/// End the command and everything it started
+#[cfg(unix)]
+pub(crate) fn kill(child: &mut Child) {
+    // 负的 pid 表示整个进程组
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
+
+/// Whether anything in the command's process group still runs, such as a
+/// server it started with `&` after it exited itself
+#[cfg(unix)]
+pub(crate) fn group_running(child: &Child) -> bool {
+    // 信号 0 只检查进程组是否还有进程
+    unsafe { libc::kill(-(child.id() as libc::pid_t), 0) == 0 }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn group_running(_child: &Child) -> bool {
+    false
+}
+
+/// Output of one stream, holding at most `limit` bytes: its start and its
+/// end, with the length of what came between
+#[derive(Debug, Default)]
+struct Capture {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total: usize,
+    limit: usize,
+}
 
-        result
+impl Capture {
+    fn new(limit: usize) -> Self {
+        Self { limit, ..Self::default() }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.total += bytes.len();
+        let room = (self.limit / 2).saturating_sub(self.head.len()).min(bytes.len());
+        self.head.extend_from_slice(&bytes[..room]);
+        self.tail.extend(&bytes[room..]);
+        let keep = self.limit - self.limit / 2;
+        if self.tail.len() > keep {
+            self.tail.drain(..self.tail.len() - keep);
+        }
+    }
+
+    fn omitted(&self) -> usize {
+        self.total - self.head.len() - self.tail.len()
+    }
+
+    fn text(&self) -> String {
+        let tail: Vec<u8> = self.tail.iter().copied().collect();
+        if self.omitted() == 0 {
+            return String::from_utf8_lossy(&[self.head.as_slice(), &tail].concat()).into_owned();
+        }
+        format!(
+            "{}\n... ({} bytes omitted) ...\n{}",
+            String::from_utf8_lossy(&self.head),
+            self.omitted(),
+            String::from_utf8_lossy(&tail)
+        )
     }
 }
 
+/// Read a child's pipe to the end on its own thread, reporting each chunk
+/// to `progress`. The capture is shared so a timeout still sees what the
+/// command printed.
+fn collect(
+    pipe: Option<impl Read + Send + 'static>,
+    limit: usize,
+    progress: ToolProgress,
+) -> (std::thread::JoinHandle<()>, Arc<Mutex<Capture>>) {
+    let capture = Arc::new(Mutex::new(Capture::new(limit)));
+    let shared = capture.clone();
+    let reader = std::thread::spawn(move || {
+        let Some(mut pipe) = pipe else {
+            return;
+        };
+        let mut buffer = [0u8; 8192];
+        while let Ok(read) = pipe.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            progress.record_output(&String::from_utf8_lossy(&buffer[..read]));
+            shared.lock().unwrap().push(&buffer[..read]);
+        }
+    });
+    (reader, capture)
+}
+
+/// Exit code followed by separately labeled stdout and stderr sections, so
+/// warnings on stderr aren't lost when a command succeeds
+fn format_output(code: Option<i32>, stdout: &Capture, stderr: &Capture) -> String {
+    let code = match code {
+        Some(code) => code.to_string(),
+        None => "none (terminated by signal)".to_string(),
+    };
+    let mut result = format!("exit code: {}\n", code);
+    for (name, capture) in [("stdout", stdout), ("stderr", stderr)] {
+        match capture.omitted() {
+            0 => result.push_str(&format!("--- {} ({} bytes) ---\n", name, capture.total)),
+            omitted => result.push_str(&format!(
+                "--- {} ({} bytes, {} omitted; raise max_output_bytes or filter the output) ---\n",
+                name, capture.total, omitted
+            )),
+        }
+        let content = capture.text();
+        if !content.is_empty() {
+            result.push_str(&content);
+            if !content.ends_with('\n') {
+                result.push('\n');
+            }
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,7 +366,10 @@ mod tests {
     async fn test_bash_echo() {
         let tool = BashTool;
         let args = serde_json::json!({"command": "echo hello"});
-        assert_eq!(tool.execute(&args).await, Ok("hello\n".to_string()));
+        assert_eq!(
+            tool.execute(&args).await,
+            Ok("exit code: 0\n--- stdout (6 bytes) ---\nhello\n--- stderr (0 bytes) ---\n".to_string())
+        );
     }
 
     #[tokio::test]
@@ -102,7 +390,113 @@ mod tests {
     async fn test_bash_invalid_command() {
         let tool = BashTool;
         let args = serde_json::json!({"command": "nonexistentcommand123"});
+        let error = tool.execute(&args).await.unwrap_err();
+        assert!(error.starts_with("exit code: 127\n"));
+        assert!(error.contains("nonexistentcommand123"));
+    }
+
+    #[tokio::test]
+    async fn test_bash_keeps_stderr_on_success() {
+        let tool = BashTool;
+        let args = serde_json::json!({"command": "echo done; echo 'warning: deprecated' >&2"});
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.contains("--- stdout (5 bytes) ---\ndone\n"));
+        assert!(result.contains("--- stderr (20 bytes) ---\nwarning: deprecated\n"));
+    }
+
+    #[tokio::test]
+    async fn test_bash_failure_keeps_stdout() {
+        let tool = BashTool;
+        let args = serde_json::json!({"command": "printf partial; exit 3"});
+        let error = tool.execute(&args).await.unwrap_err();
+        assert_eq!(error, "exit code: 3\n--- stdout (7 bytes) ---\npartial\n--- stderr (0 bytes) ---\n");
+    }
+
+    #[tokio::test]
+    async fn test_bash_refuses_interactive() {
+        let tool = BashTool;
+        let args = serde_json::json!({"command": "vim notes.txt"});
+        let error = tool.execute(&args).await.unwrap_err();
+        assert!(error.starts_with("Refusing to run an interactive command: `vim`"));
+    }
+
+    #[tokio::test]
+    async fn test_bash_stdin_is_closed() {
+        let tool = BashTool;
+        // Would block forever if stdin were inherited from a terminal
+        let args = serde_json::json!({"command": "read line; echo \"got: $line\""});
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.contains("got: \n"));
+    }
+
+    #[tokio::test]
+    async fn test_bash_reports_progress() {
+        let progress = ToolProgress::new();
+        let context = ToolContext::default().with_progress(progress.clone());
+        let args = serde_json::json!({"command": "echo building; echo 'warning: slow' >&2"});
+        BashTool.execute_with_context(&context, &args).await.unwrap();
+
+        let snapshot = progress.snapshot();
+        assert!(snapshot.reporting);
+        assert_eq!(snapshot.bytes, 23);
+        assert!(snapshot.last_line.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_bash_cwd() {
+        let dir = "/tmp/test_bash_cwd/sub";
+        std::fs::create_dir_all(dir).unwrap();
+        let tool = BashTool;
+
+        let args = serde_json::json!({"command": "pwd", "cwd": dir});
+        assert!(tool.execute(&args).await.unwrap().contains("/tmp/test_bash_cwd/sub\n"));
+
+        let args = serde_json::json!({"command": "pwd", "cwd": "/tmp/test_bash_cwd/missing"});
+        assert!(tool.execute(&args).await.unwrap_err().contains("does not exist"));
+
+        // Outside the scope the command is rejected
+        let root = crate::workspace::WorkspaceRoot::new("tmp", "/tmp/test_bash_cwd");
+        let mut workspace = crate::workspace::Workspace::new(vec![root]).unwrap();
+        workspace.set_scope("/tmp/test_bash_cwd/sub").unwrap();
+        let context = ToolContext::new(workspace);
+        let args = serde_json::json!({"command": "pwd", "cwd": "/tmp"});
+        assert!(tool.execute_with_context(&context, &args).await.is_err());
+        let args = serde_json::json!({"command": "pwd", "cwd": dir});
+        assert!(tool.execute_with_context(&context, &args).await.is_ok());
+
+        std::fs::remove_dir_all("/tmp/test_bash_cwd").ok();
+    }
+
+    #[tokio::test]
+    async fn test_bash_timeout_kills_command() {
+        let tool = BashTool;
+        let started = Instant::now();
+        // The background sleep shares the pipes, so it must be killed too
+        let args = serde_json::json!({"command": "echo started; sleep 30 & sleep 30", "timeout_ms": 300});
+        let error = tool.execute(&args).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(error.starts_with("Command timed out after 300 ms and was killed\nexit code: none"));
+        assert!(error.contains("--- stdout (8 bytes) ---\nstarted\n"));
+
+        let args = serde_json::json!({"command": "true", "timeout_ms": 0});
         assert!(tool.execute(&args).await.is_err());
+
+        // sh exits at once, but the sleep it left behind holds the pipes
+        let started = Instant::now();
+        let args = serde_json::json!({"command": "sleep 30 & echo ok", "timeout_ms": 300});
+        let error = tool.execute(&args).await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(error.starts_with("Command exited, but processes it started still held its output after 300 ms"));
+        assert!(error.contains("--- stdout (3 bytes) ---\nok\n"));
+    }
+
+    #[tokio::test]
+    async fn test_bash_output_limit() {
+        let tool = BashTool;
+        let args = serde_json::json!({"command": "seq 1 1000; echo oops >&2", "max_output_bytes": 20});
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.contains("--- stdout (3893 bytes, 3873 omitted; raise max_output_bytes or filter the output) ---\n1\n2\n3\n4\n5\n\n... (3873 bytes omitted) ...\n\n999\n1000\n"));
+        assert!(result.ends_with("--- stderr (5 bytes) ---\noops\n"));
     }
 
     #[tokio::test]
@@ -110,6 +504,9 @@ mod tests {
         let tool = BashTool;
         let args = serde_json::json!({"command": ""});
         // Empty command is valid in sh -c "", just returns empty output
-        assert_eq!(tool.execute(&args).await, Ok("".to_string()));
+        assert_eq!(
+            tool.execute(&args).await,
+            Ok("exit code: 0\n--- stdout (0 bytes) ---\n--- stderr (0 bytes) ---\n".to_string())
+        );
     }
 }
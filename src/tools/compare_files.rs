@@ -0,0 +1,192 @@
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
+use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+
+const DEFAULT_CONTEXT_LINES: usize = 3;
+const MAX_DIFF_LINES: usize = 400;
+
+/// Text files are diffed; anything with a NUL byte or invalid UTF-8 is
+/// compared by hash
+fn as_text(bytes: &[u8]) -> Option<&str> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(bytes).ok()
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Unified diff of `expected` against `actual`, cut off after `MAX_DIFF_LINES`
+fn unified_diff(expected_name: &str, actual_name: &str, expected: &str, actual: &str, context_lines: usize) -> String {
+    let diff = TextDiff::from_lines(expected, actual);
+    let (mut added, mut removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            similar::ChangeTag::Insert => added += 1,
+            similar::ChangeTag::Delete => removed += 1,
+            similar::ChangeTag::Equal => {}
+        }
+    }
+
+    let text = diff
+        .unified_diff()
+        .context_radius(context_lines)
+        .header(expected_name, actual_name)
+        .to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut output = format!("Files differ: {} lines added, {} removed\n", added, removed);
+    for line in lines.iter().take(MAX_DIFF_LINES) {
+        output.push_str(line);
+        output.push('\n');
+    }
+    if lines.len() > MAX_DIFF_LINES {
+        output.push_str(&format!("... {} more diff lines omitted\n", lines.len() - MAX_DIFF_LINES));
+    }
+    output
+}
+
+/// Compare files tool: a unified diff for text files, a SHA-256 check for
+/// binaries
+pub struct CompareFilesTool;
+
+impl ToolImpl for CompareFilesTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "expected".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "The reference file, e.g. a golden file"
+            }),
+        );
+        properties.insert(
+            "actual".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "The file to check against it, e.g. generated output"
+            }),
+        );
+        properties.insert(
+            "context_lines".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Unchanged lines shown around each change (default: {})", DEFAULT_CONTEXT_LINES)
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "compare_files".to_string(),
+                description: "Compare two files. Text files get a unified diff (expected -> actual); binary files are compared by size and SHA-256. Use this to check generated output against golden files.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["expected".to_string(), "actual".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let mut contents = Vec::new();
+            for key in ["expected", "actual"] {
+                let path = arguments
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| format!("Missing '{}' argument", key))?;
+                let resolved = context.workspace.resolve(path);
+                context.workspace.check_scope(&resolved)?;
+                let bytes = tokio::fs::read(&resolved)
+                    .await
+                    .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+                contents.push((path, bytes));
+            }
+            let context_lines = arguments
+                .get("context_lines")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as usize)
+                .unwrap_or(DEFAULT_CONTEXT_LINES);
+
+            let (expected_name, expected) = &contents[0];
+            let (actual_name, actual) = &contents[1];
+            if expected == actual {
+                return Ok(format!("Files are identical ({} bytes, sha256 {})", expected.len(), sha256(expected)));
+            }
+
+            match (as_text(expected), as_text(actual)) {
+                (Some(expected), Some(actual)) => Ok(unified_diff(expected_name, actual_name, expected, actual, context_lines)),
+                _ => Ok(format!(
+                    "Binary files differ\n  {}: {} bytes, sha256 {}\n  {}: {} bytes, sha256 {}",
+                    expected_name,
+                    expected.len(),
+                    sha256(expected),
+                    actual_name,
+                    actual.len(),
+                    sha256(actual)
+                )),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compare_text_files() {
+        let dir = "/tmp/test_compare_files_text";
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(format!("{}/golden.txt", dir), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(format!("{}/out.txt", dir), "one\n2\nthree\n").unwrap();
+        std::fs::write(format!("{}/same.txt", dir), "one\ntwo\nthree\n").unwrap();
+
+        let args = serde_json::json!({"expected": format!("{}/golden.txt", dir), "actual": format!("{}/out.txt", dir)});
+        let result = CompareFilesTool.execute(&args).await.unwrap();
+        assert!(result.starts_with("Files differ: 1 lines added, 1 removed"));
+        assert!(result.contains("-two\n+2\n"));
+        assert!(result.contains(&format!("+++ {}/out.txt", dir)));
+
+        let args = serde_json::json!({"expected": format!("{}/golden.txt", dir), "actual": format!("{}/same.txt", dir)});
+        let result = CompareFilesTool.execute(&args).await.unwrap();
+        assert!(result.starts_with("Files are identical (14 bytes, sha256 "));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_compare_binary_files() {
+        let dir = "/tmp/test_compare_files_binary";
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(format!("{}/a.bin", dir), [0u8, 1, 2]).unwrap();
+        std::fs::write(format!("{}/b.bin", dir), [0u8, 1, 3]).unwrap();
+
+        let args = serde_json::json!({"expected": format!("{}/a.bin", dir), "actual": format!("{}/b.bin", dir)});
+        let result = CompareFilesTool.execute(&args).await.unwrap();
+        assert!(result.starts_with("Binary files differ"));
+        assert!(result.contains("3 bytes, sha256 ae4b3280e56e2faf83f414a6e3dabe9d5fbe18976544c05fed121accb85b53fc"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(sha256(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+}
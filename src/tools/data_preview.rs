@@ -0,0 +1,472 @@
+use crate::tools::types::{FunctionDefinition, ParametersSchema, ToolContext, ToolDefinition, ToolFuture, ToolImpl};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use tokio::task;
+
+const DEFAULT_ROWS: usize = 5;
+const MAX_ROWS: usize = 50;
+const DEFAULT_COLUMNS: usize = 20;
+const MAX_COLUMNS: usize = 100;
+
+/// Longest cell shown in samples (in characters)
+const MAX_CELL_CHARS: usize = 40;
+
+/// DataPreview tool: schema, row count and head/tail samples of tabular
+/// files without dumping their contents into the context
+pub struct DataPreviewTool;
+
+/// Column type inferred from CSV values
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CsvType {
+    Empty,
+    Boolean,
+    Integer,
+    Float,
+    String,
+}
+
+impl CsvType {
+    fn of(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            CsvType::Empty
+        } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+            CsvType::Boolean
+        } else if value.parse::<i64>().is_ok() {
+            CsvType::Integer
+        } else if value.parse::<f64>().is_ok() {
+            CsvType::Float
+        } else {
+            CsvType::String
+        }
+    }
+
+    /// Narrowest type that holds both
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (CsvType::Empty, other) | (other, CsvType::Empty) => other,
+            (a, b) if a == b => a,
+            (CsvType::Integer, CsvType::Float) | (CsvType::Float, CsvType::Integer) => CsvType::Float,
+            _ => CsvType::String,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CsvType::Empty => "empty",
+            CsvType::Boolean => "boolean",
+            CsvType::Integer => "integer",
+            CsvType::Float => "float",
+            CsvType::String => "string",
+        }
+    }
+}
+
+struct Preview {
+    format: &'static str,
+    rows: u64,
+    /// Column name and type description
+    columns: Vec<(String, String)>,
+    head: Vec<Vec<String>>,
+    tail: Vec<Vec<String>>,
+}
+
+fn truncate(cell: &str) -> String {
+    let cell = cell.replace(['\n', '\r'], " ");
+    match cell.char_indices().nth(MAX_CELL_CHARS) {
+        Some((end, _)) => format!("{}…", &cell[..end]),
+        None => cell,
+    }
+}
+
+fn preview_csv(path: &Path, delimiter: u8, rows: usize, max_columns: usize) -> Result<Preview, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| format!("Failed to read header: {}", e))?
+        .iter()
+        .map(|header| header.to_string())
+        .collect();
+
+    let shown = headers.len().min(max_columns);
+    let mut types = vec![CsvType::Empty; shown];
+    let mut empty = vec![0u64; shown];
+    let mut head = Vec::new();
+    let mut tail = VecDeque::new();
+    let mut count = 0u64;
+
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Invalid row {}: {}", count + 2, e))?;
+        let cells: Vec<&str> = (0..shown).map(|index| record.get(index).unwrap_or_default()).collect();
+        for (index, cell) in cells.iter().enumerate() {
+            let kind = CsvType::of(cell);
+            if kind == CsvType::Empty {
+                empty[index] += 1;
+            }
+            types[index] = types[index].merge(kind);
+        }
+
+        let row: Vec<String> = cells.iter().map(|cell| truncate(cell)).collect();
+        if head.len() < rows {
+            head.push(row);
+        } else {
+            tail.push_back(row);
+            if tail.len() > rows {
+                tail.pop_front();
+            }
+        }
+        count += 1;
+    }
+
+    let columns = headers
+        .iter()
+        .take(shown)
+        .enumerate()
+        .map(|(index, name)| {
+            let mut kind = types[index].name().to_string();
+            if empty[index] > 0 && types[index] != CsvType::Empty {
+                kind.push_str(&format!(" ({} empty)", empty[index]));
+            }
+            (name.clone(), kind)
+        })
+        .collect();
+
+    Ok(Preview {
+        format: if delimiter == b'\t' { "TSV" } else { "CSV" },
+        rows: count,
+        columns: with_hidden(columns, headers.len()),
+        head,
+        tail: tail.into(),
+    })
+}
+
+/// Note how many columns were left out
+fn with_hidden(mut columns: Vec<(String, String)>, total: usize) -> Vec<(String, String)> {
+    if total > columns.len() {
+        columns.push((format!("… {} more", total - columns.len()), String::new()));
+    }
+    columns
+}
+
+fn preview_parquet(path: &Path, rows: usize, max_columns: usize) -> Result<Preview, String> {
+    let reader = SerializedFileReader::try_from(path)
+        .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let metadata = reader.metadata();
+    let total = metadata.file_metadata().num_rows().max(0) as u64;
+
+    let schema = metadata.file_metadata().schema_descr();
+    let descriptors = schema.columns();
+    let columns: Vec<(String, String)> = descriptors
+        .iter()
+        .take(max_columns)
+        .map(|column| {
+            let mut kind = column.physical_type().to_string();
+            if let Some(logical) = column.logical_type_ref() {
+                kind = format!("{} ({:?})", kind, logical);
+            }
+            if column.self_type().is_optional() {
+                kind.push_str(", nullable");
+            }
+            (column.path().string(), kind)
+        })
+        .collect();
+    let shown = columns.len();
+
+    let row_cells = |row: parquet::record::Row| -> Vec<String> {
+        row.get_column_iter()
+            .take(shown)
+            .map(|(_, field)| truncate(&field.to_string()))
+            .collect()
+    };
+
+    let mut head = Vec::new();
+    for row in reader.get_row_iter(None).map_err(|e| e.to_string())?.take(rows) {
+        head.push(row_cells(row.map_err(|e| e.to_string())?));
+    }
+
+    // 只读取包含末尾几行的行组，而不是遍历整个文件
+    let wanted = (total as usize).saturating_sub(head.len()).min(rows);
+    let mut tail = VecDeque::new();
+    let mut group = reader.num_row_groups();
+    while tail.len() < wanted && group > 0 {
+        group -= 1;
+        // 跳过行组前面不需要的行，只转换末尾的几行
+        let needed = wanted - tail.len();
+        let group_rows = reader.metadata().row_group(group).num_rows().max(0) as usize;
+        let group_reader = reader.get_row_group(group).map_err(|e| e.to_string())?;
+        let last_rows: Vec<Vec<String>> = group_reader
+            .get_row_iter(None)
+            .map_err(|e| e.to_string())?
+            .skip(group_rows.saturating_sub(needed))
+            .take(needed)
+            .map(|row| row.map(row_cells).map_err(|e| e.to_string()))
+            .collect::<Result<_, _>>()?;
+        for row in last_rows.into_iter().rev() {
+            tail.push_front(row);
+        }
+    }
+
+    Ok(Preview {
+        format: "Parquet",
+        rows: total,
+        columns: with_hidden(columns, descriptors.len()),
+        head,
+        tail: tail.into(),
+    })
+}
+
+fn render_rows(out: &mut String, title: &str, header: &[&str], rows: &[Vec<String>]) {
+    if rows.is_empty() {
+        return;
+    }
+    out.push_str(&format!("\n{} ({} rows):\n", title, rows.len()));
+    out.push_str(&header.join(" | "));
+    out.push('\n');
+    for row in rows {
+        out.push_str(&row.join(" | "));
+        out.push('\n');
+    }
+}
+
+fn render(display: &str, size: u64, preview: &Preview) -> String {
+    let mut out = format!(
+        "File: {} ({}, {} bytes)\nRows: {}\nColumns: {}\n",
+        display,
+        preview.format,
+        size,
+        preview.rows,
+        preview.columns.iter().filter(|(_, kind)| !kind.is_empty()).count()
+    );
+    for (name, kind) in &preview.columns {
+        out.push_str(&format!("  {:<24} {}\n", name, kind));
+    }
+
+    let header: Vec<&str> = preview
+        .columns
+        .iter()
+        .filter(|(_, kind)| !kind.is_empty())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    render_rows(&mut out, "Head", &header, &preview.head);
+    render_rows(&mut out, "Tail", &header, &preview.tail);
+    out
+}
+
+impl ToolImpl for DataPreviewTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "file_path".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Path to a .csv, .tsv or .parquet file"
+            }),
+        );
+        properties.insert(
+            "rows".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Rows to show from the head and from the tail (default {}, max {})", DEFAULT_ROWS, MAX_ROWS)
+            }),
+        );
+        properties.insert(
+            "max_columns".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Columns to show (default {}, max {})", DEFAULT_COLUMNS, MAX_COLUMNS)
+            }),
+        );
+        properties.insert(
+            "delimiter".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Field delimiter for delimited text files (default: ',' for .csv, tab for .tsv)"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "data_preview".to_string(),
+                description: "Preview a CSV, TSV or Parquet file: column names and types, row count, and the first and last rows. Use this instead of reading data files, which can be huge.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["file_path".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let file_path = arguments
+                .get("file_path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'file_path' argument".to_string())?;
+            let path: PathBuf = context.workspace.resolve(file_path);
+            context.workspace.check_scope(&path)?;
+
+            let rows = arguments
+                .get("rows")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_ROWS, |rows| (rows as usize).min(MAX_ROWS));
+            let max_columns = arguments
+                .get("max_columns")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_COLUMNS, |columns| (columns as usize).clamp(1, MAX_COLUMNS));
+
+            let extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default()
+                .to_lowercase();
+            let delimiter = match arguments.get("delimiter").and_then(|v| v.as_str()) {
+                Some("\\t") | Some("tab") => b'\t',
+                Some(delimiter) if delimiter.len() == 1 => delimiter.as_bytes()[0],
+                Some(delimiter) => return Err(format!("Delimiter must be a single character, got '{}'", delimiter)),
+                None if extension == "tsv" => b'\t',
+                None => b',',
+            };
+
+            let size = std::fs::metadata(&path)
+                .map_err(|e| format!("Failed to open '{}': {}", file_path, e))?
+                .len();
+            let display = context.workspace.display_path(&path);
+
+            task::spawn_blocking(move || {
+                let preview = match extension.as_str() {
+                    "parquet" | "pq" => preview_parquet(&path, rows, max_columns)?,
+                    "csv" | "tsv" | "txt" => preview_csv(&path, delimiter, rows, max_columns)?,
+                    _ => return Err(format!("Unsupported file type '{}'; expected .csv, .tsv or .parquet", extension)),
+                };
+                Ok(render(&display, size, &preview))
+            })
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_preview_csv() {
+        let file = "/tmp/test_data_preview.csv";
+        let mut content = "id,name,price,note\n".to_string();
+        for i in 1..=100 {
+            let note = if i % 10 == 0 { "" } else { "ok" };
+            content.push_str(&format!("{},item {},{}.5,{}\n", i, i, i, note));
+        }
+        std::fs::write(file, content).unwrap();
+
+        let tool = DataPreviewTool;
+        let result = tool
+            .execute(&serde_json::json!({"file_path": file, "rows": 2, "max_columns": 3}))
+            .await
+            .unwrap();
+        assert!(result.contains("(CSV, "));
+        assert!(result.contains("Rows: 100\n"));
+        assert!(result.contains("Columns: 3\n"));
+        assert!(result.contains("  id                       integer\n"));
+        assert!(result.contains("  price                    float\n"));
+        assert!(result.contains("… 1 more"));
+        assert!(result.contains("Head (2 rows):\nid | name | price\n1 | item 1 | 1.5\n2 | item 2 | 2.5\n"));
+        assert!(result.contains("Tail (2 rows):\nid | name | price\n99 | item 99 | 99.5\n100 | item 100 | 100.5\n"));
+        assert!(!result.contains("item 50"));
+
+        std::fs::remove_file(file).ok();
+    }
+
+    #[tokio::test]
+    async fn test_preview_tsv_with_empty_values() {
+        let file = "/tmp/test_data_preview.tsv";
+        std::fs::write(file, "a\tb\n1\tx\n\ty\n").unwrap();
+
+        let result = DataPreviewTool
+            .execute(&serde_json::json!({"file_path": file}))
+            .await
+            .unwrap();
+        assert!(result.contains("(TSV, "));
+        assert!(result.contains("integer (1 empty)"));
+        assert!(result.contains("Head (2 rows)"));
+        assert!(!result.contains("Tail"));
+
+        std::fs::remove_file(file).ok();
+    }
+
+    #[tokio::test]
+    async fn test_preview_parquet() {
+        let file = "/tmp/test_data_preview.parquet";
+        let schema = Arc::new(
+            parse_message_type("message schema { REQUIRED INT64 id; OPTIONAL BYTE_ARRAY name (UTF8); }").unwrap(),
+        );
+        let mut writer = SerializedFileWriter::new(
+            std::fs::File::create(file).unwrap(),
+            schema,
+            Arc::new(WriterProperties::builder().build()),
+        )
+        .unwrap();
+        // 两个行组，末尾几行来自第二个
+        for start in [0i64, 10] {
+            let ids: Vec<i64> = (start..start + 10).collect();
+            let names: Vec<ByteArray> = ids.iter().map(|id| ByteArray::from(format!("row {}", id).as_str())).collect();
+            let mut group = writer.next_row_group().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            column.typed::<Int64Type>().write_batch(&ids, None, None).unwrap();
+            column.close().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&names, Some(&[1; 10]), None)
+                .unwrap();
+            column.close().unwrap();
+            group.close().unwrap();
+        }
+        writer.close().unwrap();
+
+        let result = DataPreviewTool
+            .execute(&serde_json::json!({"file_path": file, "rows": 2}))
+            .await
+            .unwrap();
+        assert!(result.contains("(Parquet, "));
+        assert!(result.contains("Rows: 20\n"));
+        assert!(result.contains("  id                       INT64\n"));
+        assert!(result.contains("nullable"));
+        assert!(result.contains("Head (2 rows):\nid | name\n0 | \"row 0\"\n1 | \"row 1\"\n"));
+        assert!(result.contains("Tail (2 rows):\nid | name\n18 | \"row 18\"\n19 | \"row 19\"\n"));
+
+        std::fs::remove_file(file).ok();
+    }
+
+    #[tokio::test]
+    async fn test_unsupported() {
+        let result = DataPreviewTool
+            .execute(&serde_json::json!({"file_path": "Cargo.toml"}))
+            .await;
+        assert!(result.unwrap_err().contains("Unsupported file type"));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::tools::trash::Trash;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
+use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use serde_json::Value;
+
+/// Delete tool that moves files and directories to the trash instead of
+/// removing them, so `/undo` can bring them back
+pub struct DeleteTool;
+
+impl ToolImpl for DeleteTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "path".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "The file or directory to delete"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "delete".to_string(),
+                description: "Delete a file or directory by moving it to .ariste/trash/, where the user can restore it with /undo. Use this instead of `rm` in bash.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["path".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let path = arguments
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'path' argument".to_string())?;
+            let resolved = context.workspace.resolve(path);
+            context.workspace.check_scope(&resolved)?;
+
+            if !resolved.exists() {
+                return Err(format!("Path '{}' does not exist", path));
+            }
+            let trash = Trash::new(&context.workspace);
+            if trash.protects(&resolved) {
+                return Err(format!("Refusing to delete '{}': it is or contains the trash", path));
+            }
+
+            let trashed = trash.delete(&resolved)?;
+            Ok(format!(
+                "Deleted {} (moved to {}; /undo restores it)",
+                path,
+                context.workspace.display_path(&trashed)
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::{Workspace, WorkspaceRoot};
+
+    #[tokio::test]
+    async fn test_delete_moves_to_trash() {
+        let dir = "/tmp/test_delete_tool";
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(format!("{}/build", dir)).unwrap();
+        std::fs::write(format!("{}/build/out.o", dir), "obj").unwrap();
+        let workspace = Workspace::new(vec![WorkspaceRoot::new("test", dir)]).unwrap();
+        let context = ToolContext::new(workspace.clone());
+
+        let result = DeleteTool
+            .execute_with_context(&context, &serde_json::json!({"path": "build"}))
+            .await
+            .unwrap();
+        assert!(result.starts_with("Deleted build"));
+        assert!(!std::path::Path::new(dir).join("build").exists());
+
+        // The workspace root and the trash itself are off limits
+        for path in [".", ".ariste", ".ariste/trash"] {
+            let error = DeleteTool
+                .execute_with_context(&context, &serde_json::json!({"path": path}))
+                .await
+                .unwrap_err();
+            assert!(error.contains("contains the trash"), "{}", path);
+        }
+
+        Trash::new(&workspace).undo().unwrap();
+        assert!(std::path::Path::new(dir).join("build/out.o").exists());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_delete_missing_path() {
+        let result = DeleteTool
+            .execute(&serde_json::json!({"path": "/tmp/test_delete_tool_missing"}))
+            .await;
+        assert!(result.unwrap_err().contains("does not exist"));
+    }
+}
@@ -0,0 +1,645 @@
+use crate::tools::types::{FunctionDefinition, ParametersSchema, ToolContext, ToolDefinition, ToolFuture, ToolImpl};
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tokio::task;
+
+/// Registry lookups in flight at once
+const MAX_CONCURRENT_LOOKUPS: usize = 8;
+
+/// crates.io rejects requests without a user agent
+const USER_AGENT: &str = "ariste (https://github.com/killf/ariste)";
+
+/// Deps tool: reads dependency manifests, looks up the latest published
+/// versions and optionally runs the ecosystem's audit tool
+pub struct DepsTool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Python,
+}
+
+impl Ecosystem {
+    const ALL: [Ecosystem; 3] = [Ecosystem::Cargo, Ecosystem::Npm, Ecosystem::Python];
+
+    fn manifest(&self) -> &'static str {
+        match self {
+            Ecosystem::Cargo => "Cargo.toml",
+            Ecosystem::Npm => "package.json",
+            Ecosystem::Python => "pyproject.toml",
+        }
+    }
+
+    fn from_manifest(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        Self::ALL.into_iter().find(|ecosystem| ecosystem.manifest() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dependency {
+    pub name: String,
+    /// Version requirement as written, or `path:`/`git:`/`workspace` for
+    /// dependencies that don't come from a registry
+    pub requirement: String,
+    /// `normal`, `dev`, `build`, `peer`, `optional` or a dependency group
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup_error: Option<String>,
+}
+
+impl Dependency {
+    fn new(name: &str, requirement: impl Into<String>, kind: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            requirement: requirement.into(),
+            kind: kind.to_string(),
+            latest: None,
+            lookup_error: None,
+        }
+    }
+
+    fn is_registry(&self) -> bool {
+        !(self.requirement.starts_with("path:")
+            || self.requirement.starts_with("git:")
+            || self.requirement == "workspace")
+    }
+}
+
+/// A known vulnerability reported by the audit tool
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Advisory {
+    pub package: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    /// Versions (or ranges) that fix the advisory
+    pub fixed_in: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestReport {
+    manifest: String,
+    ecosystem: Ecosystem,
+    dependencies: Vec<Dependency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    advisories: Option<Vec<Advisory>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audit_error: Option<String>,
+}
+
+impl ToolImpl for DepsTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "path".to_string(),
+            json!({
+                "type": "string",
+                "description": "A Cargo.toml, package.json or pyproject.toml, or a directory containing them. Defaults to the workspace root."
+            }),
+        );
+        properties.insert(
+            "latest".to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Look up the latest published version of each dependency (default: true)"
+            }),
+        );
+        properties.insert(
+            "audit".to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Run cargo-audit, npm audit or pip-audit and report known vulnerabilities (default: false)"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "deps".to_string(),
+                description: "Report a project's dependencies from Cargo.toml, package.json or pyproject.toml as JSON: the declared requirement, the latest published version and, with audit, known security advisories. Use this instead of parsing manifests or audit output with bash.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let workspace = &context.workspace;
+            let path = match arguments.get("path").and_then(|v| v.as_str()) {
+                Some(path) => workspace.resolve(path),
+                None => workspace
+                    .search_paths()
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| workspace.primary().path.clone()),
+            };
+            workspace.check_scope(&path)?;
+            let latest = arguments.get("latest").and_then(|v| v.as_bool()).unwrap_or(true);
+            let audit = arguments.get("audit").and_then(|v| v.as_bool()).unwrap_or(false);
+
+            let manifests = find_manifests(&path)?;
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .user_agent(USER_AGENT)
+                .build()
+                .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+            let mut reports = Vec::new();
+            for (manifest, ecosystem) in manifests {
+                let text = tokio::fs::read_to_string(&manifest)
+                    .await
+                    .map_err(|e| format!("Failed to read {}: {}", manifest.display(), e))?;
+                let mut dependencies = parse_manifest(ecosystem, &text)?;
+                if latest {
+                    lookup_latest(&client, ecosystem, &mut dependencies).await;
+                }
+
+                let (advisories, audit_error) = if audit {
+                    let dir = manifest.parent().map(Path::to_path_buf).unwrap_or_default();
+                    match run_audit(ecosystem, dir).await {
+                        Ok(advisories) => (Some(advisories), None),
+                        Err(e) => (None, Some(e)),
+                    }
+                } else {
+                    (None, None)
+                };
+
+                reports.push(ManifestReport {
+                    manifest: workspace.display_path(&manifest),
+                    ecosystem,
+                    dependencies,
+                    advisories,
+                    audit_error,
+                });
+            }
+
+            serde_json::to_string_pretty(&json!({ "manifests": reports }))
+                .map_err(|e| format!("Failed to serialize report: {}", e))
+        })
+    }
+}
+
+/// The manifest at `path`, or every known manifest directly inside it
+fn find_manifests(path: &Path) -> Result<Vec<(PathBuf, Ecosystem)>, String> {
+    if path.is_file() {
+        let ecosystem = Ecosystem::from_manifest(path).ok_or_else(|| {
+            format!(
+                "{} is not a Cargo.toml, package.json or pyproject.toml",
+                path.display()
+            )
+        })?;
+        return Ok(vec![(path.to_path_buf(), ecosystem)]);
+    }
+
+    let manifests: Vec<_> = Ecosystem::ALL
+        .into_iter()
+        .map(|ecosystem| (path.join(ecosystem.manifest()), ecosystem))
+        .filter(|(manifest, _)| manifest.is_file())
+        .collect();
+    if manifests.is_empty() {
+        return Err(format!("No dependency manifest found in {}", path.display()));
+    }
+    Ok(manifests)
+}
+
+pub fn parse_manifest(ecosystem: Ecosystem, text: &str) -> Result<Vec<Dependency>, String> {
+    match ecosystem {
+        Ecosystem::Cargo => parse_cargo(text),
+        Ecosystem::Npm => parse_package_json(text),
+        Ecosystem::Python => parse_pyproject(text),
+    }
+}
+
+fn parse_cargo(text: &str) -> Result<Vec<Dependency>, String> {
+    let manifest: toml::Table = text.parse().map_err(|e| format!("Invalid Cargo.toml: {}", e))?;
+
+    let mut dependencies = Vec::new();
+    let mut collect = |table: Option<&toml::Value>, kind: &str| {
+        for (name, spec) in table.and_then(|t| t.as_table()).into_iter().flatten() {
+            let requirement = match spec {
+                toml::Value::String(version) => version.clone(),
+                toml::Value::Table(spec) => {
+                    if let Some(version) = spec.get("version").and_then(|v| v.as_str()) {
+                        version.to_string()
+                    } else if let Some(path) = spec.get("path").and_then(|v| v.as_str()) {
+                        format!("path:{}", path)
+                    } else if let Some(git) = spec.get("git").and_then(|v| v.as_str()) {
+                        format!("git:{}", git)
+                    } else if spec.get("workspace").and_then(|v| v.as_bool()) == Some(true) {
+                        "workspace".to_string()
+                    } else {
+                        "*".to_string()
+                    }
+                }
+                _ => continue,
+            };
+            // `package = "..."` 重命名时，注册表中的名字是 package
+            let name = match spec.get("package").and_then(|v| v.as_str()) {
+                Some(package) => package,
+                None => name,
+            };
+            dependencies.push(Dependency::new(name, requirement, kind));
+        }
+    };
+
+    collect(manifest.get("dependencies"), "normal");
+    collect(manifest.get("dev-dependencies"), "dev");
+    collect(manifest.get("build-dependencies"), "build");
+    collect(
+        manifest.get("workspace").and_then(|w| w.get("dependencies")),
+        "workspace",
+    );
+    Ok(dependencies)
+}
+
+fn parse_package_json(text: &str) -> Result<Vec<Dependency>, String> {
+    let manifest: Value = serde_json::from_str(text).map_err(|e| format!("Invalid package.json: {}", e))?;
+
+    let mut dependencies = Vec::new();
+    for (section, kind) in [
+        ("dependencies", "normal"),
+        ("devDependencies", "dev"),
+        ("peerDependencies", "peer"),
+        ("optionalDependencies", "optional"),
+    ] {
+        for (name, requirement) in manifest.get(section).and_then(|v| v.as_object()).into_iter().flatten() {
+            let requirement = requirement.as_str().unwrap_or("*");
+            let requirement = match requirement.split_once(':') {
+                Some(("file" | "link", path)) => format!("path:{}", path),
+                Some(("git" | "git+https" | "git+ssh" | "github", _)) => format!("git:{}", requirement),
+                Some(("workspace", _)) => "workspace".to_string(),
+                _ => requirement.to_string(),
+            };
+            dependencies.push(Dependency::new(name, requirement, kind));
+        }
+    }
+    Ok(dependencies)
+}
+
+fn parse_pyproject(text: &str) -> Result<Vec<Dependency>, String> {
+    let manifest: toml::Table = text.parse().map_err(|e| format!("Invalid pyproject.toml: {}", e))?;
+
+    let mut dependencies = Vec::new();
+
+    // PEP 621: [project] dependencies 是 PEP 508 字符串
+    if let Some(project) = manifest.get("project") {
+        let requirements = project.get("dependencies").and_then(|v| v.as_array());
+        for requirement in requirements.into_iter().flatten().filter_map(|v| v.as_str()) {
+            dependencies.push(pep508(requirement, "normal"));
+        }
+        let optional = project.get("optional-dependencies").and_then(|v| v.as_table());
+        for (group, requirements) in optional.into_iter().flatten() {
+            for requirement in requirements.as_array().into_iter().flatten().filter_map(|v| v.as_str()) {
+                dependencies.push(pep508(requirement, group));
+            }
+        }
+    }
+
+    // Poetry: 表格形式，python 本身不是依赖
+    if let Some(poetry) = manifest.get("tool").and_then(|t| t.get("poetry")) {
+        let mut collect = |table: Option<&toml::Value>, kind: &str| {
+            for (name, spec) in table.and_then(|t| t.as_table()).into_iter().flatten() {
+                if name == "python" {
+                    continue;
+                }
+                let requirement = match spec {
+                    toml::Value::String(version) => version.clone(),
+                    toml::Value::Table(spec) => {
+                        if let Some(version) = spec.get("version").and_then(|v| v.as_str()) {
+                            version.to_string()
+                        } else if let Some(path) = spec.get("path").and_then(|v| v.as_str()) {
+                            format!("path:{}", path)
+                        } else if let Some(git) = spec.get("git").and_then(|v| v.as_str()) {
+                            format!("git:{}", git)
+                        } else {
+                            "*".to_string()
+                        }
+                    }
+                    _ => continue,
+                };
+                dependencies.push(Dependency::new(name, requirement, kind));
+            }
+        };
+        collect(poetry.get("dependencies"), "normal");
+        collect(poetry.get("dev-dependencies"), "dev");
+        let groups = poetry.get("group").and_then(|g| g.as_table());
+        for (group, table) in groups.into_iter().flatten() {
+            collect(table.get("dependencies"), group);
+        }
+    }
+
+    Ok(dependencies)
+}
+
+/// Split a PEP 508 requirement such as `requests[socks]>=2.31; python_version>"3.8"`
+fn pep508(requirement: &str, kind: &str) -> Dependency {
+    let requirement = requirement.split(';').next().unwrap_or_default().trim();
+    let end = requirement
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..end];
+    let mut rest = requirement[end..].trim_start();
+    if rest.starts_with('[')
+        && let Some(close) = rest.find(']')
+    {
+        rest = rest[close + 1..].trim_start();
+    }
+    let version = match rest.strip_prefix('@') {
+        Some(url) if url.trim().starts_with("git+") => format!("git:{}", url.trim()),
+        Some(url) => format!("path:{}", url.trim()),
+        None if rest.is_empty() => "*".to_string(),
+        None => rest.trim_start_matches('(').trim_end_matches(')').trim().to_string(),
+    };
+    Dependency::new(name, version, kind)
+}
+
+/// Fill in `latest` (or `lookup_error`) for every registry dependency
+async fn lookup_latest(client: &reqwest::Client, ecosystem: Ecosystem, dependencies: &mut [Dependency]) {
+    let names: Vec<(usize, String)> = dependencies
+        .iter()
+        .enumerate()
+        .filter(|(_, dependency)| dependency.is_registry())
+        .map(|(index, dependency)| (index, dependency.name.clone()))
+        .collect();
+
+    let results: Vec<(usize, Result<String, String>)> = futures_util::stream::iter(names)
+        .map(|(index, name)| async move { (index, latest_version(client, ecosystem, &name).await) })
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+
+    for (index, result) in results {
+        match result {
+            Ok(version) => dependencies[index].latest = Some(version),
+            Err(e) => dependencies[index].lookup_error = Some(e),
+        }
+    }
+}
+
+async fn latest_version(client: &reqwest::Client, ecosystem: Ecosystem, name: &str) -> Result<String, String> {
+    let (url, pointer) = match ecosystem {
+        Ecosystem::Cargo => (format!("https://crates.io/api/v1/crates/{}", name), "/crate/max_stable_version"),
+        Ecosystem::Npm => (format!("https://registry.npmjs.org/{}/latest", name), "/version"),
+        Ecosystem::Python => (format!("https://pypi.org/pypi/{}/json", name), "/info/version"),
+    };
+
+    let response = client.get(&url).send().await.map_err(|e| format!("Request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Registry returned {}", response.status()));
+    }
+    let body: Value = response.json().await.map_err(|e| format!("Invalid registry response: {}", e))?;
+    body.pointer(pointer)
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+        .ok_or_else(|| "No published version".to_string())
+}
+
+/// Run the ecosystem's audit tool in `dir` and collect its advisories
+async fn run_audit(ecosystem: Ecosystem, dir: PathBuf) -> Result<Vec<Advisory>, String> {
+    let (program, args, install): (&str, &[&str], &str) = match ecosystem {
+        Ecosystem::Cargo => ("cargo", &["audit", "--json"], "cargo install cargo-audit"),
+        Ecosystem::Npm => ("npm", &["audit", "--json"], "install Node.js"),
+        Ecosystem::Python => ("pip-audit", &["--format", "json", "."], "pip install pip-audit"),
+    };
+
+    let output = task::spawn_blocking(move || Command::new(program).args(args).current_dir(dir).output())
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| format!("Failed to run {} ({}): {}", program, install, e))?;
+
+    // 发现漏洞时审计工具以非零状态退出，但仍输出 JSON
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let report: Value = serde_json::from_str(stdout.trim()).map_err(|_| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        format!("{} {} failed: {}", program, args.join(" "), stderr.trim())
+    })?;
+
+    Ok(match ecosystem {
+        Ecosystem::Cargo => cargo_advisories(&report),
+        Ecosystem::Npm => npm_advisories(&report),
+        Ecosystem::Python => pip_advisories(&report),
+    })
+}
+
+fn str_field(value: &Value, pointer: &str) -> Option<String> {
+    value.pointer(pointer).and_then(|v| v.as_str()).map(|v| v.to_string())
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(|v| v.as_str()).map(|v| v.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn cargo_advisories(report: &Value) -> Vec<Advisory> {
+    let list = report.pointer("/vulnerabilities/list").and_then(|v| v.as_array());
+    list.into_iter()
+        .flatten()
+        .map(|entry| Advisory {
+            package: str_field(entry, "/package/name").unwrap_or_default(),
+            version: str_field(entry, "/package/version"),
+            id: str_field(entry, "/advisory/id").unwrap_or_default(),
+            title: str_field(entry, "/advisory/title").unwrap_or_default(),
+            severity: str_field(entry, "/advisory/cvss"),
+            fixed_in: strings(entry.pointer("/versions/patched")),
+        })
+        .collect()
+}
+
+fn npm_advisories(report: &Value) -> Vec<Advisory> {
+    let vulnerabilities = report.get("vulnerabilities").and_then(|v| v.as_object());
+    let mut advisories = Vec::new();
+    for (package, entry) in vulnerabilities.into_iter().flatten() {
+        let fixed_in = match entry.get("fixAvailable") {
+            Some(Value::Object(fix)) => str_field(&Value::Object(fix.clone()), "/version").into_iter().collect(),
+            _ => Vec::new(),
+        };
+        // via 中的字符串指向传递依赖，对象才是实际的公告
+        for via in entry.get("via").and_then(|v| v.as_array()).into_iter().flatten() {
+            if !via.is_object() {
+                continue;
+            }
+            advisories.push(Advisory {
+                package: package.clone(),
+                version: str_field(via, "/range"),
+                id: str_field(via, "/url").unwrap_or_default(),
+                title: str_field(via, "/title").unwrap_or_default(),
+                severity: str_field(via, "/severity").or_else(|| str_field(entry, "/severity")),
+                fixed_in: fixed_in.clone(),
+            });
+        }
+    }
+    advisories
+}
+
+fn pip_advisories(report: &Value) -> Vec<Advisory> {
+    // pip-audit 旧版本直接输出数组
+    let dependencies = report
+        .get("dependencies")
+        .unwrap_or(report)
+        .as_array();
+    let mut advisories = Vec::new();
+    for dependency in dependencies.into_iter().flatten() {
+        for vuln in dependency.get("vulns").and_then(|v| v.as_array()).into_iter().flatten() {
+            advisories.push(Advisory {
+                package: str_field(dependency, "/name").unwrap_or_default(),
+                version: str_field(dependency, "/version"),
+                id: str_field(vuln, "/id").unwrap_or_default(),
+                title: str_field(vuln, "/description").unwrap_or_default(),
+                severity: None,
+                fixed_in: strings(vuln.get("fix_versions")),
+            });
+        }
+    }
+    advisories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cargo() {
+        let text = r#"
+[package]
+name = "demo"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+tokio = "1"
+local = { path = "../local" }
+yaml = { package = "serde_yaml", version = "0.9" }
+
+[dev-dependencies]
+tempfile = "3"
+
+[build-dependencies]
+cc = { git = "https://github.com/rust-lang/cc-rs" }
+"#;
+        let deps = parse_manifest(Ecosystem::Cargo, text).unwrap();
+        assert_eq!(deps.len(), 6);
+        assert!(deps.contains(&Dependency::new("serde", "1.0", "normal")));
+        assert!(deps.contains(&Dependency::new("serde_yaml", "0.9", "normal")));
+        assert!(deps.contains(&Dependency::new("local", "path:../local", "normal")));
+        assert!(deps.contains(&Dependency::new("tempfile", "3", "dev")));
+        assert!(deps.contains(&Dependency::new("cc", "git:https://github.com/rust-lang/cc-rs", "build")));
+
+        assert!(parse_manifest(Ecosystem::Cargo, "not = [toml").is_err());
+    }
+
+    #[test]
+    fn test_parse_package_json() {
+        let text = r#"{
+            "dependencies": {"react": "^18.2.0", "shared": "workspace:*"},
+            "devDependencies": {"vitest": "~1.0.0", "utils": "file:../utils"}
+        }"#;
+        let deps = parse_manifest(Ecosystem::Npm, text).unwrap();
+        assert!(deps.contains(&Dependency::new("react", "^18.2.0", "normal")));
+        assert!(deps.contains(&Dependency::new("shared", "workspace", "normal")));
+        assert!(deps.contains(&Dependency::new("vitest", "~1.0.0", "dev")));
+        assert!(deps.contains(&Dependency::new("utils", "path:../utils", "dev")));
+    }
+
+    #[test]
+    fn test_parse_pyproject() {
+        let text = r#"
+[project]
+dependencies = ["requests[socks]>=2.31; python_version > '3.8'", "click"]
+
+[project.optional-dependencies]
+test = ["pytest (>=7)"]
+
+[tool.poetry.dependencies]
+python = "^3.10"
+httpx = { version = "^0.27" }
+
+[tool.poetry.group.lint.dependencies]
+ruff = "*"
+"#;
+        let deps = parse_manifest(Ecosystem::Python, text).unwrap();
+        assert_eq!(deps.len(), 5);
+        assert!(deps.contains(&Dependency::new("requests", ">=2.31", "normal")));
+        assert!(deps.contains(&Dependency::new("click", "*", "normal")));
+        assert!(deps.contains(&Dependency::new("pytest", ">=7", "test")));
+        assert!(deps.contains(&Dependency::new("httpx", "^0.27", "normal")));
+        assert!(deps.contains(&Dependency::new("ruff", "*", "lint")));
+    }
+
+    #[test]
+    fn test_advisories() {
+        let cargo = json!({"vulnerabilities": {"list": [{
+            "advisory": {"id": "RUSTSEC-2023-0001", "title": "Stack overflow", "cvss": null},
+            "package": {"name": "time", "version": "0.1.45"},
+            "versions": {"patched": [">=0.2.23"]}
+        }]}});
+        let advisories = cargo_advisories(&cargo);
+        assert_eq!(advisories[0].id, "RUSTSEC-2023-0001");
+        assert_eq!(advisories[0].version.as_deref(), Some("0.1.45"));
+        assert_eq!(advisories[0].fixed_in, vec![">=0.2.23"]);
+
+        let npm = json!({"vulnerabilities": {
+            "lodash": {"severity": "high", "via": [{"title": "Prototype Pollution", "url": "https://github.com/advisories/GHSA-1", "range": "<4.17.21"}], "fixAvailable": {"version": "4.17.21"}},
+            "wrapper": {"severity": "high", "via": ["lodash"], "fixAvailable": true}
+        }});
+        let advisories = npm_advisories(&npm);
+        assert_eq!(advisories.len(), 1);
+        assert_eq!(advisories[0].package, "lodash");
+        assert_eq!(advisories[0].severity.as_deref(), Some("high"));
+        assert_eq!(advisories[0].fixed_in, vec!["4.17.21"]);
+
+        let pip = json!({"dependencies": [{"name": "jinja2", "version": "3.1.2", "vulns": [{"id": "GHSA-2", "fix_versions": ["3.1.3"], "description": "XSS"}]}]});
+        assert_eq!(pip_advisories(&pip)[0].fixed_in, vec!["3.1.3"]);
+    }
+
+    #[tokio::test]
+    async fn test_deps_report_without_lookups() {
+        let dir = "/tmp/test_deps_report";
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(format!("{}/Cargo.toml", dir), "[dependencies]\nregex = \"1\"\n").unwrap();
+        std::fs::write(format!("{}/package.json", dir), r#"{"dependencies": {"react": "^18"}}"#).unwrap();
+
+        let tool = DepsTool;
+        let result = tool
+            .execute(&json!({"path": dir, "latest": false}))
+            .await
+            .unwrap();
+        let report: Value = serde_json::from_str(&result).unwrap();
+        let manifests = report["manifests"].as_array().unwrap();
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests[0]["ecosystem"], "cargo");
+        assert_eq!(manifests[0]["dependencies"][0]["name"], "regex");
+        assert_eq!(manifests[1]["dependencies"][0]["requirement"], "^18");
+
+        let missing = tool.execute(&json!({"path": "/tmp/test_deps_report/none"})).await;
+        assert!(missing.is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
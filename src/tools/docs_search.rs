@@ -0,0 +1,126 @@
+use crate::index::KnowledgeBase;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
+use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Most results one call returns, whatever `limit` asks for
+const MAX_RESULTS: usize = 50;
+
+/// Docs search tool: the ingested documentation closest in meaning to a query
+pub struct DocsSearchTool {
+    knowledge: Arc<KnowledgeBase>,
+}
+
+impl DocsSearchTool {
+    pub fn new(knowledge: Arc<KnowledgeBase>) -> Self {
+        Self { knowledge }
+    }
+}
+
+impl ToolImpl for DocsSearchTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "query".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "What to look up, in words, e.g. 'how the payments API paginates results'"
+            }),
+        );
+        properties.insert(
+            "limit".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Sections returned (default {}, at most {})", self.knowledge.default_results(), MAX_RESULTS)
+            }),
+        );
+        properties.insert(
+            "source".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Only search documents from this path or URL prefix, e.g. 'docs/api' or 'https://docs.stripe.com'"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "docs_search".to_string(),
+                description: "Search the documentation ingested with `ariste ingest` (project guides, vendor API docs, PDFs, web pages) by meaning. Returns the closest sections with their source. Use semantic_search for the code itself".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["query".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        _context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let query = arguments
+                .get("query")
+                .and_then(|v| v.as_str())
+                .filter(|query| !query.trim().is_empty())
+                .ok_or_else(|| "Missing 'query' argument".to_string())?;
+            let limit = match arguments.get("limit").and_then(|v| v.as_u64()) {
+                Some(0) => return Err("limit must be greater than 0".to_string()),
+                Some(limit) => limit.min(MAX_RESULTS as u64) as usize,
+                None => self.knowledge.default_results(),
+            };
+            let source = arguments.get("source").and_then(|v| v.as_str());
+
+            let hits = self
+                .knowledge
+                .search(query, limit, source)
+                .await
+                .map_err(|e| format!("Docs search failed: {}", e))?;
+            if hits.is_empty() {
+                return Ok("No ingested documentation matches. Add some with `ariste ingest <path|url>`".to_string());
+            }
+            let sections: Vec<String> = hits
+                .iter()
+                .map(|hit| format!("--- {} (score {:.2}) ---\n{}", hit.source, hit.score, hit.text))
+                .collect();
+            Ok(sections.join("\n\n"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::knowledge::DOCS_INDEX_FILE;
+    use crate::index::semantic::WordEmbedder;
+
+    #[tokio::test]
+    async fn test_docs_search() {
+        let dir = std::env::temp_dir().join("ariste_test_docs_search");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("pagination.md"), "# Pagination\n\nList endpoints return a cursor for the next page.\n").unwrap();
+        let knowledge = Arc::new(KnowledgeBase::new(&dir, dir.join(DOCS_INDEX_FILE), Box::new(WordEmbedder)));
+        let tool = DocsSearchTool::new(knowledge.clone());
+
+        let args = serde_json::json!({"query": "next page cursor"});
+        assert!(tool.execute(&args).await.unwrap().starts_with("No ingested documentation matches"));
+        knowledge.ingest(dir.join("pagination.md").to_str().unwrap()).await.unwrap();
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.starts_with("--- pagination.md:1-3 (score "));
+        assert!(result.contains("cursor for the next page"));
+        assert!(tool.execute(&serde_json::json!({"query": "cursor", "limit": 0})).await.is_err());
+        assert!(tool.execute(&serde_json::json!({"query": "cursor", "limit": u64::MAX})).await.is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,12 +1,32 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::patch::{self, PatchEvent};
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
 use serde_json::Value;
+use similar::TextDiff;
 use tokio::fs;
 use tokio::io::AsyncReadExt;
 
+/// Longest diff shown in the result, in lines
+const MAX_DIFF_LINES: usize = 200;
+
 /// Edit tool for editing file contents
 pub struct EditTool;
 
+/// Unified diff of the edit, cut off after `MAX_DIFF_LINES`
+fn edit_diff(path: &str, original: &str, updated: &str) -> String {
+    let text = TextDiff::from_lines(original, updated)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut diff = lines.iter().take(MAX_DIFF_LINES).copied().collect::<Vec<_>>().join("\n");
+    if lines.len() > MAX_DIFF_LINES {
+        diff.push_str(&format!("\n... {} more diff lines omitted", lines.len() - MAX_DIFF_LINES));
+    }
+    diff
+}
+
 impl ToolImpl for EditTool {
     fn definition(&self) -> ToolDefinition {
         let mut properties = serde_json::Map::new();
@@ -35,7 +55,7 @@ impl ToolImpl for EditTool {
             "replace_all".to_string(),
             serde_json::json!({
                 "type": "boolean",
-                "description": "If true, replace all occurrences of old_string. If false (default), only replace the first occurrence."
+                "description": "If true, replace all occurrences of old_string. If false (default), old_string must occur exactly once."
             }),
         );
 
@@ -43,7 +63,7 @@ impl ToolImpl for EditTool {
             r#type: "function".to_string(),
             function: FunctionDefinition {
                 name: "edit".to_string(),
-                description: "Edit a file by replacing text. Reads the file, replaces occurrences of old_string with new_string, and writes it back. Preserves the original file encoding and line endings.".to_string(),
+                description: "Edit a file by replacing text. Reads the file, replaces old_string with new_string, and writes it back. Unless replace_all is set, old_string must match exactly one place in the file; include surrounding lines to make it unique. Returns a unified diff of the change. Preserves the original file encoding and line endings.".to_string(),
                 parameters: ParametersSchema {
                     r#type: "object".to_string(),
                     properties,
@@ -53,70 +73,97 @@ impl ToolImpl for EditTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let file_path = arguments
-            .get("file_path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'file_path' argument".to_string())?;
-
-        let old_string = arguments
-            .get("old_string")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'old_string' argument".to_string())?;
-
-        let new_string = arguments
-            .get("new_string")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'new_string' argument".to_string())?;
-
-        let replace_all = arguments
-            .get("replace_all")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
-
-        // Read the file
-        let mut file = fs::File::open(file_path)
-            .await
-            .map_err(|e| format!("Failed to open file '{}': {}", file_path, e))?;
-
-        let mut contents = Vec::new();
-        file.read_to_end(&mut contents)
-            .await
-            .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
-
-        // Convert to string
-        let original = String::from_utf8_lossy(&contents).to_string();
-
-        // Perform replacement
-        let new_contents = if replace_all {
-            original.replace(old_string, new_string)
-        } else {
-            original.replacen(old_string, new_string, 1)
-        };
-
-        // Check if replacement was made
-        if new_contents == original {
-            return Err(format!(
-                "Old string '{}' not found in file '{}'",
-                old_string, file_path
-            ));
-        }
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
 
-        // Write back to file
-        fs::write(file_path, new_contents)
-            .await
-            .map_err(|e| format!("Failed to write file '{}': {}", file_path, e))?;
-
-        let replacement_type = if replace_all {
-            "all occurrences"
-        } else {
-            "first occurrence"
-        };
-
-        Ok(format!(
-            "Successfully replaced {} of '{}' with '{}' in file '{}'",
-            replacement_type, old_string, new_string, file_path
-        ))
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let file_path = arguments
+                .get("file_path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'file_path' argument".to_string())?;
+            let resolved_path = context.workspace.resolve(file_path);
+            context.workspace.check_workdir(&resolved_path)?;
+
+            let old_string = arguments
+                .get("old_string")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'old_string' argument".to_string())?;
+
+            let new_string = arguments
+                .get("new_string")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'new_string' argument".to_string())?;
+
+            let replace_all = arguments
+                .get("replace_all")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            // Read the file
+            let mut file = fs::File::open(&resolved_path)
+                .await
+                .map_err(|e| format!("Failed to open file '{}': {}", file_path, e))?;
+
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)
+                .await
+                .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
+
+            // Convert to string
+            let original = String::from_utf8_lossy(&contents).to_string();
+
+            let occurrences = if old_string.is_empty() { 0 } else { original.matches(old_string).count() };
+            if occurrences == 0 {
+                return Err(format!(
+                    "Old string '{}' not found in file '{}'",
+                    old_string, file_path
+                ));
+            }
+            // 不确定要改哪一处时不动文件
+            if occurrences > 1 && !replace_all {
+                return Err(format!(
+                    "Old string occurs {} times in file '{}'; include more surrounding lines to pick one, or set replace_all to replace every occurrence",
+                    occurrences, file_path
+                ));
+            }
+
+            // Perform replacement
+            let new_contents = original.replace(old_string, new_string);
+            let diff = edit_diff(file_path, &original, &new_contents);
+
+            // The client applies the change itself
+            if let Some(sink) = &context.patches {
+                let proposed = patch::propose(
+                    sink,
+                    PatchEvent {
+                        path: file_path.to_string(),
+                        original: Some(original),
+                        updated: new_contents,
+                    },
+                )?;
+                return Ok(format!("{}\n{}", proposed, diff));
+            }
+
+            // Write back to file
+            fs::write(&resolved_path, new_contents)
+                .await
+                .map_err(|e| format!("Failed to write file '{}': {}", file_path, e))?;
+
+            let replaced = if occurrences == 1 {
+                "1 occurrence".to_string()
+            } else {
+                format!("{} occurrences", occurrences)
+            };
+            Ok(format!("Successfully replaced {} in file '{}'\n{}", replaced, file_path, diff))
+        })
     }
 }
 
@@ -126,28 +173,41 @@ mod tests {
     use tokio::fs;
 
     #[tokio::test]
-    async fn test_edit_replace_first() {
+    async fn test_edit_requires_unique_match() {
         let tool = EditTool;
 
         // Create test file
         let test_file = "/tmp/test_edit.txt";
-        fs::write(test_file, "Hello World\nHello Rust\nHello Test")
+        fs::write(test_file, "Hello World\nHello Rust\nHello Test\n")
             .await
             .expect("Failed to create test file");
 
+        // Ambiguous: the file is left alone
         let args = serde_json::json!({
             "file_path": test_file,
             "old_string": "Hello",
             "new_string": "Hi",
             "replace_all": false
         });
+        let error = tool.execute(&args).await.unwrap_err();
+        assert!(error.starts_with("Old string occurs 3 times in file"));
+        assert_eq!(fs::read_to_string(test_file).await.unwrap(), "Hello World\nHello Rust\nHello Test\n");
 
-        let result = tool.execute(&args).await;
-        assert!(result.is_ok());
-
-        // Verify only first occurrence was replaced
+        let args = serde_json::json!({
+            "file_path": test_file,
+            "old_string": "Hello Rust",
+            "new_string": "Hi Rust"
+        });
+        let result = tool.execute(&args).await.unwrap();
+        assert_eq!(
+            result,
+            format!(
+                "Successfully replaced 1 occurrence in file '{0}'\n--- a/{0}\n+++ b/{0}\n@@ -1,3 +1,3 @@\n Hello World\n-Hello Rust\n+Hi Rust\n Hello Test",
+                test_file
+            )
+        );
         let contents = fs::read_to_string(test_file).await.unwrap();
-        assert_eq!(contents, "Hi World\nHello Rust\nHello Test");
+        assert_eq!(contents, "Hello World\nHi Rust\nHello Test\n");
 
         // Clean up
         fs::remove_file(test_file).await.ok();
@@ -202,6 +262,32 @@ mod tests {
         fs::remove_file(test_file).await.ok();
     }
 
+    #[tokio::test]
+    async fn test_edit_proposes_patch() {
+        let tool = EditTool;
+        let test_file = "/tmp/test_edit_proposed.txt";
+        fs::write(test_file, "Hello World").await.expect("Failed to create test file");
+
+        let (sink, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let context = ToolContext::default().with_patches(Some(sink));
+        let args = serde_json::json!({
+            "file_path": test_file,
+            "old_string": "World",
+            "new_string": "Rust"
+        });
+
+        let result = tool.execute_with_context(&context, &args).await;
+        assert!(result.is_ok());
+
+        // The file is left for the client to change
+        assert_eq!(fs::read_to_string(test_file).await.unwrap(), "Hello World");
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.original.as_deref(), Some("Hello World"));
+        assert_eq!(event.updated, "Hello Rust");
+
+        fs::remove_file(test_file).await.ok();
+    }
+
     #[tokio::test]
     async fn test_edit_missing_file_path() {
         let tool = EditTool;
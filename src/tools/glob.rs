@@ -1,11 +1,33 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
 use serde_json::Value;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// Files returned when the call gives no `limit`
+const DEFAULT_LIMIT: usize = 100;
+
+/// Directories of dependencies and build output, skipped unless the
+/// pattern names them or `include_vendored` is set
+const VENDORED_DIRS: &[&str] = &[".git", "node_modules", "vendor", "target", ".venv", "venv", "__pycache__", "bower_components"];
 
 /// Glob tool for file pattern matching
 pub struct GlobTool;
 
+/// Whether `path` lies in a vendored directory below `base` that `pattern`
+/// doesn't ask for by name
+fn in_vendored_dir(path: &Path, base: &Path, pattern: &str) -> bool {
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    let parent = relative.parent().unwrap_or(Path::new(""));
+    parent.components().any(|component| match component {
+        Component::Normal(name) => {
+            let name = name.to_string_lossy();
+            VENDORED_DIRS.contains(&name.as_ref()) && !pattern.split(['/', '\\']).any(|part| part == name)
+        }
+        _ => false,
+    })
+}
+
 impl ToolImpl for GlobTool {
     fn definition(&self) -> ToolDefinition {
         let mut properties = serde_json::Map::new();
@@ -20,7 +42,21 @@ impl ToolImpl for GlobTool {
             "path".to_string(),
             serde_json::json!({
                 "type": "string",
-                "description": "The base directory to search in. If not provided, uses current working directory."
+                "description": "The base directory to search in. If not provided, searches every workspace root."
+            }),
+        );
+        properties.insert(
+            "limit".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Return at most this many files, the most recently modified first (default: {}).", DEFAULT_LIMIT)
+            }),
+        );
+        properties.insert(
+            "include_vendored".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": format!("Also match inside dependency and build directories ({}) the pattern doesn't name. Default is false.", VENDORED_DIRS.join(", "))
             }),
         );
 
@@ -28,7 +64,7 @@ impl ToolImpl for GlobTool {
             r#type: "function".to_string(),
             function: FunctionDefinition {
                 name: "glob".to_string(),
-                description: "Search for files matching a glob pattern. Returns a list of matching file paths sorted by modification time. This is useful for finding files by name pattern or extension.".to_string(),
+                description: "Search for files matching a glob pattern. Returns a list of matching file paths sorted by modification time, newest first. This is useful for finding files by name pattern or extension.".to_string(),
                 parameters: ParametersSchema {
                     r#type: "object".to_string(),
                     properties,
@@ -38,44 +74,105 @@ impl ToolImpl for GlobTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let pattern = arguments
-            .get("pattern")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'pattern' argument".to_string())?;
-
-        let base_path = arguments
-            .get("path")
-            .and_then(|v| v.as_str())
-            .unwrap_or(".");
-
-        // Construct the full pattern
-        let full_pattern = if Path::new(pattern).is_absolute() {
-            pattern.to_string()
-        } else {
-            format!("{}/{}", base_path, pattern)
-        };
-
-        // Perform glob search
-        let mut matches: Vec<String> = glob::glob(&full_pattern)
-            .map_err(|e| format!("Invalid glob pattern '{}': {}", full_pattern, e))?
-            .filter_map(|entry| match entry {
-                Ok(path) => path.into_os_string().into_string().ok(),
-                Err(e) => {
-                    eprintln!("Glob error: {}", e);
-                    None
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let pattern = arguments
+                .get("pattern")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'pattern' argument".to_string())?;
+
+            let workspace = &context.workspace;
+            let limit = arguments
+                .get("limit")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as usize)
+                .unwrap_or(DEFAULT_LIMIT);
+            if limit == 0 {
+                return Err("'limit' must be at least 1".to_string());
+            }
+            let include_vendored = arguments
+                .get("include_vendored")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            // Without an explicit path, search every workspace root (or the current scope)
+            let base_paths: Vec<String> = match arguments.get("path").and_then(|v| v.as_str()) {
+                Some(path) => {
+                    let resolved = workspace.resolve(path);
+                    workspace.check_scope(&resolved)?;
+                    vec![resolved.display().to_string()]
                 }
-            })
-            .collect();
+                None => workspace
+                    .search_paths()
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            };
 
-        // Sort matches for consistent output
-        matches.sort();
+            let mut matches = Vec::new();
+            let mut searched = Vec::new();
 
-        if matches.is_empty() {
-            Ok(format!("No files found matching pattern: {}", full_pattern))
-        } else {
-            Ok(matches.join("\n"))
-        }
+            for base_path in &base_paths {
+                // Construct the full pattern
+                let full_pattern = if Path::new(pattern).is_absolute() {
+                    pattern.to_string()
+                } else {
+                    format!("{}/{}", base_path, pattern)
+                };
+
+                // Perform glob search
+                let root_matches: Vec<PathBuf> = glob::glob(&full_pattern)
+                    .map_err(|e| format!("Invalid glob pattern '{}': {}", full_pattern, e))?
+                    .filter_map(|entry| match entry {
+                        Ok(path) if workspace.in_scope(&path) => Some(path),
+                        // 无法读取的条目跳过
+                        _ => None,
+                    })
+                    .filter(|path| include_vendored || !in_vendored_dir(path, Path::new(base_path), pattern))
+                    .collect();
+
+                matches.extend(root_matches.into_iter().map(|path| {
+                    let modified = path.metadata().and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+                    (modified, path)
+                }));
+                searched.push(full_pattern);
+
+                // An absolute pattern is the same for every root
+                if Path::new(pattern).is_absolute() {
+                    break;
+                }
+            }
+
+            if matches.is_empty() {
+                return Ok(format!("No files found matching pattern: {}", searched.join(", ")));
+            }
+
+            // 最近修改的在前，修改时间相同时按路径排序
+            matches.sort_by(|(a_time, a_path), (b_time, b_path)| b_time.cmp(a_time).then_with(|| a_path.cmp(b_path)));
+            let total = matches.len();
+            let mut lines: Vec<String> = matches
+                .iter()
+                .take(limit)
+                .map(|(_, path)| workspace.display_path(path))
+                .collect();
+            if total > limit {
+                lines.push(format!(
+                    "(showing the {} most recently modified of {} files; narrow the pattern or raise limit for more)",
+                    limit, total
+                ));
+            }
+            Ok(lines.join("\n"))
+        })
     }
 }
 
@@ -146,6 +243,77 @@ mod tests {
         fs::remove_dir_all(test_dir).await.ok();
     }
 
+    #[tokio::test]
+    async fn test_glob_multi_root_labels() {
+        use crate::workspace::{Workspace, WorkspaceRoot};
+
+        let tool = GlobTool;
+
+        let test_dir = "/tmp/test_glob_multi_root";
+        fs::create_dir_all(format!("{}/api", test_dir)).await.ok();
+        fs::create_dir_all(format!("{}/web", test_dir)).await.ok();
+        fs::write(format!("{}/api/main.rs", test_dir), "fn main() {}")
+            .await
+            .ok();
+        fs::write(format!("{}/web/app.ts", test_dir), "export {}")
+            .await
+            .ok();
+
+        let workspace = Workspace::new(vec![
+            WorkspaceRoot::new("backend", format!("{}/api", test_dir)),
+            WorkspaceRoot::new("frontend", format!("{}/web", test_dir)),
+        ])
+        .unwrap();
+        let context = ToolContext::new(workspace);
+
+        let args = serde_json::json!({"pattern": "*.*"});
+        let result = tool.execute_with_context(&context, &args).await.unwrap();
+        assert!(result.contains("backend:main.rs"));
+        assert!(result.contains("frontend:app.ts"));
+
+        // A labeled path restricts the search to that root
+        let args = serde_json::json!({"pattern": "*.*", "path": "frontend:"});
+        let result = tool.execute_with_context(&context, &args).await.unwrap();
+        assert_eq!(result, "frontend:app.ts");
+
+        // Clean up
+        fs::remove_dir_all(test_dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_glob_newest_first_and_limit() {
+        let tool = GlobTool;
+
+        let test_dir = "/tmp/test_glob_mtime";
+        fs::remove_dir_all(test_dir).await.ok();
+        fs::create_dir_all(format!("{}/node_modules/pkg", test_dir)).await.unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("old.rs", 300), ("new.rs", 0), ("mid.rs", 60), ("node_modules/pkg/dep.rs", 0)] {
+            let path = format!("{}/{}", test_dir, name);
+            std::fs::write(&path, "").unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age)).unwrap();
+        }
+
+        let args = serde_json::json!({"pattern": "**/*.rs", "path": test_dir});
+        let result = tool.execute(&args).await.unwrap();
+        let expected = ["new.rs", "mid.rs", "old.rs"].map(|name| format!("{}/{}", test_dir, name));
+        assert_eq!(result, expected.join("\n"));
+
+        let args = serde_json::json!({"pattern": "**/*.rs", "path": test_dir, "limit": 1});
+        let result = tool.execute(&args).await.unwrap();
+        assert_eq!(result.lines().next(), Some(expected[0].as_str()));
+        assert!(result.ends_with("(showing the 1 most recently modified of 3 files; narrow the pattern or raise limit for more)"));
+
+        // Vendored directories are searched when asked for
+        let args = serde_json::json!({"pattern": "node_modules/**/*.rs", "path": test_dir});
+        assert!(tool.execute(&args).await.unwrap().ends_with("dep.rs"));
+        let args = serde_json::json!({"pattern": "**/*.rs", "path": test_dir, "include_vendored": true});
+        assert_eq!(tool.execute(&args).await.unwrap().lines().count(), 4);
+
+        fs::remove_dir_all(test_dir).await.ok();
+    }
+
     #[tokio::test]
     async fn test_glob_missing_pattern() {
         let tool = GlobTool;
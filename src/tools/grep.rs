@@ -1,14 +1,31 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::read::looks_binary;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use crate::utils::gitignore::Gitignore;
+use crate::workspace::Workspace;
 use serde_json::Value;
 use std::path::Path;
 use tokio::fs;
 use tokio::io::AsyncReadExt;
 
+/// Results returned when the call gives no `head_limit`
+const DEFAULT_HEAD_LIMIT: usize = 250;
+
 /// Grep tool for searching file contents
 pub struct GrepTool;
 
+/// How to search each file and what to report
+struct Search<'a> {
+    regex: Regex,
+    output_mode: &'a str,
+    /// Match the pattern against the whole file instead of line by line
+    multiline: bool,
+    /// Lines of context before and after each match, in `content` mode
+    before: usize,
+    after: usize,
+}
+
 impl ToolImpl for GrepTool {
     fn definition(&self) -> ToolDefinition {
         let mut properties = serde_json::Map::new();
@@ -23,7 +40,7 @@ impl ToolImpl for GrepTool {
             "path".to_string(),
             serde_json::json!({
                 "type": "string",
-                "description": "The file or directory path to search in. If a directory, searches all files recursively."
+                "description": "The file or directory path to search in. If a directory, searches all files recursively. If not provided, searches every workspace root."
             }),
         );
         properties.insert(
@@ -40,6 +57,48 @@ impl ToolImpl for GrepTool {
                 "description": "Whether to perform case-insensitive search. Default is false."
             }),
         );
+        properties.insert(
+            "multiline".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Let the pattern match across lines ('.' also matches newlines, '^'/'$' match at line ends). Every line of a match is reported. Default is false."
+            }),
+        );
+        properties.insert(
+            "before_context".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": "Lines to show before each match in 'content' mode (like grep -B)."
+            }),
+        );
+        properties.insert(
+            "after_context".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": "Lines to show after each match in 'content' mode (like grep -A)."
+            }),
+        );
+        properties.insert(
+            "context".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": "Lines to show before and after each match (like grep -C); before_context and after_context take precedence."
+            }),
+        );
+        properties.insert(
+            "head_limit".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Return at most this many lines, files or counts (default: {}).", DEFAULT_HEAD_LIMIT)
+            }),
+        );
+        properties.insert(
+            "include_ignored".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Also search files that .gitignore excludes (e.g. target/, node_modules/). Default is false."
+            }),
+        );
         properties.insert(
             "output_mode".to_string(),
             serde_json::json!({
@@ -52,7 +111,7 @@ impl ToolImpl for GrepTool {
             r#type: "function".to_string(),
             function: FunctionDefinition {
                 name: "grep".to_string(),
-                description: "Search for text patterns in files using regular expressions. Supports recursive directory searching and multiple output modes.".to_string(),
+                description: "Search for text patterns in files using regular expressions. Supports recursive directory searching and multiple output modes. Files excluded by .gitignore and binary files are skipped.".to_string(),
                 parameters: ParametersSchema {
                     r#type: "object".to_string(),
                     properties,
@@ -62,122 +121,222 @@ impl ToolImpl for GrepTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let pattern = arguments
-            .get("pattern")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'pattern' argument".to_string())?;
-
-        let path = arguments
-            .get("path")
-            .and_then(|v| v.as_str())
-            .unwrap_or(".");
-
-        let glob_pattern = arguments.get("glob").and_then(|v| v.as_str());
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
 
-        let _case_insensitive = arguments
-            .get("case_insensitive")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let pattern = arguments
+                .get("pattern")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'pattern' argument".to_string())?;
+
+            let workspace = &context.workspace;
+
+            // Without an explicit path, search every workspace root (or the current scope)
+            let paths: Vec<String> = match arguments.get("path").and_then(|v| v.as_str()) {
+                Some(path) => {
+                    let resolved = workspace.resolve(path);
+                    workspace.check_scope(&resolved)?;
+                    vec![resolved.display().to_string()]
+                }
+                None => workspace
+                    .search_paths()
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            };
 
-        let output_mode = arguments
-            .get("output_mode")
-            .and_then(|v| v.as_str())
-            .unwrap_or("content");
+            let glob_pattern = arguments.get("glob").and_then(|v| v.as_str());
+            let include_ignored = arguments
+                .get("include_ignored")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let case_insensitive = arguments
+                .get("case_insensitive")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let multiline = arguments
+                .get("multiline")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            let output_mode = arguments
+                .get("output_mode")
+                .and_then(|v| v.as_str())
+                .unwrap_or("content");
+
+            let lines = |key: &str| arguments.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+            let context_lines = lines("context").unwrap_or(0);
+            let head_limit = lines("head_limit").unwrap_or(DEFAULT_HEAD_LIMIT);
+            if head_limit == 0 {
+                return Err("'head_limit' must be at least 1".to_string());
+            }
 
-        // Compile regex
-        let regex = Regex::new(pattern)
-            .map_err(|e| format!("Invalid regex pattern '{}': {}", pattern, e))?;
+            // Compile regex
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(case_insensitive)
+                .multi_line(multiline)
+                .dot_matches_new_line(multiline)
+                .build()
+                .map_err(|e| format!("Invalid regex pattern '{}': {}", pattern, e))?;
+            let search = Search {
+                regex,
+                output_mode,
+                multiline,
+                before: lines("before_context").unwrap_or(context_lines),
+                after: lines("after_context").unwrap_or(context_lines),
+            };
 
-        // Check if path is a file or directory
-        let search_path = Path::new(path);
-        let mut results = Vec::new();
-
-        if search_path.is_file() {
-            // Search single file
-            self.search_file(path, &regex, output_mode, &mut results)
-                .await?;
-        } else if search_path.is_dir() {
-            // Search directory
-            let files = self.find_files_to_search(path, glob_pattern)?;
-            for file_path in files {
-                self.search_file(&file_path, &regex, output_mode, &mut results)
-                    .await?;
+            let mut results = Vec::new();
+
+            'paths: for path in &paths {
+                // Check if path is a file or directory
+                let search_path = Path::new(path);
+
+                if search_path.is_file() {
+                    // Search single file
+                    self.search_file(workspace, path, &search, &mut results)
+                        .await?;
+                } else if search_path.is_dir() {
+                    // Search directory
+                    let mut ignore = (!include_ignored).then(|| Gitignore::new(search_path));
+                    let files = self.find_files_to_search(path, glob_pattern, ignore.as_mut())?;
+                    for file_path in files {
+                        self.search_file(workspace, &file_path, &search, &mut results)
+                            .await?;
+                        // 已超出上限，不再搜索其余文件
+                        if results.len() > head_limit {
+                            break 'paths;
+                        }
+                    }
+                } else {
+                    return Err(format!("Path '{}' is not a valid file or directory", path));
+                }
             }
-        } else {
-            return Err(format!("Path '{}' is not a valid file or directory", path));
-        }
 
-        if results.is_empty() {
-            Ok(format!("No matches found for pattern: {}", pattern))
-        } else {
+            if results.is_empty() {
+                return Ok(format!("No matches found for pattern: {}", pattern));
+            }
+            if results.len() > head_limit {
+                results.truncate(head_limit);
+                results.push(format!(
+                    "(showing the first {} results; narrow the search or raise head_limit for more)",
+                    head_limit
+                ));
+            }
             Ok(results.join("\n"))
-        }
+        })
     }
 }
 
+/// Lines (indexes, in order) covered by a match of `regex` across the
+/// whole of `contents`, and the number of matches
+fn matched_spans(regex: &Regex, contents: &str) -> (Vec<usize>, usize) {
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(contents.match_indices('\n').map(|(index, _)| index + 1))
+        .collect();
+    let line_of = |offset: usize| starts.partition_point(|&start| start <= offset) - 1;
+    let mut lines = Vec::new();
+    let mut count = 0;
+    for found in regex.find_iter(contents) {
+        count += 1;
+        let last = line_of(found.end().saturating_sub(1).max(found.start()));
+        lines.extend(line_of(found.start())..=last);
+    }
+    lines.dedup();
+    // 文件末尾换行之后的空“行”不算
+    let total = contents.lines().count();
+    lines.retain(|&line| line < total);
+    (lines, count)
+}
+
 impl GrepTool {
     async fn search_file(
         &self,
+        workspace: &Workspace,
         file_path: &str,
-        regex: &Regex,
-        output_mode: &str,
+        search: &Search<'_>,
         results: &mut Vec<String>,
     ) -> Result<(), String> {
         let mut file = fs::File::open(file_path)
             .await
             .map_err(|e| format!("Failed to open file '{}': {}", file_path, e))?;
 
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
             .await
             .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
+        // 二进制文件不搜索
+        if looks_binary(&bytes) {
+            return Ok(());
+        }
+        let contents = String::from_utf8_lossy(&bytes);
+
+        // Show the file as the model should address it (labeled in multi-root workspaces)
+        let file_path = &workspace.display_path(Path::new(file_path));
 
         let lines: Vec<&str> = contents.lines().collect();
-        let mut matches = Vec::new();
-        let mut match_count = 0;
-
-        for (line_num, line) in lines.iter().enumerate() {
-            if regex.is_match(line) {
-                match output_mode {
-                    "content" => {
-                        matches.push(format!("{}:{}:{}", file_path, line_num + 1, line));
-                    }
-                    "count" => {
-                        match_count += 1;
-                    }
-                    "files_with_matches" => {
-                        results.push(file_path.to_string());
-                        return Ok(());
-                    }
-                    _ => {
-                        matches.push(format!("{}:{}:{}", file_path, line_num + 1, line));
-                    }
-                }
-            }
+        let (matched, count) = if search.multiline {
+            matched_spans(&search.regex, &contents)
+        } else {
+            let matched: Vec<usize> = (0..lines.len()).filter(|&index| search.regex.is_match(lines[index])).collect();
+            let count = matched.len();
+            (matched, count)
+        };
+        if matched.is_empty() {
+            return Ok(());
         }
 
-        match output_mode {
-            "count" => {
-                if match_count > 0 {
-                    results.push(format!("{}:{}", file_path, match_count));
-                }
-            }
-            "files_with_matches" => {
-                // Already handled above
-            }
+        match search.output_mode {
+            "count" => results.push(format!("{}:{}", file_path, count)),
+            "files_with_matches" => results.push(file_path.to_string()),
             _ => {
-                results.extend(matches);
+                // 上下文行用 `-` 分隔行号，不相邻的片段之间用 `--` 隔开（同 grep）
+                let with_context = search.before > 0 || search.after > 0;
+                if with_context && !results.is_empty() {
+                    results.push("--".to_string());
+                }
+                let mut shown: Option<usize> = None;
+                for &index in &matched {
+                    let start = index.saturating_sub(search.before);
+                    let end = (index + search.after).min(lines.len() - 1);
+                    let from = match shown {
+                        Some(last) if last >= end => continue,
+                        Some(last) if start > last + 1 => {
+                            results.push("--".to_string());
+                            start
+                        }
+                        Some(last) => last + 1,
+                        None => start,
+                    };
+                    for (line_num, line) in lines.iter().enumerate().take(end + 1).skip(from) {
+                        let separator = if matched.binary_search(&line_num).is_ok() { ':' } else { '-' };
+                        results.push(format!("{}{}{}{}{}", file_path, separator, line_num + 1, separator, line));
+                    }
+                    shown = Some(end);
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Files under `path` (matching `glob_pattern` if given), leaving out
+    /// what `ignore` excludes
     fn find_files_to_search(
         &self,
         path: &str,
         glob_pattern: Option<&str>,
+        mut ignore: Option<&mut Gitignore>,
     ) -> Result<Vec<String>, String> {
         let search_path = Path::new(path);
 
@@ -193,42 +352,42 @@ impl GrepTool {
             let matches =
                 glob::glob(&full_pattern).map_err(|e| format!("Invalid glob pattern: {}", e))?;
 
-            for entry in matches {
-                match entry {
-                    Ok(path) => {
-                        if path.is_file() {
-                            if let Some(path_str) = path.into_os_string().into_string().ok() {
-                                files.push(path_str);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Glob error: {}", e);
-                    }
+            // 无法读取的条目跳过
+            for path in matches.flatten() {
+                if path.is_file()
+                    && !ignore.as_deref_mut().is_some_and(|ignore| ignore.is_ignored(&path, false))
+                    && let Ok(path_str) = path.into_os_string().into_string()
+                {
+                    files.push(path_str);
                 }
             }
         } else {
             // Recursively find all files
-            self.find_all_files(search_path, &mut files)?;
+            self.find_all_files(search_path, ignore, &mut files)?;
         }
 
         Ok(files)
     }
 
-    fn find_all_files(&self, dir: &Path, files: &mut Vec<String>) -> Result<(), String> {
+    fn find_all_files(&self, dir: &Path, mut ignore: Option<&mut Gitignore>, files: &mut Vec<String>) -> Result<(), String> {
         let entries = std::fs::read_dir(dir)
             .map_err(|e| format!("Failed to read directory '{:?}': {}", dir, e))?;
 
         for entry in entries {
             let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
             let path = entry.path();
+            if let Some(ignore) = ignore.as_deref_mut()
+                && ignore.is_ignored(&path, path.is_dir())
+            {
+                continue;
+            }
 
             if path.is_file() {
-                if let Some(path_str) = path.into_os_string().into_string().ok() {
+                if let Ok(path_str) = path.into_os_string().into_string() {
                     files.push(path_str);
                 }
             } else if path.is_dir() {
-                self.find_all_files(&path, files)?;
+                self.find_all_files(&path, ignore.as_deref_mut(), files)?;
             }
         }
 
@@ -285,6 +444,128 @@ mod tests {
         fs::remove_file(test_file).await.ok();
     }
 
+    #[tokio::test]
+    async fn test_grep_multi_root_labels() {
+        use crate::workspace::{Workspace, WorkspaceRoot};
+
+        let tool = GrepTool;
+
+        let test_dir = "/tmp/test_grep_multi_root";
+        fs::create_dir_all(format!("{}/api", test_dir)).await.ok();
+        fs::create_dir_all(format!("{}/web", test_dir)).await.ok();
+        fs::write(format!("{}/api/main.rs", test_dir), "fn handler() {}")
+            .await
+            .ok();
+        fs::write(format!("{}/web/app.ts", test_dir), "fetch(handler)")
+            .await
+            .ok();
+
+        let workspace = Workspace::new(vec![
+            WorkspaceRoot::new("backend", format!("{}/api", test_dir)),
+            WorkspaceRoot::new("frontend", format!("{}/web", test_dir)),
+        ])
+        .unwrap();
+        let context = ToolContext::new(workspace);
+
+        let args = serde_json::json!({"pattern": "handler", "output_mode": "files_with_matches"});
+        let result = tool.execute_with_context(&context, &args).await.unwrap();
+        assert!(result.contains("backend:main.rs"));
+        assert!(result.contains("frontend:app.ts"));
+
+        // Clean up
+        fs::remove_dir_all(test_dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_grep_skips_ignored_and_binary_files() {
+        let tool = GrepTool;
+        let test_dir = "/tmp/test_grep_ignored";
+        fs::remove_dir_all(test_dir).await.ok();
+        fs::create_dir_all(format!("{}/target/debug", test_dir)).await.unwrap();
+        fs::create_dir_all(format!("{}/src", test_dir)).await.unwrap();
+        fs::write(format!("{}/.gitignore", test_dir), "target/\n").await.unwrap();
+        fs::write(format!("{}/src/lib.rs", test_dir), "fn needle() {}").await.unwrap();
+        fs::write(format!("{}/target/debug/out.rs", test_dir), "fn needle() {}").await.unwrap();
+        fs::write(format!("{}/src/blob.bin", test_dir), b"needle\0\x01").await.unwrap();
+
+        let args = serde_json::json!({"pattern": "needle", "path": test_dir, "output_mode": "files_with_matches"});
+        let result = tool.execute(&args).await.unwrap();
+        assert_eq!(result, format!("{}/src/lib.rs", test_dir));
+
+        let args = serde_json::json!({"pattern": "needle", "path": test_dir, "glob": "**/*.rs", "output_mode": "files_with_matches"});
+        assert_eq!(tool.execute(&args).await.unwrap(), format!("{}/src/lib.rs", test_dir));
+
+        let args = serde_json::json!({"pattern": "needle", "path": test_dir, "include_ignored": true, "output_mode": "files_with_matches"});
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.contains("target/debug/out.rs"));
+        assert!(!result.contains("blob.bin"));
+
+        fs::remove_dir_all(test_dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_grep_context_and_head_limit() {
+        let tool = GrepTool;
+        let test_file = "/tmp/test_grep_context.txt";
+        fs::write(test_file, "a\nneedle 1\nb\nc\nd\ne\nneedle 2\nneedle 3\nf")
+            .await
+            .unwrap();
+
+        let args = serde_json::json!({"pattern": "needle", "path": test_file, "context": 1});
+        let result = tool.execute(&args).await.unwrap();
+        assert_eq!(
+            result,
+            [
+                format!("{}-1-a", test_file),
+                format!("{}:2:needle 1", test_file),
+                format!("{}-3-b", test_file),
+                "--".to_string(),
+                format!("{}-6-e", test_file),
+                format!("{}:7:needle 2", test_file),
+                format!("{}:8:needle 3", test_file),
+                format!("{}-9-f", test_file),
+            ]
+            .join("\n")
+        );
+
+        let args = serde_json::json!({"pattern": "needle", "path": test_file, "after_context": 1, "context": 3, "head_limit": 2});
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.starts_with(&format!("{}-1-a\n{}:2:needle 1\n(showing the first 2 results", test_file, test_file)));
+
+        fs::remove_file(test_file).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_grep_case_insensitive_and_multiline() {
+        let tool = GrepTool;
+        let test_file = "/tmp/test_grep_modes.rs";
+        fs::write(test_file, "struct Config {\n    name: String,\n}\nfn config() {}\n")
+            .await
+            .unwrap();
+
+        let args = serde_json::json!({"pattern": "config", "path": test_file, "output_mode": "count"});
+        assert_eq!(tool.execute(&args).await.unwrap(), format!("{}:1", test_file));
+        let args = serde_json::json!({"pattern": "config", "path": test_file, "output_mode": "count", "case_insensitive": true});
+        assert_eq!(tool.execute(&args).await.unwrap(), format!("{}:2", test_file));
+
+        // Without multiline the pattern can't cross the line break
+        let args = serde_json::json!({"pattern": r"Config \{.*?\}", "path": test_file});
+        assert!(tool.execute(&args).await.unwrap().starts_with("No matches found"));
+        let args = serde_json::json!({"pattern": r"Config \{.*?\}", "path": test_file, "multiline": true, "after_context": 1});
+        assert_eq!(
+            tool.execute(&args).await.unwrap(),
+            [
+                format!("{}:1:struct Config {{", test_file),
+                format!("{}:2:    name: String,", test_file),
+                format!("{}:3:}}", test_file),
+                format!("{}-4-fn config() {{}}", test_file),
+            ]
+            .join("\n")
+        );
+
+        fs::remove_file(test_file).await.ok();
+    }
+
     #[tokio::test]
     async fn test_grep_missing_pattern() {
         let tool = GrepTool;
@@ -0,0 +1,212 @@
+/// Shell programs that only wrap the command that follows them
+const WRAPPERS: &[&str] = &["sudo", "env", "exec", "time", "nohup", "nice", "command"];
+
+const EDITORS: &[&str] = &["vi", "vim", "nvim", "nano", "emacs", "pico", "micro", "joe"];
+const MONITORS: &[&str] = &["top", "htop", "btop", "atop", "iotop", "watch"];
+const REPLS: &[&str] = &["python", "python3", "node", "irb", "ghci", "bash", "sh", "zsh", "fish", "lua"];
+const SQL_CLIENTS: &[&str] = &["mysql", "psql", "sqlite3", "mongo", "mongosh", "redis-cli"];
+
+/// One command of a pipeline or list, with whether its stdin is fed by a
+/// pipe or redirect
+struct Segment<'a> {
+    words: Vec<&'a str>,
+    has_input: bool,
+}
+
+/// Byte offset of the first `|`, `&`, `;` or newline outside quotes
+fn separator(text: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            // 单引号内反斜杠没有特殊含义
+            (None | Some('"'), '\\') => escaped = true,
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '|' | '&' | ';' | '\n') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn segments(command: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut piped = false;
+    let mut rest = command;
+    while !rest.is_empty() {
+        let end = separator(rest).unwrap_or(rest.len());
+        let text = &rest[..end];
+        let separator = &rest[end..];
+        let (next_piped, skip) = if separator.starts_with("||") || separator.starts_with("&&") {
+            (false, 2)
+        } else if separator.starts_with('|') {
+            (true, 1)
+        } else {
+            (false, separator.len().min(1))
+        };
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if !words.is_empty() {
+            segments.push(Segment {
+                has_input: piped || words.iter().any(|word| word.starts_with('<')),
+                words,
+            });
+        }
+        piped = next_piped;
+        rest = &separator[skip..];
+    }
+    segments
+}
+
+/// The program a segment runs and its arguments, skipping environment
+/// assignments and wrappers such as `sudo`
+fn program<'a>(words: &'a [&'a str]) -> Option<(&'a str, &'a [&'a str])> {
+    let mut index = 0;
+    while let Some(word) = words.get(index) {
+        let is_assignment = word.contains('=') && !word.starts_with('-') && !word.starts_with('=');
+        if is_assignment || WRAPPERS.contains(word) || (index > 0 && word.starts_with('-') && WRAPPERS.contains(&words[index - 1])) {
+            index += 1;
+            continue;
+        }
+        let name = word.rsplit('/').next().unwrap_or(word);
+        return Some((name, &words[index + 1..]));
+    }
+    None
+}
+
+/// Whether a cluster of short options such as `-am` includes one of `letters`
+fn has_short_flag(args: &[&str], letters: &[char]) -> bool {
+    args.iter().any(|arg| {
+        arg.strip_prefix('-').is_some_and(|cluster| {
+            !cluster.is_empty() && cluster.chars().all(|c| c.is_ascii_alphabetic()) && cluster.contains(letters)
+        })
+    })
+}
+
+fn has_flag(args: &[&str], flags: &[&str]) -> bool {
+    args.iter().any(|arg| {
+        flags.iter().any(|flag| arg == flag || (flag.starts_with("--") && arg.starts_with(&format!("{}=", flag))))
+    })
+}
+
+/// Why `command` would wait on a terminal, with what to do instead, or
+/// `None` when it looks safe to run without one
+pub fn interactive_reason(command: &str) -> Option<String> {
+    for segment in segments(command) {
+        let Some((name, args)) = program(&segment.words) else {
+            continue;
+        };
+
+        let reason = if EDITORS.contains(&name) {
+            if name == "emacs" && has_flag(args, &["--batch", "-batch", "--script"]) {
+                continue;
+            }
+            "opens an interactive editor; use the edit or write tools to change files".to_string()
+        } else if MONITORS.contains(&name) {
+            if name == "top" && has_flag(args, &["-b", "-bn1", "-n"]) {
+                continue;
+            }
+            format!(
+                "refreshes the screen until quit; take a single snapshot instead (e.g. `top -b -n 1`, `ps aux`){}",
+                if name == "watch" { " or run the watched command once" } else { "" }
+            )
+        } else if name == "ssh" {
+            let batch = has_flag(args, &["-T", "-n", "-f"]) || args.iter().any(|arg| arg.contains("BatchMode=yes"));
+            // 除选项外只有主机名时会打开交互式会话
+            let operands = args.iter().filter(|arg| !arg.starts_with('-')).count();
+            if batch || operands > 1 {
+                continue;
+            }
+            "opens an interactive session; pass the remote command and `-T -o BatchMode=yes`, e.g. `ssh -T -o BatchMode=yes host 'uname -a'`".to_string()
+        } else if REPLS.contains(&name) {
+            if segment.has_input || args.iter().any(|arg| !arg.starts_with('-') || *arg == "-c" || *arg == "-e" || *arg == "-m") {
+                continue;
+            }
+            format!("starts an interactive {} prompt; pass a script or `-c`/`-e` with the code to run", name)
+        } else if SQL_CLIENTS.contains(&name) {
+            let query = has_flag(args, &["-e", "-c", "--execute", "--command", "--eval", "-f", "--file"]);
+            let sqlite_query = name == "sqlite3" && args.iter().filter(|arg| !arg.starts_with('-')).count() > 1;
+            let redis_command = name == "redis-cli" && args.iter().any(|arg| !arg.starts_with('-'));
+            if segment.has_input || query || sqlite_query || redis_command {
+                continue;
+            }
+            format!("starts an interactive {} shell; pass the query on the command line (e.g. `-c`/`-e`) or pipe it in", name)
+        } else if name == "git" {
+            let subcommand = args.iter().find(|arg| !arg.starts_with('-')).copied().unwrap_or_default();
+            match subcommand {
+                "commit"
+                    if !has_short_flag(args, &['m', 'F', 'C'])
+                        && !has_flag(args, &["--message", "--file", "--no-edit", "--reuse-message", "--fixup"]) =>
+                {
+                    "opens an editor for the commit message; pass it with `-m`".to_string()
+                }
+                "rebase" if has_flag(args, &["-i", "--interactive"]) => {
+                    "opens an editor for the todo list; use a non-interactive rebase or `git commit --fixup` with `GIT_SEQUENCE_EDITOR=:`".to_string()
+                }
+                "add" | "checkout" | "reset" | "stash" if has_flag(args, &["-i", "--interactive", "-p", "--patch"]) => {
+                    "prompts for each hunk; name the files or paths to include instead".to_string()
+                }
+                _ => continue,
+            }
+        } else if (name == "tmux" || name == "screen") && !has_flag(args, &["-d", "-dm", "ls", "list-sessions", "kill-session", "send-keys"]) {
+            "attaches to a terminal multiplexer; run the command directly".to_string()
+        } else {
+            continue;
+        };
+        return Some(format!("`{}` {}", name, reason));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interactive_commands() {
+        assert!(interactive_reason("vim src/main.rs").unwrap().contains("edit or write tools"));
+        assert!(interactive_reason("cd src && sudo nano config").is_some());
+        assert!(interactive_reason("top").unwrap().contains("top -b -n 1"));
+        assert!(interactive_reason("ssh prod").unwrap().contains("BatchMode"));
+        assert!(interactive_reason("python3").is_some());
+        assert!(interactive_reason("psql -U admin mydb").is_some());
+        assert!(interactive_reason("git add -p").is_some());
+        assert!(interactive_reason("git commit").unwrap().contains("-m"));
+        assert!(interactive_reason("git commit -a -s").is_some());
+        assert!(interactive_reason("echo \"x;\" ; vim").is_some());
+        assert!(interactive_reason("git rebase -i HEAD~3").is_some());
+        assert!(interactive_reason("FOO=1 /usr/bin/vim x").is_some());
+    }
+
+    #[test]
+    fn test_non_interactive_commands() {
+        for command in [
+            "ls -la | less_than_nothing",
+            "top -b -n 1",
+            "ssh -T host uptime",
+            "ssh host 'uname -a'",
+            "python3 script.py",
+            "python -c 'print(1)'",
+            "echo 'print(1)' | python3",
+            "python3 < script.py",
+            "psql -c 'select 1' mydb",
+            "sqlite3 db.sqlite 'select 1'",
+            "git commit -m 'fix'",
+            "git commit -am \"fix\"",
+            "git commit -qm fix",
+            "git commit -sm fix",
+            "git commit --message=fix",
+            "echo \"done; vim is great\"",
+            "echo 'a | top' && ls",
+            "git commit --amend --no-edit",
+            "git rebase main",
+            "emacs --batch -l build.el",
+            "cargo test && echo vim",
+            "",
+        ] {
+            assert_eq!(interactive_reason(command), None, "{}", command);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
+use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use crate::workspace::Workspace;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const DEFAULT_DEPTH: u64 = 1;
+const MAX_DEPTH: u64 = 5;
+const MAX_ENTRIES: usize = 500;
+
+/// Directories listed but not descended into
+const SKIPPED_DIRS: &[&str] = &[".git", "target", "node_modules", "__pycache__", ".venv"];
+
+/// One row of the listing
+#[derive(Debug)]
+struct Entry {
+    kind: char,
+    size: Option<u64>,
+    modified: Option<u64>,
+    mode: String,
+    path: String,
+}
+
+/// Size in B/K/M/G with one decimal above a kilobyte
+pub(crate) fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", size, UNITS[unit])
+}
+
+/// Unix seconds as `YYYY-MM-DD HH:MM` in UTC
+fn format_timestamp(secs: u64) -> String {
+    // 按公历换算天数（Howard Hinnant 的 civil_from_days 算法）
+    let days = (secs / 86_400) as i64;
+    let minutes = (secs % 86_400) / 60;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+}
+
+#[cfg(unix)]
+fn mode_string(metadata: &std::fs::Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = metadata.permissions().mode();
+    (0..9)
+        .map(|bit| {
+            if mode & (0o400 >> bit) == 0 {
+                '-'
+            } else {
+                ['r', 'w', 'x'][bit % 3]
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn mode_string(metadata: &std::fs::Metadata) -> String {
+    if metadata.permissions().readonly() { "r--" } else { "rw-" }.to_string()
+}
+
+/// Walk `dir` breadth-first down to `depth` levels, stopping at `MAX_ENTRIES`.
+/// Returns the entries and whether the listing was cut short. Only `dir`
+/// itself must be readable; subdirectories that are not are marked in
+/// their row.
+fn list(dir: &Path, depth: u64, all: bool, workspace: &Workspace) -> Result<(Vec<Entry>, bool), String> {
+    let mut entries: Vec<Entry> = Vec::new();
+    // 待读取的目录、层级，以及它在 entries 中的行
+    let mut pending: Vec<(PathBuf, u64, Option<usize>)> = vec![(dir.to_path_buf(), 1, None)];
+    let mut index = 0;
+
+    while index < pending.len() {
+        let (current, level, row) = pending[index].clone();
+        index += 1;
+
+        let read = match (std::fs::read_dir(&current), row) {
+            (Ok(read), _) => read,
+            (Err(e), None) => return Err(format!("Failed to list '{}': {}", current.display(), e)),
+            (Err(e), Some(row)) => {
+                entries[row].path.push_str(&format!(" (unreadable: {})", e.kind()));
+                continue;
+            }
+        };
+        let mut children: Vec<PathBuf> = read.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+        children.sort();
+
+        for child in children {
+            let name = child.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            if (!all && name.starts_with('.')) || !workspace.in_scope(&child) {
+                continue;
+            }
+            if entries.len() >= MAX_ENTRIES {
+                return Ok((entries, true));
+            }
+
+            // symlink_metadata 以便链接本身显示为 l，而不是跟随到目标
+            let Ok(metadata) = std::fs::symlink_metadata(&child) else {
+                continue;
+            };
+            let kind = if metadata.is_symlink() {
+                'l'
+            } else if metadata.is_dir() {
+                'd'
+            } else {
+                'f'
+            };
+            let relative = child.strip_prefix(dir).unwrap_or(&child).display().to_string();
+            entries.push(Entry {
+                kind,
+                size: (kind == 'f').then_some(metadata.len()),
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs()),
+                mode: mode_string(&metadata),
+                path: if kind == 'd' { format!("{}/", relative) } else { relative },
+            });
+
+            if kind == 'd' && level < depth && !SKIPPED_DIRS.contains(&name.as_str()) {
+                pending.push((child, level + 1, Some(entries.len() - 1)));
+            }
+        }
+    }
+
+    // 广度优先遍历后按路径排序，子目录内容紧跟在目录之后
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((entries, false))
+}
+
+fn render(entries: &[Entry]) -> String {
+    let sizes: Vec<String> = entries
+        .iter()
+        .map(|entry| entry.size.map(human_size).unwrap_or_else(|| "-".to_string()))
+        .collect();
+    let size_width = sizes.iter().map(|size| size.len()).max().unwrap_or(0).max(4);
+    let mode_width = entries.iter().map(|entry| entry.mode.len()).max().unwrap_or(0).max(4);
+
+    let mut output = format!(
+        "t  {:>size_width$}  {:<16}  {:<mode_width$}  path\n",
+        "size", "modified", "mode"
+    );
+    for (entry, size) in entries.iter().zip(&sizes) {
+        let modified = entry.modified.map(format_timestamp).unwrap_or_else(|| "-".to_string());
+        output.push_str(&format!(
+            "{}  {:>size_width$}  {:<16}  {:<mode_width$}  {}\n",
+            entry.kind, size, modified, entry.mode, entry.path
+        ));
+    }
+    output
+}
+
+/// Ls tool listing a directory with type, size, mtime and permissions
+pub struct LsTool;
+
+impl ToolImpl for LsTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "path".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "The directory to list (default: the workspace root)"
+            }),
+        );
+        properties.insert(
+            "depth".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("How many levels to descend (default: {}, max: {}); .git, target and node_modules are listed but not entered", DEFAULT_DEPTH, MAX_DEPTH)
+            }),
+        );
+        properties.insert(
+            "all".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Include hidden entries whose names start with '.' (default: false)"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "ls".to_string(),
+                description: "List a directory as a compact table of type (f file, d directory, l symlink), size, modification time (UTC), permissions and path. Use this instead of `ls -la` in bash.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let path = arguments.get("path").and_then(|v| v.as_str()).unwrap_or(".");
+            let depth = arguments
+                .get("depth")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_DEPTH)
+                .clamp(1, MAX_DEPTH);
+            let all = arguments.get("all").and_then(|v| v.as_bool()).unwrap_or(false);
+
+            let resolved = match (path, context.workspace.scope()) {
+                (".", Some(scope)) => scope.to_path_buf(),
+                _ => context.workspace.resolve(path),
+            };
+            context.workspace.check_scope(&resolved)?;
+            if !resolved.is_dir() {
+                return Err(format!("'{}' is not a directory", path));
+            }
+
+            let workspace = context.workspace.clone();
+            let (entries, truncated) = tokio::task::spawn_blocking(move || list(&resolved, depth, all, &workspace))
+                .await
+                .map_err(|e| format!("Failed to list '{}': {}", path, e))??;
+
+            if entries.is_empty() {
+                return Ok(format!("'{}' is empty", path));
+            }
+            let mut output = render(&entries);
+            if truncated {
+                output.push_str(&format!(
+                    "... stopped after {} entries; list a subdirectory or lower depth\n",
+                    MAX_ENTRIES
+                ));
+            }
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_helpers() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_709_210_096), "2024-02-29 12:34");
+        assert_eq!(human_size(512), "512B");
+        assert_eq!(human_size(1536), "1.5K");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0M");
+    }
+
+    #[tokio::test]
+    async fn test_ls_depth_and_hidden() {
+        let dir = "/tmp/test_ls_tool";
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(format!("{}/src/nested", dir)).unwrap();
+        std::fs::create_dir_all(format!("{}/target/debug", dir)).unwrap();
+        std::fs::write(format!("{}/Cargo.toml", dir), "[package]\n").unwrap();
+        std::fs::write(format!("{}/src/main.rs", dir), "fn main() {}").unwrap();
+        std::fs::write(format!("{}/src/nested/deep.rs", dir), "").unwrap();
+        std::fs::write(format!("{}/.env", dir), "SECRET=1").unwrap();
+
+        let shallow = LsTool.execute(&serde_json::json!({"path": dir})).await.unwrap();
+        assert!(shallow.starts_with("t  size"));
+        assert!(shallow.contains("f   10B"));
+        assert!(shallow.contains("  Cargo.toml\n"));
+        assert!(shallow.contains("  src/\n"));
+        assert!(!shallow.contains("main.rs"));
+        assert!(!shallow.contains(".env"));
+
+        let deep = LsTool
+            .execute(&serde_json::json!({"path": dir, "depth": 3, "all": true}))
+            .await
+            .unwrap();
+        assert!(deep.contains("  src/main.rs\n"));
+        assert!(deep.contains("  src/nested/deep.rs\n"));
+        assert!(deep.contains("  .env\n"));
+        // target is listed but not entered
+        assert!(deep.contains("  target/\n"));
+        assert!(!deep.contains("debug"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ls_unreadable_subdirectory() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = "/tmp/test_ls_unreadable";
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(format!("{}/locked", dir)).unwrap();
+        std::fs::write(format!("{}/readme.md", dir), "").unwrap();
+        std::fs::set_permissions(format!("{}/locked", dir), std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        let listing = LsTool.execute(&serde_json::json!({"path": dir, "depth": 2})).await;
+        // root 可以读取任何目录，此时无从验证
+        let readable = std::fs::read_dir(format!("{}/locked", dir)).is_ok();
+        std::fs::set_permissions(format!("{}/locked", dir), std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::remove_dir_all(dir).ok();
+        if readable {
+            return;
+        }
+        let listing = listing.unwrap();
+        assert!(listing.contains("  locked/ (unreadable: permission denied)\n"));
+        assert!(listing.contains("  readme.md\n"));
+    }
+
+    #[tokio::test]
+    async fn test_ls_not_a_directory() {
+        let result = LsTool.execute(&serde_json::json!({"path": "/tmp/test_ls_missing_dir"})).await;
+        assert!(result.unwrap_err().contains("not a directory"));
+    }
+}
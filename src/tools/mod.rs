@@ -8,8 +8,24 @@ mod edit;
 mod web_fetch;
 mod todo_write;
 mod task;
+mod deps;
+mod data_preview;
+mod delete;
+mod move_file;
+mod ls;
+mod compare_files;
+mod semantic_search;
+mod docs_search;
+mod shells;
+pub mod trash;
+mod patch;
+pub mod attachment;
+pub mod progress;
+mod interactive;
 
-pub use types::{Tool, ToolDefinition};
+pub use types::{FunctionDefinition, ParametersSchema, ToolContext, ToolDefinition, ToolFuture, ToolImpl, ToolRegistry};
+pub use patch::{PatchEvent, PatchSink};
+pub use attachment::{ImageAttachment, ImageSink};
 pub use bash::BashTool;
 pub use read::ReadTool;
 pub use write::WriteTool;
@@ -18,4 +34,13 @@ pub use grep::GrepTool;
 pub use edit::EditTool;
 pub use web_fetch::WebFetchTool;
 pub use todo_write::TodoWriteTool;
-pub use task::TaskTool;
+pub use task::{ParallelTasksTool, TaskCancelTool, TaskResultTool, TaskStatusTool, TaskTool};
+pub use deps::DepsTool;
+pub use data_preview::DataPreviewTool;
+pub use delete::DeleteTool;
+pub use move_file::MoveTool;
+pub use ls::LsTool;
+pub use compare_files::CompareFilesTool;
+pub use semantic_search::SemanticSearchTool;
+pub use docs_search::DocsSearchTool;
+pub use shells::{BackgroundShells, BashOutputTool, KillShellTool};
@@ -0,0 +1,142 @@
+use crate::tools::trash::Trash;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
+use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use serde_json::Value;
+
+/// Move tool for moving or renaming files and directories; a replaced
+/// destination goes to the trash so `/undo` can restore both
+pub struct MoveTool;
+
+impl ToolImpl for MoveTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "source".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "The file or directory to move"
+            }),
+        );
+        properties.insert(
+            "destination".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "The new path; missing parent directories are created"
+            }),
+        );
+        properties.insert(
+            "overwrite".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Replace an existing destination, moving it to the trash (default: false)"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "move".to_string(),
+                description: "Move or rename a file or directory. The user can reverse it with /undo. Use this instead of `mv` in bash.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["source".to_string(), "destination".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let source = arguments
+                .get("source")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'source' argument".to_string())?;
+            let destination = arguments
+                .get("destination")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'destination' argument".to_string())?;
+            let overwrite = arguments.get("overwrite").and_then(|v| v.as_bool()).unwrap_or(false);
+
+            let from = context.workspace.resolve(source);
+            let to = context.workspace.resolve(destination);
+            context.workspace.check_scope(&from)?;
+            context.workspace.check_scope(&to)?;
+
+            if !from.exists() {
+                return Err(format!("Path '{}' does not exist", source));
+            }
+            let trash = Trash::new(&context.workspace);
+            if trash.protects(&from) || trash.protects(&to) {
+                return Err("Refusing to move files into or out of the trash; use /undo to restore them".to_string());
+            }
+
+            let replaced = trash.relocate(&from, &to, overwrite)?;
+            Ok(match replaced {
+                Some(_) => format!("Moved {} to {} (the previous {} is in the trash)", source, destination, destination),
+                None => format!("Moved {} to {}", source, destination),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::{Workspace, WorkspaceRoot};
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_move_and_undo() {
+        let dir = "/tmp/test_move_tool";
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(format!("{}/a.txt", dir), "a").unwrap();
+        let workspace = Workspace::new(vec![WorkspaceRoot::new("test", dir)]).unwrap();
+        let context = ToolContext::new(workspace.clone());
+
+        let result = MoveTool
+            .execute_with_context(&context, &serde_json::json!({"source": "a.txt", "destination": "docs/b.txt"}))
+            .await
+            .unwrap();
+        assert_eq!(result, "Moved a.txt to docs/b.txt");
+        assert_eq!(std::fs::read_to_string(format!("{}/docs/b.txt", dir)).unwrap(), "a");
+
+        Trash::new(&workspace).undo().unwrap();
+        assert!(Path::new(dir).join("a.txt").exists());
+        assert!(!Path::new(dir).join("docs/b.txt").exists());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_move_overwrite() {
+        let dir = "/tmp/test_move_tool_overwrite";
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(format!("{}/a.txt", dir), "a").unwrap();
+        std::fs::write(format!("{}/b.txt", dir), "b").unwrap();
+        let context = ToolContext::new(Workspace::new(vec![WorkspaceRoot::new("test", dir)]).unwrap());
+
+        let args = serde_json::json!({"source": "a.txt", "destination": "b.txt"});
+        let error = MoveTool.execute_with_context(&context, &args).await.unwrap_err();
+        assert!(error.contains("already exists"));
+
+        let args = serde_json::json!({"source": "a.txt", "destination": "b.txt", "overwrite": true});
+        let result = MoveTool.execute_with_context(&context, &args).await.unwrap();
+        assert!(result.contains("in the trash"));
+        assert_eq!(std::fs::read_to_string(format!("{}/b.txt", dir)).unwrap(), "a");
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
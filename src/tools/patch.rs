@@ -0,0 +1,66 @@
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// A file change proposed to a client that applies edits itself (e.g. an
+/// editor showing a diff preview) instead of the tool writing the file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PatchEvent {
+    /// Path as given by the model
+    pub path: String,
+    /// Current file contents; `None` when the file would be created
+    pub original: Option<String>,
+    pub updated: String,
+}
+
+impl PatchEvent {
+    /// Event as sent over stdio/server transports
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "patch",
+            "path": self.path,
+            "original": self.original,
+            "updated": self.updated,
+        })
+    }
+}
+
+/// Where write/edit send proposed changes when the client applies them
+pub type PatchSink = UnboundedSender<PatchEvent>;
+
+/// Hand `event` to the client and describe the outcome for the model
+pub fn propose(sink: &PatchSink, event: PatchEvent) -> Result<String, String> {
+    let path = event.path.clone();
+    sink.send(event)
+        .map_err(|_| format!("Failed to propose edit to '{}': client disconnected", path))?;
+    Ok(format!("Proposed edit to '{}'; the client will review and apply it", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_event_json() {
+        let event = PatchEvent {
+            path: "a.txt".to_string(),
+            original: None,
+            updated: "new".to_string(),
+        };
+        let json = event.to_json();
+        assert_eq!(json["type"], "patch");
+        assert_eq!(json["path"], "a.txt");
+        assert!(json["original"].is_null());
+    }
+
+    #[test]
+    fn test_propose_to_closed_client() {
+        let (sink, receiver) = tokio::sync::mpsc::unbounded_channel();
+        drop(receiver);
+        let event = PatchEvent {
+            path: "a.txt".to_string(),
+            original: None,
+            updated: String::new(),
+        };
+        assert!(propose(&sink, event).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest output line kept for heartbeats
+const MAX_LINE_CHARS: usize = 80;
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    reporting: bool,
+    bytes: usize,
+    last_line: Option<String>,
+    last_output: Option<Instant>,
+}
+
+/// Output seen so far from a running tool, shared between the tool and
+/// the heartbeat that reports on it
+#[derive(Debug, Clone, Default)]
+pub struct ToolProgress {
+    state: Arc<Mutex<ProgressState>>,
+}
+
+/// What a heartbeat reports about a running tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressSnapshot {
+    /// Whether the tool reports its output; tools that don't are never
+    /// called silent
+    pub reporting: bool,
+    pub bytes: usize,
+    pub last_line: Option<String>,
+    /// Time since the last output, or `None` if there never was any
+    pub silent_for: Option<Duration>,
+}
+
+impl ToolProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark the tool as one that reports its output as it runs
+    pub fn watch(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.reporting = true;
+        }
+    }
+
+    /// Record a chunk of output as it arrives
+    pub fn record_output(&self, chunk: &str) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.reporting = true;
+        state.bytes += chunk.len();
+        state.last_output = Some(Instant::now());
+        if let Some(line) = chunk.lines().rev().map(str::trim).find(|line| !line.is_empty()) {
+            let mut short: String = line.chars().take(MAX_LINE_CHARS).collect();
+            if short.len() < line.len() {
+                short.push('…');
+            }
+            state.last_line = Some(short);
+        }
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        match self.state.lock() {
+            Ok(state) => ProgressSnapshot {
+                reporting: state.reporting,
+                bytes: state.bytes,
+                last_line: state.last_line.clone(),
+                silent_for: state.last_output.map(|at| at.elapsed()),
+            },
+            Err(_) => ProgressSnapshot {
+                reporting: false,
+                bytes: 0,
+                last_line: None,
+                silent_for: None,
+            },
+        }
+    }
+}
+
+impl ProgressSnapshot {
+    /// Heartbeat detail for tools that report output, e.g. `1520 bytes of
+    /// output, last: Compiling serde`
+    pub fn describe(&self, quiet_after: Duration) -> Option<String> {
+        if !self.reporting {
+            return None;
+        }
+        let Some(silent) = self.silent_for else {
+            return Some("no output yet".to_string());
+        };
+        let mut detail = format!("{} bytes of output", self.bytes);
+        if let Some(line) = &self.last_line {
+            detail.push_str(&format!(", last: {}", line));
+        }
+        if silent >= quiet_after {
+            detail.push_str(&format!(" (quiet for {})", format_duration(silent)));
+        }
+        Some(detail)
+    }
+}
+
+/// `850ms`, `3.2s`, `2m14s` or `1h05m`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else if duration.as_millis() >= 1000 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+/// Runtime note appended to the result of a tool that took `elapsed`, so
+/// the model can tell a slow command from a stuck one. Quick calls get no
+/// note; a long quiet stretch at the end is called out.
+pub fn runtime_note(elapsed: Duration, snapshot: &ProgressSnapshot, quiet_after: Duration) -> Option<String> {
+    if elapsed < Duration::from_secs(1) {
+        return None;
+    }
+    let mut note = format!("[ran for {}", format_duration(elapsed));
+    match snapshot.silent_for {
+        Some(silent) if silent >= quiet_after => {
+            note.push_str(&format!("; no output for the last {}", format_duration(silent)));
+        }
+        None if snapshot.reporting && elapsed >= quiet_after => note.push_str("; produced no output"),
+        _ => {}
+    }
+    note.push(']');
+    Some(note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(850)), "850ms");
+        assert_eq!(format_duration(Duration::from_millis(3200)), "3.2s");
+        assert_eq!(format_duration(Duration::from_secs(134)), "2m14s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h05m");
+    }
+
+    #[test]
+    fn test_progress_tracks_last_line() {
+        let progress = ToolProgress::new();
+        assert_eq!(progress.snapshot().silent_for, None);
+
+        progress.record_output("   Compiling serde v1.0\n   Compiling tokio v1.48\n\n");
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.bytes, 50);
+        assert_eq!(snapshot.last_line.as_deref(), Some("Compiling tokio v1.48"));
+        assert!(snapshot.silent_for.is_some());
+
+        progress.record_output(&"x".repeat(200));
+        let line = progress.snapshot().last_line.unwrap();
+        assert!(line.ends_with('…'));
+        assert_eq!(line.chars().count(), MAX_LINE_CHARS + 1);
+    }
+
+    #[test]
+    fn test_runtime_note() {
+        let quiet = Duration::from_secs(10);
+        let snapshot = ProgressSnapshot {
+            reporting: true,
+            bytes: 10,
+            last_line: None,
+            silent_for: Some(Duration::from_secs(2)),
+        };
+        assert_eq!(runtime_note(Duration::from_millis(200), &snapshot, quiet), None);
+        assert_eq!(runtime_note(Duration::from_secs(5), &snapshot, quiet).unwrap(), "[ran for 5.0s]");
+
+        let stuck = ProgressSnapshot {
+            silent_for: Some(Duration::from_secs(100)),
+            ..snapshot
+        };
+        assert_eq!(
+            runtime_note(Duration::from_secs(134), &stuck, quiet).unwrap(),
+            "[ran for 2m14s; no output for the last 1m40s]"
+        );
+
+        let silent = ProgressSnapshot {
+            reporting: true,
+            bytes: 0,
+            last_line: None,
+            silent_for: None,
+        };
+        assert_eq!(
+            runtime_note(Duration::from_secs(30), &silent, quiet).unwrap(),
+            "[ran for 30.0s; produced no output]"
+        );
+        assert_eq!(silent.describe(quiet).as_deref(), Some("no output yet"));
+        assert_eq!(
+            stuck.describe(quiet).as_deref(),
+            Some("10 bytes of output (quiet for 1m40s)")
+        );
+
+        // Tools that don't report output are never called silent
+        let unreported = ProgressSnapshot {
+            reporting: false,
+            ..silent
+        };
+        assert_eq!(runtime_note(Duration::from_secs(30), &unreported, quiet).unwrap(), "[ran for 30.0s]");
+        assert_eq!(unreported.describe(quiet), None);
+    }
+}
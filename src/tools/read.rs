@@ -1,12 +1,67 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::attachment::{self, ImageAttachment};
+use crate::tools::ls::human_size;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use crate::utils::{encode_base64, image_mime};
 use serde_json::Value;
 use tokio::fs;
 use tokio::io::AsyncReadExt;
 
+/// Lines returned when the call gives no `limit`
+const DEFAULT_LIMIT: usize = 2000;
+
+/// Bytes checked for a NUL byte to tell binary files from text, as git does
+const BINARY_SNIFF_BYTES: usize = 8000;
+
 /// Read tool for reading file contents
 pub struct ReadTool;
 
+/// Lines `offset..offset + limit` of `text` (1-based), numbered like
+/// `cat -n`, with a note on how to read on when more lines follow
+fn numbered_lines(text: &str, offset: usize, limit: usize) -> Result<String, String> {
+    let total = text.lines().count();
+    if total == 0 {
+        return Ok(String::new());
+    }
+    if offset > total {
+        return Err(format!("offset {} is past the end of the file ({} lines)", offset, total));
+    }
+    let mut numbered = String::new();
+    for (index, line) in text.lines().enumerate().skip(offset - 1).take(limit) {
+        numbered.push_str(&format!("{:>6}\t{}\n", index + 1, line));
+    }
+    let last = (offset - 1 + limit).min(total);
+    if last < total {
+        numbered.push_str(&format!(
+            "(lines {}-{} of {}; pass offset: {} to read on)\n",
+            offset,
+            last,
+            total,
+            last + 1
+        ));
+    }
+    Ok(numbered)
+}
+
+/// MIME type of a binary file, or `None` for text. Known formats go by
+/// their header, anything else with a NUL byte near the start is binary.
+fn binary_mime(bytes: &[u8]) -> Option<&'static str> {
+    let known = match bytes {
+        [b'%', b'P', b'D', b'F', ..] => Some("application/pdf"),
+        [b'P', b'K', 0x03, 0x04, ..] => Some("application/zip"),
+        [0x1F, 0x8B, ..] => Some("application/gzip"),
+        [0x7F, b'E', b'L', b'F', ..] => Some("application/x-elf"),
+        [0x00, b'a', b's', b'm', ..] => Some("application/wasm"),
+        _ => None,
+    };
+    known.or_else(|| looks_binary(bytes).then_some("application/octet-stream"))
+}
+
+/// Whether `bytes` have a NUL byte near the start, as text never does
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
 impl ToolImpl for ReadTool {
     fn definition(&self) -> ToolDefinition {
         let mut properties = serde_json::Map::new();
@@ -17,12 +72,26 @@ impl ToolImpl for ReadTool {
                 "description": "The absolute path to the file to read (e.g., '/home/user/document.txt')"
             }),
         );
+        properties.insert(
+            "offset".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": "Line number to start reading from, counting from 1 (default: 1)"
+            }),
+        );
+        properties.insert(
+            "limit".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Number of lines to read (default: {})", DEFAULT_LIMIT)
+            }),
+        );
 
         ToolDefinition {
             r#type: "function".to_string(),
             function: FunctionDefinition {
                 name: "read".to_string(),
-                description: "Read the contents of a file from the file system".to_string(),
+                description: "Read a file from the file system. Lines are returned numbered like `cat -n` (the number and a tab come before each line, and are not part of the file); use offset and limit to page through large files. Binary files are described by size and type; images are shown to models that can see them.".to_string(),
                 parameters: ParametersSchema {
                     r#type: "object".to_string(),
                     properties,
@@ -32,25 +101,62 @@ impl ToolImpl for ReadTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let file_path = arguments
-            .get("file_path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'file_path' argument".to_string())?;
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let file_path = arguments
+                .get("file_path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'file_path' argument".to_string())?;
+            let offset = arguments.get("offset").and_then(|v| v.as_u64()).unwrap_or(1).max(1) as usize;
+            let limit = arguments
+                .get("limit")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_LIMIT, |limit| limit as usize);
+            if limit == 0 {
+                return Err("'limit' must be at least 1".to_string());
+            }
+            let resolved_path = context.workspace.resolve(file_path);
+            context.workspace.check_scope(&resolved_path)?;
 
-        // Read the file asynchronously
-        let mut file = fs::File::open(file_path)
-            .await
-            .map_err(|e| format!("Failed to open file '{}': {}", file_path, e))?;
+            // Read the file asynchronously
+            let mut file = fs::File::open(&resolved_path)
+                .await
+                .map_err(|e| format!("Failed to open file '{}': {}", file_path, e))?;
 
-        let mut contents = Vec::new();
-        file.read_to_end(&mut contents)
-            .await
-            .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)
+                .await
+                .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?;
 
-        // Convert to string, replacing any invalid UTF-8 sequences
-        let result = String::from_utf8_lossy(&contents).to_string();
-        Ok(result)
+            let size = human_size(contents.len() as u64);
+            if let Some(mime) = image_mime(&contents) {
+                let Some(images) = &context.images else {
+                    return Ok(format!("'{}' is an image ({}, {}); this model cannot view images", file_path, mime, size));
+                };
+                attachment::attach(images, ImageAttachment {
+                    path: file_path.to_string(),
+                    data: encode_base64(&contents),
+                });
+                return Ok(format!("'{}' is an image ({}, {}), attached below", file_path, mime, size));
+            }
+            if let Some(mime) = binary_mime(&contents) {
+                return Ok(format!("'{}' is a binary file ({}, {}); its contents are not shown", file_path, mime, size));
+            }
+
+            // Convert to string, replacing any invalid UTF-8 sequences
+            let text = String::from_utf8_lossy(&contents);
+            numbered_lines(&text, offset, limit).map_err(|e| format!("Failed to read file '{}': {}", file_path, e))
+        })
     }
 }
 
@@ -71,7 +177,7 @@ mod tests {
 
         let args = serde_json::json!({"file_path": test_file});
         let result = tool.execute(&args).await;
-        assert_eq!(result, Ok("Hello, World!".to_string()));
+        assert_eq!(result, Ok("     1\tHello, World!\n".to_string()));
 
         // Clean up
         fs::remove_file(test_file).await.ok();
@@ -108,12 +214,63 @@ mod tests {
 
         let args = serde_json::json!({"file_path": test_file});
         let result = tool.execute(&args).await;
-        assert_eq!(result, Ok(content.to_string()));
+        assert_eq!(result, Ok("     1\tLine 1\n     2\tLine 2\n     3\tLine 3\n".to_string()));
 
         // Clean up
         fs::remove_file(test_file).await.ok();
     }
 
+    #[tokio::test]
+    async fn test_read_offset_and_limit() {
+        let tool = ReadTool;
+        let test_file = "/tmp/test_read_paged.txt";
+        let content: String = (1..=10).map(|n| format!("line {}\n", n)).collect();
+        fs::write(test_file, content).await.expect("Failed to create test file");
+
+        let args = serde_json::json!({"file_path": test_file, "offset": 4, "limit": 2});
+        assert_eq!(
+            tool.execute(&args).await,
+            Ok("     4\tline 4\n     5\tline 5\n(lines 4-5 of 10; pass offset: 6 to read on)\n".to_string())
+        );
+        let args = serde_json::json!({"file_path": test_file, "offset": 9});
+        assert_eq!(tool.execute(&args).await, Ok("     9\tline 9\n    10\tline 10\n".to_string()));
+
+        let args = serde_json::json!({"file_path": test_file, "offset": 11});
+        assert!(tool.execute(&args).await.unwrap_err().contains("past the end of the file (10 lines)"));
+        let args = serde_json::json!({"file_path": test_file, "limit": 0});
+        assert!(tool.execute(&args).await.is_err());
+
+        fs::remove_file(test_file).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_read_binary_and_image_files() {
+        let tool = ReadTool;
+        let binary = "/tmp/test_read_binary.bin";
+        fs::write(binary, [0x01, 0x00, 0x02, 0x03]).await.unwrap();
+        let image = "/tmp/test_read_image.png";
+        fs::write(image, [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]).await.unwrap();
+
+        let args = serde_json::json!({"file_path": binary});
+        assert_eq!(
+            tool.execute(&args).await,
+            Ok(format!("'{}' is a binary file (application/octet-stream, 4B); its contents are not shown", binary))
+        );
+        let args = serde_json::json!({"file_path": image});
+        assert!(tool.execute(&args).await.unwrap().ends_with("(image/png, 8B); this model cannot view images"));
+
+        // With a vision model the image goes along with the result
+        let sink = crate::tools::ImageSink::default();
+        let context = ToolContext::default().with_images(Some(sink.clone()));
+        let result = tool.execute_with_context(&context, &args).await.unwrap();
+        assert!(result.ends_with("attached below"));
+        assert_eq!(attachment::take_for(&sink, image), vec!["iVBORw0KGgo=".to_string()]);
+        assert!(sink.lock().unwrap().is_empty());
+
+        fs::remove_file(binary).await.ok();
+        fs::remove_file(image).await.ok();
+    }
+
     #[tokio::test]
     async fn test_read_nonexistent_file() {
         let tool = ReadTool;
@@ -0,0 +1,129 @@
+use crate::index::SemanticIndex;
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
+use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Most results one call returns, whatever `limit` asks for
+const MAX_RESULTS: usize = 50;
+
+/// Semantic search tool: the indexed code closest in meaning to a query
+pub struct SemanticSearchTool {
+    index: Arc<SemanticIndex>,
+}
+
+impl SemanticSearchTool {
+    pub fn new(index: Arc<SemanticIndex>) -> Self {
+        Self { index }
+    }
+}
+
+impl ToolImpl for SemanticSearchTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "query".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "What the code does, in words, e.g. 'where retries back off after a rate limit'"
+            }),
+        );
+        properties.insert(
+            "limit".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Chunks returned (default {}, at most {})", self.index.default_results(), MAX_RESULTS)
+            }),
+        );
+        properties.insert(
+            "path".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Only search files under this directory, relative to the workspace root"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "semantic_search".to_string(),
+                description: "Search the code by meaning rather than exact text. Returns the functions, types and sections closest to the query, with their file and lines. Use grep for exact names".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["query".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        _context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let query = arguments
+                .get("query")
+                .and_then(|v| v.as_str())
+                .filter(|query| !query.trim().is_empty())
+                .ok_or_else(|| "Missing 'query' argument".to_string())?;
+            let limit = match arguments.get("limit").and_then(|v| v.as_u64()) {
+                Some(0) => return Err("limit must be greater than 0".to_string()),
+                Some(limit) => limit.min(MAX_RESULTS as u64) as usize,
+                None => self.index.default_results(),
+            };
+            let path = arguments.get("path").and_then(|v| v.as_str());
+
+            let hits = self
+                .index
+                .search(query, limit, path)
+                .await
+                .map_err(|e| format!("Semantic search failed: {}", e))?;
+            if hits.is_empty() {
+                return Ok("No indexed code matches. The index may still be building; /index shows its state".to_string());
+            }
+            let sections: Vec<String> = hits
+                .iter()
+                .map(|hit| format!("--- {} (score {:.2}) ---\n{}", hit.source, hit.score, hit.text))
+                .collect();
+            Ok(sections.join("\n\n"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::semantic::WordEmbedder;
+
+    #[tokio::test]
+    async fn test_semantic_search() {
+        let dir = std::env::temp_dir().join("ariste_test_semantic_search");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(dir.join("src/ui")).unwrap();
+        std::fs::write(dir.join("src/retry.rs"), "/// Wait longer after each rate limit\nfn backoff(attempt: u32) -> u64 {\n    500 << attempt\n}\n").unwrap();
+        std::fs::write(dir.join("src/ui/spinner.rs"), "/// Frames of the waiting spinner\nconst FRAMES: &[&str] = &[];\n").unwrap();
+        let index = Arc::new(SemanticIndex::new(&dir, dir.join("index.json"), Box::new(WordEmbedder)));
+        index.rebuild().await.unwrap();
+        let tool = SemanticSearchTool::new(index);
+
+        let args = serde_json::json!({"query": "rate limit backoff", "limit": 1});
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.starts_with("--- src/retry.rs:1-4 (score "));
+        assert!(result.contains("fn backoff"));
+
+        let args = serde_json::json!({"query": "rate limit backoff", "path": "src/ui"});
+        assert!(tool.execute(&args).await.unwrap().starts_with("--- src/ui/spinner.rs:1-2"));
+        assert!(tool.execute(&serde_json::json!({"query": " "})).await.is_err());
+        let args = serde_json::json!({"query": "rate limit backoff", "limit": u64::MAX});
+        assert!(tool.execute(&args).await.unwrap().contains("fn backoff"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
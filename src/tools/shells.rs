@@ -0,0 +1,469 @@
+use crate::tools::bash::{group_running, kill, shell_command};
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
+use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Unread output kept of each stream of a background shell; older unread
+/// output is dropped
+const MAX_BUFFERED_BYTES: usize = 1_000_000;
+/// Bytes of each stream one bash_output call returns, from the end
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 30_000;
+/// How long bash_output waits for the pipes of an exited shell to drain
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Output of one stream not yet returned by bash_output
+#[derive(Debug, Default)]
+struct Stream {
+    unread: Vec<u8>,
+    dropped: usize,
+    closed: bool,
+}
+
+impl Stream {
+    fn push(&mut self, bytes: &[u8]) {
+        self.unread.extend_from_slice(bytes);
+        if self.unread.len() > MAX_BUFFERED_BYTES {
+            let excess = self.unread.len() - MAX_BUFFERED_BYTES;
+            self.unread.drain(..excess);
+            self.dropped += excess;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Running,
+    /// Exited by itself; no code when a signal ended it
+    Exited(Option<i32>),
+    Killed,
+}
+
+impl std::fmt::Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Exited(Some(code)) => write!(f, "exited with code {}", code),
+            Self::Exited(None) => write!(f, "terminated by signal"),
+            Self::Killed => write!(f, "killed"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shell {
+    command: String,
+    child: Child,
+    state: State,
+    stdout: Arc<Mutex<Stream>>,
+    stderr: Arc<Mutex<Stream>>,
+}
+
+impl Shell {
+    fn poll(&mut self) -> State {
+        if self.state == State::Running
+            && let Ok(Some(status)) = self.child.try_wait()
+        {
+            self.state = State::Exited(status.code());
+        }
+        self.state
+    }
+
+    /// Whether the shell exited but left processes running in its group:
+    /// `nohup server &` exits at once and leaves the server running
+    fn lingering(&mut self) -> bool {
+        matches!(self.poll(), State::Exited(_)) && group_running(&self.child)
+    }
+
+    /// Kill the shell and its process group, which may outlive the shell
+    fn stop(&mut self) {
+        let running = self.poll() == State::Running;
+        if running || self.lingering() {
+            kill(&mut self.child);
+            if running {
+                let _ = self.child.wait();
+            }
+            self.state = State::Killed;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ShellTable {
+    last_id: usize,
+    shells: BTreeMap<usize, Shell>,
+}
+
+impl Drop for ShellTable {
+    fn drop(&mut self) {
+        // 后台进程在自己的进程组里，不会随 ariste 退出，需要在这里结束
+        for shell in self.shells.values_mut() {
+            shell.stop();
+        }
+    }
+}
+
+/// Commands started with `run_in_background`, by shell id. Clones share
+/// the shells; they are killed when the last clone is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct BackgroundShells {
+    table: Arc<Mutex<ShellTable>>,
+}
+
+impl BackgroundShells {
+    /// Start `command` without waiting for it, returning its shell id and pid
+    pub fn spawn(&self, command: &str, cwd: Option<&Path>) -> Result<(String, u32), String> {
+        let mut child = shell_command(command, cwd)
+            .spawn()
+            .map_err(|e| format!("Failed to execute command: {}", e))?;
+        let pid = child.id();
+        let shell = Shell {
+            command: command.to_string(),
+            stdout: read_into(child.stdout.take()),
+            stderr: read_into(child.stderr.take()),
+            child,
+            state: State::Running,
+        };
+        let mut table = self.table.lock().unwrap();
+        table.last_id += 1;
+        let id = table.last_id;
+        table.shells.insert(id, shell);
+        Ok((shell_name(id), pid))
+    }
+
+    /// Status of shell `id` and the output it printed since the last call,
+    /// keeping only the lines matching `filter` when one is given
+    pub async fn output(&self, id: &str, filter: Option<&Regex>, max_bytes: usize) -> Result<String, String> {
+        let (name, command, state, lingering, stdout, stderr) = {
+            let mut table = self.table.lock().unwrap();
+            let known = known_shells(&table);
+            let (name, shell) = parse_id(id)
+                .and_then(|id| Some((shell_name(id), table.shells.get_mut(&id)?)))
+                .ok_or_else(|| format!("No background shell '{}'; {}", id, known))?;
+            let state = shell.poll();
+            let lingering = shell.lingering();
+            (name, shell.command.clone(), state, lingering, shell.stdout.clone(), shell.stderr.clone())
+        };
+        if state != State::Running && !lingering {
+            // 进程已退出，等读线程取完管道里剩下的输出（不持有 table 锁）
+            let deadline = Instant::now() + DRAIN_TIMEOUT;
+            while !(stdout.lock().unwrap().closed && stderr.lock().unwrap().closed) && Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+
+        let mut result = format!("{} (`{}`): {}\n", name, command, state);
+        if lingering {
+            result.push_str("Processes it started are still running; kill_shell stops them\n");
+        }
+        for (stream_name, stream) in [("stdout", &stdout), ("stderr", &stderr)] {
+            let (unread, dropped) = {
+                let mut stream = stream.lock().unwrap();
+                (std::mem::take(&mut stream.unread), std::mem::take(&mut stream.dropped))
+            };
+            let mut text = String::from_utf8_lossy(&unread).into_owned();
+            if let Some(filter) = filter {
+                text = text
+                    .lines()
+                    .filter(|line| filter.is_match(line))
+                    .map(|line| format!("{}\n", line))
+                    .collect();
+            }
+            let mut cut = 0;
+            if text.len() > max_bytes {
+                let mut start = text.len() - max_bytes;
+                while !text.is_char_boundary(start) {
+                    start += 1;
+                }
+                cut = start;
+                text.drain(..start);
+            }
+
+            let new = unread.len() + dropped;
+            match dropped + cut {
+                0 => result.push_str(&format!("--- {} ({} new bytes) ---\n", stream_name, new)),
+                omitted => result.push_str(&format!(
+                    "--- {} ({} new bytes, {} omitted; call bash_output more often or pass a filter) ---\n",
+                    stream_name, new, omitted
+                )),
+            }
+            if !text.is_empty() {
+                result.push_str(&text);
+                if !text.ends_with('\n') {
+                    result.push('\n');
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Kill shell `id` and everything it started
+    pub fn kill(&self, id: &str) -> Result<String, String> {
+        let mut table = self.table.lock().unwrap();
+        let known = known_shells(&table);
+        let (name, shell) = parse_id(id)
+            .and_then(|id| Some((shell_name(id), table.shells.get_mut(&id)?)))
+            .ok_or_else(|| format!("No background shell '{}'; {}", id, known))?;
+        match shell.poll() {
+            State::Running => {
+                shell.stop();
+                Ok(format!("Killed {} (`{}`)", name, shell.command))
+            }
+            state if shell.lingering() => {
+                shell.stop();
+                Ok(format!(
+                    "Killed the processes {} (`{}`) left running (the shell itself: {})",
+                    name, shell.command, state
+                ))
+            }
+            state => Ok(format!("{} (`{}`) is no longer running: {}", name, shell.command, state)),
+        }
+    }
+
+    /// Kill every shell, and whatever they left running
+    pub fn kill_all(&self) {
+        let mut table = self.table.lock().unwrap();
+        for shell in table.shells.values_mut() {
+            shell.stop();
+        }
+    }
+}
+
+fn shell_name(id: usize) -> String {
+    format!("shell_{}", id)
+}
+
+/// `shell_3` or just `3`
+fn parse_id(id: &str) -> Option<usize> {
+    let id = id.trim();
+    id.strip_prefix("shell_").unwrap_or(id).parse().ok()
+}
+
+fn known_shells(table: &ShellTable) -> String {
+    if table.shells.is_empty() {
+        return "none have been started".to_string();
+    }
+    let names: Vec<String> = table.shells.keys().map(|&id| shell_name(id)).collect();
+    format!("known shells: {}", names.join(", "))
+}
+
+/// Read a pipe to the end on its own thread into a shared stream
+fn read_into(pipe: Option<impl Read + Send + 'static>) -> Arc<Mutex<Stream>> {
+    let stream = Arc::new(Mutex::new(Stream::default()));
+    let shared = stream.clone();
+    std::thread::spawn(move || {
+        if let Some(mut pipe) = pipe {
+            let mut buffer = [0u8; 8192];
+            while let Ok(read) = pipe.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                shared.lock().unwrap().push(&buffer[..read]);
+            }
+        }
+        shared.lock().unwrap().closed = true;
+    });
+    stream
+}
+
+fn shell_id_property() -> Value {
+    serde_json::json!({
+        "type": "string",
+        "description": "Shell id returned by bash with run_in_background, e.g. shell_1"
+    })
+}
+
+fn shell_id(arguments: &Value) -> Result<&str, String> {
+    arguments
+        .get("shell_id")
+        .and_then(|v| v.as_str())
+        .filter(|id| !id.trim().is_empty())
+        .ok_or_else(|| "Missing 'shell_id' argument".to_string())
+}
+
+/// Bash output tool: new output and status of a background shell
+pub struct BashOutputTool;
+
+impl ToolImpl for BashOutputTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert("shell_id".to_string(), shell_id_property());
+        properties.insert(
+            "filter".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Regular expression; only output lines matching it are returned. The rest is still consumed"
+            }),
+        );
+        properties.insert(
+            "max_output_bytes".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Bytes returned of each of stdout and stderr, from the end (default {})", DEFAULT_MAX_OUTPUT_BYTES)
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "bash_output".to_string(),
+                description: "Read the output a background shell printed since the last bash_output call, and whether it is still running or its exit code".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["shell_id".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let id = shell_id(arguments)?;
+            let filter = match arguments.get("filter").and_then(|v| v.as_str()) {
+                Some(filter) => Some(Regex::new(filter).map_err(|e| format!("Invalid filter: {}", e))?),
+                None => None,
+            };
+            let max_bytes = match arguments.get("max_output_bytes").and_then(|v| v.as_u64()) {
+                Some(0) => return Err("max_output_bytes must be greater than 0".to_string()),
+                Some(bytes) => bytes as usize,
+                None => DEFAULT_MAX_OUTPUT_BYTES,
+            };
+
+            context.shells.output(id, filter.as_ref(), max_bytes).await
+        })
+    }
+}
+
+/// Kill shell tool: stop a background shell
+pub struct KillShellTool;
+
+impl ToolImpl for KillShellTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert("shell_id".to_string(), shell_id_property());
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "kill_shell".to_string(),
+                description: "Stop a background shell and everything it started. Its remaining output can still be read with bash_output".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["shell_id".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
+
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let id = shell_id(arguments)?.to_string();
+            let shells = context.shells.clone();
+            tokio::task::spawn_blocking(move || shells.kill(&id))
+                .await
+                .map_err(|e| format!("Task join error: {}", e))?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::BashTool;
+
+    /// bash_output with `args` until `done` holds for all it returned so far
+    async fn read_until(context: &ToolContext, args: Value, done: impl Fn(&str) -> bool) -> String {
+        let mut seen = String::new();
+        for _ in 0..250 {
+            seen.push_str(&BashOutputTool.execute_with_context(context, &args).await.unwrap());
+            if done(&seen) {
+                return seen;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("shell never got there: {}", seen);
+    }
+
+    #[tokio::test]
+    async fn test_background_shell_lifecycle() {
+        let context = ToolContext::default();
+        let args = serde_json::json!({"command": "echo ready; sleep 30", "run_in_background": true});
+        let started = BashTool.execute_with_context(&context, &args).await.unwrap();
+        assert!(started.starts_with("Started shell_1 in the background (pid "));
+
+        let args = serde_json::json!({"shell_id": "shell_1"});
+        let output = read_until(&context, args.clone(), |seen| seen.contains("ready\n")).await;
+        assert!(output.starts_with("shell_1 (`echo ready; sleep 30`): running\n"));
+        // Output already read isn't returned again
+        let again = BashOutputTool.execute_with_context(&context, &args).await.unwrap();
+        assert!(again.contains("--- stdout (0 new bytes) ---\n"));
+
+        assert_eq!(KillShellTool.execute_with_context(&context, &args).await.unwrap(), "Killed shell_1 (`echo ready; sleep 30`)");
+        let killed = BashOutputTool.execute_with_context(&context, &args).await.unwrap();
+        assert!(killed.starts_with("shell_1 (`echo ready; sleep 30`): killed\n"));
+        assert!(KillShellTool.execute_with_context(&context, &args).await.unwrap().ends_with("is no longer running: killed"));
+
+        let error = BashOutputTool.execute_with_context(&context, &serde_json::json!({"shell_id": "shell_9"})).await.unwrap_err();
+        assert_eq!(error, "No background shell 'shell_9'; known shells: shell_1");
+    }
+
+    #[tokio::test]
+    async fn test_background_shell_exit_and_filter() {
+        let context = ToolContext::default();
+        let args = serde_json::json!({"command": "printf 'compiling\\nerror: missing ;\\ndone\\n'; exit 2", "run_in_background": true});
+        BashTool.execute_with_context(&context, &args).await.unwrap();
+        let (id, _) = context.shells.spawn("echo second", None).unwrap();
+        assert_eq!(id, "shell_2");
+
+        let args = serde_json::json!({"shell_id": "1", "filter": "^error"});
+        let output = read_until(&context, args, |seen| seen.contains("exited with code 2")).await;
+        assert!(output.starts_with("shell_1 (`printf"));
+        assert!(output.contains("error: missing ;\n"));
+        assert!(!output.contains("compiling\n"));
+    }
+
+    #[tokio::test]
+    async fn test_kill_shell_stops_what_an_exited_shell_left() {
+        let context = ToolContext::default();
+        let args = serde_json::json!({"command": "sleep 30 & echo started", "run_in_background": true});
+        BashTool.execute_with_context(&context, &args).await.unwrap();
+
+        let args = serde_json::json!({"shell_id": "shell_1"});
+        let output = read_until(&context, args.clone(), |seen| seen.contains("exited with code 0")).await;
+        assert!(output.contains("Processes it started are still running"));
+        let killed = KillShellTool.execute_with_context(&context, &args).await.unwrap();
+        assert_eq!(killed, "Killed the processes shell_1 (`sleep 30 & echo started`) left running (the shell itself: exited with code 0)");
+        let output = BashOutputTool.execute_with_context(&context, &args).await.unwrap();
+        assert!(output.starts_with("shell_1 (`sleep 30 & echo started`): killed\n"));
+        assert!(!output.contains("still running"));
+        assert!(KillShellTool.execute_with_context(&context, &args).await.unwrap().ends_with("is no longer running: killed"));
+    }
+}
@@ -1,27 +1,54 @@
-use crate::tools::types::ToolImpl;
+use crate::agent::SubAgentRegistry;
+use crate::tools::types::{ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
 use serde_json::Value;
 
-/// Task tool for spawning subagent tasks
-pub struct TaskTool;
+/// Task tool for spawning subagent tasks. Its `subagent_type` enum lists
+/// the types of the registry it was built from.
+pub struct TaskTool {
+    /// Name and description of each subagent type
+    types: Vec<(String, String)>,
+}
+
+impl TaskTool {
+    pub fn new(registry: &SubAgentRegistry) -> Self {
+        Self {
+            types: registry_types(registry),
+        }
+    }
+}
+
+impl Default for TaskTool {
+    fn default() -> Self {
+        Self::new(&SubAgentRegistry::builtin())
+    }
+}
+
+/// The `subagent_type` property: an enum of the type names, described
+fn subagent_type_schema(types: &[(String, String)]) -> Value {
+    let names: Vec<&str> = types.iter().map(|(name, _)| name.as_str()).collect();
+    let described: Vec<String> = types
+        .iter()
+        .map(|(name, description)| format!("{} ({})", name, description))
+        .collect();
+    serde_json::json!({
+        "type": "string",
+        "description": format!("The type of subagent to spawn: {}", described.join("; ")),
+        "enum": names
+    })
+}
+
+fn registry_types(registry: &SubAgentRegistry) -> Vec<(String, String)> {
+    registry
+        .iter()
+        .map(|profile| (profile.name.clone(), profile.description.clone()))
+        .collect()
+}
 
 impl ToolImpl for TaskTool {
     fn definition(&self) -> ToolDefinition {
         let mut properties = serde_json::Map::new();
-        properties.insert(
-            "subagent_type".to_string(),
-            serde_json::json!({
-                "type": "string",
-                "description": "The type of subagent to spawn",
-                "enum": [
-                    "general-purpose",
-                    "explore",
-                    "plan",
-                    "code-review",
-                    "test-runner"
-                ]
-            }),
-        );
+        properties.insert("subagent_type".to_string(), subagent_type_schema(&self.types));
         properties.insert(
             "prompt".to_string(),
             serde_json::json!({
@@ -47,7 +74,22 @@ impl ToolImpl for TaskTool {
             "include_tools".to_string(),
             serde_json::json!({
                 "type": "boolean",
-                "description": "Whether the subagent should have access to tools (default: false)"
+                "description": "Whether the subagent should have access to tools (default: false). Explore and code-review subagents only get read-only tools (read, glob, grep)."
+            }),
+        );
+
+        properties.insert(
+            "force".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Rerun the subagent even if an identical read-only task was already answered for the current repository state (default: false)"
+            }),
+        );
+        properties.insert(
+            "run_in_background".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Start the subagent and return its task id right away instead of waiting (default: false). Check on it with task_status and collect its answer with task_result."
             }),
         );
 
@@ -65,10 +107,198 @@ impl ToolImpl for TaskTool {
         }
     }
 
-    async fn execute(&self, _arguments: &Value) -> Result<String, String> {
-        // This should never be called directly
-        // Agent::execute_tool handles Task specially by calling spawn_task
-        Err("Task tool must be executed through Agent::execute_tool".to_string())
+    fn execute<'a>(&'a self, _arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            // This should never be called directly
+            // Agent::execute_tool handles Task specially by calling spawn_task
+            Err("Task tool must be executed through Agent::execute_tool".to_string())
+        })
+    }
+}
+
+/// Runs several subagent tasks in one call, a few at a time
+pub struct ParallelTasksTool {
+    types: Vec<(String, String)>,
+}
+
+impl ParallelTasksTool {
+    pub fn new(registry: &SubAgentRegistry) -> Self {
+        Self {
+            types: registry_types(registry),
+        }
+    }
+}
+
+impl Default for ParallelTasksTool {
+    fn default() -> Self {
+        Self::new(&SubAgentRegistry::builtin())
+    }
+}
+
+impl ToolImpl for ParallelTasksTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "tasks".to_string(),
+            serde_json::json!({
+                "type": "array",
+                "description": "Independent tasks to run at the same time",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "subagent_type": subagent_type_schema(&self.types),
+                        "description": {
+                            "type": "string",
+                            "description": "Short summary of the task (3-5 words)"
+                        },
+                        "prompt": {
+                            "type": "string",
+                            "description": "Detailed prompt explaining what the subagent should do"
+                        },
+                        "include_tools": {
+                            "type": "boolean",
+                            "description": "Whether the subagent should have access to tools (default: false)"
+                        }
+                    },
+                    "required": ["subagent_type", "description", "prompt"]
+                }
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "parallel_tasks".to_string(),
+                description: "Launch several subagents at once for tasks that don't depend on each other, e.g. exploring different parts of the codebase. Returns one result per task, in order.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["tasks".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, _arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            // Agent::execute_tool runs the tasks itself
+            Err("parallel_tasks must be executed through Agent::execute_tool".to_string())
+        })
+    }
+}
+
+/// Stops a running background subagent task
+pub struct TaskCancelTool;
+
+impl ToolImpl for TaskCancelTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "task_id".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": "Id of the background task to stop"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "task_cancel".to_string(),
+                description: "Stop a background subagent task that is no longer needed or went in the wrong direction. Its reply in progress ends and no further tools run.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["task_id".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, _arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            // Agent::execute_tool owns the tasks
+            Err("task_cancel must be executed through Agent::execute_tool".to_string())
+        })
+    }
+}
+
+/// Reports how background subagent tasks are doing
+pub struct TaskStatusTool;
+
+impl ToolImpl for TaskStatusTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "task_id".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": "Id of the background task to check (default: all tasks of the session)"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "task_status".to_string(),
+                description: "Check whether background subagent tasks started with run_in_background are still running, and how long they have taken.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, _arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            // Agent::execute_tool owns the tasks
+            Err("task_status must be executed through Agent::execute_tool".to_string())
+        })
+    }
+}
+
+/// Returns the answer of a background subagent task, waiting for it if asked
+pub struct TaskResultTool;
+
+impl ToolImpl for TaskResultTool {
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        properties.insert(
+            "task_id".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": "Id of the background task"
+            }),
+        );
+        properties.insert(
+            "wait".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Wait for the task to finish if it is still running (default: true)"
+            }),
+        );
+
+        ToolDefinition {
+            r#type: "function".to_string(),
+            function: FunctionDefinition {
+                name: "task_result".to_string(),
+                description: "Get the result of a background subagent task started with run_in_background. Waits for it to finish unless wait is false.".to_string(),
+                parameters: ParametersSchema {
+                    r#type: "object".to_string(),
+                    properties,
+                    required: vec!["task_id".to_string()],
+                },
+            },
+        }
+    }
+
+    fn execute<'a>(&'a self, _arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            // Agent::execute_tool owns the tasks
+            Err("task_result must be executed through Agent::execute_tool".to_string())
+        })
     }
 }
 
@@ -78,7 +308,7 @@ mod tests {
 
     #[test]
     fn test_task_definition() {
-        let tool = TaskTool;
+        let tool = TaskTool::default();
         let def = tool.definition();
 
         assert_eq!(def.function.name, "task");
@@ -96,5 +326,39 @@ mod tests {
         let subagent_type = params.properties.get("subagent_type").unwrap();
         let enum_values = subagent_type.get("enum").unwrap().as_array().unwrap();
         assert_eq!(enum_values.len(), 5);
+        assert!(subagent_type["description"].as_str().unwrap().contains("explore (Fast agent"));
+        assert_eq!(params.properties["run_in_background"]["type"], "boolean");
+    }
+
+    #[test]
+    fn test_task_status_and_result_definitions() {
+        let status = TaskStatusTool.definition();
+        assert_eq!(status.function.name, "task_status");
+        assert!(status.function.parameters.required.is_empty());
+
+        let result = TaskResultTool.definition();
+        assert_eq!(result.function.name, "task_result");
+        assert_eq!(result.function.parameters.required, vec!["task_id".to_string()]);
+        assert_eq!(result.function.parameters.properties["wait"]["type"], "boolean");
+    }
+
+    #[test]
+    fn test_task_definition_lists_registered_types() {
+        let mut registry = SubAgentRegistry::builtin();
+        registry.register(crate::agent::SubAgentProfile::new("docs-writer", "Writes documentation"));
+        let def = TaskTool::new(&registry).definition();
+        let enum_values = def.function.parameters.properties["subagent_type"]["enum"].clone();
+        assert_eq!(enum_values.as_array().unwrap().len(), 6);
+        assert_eq!(enum_values[5], "docs-writer");
+    }
+
+    #[test]
+    fn test_parallel_tasks_definition() {
+        let def = ParallelTasksTool::default().definition();
+        assert_eq!(def.function.name, "parallel_tasks");
+        assert_eq!(def.function.parameters.required, vec!["tasks".to_string()]);
+        let items = &def.function.parameters.properties["tasks"]["items"];
+        assert_eq!(items["properties"]["subagent_type"]["enum"].as_array().unwrap().len(), 5);
+        assert_eq!(items["required"], serde_json::json!(["subagent_type", "description", "prompt"]));
     }
 }
@@ -1,4 +1,4 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::types::{ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
 use serde_json::Value;
 use serde::{Deserialize, Serialize};
@@ -58,86 +58,88 @@ impl ToolImpl for TodoWriteTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let todos = arguments
-            .get("todos")
-            .and_then(|v| v.as_array())
-            .ok_or_else(|| "Missing 'todos' argument or it's not an array".to_string())?;
-
-        // Parse todos
-        let parsed_todos: Result<Vec<TodoItem>, String> = todos
-            .iter()
-            .map(|item| {
-                let content = item
-                    .get("content")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| "Missing 'content' field in todo item".to_string())?;
-
-                let status = item
-                    .get("status")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| "Missing 'status' field in todo item".to_string())?;
-
-                let active_form = item
-                    .get("activeForm")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| "Missing 'activeForm' field in todo item".to_string())?;
-
-                // Validate status
-                if !matches!(status, "pending" | "in_progress" | "completed") {
-                    return Err(format!(
-                        "Invalid status '{}': must be one of 'pending', 'in_progress', or 'completed'",
-                        status
-                    ));
-                }
-
-                Ok(TodoItem {
-                    content: content.to_string(),
-                    status: status.to_string(),
-                    active_form: active_form.to_string(),
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let todos = arguments
+                .get("todos")
+                .and_then(|v| v.as_array())
+                .ok_or_else(|| "Missing 'todos' argument or it's not an array".to_string())?;
+
+            // Parse todos
+            let parsed_todos: Result<Vec<TodoItem>, String> = todos
+                .iter()
+                .map(|item| {
+                    let content = item
+                        .get("content")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| "Missing 'content' field in todo item".to_string())?;
+
+                    let status = item
+                        .get("status")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| "Missing 'status' field in todo item".to_string())?;
+
+                    let active_form = item
+                        .get("activeForm")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| "Missing 'activeForm' field in todo item".to_string())?;
+
+                    // Validate status
+                    if !matches!(status, "pending" | "in_progress" | "completed") {
+                        return Err(format!(
+                            "Invalid status '{}': must be one of 'pending', 'in_progress', or 'completed'",
+                            status
+                        ));
+                    }
+
+                    Ok(TodoItem {
+                        content: content.to_string(),
+                        status: status.to_string(),
+                        active_form: active_form.to_string(),
+                    })
                 })
-            })
-            .collect();
-
-        let parsed_todos = parsed_todos?;
-
-        // Count tasks by status
-        let pending_count = parsed_todos
-            .iter()
-            .filter(|t| t.status == "pending")
-            .count();
-        let in_progress_count = parsed_todos
-            .iter()
-            .filter(|t| t.status == "in_progress")
-            .count();
-        let completed_count = parsed_todos
-            .iter()
-            .filter(|t| t.status == "completed")
-            .count();
-
-        // Format output
-        let mut output = String::new();
-        output.push_str("Todo list updated:\n");
-
-        for todo in &parsed_todos {
-            let status_icon = match todo.status.as_str() {
-                "pending" => "○",
-                "in_progress" => "◐",
-                "completed" => "●",
-                _ => "?",
-            };
-            output.push_str(&format!("  {} {}\n", status_icon, todo.active_form));
-        }
-
-        output.push_str(&format!(
-            "\nTotal: {} tasks ({} pending, {} in progress, {} completed)",
-            parsed_todos.len(),
-            pending_count,
-            in_progress_count,
-            completed_count
-        ));
-
-        Ok(output)
+                .collect();
+
+            let parsed_todos = parsed_todos?;
+
+            // Count tasks by status
+            let pending_count = parsed_todos
+                .iter()
+                .filter(|t| t.status == "pending")
+                .count();
+            let in_progress_count = parsed_todos
+                .iter()
+                .filter(|t| t.status == "in_progress")
+                .count();
+            let completed_count = parsed_todos
+                .iter()
+                .filter(|t| t.status == "completed")
+                .count();
+
+            // Format output
+            let mut output = String::new();
+            output.push_str("Todo list updated:\n");
+
+            for todo in &parsed_todos {
+                let status_icon = match todo.status.as_str() {
+                    "pending" => "○",
+                    "in_progress" => "◐",
+                    "completed" => "●",
+                    _ => "?",
+                };
+                output.push_str(&format!("  {} {}\n", status_icon, todo.active_form));
+            }
+
+            output.push_str(&format!(
+                "\nTotal: {} tasks ({} pending, {} in progress, {} completed)",
+                parsed_todos.len(),
+                pending_count,
+                in_progress_count,
+                completed_count
+            ));
+
+            Ok(output)
+        })
     }
 }
 
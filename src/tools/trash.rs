@@ -0,0 +1,272 @@
+use crate::workspace::Workspace;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where deleted files go, relative to the primary workspace root
+pub const TRASH_DIR: &str = ".ariste/trash";
+
+/// Journal of file operations `/undo` can reverse, newest last
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// A file operation made by the delete or move tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FileOp {
+    Delete {
+        path: PathBuf,
+        trashed: PathBuf,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+        /// Where the file previously at `to` was trashed, if it was replaced
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replaced: Option<PathBuf>,
+    },
+}
+
+/// Deleted files kept under `.ariste/trash/` with a journal of what was
+/// deleted or moved, so the operations can be undone
+#[derive(Debug, Clone)]
+pub struct Trash {
+    dir: PathBuf,
+}
+
+/// `rename`, falling back to copy and remove for files on another device
+fn relocate(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    match std::fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) if from.is_file() => {
+            std::fs::copy(from, to)?;
+            std::fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+impl Trash {
+    pub fn new(workspace: &Workspace) -> Self {
+        Self {
+            dir: workspace.resolve(TRASH_DIR),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether deleting or moving `path` would touch the trash: it is the
+    /// trash, inside it, or one of its parents
+    pub fn protects(&self, path: &Path) -> bool {
+        let canonical = |path: &Path| {
+            path.canonicalize()
+                .unwrap_or_else(|_| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()))
+        };
+        let (path, dir) = (canonical(path), canonical(&self.dir));
+        path.starts_with(&dir) || dir.starts_with(&path)
+    }
+
+    /// Move `path` into the trash and return where it went
+    fn put(&self, path: &Path) -> Result<PathBuf, String> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "item".to_string());
+
+        let mut trashed = self.dir.join(format!("{}-{}", millis, name));
+        let mut attempt = 1;
+        while trashed.exists() {
+            trashed = self.dir.join(format!("{}-{}-{}", millis, attempt, name));
+            attempt += 1;
+        }
+        relocate(path, &trashed).map_err(|e| format!("Failed to move '{}' to the trash: {}", path.display(), e))?;
+        Ok(trashed)
+    }
+
+    fn journal(&self) -> PathBuf {
+        self.dir.join(JOURNAL_FILE)
+    }
+
+    fn load(&self) -> Vec<FileOp> {
+        std::fs::read_to_string(self.journal())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    fn save(&self, ops: &[FileOp]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let mut content = String::new();
+        for op in ops {
+            content.push_str(&serde_json::to_string(op).map_err(|e| e.to_string())?);
+            content.push('\n');
+        }
+        std::fs::write(self.journal(), content).map_err(|e| format!("Failed to update the trash journal: {}", e))
+    }
+
+    fn record(&self, op: FileOp) -> Result<(), String> {
+        let mut ops = self.load();
+        ops.push(op);
+        self.save(&ops)
+    }
+
+    /// Move `path` to the trash, recording it for `/undo`
+    pub fn delete(&self, path: &Path) -> Result<PathBuf, String> {
+        let trashed = self.put(path)?;
+        self.record(FileOp::Delete {
+            path: path.to_path_buf(),
+            trashed: trashed.clone(),
+        })?;
+        Ok(trashed)
+    }
+
+    /// Move `from` to `to`, trashing an existing `to` when `overwrite` is set
+    pub fn relocate(&self, from: &Path, to: &Path, overwrite: bool) -> Result<Option<PathBuf>, String> {
+        let replaced = if to.exists() {
+            if !overwrite {
+                return Err(format!("'{}' already exists; pass overwrite: true to replace it", to.display()));
+            }
+            Some(self.put(to)?)
+        } else {
+            None
+        };
+        relocate(from, to).map_err(|e| format!("Failed to move '{}' to '{}': {}", from.display(), to.display(), e))?;
+        self.record(FileOp::Move {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            replaced: replaced.clone(),
+        })?;
+        Ok(replaced)
+    }
+
+    /// Reverse the most recent delete or move. Returns a description of
+    /// what was restored, or `None` when there is nothing to undo. Nothing
+    /// is changed unless the whole operation can be reversed; the journal
+    /// keeps the entry when it cannot.
+    pub fn undo(&self) -> Result<Option<String>, String> {
+        let mut ops = self.load();
+        let Some(op) = ops.pop() else {
+            return Ok(None);
+        };
+
+        let restore = |from: &Path, to: &Path| {
+            relocate(from, to).map_err(|e| format!("Failed to restore '{}': {}", to.display(), e))
+        };
+        // 先检查每一步都能执行，再移动文件
+        let check = |from: &Path, to: &Path| {
+            if std::fs::symlink_metadata(from).is_err() {
+                return Err(format!("Cannot restore '{}': '{}' is gone", to.display(), from.display()));
+            }
+            Ok(())
+        };
+        let vacant = |to: &Path| {
+            if std::fs::symlink_metadata(to).is_ok() {
+                return Err(format!("Cannot restore '{}': something else is there now", to.display()));
+            }
+            Ok(())
+        };
+        let description = match &op {
+            FileOp::Delete { path, trashed } => {
+                check(trashed, path)?;
+                vacant(path)?;
+                restore(trashed, path)?;
+                format!("Restored {}", path.display())
+            }
+            FileOp::Move { from, to, replaced } => {
+                check(to, from)?;
+                vacant(from)?;
+                if let Some(replaced) = replaced {
+                    check(replaced, to)?;
+                }
+                restore(to, from)?;
+                if let Some(replaced) = replaced
+                    && let Err(e) = restore(replaced, to)
+                {
+                    // 第二步失败时撤回第一步，保持移动后的状态
+                    relocate(from, to).ok();
+                    return Err(e);
+                }
+                format!("Moved {} back to {}", to.display(), from.display())
+            }
+        };
+        self.save(&ops)?;
+        Ok(Some(description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::WorkspaceRoot;
+
+    fn workspace(dir: &str) -> Workspace {
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(dir).unwrap();
+        Workspace::new(vec![WorkspaceRoot::new("test", dir)]).unwrap()
+    }
+
+    #[test]
+    fn test_delete_and_undo() {
+        let dir = "/tmp/test_trash_delete";
+        let trash = Trash::new(&workspace(dir));
+        let file = Path::new(dir).join("notes.txt");
+        std::fs::write(&file, "keep me").unwrap();
+
+        let trashed = trash.delete(&file).unwrap();
+        assert!(!file.exists());
+        assert!(trashed.starts_with(trash.dir()));
+        assert!(trash.protects(&trashed));
+        assert!(trash.protects(Path::new(dir)));
+        assert!(!trash.protects(&file));
+
+        assert_eq!(trash.undo().unwrap(), Some(format!("Restored {}", file.display())));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+        assert_eq!(trash.undo().unwrap(), None);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_move_with_overwrite_and_undo() {
+        let dir = "/tmp/test_trash_move";
+        let trash = Trash::new(&workspace(dir));
+        let from = Path::new(dir).join("new.txt");
+        let to = Path::new(dir).join("old.txt");
+        std::fs::write(&from, "new").unwrap();
+        std::fs::write(&to, "old").unwrap();
+
+        assert!(trash.relocate(&from, &to, false).unwrap_err().contains("already exists"));
+        assert!(trash.relocate(&from, &to, true).unwrap().is_some());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+
+        // The replaced file is gone from the trash: nothing is touched
+        let replaced = trash.load().pop().and_then(|op| match op {
+            FileOp::Move { replaced, .. } => replaced,
+            _ => None,
+        });
+        let replaced = replaced.unwrap();
+        std::fs::rename(&replaced, Path::new(dir).join("aside")).unwrap();
+        assert!(trash.undo().unwrap_err().contains("is gone"));
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+        std::fs::rename(Path::new(dir).join("aside"), &replaced).unwrap();
+
+        trash.undo().unwrap();
+        assert_eq!(std::fs::read_to_string(&from).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "old");
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
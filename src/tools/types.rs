@@ -1,3 +1,9 @@
+use crate::tools::attachment::ImageSink;
+use crate::tools::patch::PatchSink;
+use crate::tools::progress::ToolProgress;
+use crate::tools::shells::BackgroundShells;
+use crate::workspace::{ProjectProfile, Workspace};
+use futures_util::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -22,58 +28,171 @@ pub struct ParametersSchema {
     pub required: Vec<String>,
 }
 
-/// Enum representing all available tools
-pub enum Tool {
-    Bash(BashTool),
-    Read(ReadTool),
-    Write(WriteTool),
-    Glob(GlobTool),
-    Grep(GrepTool),
-    Edit(EditTool),
-    WebFetch(WebFetchTool),
-    TodoWrite(TodoWriteTool),
-    Task(TaskTool),
+/// The tools an agent can call, keyed by name in registration order.
+/// Library users and plugins add their own with [`ToolRegistry::register`].
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<(String, Box<dyn ToolImpl>)>,
 }
 
-impl Tool {
-    /// Get the tool definition
-    pub fn definition(&self) -> ToolDefinition {
-        match self {
-            Tool::Bash(tool) => tool.definition(),
-            Tool::Read(tool) => tool.definition(),
-            Tool::Write(tool) => tool.definition(),
-            Tool::Glob(tool) => tool.definition(),
-            Tool::Grep(tool) => tool.definition(),
-            Tool::Edit(tool) => tool.definition(),
-            Tool::WebFetch(tool) => tool.definition(),
-            Tool::TodoWrite(tool) => tool.definition(),
-            Tool::Task(tool) => tool.definition(),
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All tools that ship with the crate
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(BashTool);
+        registry.register(ReadTool);
+        registry.register(WriteTool);
+        registry.register(GlobTool);
+        registry.register(GrepTool);
+        registry.register(EditTool);
+        registry.register(WebFetchTool);
+        registry.register(TodoWriteTool);
+        registry.register(TaskTool::default());
+        registry.register(ParallelTasksTool::default());
+        registry.register(TaskCancelTool);
+        registry.register(TaskStatusTool);
+        registry.register(TaskResultTool);
+        registry.register(DepsTool);
+        registry.register(DataPreviewTool);
+        registry.register(DeleteTool);
+        registry.register(MoveTool);
+        registry.register(LsTool);
+        registry.register(CompareFilesTool);
+        registry.register(BashOutputTool);
+        registry.register(KillShellTool);
+        registry
+    }
+
+    /// Add a tool under the name in its definition. A tool already
+    /// registered under that name is replaced in place and returned.
+    pub fn register(&mut self, tool: impl ToolImpl + 'static) -> Option<Box<dyn ToolImpl>> {
+        let name = tool.definition().function.name;
+        let tool: Box<dyn ToolImpl> = Box::new(tool);
+        match self.tools.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, slot)) => Some(std::mem::replace(slot, tool)),
+            None => {
+                self.tools.push((name, tool));
+                None
+            }
         }
     }
 
-    /// Execute the tool
-    pub async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        match self {
-            Tool::Bash(tool) => tool.execute(arguments).await,
-            Tool::Read(tool) => tool.execute(arguments).await,
-            Tool::Write(tool) => tool.execute(arguments).await,
-            Tool::Glob(tool) => tool.execute(arguments).await,
-            Tool::Grep(tool) => tool.execute(arguments).await,
-            Tool::Edit(tool) => tool.execute(arguments).await,
-            Tool::WebFetch(tool) => tool.execute(arguments).await,
-            Tool::TodoWrite(tool) => tool.execute(arguments).await,
-            Tool::Task(tool) => tool.execute(arguments).await,
+    /// Remove the tool called `name`, returning it if it was registered
+    pub fn unregister(&mut self, name: &str) -> Option<Box<dyn ToolImpl>> {
+        let index = self.tools.iter().position(|(existing, _)| existing == name)?;
+        Some(self.tools.remove(index).1)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn ToolImpl> {
+        self.tools
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, tool)| tool.as_ref())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Definitions of every registered tool, in registration order
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|(_, tool)| tool.definition()).collect()
+    }
+
+    /// Keep only the tools whose name passes `keep`
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.tools.retain(|(name, _)| keep(name));
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+}
+
+/// Agent state that tools may need while executing
+#[derive(Debug, Clone, Default)]
+pub struct ToolContext {
+    pub workspace: Workspace,
+    pub profile: ProjectProfile,
+    /// When set, write/edit propose their changes here instead of writing.
+    /// Delete and move always apply: they can be reversed with `/undo`,
+    /// and go through the usual permission check instead.
+    pub patches: Option<PatchSink>,
+    /// Output reported by long-running tools for heartbeats
+    pub progress: ToolProgress,
+    /// Set when the model accepts images: read passes image files here
+    pub images: Option<ImageSink>,
+    /// Commands bash started in the background, for bash_output and kill_shell
+    pub shells: BackgroundShells,
+}
+
+impl ToolContext {
+    pub fn new(workspace: Workspace) -> Self {
+        Self {
+            workspace,
+            profile: ProjectProfile::default(),
+            patches: None,
+            progress: ToolProgress::default(),
+            images: None,
+            shells: BackgroundShells::default(),
         }
     }
+
+    pub fn with_profile(mut self, profile: ProjectProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_patches(mut self, patches: Option<PatchSink>) -> Self {
+        self.patches = patches;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ToolProgress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn with_images(mut self, images: Option<ImageSink>) -> Self {
+        self.images = images;
+        self
+    }
+
+    pub fn with_shells(mut self, shells: BackgroundShells) -> Self {
+        self.shells = shells;
+        self
+    }
 }
 
-/// Trait that all tools must implement
+/// Future returned by a tool execution
+pub type ToolFuture<'a> = BoxFuture<'a, Result<String, String>>;
+
+/// Trait that all tools must implement. It is object safe so tools can be
+/// registered at runtime as `Box<dyn ToolImpl>`.
 pub trait ToolImpl: Send + Sync {
     /// Returns the tool definition for the AI model
     fn definition(&self) -> ToolDefinition;
 
     /// Executes the tool with the given arguments
-    async fn execute(&self, arguments: &Value) -> Result<String, String>;
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a>;
+
+    /// Executes the tool within the agent's context. Tools that work with
+    /// paths override this; the rest ignore the context.
+    fn execute_with_context<'a>(&'a self, _context: &'a ToolContext, arguments: &'a Value) -> ToolFuture<'a> {
+        self.execute(arguments)
+    }
 }
 
 // Import the actual tool implementations
@@ -85,4 +204,38 @@ pub use crate::tools::grep::GrepTool;
 pub use crate::tools::edit::EditTool;
 pub use crate::tools::web_fetch::WebFetchTool;
 pub use crate::tools::todo_write::TodoWriteTool;
-pub use crate::tools::task::TaskTool;
+pub use crate::tools::task::{ParallelTasksTool, TaskCancelTool, TaskResultTool, TaskStatusTool, TaskTool};
+pub use crate::tools::deps::DepsTool;
+pub use crate::tools::data_preview::DataPreviewTool;
+pub use crate::tools::delete::DeleteTool;
+pub use crate::tools::move_file::MoveTool;
+pub use crate::tools::ls::LsTool;
+pub use crate::tools::compare_files::CompareFilesTool;
+pub use crate::tools::shells::{BashOutputTool, KillShellTool};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut registry = ToolRegistry::builtin();
+        let names: Vec<&str> = registry.names().collect();
+        assert_eq!(names[..3], ["bash", "read", "write"]);
+        assert_eq!(registry.definitions().len(), registry.len());
+
+        // Registering under an existing name replaces the tool in place
+        let count = registry.len();
+        assert!(registry.register(ReadTool).is_some());
+        assert_eq!(registry.len(), count);
+        assert_eq!(registry.names().nth(1), Some("read"));
+
+        assert!(registry.unregister("bash").is_some());
+        assert!(registry.unregister("bash").is_none());
+        assert!(!registry.contains("bash"));
+
+        registry.retain(|name| name == "ls");
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["ls"]);
+        assert!(ToolRegistry::new().is_empty());
+    }
+}
@@ -1,7 +1,93 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::types::{ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
+use crate::utils::html;
+use encoding_rs::{DecoderResult, Encoding, UTF_8, UTF_16BE, UTF_16LE};
 use serde_json::Value;
 
+/// Bytes of the converted body returned unless `max_bytes` says otherwise
+const DEFAULT_MAX_BYTES: usize = 50_000;
+/// Bytes read of any response; the rest is never downloaded
+const MAX_DOWNLOAD_BYTES: usize = 5 * 1024 * 1024;
+
+/// How a response body is shown, by its content type
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Content {
+    /// Converted to markdown
+    Html,
+    /// Pretty-printed
+    Json,
+    Text,
+    /// Images, archives, PDFs and the like: not fetched
+    Binary,
+    /// No or an unfamiliar type; text unless the bytes say otherwise
+    Unknown,
+}
+
+impl Content {
+    fn of(content_type: &str) -> Self {
+        let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        let (kind, subtype) = mime.split_once('/').unwrap_or((mime.as_str(), ""));
+        match (kind, subtype) {
+            ("text", "html") | ("application", "xhtml+xml") => Self::Html,
+            ("application", "json") | (_, "json") => Self::Json,
+            _ if subtype.ends_with("+json") => Self::Json,
+            ("text", _) => Self::Text,
+            ("application", "xml" | "javascript" | "ecmascript" | "x-yaml" | "yaml" | "toml" | "graphql" | "x-www-form-urlencoded") => Self::Text,
+            _ if subtype.ends_with("+xml") => Self::Text,
+            ("image" | "audio" | "video" | "font", _) => Self::Binary,
+            ("application", "octet-stream" | "pdf" | "zip" | "gzip" | "x-gzip" | "x-tar" | "x-7z-compressed" | "wasm" | "vnd.rar") => {
+                Self::Binary
+            }
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The encoding `content_type` names in its `charset`, UTF-8 otherwise
+fn charset(content_type: &str) -> &'static Encoding {
+    content_type
+        .split(';')
+        .skip(1)
+        .find_map(|parameter| {
+            let (name, value) = parameter.split_once('=')?;
+            name.trim().eq_ignore_ascii_case("charset").then(|| value.trim().trim_matches('"'))
+        })
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8)
+}
+
+/// Whether `bytes` look like a binary file rather than text in `encoding`
+fn looks_binary(bytes: &[u8], encoding: &'static Encoding) -> bool {
+    let head = &bytes[..bytes.len().min(8192)];
+    if head.contains(&0) && encoding != UTF_16LE && encoding != UTF_16BE {
+        return true;
+    }
+    // 截断处可能切开一个多字节字符，所以不作为最后一段解码
+    let mut decoder = encoding.new_decoder();
+    let capacity = decoder.max_utf8_buffer_length_without_replacement(head.len()).unwrap_or(head.len() * 3);
+    let mut text = String::with_capacity(capacity);
+    let (result, _) = decoder.decode_to_string_without_replacement(head, &mut text, false);
+    matches!(result, DecoderResult::Malformed(..))
+}
+
+/// `text` cut to `max_bytes` at a character boundary, with a note saying so
+fn truncated(mut text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let total = text.len();
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(&format!(
+        "\n\n... (truncated: {} of {} bytes shown; raise max_bytes to see more)",
+        end, total
+    ));
+    text
+}
+
 /// WebFetch tool for fetching web content
 pub struct WebFetchTool;
 
@@ -43,12 +129,26 @@ impl ToolImpl for WebFetchTool {
                 "description": "Optional request body for POST/PUT requests."
             }),
         );
+        properties.insert(
+            "max_bytes".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "description": format!("Bytes of the (converted) body returned; longer bodies are cut with a notice (default {})", DEFAULT_MAX_BYTES)
+            }),
+        );
+        properties.insert(
+            "raw".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Return HTML and JSON exactly as received instead of converting HTML to markdown and pretty-printing JSON"
+            }),
+        );
 
         ToolDefinition {
             r#type: "function".to_string(),
             function: FunctionDefinition {
                 name: "web_fetch".to_string(),
-                description: "Fetch content from a URL. Supports various HTTP methods, custom headers, and timeouts. Returns the response body as text. Useful for retrieving web pages, API responses, or online resources.".to_string(),
+                description: "Fetch content from a URL. Supports various HTTP methods, custom headers, and timeouts. Web pages come back as markdown of their main text, JSON pretty-printed, and bodies over max_bytes are cut. Binary downloads (images, archives, PDFs) are declined. Useful for retrieving web pages, API responses, or online resources.".to_string(),
                 parameters: ParametersSchema {
                     r#type: "object".to_string(),
                     properties,
@@ -58,82 +158,210 @@ impl ToolImpl for WebFetchTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let url = arguments
-            .get("url")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'url' argument".to_string())?;
-
-        let timeout_secs = arguments
-            .get("timeout")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(30);
-
-        let method = arguments
-            .get("method")
-            .and_then(|v| v.as_str())
-            .unwrap_or("GET");
-
-        let timeout_duration = std::time::Duration::from_secs(timeout_secs);
-
-        // Build HTTP client
-        let client = reqwest::Client::builder()
-            .timeout(timeout_duration)
-            .build()
-            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
-
-        // Build request
-        let mut request = match method.to_uppercase().as_str() {
-            "GET" => client.get(url),
-            "POST" => client.post(url),
-            "PUT" => client.put(url),
-            "DELETE" => client.delete(url),
-            "PATCH" => client.patch(url),
-            "HEAD" => client.head(url),
-            _ => {
-                return Err(format!("Unsupported HTTP method: {}", method));
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let url = arguments
+                .get("url")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'url' argument".to_string())?;
+
+            let timeout_secs = arguments
+                .get("timeout")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(30);
+
+            let method = arguments
+                .get("method")
+                .and_then(|v| v.as_str())
+                .unwrap_or("GET");
+
+            let max_bytes = match arguments.get("max_bytes").and_then(|v| v.as_u64()) {
+                Some(0) => return Err("max_bytes must be greater than 0".to_string()),
+                Some(bytes) => bytes as usize,
+                None => DEFAULT_MAX_BYTES,
+            };
+            let raw = arguments.get("raw").and_then(|v| v.as_bool()).unwrap_or(false);
+
+            let timeout_duration = std::time::Duration::from_secs(timeout_secs);
+
+            // Build HTTP client
+            let client = reqwest::Client::builder()
+                .timeout(timeout_duration)
+                .build()
+                .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+            // Build request
+            let mut request = match method.to_uppercase().as_str() {
+                "GET" => client.get(url),
+                "POST" => client.post(url),
+                "PUT" => client.put(url),
+                "DELETE" => client.delete(url),
+                "PATCH" => client.patch(url),
+                "HEAD" => client.head(url),
+                _ => {
+                    return Err(format!("Unsupported HTTP method: {}", method));
+                }
+            };
+
+            // Add headers if provided
+            if let Some(headers) = arguments.get("headers").and_then(|v| v.as_object()) {
+                for (key, value) in headers {
+                    if let Some(header_value) = value.as_str() {
+                        request = request.header(key, header_value);
+                    }
+                }
+            }
+
+            // Add body if provided
+            if let Some(body) = arguments.get("body").and_then(|v| v.as_str()) {
+                request = request.body(body.to_string());
             }
-        };
 
-        // Add headers if provided
-        if let Some(headers) = arguments.get("headers").and_then(|v| v.as_object()) {
-            for (key, value) in headers {
-                if let Some(header_value) = value.as_str() {
-                    request = request.header(key, header_value);
+            // Execute request
+            let mut response = request
+                .send()
+                .await
+                .map_err(|e| format!("Request failed: {}", e))?;
+
+            let status = response.status();
+            let url_final = response.url().clone();
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let declined = |size: Option<u64>| {
+                let size = size.map(|size| format!(", {} bytes", size)).unwrap_or_default();
+                format!(
+                    "{} is a binary file ({}{}), not text; download it with bash (curl -o <file>) if you need it",
+                    url_final,
+                    if content_type.is_empty() { "no content type" } else { &content_type },
+                    size
+                )
+            };
+            let content = Content::of(&content_type);
+            if content == Content::Binary {
+                return Err(declined(response.content_length()));
+            }
+
+            // 只读取上限以内的部分，超大的响应不会全部下载
+            let mut body = Vec::new();
+            let mut complete = true;
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| format!("Failed to read response body: {}", e))?
+            {
+                body.extend_from_slice(&chunk);
+                if body.len() >= MAX_DOWNLOAD_BYTES {
+                    body.truncate(MAX_DOWNLOAD_BYTES);
+                    complete = false;
+                    break;
                 }
             }
-        }
+            // 按响应声明的字符集解码，和 reqwest 的 text() 一样
+            let encoding = charset(&content_type);
+            if content == Content::Unknown && looks_binary(&body, encoding) {
+                return Err(declined(None));
+            }
 
-        // Add body if provided
-        if let Some(body) = arguments.get("body").and_then(|v| v.as_str()) {
-            request = request.body(body.to_string());
-        }
+            let text = encoding.decode(&body).0.into_owned();
+            let text = match content {
+                Content::Html if !raw => html::readable(&text),
+                Content::Json if !raw && complete => serde_json::from_str::<Value>(&text)
+                    .ok()
+                    .and_then(|json| serde_json::to_string_pretty(&json).ok())
+                    .unwrap_or(text),
+                _ => text,
+            };
+            let mut text = truncated(text, max_bytes);
+            if !complete {
+                text.push_str(&format!("\n\n(download stopped after {} MB)", MAX_DOWNLOAD_BYTES / (1024 * 1024)));
+            }
 
-        // Execute request
-        let response = request
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
-
-        let status = response.status();
-        let url_final = response.url().clone();
-
-        let body = response
-            .text()
-            .await
-            .map_err(|e| format!("Failed to read response body: {}", e))?;
-
-        // Return formatted result
-        Ok(format!(
-            "Status: {}\nURL: {}\n\n{}",
-            status, url_final, body
-        ))
+            // Return formatted result
+            Ok(format!(
+                "Status: {}\nURL: {}\nContent-Type: {}\n\n{}",
+                status, url_final, content_type, text
+            ))
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A server answering every request with `body` as `content_type`;
+    /// returns its URL
+    async fn serve(content_type: &'static str, body: &'static [u8]) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = [0u8; 4096];
+                let _ = socket.read(&mut buffer).await;
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    content_type,
+                    body.len()
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(body).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_web_fetch_converts_by_content_type() {
+        let page = b"<html><head><title>Docs</title><script>x()</script></head><body><nav>Home</nav><main><h1>Install</h1><p>Run <code>cargo install</code>.</p></main></body></html>";
+        let url = serve("text/html; charset=utf-8", page).await;
+        let result = WebFetchTool.execute(&serde_json::json!({"url": url})).await.unwrap();
+        assert!(result.starts_with("Status: 200 OK\n"));
+        assert!(result.ends_with("Content-Type: text/html; charset=utf-8\n\n# Install\n\nRun `cargo install`."));
+        let raw = WebFetchTool.execute(&serde_json::json!({"url": url, "raw": true})).await.unwrap();
+        assert!(raw.contains("<nav>Home</nav>"));
+
+        let url = serve("application/json", br#"{"name":"ariste","tags":["cli"]}"#).await;
+        let result = WebFetchTool.execute(&serde_json::json!({"url": url})).await.unwrap();
+        assert!(result.ends_with("{\n  \"name\": \"ariste\",\n  \"tags\": [\n    \"cli\"\n  ]\n}"));
+
+        let url = serve("image/png", b"\x89PNG\r\n\x1a\n\0\0\0").await;
+        let error = WebFetchTool.execute(&serde_json::json!({"url": url})).await.unwrap_err();
+        assert!(error.contains("is a binary file (image/png, 11 bytes)"));
+        // Without a type the bytes decide
+        let url = serve("application/x-unknown", b"PK\x03\x04\0\0binary").await;
+        assert!(WebFetchTool.execute(&serde_json::json!({"url": url})).await.unwrap_err().contains("binary file"));
+    }
+
+    #[tokio::test]
+    async fn test_web_fetch_decodes_charset() {
+        // 你好 in GBK
+        let url = serve("text/html; charset=GBK", b"<html><body><p>\xc4\xe3\xba\xc3</p></body></html>").await;
+        let result = WebFetchTool.execute(&serde_json::json!({"url": url})).await.unwrap();
+        assert!(result.ends_with("\n\n你好"));
+        // こんにちは in Shift-JIS, under a type that leaves it to the bytes
+        let url = serve("application/x-notes; charset=\"shift_jis\"", b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd").await;
+        let result = WebFetchTool.execute(&serde_json::json!({"url": url})).await.unwrap();
+        assert!(result.ends_with("\n\nこんにちは"));
+        let url = serve("text/plain; charset=iso-8859-1", b"caf\xe9").await;
+        assert!(WebFetchTool.execute(&serde_json::json!({"url": url})).await.unwrap().ends_with("\n\ncafé"));
+
+        assert_eq!(charset("text/html"), UTF_8);
+        assert!(looks_binary(b"\x82\xb1\x82", UTF_8));
+        assert!(!looks_binary(b"\x82\xb1\x82", encoding_rs::SHIFT_JIS));
+    }
+
+    #[tokio::test]
+    async fn test_web_fetch_max_bytes() {
+        let url = serve("text/plain", b"0123456789abcdefghij").await;
+        let result = WebFetchTool.execute(&serde_json::json!({"url": url, "max_bytes": 8})).await.unwrap();
+        assert!(result.ends_with("\n\n01234567\n\n... (truncated: 8 of 20 bytes shown; raise max_bytes to see more)"));
+        assert!(WebFetchTool.execute(&serde_json::json!({"url": url, "max_bytes": 0})).await.is_err());
+    }
 
     #[tokio::test]
     async fn test_web_fetch_simple() {
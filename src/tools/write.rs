@@ -1,7 +1,32 @@
-use crate::tools::types::ToolImpl;
+use crate::tools::patch::{self, PatchEvent};
+use crate::tools::types::{ToolContext, ToolFuture, ToolImpl};
 use crate::tools::types::{ToolDefinition, FunctionDefinition, ParametersSchema};
 use serde_json::Value;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// How `write` treats an existing file
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WriteMode {
+    Overwrite,
+    Append,
+    /// Fail if the file already exists
+    CreateNew,
+}
+
+impl WriteMode {
+    fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value {
+            None | Some("overwrite") => Ok(WriteMode::Overwrite),
+            Some("append") => Ok(WriteMode::Append),
+            Some("create_new") => Ok(WriteMode::CreateNew),
+            Some(other) => Err(format!(
+                "Invalid mode '{}': expected overwrite, append or create_new",
+                other
+            )),
+        }
+    }
+}
 
 /// Write tool for writing content to files
 pub struct WriteTool;
@@ -23,12 +48,27 @@ impl ToolImpl for WriteTool {
                 "description": "The content to write to the file"
             }),
         );
+        properties.insert(
+            "mode".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "enum": ["overwrite", "append", "create_new"],
+                "description": "overwrite (default) replaces the file, append adds to the end, create_new fails if the file already exists"
+            }),
+        );
+        properties.insert(
+            "create_dirs".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Create missing parent directories (default: true)"
+            }),
+        );
 
         ToolDefinition {
             r#type: "function".to_string(),
             function: FunctionDefinition {
                 name: "write".to_string(),
-                description: "Write content to a file. Creates the file if it doesn't exist, overwrites if it does.".to_string(),
+                description: "Write content to a file. Creates the file (and its parent directories) if it doesn't exist; overwrites, appends to or refuses an existing file depending on mode.".to_string(),
                 parameters: ParametersSchema {
                     r#type: "object".to_string(),
                     properties,
@@ -38,23 +78,81 @@ impl ToolImpl for WriteTool {
         }
     }
 
-    async fn execute(&self, arguments: &Value) -> Result<String, String> {
-        let file_path = arguments
-            .get("file_path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'file_path' argument".to_string())?;
+    fn execute<'a>(&'a self, arguments: &'a Value) -> ToolFuture<'a> {
+        Box::pin(async move {
+            self.execute_with_context(&ToolContext::default(), arguments).await
+        })
+    }
 
-        let content = arguments
-            .get("content")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| "Missing 'content' argument".to_string())?;
+    fn execute_with_context<'a>(
+        &'a self,
+        context: &'a ToolContext,
+        arguments: &'a Value,
+    ) -> ToolFuture<'a> {
+        Box::pin(async move {
+            let file_path = arguments
+                .get("file_path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'file_path' argument".to_string())?;
+            let resolved_path = context.workspace.resolve(file_path);
+            context.workspace.check_workdir(&resolved_path)?;
 
-        // Write to the file asynchronously
-        fs::write(file_path, content)
-            .await
-            .map_err(|e| format!("Failed to write to file '{}': {}", file_path, e))?;
+            let content = arguments
+                .get("content")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "Missing 'content' argument".to_string())?;
+            let mode = WriteMode::parse(arguments.get("mode").and_then(|v| v.as_str()))?;
+            let create_dirs = arguments.get("create_dirs").and_then(|v| v.as_bool()).unwrap_or(true);
+
+            // The client applies the change itself
+            if let Some(sink) = &context.patches {
+                let original = fs::read_to_string(&resolved_path).await.ok();
+                let updated = match (mode, &original) {
+                    (WriteMode::CreateNew, Some(_)) => {
+                        return Err(format!("File '{}' already exists", file_path));
+                    }
+                    (WriteMode::Append, Some(original)) => format!("{}{}", original, content),
+                    _ => content.to_string(),
+                };
+                return patch::propose(
+                    sink,
+                    PatchEvent {
+                        path: file_path.to_string(),
+                        original,
+                        updated,
+                    },
+                );
+            }
+
+            if create_dirs
+                && let Some(parent) = resolved_path.parent()
+                && !parent.as_os_str().is_empty()
+            {
+                fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| format!("Failed to create directories for '{}': {}", file_path, e))?;
+            }
+
+            // Write to the file asynchronously
+            let mut options = fs::OpenOptions::new();
+            match mode {
+                WriteMode::Overwrite => options.write(true).create(true).truncate(true),
+                WriteMode::Append => options.append(true).create(true),
+                WriteMode::CreateNew => options.write(true).create_new(true),
+            };
+            let mut file = options.open(&resolved_path).await.map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => format!("File '{}' already exists", file_path),
+                _ => format!("Failed to write to file '{}': {}", file_path, e),
+            })?;
+            file.write_all(content.as_bytes())
+                .await
+                .map_err(|e| format!("Failed to write to file '{}': {}", file_path, e))?;
 
-        Ok(format!("Successfully wrote to file: {}", file_path))
+            Ok(match mode {
+                WriteMode::Append => format!("Successfully appended to file: {}", file_path),
+                _ => format!("Successfully wrote to file: {}", file_path),
+            })
+        })
     }
 }
 
@@ -158,6 +256,69 @@ mod tests {
         fs::remove_file(test_file).await.ok();
     }
 
+    #[tokio::test]
+    async fn test_write_creates_parent_dirs() {
+        let tool = WriteTool;
+        let dir = "/tmp/test_write_dirs";
+        fs::remove_dir_all(dir).await.ok();
+        let test_file = "/tmp/test_write_dirs/a/b/c.txt";
+
+        let args = serde_json::json!({"file_path": test_file, "content": "x", "create_dirs": false});
+        assert!(tool.execute(&args).await.is_err());
+
+        let args = serde_json::json!({"file_path": test_file, "content": "x"});
+        assert!(tool.execute(&args).await.is_ok());
+        assert_eq!(fs::read_to_string(test_file).await.unwrap(), "x");
+
+        fs::remove_dir_all(dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_write_append_and_create_new() {
+        let tool = WriteTool;
+        let test_file = "/tmp/test_write_modes.txt";
+        fs::remove_file(test_file).await.ok();
+
+        let args = serde_json::json!({"file_path": test_file, "content": "one\n", "mode": "create_new"});
+        assert!(tool.execute(&args).await.is_ok());
+        assert_eq!(
+            tool.execute(&args).await,
+            Err(format!("File '{}' already exists", test_file))
+        );
+
+        let args = serde_json::json!({"file_path": test_file, "content": "two\n", "mode": "append"});
+        assert_eq!(
+            tool.execute(&args).await,
+            Ok(format!("Successfully appended to file: {}", test_file))
+        );
+        assert_eq!(fs::read_to_string(test_file).await.unwrap(), "one\ntwo\n");
+
+        let args = serde_json::json!({"file_path": test_file, "content": "x", "mode": "prepend"});
+        assert!(tool.execute(&args).await.unwrap_err().contains("Invalid mode 'prepend'"));
+
+        fs::remove_file(test_file).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_write_proposes_patch() {
+        let tool = WriteTool;
+        let test_file = "/tmp/test_write_proposed.txt";
+        fs::remove_file(test_file).await.ok();
+
+        let (sink, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let context = ToolContext::default().with_patches(Some(sink));
+        let args = serde_json::json!({"file_path": test_file, "content": "Hello"});
+
+        let result = tool.execute_with_context(&context, &args).await;
+        assert!(result.unwrap().contains("Proposed edit"));
+        assert!(!std::path::Path::new(test_file).exists());
+
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.path, test_file);
+        assert_eq!(event.original, None);
+        assert_eq!(event.updated, "Hello");
+    }
+
     #[tokio::test]
     async fn test_write_missing_file_path() {
         let tool = WriteTool;
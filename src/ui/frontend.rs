@@ -0,0 +1,131 @@
+use crate::ui::glyphs;
+use std::sync::Arc;
+
+/// Kind of a notification, which decides how it is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notice {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// A piece of a reply as the model generates it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFragment<'a> {
+    Thinking(&'a str),
+    Content(&'a str),
+    /// The reply is complete
+    End,
+}
+
+/// Answer to a permission request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approval {
+    /// Allow this call
+    Once,
+    /// Allow this call and every identical one, remembered in settings
+    Always,
+    /// Refuse this call
+    Deny,
+    /// Refuse this call and every identical one, remembered in settings
+    Never,
+}
+
+/// Where the agent loop talks to the user. The library itself never
+/// writes to the terminal: the CLI implements this for its terminal, and a
+/// TUI, server or stdio client implements it to drive the same loop.
+pub trait Frontend: Send + Sync + std::fmt::Debug {
+    /// The next prompt from the user, or `None` when input has ended
+    fn prompt(&self) -> Option<String>;
+
+    /// A reply was requested and nothing of it has arrived yet; the
+    /// terminal shows a spinner until the first fragment
+    fn waiting(&self) {}
+
+    /// A fragment of the reply being streamed. `End` follows every reply
+    /// that was waited on, streamed or not.
+    fn stream(&self, fragment: StreamFragment<'_>);
+
+    /// A reply that was not streamed, shown whole
+    fn response(&self, content: &str);
+
+    /// The tool calls an answer is based on
+    fn citations(&self, citations: &[String]);
+
+    fn tool_start(&self, name: &str, args: Option<&str>);
+
+    /// A tool is still running after `elapsed`; `detail` is its latest output
+    fn tool_heartbeat(&self, elapsed: &str, detail: Option<&str>);
+
+    fn tool_result(&self, name: &str, result: &str);
+
+    fn tool_error(&self, error: &str);
+
+    /// Ask the user to approve something; `false` declines
+    fn confirm(&self, question: &str) -> bool;
+
+    /// A unified diff of changes for the user to review. Shown as a notice
+    /// unless overridden.
+    fn diff(&self, diff: &str) {
+        self.notify(Notice::Info, diff);
+    }
+
+    /// Ask whether a tool may run. Frontends without a way to answer
+    /// "always" or "never" get a plain yes/no question.
+    fn approve(&self, request: &str) -> Approval {
+        if self.confirm(request) { Approval::Once } else { Approval::Deny }
+    }
+
+    fn notify(&self, notice: Notice, message: &str);
+
+    /// The turn paused for a correction (see `RedirectToken`): ask the user
+    /// for guidance to add before it goes on. `None` goes on unchanged.
+    fn redirect(&self) -> Option<String> {
+        None
+    }
+
+    /// A line of what subagent `task` (e.g. `task#3`) is doing: part of its
+    /// reply or one of its tool calls. Shown as a notice unless overridden.
+    fn subagent_output(&self, task: &str, line: &str) {
+        self.notify(Notice::Info, &format!("{} {} {}", task, glyphs().border(), line));
+    }
+}
+
+/// Shows nothing and declines every question: the frontend of an agent
+/// until `Agent::set_frontend` gives it one
+#[derive(Debug, Default)]
+pub struct SilentFrontend;
+
+impl SilentFrontend {
+    /// Shared handle, as held by the agent
+    pub fn shared() -> Arc<dyn Frontend> {
+        Arc::new(Self)
+    }
+}
+
+impl Frontend for SilentFrontend {
+    fn prompt(&self) -> Option<String> {
+        None
+    }
+
+    fn stream(&self, _fragment: StreamFragment<'_>) {}
+
+    fn response(&self, _content: &str) {}
+
+    fn citations(&self, _citations: &[String]) {}
+
+    fn tool_start(&self, _name: &str, _args: Option<&str>) {}
+
+    fn tool_heartbeat(&self, _elapsed: &str, _detail: Option<&str>) {}
+
+    fn tool_result(&self, _name: &str, _result: &str) {}
+
+    fn tool_error(&self, _error: &str) {}
+
+    fn confirm(&self, _question: &str) -> bool {
+        false
+    }
+
+    fn notify(&self, _notice: Notice, _message: &str) {}
+}
@@ -0,0 +1,243 @@
+use crate::config::GlyphConfig;
+use std::sync::RwLock;
+
+/// Terminals known to lack the emoji and box-drawing characters
+const ASCII_TERMS: &[&str] = &["dumb", "linux", "vt100", "vt102", "vt220", "ansi", "cons25"];
+
+/// Environment variable that turns on accessibility mode
+pub const ACCESSIBLE_ENV: &str = "ARISTE_ACCESSIBLE";
+
+/// Symbols the terminal UI draws with. The configurable ones (prompt, tool
+/// and status icons) can be replaced one by one; in ASCII mode every
+/// symbol, box lines included, falls back to plain ASCII. Accessibility
+/// mode goes further for screen readers: status icons become words and
+/// the UI writes one plain line per event (see `UI`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glyphs {
+    pub ascii: bool,
+    pub accessible: bool,
+    pub prompt: String,
+    pub tool: String,
+    pub success: String,
+    pub error: String,
+    pub warning: String,
+    pub info: String,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+impl Glyphs {
+    pub fn unicode() -> Self {
+        Self {
+            ascii: false,
+            accessible: false,
+            prompt: "⟩".to_string(),
+            tool: "🔨".to_string(),
+            success: "✓".to_string(),
+            error: "✖".to_string(),
+            warning: "⚠".to_string(),
+            info: "ℹ".to_string(),
+        }
+    }
+
+    pub fn ascii() -> Self {
+        Self {
+            ascii: true,
+            accessible: false,
+            prompt: ">".to_string(),
+            tool: "*".to_string(),
+            success: "+".to_string(),
+            error: "x".to_string(),
+            warning: "!".to_string(),
+            info: "i".to_string(),
+        }
+    }
+
+    /// Words read out in place of icons, with ASCII for everything else
+    pub fn accessible() -> Self {
+        Self {
+            ascii: true,
+            accessible: true,
+            prompt: ">".to_string(),
+            tool: "Running tool".to_string(),
+            success: "Done:".to_string(),
+            error: "Error:".to_string(),
+            warning: "Warning:".to_string(),
+            info: "Note:".to_string(),
+        }
+    }
+
+    /// Symbols from the `glyphs` settings. Without an explicit `ascii`
+    /// setting, ASCII mode is picked from the locale and TERM. Configured
+    /// symbols still apply in accessibility mode.
+    pub fn from_settings(config: Option<&GlyphConfig>, accessible: bool) -> Self {
+        let config = config.cloned().unwrap_or_default();
+        let ascii = accessible || config.ascii.unwrap_or_else(|| {
+            let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find(|value| !value.is_empty());
+            needs_ascii(locale.as_deref(), std::env::var("TERM").ok().as_deref())
+        });
+        let builtin = if accessible {
+            Self::accessible()
+        } else if ascii {
+            Self::ascii()
+        } else {
+            Self::unicode()
+        };
+        let pick = |configured: Option<String>, fallback: String| configured.filter(|glyph| !glyph.is_empty()).unwrap_or(fallback);
+        Self {
+            ascii,
+            accessible,
+            prompt: pick(config.prompt, builtin.prompt),
+            tool: pick(config.tool, builtin.tool),
+            success: pick(config.success, builtin.success),
+            error: pick(config.error, builtin.error),
+            warning: pick(config.warning, builtin.warning),
+            info: pick(config.info, builtin.info),
+        }
+    }
+
+    /// Picks the plain or the ASCII form of a fixed glyph
+    fn either(&self, unicode: &'static str, ascii: &'static str) -> &'static str {
+        if self.ascii { ascii } else { unicode }
+    }
+
+    /// Welcome and goodbye mark
+    pub fn star(&self) -> &'static str {
+        self.either("✦", "*")
+    }
+
+    /// Left edge of thinking blocks and the welcome message
+    pub fn border(&self) -> &'static str {
+        self.either("│", "|")
+    }
+
+    pub fn corner_top(&self) -> &'static str {
+        self.either("┌", "+")
+    }
+
+    pub fn corner_bottom(&self) -> &'static str {
+        self.either("└", "+")
+    }
+
+    /// Horizontal line of the turn separator
+    pub fn rule(&self) -> &'static str {
+        self.either("─", "-")
+    }
+
+    /// Banner box: corners (top left, top right, bottom left, bottom
+    /// right), then the horizontal and vertical edges
+    pub fn banner_box(&self) -> [&'static str; 6] {
+        if self.ascii {
+            ["+", "+", "+", "+", "=", "|"]
+        } else {
+            ["╔", "╗", "╚", "╝", "═", "║"]
+        }
+    }
+
+    /// Marks the tool calls an answer was based on
+    pub fn citation(&self) -> &'static str {
+        self.either("↳", "->")
+    }
+
+    /// Marks a heartbeat of a tool still running
+    pub fn heartbeat(&self) -> &'static str {
+        self.either("⋯", "...")
+    }
+
+    pub fn ellipsis(&self) -> &'static str {
+        self.either("…", "...")
+    }
+}
+
+/// Whether accessibility mode is on: the `accessible` setting, or else
+/// `ARISTE_ACCESSIBLE` set to anything but `0`/`false`
+pub fn accessibility_requested(setting: Option<bool>) -> bool {
+    setting.unwrap_or_else(|| {
+        std::env::var(ACCESSIBLE_ENV)
+            .map(|value| !matches!(value.trim().to_lowercase().as_str(), "" | "0" | "false" | "no" | "off"))
+            .unwrap_or(false)
+    })
+}
+
+/// Whether the locale (`LC_ALL`/`LC_CTYPE`/`LANG`) or terminal can't show
+/// Unicode symbols: a non-UTF-8 locale such as `C` or `POSIX`, or a
+/// terminal like the Linux console. An unset locale is left to the terminal.
+pub fn needs_ascii(locale: Option<&str>, term: Option<&str>) -> bool {
+    if let Some(term) = term
+        && ASCII_TERMS.contains(&term)
+    {
+        return true;
+    }
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            !(locale.contains("utf-8") || locale.contains("utf8"))
+        }
+        None => false,
+    }
+}
+
+// 全局符号：启动时按配置设置一次
+static GLYPHS: RwLock<Option<Glyphs>> = RwLock::new(None);
+
+/// Symbols in use, set with `configure`
+pub fn glyphs() -> Glyphs {
+    GLYPHS.read().ok().and_then(|glyphs| glyphs.clone()).unwrap_or_default()
+}
+
+/// Set the symbols used from now on
+pub fn configure(glyphs: Glyphs) {
+    if let Ok(mut current) = GLYPHS.write() {
+        *current = Some(glyphs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_ascii() {
+        assert!(!needs_ascii(Some("en_US.UTF-8"), Some("xterm-256color")));
+        assert!(!needs_ascii(Some("zh_CN.utf8"), None));
+        assert!(needs_ascii(Some("C"), Some("xterm")));
+        assert!(needs_ascii(Some("POSIX"), None));
+        assert!(needs_ascii(Some("en_US.UTF-8"), Some("linux")));
+        assert!(!needs_ascii(None, Some("xterm")));
+    }
+
+    #[test]
+    fn test_glyphs_from_settings() {
+        let config = GlyphConfig {
+            ascii: Some(true),
+            prompt: Some("$".to_string()),
+            tool: Some(String::new()),
+            ..Default::default()
+        };
+        let glyphs = Glyphs::from_settings(Some(&config), false);
+        assert_eq!(glyphs.prompt, "$");
+        // An empty glyph keeps the built-in one of the mode
+        assert_eq!(glyphs.tool, "*");
+        assert_eq!(glyphs.success, "+");
+        assert_eq!(glyphs.banner_box()[0], "+");
+        assert!(glyphs.rule().is_ascii());
+
+        let glyphs = Glyphs::from_settings(Some(&GlyphConfig { ascii: Some(false), ..Default::default() }), false);
+        assert_eq!(glyphs, Glyphs::unicode());
+        assert_eq!(glyphs.border(), "│");
+
+        // Accessibility mode wins over `ascii: false`
+        let glyphs = Glyphs::from_settings(Some(&GlyphConfig { ascii: Some(false), ..Default::default() }), true);
+        assert_eq!(glyphs, Glyphs::accessible());
+        assert_eq!(glyphs.error, "Error:");
+        assert_eq!(glyphs.ellipsis(), "...");
+        assert!(accessibility_requested(Some(true)));
+    }
+}
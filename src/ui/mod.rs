@@ -1,3 +1,5 @@
-mod terminal;
+mod frontend;
+pub mod glyphs;
 
-pub use terminal::UI;
+pub use frontend::{Approval, Frontend, Notice, SilentFrontend, StreamFragment};
+pub use glyphs::{Glyphs, glyphs};
@@ -0,0 +1,55 @@
+use crate::error::Error;
+use serde_json::{json, Value};
+
+const GISTS_API: &str = "https://api.github.com/gists";
+
+/// Upload `content` as a secret gist and return its URL
+pub async fn create_secret_gist(
+    token: &str,
+    filename: &str,
+    description: &str,
+    content: &str,
+) -> Result<String, Error> {
+    let response = reqwest::Client::new()
+        .post(GISTS_API)
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", concat!("ariste/", env!("CARGO_PKG_VERSION")))
+        .json(&gist_payload(filename, description, content))
+        .send()
+        .await?;
+
+    let status = response.status();
+    let body: Value = response.json().await?;
+    if !status.is_success() {
+        let message = body.get("message").and_then(|v| v.as_str()).unwrap_or("unknown error");
+        return Err(Error::Message(format!("Gist upload failed ({}): {}", status, message)));
+    }
+
+    body.get("html_url")
+        .and_then(|v| v.as_str())
+        .map(|url| url.to_string())
+        .ok_or_else(|| Error::Message("Gist upload returned no URL".to_string()))
+}
+
+fn gist_payload(filename: &str, description: &str, content: &str) -> Value {
+    json!({
+        "description": description,
+        "public": false,
+        "files": {
+            filename: { "content": content }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gist_payload_is_secret() {
+        let payload = gist_payload("session.md", "Ariste session", "# Hi");
+        assert_eq!(payload["public"], false);
+        assert_eq!(payload["files"]["session.md"]["content"], "# Hi");
+    }
+}
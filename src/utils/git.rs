@@ -0,0 +1,142 @@
+use crate::error::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use tokio::process::Command;
+
+async fn run_git(root: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(Error::Message(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Whether `root` is inside a git work tree
+pub async fn is_repository(root: &Path) -> bool {
+    run_git(root, &["rev-parse", "--is-inside-work-tree"])
+        .await
+        .is_ok_and(|out| out.trim() == "true")
+}
+
+/// Files with uncommitted changes (staged or not) relative to HEAD
+pub async fn changed_files(root: &Path) -> Result<Vec<String>, Error> {
+    let output = run_git(root, &["diff", "HEAD", "--name-only"]).await?;
+    Ok(output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Uncommitted changes relative to HEAD, limited to `files` when non-empty.
+/// With `function_context` each hunk includes the whole enclosing function.
+pub async fn diff(root: &Path, files: &[String], function_context: bool) -> Result<String, Error> {
+    let mut args = vec!["diff", "HEAD"];
+    if function_context {
+        args.push("--function-context");
+    }
+    if !files.is_empty() {
+        args.push("--");
+        args.extend(files.iter().map(|file| file.as_str()));
+    }
+    run_git(root, &args).await
+}
+
+/// Hash identifying the current repository state: HEAD plus any
+/// uncommitted or untracked changes. Untracked files count by size and
+/// modification time, so editing one changes the hash.
+pub async fn state_hash(root: &Path) -> Result<u64, Error> {
+    let head = run_git(root, &["rev-parse", "HEAD"]).await?;
+    let status = run_git(root, &["status", "--porcelain"]).await?;
+    let changes = run_git(root, &["diff", "HEAD"]).await?;
+    let untracked = run_git(root, &["ls-files", "--others", "--exclude-standard", "-z"]).await?;
+
+    let mut hasher = DefaultHasher::new();
+    head.hash(&mut hasher);
+    status.hash(&mut hasher);
+    changes.hash(&mut hasher);
+    for file in untracked.split('\0').filter(|file| !file.is_empty()) {
+        // git diff 不包含未跟踪文件的内容
+        let metadata = tokio::fs::metadata(root.join(file)).await.ok();
+        file.hash(&mut hasher);
+        metadata.as_ref().map(|metadata| metadata.len()).hash(&mut hasher);
+        metadata.and_then(|metadata| metadata.modified().ok()).hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn git(root: &str, args: &[&str]) {
+        Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .await
+            .expect("Failed to run git");
+    }
+
+    #[tokio::test]
+    async fn test_diff_of_changed_file() {
+        let test_dir = "/tmp/test_git_diff";
+        tokio::fs::remove_dir_all(test_dir).await.ok();
+        tokio::fs::create_dir_all(test_dir).await.unwrap();
+
+        git(test_dir, &["init", "-q"]).await;
+        tokio::fs::write(format!("{}/a.txt", test_dir), "one\n").await.unwrap();
+        tokio::fs::write(format!("{}/b.txt", test_dir), "two\n").await.unwrap();
+        git(test_dir, &["add", "."]).await;
+        git(test_dir, &["commit", "-q", "-m", "init"]).await;
+
+        // Requires git to be installed
+        if !is_repository(Path::new(test_dir)).await {
+            return;
+        }
+
+        tokio::fs::write(format!("{}/a.txt", test_dir), "one\nmore\n").await.unwrap();
+
+        let files = changed_files(Path::new(test_dir)).await.unwrap();
+        assert_eq!(files, vec!["a.txt".to_string()]);
+
+        let patch = diff(Path::new(test_dir), &files, false).await.unwrap();
+        assert!(patch.contains("+more"));
+
+        let other = diff(Path::new(test_dir), &["b.txt".to_string()], false).await.unwrap();
+        assert!(other.is_empty());
+
+        // The state hash follows working tree changes
+        let before = state_hash(Path::new(test_dir)).await.unwrap();
+        assert_eq!(before, state_hash(Path::new(test_dir)).await.unwrap());
+        tokio::fs::write(format!("{}/b.txt", test_dir), "changed\n").await.unwrap();
+        assert_ne!(before, state_hash(Path::new(test_dir)).await.unwrap());
+
+        // Editing an untracked file changes it too
+        tokio::fs::write(format!("{}/notes.txt", test_dir), "draft\n").await.unwrap();
+        let untracked = state_hash(Path::new(test_dir)).await.unwrap();
+        tokio::fs::write(format!("{}/notes.txt", test_dir), "final draft\n").await.unwrap();
+        assert_ne!(untracked, state_hash(Path::new(test_dir)).await.unwrap());
+
+        tokio::fs::remove_dir_all(test_dir).await.ok();
+    }
+
+    #[tokio::test]
+    async fn test_not_a_repository() {
+        assert!(!is_repository(Path::new("/")).await);
+    }
+}
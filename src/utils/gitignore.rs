@@ -0,0 +1,164 @@
+use glob::{MatchOptions, Pattern};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// `*` 不跨目录，与 git 的匹配规则一致
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// One line of a `.gitignore`
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+    /// Contains a `/`: matched against the path from the `.gitignore`'s
+    /// directory rather than the file name alone
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = Pattern::new(line.trim_start_matches('/')).ok()?;
+        Some(Self {
+            pattern,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule applies to `relative` (from its `.gitignore`'s directory)
+    fn applies(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            return self.pattern.matches_with(relative, MATCH_OPTIONS);
+        }
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        self.pattern.matches_with(name, MATCH_OPTIONS)
+    }
+}
+
+/// The `.gitignore` rules of a tree, read as directories are visited.
+/// Rules come from the `.gitignore` of every directory from the repository
+/// top (the nearest ancestor with `.git`) down; deeper files and later
+/// lines win, `!` re-includes, and `.git` itself is always ignored.
+#[derive(Debug)]
+pub struct Gitignore {
+    top: PathBuf,
+    /// Rules of each directory's `.gitignore`, loaded on first use
+    rules: HashMap<PathBuf, Vec<Rule>>,
+}
+
+impl Gitignore {
+    /// Rules for searching under `dir`
+    pub fn new(dir: &Path) -> Self {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let top = dir
+            .ancestors()
+            .find(|ancestor| ancestor.join(".git").exists())
+            .unwrap_or(&dir)
+            .to_path_buf();
+        Self {
+            top,
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Whether `path` or a directory it is in is ignored; paths outside
+    /// the tree never are
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let Ok(relative) = path.strip_prefix(&self.top) else {
+            return false;
+        };
+        let mut current = self.top.clone();
+        let components: Vec<_> = relative.components().collect();
+        for (index, component) in components.iter().enumerate() {
+            current.push(component);
+            let last = index + 1 == components.len();
+            if component.as_os_str() == ".git" || self.matches(&current, !last || is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The decision of the deepest `.gitignore` with a rule for `path`
+    fn matches(&mut self, path: &Path, is_dir: bool) -> bool {
+        let mut dir = path.parent();
+        while let Some(base) = dir {
+            let relative = path
+                .strip_prefix(base)
+                .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            let rules = self.rules_of(base);
+            if let Some(rule) = rules.iter().rev().find(|rule| rule.applies(&relative, is_dir)) {
+                return !rule.negated;
+            }
+            if base == self.top {
+                break;
+            }
+            dir = base.parent();
+        }
+        false
+    }
+
+    fn rules_of(&mut self, dir: &Path) -> &[Rule] {
+        self.rules.entry(dir.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(dir.join(".gitignore"))
+                .map(|text| text.lines().filter_map(Rule::parse).collect())
+                .unwrap_or_default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_rules() {
+        let dir = Path::new("/tmp/test_gitignore_rules");
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::create_dir_all(dir.join("web/dist")).unwrap();
+        std::fs::write(dir.join(".gitignore"), "target/\n*.log\n!keep.log\n/build\n# comment\n").unwrap();
+        std::fs::write(dir.join("web/.gitignore"), "dist\n!debug.log\n").unwrap();
+
+        let mut ignore = Gitignore::new(&dir.join("web"));
+        assert!(ignore.is_ignored(&dir.join("target"), true));
+        assert!(!ignore.is_ignored(&dir.join("target"), false));
+        assert!(ignore.is_ignored(&dir.join("target/debug/app.rs"), false));
+        assert!(ignore.is_ignored(&dir.join("web/server.log"), false));
+        assert!(!ignore.is_ignored(&dir.join("keep.log"), false));
+        // The deeper .gitignore wins
+        assert!(!ignore.is_ignored(&dir.join("web/debug.log"), false));
+        assert!(ignore.is_ignored(&dir.join("web/dist/app.js"), false));
+        // Anchored to the top: build/ in a subdirectory is kept
+        assert!(ignore.is_ignored(&dir.join("build"), true));
+        assert!(!ignore.is_ignored(&dir.join("web/build"), true));
+        assert!(ignore.is_ignored(&dir.join(".git/config"), false));
+        assert!(!ignore.is_ignored(&dir.join("web/src/main.rs"), false));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
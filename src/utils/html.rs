@@ -0,0 +1,476 @@
+//! HTML turned into markdown, so web pages and HTML docs reach the model
+//! as headings, lists, links and code instead of markup
+
+/// Elements whose content is never text to read
+const SKIPPED: &[&str] = &["script", "style", "noscript", "template", "svg", "head", "title", "iframe", "object", "canvas"];
+/// Page chrome `readable` leaves out around the main text
+const CHROME: &[&str] = &["nav", "aside", "footer", "form", "button", "dialog"];
+/// Elements that start on a line of their own
+const BLOCKS: &[&str] = &[
+    "p", "div", "section", "article", "main", "header", "footer", "nav", "aside", "form", "table", "thead", "tbody",
+    "tr", "ul", "ol", "dl", "dt", "dd", "blockquote", "figure", "figcaption", "details", "summary", "address",
+];
+
+/// The `<title>` of a page
+pub fn title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = collapse_whitespace(&decode_entities(&html[start..end]));
+    (!title.is_empty()).then_some(title)
+}
+
+/// Markdown for `html`
+pub fn to_markdown(html: &str) -> String {
+    convert(html, SKIPPED)
+}
+
+/// The main text of a page as markdown, readability-style: the `<main>` or
+/// `<article>` element when there is one, without navigation, sidebars,
+/// footers and forms, under the page title
+pub fn readable(html: &str) -> String {
+    let content = main_content(html);
+    let mut skipped: Vec<&str> = SKIPPED.iter().chain(CHROME).copied().collect();
+    if content.is_none() {
+        // 没有正文元素时，页面级的 header 多是站点导航
+        skipped.push("header");
+    }
+    let body = convert(content.unwrap_or(html), &skipped);
+    match title(html) {
+        Some(title) if body.is_empty() => format!("# {}", title),
+        Some(title) if !body.starts_with("# ") => format!("# {}\n\n{}", title, body),
+        _ => body,
+    }
+}
+
+/// `<main>` or else `<article>`, from its opening tag to its last closing one
+fn main_content(html: &str) -> Option<&str> {
+    let lower = html.to_ascii_lowercase();
+    for name in ["main", "article"] {
+        let open = format!("<{}", name);
+        let start = lower.match_indices(&open).map(|(start, _)| start).find(|&start| {
+            lower[start + open.len()..].starts_with(|c: char| c == '>' || c.is_ascii_whitespace())
+        });
+        let end = lower.rfind(&format!("</{}", name));
+        if let (Some(start), Some(end)) = (start, end)
+            && end > start
+        {
+            return Some(&html[start..end]);
+        }
+    }
+    None
+}
+
+/// Markdown for `html`, leaving out the content of the `skipped` elements
+fn convert(html: &str, skipped: &[&str]) -> String {
+    let mut writer = Writer::default();
+    let mut skipping: Option<String> = None;
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            if skipping.is_none() {
+                writer.text(rest);
+            }
+            break;
+        };
+        if open > 0 && skipping.is_none() {
+            writer.text(&rest[..open]);
+        }
+        rest = &rest[open..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+        let Some(end) = tag_end(rest) else {
+            // 不成对的 '<' 当作文本
+            if skipping.is_none() {
+                writer.text("<");
+            }
+            rest = &rest[1..];
+            continue;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if let Some(skipped) = &skipping {
+            if closing && name == *skipped {
+                skipping = None;
+            }
+            continue;
+        }
+        if skipped.contains(&name.as_str()) && !closing && !tag.ends_with('/') {
+            skipping = Some(name);
+            continue;
+        }
+        if closing {
+            writer.close(&name);
+        } else {
+            writer.open(&name, tag);
+        }
+    }
+    writer.finish()
+}
+
+/// Index of the `>` ending the tag at the start of `text`, passing over
+/// quoted attribute values
+fn tag_end(text: &str) -> Option<usize> {
+    let first = text[1..].chars().next()?;
+    if !(first.is_ascii_alphabetic() || first == '/' || first == '!' || first == '?') {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Value of attribute `name` in the inside of a tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(name) {
+        let start = from + found;
+        from = start + name.len();
+        let before = lower[..start].chars().last();
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let after = lower[from..].trim_start();
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let offset = tag.len() - value.len();
+        let value = tag[offset..].trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+            _ => value.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or(""),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `text` with character references replaced
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').filter(|&end| end <= 10).map(|end| &rest[1..=end]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "copy" => Some('©'),
+            "reg" => Some('®'),
+            "trade" => Some('™'),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Markdown built up as the elements open and close
+#[derive(Debug, Default)]
+struct Writer {
+    out: String,
+    /// Line breaks owed before the next text: 1 ends the line, 2 a paragraph
+    breaks: usize,
+    /// Inside `<pre>`, where whitespace is kept
+    pre: usize,
+    /// Open lists, with the next number of ordered ones
+    lists: Vec<Option<usize>>,
+    /// Where the text of each open link starts, and its target
+    links: Vec<(usize, Option<String>)>,
+    /// Cells written in the current table row
+    cells: usize,
+}
+
+impl Writer {
+    fn block(&mut self, breaks: usize) {
+        self.breaks = self.breaks.max(breaks);
+    }
+
+    /// Drop trailing `chars`; open links starting in them now start at the end
+    fn trim_end(&mut self, chars: &[char]) {
+        let trimmed = self.out.trim_end_matches(chars).len();
+        self.out.truncate(trimmed);
+        for (start, _) in &mut self.links {
+            *start = (*start).min(trimmed);
+        }
+    }
+
+    /// Write out the breaks owed, at most one blank line
+    fn flush(&mut self) {
+        if self.breaks == 0 {
+            return;
+        }
+        self.trim_end(&[' ', '\t']);
+        if !self.out.is_empty() {
+            let present = self.out.len() - self.out.trim_end_matches('\n').len();
+            for _ in present..self.breaks.min(2) {
+                self.out.push('\n');
+            }
+        }
+        self.breaks = 0;
+    }
+
+    fn push(&mut self, markup: &str) {
+        self.flush();
+        self.out.push_str(markup);
+    }
+
+    fn text(&mut self, raw: &str) {
+        let text = decode_entities(raw);
+        if self.pre > 0 {
+            self.push(&text);
+            return;
+        }
+        let collapsed = collapse_whitespace(&text);
+        if collapsed.is_empty() {
+            if !text.is_empty() && !self.out.ends_with([' ', '\n']) && self.breaks == 0 {
+                self.out.push(' ');
+            }
+            return;
+        }
+        let leading = text.starts_with(char::is_whitespace) && self.breaks == 0;
+        self.flush();
+        if leading && !self.out.is_empty() && !self.out.ends_with([' ', '\n', '(', '[']) {
+            self.out.push(' ');
+        }
+        self.out.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+    }
+
+    fn open(&mut self, name: &str, tag: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block(2);
+                let level = name[1..].parse().unwrap_or(1);
+                self.push(&format!("{} ", "#".repeat(level)));
+            }
+            "br" => {
+                self.trim_end(&[' ']);
+                self.out.push('\n');
+            }
+            "hr" => {
+                self.block(2);
+                self.push("---");
+                self.block(2);
+            }
+            "pre" => {
+                self.block(2);
+                self.push("```\n");
+                self.pre += 1;
+            }
+            "code" if self.pre == 0 => self.push("`"),
+            "strong" | "b" => self.push("**"),
+            "em" | "i" => self.push("*"),
+            "a" => {
+                self.flush();
+                let href = attribute(tag, "href").filter(|href| !href.starts_with('#') && !href.starts_with("javascript:"));
+                self.links.push((self.out.len(), href));
+            }
+            "img" => {
+                if let Some(alt) = attribute(tag, "alt").filter(|alt| !alt.trim().is_empty()) {
+                    self.push(&format!("[image: {}]", alt.trim()));
+                }
+            }
+            "ul" => {
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+                self.lists.push(None);
+            }
+            "ol" => {
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+                self.lists.push(Some(1));
+            }
+            "li" => {
+                self.block(1);
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.push(&format!("{}{}", indent, marker));
+            }
+            "tr" => {
+                self.block(1);
+                self.cells = 0;
+            }
+            "td" | "th" => {
+                if self.cells > 0 {
+                    self.push(" | ");
+                }
+                self.cells += 1;
+            }
+            "blockquote" => {
+                self.block(2);
+                self.push("> ");
+            }
+            _ if BLOCKS.contains(&name) => self.block(2),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.block(2),
+            "pre" if self.pre > 0 => {
+                self.pre -= 1;
+                if !self.out.ends_with('\n') {
+                    self.out.push('\n');
+                }
+                self.out.push_str("```");
+                self.block(2);
+            }
+            "code" if self.pre == 0 => self.out.push('`'),
+            "strong" | "b" => self.out.push_str("**"),
+            "em" | "i" => self.out.push('*'),
+            "a" => {
+                if let Some((start, Some(href))) = self.links.pop() {
+                    let text = self.out[start..].trim().to_string();
+                    if !text.is_empty() && text != href {
+                        self.out.truncate(start);
+                        self.out.push_str(&format!("[{}]({})", text, href));
+                    }
+                }
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            "li" | "tr" => self.block(1),
+            _ if BLOCKS.contains(&name) => self.block(2),
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> String {
+        let lines: Vec<&str> = self.out.lines().map(str::trim_end).collect();
+        let mut text = String::new();
+        let mut blank = 0;
+        for line in lines {
+            if line.is_empty() {
+                blank += 1;
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str(if blank > 0 { "\n\n" } else { "\n" });
+            }
+            blank = 0;
+            text.push_str(line);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"<!DOCTYPE html><html><head><title>Retry &amp; backoff</title><style>p { color: red }</style></head>
+<body><h1>Retries</h1>
+<p>Requests are <b>retried</b> after a
+    <a href="/docs/errors">rate limit</a>, see <code>RetryPolicy</code>.</p>
+<!-- not shown -->
+<ul><li>Wait 500 ms</li><li>Then double it<ol><li>at most 5 times</li></ol></li></ul>
+<pre><code>let policy = RetryPolicy::default();
+policy.send(request).await?;</code></pre>
+<script>track();</script>
+<table><tr><th>Status</th><th>Retried</th></tr><tr><td>429</td><td>yes</td></tr></table>
+<p>1 &lt; 2 &#8212; done</p></body></html>"#;
+        assert_eq!(title(html).as_deref(), Some("Retry & backoff"));
+        assert_eq!(
+            to_markdown(html),
+            "# Retries\n\nRequests are **retried** after a [rate limit](/docs/errors), see `RetryPolicy`.\n\n\
+             - Wait 500 ms\n- Then double it\n  1. at most 5 times\n\n\
+             ```\nlet policy = RetryPolicy::default();\npolicy.send(request).await?;\n```\n\n\
+             Status | Retried\n429 | yes\n\n1 < 2 — done"
+        );
+        // Leading whitespace trimmed away from under a nested link
+        assert_eq!(to_markdown(" <a href=\"x\"><div><a href=\"y\"></a></a>"), "");
+        assert_eq!(to_markdown(" <a href=\"x\"><div>Docs</div></a>"), "[Docs](x)");
+    }
+
+    #[test]
+    fn test_readable_keeps_main_text() {
+        let page = r#"<html><head><title>Pagination | Acme API</title></head><body>
+<header><a href="/">Acme</a> <nav><a href="/docs">Docs</a> <a href="/blog">Blog</a></nav></header>
+<main><article><header><h1>Pagination</h1></header>
+<p>List endpoints return a <code>next_cursor</code>.</p>
+<aside>Was this page helpful? <button>Yes</button></aside></article></main>
+<footer>© 2026 Acme</footer></body></html>"#;
+        assert_eq!(readable(page), "# Pagination
+
+List endpoints return a `next_cursor`.");
+
+        // Without a main element the page chrome still goes, and the title leads
+        let page = "<title>Status</title><header><nav>Home</nav></header><div><p>All systems normal</p></div><footer>Contact</footer>";
+        assert_eq!(readable(page), "# Status
+
+All systems normal");
+    }
+}
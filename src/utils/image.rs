@@ -14,13 +14,44 @@ pub async fn load_image(image_url: &str) -> Result<Bytes, Error> {
 pub async fn load_image_as_base64(image_url: &str) -> Result<String, Error> {
     let buf = load_image(image_url).await?;
 
-    Ok(base64.encode(buf))
+    Ok(encode_base64(&buf))
+}
+
+pub fn encode_base64(bytes: &[u8]) -> String {
+    base64.encode(bytes)
+}
+
+/// 根据文件头识别常见图片格式，返回 MIME 类型
+pub fn image_mime(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        _ => None,
+    }
+}
+
+/// MIME type of a base64-encoded image, from its first bytes
+pub fn base64_mime(data: &str) -> &'static str {
+    let head = data.get(..16).and_then(|head| base64.decode(head).ok()).unwrap_or_default();
+    image_mime(&head).unwrap_or("image/png")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_image_mime() {
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D];
+        assert_eq!(image_mime(&png), Some("image/png"));
+        assert_eq!(image_mime(b"RIFF\x10\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(image_mime(b"fn main() {}"), None);
+        assert_eq!(base64_mime(&encode_base64(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10, b'J', b'F', b'I', b'F', 0, 1])), "image/jpeg");
+    }
+
     #[tokio::test]
     async fn test_image() {
         let result = load_image_as_base64("http://172.16.200.202:9000/api/view?filename=ComfyUI_00811_.png&subfolder=&type=output").await;
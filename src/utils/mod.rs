@@ -1,3 +1,7 @@
+pub mod gist;
+pub mod git;
+pub mod gitignore;
+pub mod html;
 mod image;
 
-pub use image::load_image_as_base64;
+pub use image::{base64_mime, encode_base64, image_mime, load_image_as_base64};
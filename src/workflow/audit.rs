@@ -0,0 +1,274 @@
+use crate::agent::{Agent, SubAgentTask, SubAgentType};
+use crate::error::Error;
+use crate::workflow::findings::{self, Finding, Severity};
+use crate::workflow::snapshot::Snapshot;
+use crate::workflow::subagent_result;
+use regex::Regex;
+use std::path::Path;
+
+/// Largest slice of a single file sent to a reviewer
+const MAX_FILE_CHARS: usize = 24_000;
+
+/// Files are packed into batches of about this many characters, one
+/// CodeReview subagent per batch
+const MAX_BATCH_CHARS: usize = 24_000;
+
+/// Risky constructs that make a file worth a security review, by category
+const RISKY_PATTERNS: &[(&str, &str)] = &[
+    (
+        "exec",
+        r"Command::new|std::process|\bexec(?:Sync|File|vp?)?\s*\(|\bspawn\s*\(|os\.system|subprocess\.|child_process|\beval\s*\(|\bpopen\s*\(",
+    ),
+    (
+        "deserialize",
+        r"\bdeserialize|serde_json::from_|serde_yaml::from_|bincode::|pickle\.loads?|yaml\.(?:unsafe_)?load\b|\bunserialize\s*\(|ObjectInputStream|marshal\.loads",
+    ),
+    (
+        "sql",
+        r"(?i)\bselect\s.+\sfrom\b|\binsert\s+into\b|\bupdate\s+\w+\s+set\b|\bdelete\s+from\b|\bexecute(?:many)?\s*\(|sqlx::query|\.raw\s*\(",
+    ),
+];
+
+const SECURITY_PROMPT: &str = "You are performing a security review. Look only for exploitable issues: \
+     command or code injection, unsafe deserialization of untrusted data, SQL injection, path traversal, \
+     hard-coded secrets, missing authorization and similar. Ignore style and ordinary bugs. The matched \
+     risky lines are hints, not findings; report an issue only when untrusted input can reach it.";
+
+/// A file that matched at least one risky pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<'a> {
+    /// Path relative to the workspace root
+    pub file: String,
+    pub content: &'a str,
+    pub categories: Vec<&'static str>,
+    /// 1-based lines that matched
+    pub lines: Vec<usize>,
+}
+
+/// Outcome of `ariste audit-code`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditReport {
+    pub files_scanned: usize,
+    pub files_reviewed: usize,
+    /// Sorted by severity
+    pub findings: Vec<Finding>,
+}
+
+impl std::fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Scanned {} files, reviewed {} with risky patterns, {} findings",
+            self.files_scanned,
+            self.files_reviewed,
+            self.findings.len()
+        )?;
+        write!(f, "{}", findings::terminal(&self.findings))
+    }
+}
+
+impl AuditReport {
+    /// Whether any finding is at least as severe as `threshold`
+    pub fn has_findings_at(&self, threshold: Severity) -> bool {
+        findings::any_at(&self.findings, threshold)
+    }
+}
+
+fn relative(path: &Path, base: &Path) -> String {
+    let path = path.strip_prefix(base).unwrap_or(path);
+    path.display().to_string().trim_start_matches("./").to_string()
+}
+
+/// Files in the snapshot that match a risky pattern
+pub fn candidates<'a>(snapshot: &'a Snapshot, base: &Path) -> Vec<Candidate<'a>> {
+    let patterns: Vec<(&'static str, Regex)> = RISKY_PATTERNS
+        .iter()
+        .map(|(category, pattern)| (*category, Regex::new(pattern).expect("valid risky pattern")))
+        .collect();
+
+    snapshot
+        .files()
+        .filter_map(|(path, content)| {
+            let mut categories = Vec::new();
+            let mut lines = Vec::new();
+            for (number, line) in content.lines().enumerate() {
+                for (category, regex) in &patterns {
+                    if regex.is_match(line) {
+                        if !categories.contains(category) {
+                            categories.push(*category);
+                        }
+                        if lines.last() != Some(&(number + 1)) {
+                            lines.push(number + 1);
+                        }
+                    }
+                }
+            }
+            (!categories.is_empty()).then(|| Candidate {
+                file: relative(path, base),
+                content,
+                categories,
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// Group candidates so each reviewer gets about `MAX_BATCH_CHARS` of code
+fn batches<'a, 'b>(candidates: &'b [Candidate<'a>]) -> Vec<Vec<&'b Candidate<'a>>> {
+    let mut batches: Vec<Vec<&Candidate>> = Vec::new();
+    let mut size = 0;
+    for candidate in candidates {
+        let len = candidate.content.len().min(MAX_FILE_CHARS);
+        if batches.is_empty() || size + len > MAX_BATCH_CHARS {
+            batches.push(Vec::new());
+            size = 0;
+        }
+        size += len;
+        if let Some(batch) = batches.last_mut() {
+            batch.push(candidate);
+        }
+    }
+    batches
+}
+
+fn review_prompt(batch: &[&Candidate]) -> String {
+    let mut prompt = format!(
+        "{}\n\nReply with only a JSON array of findings, `[]` if there are none. Each finding is \
+         {{\"file\": <path as given>, \"line\": <number>, \"severity\": \"critical\"|\"high\"|\"medium\"|\"low\", \
+         \"rule\": <kebab-case id such as command-injection>, \"message\": <what is wrong and how to fix it>}}.\n",
+        SECURITY_PROMPT
+    );
+
+    for candidate in batch {
+        let lines: Vec<String> = candidate.lines.iter().map(|line| line.to_string()).collect();
+        prompt.push_str(&format!(
+            "\n### {} (matched {}: lines {})\n```\n",
+            candidate.file,
+            candidate.categories.join(", "),
+            lines.join(", ")
+        ));
+        let mut size = 0;
+        for (number, line) in candidate.content.lines().enumerate() {
+            size += line.len() + 1;
+            if size > MAX_FILE_CHARS {
+                prompt.push_str("... (truncated)\n");
+                break;
+            }
+            prompt.push_str(&format!("{:>5} | {}\n", number + 1, line));
+        }
+        prompt.push_str("```\n");
+    }
+    prompt
+}
+
+/// Security review of `path` (the `ariste audit-code` command): files that
+/// use risky constructs are split into batches, each reviewed by a
+/// CodeReview subagent with a security prompt, and the findings are merged
+/// and sorted by severity.
+pub async fn run(agent: &mut Agent, path: &str) -> Result<AuditReport, Error> {
+    let root = agent.workspace.resolve(path);
+    if !root.exists() {
+        return Err(Error::Message(format!("No such file or directory: {}", path)));
+    }
+    let snapshot = Snapshot::take(&root)?;
+    let base = agent.workspace.primary().path.clone();
+    let candidates = candidates(&snapshot, &base);
+
+    let mut report = AuditReport {
+        files_scanned: snapshot.len(),
+        files_reviewed: candidates.len(),
+        findings: Vec::new(),
+    };
+    if candidates.is_empty() {
+        return Ok(report);
+    }
+
+    let tasks: Vec<SubAgentTask> = batches(&candidates)
+        .iter()
+        .enumerate()
+        .map(|(index, batch)| {
+            SubAgentTask::new(
+                SubAgentType::CodeReview,
+                format!("Security review, batch {}", index + 1),
+                review_prompt(batch),
+            )
+        })
+        .collect();
+
+    let outputs = agent.spawn_multiple_tasks(tasks).await?;
+    let findings = outputs
+        .iter()
+        .flat_map(|output| findings::parse_findings(&subagent_result(output), "security"))
+        .collect();
+    report.findings = findings::aggregate(findings);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let dir = Path::new("/tmp/test_audit_candidates");
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join("run.rs"),
+            "fn run(cmd: &str) {\n    Command::new(\"sh\").arg(cmd);\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("db.py"), "cur.execute(f\"SELECT * FROM users WHERE id={uid}\")\n").unwrap();
+        std::fs::write(dir.join("plain.rs"), "fn add(a: i32) -> i32 { a + 1 }\n").unwrap();
+
+        let snapshot = Snapshot::take(dir).unwrap();
+        let candidates = candidates(&snapshot, dir);
+        assert_eq!(candidates.len(), 2);
+
+        let db = candidates.iter().find(|c| c.file == "db.py").unwrap();
+        assert_eq!(db.categories, vec!["sql"]);
+        assert_eq!(db.lines, vec![1]);
+        let run = candidates.iter().find(|c| c.file == "run.rs").unwrap();
+        assert_eq!(run.categories, vec!["exec"]);
+        assert_eq!(run.lines, vec![2]);
+
+        let prompt = review_prompt(&batches(&candidates)[0]);
+        assert!(prompt.contains("### run.rs (matched exec: lines 2)"));
+        assert!(prompt.contains("    2 |     Command::new"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_batches() {
+        let big = "x".repeat(MAX_BATCH_CHARS - 10);
+        let candidate = |content| Candidate {
+            file: "a".to_string(),
+            content,
+            categories: vec!["exec"],
+            lines: vec![1],
+        };
+        let candidates = vec![candidate(&big), candidate("small"), candidate(&big)];
+        let batches = batches(&candidates);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), 2);
+    }
+
+    #[test]
+    fn test_report_threshold() {
+        let report = AuditReport {
+            findings: vec![Finding {
+                file: "a.rs".to_string(),
+                line: Some(1),
+                severity: Severity::Medium,
+                rule: "sql-injection".to_string(),
+                message: "m".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(report.has_findings_at(Severity::Low));
+        assert!(report.has_findings_at(Severity::Medium));
+        assert!(!report.has_findings_at(Severity::High));
+        assert!(report.to_string().contains("1 medium"));
+    }
+}
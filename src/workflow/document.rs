@@ -0,0 +1,100 @@
+use crate::agent::{Agent, SubAgentType};
+use crate::error::Error;
+use crate::workflow::snapshot::Snapshot;
+
+/// Outcome of `ariste document`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentReport {
+    pub kept: Vec<String>,
+    pub reverted: Vec<String>,
+}
+
+fn explore_prompt(path: &str) -> String {
+    format!(
+        "Survey `{}` for documentation work. For each module or file, report its purpose, its public \
+         items, how it fits with the rest of the code, and any non-obvious behavior. Note which items \
+         already have doc comments and which don't. Do not modify anything.",
+        path
+    )
+}
+
+fn document_prompt(path: &str, survey: &str) -> String {
+    format!(
+        "Write or update the documentation for `{path}`.\n\n\
+         - Add module-level docs (`//!` in Rust) and doc comments for public items that lack them.\n\
+         - Fix existing docs that contradict the code; keep accurate ones as they are.\n\
+         - Match the language and tone of the existing comments; keep them short.\n\
+         - Only change comments and documentation files, never code.\n\
+         - Use the edit tool for changes to existing files.\n\n\
+         Survey of the code:\n{survey}"
+    )
+}
+
+/// Generate or update documentation for `path` (the `ariste document`
+/// command): an Explore subagent surveys the code, a GeneralPurpose
+/// subagent writes the docs, and every changed file is shown as a diff for
+/// the user to keep or revert.
+pub async fn run(agent: &mut Agent, path: &str) -> Result<DocumentReport, Error> {
+    let root = agent.workspace.resolve(path);
+    if !root.exists() {
+        return Err(Error::Message(format!("No such file or directory: {}", path)));
+    }
+    let snapshot = Snapshot::take(&root)?;
+    if snapshot.is_empty() {
+        return Err(Error::Message(format!("No text files to document in {}", path)));
+    }
+
+    let survey = agent
+        .spawn_task_with_options(
+            SubAgentType::Explore,
+            &format!("Survey {} for documentation", path),
+            &explore_prompt(path),
+            None,
+            true,
+        )
+        .await?;
+
+    agent
+        .spawn_task_with_options(
+            SubAgentType::GeneralPurpose,
+            &format!("Document {}", path),
+            &document_prompt(path, &survey),
+            None,
+            true,
+        )
+        .await?;
+
+    let frontend = agent.frontend();
+    let mut report = DocumentReport::default();
+    for change in snapshot.changes()? {
+        let name = change.path.display().to_string();
+        frontend.diff(&change.unified_diff());
+        if frontend.confirm(&format!("Keep changes to {}?", name)) {
+            report.kept.push(name);
+        } else {
+            change.revert()?;
+            report.reverted.push(name);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompts() {
+        assert!(explore_prompt("src/tools").contains("`src/tools`"));
+        let prompt = document_prompt("src/tools", "bash.rs runs commands");
+        assert!(prompt.contains("never code"));
+        assert!(prompt.ends_with("bash.rs runs commands"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_path() {
+        let mut agent = Agent::load_from_config().await.unwrap();
+        let result = run(&mut agent, "/tmp/definitely/not/here").await;
+        assert!(result.unwrap_err().to_string().contains("No such file"));
+    }
+}
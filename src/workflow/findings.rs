@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// How bad a finding is, most severe first so sorting puts it on top
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+impl Severity {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "critical" => Severity::Critical,
+            "high" | "error" => Severity::High,
+            "medium" | "moderate" | "warning" => Severity::Medium,
+            _ => Severity::Low,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Critical => "critical",
+            Severity::High => "high",
+            Severity::Medium => "medium",
+            Severity::Low => "low",
+        }
+    }
+
+    /// SARIF result level
+    fn level(&self) -> &'static str {
+        match self {
+            Severity::Critical | Severity::High => "error",
+            Severity::Medium => "warning",
+            Severity::Low => "note",
+        }
+    }
+}
+
+/// How findings are written out: for people, or for CI and code-scanning
+/// dashboards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Terminal,
+    Json,
+    Sarif,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 3] = ["terminal", "json", "sarif"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "terminal" | "text" => Some(OutputFormat::Terminal),
+            "json" => Some(OutputFormat::Json),
+            "sarif" => Some(OutputFormat::Sarif),
+            _ => None,
+        }
+    }
+
+    pub fn is_machine_readable(&self) -> bool {
+        *self != OutputFormat::Terminal
+    }
+}
+
+/// One issue reported by a review subagent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    /// Path relative to the workspace root
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    pub severity: Severity,
+    /// Short kebab-case identifier such as `command-injection`
+    pub rule: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = match self.line {
+            Some(line) => format!("{}:{}", self.file, line),
+            None => self.file.clone(),
+        };
+        write!(f, "[{}] {} {}: {}", self.severity.name(), location, self.rule, self.message)
+    }
+}
+
+/// Findings in a subagent reply. The reply is asked to be a JSON array but
+/// models wrap it in prose or code fences, so the outermost brackets are
+/// parsed and entries that don't fit are skipped. Entries without a rule
+/// get `default_rule`.
+pub fn parse_findings(reply: &str, default_rule: &str) -> Vec<Finding> {
+    let (Some(start), Some(end)) = (reply.find('['), reply.rfind(']')) else {
+        return Vec::new();
+    };
+    if end < start {
+        return Vec::new();
+    }
+    let Ok(Value::Array(entries)) = serde_json::from_str::<Value>(&reply[start..=end]) else {
+        return Vec::new();
+    };
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let text = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(|v| v.trim().to_string());
+            Some(Finding {
+                file: text("file")?,
+                line: entry.get("line").and_then(|v| v.as_u64()).filter(|line| *line > 0),
+                severity: Severity::parse(&text("severity").unwrap_or_default()),
+                rule: text("rule").filter(|rule| !rule.is_empty()).unwrap_or_else(|| default_rule.to_string()),
+                message: text("message")?,
+            })
+        })
+        .collect()
+}
+
+/// Sort by severity then location, dropping duplicates reported by
+/// overlapping batches
+pub fn aggregate(mut findings: Vec<Finding>) -> Vec<Finding> {
+    findings.sort_by(|a, b| {
+        (a.severity, &a.file, a.line, &a.rule).cmp(&(b.severity, &b.file, b.line, &b.rule))
+    });
+    findings.dedup_by(|a, b| a.file == b.file && a.line == b.line && a.rule == b.rule);
+    findings
+}
+
+/// Number of findings per severity
+pub fn counts(findings: &[Finding]) -> BTreeMap<Severity, usize> {
+    let mut counts = BTreeMap::new();
+    for finding in findings {
+        *counts.entry(finding.severity).or_default() += 1;
+    }
+    counts
+}
+
+/// Whether any finding is at least as severe as `threshold`
+pub fn any_at(findings: &[Finding], threshold: Severity) -> bool {
+    findings.iter().any(|finding| finding.severity <= threshold)
+}
+
+/// Severity totals followed by one line per finding
+pub fn terminal(findings: &[Finding]) -> String {
+    let mut out = String::new();
+    let counts = counts(findings);
+    if !counts.is_empty() {
+        let summary: Vec<String> = counts
+            .iter()
+            .map(|(severity, count)| format!("{} {}", count, severity.name()))
+            .collect();
+        out.push_str(&format!("  {}\n", summary.join(", ")));
+    }
+    for finding in findings {
+        out.push_str(&format!("  {}\n", finding));
+    }
+    out
+}
+
+/// Findings with per-severity totals, for scripts
+pub fn json(findings: &[Finding]) -> Value {
+    let summary: serde_json::Map<String, Value> = counts(findings)
+        .into_iter()
+        .map(|(severity, count)| (severity.name().to_string(), json!(count)))
+        .collect();
+    json!({"summary": summary, "findings": findings})
+}
+
+/// Findings in the requested format
+pub fn render(findings: &[Finding], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Terminal => terminal(findings),
+        OutputFormat::Json => serde_json::to_string_pretty(&json(findings)).unwrap_or_default(),
+        OutputFormat::Sarif => serde_json::to_string_pretty(&sarif(findings)).unwrap_or_default(),
+    }
+}
+
+/// SARIF 2.1.0 log for code-scanning uploads
+pub fn sarif(findings: &[Finding]) -> Value {
+    let mut rules: Vec<&str> = findings.iter().map(|finding| finding.rule.as_str()).collect();
+    rules.sort();
+    rules.dedup();
+
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            let mut location = json!({"artifactLocation": {"uri": finding.file}});
+            if let Some(line) = finding.line {
+                location["region"] = json!({"startLine": line});
+            }
+            json!({
+                "ruleId": finding.rule,
+                "level": finding.severity.level(),
+                "message": {"text": finding.message},
+                "locations": [{"physicalLocation": location}],
+                "properties": {"severity": finding.severity.name()}
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "ariste",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|rule| json!({"id": rule})).collect::<Vec<_>>()
+                }
+            },
+            "results": results
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(file: &str, line: u64, severity: Severity, rule: &str) -> Finding {
+        Finding {
+            file: file.to_string(),
+            line: Some(line),
+            severity,
+            rule: rule.to_string(),
+            message: "issue".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_findings() {
+        let reply = r#"Here is what I found:
+```json
+[
+  {"file": "src/db.rs", "line": 12, "severity": "HIGH", "rule": "sql-injection", "message": "Query built with format!"},
+  {"file": "src/run.rs", "severity": "whatever", "message": "Shell command from user input"},
+  {"line": 3, "message": "no file"}
+]
+```"#;
+        let findings = parse_findings(reply, "security");
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert_eq!(findings[0].line, Some(12));
+        assert_eq!(findings[1].severity, Severity::Low);
+        assert_eq!(findings[1].rule, "security");
+
+        assert!(parse_findings("No issues found.", "security").is_empty());
+        assert!(parse_findings("[]", "security").is_empty());
+    }
+
+    #[test]
+    fn test_aggregate_and_counts() {
+        let findings = aggregate(vec![
+            finding("b.rs", 1, Severity::Low, "x"),
+            finding("a.rs", 5, Severity::Critical, "exec"),
+            finding("a.rs", 5, Severity::Critical, "exec"),
+            finding("a.rs", 2, Severity::Medium, "sql"),
+        ]);
+        assert_eq!(findings.len(), 3);
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!(findings[2].file, "b.rs");
+
+        let counts = counts(&findings);
+        assert_eq!(counts[&Severity::Critical], 1);
+        assert!(!counts.contains_key(&Severity::High));
+    }
+
+    #[test]
+    fn test_output_formats() {
+        assert_eq!(OutputFormat::parse("SARIF"), Some(OutputFormat::Sarif));
+        assert_eq!(OutputFormat::parse("xml"), None);
+        assert!(!OutputFormat::Terminal.is_machine_readable());
+
+        let findings = vec![finding("src/db.rs", 12, Severity::High, "sql-injection")];
+        let json: Value = serde_json::from_str(&render(&findings, OutputFormat::Json)).unwrap();
+        assert_eq!(json["summary"]["high"], 1);
+        assert_eq!(json["findings"][0]["severity"], "high");
+        assert_eq!(json["findings"][0]["line"], 12);
+
+        let text = render(&findings, OutputFormat::Terminal);
+        assert_eq!(text, "  1 high\n  [high] src/db.rs:12 sql-injection: issue\n");
+        assert!(render(&findings, OutputFormat::Sarif).contains("\"version\": \"2.1.0\""));
+    }
+
+    #[test]
+    fn test_sarif() {
+        let log = sarif(&[
+            finding("src/db.rs", 12, Severity::High, "sql-injection"),
+            finding("src/a.rs", 1, Severity::Low, "sql-injection"),
+        ]);
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 1);
+        let result = &run["results"][0];
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "src/db.rs");
+        assert_eq!(result["locations"][0]["physicalLocation"]["region"]["startLine"], 12);
+        assert_eq!(run["results"][1]["level"], "note");
+    }
+}
@@ -0,0 +1,31 @@
+//! 由多个子代理协作完成的命令（如 `ariste document`、`ariste audit-code`、`ariste translate`）
+
+pub mod audit;
+pub mod document;
+pub mod findings;
+pub mod review;
+mod snapshot;
+pub mod translate;
+
+pub use snapshot::{FileChange, Snapshot};
+
+/// The `result` field of a formatted subagent report
+pub(crate) fn subagent_result(output: &str) -> String {
+    let json = output.split_once('\n').map(|(_, json)| json).unwrap_or(output);
+    serde_json::from_str::<serde_json::Value>(json)
+        .ok()
+        .and_then(|report| report.get("result")?.as_str().map(|result| result.to_string()))
+        .unwrap_or_else(|| output.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subagent_result() {
+        let output = "=== Subagent Task Complete ===\n{\"task\": \"x\", \"result\": \"[]\"}";
+        assert_eq!(subagent_result(output), "[]");
+        assert_eq!(subagent_result("plain"), "plain");
+    }
+}
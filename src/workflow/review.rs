@@ -0,0 +1,49 @@
+use crate::agent::{Agent, SubAgentType};
+use crate::error::Error;
+use crate::workflow::subagent_result;
+use crate::workflow::findings::{self, Finding};
+
+fn review_prompt(path: Option<&str>) -> String {
+    let target = match path {
+        Some(path) => format!("the uncommitted changes to `{}` (or the file itself if it is unchanged)", path),
+        None => "the uncommitted changes in the repository".to_string(),
+    };
+    format!(
+        "Review {target}. Read the surrounding code where the diff alone isn't enough. Report bugs, \
+         security problems, performance issues and maintainability concerns worth acting on; skip \
+         nitpicks.\n\n\
+         Reply with only a JSON array of findings, `[]` if there are none. Each finding is \
+         {{\"file\": <path relative to the repository root>, \"line\": <number in the new file>, \
+         \"severity\": \"critical\"|\"high\"|\"medium\"|\"low\", \"rule\": <kebab-case category such as \
+         null-dereference or sql-injection>, \"message\": <what is wrong and how to fix it>}}."
+    )
+}
+
+/// Review uncommitted changes with a CodeReview subagent (the `ariste
+/// review` command) and return its findings sorted by severity
+pub async fn run(agent: &mut Agent, path: Option<&str>) -> Result<Vec<Finding>, Error> {
+    let description = match path {
+        Some(path) => format!("Review changes to {}", path),
+        None => "Review uncommitted changes".to_string(),
+    };
+    let output = agent
+        .spawn_task_with_options(SubAgentType::CodeReview, &description, &review_prompt(path), None, true)
+        .await?;
+    Ok(findings::aggregate(findings::parse_findings(
+        &subagent_result(&output),
+        "review",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_prompt() {
+        assert!(review_prompt(None).contains("uncommitted changes in the repository"));
+        // The path must appear in the prompt so the attached diff is narrowed to it
+        assert!(review_prompt(Some("src/db.rs")).contains("`src/db.rs`"));
+        assert!(review_prompt(None).contains("JSON array"));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::Error;
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are left out of snapshots
+const MAX_SNAPSHOT_FILE_BYTES: u64 = 1_000_000;
+
+/// Directories never worth snapshotting
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "dist", "build", "__pycache__"];
+
+/// Contents of every text file under a directory, taken before a workflow
+/// lets a subagent edit files so the changes can be reviewed (and undone)
+/// file by file afterwards
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, String>,
+}
+
+/// A file that differs from its snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// `None` when the file was created
+    pub before: Option<String>,
+    /// `None` when the file was deleted
+    pub after: Option<String>,
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name) {
+                walk(&path, files)?;
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Text files under `root` (or `root` itself when it is a file)
+fn text_files(root: &Path) -> Result<BTreeMap<PathBuf, String>, Error> {
+    let mut paths = Vec::new();
+    if root.is_file() {
+        paths.push(root.to_path_buf());
+    } else {
+        walk(root, &mut paths)?;
+    }
+
+    Ok(paths
+        .into_iter()
+        .filter(|path| path.metadata().is_ok_and(|m| m.len() <= MAX_SNAPSHOT_FILE_BYTES))
+        .filter_map(|path| std::fs::read_to_string(&path).ok().map(|content| (path, content)))
+        .collect())
+}
+
+impl Snapshot {
+    pub fn take(root: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            root: root.to_path_buf(),
+            files: text_files(root)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Path and content of every file in the snapshot
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &str)> {
+        self.files.iter().map(|(path, content)| (path.as_path(), content.as_str()))
+    }
+
+    /// Files created, modified or deleted since the snapshot was taken
+    pub fn changes(&self) -> Result<Vec<FileChange>, Error> {
+        let current = text_files(&self.root)?;
+        let mut changes = Vec::new();
+
+        for (path, before) in &self.files {
+            match current.get(path) {
+                Some(after) if after == before => {}
+                after => changes.push(FileChange {
+                    path: path.clone(),
+                    before: Some(before.clone()),
+                    after: after.cloned(),
+                }),
+            }
+        }
+        for (path, after) in current {
+            if !self.files.contains_key(&path) {
+                changes.push(FileChange {
+                    path,
+                    before: None,
+                    after: Some(after),
+                });
+            }
+        }
+        Ok(changes)
+    }
+}
+
+impl FileChange {
+    /// Unified diff of the change
+    pub fn unified_diff(&self) -> String {
+        let path = self.path.display().to_string();
+        let before = self.before.as_deref().unwrap_or_default();
+        let after = self.after.as_deref().unwrap_or_default();
+        TextDiff::from_lines(before, after)
+            .unified_diff()
+            .context_radius(3)
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string()
+    }
+
+    /// Put the file back the way the snapshot found it
+    pub fn revert(&self) -> Result<(), Error> {
+        match &self.before {
+            Some(before) => std::fs::write(&self.path, before)?,
+            None => std::fs::remove_file(&self.path)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_and_revert() {
+        let dir = Path::new("/tmp/test_workflow_snapshot");
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "pub fn a() {}\n").unwrap();
+        std::fs::write(dir.join("src/keep.rs"), "fn keep() {}\n").unwrap();
+        std::fs::write(dir.join("target/out.rs"), "ignored").unwrap();
+
+        let snapshot = Snapshot::take(dir).unwrap();
+        assert_eq!(snapshot.len(), 2);
+
+        std::fs::write(dir.join("src/lib.rs"), "/// Does a.\npub fn a() {}\n").unwrap();
+        std::fs::write(dir.join("src/new.rs"), "new\n").unwrap();
+
+        let changes = snapshot.changes().unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].unified_diff().contains("+/// Does a."));
+        assert_eq!(changes[1].before, None);
+
+        for change in &changes {
+            change.revert().unwrap();
+        }
+        assert!(snapshot.changes().unwrap().is_empty());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,362 @@
+use crate::agent::{Agent, SubAgentTask, SubAgentType};
+use crate::error::Error;
+use crate::workflow::subagent_result;
+use crate::workspace::Workspace;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Files translated concurrently
+const MAX_PARALLEL_FILES: usize = 4;
+
+/// `{name}`, `{{name}}`, `${name}`, `{0}`, `%s`, `%1$d`, `%(name)s`
+const PLACEHOLDER_PATTERN: &str = r"\{\{\s*[\w.]+\s*\}\}|\$\{\w+\}|\{[\w.]*\}|%(?:\d+\$)?[sdif@]|%\(\w+\)[sd]";
+
+/// Resource file formats whose structure can be checked after translation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResourceFormat {
+    Json,
+    Toml,
+    /// Java-style `key=value` lines
+    Properties,
+}
+
+impl ResourceFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(ResourceFormat::Json),
+            "toml" => Some(ResourceFormat::Toml),
+            "properties" => Some(ResourceFormat::Properties),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ResourceFormat::Json => "JSON",
+            ResourceFormat::Toml => "TOML",
+            ResourceFormat::Properties => "Java properties",
+        }
+    }
+}
+
+/// Outcome of `ariste translate`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TranslateReport {
+    /// Translated files that were written
+    pub written: Vec<String>,
+    /// Source files that could not be translated, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Every leaf of a resource file keyed by its dotted path. String leaves
+/// are translated; anything else must come back unchanged.
+fn flatten(format: ResourceFormat, text: &str) -> Result<BTreeMap<String, Value>, String> {
+    fn walk(prefix: &str, value: Value, leaves: &mut BTreeMap<String, Value>) {
+        let key = |name: &str| {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", prefix, name)
+            }
+        };
+        match value {
+            Value::Object(map) => {
+                for (name, value) in map {
+                    walk(&key(&name), value, leaves);
+                }
+            }
+            Value::Array(items) => {
+                for (index, value) in items.into_iter().enumerate() {
+                    walk(&key(&index.to_string()), value, leaves);
+                }
+            }
+            leaf => {
+                leaves.insert(prefix.to_string(), leaf);
+            }
+        }
+    }
+
+    let value: Value = match format {
+        ResourceFormat::Json => serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))?,
+        ResourceFormat::Toml => {
+            let table: toml::Table = text.parse().map_err(|e| format!("invalid TOML: {}", e))?;
+            serde_json::to_value(table).map_err(|e| e.to_string())?
+        }
+        ResourceFormat::Properties => {
+            let mut map = serde_json::Map::new();
+            for line in text.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                    continue;
+                }
+                let Some((key, value)) = line.split_once(['=', ':']) else {
+                    return Err(format!("invalid properties line: {}", line));
+                };
+                map.insert(key.trim().to_string(), Value::String(value.trim().to_string()));
+            }
+            // 键里的点不是层级，整体作为一个叶子
+            return Ok(map.into_iter().collect());
+        }
+    };
+
+    let mut leaves = BTreeMap::new();
+    walk("", value, &mut leaves);
+    Ok(leaves)
+}
+
+fn placeholders(regex: &Regex, text: &str) -> Vec<String> {
+    let mut found: Vec<String> = regex.find_iter(text).map(|m| m.as_str().to_string()).collect();
+    found.sort();
+    found
+}
+
+/// Check that `translated` has the same keys as `source`, that non-string
+/// values are untouched and that every string keeps its placeholders
+pub fn validate(format: ResourceFormat, source: &str, translated: &str) -> Result<(), String> {
+    let source = flatten(format, source).map_err(|e| format!("source is {}", e))?;
+    let translated = flatten(format, translated).map_err(|e| format!("translation is {}", e))?;
+    let regex = Regex::new(PLACEHOLDER_PATTERN).expect("valid placeholder pattern");
+
+    let missing: Vec<&str> = source
+        .keys()
+        .filter(|key| !translated.contains_key(*key))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("missing keys: {}", missing.join(", ")));
+    }
+    let extra: Vec<&str> = translated
+        .keys()
+        .filter(|key| !source.contains_key(*key))
+        .map(String::as_str)
+        .collect();
+    if !extra.is_empty() {
+        return Err(format!("unexpected keys: {}", extra.join(", ")));
+    }
+
+    for (key, original) in &source {
+        let value = &translated[key];
+        match (original, value) {
+            (Value::String(original), Value::String(value))
+                if placeholders(&regex, original) != placeholders(&regex, value) =>
+            {
+                return Err(format!(
+                    "placeholders changed in `{}`: {:?} became {:?}",
+                    key,
+                    placeholders(&regex, original),
+                    placeholders(&regex, value)
+                ));
+            }
+            (Value::String(_), Value::String(_)) => {}
+            (original, value) if original != value => {
+                return Err(format!("non-text value changed in `{}`", key));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Where the translation of `source` goes: the `from` locale in the path
+/// (a directory such as `locales/en/` or a file name such as `en.json` or
+/// `messages.en.json`) is replaced with `to`
+pub fn target_path(source: &Path, from: &str, to: &str) -> Option<PathBuf> {
+    let mut replaced = false;
+    let mut target = PathBuf::new();
+    let components: Vec<_> = source.components().collect();
+    for (index, component) in components.iter().enumerate() {
+        let part = component.as_os_str().to_string_lossy();
+        let is_file = index + 1 == components.len();
+        if !replaced && part == from {
+            target.push(to);
+            replaced = true;
+        } else if !replaced && is_file {
+            let segments: Vec<&str> = part.split('.').collect();
+            match segments.iter().position(|segment| *segment == from) {
+                Some(position) if position + 1 < segments.len() => {
+                    let mut segments = segments;
+                    segments[position] = to;
+                    target.push(segments.join("."));
+                    replaced = true;
+                }
+                _ => target.push(component.as_os_str()),
+            }
+        } else {
+            target.push(component.as_os_str());
+        }
+    }
+    replaced.then_some(target)
+}
+
+/// `target_path` for a resolved path, only looking at the part inside its
+/// workspace root, so a locale name in the root's own path is left alone
+fn workspace_target(workspace: &Workspace, source: &Path, from: &str, to: &str) -> Option<PathBuf> {
+    match workspace.root_of(source) {
+        Some(root) => {
+            let relative = source.strip_prefix(&root.path).ok()?;
+            target_path(relative, from, to).map(|target| root.path.join(target))
+        }
+        None => target_path(source, from, to),
+    }
+}
+
+fn translate_prompt(format: ResourceFormat, file: &str, content: &str, from: &str, to: &str) -> String {
+    format!(
+        "Translate the {format} resource file `{file}` from `{from}` to `{to}`.\n\n\
+         - Translate only the text values; keep every key, the nesting, the order and all non-text \
+         values exactly as they are.\n\
+         - Keep placeholders such as {{name}}, {{{{count}}}}, ${{user}}, %s and %1$d unchanged.\n\
+         - Keep the file valid {format}.\n\n\
+         Reply with only the translated file, no explanations.\n\n\
+         ```\n{content}\n```",
+        format = format.name()
+    )
+}
+
+/// The file inside the first code fence of a reply, or the whole reply
+fn strip_fences(reply: &str) -> String {
+    let trimmed = reply.trim();
+    if let Some(start) = trimmed.find("```") {
+        let body = &trimmed[start + 3..];
+        let body = body.split_once('\n').map(|(_, rest)| rest).unwrap_or_default();
+        if let Some(end) = body.rfind("```") {
+            return body[..end].trim_end().to_string() + "\n";
+        }
+    }
+    trimmed.to_string() + "\n"
+}
+
+/// Translate the resource files matching `pattern` (the `ariste translate`
+/// command). Each file is translated by its own subagent; a translation is
+/// only written when its keys and placeholders match the source.
+pub async fn run(agent: &mut Agent, pattern: &str, from: &str, to: &str) -> Result<TranslateReport, Error> {
+    let pattern = agent.workspace.resolve(pattern).display().to_string();
+    let entries = glob::glob(&pattern).map_err(|e| Error::Message(format!("Invalid glob pattern: {}", e)))?;
+
+    let mut report = TranslateReport::default();
+    let mut jobs = Vec::new();
+    for path in entries.flatten().filter(|path| path.is_file()) {
+        let name = agent.workspace.display_path(&path);
+        let Some(format) = ResourceFormat::from_path(&path) else {
+            report.failed.push((name, "unsupported format (expected .json, .toml or .properties)".to_string()));
+            continue;
+        };
+        let Some(target) = workspace_target(&agent.workspace, &path, from, to) else {
+            report.failed.push((name, format!("no `{}` locale in the path to replace", from)));
+            continue;
+        };
+        let content = std::fs::read_to_string(&path)?;
+        jobs.push((name, format, content, target));
+    }
+    if jobs.is_empty() && report.failed.is_empty() {
+        return Err(Error::Message(format!("No files match {}", pattern)));
+    }
+
+    for chunk in jobs.chunks(MAX_PARALLEL_FILES) {
+        let tasks = chunk
+            .iter()
+            .map(|(name, format, content, _)| {
+                SubAgentTask::new(
+                    SubAgentType::GeneralPurpose,
+                    format!("Translate {} to {}", name, to),
+                    translate_prompt(*format, name, content, from, to),
+                )
+            })
+            .collect();
+        let outputs = agent.spawn_multiple_tasks(tasks).await?;
+
+        for ((name, format, content, target), output) in chunk.iter().zip(outputs) {
+            let translated = strip_fences(&subagent_result(&output));
+            if let Err(reason) = validate(*format, content, &translated) {
+                report.failed.push((name.clone(), reason));
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(target, translated)?;
+            report.written.push(agent.workspace.display_path(target));
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::WorkspaceRoot;
+
+    #[test]
+    fn test_target_path() {
+        assert_eq!(
+            target_path(Path::new("locales/en/common.json"), "en", "fr"),
+            Some(PathBuf::from("locales/fr/common.json"))
+        );
+        assert_eq!(
+            target_path(Path::new("i18n/en.json"), "en", "fr"),
+            Some(PathBuf::from("i18n/fr.json"))
+        );
+        assert_eq!(
+            target_path(Path::new("i18n/messages.en.properties"), "en", "de"),
+            Some(PathBuf::from("i18n/messages.de.properties"))
+        );
+        assert_eq!(target_path(Path::new("i18n/strings.json"), "en", "fr"), None);
+    }
+
+    #[test]
+    fn test_workspace_target_ignores_root_path() {
+        let workspace = Workspace::new(vec![WorkspaceRoot::new("app", "/srv/en/app")]).unwrap();
+        assert_eq!(
+            workspace_target(&workspace, Path::new("/srv/en/app/i18n/en.json"), "en", "fr"),
+            Some(PathBuf::from("/srv/en/app/i18n/fr.json"))
+        );
+        assert_eq!(
+            workspace_target(&workspace, Path::new("/srv/en/app/i18n/strings.json"), "en", "fr"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_validate_json() {
+        let source = r#"{"greeting": "Hello, {name}!", "count": "%d items", "nested": {"ok": "OK"}, "max": 3}"#;
+        let good = r#"{"greeting": "Bonjour, {name} !", "count": "%d éléments", "nested": {"ok": "D'accord"}, "max": 3}"#;
+        assert_eq!(validate(ResourceFormat::Json, source, good), Ok(()));
+
+        let renamed = r#"{"salut": "Bonjour, {name} !", "count": "%d éléments", "nested": {"ok": "OK"}, "max": 3}"#;
+        assert!(validate(ResourceFormat::Json, source, renamed).unwrap_err().contains("missing keys: greeting"));
+
+        let lost = r#"{"greeting": "Bonjour, {nom} !", "count": "%d éléments", "nested": {"ok": "OK"}, "max": 3}"#;
+        assert!(validate(ResourceFormat::Json, source, lost).unwrap_err().contains("placeholders changed in `greeting`"));
+
+        let number = r#"{"greeting": "Bonjour, {name} !", "count": "%d éléments", "nested": {"ok": "OK"}, "max": 4}"#;
+        assert!(validate(ResourceFormat::Json, source, number).unwrap_err().contains("`max`"));
+
+        assert!(validate(ResourceFormat::Json, source, "not json").unwrap_err().starts_with("translation is"));
+    }
+
+    #[test]
+    fn test_validate_toml_and_properties() {
+        let source = "[menu]\nopen = \"Open {{file}}\"\n";
+        assert_eq!(validate(ResourceFormat::Toml, source, "[menu]\nopen = \"Ouvrir {{file}}\"\n"), Ok(()));
+        assert!(validate(ResourceFormat::Toml, source, "[menu]\nopen = \"Ouvrir\"\n").is_err());
+
+        let source = "# comment\napp.title=Welcome ${user}\n";
+        assert_eq!(validate(ResourceFormat::Properties, source, "app.title = Bienvenue ${user}\n"), Ok(()));
+        assert!(validate(ResourceFormat::Properties, source, "app.name=Bienvenue ${user}\n").is_err());
+    }
+
+    #[test]
+    fn test_strip_fences() {
+        assert_eq!(strip_fences("```json\n{\"a\": \"b\"}\n```"), "{\"a\": \"b\"}\n");
+        assert_eq!(strip_fences("Here you go:\n```\nx=y\n```\nDone."), "x=y\n");
+        assert_eq!(strip_fences("{\"a\": 1}"), "{\"a\": 1}\n");
+    }
+
+    #[test]
+    fn test_translate_prompt() {
+        let prompt = translate_prompt(ResourceFormat::Json, "locales/en/a.json", "{}", "en", "fr");
+        assert!(prompt.contains("JSON resource file `locales/en/a.json` from `en` to `fr`"));
+        assert!(prompt.contains("{name}, {{count}}, ${user}, %s"));
+    }
+}
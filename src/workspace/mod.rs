@@ -0,0 +1,5 @@
+mod profile;
+mod roots;
+
+pub use profile::{ProjectKind, ProjectProfile};
+pub use roots::{Workspace, WorkspaceRoot};
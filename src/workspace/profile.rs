@@ -0,0 +1,194 @@
+use std::path::{Path, PathBuf};
+
+/// Build systems recognized by project detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectKind {
+    Cargo,
+    Npm,
+    Poetry,
+    GoModules,
+    CMake,
+}
+
+impl ProjectKind {
+    const ALL: [ProjectKind; 5] = [
+        ProjectKind::Cargo,
+        ProjectKind::Npm,
+        ProjectKind::Poetry,
+        ProjectKind::GoModules,
+        ProjectKind::CMake,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectKind::Cargo => "Cargo (Rust)",
+            ProjectKind::Npm => "npm (JavaScript/TypeScript)",
+            ProjectKind::Poetry => "Poetry (Python)",
+            ProjectKind::GoModules => "Go modules",
+            ProjectKind::CMake => "CMake (C/C++)",
+        }
+    }
+
+    /// The file whose presence marks a project of this kind
+    fn marker(&self) -> &'static str {
+        match self {
+            ProjectKind::Cargo => "Cargo.toml",
+            ProjectKind::Npm => "package.json",
+            ProjectKind::Poetry => "pyproject.toml",
+            ProjectKind::GoModules => "go.mod",
+            ProjectKind::CMake => "CMakeLists.txt",
+        }
+    }
+
+    fn matches(&self, root: &Path) -> bool {
+        let marker = root.join(self.marker());
+        match self {
+            // pyproject.toml is shared by many tools; only claim Poetry projects
+            ProjectKind::Poetry => std::fs::read_to_string(marker)
+                .is_ok_and(|content| content.contains("[tool.poetry]")),
+            _ => marker.is_file(),
+        }
+    }
+}
+
+/// Project type detected at startup, with the default build/test commands
+/// the agent and subagents should use for that project
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProjectProfile {
+    pub root: PathBuf,
+    /// Detected kinds, most specific first (a repo can be e.g. Cargo + npm)
+    pub kinds: Vec<ProjectKind>,
+    pub build_command: Option<String>,
+    pub test_command: Option<String>,
+}
+
+impl ProjectProfile {
+    /// Detect the project type of a directory from its marker files
+    pub fn detect(root: &Path) -> Self {
+        let kinds: Vec<ProjectKind> = ProjectKind::ALL
+            .iter()
+            .copied()
+            .filter(|kind| kind.matches(root))
+            .collect();
+
+        let (build_command, test_command) = match kinds.first() {
+            Some(kind) => Self::default_commands(*kind, root),
+            None => (None, None),
+        };
+
+        Self {
+            root: root.to_path_buf(),
+            kinds,
+            build_command,
+            test_command,
+        }
+    }
+
+    fn default_commands(kind: ProjectKind, root: &Path) -> (Option<String>, Option<String>) {
+        let (build, test) = match kind {
+            ProjectKind::Cargo => ("cargo build".to_string(), "cargo test".to_string()),
+            ProjectKind::Npm => {
+                // Use the package manager the lockfile belongs to
+                let manager = if root.join("pnpm-lock.yaml").is_file() {
+                    "pnpm"
+                } else if root.join("yarn.lock").is_file() {
+                    "yarn"
+                } else {
+                    "npm"
+                };
+                (format!("{} run build", manager), format!("{} test", manager))
+            }
+            ProjectKind::Poetry => ("poetry build".to_string(), "poetry run pytest".to_string()),
+            ProjectKind::GoModules => ("go build ./...".to_string(), "go test ./...".to_string()),
+            ProjectKind::CMake => (
+                "cmake -S . -B build && cmake --build build".to_string(),
+                "ctest --test-dir build".to_string(),
+            ),
+        };
+        (Some(build), Some(test))
+    }
+
+    pub fn is_known(&self) -> bool {
+        !self.kinds.is_empty()
+    }
+
+    /// Description of the project for the system prompt
+    pub fn describe(&self) -> Option<String> {
+        if !self.is_known() {
+            return None;
+        }
+
+        let names: Vec<&str> = self.kinds.iter().map(|kind| kind.name()).collect();
+        let mut text = format!("Project type: {}.", names.join(", "));
+        if let Some(build) = &self.build_command {
+            text.push_str(&format!(" Build with `{}`.", build));
+        }
+        if let Some(test) = &self.test_command {
+            text.push_str(&format!(" Run tests with `{}`.", test));
+        }
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_cargo() {
+        let test_dir = "/tmp/test_profile_cargo";
+        std::fs::create_dir_all(test_dir).unwrap();
+        std::fs::write(format!("{}/Cargo.toml", test_dir), "[package]").unwrap();
+
+        let profile = ProjectProfile::detect(Path::new(test_dir));
+        assert_eq!(profile.kinds, vec![ProjectKind::Cargo]);
+        assert_eq!(profile.build_command.as_deref(), Some("cargo build"));
+        assert_eq!(profile.test_command.as_deref(), Some("cargo test"));
+        assert!(profile.describe().unwrap().contains("Cargo"));
+
+        std::fs::remove_dir_all(test_dir).ok();
+    }
+
+    #[test]
+    fn test_detect_npm_with_yarn() {
+        let test_dir = "/tmp/test_profile_yarn";
+        std::fs::create_dir_all(test_dir).unwrap();
+        std::fs::write(format!("{}/package.json", test_dir), "{}").unwrap();
+        std::fs::write(format!("{}/yarn.lock", test_dir), "").unwrap();
+
+        let profile = ProjectProfile::detect(Path::new(test_dir));
+        assert_eq!(profile.kinds, vec![ProjectKind::Npm]);
+        assert_eq!(profile.test_command.as_deref(), Some("yarn test"));
+
+        std::fs::remove_dir_all(test_dir).ok();
+    }
+
+    #[test]
+    fn test_detect_poetry_requires_poetry_section() {
+        let test_dir = "/tmp/test_profile_poetry";
+        std::fs::create_dir_all(test_dir).unwrap();
+        std::fs::write(format!("{}/pyproject.toml", test_dir), "[project]\nname = \"x\"").unwrap();
+        assert!(!ProjectProfile::detect(Path::new(test_dir)).is_known());
+
+        std::fs::write(format!("{}/pyproject.toml", test_dir), "[tool.poetry]\nname = \"x\"").unwrap();
+        assert_eq!(
+            ProjectProfile::detect(Path::new(test_dir)).kinds,
+            vec![ProjectKind::Poetry]
+        );
+
+        std::fs::remove_dir_all(test_dir).ok();
+    }
+
+    #[test]
+    fn test_detect_unknown() {
+        let test_dir = "/tmp/test_profile_unknown";
+        std::fs::create_dir_all(test_dir).unwrap();
+
+        let profile = ProjectProfile::detect(Path::new(test_dir));
+        assert!(!profile.is_known());
+        assert!(profile.build_command.is_none());
+        assert!(profile.describe().is_none());
+
+        std::fs::remove_dir_all(test_dir).ok();
+    }
+}
@@ -0,0 +1,444 @@
+use crate::config::RootConfig;
+use crate::error::Error;
+use std::path::{Path, PathBuf};
+
+/// A labeled directory the agent is allowed to work in
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceRoot {
+    pub label: String,
+    pub path: PathBuf,
+}
+
+impl WorkspaceRoot {
+    pub fn new(label: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            label: label.into(),
+            path: path.into(),
+        }
+    }
+}
+
+/// The set of roots the agent works in. The first root is the primary one:
+/// relative paths without a `label:` prefix are resolved against it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspace {
+    roots: Vec<WorkspaceRoot>,
+    /// Optional subdirectory that read/glob/grep are restricted to (see `/scope`)
+    scope: Option<Scope>,
+    /// Canonical roots every tool path must stay inside; `None` allows any path
+    confined: Option<Vec<PathBuf>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Scope {
+    /// The scope as resolved from the user's argument, used for searching
+    path: PathBuf,
+    /// Canonical form, used for containment checks
+    canonical: PathBuf,
+}
+
+impl Workspace {
+    /// Single-root workspace for the current directory
+    pub fn current_dir() -> Self {
+        let label = std::env::current_dir()
+            .ok()
+            .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "workdir".to_string());
+
+        Self {
+            roots: vec![WorkspaceRoot::new(label, ".")],
+            scope: None,
+            confined: None,
+        }
+    }
+
+    pub fn new(roots: Vec<WorkspaceRoot>) -> Result<Self, Error> {
+        if roots.is_empty() {
+            return Err(Error::Message("Workspace needs at least one root".to_string()));
+        }
+
+        for (i, root) in roots.iter().enumerate() {
+            if root.label.is_empty() || root.label.contains(['/', '\\', ':']) {
+                return Err(Error::Message(format!("Invalid workspace root label: '{}'", root.label)));
+            }
+            if roots[..i].iter().any(|other| other.label == root.label) {
+                return Err(Error::Message(format!("Duplicate workspace root label: '{}'", root.label)));
+            }
+        }
+
+        Ok(Self {
+            roots,
+            scope: None,
+            confined: None,
+        })
+    }
+
+    /// Build the workspace from the `roots` setting, falling back to the current directory
+    pub fn from_config(roots: Option<&[RootConfig]>) -> Result<Self, Error> {
+        let roots = match roots {
+            Some(roots) if !roots.is_empty() => roots,
+            _ => return Ok(Self::current_dir()),
+        };
+
+        let roots = roots
+            .iter()
+            .map(|root| {
+                let path = PathBuf::from(&root.path);
+                if !path.is_dir() {
+                    return Err(Error::Message(format!(
+                        "Workspace root '{}' is not a directory",
+                        root.path
+                    )));
+                }
+
+                let label = root.label.clone().unwrap_or_else(|| {
+                    path.canonicalize()
+                        .ok()
+                        .and_then(|p| p.file_name().map(|name| name.to_string_lossy().to_string()))
+                        .unwrap_or_else(|| root.path.clone())
+                });
+
+                Ok(WorkspaceRoot::new(label, path))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Self::new(roots)
+    }
+
+    pub fn roots(&self) -> &[WorkspaceRoot] {
+        &self.roots
+    }
+
+    pub fn primary(&self) -> &WorkspaceRoot {
+        &self.roots[0]
+    }
+
+    pub fn is_multi_root(&self) -> bool {
+        self.roots.len() > 1
+    }
+
+    pub fn root(&self, label: &str) -> Option<&WorkspaceRoot> {
+        self.roots.iter().find(|root| root.label == label)
+    }
+
+    /// Resolve a tool path argument. Accepts `label:relative/path` to address a
+    /// specific root; other relative paths are resolved against the primary root.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        if let Some((label, rest)) = path.split_once(':')
+            && let Some(root) = self.root(label)
+        {
+            return root.path.join(rest.trim_start_matches('/'));
+        }
+
+        let path = Path::new(path);
+        let base = &self.primary().path;
+        if path.is_absolute() || base == Path::new(".") {
+            path.to_path_buf()
+        } else {
+            base.join(path)
+        }
+    }
+
+    /// Find the root a path belongs to
+    pub fn root_of(&self, path: &Path) -> Option<&WorkspaceRoot> {
+        self.roots.iter().find(|root| path.starts_with(&root.path))
+    }
+
+    /// Format a path for the model. In a multi-root workspace paths inside a
+    /// root are shown as `label:relative/path` so the model knows where they live.
+    pub fn display_path(&self, path: &Path) -> String {
+        if self.is_multi_root()
+            && let Some(root) = self.root_of(path)
+            && let Ok(relative) = path.strip_prefix(&root.path)
+        {
+            return format!("{}:{}", root.label, relative.display());
+        }
+
+        path.display().to_string()
+    }
+
+    /// Restrict tool visibility to a subdirectory of the workspace
+    pub fn set_scope(&mut self, path: &str) -> Result<PathBuf, Error> {
+        let resolved = self.resolve(path);
+        let canonical = resolved
+            .canonicalize()
+            .map_err(|e| Error::Message(format!("Invalid scope '{}': {}", path, e)))?;
+
+        if !canonical.is_dir() {
+            return Err(Error::Message(format!("Scope '{}' is not a directory", path)));
+        }
+        let inside_root = self.roots.iter().any(|root| {
+            root.path
+                .canonicalize()
+                .is_ok_and(|root_path| canonical.starts_with(root_path))
+        });
+        if !inside_root {
+            return Err(Error::Message(format!(
+                "Scope '{}' is outside the workspace roots",
+                path
+            )));
+        }
+
+        self.scope = Some(Scope {
+            path: resolved.clone(),
+            canonical,
+        });
+        Ok(resolved)
+    }
+
+    pub fn reset_scope(&mut self) {
+        self.scope = None;
+    }
+
+    pub fn scope(&self) -> Option<&Path> {
+        self.scope.as_ref().map(|scope| scope.path.as_path())
+    }
+
+    /// Directories searched when a tool gets no explicit path: the scope if
+    /// one is set, otherwise every root
+    pub fn search_paths(&self) -> Vec<PathBuf> {
+        match &self.scope {
+            Some(scope) => vec![scope.path.clone()],
+            None => self.roots.iter().map(|root| root.path.clone()).collect(),
+        }
+    }
+
+    /// Reject tool paths outside the roots (traversal with `..`, absolute
+    /// paths, symlinks pointing elsewhere) unless `confine` is false
+    pub fn confine(&mut self, confine: bool) {
+        self.confined = confine.then(|| {
+            self.roots
+                .iter()
+                .map(|root| root.path.canonicalize().unwrap_or_else(|_| root.path.clone()))
+                .collect()
+        });
+    }
+
+    /// Whether a path lies inside the roots, or anywhere when not confined
+    fn inside_roots(&self, canonical: &Path) -> bool {
+        match &self.confined {
+            Some(roots) => roots.iter().any(|root| canonical.starts_with(root)),
+            None => true,
+        }
+    }
+
+    /// Error for tools when a path is outside the working directory
+    pub fn check_workdir(&self, path: &Path) -> Result<(), String> {
+        if self.inside_roots(&canonical(path)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Path '{}' is outside the working directory; set allow_outside_workdir in .ariste/settings.json to allow it",
+                path.display()
+            ))
+        }
+    }
+
+    /// Whether a path is visible under the current scope (and inside the
+    /// roots when confined)
+    pub fn in_scope(&self, path: &Path) -> bool {
+        let canonical = canonical(path);
+        self.inside_roots(&canonical)
+            && match &self.scope {
+                Some(scope) => canonical.starts_with(&scope.canonical),
+                None => true,
+            }
+    }
+
+    /// Error for tools when a path is outside the working directory or the
+    /// current scope
+    pub fn check_scope(&self, path: &Path) -> Result<(), String> {
+        self.check_workdir(path)?;
+        if self.in_scope(path) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Path '{}' is outside the current scope '{}' (use /scope reset to restore the full tree)",
+                path.display(),
+                self.scope().unwrap_or(path).display()
+            ))
+        }
+    }
+
+    /// Description of the roots for the system prompt
+    pub fn describe(&self) -> String {
+        let mut text = String::from("The workspace has multiple roots:\n");
+        for root in &self.roots {
+            text.push_str(&format!("- {}: {}\n", root.label, root.path.display()));
+        }
+        text.push_str(&format!(
+            "Address files in a specific root as `label:relative/path` (e.g. `{0}:src/main.rs`). \
+             Relative paths without a label refer to the `{0}` root.",
+            self.primary().label
+        ));
+        text
+    }
+}
+
+/// Canonical form of a path that may not exist yet: the deepest existing
+/// ancestor is canonicalized and the rest is appended with `..` applied
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut base = absolute.as_path();
+    let mut rest = Vec::new();
+    let mut resolved = loop {
+        match (base.canonicalize(), base.parent(), base.file_name()) {
+            (Ok(canonical), _, _) => break canonical,
+            (Err(_), Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                base = parent;
+            }
+            // `..` 或根目录：只能按字面处理
+            _ => break base.to_path_buf(),
+        }
+    };
+    for component in rest.iter().rev() {
+        resolved.push(component);
+    }
+    let mut normalized = PathBuf::new();
+    for component in resolved.components() {
+        match component {
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            std::path::Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::current_dir()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_roots() -> Workspace {
+        Workspace::new(vec![
+            WorkspaceRoot::new("backend", "/srv/backend"),
+            WorkspaceRoot::new("frontend", "/srv/frontend"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_labeled_path() {
+        let workspace = two_roots();
+        assert_eq!(
+            workspace.resolve("frontend:src/app.ts"),
+            PathBuf::from("/srv/frontend/src/app.ts")
+        );
+    }
+
+    #[test]
+    fn test_resolve_relative_path_uses_primary_root() {
+        let workspace = two_roots();
+        assert_eq!(
+            workspace.resolve("src/main.rs"),
+            PathBuf::from("/srv/backend/src/main.rs")
+        );
+        assert_eq!(workspace.resolve("/etc/hosts"), PathBuf::from("/etc/hosts"));
+    }
+
+    #[test]
+    fn test_resolve_unknown_label_is_a_plain_path() {
+        let workspace = two_roots();
+        assert_eq!(
+            workspace.resolve("docs:readme.md"),
+            PathBuf::from("/srv/backend/docs:readme.md")
+        );
+    }
+
+    #[test]
+    fn test_display_path() {
+        let workspace = two_roots();
+        assert_eq!(
+            workspace.display_path(Path::new("/srv/frontend/src/app.ts")),
+            "frontend:src/app.ts"
+        );
+        assert_eq!(workspace.display_path(Path::new("/tmp/x")), "/tmp/x");
+
+        // Single-root workspaces keep paths unchanged
+        let single = Workspace::current_dir();
+        assert_eq!(single.display_path(Path::new("./src/lib.rs")), "./src/lib.rs");
+    }
+
+    #[test]
+    fn test_scope() {
+        let test_dir = "/tmp/test_workspace_scope";
+        std::fs::create_dir_all(format!("{}/crates/core/src", test_dir)).unwrap();
+        std::fs::create_dir_all(format!("{}/crates/cli", test_dir)).unwrap();
+
+        let mut workspace = Workspace::new(vec![WorkspaceRoot::new("repo", test_dir)]).unwrap();
+        assert!(workspace.in_scope(Path::new("/etc")));
+
+        let scope = workspace.set_scope("crates/core").unwrap();
+        assert_eq!(workspace.search_paths(), vec![scope]);
+        assert!(workspace.in_scope(&workspace.resolve("crates/core/src")));
+        assert!(!workspace.in_scope(&workspace.resolve("crates/cli")));
+        assert!(workspace.check_scope(Path::new("/etc")).is_err());
+
+        // Scopes must be directories inside a root
+        assert!(workspace.set_scope("/tmp").is_err());
+        assert!(workspace.set_scope("crates/missing").is_err());
+
+        workspace.reset_scope();
+        assert!(workspace.scope().is_none());
+        assert!(workspace.in_scope(&workspace.resolve("crates/cli")));
+
+        std::fs::remove_dir_all(test_dir).ok();
+    }
+
+    #[test]
+    fn test_confined_to_roots() {
+        let dir = std::env::temp_dir().join(format!("ariste-confined-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let mut workspace = Workspace::new(vec![WorkspaceRoot::new("app", &dir)]).unwrap();
+
+        // Not confined by default
+        assert!(workspace.check_scope(Path::new("/etc/hosts")).is_ok());
+
+        workspace.confine(true);
+        assert!(workspace.check_workdir(&workspace.resolve("src/main.rs")).is_ok());
+        assert!(workspace.check_workdir(&workspace.resolve("new/dir/file.txt")).is_ok());
+        assert!(workspace.check_workdir(&workspace.resolve("new/../src/lib.rs")).is_ok());
+        let error = workspace.check_workdir(&workspace.resolve("../outside.txt")).unwrap_err();
+        assert!(error.contains("outside the working directory"));
+        assert!(workspace.check_workdir(&workspace.resolve("new/../../outside.txt")).is_err());
+        assert!(workspace.check_scope(Path::new("/etc/hosts")).is_err());
+        assert!(!workspace.in_scope(Path::new("/etc/hosts")));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc", dir.join("etc")).unwrap();
+            assert!(workspace.check_workdir(&workspace.resolve("etc/hosts")).is_err());
+        }
+
+        workspace.confine(false);
+        assert!(workspace.check_scope(Path::new("/etc/hosts")).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_roots() {
+        assert!(Workspace::new(vec![]).is_err());
+        assert!(Workspace::new(vec![
+            WorkspaceRoot::new("a", "/a"),
+            WorkspaceRoot::new("a", "/b"),
+        ])
+        .is_err());
+        assert!(Workspace::new(vec![WorkspaceRoot::new("a:b", "/a")]).is_err());
+    }
+}